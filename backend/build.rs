@@ -0,0 +1,10 @@
+// Generates the gRPC server code for proto/internal.proto (see src/grpc.rs).
+// protoc comes from protoc-bin-vendored, so builds don't need it installed.
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    tonic_build::configure()
+        .build_client(false)
+        .compile_protos(&["proto/internal.proto"], &["proto"])?;
+    Ok(())
+}
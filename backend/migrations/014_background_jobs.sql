@@ -0,0 +1,36 @@
+-- Persistent background job queue
+-- Workers claim jobs with FOR UPDATE SKIP LOCKED so several can run side by side
+
+CREATE TABLE IF NOT EXISTS background_jobs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    job_type VARCHAR(100) NOT NULL,
+    payload JSONB NOT NULL DEFAULT '{}'::jsonb,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'running', 'completed', 'failed', 'cancelled')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL DEFAULT 5,
+    last_error TEXT,
+    result JSONB,
+    unique_key VARCHAR(255),
+    run_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    locked_at TIMESTAMP,
+    locked_by VARCHAR(255),
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMP
+);
+
+-- Fast lookup of due jobs
+CREATE INDEX IF NOT EXISTS idx_background_jobs_due ON background_jobs(run_at)
+    WHERE status = 'pending';
+
+CREATE INDEX IF NOT EXISTS idx_background_jobs_status ON background_jobs(status, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_background_jobs_type ON background_jobs(job_type);
+CREATE INDEX IF NOT EXISTS idx_background_jobs_created_by ON background_jobs(created_by);
+
+-- Only one queued/running instance per unique key (used for recurring jobs)
+CREATE UNIQUE INDEX IF NOT EXISTS idx_background_jobs_unique_active ON background_jobs(unique_key)
+    WHERE status IN ('pending', 'running');
+
+COMMENT ON TABLE background_jobs IS 'Persistent job queue with retries and exponential backoff';
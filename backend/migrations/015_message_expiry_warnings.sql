@@ -0,0 +1,5 @@
+-- Track which disappearing messages have already had a TimeToExpire push
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS expiry_warned_at TIMESTAMP;
+
+CREATE INDEX IF NOT EXISTS idx_messages_expires_pending ON messages(expires_at)
+    WHERE expires_at IS NOT NULL AND deleted_at IS NULL;
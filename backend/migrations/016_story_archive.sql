@@ -0,0 +1,23 @@
+-- Story archive ("Memories")
+-- Opt-in: when a story expires its media is copied under archive/ and kept for the author
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS archive_stories BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE IF NOT EXISTS story_archive (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    original_story_id UUID NOT NULL UNIQUE,
+    media_url TEXT NOT NULL,
+    media_type VARCHAR(20) NOT NULL,
+    thumbnail_url TEXT,
+    caption TEXT,
+    view_count INTEGER NOT NULL DEFAULT 0,
+    like_count INTEGER NOT NULL DEFAULT 0,
+    comment_count INTEGER NOT NULL DEFAULT 0,
+    original_created_at TIMESTAMP NOT NULL,
+    archived_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_story_archive_user ON story_archive(user_id, original_created_at DESC);
+
+COMMENT ON TABLE story_archive IS 'Expired stories kept privately for their author (Memories)';
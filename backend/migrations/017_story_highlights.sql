@@ -0,0 +1,22 @@
+-- Story highlights: named collections of archived stories pinned to a profile
+
+CREATE TABLE IF NOT EXISTS story_highlights (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    title VARCHAR(50) NOT NULL,
+    cover_url TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS story_highlight_items (
+    highlight_id UUID NOT NULL REFERENCES story_highlights(id) ON DELETE CASCADE,
+    memory_id UUID NOT NULL REFERENCES story_archive(id) ON DELETE CASCADE,
+    added_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (highlight_id, memory_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_story_highlights_user ON story_highlights(user_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_story_highlight_items_memory ON story_highlight_items(memory_id);
+
+COMMENT ON TABLE story_highlights IS 'Public collections of archived stories shown on a profile';
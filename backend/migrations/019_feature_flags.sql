@@ -0,0 +1,26 @@
+-- Feature flags
+-- A flag is on for a user when it is enabled and the user is either listed
+-- explicitly or falls inside the rollout percentage
+
+CREATE TABLE IF NOT EXISTS feature_flags (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    key VARCHAR(100) NOT NULL UNIQUE,
+    description TEXT,
+    enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    rollout_percentage INTEGER NOT NULL DEFAULT 100 CHECK (rollout_percentage BETWEEN 0 AND 100),
+    user_ids UUID[] NOT NULL DEFAULT '{}',
+    metadata JSONB NOT NULL DEFAULT '{}',
+    updated_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- Global maintenance switch; metadata.message is shown to blocked clients
+INSERT INTO feature_flags (key, description, enabled, metadata)
+VALUES (
+    'maintenance_mode',
+    'Reject non-admin API and WebSocket traffic with 503',
+    FALSE,
+    '{"message": "We are doing some maintenance and will be back shortly."}'
+)
+ON CONFLICT (key) DO NOTHING;
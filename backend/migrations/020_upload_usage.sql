@@ -0,0 +1,9 @@
+-- Daily upload volume per user, for the upload_bytes_per_day quota
+-- (story/chat/comment quotas are counted from their own tables)
+
+CREATE TABLE IF NOT EXISTS upload_usage (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    bytes BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (user_id, day)
+);
@@ -0,0 +1,26 @@
+-- Bot accounts
+-- Bots are users with is_bot set, owned by the human who registered them.
+-- They authenticate with API tokens instead of passwords.
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS is_bot BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS bot_owner_id UUID REFERENCES users(id) ON DELETE CASCADE;
+
+CREATE INDEX IF NOT EXISTS idx_users_bot_owner ON users(bot_owner_id) WHERE is_bot;
+
+CREATE TABLE IF NOT EXISTS bot_tokens (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    bot_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash VARCHAR(64) NOT NULL UNIQUE, -- SHA-256 hex of the token
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMP
+);
+
+-- Messages waiting to be fetched by bots that long-poll instead of using a webhook
+CREATE TABLE IF NOT EXISTS bot_updates (
+    id BIGSERIAL PRIMARY KEY,
+    bot_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_bot_updates_bot ON bot_updates(bot_id, id);
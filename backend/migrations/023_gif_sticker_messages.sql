@@ -0,0 +1,12 @@
+-- GIF and sticker messages
+-- Both point media_url at the GIF provider's CDN; content optionally holds the provider id.
+
+ALTER TABLE messages DROP CONSTRAINT IF EXISTS messages_message_type_check;
+ALTER TABLE messages ADD CONSTRAINT messages_message_type_check
+    CHECK (message_type IN ('text', 'image', 'video', 'gif', 'sticker'));
+
+ALTER TABLE messages DROP CONSTRAINT IF EXISTS valid_content;
+ALTER TABLE messages ADD CONSTRAINT valid_content CHECK (
+    (message_type = 'text' AND content IS NOT NULL) OR
+    (message_type IN ('image', 'video', 'gif', 'sticker') AND media_url IS NOT NULL)
+);
@@ -0,0 +1,27 @@
+-- On-demand translation of messages and story captions
+-- Translations are cached per (source, target_lang) so each text is only sent to
+-- the translation backend once per language.
+
+-- End-to-end encrypted chats: the server can't read these messages, so it
+-- never tries to process them (translation, previews, ...)
+ALTER TABLE chat_rooms ADD COLUMN IF NOT EXISTS is_e2ee BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE IF NOT EXISTS message_translations (
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    target_lang VARCHAR(10) NOT NULL,
+    source_lang VARCHAR(10),
+    translated_text TEXT NOT NULL,
+    provider VARCHAR(20) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (message_id, target_lang)
+);
+
+CREATE TABLE IF NOT EXISTS story_translations (
+    story_id UUID NOT NULL REFERENCES stories(id) ON DELETE CASCADE,
+    target_lang VARCHAR(10) NOT NULL,
+    source_lang VARCHAR(10),
+    translated_text TEXT NOT NULL,
+    provider VARCHAR(20) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (story_id, target_lang)
+);
@@ -0,0 +1,9 @@
+-- Accessibility descriptions for story media
+-- alt_text is written by the author, or generated (image captioning / speech
+-- transcription) when they leave it empty; alt_text_generated tells them apart.
+
+ALTER TABLE stories ADD COLUMN IF NOT EXISTS alt_text TEXT;
+ALTER TABLE stories ADD COLUMN IF NOT EXISTS alt_text_generated BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE story_archive ADD COLUMN IF NOT EXISTS alt_text TEXT;
+ALTER TABLE story_archive ADD COLUMN IF NOT EXISTS alt_text_generated BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,5 @@
+-- Poster frames and short looping previews for video stories
+-- thumbnail_url (existing column) holds the poster JPEG; preview_url a 2 second
+-- muted MP4 clients loop in story rings and grids.
+
+ALTER TABLE stories ADD COLUMN IF NOT EXISTS preview_url TEXT;
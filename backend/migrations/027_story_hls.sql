@@ -0,0 +1,6 @@
+-- HLS playback for video stories
+-- playback_url points at the master playlist of the multi-bitrate package stored
+-- under stories/<user_id>/hls/<story_id>/. NULL until packaging finishes; clients
+-- fall back to the progressive media_url.
+
+ALTER TABLE stories ADD COLUMN IF NOT EXISTS playback_url TEXT;
@@ -0,0 +1,22 @@
+-- Content-addressable media storage
+-- Uploads are stored once per SHA-256 under media/<hash>.<ext>. Every message or
+-- story row that points at an object holds a reference; the object is only
+-- deleted from storage once the last reference is released.
+
+CREATE TABLE IF NOT EXISTS media_objects (
+    sha256 CHAR(64) PRIMARY KEY,
+    s3_key TEXT NOT NULL UNIQUE,
+    content_type VARCHAR(100),
+    size_bytes BIGINT NOT NULL,
+    refcount INTEGER NOT NULL DEFAULT 0 CHECK (refcount >= 0),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    -- Bumped on every upload that resolves to this object, so an object that was
+    -- just handed out isn't deleted before the client attaches it to anything
+    last_used_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_media_objects_unreferenced
+    ON media_objects(last_used_at) WHERE refcount = 0;
+
+-- Expired stories release their media references exactly once
+ALTER TABLE stories ADD COLUMN IF NOT EXISTS media_released BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,13 @@
+-- Malware scanning of uploads
+-- Every content-addressed object is scanned before it is made available.
+-- Infected files are kept under quarantine/ (never at a distributable key) so
+-- admins can review them; re-uploads of the same bytes are rejected on sight.
+
+ALTER TABLE media_objects ADD COLUMN IF NOT EXISTS scan_status VARCHAR(20) NOT NULL DEFAULT 'unscanned'
+    CHECK (scan_status IN ('unscanned', 'clean', 'infected'));
+ALTER TABLE media_objects ADD COLUMN IF NOT EXISTS scan_signature TEXT;
+ALTER TABLE media_objects ADD COLUMN IF NOT EXISTS scanned_at TIMESTAMP;
+ALTER TABLE media_objects ADD COLUMN IF NOT EXISTS uploaded_by UUID REFERENCES users(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_media_objects_quarantined
+    ON media_objects(scanned_at DESC) WHERE scan_status = 'infected';
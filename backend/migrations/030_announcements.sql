@@ -0,0 +1,29 @@
+-- System announcements (maintenance notices, feature launches)
+-- An announcement is delivered once, at starts_at, as a notification to every
+-- user in its audience. Read tracking uses the notifications' is_read; when the
+-- announcement expires its notifications are removed and the read count kept.
+
+CREATE TABLE IF NOT EXISTS announcements (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    title VARCHAR(100) NOT NULL,
+    body TEXT NOT NULL,
+    link_url TEXT,
+    -- {"roles": [...], "user_ids": [...], "joined_after": ..., "joined_before": ...}; empty = everyone
+    audience JSONB NOT NULL DEFAULT '{}',
+    starts_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP,
+    status VARCHAR(20) NOT NULL DEFAULT 'scheduled'
+        CHECK (status IN ('scheduled', 'delivered', 'expired', 'cancelled')),
+    recipient_count INTEGER NOT NULL DEFAULT 0,
+    read_count INTEGER NOT NULL DEFAULT 0,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    delivered_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_announcements_expiring
+    ON announcements(expires_at) WHERE status = 'delivered';
+
+ALTER TABLE notifications ADD COLUMN IF NOT EXISTS announcement_id UUID REFERENCES announcements(id) ON DELETE CASCADE;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_notifications_announcement_user
+    ON notifications(announcement_id, user_id) WHERE announcement_id IS NOT NULL;
@@ -0,0 +1,18 @@
+-- Self-reported demographics
+-- birthdate/gender were read for ad analytics but never added by a migration.
+-- Each field only feeds ad targeting when its share_* flag is on (off by default).
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS birthdate DATE;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS gender VARCHAR(20);
+ALTER TABLE users ADD COLUMN IF NOT EXISTS country VARCHAR(2);
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS share_age_with_ads BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS share_gender_with_ads BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS share_country_with_ads BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_gender_check;
+ALTER TABLE users ADD CONSTRAINT users_gender_check
+    CHECK (gender IS NULL OR gender IN ('female', 'male', 'non_binary', 'other', 'prefer_not_to_say'));
+
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_country_check;
+ALTER TABLE users ADD CONSTRAINT users_country_check CHECK (country IS NULL OR country ~ '^[A-Z]{2}$');
@@ -0,0 +1,38 @@
+-- Age-appropriate content gating
+-- Stories and ads can be marked 18+. They are only shown to users whose
+-- birthdate says they are adults; accounts without a birthdate don't see them.
+-- Adults also can't start chats with minors unless the minor follows them.
+
+ALTER TABLE stories ADD COLUMN IF NOT EXISTS is_mature BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE story_archive ADD COLUMN IF NOT EXISTS is_mature BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE advertisements ADD COLUMN IF NOT EXISTS is_mature BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Known to be 18 or older
+CREATE OR REPLACE FUNCTION user_is_adult(uid UUID)
+RETURNS BOOLEAN AS $$
+    SELECT COALESCE(
+        (SELECT birthdate <= CURRENT_DATE - INTERVAL '18 years' FROM users WHERE id = uid),
+        FALSE
+    );
+$$ LANGUAGE SQL STABLE;
+
+-- Known to be under 18
+CREATE OR REPLACE FUNCTION user_is_minor(uid UUID)
+RETURNS BOOLEAN AS $$
+    SELECT COALESCE(
+        (SELECT birthdate > CURRENT_DATE - INTERVAL '18 years' FROM users WHERE id = uid),
+        FALSE
+    );
+$$ LANGUAGE SQL STABLE;
+
+-- Whether `sender` may not reach out to `recipient`: the recipient is a minor,
+-- the sender isn't, and the recipient doesn't follow the sender. Senders without
+-- a birthdate count as adults here.
+CREATE OR REPLACE FUNCTION minor_contact_restricted(sender UUID, recipient UUID)
+RETURNS BOOLEAN AS $$
+    SELECT user_is_minor(recipient)
+        AND NOT user_is_minor(sender)
+        AND NOT EXISTS (
+            SELECT 1 FROM follows WHERE follower_id = recipient AND following_id = sender
+        );
+$$ LANGUAGE SQL STABLE;
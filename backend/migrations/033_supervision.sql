@@ -0,0 +1,67 @@
+-- Guardian supervision of minor accounts
+-- A guardian (an adult) asks to supervise a minor; the link only becomes active
+-- once the minor accepts. Guardians see time spent and new contacts, never
+-- content, and can limit who may message or find the minor.
+
+CREATE TABLE IF NOT EXISTS supervision_links (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    guardian_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    minor_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- pending, active, declined or ended
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    -- who may start chats with the minor: everyone, following (people the minor follows) or nobody
+    message_policy VARCHAR(20) NOT NULL DEFAULT 'everyone',
+    hide_from_discovery BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    accepted_at TIMESTAMP,
+    ended_at TIMESTAMP,
+    CHECK (guardian_id != minor_id),
+    CHECK (status IN ('pending', 'active', 'declined', 'ended')),
+    CHECK (message_policy IN ('everyone', 'following', 'nobody'))
+);
+
+-- One open request per pair, and a minor has at most one active guardian
+CREATE UNIQUE INDEX IF NOT EXISTS idx_supervision_open_pair
+    ON supervision_links(guardian_id, minor_id) WHERE status IN ('pending', 'active');
+CREATE UNIQUE INDEX IF NOT EXISTS idx_supervision_active_minor
+    ON supervision_links(minor_id) WHERE status = 'active';
+
+-- Time spent, only recorded while a minor is supervised
+CREATE TABLE IF NOT EXISTS supervised_activity (
+    minor_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    seconds_active INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (minor_id, day)
+);
+
+-- Whether the minor's guardian has blocked `sender` from starting chats with `recipient`
+CREATE OR REPLACE FUNCTION supervision_blocks_contact(sender UUID, recipient UUID)
+RETURNS BOOLEAN AS $$
+    SELECT EXISTS (
+        SELECT 1 FROM supervision_links l
+        WHERE l.minor_id = recipient
+          AND l.status = 'active'
+          AND l.guardian_id != sender
+          AND (
+              l.message_policy = 'nobody'
+              OR (l.message_policy = 'following' AND NOT EXISTS (
+                  SELECT 1 FROM follows WHERE follower_id = recipient AND following_id = sender
+              ))
+          )
+    );
+$$ LANGUAGE SQL STABLE;
+
+-- Whether `minor` should be left out of `viewer`'s search and suggestions
+CREATE OR REPLACE FUNCTION supervision_hides_from(viewer UUID, minor UUID)
+RETURNS BOOLEAN AS $$
+    SELECT EXISTS (
+        SELECT 1 FROM supervision_links l
+        WHERE l.minor_id = minor
+          AND l.status = 'active'
+          AND l.hide_from_discovery
+          AND l.guardian_id != viewer
+          AND NOT EXISTS (
+              SELECT 1 FROM follows WHERE follower_id = minor AND following_id = viewer
+          )
+    );
+$$ LANGUAGE SQL STABLE;
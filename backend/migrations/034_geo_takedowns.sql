@@ -0,0 +1,40 @@
+-- Country-specific legal takedowns
+-- Content that is unlawful in some countries is withheld only there. Rows are
+-- never deleted: lifting a takedown records who lifted it and why, so the table
+-- doubles as the audit trail of legal requests.
+
+CREATE TABLE IF NOT EXISTS geo_takedowns (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- story or comment
+    content_type VARCHAR(20) NOT NULL,
+    content_id UUID NOT NULL,
+    -- ISO 3166-1 alpha-2 codes
+    countries TEXT[] NOT NULL,
+    legal_basis TEXT NOT NULL,
+    -- Court order / case number, and who asked for it
+    reference TEXT,
+    requesting_authority TEXT,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    lifted_at TIMESTAMP,
+    lifted_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    lift_reason TEXT,
+    CHECK (content_type IN ('story', 'comment')),
+    CHECK (cardinality(countries) > 0)
+);
+
+CREATE INDEX IF NOT EXISTS idx_geo_takedowns_content
+    ON geo_takedowns(content_type, content_id) WHERE lifted_at IS NULL;
+
+-- Whether the content is withheld in `country`; a NULL country (unknown
+-- location) is never blocked
+CREATE OR REPLACE FUNCTION geo_blocked(kind TEXT, item UUID, country TEXT)
+RETURNS BOOLEAN AS $$
+    SELECT country IS NOT NULL AND EXISTS (
+        SELECT 1 FROM geo_takedowns t
+        WHERE t.content_type = kind
+          AND t.content_id = item
+          AND t.lifted_at IS NULL
+          AND country = ANY(t.countries)
+    );
+$$ LANGUAGE SQL STABLE;
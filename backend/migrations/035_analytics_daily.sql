@@ -0,0 +1,20 @@
+-- Pre-computed daily analytics
+-- The snapshot_analytics job writes one row per finished day so the admin
+-- analytics endpoint no longer recounts every table for each day it returns.
+-- Only the current day is computed on request.
+
+CREATE TABLE IF NOT EXISTS analytics_daily (
+    date DATE PRIMARY KEY,
+    total_users INTEGER NOT NULL DEFAULT 0,
+    new_users INTEGER NOT NULL DEFAULT 0,
+    active_users INTEGER NOT NULL DEFAULT 0,
+    total_stories INTEGER NOT NULL DEFAULT 0,
+    new_stories INTEGER NOT NULL DEFAULT 0,
+    total_messages INTEGER NOT NULL DEFAULT 0,
+    new_messages INTEGER NOT NULL DEFAULT 0,
+    total_follows INTEGER NOT NULL DEFAULT 0,
+    new_follows INTEGER NOT NULL DEFAULT 0,
+    total_ad_impressions INTEGER NOT NULL DEFAULT 0,
+    total_ad_clicks INTEGER NOT NULL DEFAULT 0,
+    computed_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,26 @@
+-- users.story_count had no trigger (follower, following, like, comment and
+-- reply counts are maintained by the triggers in 003 and 004), so it stayed
+-- at whatever the row was created with. Keep it in step with live stories;
+-- the reconcile_counters job repairs any drift already in the table.
+
+CREATE OR REPLACE FUNCTION update_user_story_counts()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        UPDATE users SET story_count = COALESCE(story_count, 0) + 1 WHERE id = NEW.user_id;
+        RETURN NEW;
+    ELSIF TG_OP = 'DELETE' THEN
+        UPDATE users SET story_count = GREATEST(COALESCE(story_count, 0) - 1, 0) WHERE id = OLD.user_id;
+        RETURN OLD;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trigger_update_user_story_counts ON stories;
+CREATE TRIGGER trigger_update_user_story_counts
+    AFTER INSERT OR DELETE ON stories
+    FOR EACH ROW
+    EXECUTE FUNCTION update_user_story_counts();
+
+UPDATE users u SET story_count = (SELECT COUNT(*) FROM stories s WHERE s.user_id = u.id);
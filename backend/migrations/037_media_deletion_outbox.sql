@@ -0,0 +1,21 @@
+-- Outbox for S3 deletions
+-- Object deletes can't join a database transaction, so flows that free media
+-- queue the keys here in the same transaction as their own writes. The entry
+-- is flushed right after commit and retried by the process_media_outbox job
+-- if storage was unreachable.
+
+CREATE TABLE IF NOT EXISTS media_deletion_outbox (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- Keys still to delete; shrinks as partial deletes succeed
+    s3_keys TEXT[] NOT NULL,
+    -- What freed the media (story_deleted, memory_deleted, expired, ...)
+    reason VARCHAR(50) NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    processed_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_media_outbox_pending
+    ON media_deletion_outbox(next_attempt_at) WHERE processed_at IS NULL;
@@ -0,0 +1,13 @@
+-- Geo and language targeting for ads
+-- Empty arrays mean no restriction. Targeted ads are only served when the
+-- request's country (CF-IPCountry, else the Accept-Language region) or
+-- language is one of the targets; an unknown location never matches.
+
+ALTER TABLE advertisements ADD COLUMN IF NOT EXISTS target_countries TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE advertisements ADD COLUMN IF NOT EXISTS target_languages TEXT[] NOT NULL DEFAULT '{}';
+
+CREATE OR REPLACE FUNCTION ad_geo_match(countries TEXT[], languages TEXT[], country TEXT, lang TEXT)
+RETURNS BOOLEAN AS $$
+    SELECT (cardinality(countries) = 0 OR COALESCE(country = ANY(countries), FALSE))
+       AND (cardinality(languages) = 0 OR COALESCE(lang = ANY(languages), FALSE));
+$$ LANGUAGE SQL IMMUTABLE;
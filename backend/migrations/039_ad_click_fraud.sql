@@ -0,0 +1,50 @@
+-- Click fraud detection
+-- Clicks are checked by the detect_click_fraud job (and at click time for
+-- users and IPs already flagged). A fraudulent click keeps clicked = true for
+-- the record but carries a fraud_reason and is counted in
+-- advertisements.fraudulent_clicks, which billing subtracts from click_count.
+
+ALTER TABLE ad_impressions ADD COLUMN IF NOT EXISTS ip_address TEXT;
+ALTER TABLE ad_impressions ADD COLUMN IF NOT EXISTS click_ip TEXT;
+ALTER TABLE ad_impressions ADD COLUMN IF NOT EXISTS fraud_reason VARCHAR(30);
+
+CREATE INDEX IF NOT EXISTS idx_ad_impressions_clicked_at ON ad_impressions(clicked_at) WHERE clicked;
+CREATE INDEX IF NOT EXISTS idx_ad_impressions_fraud ON ad_impressions(ad_id) WHERE fraud_reason IS NOT NULL;
+
+ALTER TABLE advertisements ADD COLUMN IF NOT EXISTS fraudulent_clicks INTEGER NOT NULL DEFAULT 0;
+
+-- Users and IPs whose clicking looks automated; clicks from an active flag
+-- are discounted as they happen
+CREATE TABLE IF NOT EXISTS ad_fraud_flags (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- user or ip
+    subject_type VARCHAR(10) NOT NULL,
+    subject TEXT NOT NULL,
+    reason VARCHAR(30) NOT NULL,
+    -- What the detector saw when it raised the flag
+    details JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    cleared_at TIMESTAMP,
+    cleared_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    CHECK (subject_type IN ('user', 'ip'))
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_ad_fraud_flags_active
+    ON ad_fraud_flags(subject_type, subject) WHERE cleared_at IS NULL;
+
+-- Why a click is fraudulent, or NULL: clicked within half a second of being
+-- shown (faster than a person can react), or from a flagged user or IP
+CREATE OR REPLACE FUNCTION ad_click_fraud_reason(clicker UUID, ip TEXT, shown TIMESTAMP, clicked TIMESTAMP)
+RETURNS TEXT AS $$
+    SELECT CASE
+        WHEN clicked - shown < INTERVAL '500 milliseconds' THEN 'instant_click'
+        WHEN EXISTS (
+            SELECT 1 FROM ad_fraud_flags f
+            WHERE f.subject_type = 'user' AND f.subject = clicker::text AND f.cleared_at IS NULL
+        ) THEN 'flagged_user'
+        WHEN ip IS NOT NULL AND EXISTS (
+            SELECT 1 FROM ad_fraud_flags f
+            WHERE f.subject_type = 'ip' AND f.subject = ip AND f.cleared_at IS NULL
+        ) THEN 'flagged_ip'
+    END;
+$$ LANGUAGE SQL STABLE;
@@ -0,0 +1,35 @@
+-- Ad conversion tracking
+-- Advertisers report conversions from their landing pages (pixel) or servers
+-- (API). A conversion is attributed to the impression it names: click-through
+-- when the impression was clicked within the campaign's attribution window,
+-- view-through when it was only shown within it. Fraudulent clicks never get
+-- credit.
+
+ALTER TABLE advertisements
+    ADD COLUMN IF NOT EXISTS attribution_window_hours INTEGER NOT NULL DEFAULT 168;
+ALTER TABLE advertisements DROP CONSTRAINT IF EXISTS advertisements_attribution_window_check;
+ALTER TABLE advertisements ADD CONSTRAINT advertisements_attribution_window_check
+    CHECK (attribution_window_hours BETWEEN 1 AND 2160);
+
+CREATE TABLE IF NOT EXISTS ad_conversions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    ad_id UUID NOT NULL REFERENCES advertisements(id) ON DELETE CASCADE,
+    impression_id UUID NOT NULL REFERENCES ad_impressions(id) ON DELETE CASCADE,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    -- Advertiser-defined, e.g. purchase or signup
+    event VARCHAR(50) NOT NULL,
+    value NUMERIC(12, 2),
+    currency VARCHAR(3),
+    -- Advertiser's reference; repeats of the same event and order are ignored
+    order_id VARCHAR(100) NOT NULL DEFAULT '',
+    -- click or view
+    attribution VARCHAR(10) NOT NULL,
+    -- pixel or api
+    source VARCHAR(10) NOT NULL,
+    converted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CHECK (attribution IN ('click', 'view')),
+    CHECK (source IN ('pixel', 'api')),
+    UNIQUE (impression_id, event, order_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_ad_conversions_ad ON ad_conversions(ad_id, converted_at DESC);
@@ -0,0 +1,116 @@
+-- Budget-based (CPM) campaigns
+-- Besides flat packages (billing_model = 'package', served until
+-- target_impressions), a campaign can be billed per thousand impressions out of
+-- a prepaid balance. Every impression deducts cpm_rate / 1000; a campaign that
+-- can't pay for its next impression is paused until it is topped up.
+
+ALTER TABLE advertisements ADD COLUMN IF NOT EXISTS billing_model VARCHAR(10) NOT NULL DEFAULT 'package';
+ALTER TABLE advertisements ADD COLUMN IF NOT EXISTS cpm_rate NUMERIC(10, 2);
+ALTER TABLE advertisements ADD COLUMN IF NOT EXISTS budget_balance NUMERIC(12, 4) NOT NULL DEFAULT 0;
+ALTER TABLE advertisements ADD COLUMN IF NOT EXISTS total_spend NUMERIC(12, 4) NOT NULL DEFAULT 0;
+ALTER TABLE advertisements ADD COLUMN IF NOT EXISTS budget_exhausted_at TIMESTAMP;
+
+ALTER TABLE advertisements DROP CONSTRAINT IF EXISTS advertisements_billing_model_check;
+ALTER TABLE advertisements ADD CONSTRAINT advertisements_billing_model_check CHECK (
+    billing_model = 'package' OR (billing_model = 'cpm' AND cpm_rate > 0)
+);
+
+-- target_impressions only caps package campaigns
+ALTER TABLE advertisements DROP CONSTRAINT IF EXISTS check_impressions;
+ALTER TABLE advertisements ADD CONSTRAINT check_impressions CHECK (
+    billing_model = 'cpm' OR current_impressions <= target_impressions
+);
+
+CREATE OR REPLACE FUNCTION update_ad_status()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.billing_model = 'package'
+       AND NEW.current_impressions >= NEW.target_impressions
+       AND NEW.status = 'active' THEN
+        NEW.status := 'completed';
+    END IF;
+    NEW.updated_at := NOW();
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Whether a campaign may be shown once more
+CREATE OR REPLACE FUNCTION ad_in_budget(
+    model TEXT, current_impressions INTEGER, target_impressions INTEGER, balance NUMERIC, rate NUMERIC
+)
+RETURNS BOOLEAN AS $$
+    SELECT CASE model
+        WHEN 'cpm' THEN balance >= rate / 1000
+        ELSE current_impressions < target_impressions
+    END;
+$$ LANGUAGE SQL IMMUTABLE;
+
+-- Spend per campaign and day, for reporting
+CREATE TABLE IF NOT EXISTS ad_spend_daily (
+    ad_id UUID NOT NULL REFERENCES advertisements(id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    impressions INTEGER NOT NULL DEFAULT 0,
+    spend NUMERIC(12, 4) NOT NULL DEFAULT 0,
+    PRIMARY KEY (ad_id, day)
+);
+
+-- Charge CPM campaigns for each impression, pausing them once the balance
+-- can't cover another one
+CREATE OR REPLACE FUNCTION charge_cpm_impression()
+RETURNS TRIGGER AS $$
+DECLARE
+    cost NUMERIC;
+    balance NUMERIC;
+    charged NUMERIC;
+BEGIN
+    SELECT cpm_rate / 1000, budget_balance INTO cost, balance
+    FROM advertisements
+    WHERE id = NEW.ad_id AND billing_model = 'cpm'
+    FOR UPDATE;
+
+    IF cost IS NULL THEN
+        RETURN NEW;
+    END IF;
+
+    -- Impressions served concurrently with the last affordable one are only
+    -- charged what is left
+    charged := LEAST(cost, balance);
+
+    UPDATE advertisements
+    SET budget_balance = balance - charged,
+        total_spend = total_spend + charged,
+        status = CASE WHEN balance - charged < cost AND status = 'active' THEN 'paused' ELSE status END,
+        budget_exhausted_at = CASE WHEN balance - charged < cost THEN NOW() ELSE budget_exhausted_at END
+    WHERE id = NEW.ad_id;
+
+    INSERT INTO ad_spend_daily (ad_id, day, impressions, spend)
+    VALUES (NEW.ad_id, CURRENT_DATE, 1, charged)
+    ON CONFLICT (ad_id, day) DO UPDATE
+    SET impressions = ad_spend_daily.impressions + 1,
+        spend = ad_spend_daily.spend + EXCLUDED.spend;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trigger_charge_cpm_impression ON ad_impressions;
+CREATE TRIGGER trigger_charge_cpm_impression
+    AFTER INSERT ON ad_impressions
+    FOR EACH ROW
+    EXECUTE FUNCTION charge_cpm_impression();
+
+-- Prepaid balance added through Stripe checkout
+CREATE TABLE IF NOT EXISTS ad_budget_topups (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    ad_id UUID NOT NULL REFERENCES advertisements(id) ON DELETE CASCADE,
+    amount NUMERIC(12, 2) NOT NULL CHECK (amount > 0),
+    -- pending until Stripe confirms payment, then paid
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    stripe_session_id TEXT,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    paid_at TIMESTAMP,
+    CHECK (status IN ('pending', 'paid'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_ad_budget_topups_ad ON ad_budget_topups(ad_id, created_at DESC);
@@ -0,0 +1,83 @@
+-- Per-user retention of saved messages
+-- A message due for deletion (expired, or a view-once message that was
+-- opened) that someone saved is retained instead: it stays for the members
+-- who saved it and is hidden for everyone else. Once nobody has it saved the
+-- expiration sweep deletes it for good.
+
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS retained_at TIMESTAMP;
+
+CREATE INDEX IF NOT EXISTS idx_messages_retained ON messages(retained_at) WHERE retained_at IS NOT NULL AND deleted_at IS NULL;
+
+-- Messages a member can no longer see although the row lives on for others
+CREATE TABLE IF NOT EXISTS message_hidden (
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    hidden_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (message_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_message_hidden_user ON message_hidden(user_id);
+
+-- Retire a message that is due for deletion. Without savers it is deleted;
+-- otherwise it is retained and hidden for every member who hasn't saved it.
+-- Returns true when the message was deleted.
+CREATE OR REPLACE FUNCTION retire_message(msg_id UUID)
+RETURNS BOOLEAN AS $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM saved_messages WHERE message_id = msg_id) THEN
+        UPDATE messages SET deleted_at = NOW() WHERE id = msg_id AND deleted_at IS NULL;
+        RETURN TRUE;
+    END IF;
+
+    UPDATE messages SET retained_at = COALESCE(retained_at, NOW()) WHERE id = msg_id AND deleted_at IS NULL;
+
+    INSERT INTO message_hidden (message_id, user_id)
+    SELECT m.id, cm.user_id
+    FROM messages m
+    JOIN chat_members cm ON cm.chat_room_id = m.chat_room_id
+    WHERE m.id = msg_id
+      AND NOT EXISTS (SELECT 1 FROM saved_messages sm WHERE sm.message_id = m.id AND sm.user_id = cm.user_id)
+    ON CONFLICT DO NOTHING;
+
+    RETURN FALSE;
+END;
+$$ LANGUAGE plpgsql;
+
+-- View-once messages are retired on their first view by someone other than the sender
+CREATE OR REPLACE FUNCTION auto_delete_viewed_message()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM messages
+        WHERE id = NEW.message_id
+          AND view_once = TRUE
+          AND deleted_at IS NULL
+          AND sender_id != NEW.user_id
+    ) THEN
+        PERFORM retire_message(NEW.message_id);
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Unsaving a retained message hides it for that member too
+CREATE OR REPLACE FUNCTION hide_unsaved_retained_message()
+RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO message_hidden (message_id, user_id)
+    SELECT id, OLD.user_id FROM messages
+    WHERE id = OLD.message_id AND retained_at IS NOT NULL AND deleted_at IS NULL
+      -- Not when the row goes because the user is being deleted
+      AND EXISTS (SELECT 1 FROM users WHERE id = OLD.user_id)
+    ON CONFLICT DO NOTHING;
+
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trigger_hide_unsaved_retained_message ON saved_messages;
+CREATE TRIGGER trigger_hide_unsaved_retained_message
+    AFTER DELETE ON saved_messages
+    FOR EACH ROW
+    EXECUTE FUNCTION hide_unsaved_retained_message();
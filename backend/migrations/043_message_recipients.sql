@@ -0,0 +1,123 @@
+-- Per-recipient message state
+-- One row per message and chat member, created with the message, tracking
+-- when it reached the member, when they read it and whether it's hidden from
+-- them. Hiding replaces message_hidden (042): either the member deleted the
+-- message for themselves, or it was retired and retained only for its savers.
+
+CREATE TABLE IF NOT EXISTS message_recipients (
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    delivered_at TIMESTAMP,
+    read_at TIMESTAMP,
+    hidden_at TIMESTAMP,
+    -- deleted (delete for me) or retired (expired or viewed, kept for savers)
+    hidden_reason VARCHAR(20),
+    PRIMARY KEY (message_id, user_id),
+    CHECK ((hidden_at IS NULL) = (hidden_reason IS NULL)),
+    CHECK (hidden_reason IN ('deleted', 'retired'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_message_recipients_user ON message_recipients(user_id);
+CREATE INDEX IF NOT EXISTS idx_message_recipients_hidden ON message_recipients(user_id, message_id) WHERE hidden_at IS NOT NULL;
+
+-- Backfill live messages; the sender's own copy counts as delivered and read
+INSERT INTO message_recipients (message_id, user_id, delivered_at, read_at)
+SELECT m.id, cm.user_id, m.created_at,
+       CASE WHEN cm.user_id = m.sender_id THEN m.created_at ELSE mr.read_at END
+FROM messages m
+JOIN chat_members cm ON cm.chat_room_id = m.chat_room_id
+LEFT JOIN message_reads mr ON mr.message_id = m.id AND mr.user_id = cm.user_id
+WHERE m.deleted_at IS NULL
+ON CONFLICT DO NOTHING;
+
+INSERT INTO message_recipients (message_id, user_id, hidden_at, hidden_reason)
+SELECT message_id, user_id, hidden_at, 'retired' FROM message_hidden
+ON CONFLICT (message_id, user_id) DO UPDATE
+SET hidden_at = EXCLUDED.hidden_at, hidden_reason = EXCLUDED.hidden_reason;
+
+-- Every member at send time gets a row
+CREATE OR REPLACE FUNCTION create_message_recipients()
+RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO message_recipients (message_id, user_id, delivered_at, read_at)
+    SELECT NEW.id, cm.user_id,
+           CASE WHEN cm.user_id = NEW.sender_id THEN NEW.created_at END,
+           CASE WHEN cm.user_id = NEW.sender_id THEN NEW.created_at END
+    FROM chat_members cm
+    WHERE cm.chat_room_id = NEW.chat_room_id
+    ON CONFLICT DO NOTHING;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trigger_create_message_recipients ON messages;
+CREATE TRIGGER trigger_create_message_recipients
+    AFTER INSERT ON messages
+    FOR EACH ROW
+    EXECUTE FUNCTION create_message_recipients();
+
+-- Read receipts also stamp the recipient row (a read implies delivery)
+CREATE OR REPLACE FUNCTION record_message_recipient_read()
+RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO message_recipients (message_id, user_id, delivered_at, read_at)
+    VALUES (NEW.message_id, NEW.user_id, NEW.read_at, NEW.read_at)
+    ON CONFLICT (message_id, user_id) DO UPDATE
+    SET read_at = COALESCE(message_recipients.read_at, EXCLUDED.read_at),
+        delivered_at = COALESCE(message_recipients.delivered_at, EXCLUDED.delivered_at);
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trigger_record_message_recipient_read ON message_reads;
+CREATE TRIGGER trigger_record_message_recipient_read
+    AFTER INSERT ON message_reads
+    FOR EACH ROW
+    EXECUTE FUNCTION record_message_recipient_read();
+
+-- Hide a message from one member
+CREATE OR REPLACE FUNCTION hide_message_for(msg_id UUID, member_id UUID, reason TEXT)
+RETURNS VOID AS $$
+    INSERT INTO message_recipients (message_id, user_id, hidden_at, hidden_reason)
+    VALUES (msg_id, member_id, NOW(), reason)
+    ON CONFLICT (message_id, user_id) DO UPDATE
+    SET hidden_at = COALESCE(message_recipients.hidden_at, EXCLUDED.hidden_at),
+        hidden_reason = COALESCE(message_recipients.hidden_reason, EXCLUDED.hidden_reason);
+$$ LANGUAGE SQL;
+
+CREATE OR REPLACE FUNCTION retire_message(msg_id UUID)
+RETURNS BOOLEAN AS $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM saved_messages WHERE message_id = msg_id) THEN
+        UPDATE messages SET deleted_at = NOW() WHERE id = msg_id AND deleted_at IS NULL;
+        RETURN TRUE;
+    END IF;
+
+    UPDATE messages SET retained_at = COALESCE(retained_at, NOW()) WHERE id = msg_id AND deleted_at IS NULL;
+
+    PERFORM hide_message_for(m.id, cm.user_id, 'retired')
+    FROM messages m
+    JOIN chat_members cm ON cm.chat_room_id = m.chat_room_id
+    WHERE m.id = msg_id
+      AND NOT EXISTS (SELECT 1 FROM saved_messages sm WHERE sm.message_id = m.id AND sm.user_id = cm.user_id);
+
+    RETURN FALSE;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION hide_unsaved_retained_message()
+RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM hide_message_for(id, OLD.user_id, 'retired')
+    FROM messages
+    WHERE id = OLD.message_id AND retained_at IS NOT NULL AND deleted_at IS NULL
+      -- Not when the row goes because the user is being deleted
+      AND EXISTS (SELECT 1 FROM users WHERE id = OLD.user_id);
+
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TABLE IF EXISTS message_hidden;
@@ -0,0 +1,21 @@
+-- Exports of a single conversation
+-- The file lives in the bucket under exports/ until expires_at; the expiration
+-- sweep deletes it along with the row; files left behind when a user or chat
+-- is deleted fall to the bucket cleanup's orphan sweep. Rows also count toward
+-- the daily export quota.
+
+CREATE TABLE IF NOT EXISTS chat_exports (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    chat_room_id UUID NOT NULL REFERENCES chat_rooms(id) ON DELETE CASCADE,
+    -- json or html
+    format VARCHAR(10) NOT NULL CHECK (format IN ('json', 'html')),
+    s3_key TEXT NOT NULL,
+    message_count INTEGER NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_chat_exports_user ON chat_exports(user_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_chat_exports_expires ON chat_exports(expires_at);
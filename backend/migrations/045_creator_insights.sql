@@ -0,0 +1,85 @@
+-- Insights for creator and business accounts
+-- Story views and profile visits to these accounts carry the viewer's country
+-- and device. Stories are deleted when they expire, so the roll_up_insights
+-- job folds new views into per-day aggregates every hour; the insights API
+-- only reads the aggregates.
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS account_type VARCHAR(20) NOT NULL DEFAULT 'personal';
+
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_account_type_check;
+ALTER TABLE users ADD CONSTRAINT users_account_type_check CHECK (account_type IN ('personal', 'creator', 'business'));
+
+CREATE INDEX IF NOT EXISTS idx_users_professional ON users(id) WHERE account_type <> 'personal';
+
+ALTER TABLE story_views ADD COLUMN IF NOT EXISTS country VARCHAR(2);
+ALTER TABLE story_views ADD COLUMN IF NOT EXISTS device_type VARCHAR(10);
+
+CREATE INDEX IF NOT EXISTS idx_story_views_viewed_at ON story_views(viewed_at);
+
+-- Only recorded for creator and business profiles; pruned after 90 days
+CREATE TABLE IF NOT EXISTS profile_visits (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    profile_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    visitor_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    country VARCHAR(2),
+    device_type VARCHAR(10),
+    visited_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_profile_visits_visited_at ON profile_visits(visited_at);
+
+-- One row per account and day
+CREATE TABLE IF NOT EXISTS insights_daily (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    date DATE NOT NULL,
+    -- Follower count at the last roll-up of the day
+    followers INTEGER NOT NULL DEFAULT 0,
+    new_followers INTEGER NOT NULL DEFAULT 0,
+    story_views INTEGER NOT NULL DEFAULT 0,
+    -- Distinct accounts that viewed a story that day
+    reach INTEGER NOT NULL DEFAULT 0,
+    profile_visits INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (user_id, date)
+);
+
+-- Story views by hour of day (UTC), country and device
+CREATE TABLE IF NOT EXISTS insights_breakdown (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    date DATE NOT NULL,
+    dimension VARCHAR(10) NOT NULL CHECK (dimension IN ('hour', 'country', 'device')),
+    value VARCHAR(10) NOT NULL,
+    views INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (user_id, date, dimension, value)
+);
+
+-- Who was reached on which day, so reach over a range counts each account
+-- once; pruned after 90 days
+CREATE TABLE IF NOT EXISTS insights_reach (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    date DATE NOT NULL,
+    viewer_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (user_id, date, viewer_id)
+);
+
+-- Per-story totals, kept after the story itself expires
+CREATE TABLE IF NOT EXISTS story_insights (
+    story_id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    media_type VARCHAR(10) NOT NULL,
+    caption TEXT,
+    views INTEGER NOT NULL DEFAULT 0,
+    likes INTEGER NOT NULL DEFAULT 0,
+    comments INTEGER NOT NULL DEFAULT 0,
+    posted_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_story_insights_user ON story_insights(user_id, posted_at DESC);
+
+-- How far story views and profile visits have been rolled up
+CREATE TABLE IF NOT EXISTS insights_rollup (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    rolled_up_to TIMESTAMP NOT NULL
+);
+
+INSERT INTO insights_rollup (id, rolled_up_to) VALUES (TRUE, NOW()) ON CONFLICT DO NOTHING;
@@ -0,0 +1,48 @@
+-- DM privacy and message requests
+-- Each account chooses who may message it: everyone, followers (people who
+-- follow the account), mutuals (follow each other) or nobody. Anyone else
+-- who starts a chat lands in the recipient's message requests, which they
+-- can accept or decline; with nobody, new chats are refused outright.
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS dm_privacy VARCHAR(20) NOT NULL DEFAULT 'everyone';
+
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_dm_privacy_check;
+ALTER TABLE users ADD CONSTRAINT users_dm_privacy_check
+    CHECK (dm_privacy IN ('everyone', 'followers', 'mutuals', 'nobody'));
+
+-- Friends: both accounts follow each other
+CREATE OR REPLACE FUNCTION are_mutuals(a UUID, b UUID)
+RETURNS BOOLEAN AS $$
+    SELECT EXISTS (SELECT 1 FROM follows WHERE follower_id = a AND following_id = b)
+       AND EXISTS (SELECT 1 FROM follows WHERE follower_id = b AND following_id = a);
+$$ LANGUAGE SQL STABLE;
+
+-- Whether `sender` may message `recipient` directly: allowed, request or denied
+CREATE OR REPLACE FUNCTION dm_permission(sender UUID, recipient UUID)
+RETURNS TEXT AS $$
+    SELECT CASE
+        WHEN u.dm_privacy = 'everyone' THEN 'allowed'
+        WHEN u.dm_privacy = 'nobody' THEN 'denied'
+        WHEN u.dm_privacy = 'followers'
+             AND EXISTS (SELECT 1 FROM follows WHERE follower_id = sender AND following_id = recipient) THEN 'allowed'
+        WHEN u.dm_privacy = 'mutuals' AND are_mutuals(sender, recipient) THEN 'allowed'
+        ELSE 'request'
+    END
+    FROM users u
+    WHERE u.id = recipient;
+$$ LANGUAGE SQL STABLE;
+
+-- A member who didn't permit whoever brought them into a chat
+CREATE TABLE IF NOT EXISTS message_requests (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    chat_room_id UUID NOT NULL REFERENCES chat_rooms(id) ON DELETE CASCADE,
+    sender_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    recipient_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'accepted', 'declined')),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    responded_at TIMESTAMP,
+    UNIQUE (chat_room_id, recipient_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_message_requests_recipient ON message_requests(recipient_id, created_at DESC) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_message_requests_declined ON message_requests(sender_id, recipient_id) WHERE status = 'declined';
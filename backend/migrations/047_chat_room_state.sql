@@ -0,0 +1,34 @@
+-- Message request state of a chat room
+-- A 1:1 chat is pending from the moment it becomes a message request until
+-- the recipient accepts it. A declined request leaves it pending, so the
+-- sender isn't told. Group chats stay accepted; a member invited through a
+-- request sees the group in their requests until they answer.
+
+ALTER TABLE chat_rooms ADD COLUMN IF NOT EXISTS state VARCHAR(20) NOT NULL DEFAULT 'accepted';
+
+ALTER TABLE chat_rooms DROP CONSTRAINT IF EXISTS chat_rooms_state_check;
+ALTER TABLE chat_rooms ADD CONSTRAINT chat_rooms_state_check CHECK (state IN ('pending', 'accepted'));
+
+UPDATE chat_rooms cr SET state = 'pending'
+WHERE NOT cr.is_group
+  AND EXISTS (SELECT 1 FROM message_requests WHERE chat_room_id = cr.id AND status <> 'accepted');
+
+CREATE OR REPLACE FUNCTION sync_chat_room_state()
+RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE chat_rooms cr
+    SET state = CASE
+        WHEN EXISTS (SELECT 1 FROM message_requests WHERE chat_room_id = cr.id AND status <> 'accepted') THEN 'pending'
+        ELSE 'accepted'
+    END
+    WHERE cr.id = NEW.chat_room_id AND NOT cr.is_group;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trigger_sync_chat_room_state ON message_requests;
+CREATE TRIGGER trigger_sync_chat_room_state
+    AFTER INSERT OR UPDATE OF status ON message_requests
+    FOR EACH ROW
+    EXECUTE FUNCTION sync_chat_room_state();
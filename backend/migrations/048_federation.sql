@@ -0,0 +1,37 @@
+-- ActivityPub federation (opt-in)
+-- Accounts that turn it on get an actor other servers can follow; their
+-- public stories are sent to remote followers as Create activities and
+-- withdrawn with Delete once they expire or are removed.
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS federated BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Signing key of a federated actor (RSA, PEM)
+CREATE TABLE IF NOT EXISTS federation_keys (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    public_key_pem TEXT NOT NULL,
+    private_key_pem TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- Remote actors following a local account
+CREATE TABLE IF NOT EXISTS federation_followers (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    actor_id TEXT NOT NULL,
+    inbox_url TEXT NOT NULL,
+    shared_inbox_url TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, actor_id)
+);
+
+-- Stories sent out, so they can be withdrawn. No foreign key to stories:
+-- the row has to outlive the story to send the Delete.
+CREATE TABLE IF NOT EXISTS federated_stories (
+    story_id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    expires_at TIMESTAMP NOT NULL,
+    published_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    retracted_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_federated_stories_live ON federated_stories(expires_at) WHERE retracted_at IS NULL;
@@ -0,0 +1,14 @@
+-- Analytics summary emails sent to admins
+-- One row per report; the report job checks the latest to know when the next
+-- one is due, so restarts don't send it twice.
+
+CREATE TABLE IF NOT EXISTS analytics_reports (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    period_start DATE NOT NULL,
+    period_end DATE NOT NULL,
+    recipients TEXT[] NOT NULL,
+    metrics TEXT[] NOT NULL,
+    sent_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_analytics_reports_sent_at ON analytics_reports(sent_at DESC);
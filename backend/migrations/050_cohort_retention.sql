@@ -0,0 +1,61 @@
+-- Cohort retention
+-- Story views and messages are deleted as they expire and logins were never
+-- recorded, so activity is kept as one row per user and week (weeks start on
+-- Monday). Views and messages are recorded by the triggers below, logins by
+-- the login handler. The compute_retention job turns these into
+-- retention_cohorts: per signup week, how many of its users were active in
+-- each following week.
+
+CREATE TABLE IF NOT EXISTS user_activity_weeks (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    week DATE NOT NULL,
+    PRIMARY KEY (user_id, week)
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_activity_weeks_week ON user_activity_weeks(week);
+
+CREATE OR REPLACE FUNCTION record_user_activity_week()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_TABLE_NAME = 'story_views' THEN
+        INSERT INTO user_activity_weeks (user_id, week)
+        VALUES (NEW.viewer_id, date_trunc('week', NEW.viewed_at)::date)
+        ON CONFLICT DO NOTHING;
+    ELSE
+        INSERT INTO user_activity_weeks (user_id, week)
+        VALUES (NEW.sender_id, date_trunc('week', NEW.created_at)::date)
+        ON CONFLICT DO NOTHING;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trigger_story_view_activity ON story_views;
+CREATE TRIGGER trigger_story_view_activity
+    AFTER INSERT ON story_views
+    FOR EACH ROW
+    EXECUTE FUNCTION record_user_activity_week();
+
+DROP TRIGGER IF EXISTS trigger_message_activity ON messages;
+CREATE TRIGGER trigger_message_activity
+    AFTER INSERT ON messages
+    FOR EACH ROW
+    EXECUTE FUNCTION record_user_activity_week();
+
+-- Whatever activity is still in the tables
+INSERT INTO user_activity_weeks (user_id, week)
+SELECT DISTINCT viewer_id, date_trunc('week', viewed_at)::date FROM story_views
+ON CONFLICT DO NOTHING;
+INSERT INTO user_activity_weeks (user_id, week)
+SELECT DISTINCT sender_id, date_trunc('week', created_at)::date FROM messages
+ON CONFLICT DO NOTHING;
+
+-- week_offset 0 is the signup week itself
+CREATE TABLE IF NOT EXISTS retention_cohorts (
+    cohort_week DATE NOT NULL,
+    week_offset INTEGER NOT NULL,
+    cohort_size INTEGER NOT NULL,
+    active_users INTEGER NOT NULL,
+    computed_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (cohort_week, week_offset)
+);
@@ -0,0 +1,10 @@
+-- Email domains refused at signup, managed by admins. A domain also covers
+-- its subdomains. Disposable-mail providers are refused separately by the
+-- built-in list in signup_guard.rs.
+
+CREATE TABLE IF NOT EXISTS blocked_email_domains (
+    domain TEXT PRIMARY KEY,
+    reason TEXT,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
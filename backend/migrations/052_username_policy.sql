@@ -0,0 +1,67 @@
+-- Username policy
+-- username_skeleton folds a name to the form it is read as: compatibility
+-- normalization, lower case, Cyrillic and Greek lookalikes to Latin, digits
+-- and symbols used as letters (0 -> o, 1 -> l, $ -> s), i/l treated as one
+-- letter, rn as m and vv as w, separators dropped. Two names with the same
+-- skeleton are indistinguishable at a glance, so a new name may not share one
+-- with an existing account or a reserved name.
+
+CREATE OR REPLACE FUNCTION username_skeleton(name TEXT)
+RETURNS TEXT AS $$
+    SELECT replace(replace(
+        translate(lower(normalize(name, NFKC)),
+                  'авеһніјкӏморсѕтухԁαβεικνορτυχıɡi0134578|!$@_.-',
+                  'abehhljklmopcstyxdabelkvoptuxlgloleastbllsa'),
+        'rn', 'm'), 'vv', 'w')
+$$ LANGUAGE SQL IMMUTABLE;
+
+CREATE INDEX IF NOT EXISTS idx_users_username_skeleton ON users(username_skeleton(username));
+
+-- Names nobody may take; with match_substring, nor any name containing it
+CREATE TABLE IF NOT EXISTS reserved_usernames (
+    name TEXT PRIMARY KEY,
+    match_substring BOOLEAN NOT NULL DEFAULT FALSE,
+    reason TEXT,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO reserved_usernames (name, match_substring, reason) VALUES
+    ('relays', FALSE, 'brand'),
+    ('relayssocial', TRUE, 'brand'),
+    ('relaysofficial', TRUE, 'brand'),
+    ('admin', FALSE, 'staff'),
+    ('administrator', FALSE, 'staff'),
+    ('moderator', FALSE, 'staff'),
+    ('mod', FALSE, 'staff'),
+    ('staff', FALSE, 'staff'),
+    ('support', FALSE, 'staff'),
+    ('help', FALSE, 'staff'),
+    ('helpdesk', FALSE, 'staff'),
+    ('security', FALSE, 'staff'),
+    ('safety', FALSE, 'staff'),
+    ('trustandsafety', FALSE, 'staff'),
+    ('official', FALSE, 'staff'),
+    ('team', FALSE, 'staff'),
+    ('system', FALSE, 'system'),
+    ('root', FALSE, 'system'),
+    ('api', FALSE, 'system'),
+    ('www', FALSE, 'system'),
+    ('noreply', FALSE, 'system'),
+    ('null', FALSE, 'system'),
+    ('undefined', FALSE, 'system'),
+    ('anonymous', FALSE, 'system'),
+    ('everyone', FALSE, 'system'),
+    ('here', FALSE, 'system'),
+    ('me', FALSE, 'system'),
+    ('settings', FALSE, 'route'),
+    ('login', FALSE, 'route'),
+    ('signup', FALSE, 'route'),
+    ('logout', FALSE, 'route'),
+    ('explore', FALSE, 'route'),
+    ('discover', FALSE, 'route'),
+    ('search', FALSE, 'route'),
+    ('stories', FALSE, 'route'),
+    ('messages', FALSE, 'route'),
+    ('notifications', FALSE, 'route')
+ON CONFLICT (name) DO NOTHING;
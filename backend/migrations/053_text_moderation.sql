@@ -0,0 +1,54 @@
+-- Text moderation for captions, comments and group names
+-- Terms are matched as whole words after folding leetspeak and repeated
+-- letters (see text_moderation.rs). What happens on a match depends on the term's
+-- severity: block the text, mask the word, or let it through and queue it
+-- for review here. Modes per severity are set with MODERATION_MODE_*.
+
+CREATE TABLE IF NOT EXISTS moderation_terms (
+    term TEXT PRIMARY KEY,
+    severity VARCHAR(10) NOT NULL CHECK (severity IN ('low', 'medium', 'high')),
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS moderation_flags (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    content_type VARCHAR(20) NOT NULL CHECK (content_type IN ('caption', 'comment', 'group_name')),
+    -- Story, comment or chat room; no foreign key so the flag outlives it
+    content_id UUID NOT NULL,
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    content_text TEXT NOT NULL,
+    matched_terms TEXT[] NOT NULL,
+    severity VARCHAR(10) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'dismissed', 'actioned')),
+    reviewed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    reviewed_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_moderation_flags_pending ON moderation_flags(created_at) WHERE status = 'pending';
+
+INSERT INTO moderation_terms (term, severity) VALUES
+    ('nigger', 'high'),
+    ('nigga', 'high'),
+    ('faggot', 'high'),
+    ('fag', 'high'),
+    ('kike', 'high'),
+    ('spic', 'high'),
+    ('chink', 'high'),
+    ('tranny', 'high'),
+    ('retard', 'high'),
+    ('fuck', 'medium'),
+    ('motherfucker', 'medium'),
+    ('shit', 'medium'),
+    ('cunt', 'medium'),
+    ('bitch', 'medium'),
+    ('asshole', 'medium'),
+    ('dick', 'medium'),
+    ('whore', 'medium'),
+    ('slut', 'medium'),
+    ('bastard', 'low'),
+    ('damn', 'low'),
+    ('crap', 'low'),
+    ('piss', 'low')
+ON CONFLICT (term) DO NOTHING;
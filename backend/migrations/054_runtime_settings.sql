@@ -0,0 +1,23 @@
+-- Runtime settings that admins can change without a redeploy
+-- Only overrides are stored; every setting's type, bounds and default live in
+-- runtime_settings.rs. Deleting a row puts the setting back on its default.
+
+CREATE TABLE IF NOT EXISTS runtime_settings (
+    key VARCHAR(100) PRIMARY KEY,
+    value JSONB NOT NULL,
+    updated_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- One row per change; a null value means the default was in effect
+CREATE TABLE IF NOT EXISTS runtime_setting_changes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    key VARCHAR(100) NOT NULL,
+    old_value JSONB,
+    new_value JSONB,
+    reason TEXT,
+    changed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    changed_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_runtime_setting_changes_key ON runtime_setting_changes(key, changed_at DESC);
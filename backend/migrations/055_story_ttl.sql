@@ -0,0 +1,6 @@
+-- Story lifetimes chosen at upload: 1, 6, 24 or 48 hours, or permanent for
+-- creator and business accounts. Permanent stories get a far-future
+-- expires_at so every "expires_at > NOW()" check keeps treating them as live;
+-- feeds only show them while they're as new as a regular story.
+
+ALTER TABLE stories ADD COLUMN IF NOT EXISTS is_permanent BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,23 @@
+-- Carousel stories: up to 10 ordered media items in one story
+-- Rows only exist for stories with more than one item. Position 0 repeats the
+-- story's own media_url, which keeps holding that object's reference; every
+-- later item holds a reference of its own.
+
+CREATE TABLE IF NOT EXISTS story_media (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    story_id UUID NOT NULL REFERENCES stories(id) ON DELETE CASCADE,
+    position SMALLINT NOT NULL CHECK (position BETWEEN 0 AND 9),
+    media_url TEXT NOT NULL,
+    media_type VARCHAR(10) NOT NULL,
+    alt_text TEXT,
+    view_count INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (story_id, position)
+);
+
+CREATE TABLE IF NOT EXISTS story_media_views (
+    media_id UUID NOT NULL REFERENCES story_media(id) ON DELETE CASCADE,
+    viewer_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    viewed_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (media_id, viewer_id)
+);
@@ -0,0 +1,25 @@
+-- Co-authored stories. The author invites one other user; once they accept,
+-- the story shows on both profiles and under both names in the story tray,
+-- and either of them can delete it.
+
+CREATE TABLE IF NOT EXISTS story_collaborators (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    story_id UUID NOT NULL REFERENCES stories(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    invited_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'accepted', 'declined')),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    responded_at TIMESTAMP
+);
+
+-- At most one open or accepted co-author per story
+CREATE UNIQUE INDEX IF NOT EXISTS idx_story_collaborators_open
+    ON story_collaborators(story_id) WHERE status IN ('pending', 'accepted');
+CREATE INDEX IF NOT EXISTS idx_story_collaborators_user
+    ON story_collaborators(user_id) WHERE status IN ('pending', 'accepted');
+
+-- Everyone a story appears under: its author and an accepted co-author
+CREATE OR REPLACE VIEW story_authors AS
+    SELECT id AS story_id, user_id FROM stories
+    UNION ALL
+    SELECT story_id, user_id FROM story_collaborators WHERE status = 'accepted';
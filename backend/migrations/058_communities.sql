@@ -0,0 +1,26 @@
+-- Communities: public group rooms anyone can find and join. They are ordinary
+-- group chats (is_group = TRUE) with room_type = 'public', so messaging,
+-- unread counts and the websocket work unchanged.
+
+ALTER TABLE chat_rooms ADD COLUMN IF NOT EXISTS room_type VARCHAR(20) NOT NULL DEFAULT 'private'
+    CHECK (room_type IN ('private', 'public'));
+ALTER TABLE chat_rooms ADD COLUMN IF NOT EXISTS description TEXT;
+ALTER TABLE chat_rooms ADD COLUMN IF NOT EXISTS avatar_url TEXT;
+-- New members wait for a moderator instead of joining straight away
+ALTER TABLE chat_rooms ADD COLUMN IF NOT EXISTS join_approval BOOLEAN NOT NULL DEFAULT FALSE;
+-- Code for the community's join link; rotating it invalidates old links
+ALTER TABLE chat_rooms ADD COLUMN IF NOT EXISTS invite_code VARCHAR(32) UNIQUE;
+
+CREATE INDEX IF NOT EXISTS idx_chat_rooms_public_name_trgm
+    ON chat_rooms USING gin (name gin_trgm_ops) WHERE room_type = 'public';
+
+-- Roles only matter in communities; other rooms leave everyone a member
+ALTER TABLE chat_members ADD COLUMN IF NOT EXISTS role VARCHAR(20) NOT NULL DEFAULT 'member'
+    CHECK (role IN ('owner', 'moderator', 'member'));
+
+CREATE TABLE IF NOT EXISTS community_join_requests (
+    chat_room_id UUID NOT NULL REFERENCES chat_rooms(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (chat_room_id, user_id)
+);
@@ -0,0 +1,20 @@
+-- Broadcast channels: one-to-many rooms where only the owner posts. A
+-- channel is a group chat with room_type = 'channel'; subscribers are its
+-- members and can only react to posts.
+
+ALTER TABLE chat_rooms DROP CONSTRAINT IF EXISTS chat_rooms_room_type_check;
+ALTER TABLE chat_rooms ADD CONSTRAINT chat_rooms_room_type_check
+    CHECK (room_type IN ('private', 'public', 'channel'));
+
+-- Each creator runs at most one channel
+CREATE UNIQUE INDEX IF NOT EXISTS idx_chat_rooms_one_channel
+    ON chat_rooms(created_by) WHERE room_type = 'channel';
+
+-- One reaction per subscriber per post; reacting again replaces it
+CREATE TABLE IF NOT EXISTS message_reactions (
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    emoji VARCHAR(32) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (message_id, user_id)
+);
@@ -0,0 +1,46 @@
+-- Live streaming. Media goes peer to peer over WebRTC; the server only keeps
+-- track of sessions, hands out viewer join tokens, relays SDP/ICE over the
+-- WebSocket and stores the live chat.
+
+CREATE TABLE IF NOT EXISTS live_sessions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    host_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    title VARCHAR(200),
+    status VARCHAR(20) NOT NULL DEFAULT 'live' CHECK (status IN ('live', 'ended')),
+    peak_viewers INTEGER NOT NULL DEFAULT 0,
+    started_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    ended_at TIMESTAMP
+);
+
+-- A host streams one session at a time
+CREATE UNIQUE INDEX IF NOT EXISTS idx_live_sessions_one_live
+    ON live_sessions(host_id) WHERE status = 'live';
+
+-- A viewer holds an unused join token (only its hash is stored) until they
+-- present it over the WebSocket; joined_at is set then and left_at when they go
+CREATE TABLE IF NOT EXISTS live_viewers (
+    session_id UUID NOT NULL REFERENCES live_sessions(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash VARCHAR(64),
+    token_expires_at TIMESTAMP,
+    joined_at TIMESTAMP,
+    left_at TIMESTAMP,
+    PRIMARY KEY (session_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_live_viewers_user ON live_viewers(user_id) WHERE joined_at IS NOT NULL AND left_at IS NULL;
+
+CREATE TABLE IF NOT EXISTS live_chat_messages (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    session_id UUID NOT NULL REFERENCES live_sessions(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_live_chat_messages_session ON live_chat_messages(session_id, created_at DESC);
+
+-- Live titles and chat go through the text filter too
+ALTER TABLE moderation_flags DROP CONSTRAINT IF EXISTS moderation_flags_content_type_check;
+ALTER TABLE moderation_flags ADD CONSTRAINT moderation_flags_content_type_check
+    CHECK (content_type IN ('caption', 'comment', 'group_name', 'live_title', 'live_chat'));
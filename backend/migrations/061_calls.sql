@@ -0,0 +1,23 @@
+-- 1:1 audio and video calls. Media goes peer to peer over WebRTC; the server
+-- relays the offer, answer and ICE candidates over the WebSocket and keeps a
+-- record of each call for the chat history.
+
+CREATE TABLE IF NOT EXISTS calls (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    chat_room_id UUID NOT NULL REFERENCES chat_rooms(id) ON DELETE CASCADE,
+    caller_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    callee_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    media VARCHAR(10) NOT NULL CHECK (media IN ('audio', 'video')),
+    -- ringing until answered; missed when the caller gave up or nobody answered
+    status VARCHAR(20) NOT NULL DEFAULT 'ringing'
+        CHECK (status IN ('ringing', 'accepted', 'declined', 'missed', 'ended')),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    answered_at TIMESTAMP,
+    ended_at TIMESTAMP,
+    -- Time from answer to hang-up; null for calls that never connected
+    duration_seconds INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS idx_calls_chat_room ON calls(chat_room_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_calls_active_caller ON calls(caller_id) WHERE status IN ('ringing', 'accepted');
+CREATE INDEX IF NOT EXISTS idx_calls_active_callee ON calls(callee_id) WHERE status IN ('ringing', 'accepted');
@@ -0,0 +1,77 @@
+-- Localized server messages
+-- Notifications keep their English text in message and also carry a catalog
+-- key with its arguments, so they are rendered in the reader's language when
+-- listed (see i18n.rs). Rows from before this migration have no key and are
+-- shown as stored.
+
+-- Preferred language; NULL follows the request's Accept-Language
+ALTER TABLE users ADD COLUMN IF NOT EXISTS locale VARCHAR(10);
+
+ALTER TABLE notifications ADD COLUMN IF NOT EXISTS message_key VARCHAR(50);
+ALTER TABLE notifications ADD COLUMN IF NOT EXISTS message_args JSONB NOT NULL DEFAULT '{}';
+
+-- {"es": {"title": ..., "body": ...}, ...}; locales without an entry get title/body
+ALTER TABLE announcements ADD COLUMN IF NOT EXISTS translations JSONB NOT NULL DEFAULT '{}';
+
+CREATE OR REPLACE FUNCTION create_follow_notification()
+RETURNS TRIGGER AS $$
+DECLARE
+    follower_name TEXT := (SELECT username FROM users WHERE id = NEW.follower_id);
+BEGIN
+    INSERT INTO notifications (user_id, type, from_user_id, message, message_key, message_args)
+    VALUES (
+        NEW.following_id,
+        'follow',
+        NEW.follower_id,
+        follower_name || ' started following you',
+        'follow',
+        jsonb_build_object('username', follower_name)
+    );
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION create_like_notification()
+RETURNS TRIGGER AS $$
+DECLARE
+    liker_name TEXT := (SELECT username FROM users WHERE id = NEW.user_id);
+BEGIN
+    -- Only notify if it's not the user's own story
+    IF (SELECT user_id FROM stories WHERE id = NEW.story_id) != NEW.user_id THEN
+        INSERT INTO notifications (user_id, type, from_user_id, story_id, message, message_key, message_args)
+        VALUES (
+            (SELECT user_id FROM stories WHERE id = NEW.story_id),
+            'like',
+            NEW.user_id,
+            NEW.story_id,
+            liker_name || ' liked your story',
+            'like',
+            jsonb_build_object('username', liker_name)
+        );
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION create_comment_notification()
+RETURNS TRIGGER AS $$
+DECLARE
+    commenter_name TEXT := (SELECT username FROM users WHERE id = NEW.user_id);
+BEGIN
+    -- Only notify if it's not the user's own story
+    IF (SELECT user_id FROM stories WHERE id = NEW.story_id) != NEW.user_id THEN
+        INSERT INTO notifications (user_id, type, from_user_id, story_id, comment_id, message, message_key, message_args)
+        VALUES (
+            (SELECT user_id FROM stories WHERE id = NEW.story_id),
+            'comment',
+            NEW.user_id,
+            NEW.story_id,
+            NEW.id,
+            commenter_name || ' commented on your story',
+            'comment',
+            jsonb_build_object('username', commenter_name)
+        );
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,21 @@
+-- Per-story permissions chosen by the author
+-- allow_sharing       viewers may forward the story into a chat
+-- allow_comments      new comments and replies are accepted; existing ones are hidden while off
+-- screenshot_warning  clients warn viewers that the author asked for no screenshots
+-- hide_like_count     only the author sees how many likes the story has
+
+ALTER TABLE stories ADD COLUMN IF NOT EXISTS allow_sharing BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE stories ADD COLUMN IF NOT EXISTS allow_comments BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE stories ADD COLUMN IF NOT EXISTS screenshot_warning BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE stories ADD COLUMN IF NOT EXISTS hide_like_count BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- A story shared into a chat; content holds the story id
+ALTER TABLE messages DROP CONSTRAINT IF EXISTS messages_message_type_check;
+ALTER TABLE messages ADD CONSTRAINT messages_message_type_check
+    CHECK (message_type IN ('text', 'image', 'video', 'gif', 'sticker', 'story'));
+
+ALTER TABLE messages DROP CONSTRAINT IF EXISTS valid_content;
+ALTER TABLE messages ADD CONSTRAINT valid_content CHECK (
+    (message_type IN ('text', 'story') AND content IS NOT NULL) OR
+    (message_type IN ('image', 'video', 'gif', 'sticker') AND media_url IS NOT NULL)
+);
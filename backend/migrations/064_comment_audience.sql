@@ -0,0 +1,20 @@
+-- Who may comment: everyone, followers (of the author), following (people
+-- the author follows) or off. Set per account, optionally overridden per
+-- story; allow_comments = FALSE (migration 063) still turns a story's
+-- comments off whatever the audience.
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS comment_audience VARCHAR(20) NOT NULL DEFAULT 'everyone'
+    CHECK (comment_audience IN ('everyone', 'followers', 'following', 'off'));
+
+-- NULL follows the author's account setting
+ALTER TABLE stories ADD COLUMN IF NOT EXISTS comment_audience VARCHAR(20)
+    CHECK (comment_audience IN ('everyone', 'followers', 'following', 'off'));
+
+CREATE OR REPLACE FUNCTION story_comment_audience(p_story_id UUID)
+RETURNS TEXT AS $$
+    SELECT CASE WHEN NOT s.allow_comments THEN 'off'
+                ELSE COALESCE(s.comment_audience, u.comment_audience) END
+    FROM stories s
+    JOIN users u ON u.id = s.user_id
+    WHERE s.id = p_story_id
+$$ LANGUAGE sql STABLE;
@@ -0,0 +1,43 @@
+-- Muted words
+-- Words and phrases a user doesn't want to see. Comments on their stories
+-- that contain one are hidden from everyone but the commenter, and direct
+-- messages that contain one reach them folded behind a warning. Both are
+-- decided when the comment or message is written (see muted_words.rs).
+
+CREATE TABLE IF NOT EXISTS muted_words (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- Stored trimmed and lowercased
+    phrase VARCHAR(100) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, phrase)
+);
+
+ALTER TABLE story_comments ADD COLUMN IF NOT EXISTS hidden_by_filter BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- The muted phrase a message matched for this recipient
+ALTER TABLE message_recipients ADD COLUMN IF NOT EXISTS muted_word VARCHAR(100);
+
+-- Hidden comments don't notify the author either
+CREATE OR REPLACE FUNCTION create_comment_notification()
+RETURNS TRIGGER AS $$
+DECLARE
+    commenter_name TEXT := (SELECT username FROM users WHERE id = NEW.user_id);
+BEGIN
+    -- Only notify if it's not the user's own story
+    IF (SELECT user_id FROM stories WHERE id = NEW.story_id) != NEW.user_id AND NOT NEW.hidden_by_filter THEN
+        INSERT INTO notifications (user_id, type, from_user_id, story_id, comment_id, message, message_key, message_args)
+        VALUES (
+            (SELECT user_id FROM stories WHERE id = NEW.story_id),
+            'comment',
+            NEW.user_id,
+            NEW.story_id,
+            NEW.id,
+            commenter_name || ' commented on your story',
+            'comment',
+            jsonb_build_object('username', commenter_name)
+        );
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
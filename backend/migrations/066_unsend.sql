@@ -0,0 +1,18 @@
+-- Unsend
+-- A sender can take a message back for everyone within 30 seconds of sending
+-- it. Its content and media are removed, but the row stays in the chat as an
+-- 'unsent' placeholder so the other members can see that something was
+-- taken back, and by whom.
+
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS unsent_at TIMESTAMP;
+
+ALTER TABLE messages DROP CONSTRAINT IF EXISTS messages_message_type_check;
+ALTER TABLE messages ADD CONSTRAINT messages_message_type_check
+    CHECK (message_type IN ('text', 'image', 'video', 'gif', 'sticker', 'story', 'unsent'));
+
+ALTER TABLE messages DROP CONSTRAINT IF EXISTS valid_content;
+ALTER TABLE messages ADD CONSTRAINT valid_content CHECK (
+    (message_type IN ('text', 'story') AND content IS NOT NULL) OR
+    (message_type IN ('image', 'video', 'gif', 'sticker') AND media_url IS NOT NULL) OR
+    (message_type = 'unsent' AND unsent_at IS NOT NULL AND content IS NULL AND media_url IS NULL)
+);
@@ -0,0 +1,11 @@
+-- Room retention
+-- A group's owner can have every message in it removed a fixed time after it
+-- was sent. The expiration sweep gives messages that reach the limit an
+-- expires_at, so they're warned about, retained for savers and deleted the
+-- same way self-destructing messages are (see apply_room_retention in
+-- expiration.rs). NULL keeps messages until someone deletes them.
+
+ALTER TABLE chat_rooms ADD COLUMN IF NOT EXISTS retention_seconds INTEGER
+    CHECK (retention_seconds > 0);
+
+CREATE INDEX IF NOT EXISTS idx_chat_rooms_retention ON chat_rooms(id) WHERE retention_seconds IS NOT NULL;
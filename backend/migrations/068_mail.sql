@@ -0,0 +1,36 @@
+-- Transactional mail
+-- Every email goes through mail_deliveries and is sent by a deliver_mail job,
+-- which retries with the job queue's backoff. Addresses that hard-bounced or
+-- complained (reported by the provider's webhook) or that an admin blocked
+-- are suppressed: mail to them is recorded but never sent.
+
+CREATE TABLE IF NOT EXISTS mail_deliveries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    recipient VARCHAR(255) NOT NULL,
+    template VARCHAR(50) NOT NULL,
+    subject TEXT NOT NULL,
+    text_body TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'retrying', 'sent', 'failed', 'suppressed')),
+    provider VARCHAR(20),
+    -- The provider's id for the sent message, which its bounce reports refer to
+    provider_message_id TEXT,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    sent_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_mail_deliveries_status ON mail_deliveries(status, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_mail_deliveries_recipient ON mail_deliveries(recipient, created_at DESC);
+
+CREATE TABLE IF NOT EXISTS mail_suppressions (
+    -- Stored lowercased
+    email VARCHAR(255) PRIMARY KEY,
+    reason VARCHAR(20) NOT NULL CHECK (reason IN ('bounce', 'complaint', 'manual')),
+    provider VARCHAR(20),
+    detail TEXT,
+    -- The admin who added a manual suppression
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,28 @@
+-- Phone numbers
+-- Users can sign up, log in and recover their account with a phone number
+-- confirmed by a one-time code sent over SMS (see phone.rs). A number is
+-- stored on the user, in E.164 form, only once it's verified. People who have
+-- it in their contacts can find the account unless discoverable_by_phone is off.
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS phone_number VARCHAR(16) UNIQUE;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS phone_verified_at TIMESTAMP;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS discoverable_by_phone BOOLEAN NOT NULL DEFAULT TRUE;
+
+CREATE TABLE IF NOT EXISTS phone_codes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    phone_number VARCHAR(16) NOT NULL,
+    purpose VARCHAR(20) NOT NULL CHECK (purpose IN ('signup', 'verify', 'login', 'recovery')),
+    -- The account the code was sent for: the one adding the number for
+    -- 'verify', the number's owner for 'login' and 'recovery'
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    -- SHA-256 of "<phone_number>:<code>"
+    code_hash VARCHAR(64) NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    requested_ip VARCHAR(45),
+    expires_at TIMESTAMP NOT NULL,
+    consumed_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_phone_codes_number ON phone_codes(phone_number, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_phone_codes_ip ON phone_codes(requested_ip, created_at DESC);
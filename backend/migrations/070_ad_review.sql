@@ -0,0 +1,39 @@
+-- Ad review
+-- Each time a paid ad goes to pending_approval its creative is snapshotted as
+-- the ad's next revision. The reviewer's decision is recorded on that
+-- revision: internal notes for other reviewers, and for rejections the reason
+-- shown to the advertiser, who can edit the ad and resubmit it as a new
+-- revision. submitted_at and decided_at give the review SLA metrics.
+
+ALTER TABLE advertisements ADD COLUMN IF NOT EXISTS revision INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS ad_revisions (
+    ad_id UUID NOT NULL REFERENCES advertisements(id) ON DELETE CASCADE,
+    revision INTEGER NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    description TEXT,
+    image_url TEXT,
+    link_url TEXT,
+    is_mature BOOLEAN NOT NULL DEFAULT FALSE,
+    submitted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    decision VARCHAR(10) CHECK (decision IN ('approved', 'rejected')),
+    reviewer_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    -- Shown to the advertiser
+    rejection_reason TEXT,
+    -- Only shown to reviewers
+    notes TEXT,
+    decided_at TIMESTAMP,
+    PRIMARY KEY (ad_id, revision),
+    CHECK ((decision IS NULL) = (decided_at IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS idx_ad_revisions_pending ON ad_revisions(submitted_at) WHERE decided_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_ad_revisions_decided ON ad_revisions(decided_at) WHERE decided_at IS NOT NULL;
+
+-- Ads already waiting for review become their first revision, submitted when paid for
+UPDATE advertisements SET revision = 1 WHERE status = 'pending_approval' AND revision = 0;
+INSERT INTO ad_revisions (ad_id, revision, title, description, image_url, link_url, is_mature, submitted_at)
+SELECT id, revision, title, description, image_url, link_url, is_mature, COALESCE(paid_at, created_at)
+FROM advertisements
+WHERE status = 'pending_approval'
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,37 @@
+-- Boosted stories
+-- A creator pays to show one of their stories to people who don't follow
+-- them. The boost buys a number of impressions at the boosts.cpm_usd rate and
+-- runs from payment until it has them or the story expires. Boosted stories
+-- are placed in the story feed with the ads, labelled as sponsored.
+
+CREATE TABLE IF NOT EXISTS story_boosts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- Kept after the story is deleted so the creator's spend stays on record
+    story_id UUID REFERENCES stories(id) ON DELETE SET NULL,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending_payment'
+        CHECK (status IN ('pending_payment', 'active', 'completed', 'cancelled')),
+    target_impressions INTEGER NOT NULL CHECK (target_impressions > 0),
+    impressions INTEGER NOT NULL DEFAULT 0,
+    -- Taps through to the creator's profile
+    clicks INTEGER NOT NULL DEFAULT 0,
+    price NUMERIC(10, 2) NOT NULL CHECK (price > 0),
+    -- ISO 3166-1 alpha-2 countries to show the story in; empty for everywhere
+    target_countries TEXT[] NOT NULL DEFAULT '{}',
+    stripe_session_id TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    paid_at TIMESTAMP,
+    ended_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_story_boosts_active ON story_boosts(story_id) WHERE status = 'active';
+CREATE INDEX IF NOT EXISTS idx_story_boosts_user ON story_boosts(user_id, created_at DESC);
+
+-- One impression per viewer; follows and likes after it are credited to the boost
+CREATE TABLE IF NOT EXISTS story_boost_impressions (
+    boost_id UUID NOT NULL REFERENCES story_boosts(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    clicked_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (boost_id, user_id)
+);
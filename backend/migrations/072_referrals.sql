@@ -0,0 +1,158 @@
+-- Referral program
+-- Every user can share a referral code; an account created with one is
+-- attributed to its owner. A referral converts once the new user posts a
+-- story or sends a message, and converted referrals earn the referrer the
+-- rewards an admin configured in referral_rewards: a badge or streak freezes,
+-- which keep a messaging streak alive over one missed day. Referrals from
+-- the referrer's own network or device (account_fingerprints) are flagged
+-- and earn nothing.
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS referral_code VARCHAR(12) UNIQUE;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS streak_freezes INTEGER NOT NULL DEFAULT 0 CHECK (streak_freezes >= 0);
+
+-- IP addresses and client device ids each account signed up or logged in from
+CREATE TABLE IF NOT EXISTS account_fingerprints (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind VARCHAR(10) NOT NULL CHECK (kind IN ('ip', 'device')),
+    value TEXT NOT NULL,
+    first_seen_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_seen_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, kind, value)
+);
+
+CREATE INDEX IF NOT EXISTS idx_account_fingerprints_value ON account_fingerprints(kind, value);
+CREATE INDEX IF NOT EXISTS idx_account_fingerprints_seen ON account_fingerprints(last_seen_at);
+
+CREATE TABLE IF NOT EXISTS referrals (
+    -- An account has at most one referrer
+    referred_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    referrer_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    status VARCHAR(10) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'converted', 'flagged')),
+    -- same_ip or same_device
+    flag_reason VARCHAR(20),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    converted_at TIMESTAMP,
+    CHECK (referred_id <> referrer_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_referrals_referrer ON referrals(referrer_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_referrals_pending ON referrals(created_at) WHERE status = 'pending';
+
+-- Reward tiers, each granted once when a referrer reaches referrals_required
+-- converted referrals
+CREATE TABLE IF NOT EXISTS referral_rewards (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    referrals_required INTEGER NOT NULL CHECK (referrals_required > 0),
+    kind VARCHAR(20) NOT NULL CHECK (kind IN ('badge', 'streak_freezes')),
+    badge VARCHAR(40),
+    streak_freezes INTEGER,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CHECK ((kind = 'badge' AND badge IS NOT NULL) OR (kind = 'streak_freezes' AND streak_freezes > 0))
+);
+
+CREATE TABLE IF NOT EXISTS referral_reward_grants (
+    reward_id UUID NOT NULL REFERENCES referral_rewards(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    granted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (reward_id, user_id)
+);
+
+CREATE TABLE IF NOT EXISTS user_badges (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    badge VARCHAR(40) NOT NULL,
+    awarded_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, badge)
+);
+
+INSERT INTO referral_rewards (referrals_required, kind, badge, streak_freezes) VALUES
+    (1, 'streak_freezes', NULL, 1),
+    (3, 'streak_freezes', NULL, 2),
+    (5, 'badge', 'recruiter', NULL),
+    (25, 'badge', 'ambassador', NULL);
+
+-- update_streak from migration 006, now spending a streak freeze from either
+-- user to bridge a single missed day
+CREATE OR REPLACE FUNCTION update_streak(p_user1_id UUID, p_user2_id UUID)
+RETURNS TABLE(current_streak INTEGER, longest_streak INTEGER) AS $$
+DECLARE
+    v_user1_id UUID;
+    v_user2_id UUID;
+    v_last_date DATE;
+    v_current_streak INTEGER;
+    v_longest_streak INTEGER;
+    v_today DATE := CURRENT_DATE;
+    v_yesterday DATE := CURRENT_DATE - INTERVAL '1 day';
+    v_exists BOOLEAN;
+    v_frozen_by UUID;
+BEGIN
+    -- Ensure user1_id < user2_id
+    IF p_user1_id < p_user2_id THEN
+        v_user1_id := p_user1_id;
+        v_user2_id := p_user2_id;
+    ELSE
+        v_user1_id := p_user2_id;
+        v_user2_id := p_user1_id;
+    END IF;
+
+    -- Check if streak exists
+    SELECT last_interaction_date, user_streaks.current_streak, user_streaks.longest_streak
+    INTO v_last_date, v_current_streak, v_longest_streak
+    FROM user_streaks
+    WHERE user1_id = v_user1_id AND user2_id = v_user2_id;
+    v_exists := FOUND;
+
+    -- One missed day is bridged by a freeze, if either user has one left
+    IF v_exists AND v_last_date = v_today - 2 THEN
+        UPDATE users SET streak_freezes = streak_freezes - 1
+        WHERE id = (
+            SELECT id FROM users
+            WHERE id IN (v_user1_id, v_user2_id) AND streak_freezes > 0
+            ORDER BY streak_freezes DESC
+            LIMIT 1
+        )
+        RETURNING id INTO v_frozen_by;
+        IF v_frozen_by IS NOT NULL THEN
+            v_last_date := v_yesterday;
+        END IF;
+    END IF;
+
+    IF NOT v_exists THEN
+        -- Create new streak
+        INSERT INTO user_streaks (user1_id, user2_id, current_streak, longest_streak, last_interaction_date)
+        VALUES (v_user1_id, v_user2_id, 1, 1, v_today)
+        RETURNING user_streaks.current_streak, user_streaks.longest_streak
+        INTO current_streak, longest_streak;
+    ELSIF v_last_date = v_today THEN
+        -- Already interacted today, return current values
+        current_streak := v_current_streak;
+        longest_streak := v_longest_streak;
+    ELSIF v_last_date = v_yesterday THEN
+        -- Consecutive day, increment streak
+        v_current_streak := v_current_streak + 1;
+        v_longest_streak := GREATEST(v_longest_streak, v_current_streak);
+
+        UPDATE user_streaks
+        SET current_streak = v_current_streak,
+            longest_streak = v_longest_streak,
+            last_interaction_date = v_today,
+            updated_at = NOW()
+        WHERE user1_id = v_user1_id AND user2_id = v_user2_id;
+
+        current_streak := v_current_streak;
+        longest_streak := v_longest_streak;
+    ELSE
+        -- Streak broken, reset to 1
+        UPDATE user_streaks
+        SET current_streak = 1,
+            longest_streak = v_longest_streak,
+            last_interaction_date = v_today,
+            updated_at = NOW()
+        WHERE user1_id = v_user1_id AND user2_id = v_user2_id;
+
+        current_streak := 1;
+        longest_streak := v_longest_streak;
+    END IF;
+
+    RETURN QUERY SELECT current_streak, longest_streak;
+END;
+$$ LANGUAGE plpgsql;
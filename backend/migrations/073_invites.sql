@@ -0,0 +1,47 @@
+-- Invite codes
+-- With the signup.invite_only runtime setting on, an account can only be
+-- created with an invite code. Admins generate codes in batches; every user
+-- can also create codes up to their allowance (invites.per_user, or
+-- users.invite_allowance when an admin set one). Each redemption records who
+-- invited whom, giving the invite graph.
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS invite_allowance INTEGER CHECK (invite_allowance >= 0);
+
+CREATE TABLE IF NOT EXISTS invite_batches (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    label VARCHAR(100) NOT NULL,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS invite_codes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    code VARCHAR(16) NOT NULL UNIQUE,
+    -- Set for codes an admin generated, null for codes users created
+    batch_id UUID REFERENCES invite_batches(id) ON DELETE CASCADE,
+    -- The user a code belongs to; null for batch codes
+    owner_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    max_uses INTEGER NOT NULL DEFAULT 1 CHECK (max_uses > 0),
+    uses INTEGER NOT NULL DEFAULT 0,
+    expires_at TIMESTAMP,
+    revoked_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CHECK ((batch_id IS NULL) <> (owner_id IS NULL)),
+    CHECK (uses <= max_uses)
+);
+
+CREATE INDEX IF NOT EXISTS idx_invite_codes_batch ON invite_codes(batch_id) WHERE batch_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_invite_codes_owner ON invite_codes(owner_id, created_at DESC) WHERE owner_id IS NOT NULL;
+
+CREATE TABLE IF NOT EXISTS invite_redemptions (
+    -- An account is created with at most one code
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    code_id UUID REFERENCES invite_codes(id) ON DELETE SET NULL,
+    -- The code's owner, copied so the graph survives the code being deleted
+    inviter_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    batch_id UUID REFERENCES invite_batches(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_invite_redemptions_inviter ON invite_redemptions(inviter_id) WHERE inviter_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_invite_redemptions_code ON invite_redemptions(code_id);
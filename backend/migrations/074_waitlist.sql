@@ -0,0 +1,15 @@
+-- Waitlist
+-- While signup is invite-only, people can leave their email address to be
+-- invited later. Admins release the longest-waiting entries in batches: each
+-- gets a single-use invite code from a new invite batch, sent by email.
+
+CREATE TABLE IF NOT EXISTS waitlist (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- Lowercased
+    email VARCHAR(255) NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    released_at TIMESTAMP,
+    invite_code_id UUID REFERENCES invite_codes(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_waitlist_waiting ON waitlist(created_at, id) WHERE released_at IS NULL;
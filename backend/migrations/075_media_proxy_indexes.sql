@@ -0,0 +1,10 @@
+-- Media proxy lookups
+-- GET /media/*key decides who may fetch an object from the stories and
+-- messages that point at it, so those lookups by URL need indexes.
+
+CREATE INDEX IF NOT EXISTS idx_stories_media_url ON stories(media_url);
+CREATE INDEX IF NOT EXISTS idx_stories_thumbnail_url ON stories(thumbnail_url) WHERE thumbnail_url IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_stories_preview_url ON stories(preview_url) WHERE preview_url IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_story_media_media_url ON story_media(media_url);
+CREATE INDEX IF NOT EXISTS idx_messages_media_url ON messages(media_url) WHERE media_url IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_messages_media_thumbnail_url ON messages(media_thumbnail_url) WHERE media_thumbnail_url IS NOT NULL;
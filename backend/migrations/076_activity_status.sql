@@ -0,0 +1,18 @@
+-- Activity status
+-- Mutuals see each other's online status and when they were last active in
+-- chat headers. Presence lives in Redis; last_active_at is its durable copy,
+-- written when a user's sockets connect and disconnect, for when the Redis
+-- entry has expired. Anyone can turn activity status off, and then they
+-- don't see anyone else's either.
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS show_activity_status BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS last_active_at TIMESTAMP;
+
+-- Whether `viewer` may see `subject`'s activity status
+CREATE OR REPLACE FUNCTION activity_visible(viewer UUID, subject UUID)
+RETURNS BOOLEAN AS $$
+    SELECT viewer <> subject
+       AND (SELECT show_activity_status FROM users WHERE id = viewer)
+       AND (SELECT show_activity_status FROM users WHERE id = subject)
+       AND are_mutuals(viewer, subject);
+$$ LANGUAGE SQL STABLE;
@@ -0,0 +1,17 @@
+-- Message delivery state
+-- What the sender sees on their own message, from the recipients' rows in
+-- message_recipients (043): read once every other member has read it,
+-- delivered once it has reached a device of every other member, sent
+-- otherwise.
+
+CREATE OR REPLACE FUNCTION message_delivery_state(msg_id UUID)
+RETURNS TEXT AS $$
+    SELECT CASE
+        WHEN bool_and(r.read_at IS NOT NULL) THEN 'read'
+        WHEN bool_and(r.delivered_at IS NOT NULL) THEN 'delivered'
+        ELSE 'sent'
+    END
+    FROM message_recipients r
+    JOIN messages m ON m.id = r.message_id
+    WHERE r.message_id = msg_id AND r.user_id <> m.sender_id;
+$$ LANGUAGE SQL STABLE;
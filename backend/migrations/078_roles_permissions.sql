@@ -0,0 +1,59 @@
+-- Roles and permissions
+-- users.role names a row in roles; what a role may do is the set of
+-- permissions granted to it in role_permissions. The permissions themselves
+-- are fixed by the code, which checks them by name. user, moderator and admin
+-- are built in and can't be deleted; admins can add roles and change what
+-- any role grants.
+
+CREATE TABLE IF NOT EXISTS roles (
+    name VARCHAR(32) PRIMARY KEY CHECK (name ~ '^[a-z][a-z0-9_]*$'),
+    description TEXT NOT NULL DEFAULT '',
+    built_in BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS permissions (
+    name VARCHAR(64) PRIMARY KEY,
+    description TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS role_permissions (
+    role VARCHAR(32) NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+    permission VARCHAR(64) NOT NULL REFERENCES permissions(name) ON DELETE CASCADE,
+    PRIMARY KEY (role, permission)
+);
+
+INSERT INTO roles (name, description, built_in) VALUES
+    ('user', 'Regular account', TRUE),
+    ('moderator', 'Handles reports, bans and content review', TRUE),
+    ('admin', 'Full access', TRUE)
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO permissions (name, description) VALUES
+    ('can_ban', 'Ban and unban users'),
+    ('can_delete_users', 'Delete user accounts'),
+    ('can_moderate_content', 'Review flags, takedowns, quarantined uploads, reserved usernames and blocked signup domains'),
+    ('can_manage_ads', 'Create, review and manage ads and see their reports'),
+    ('can_view_analytics', 'See platform analytics, live stats, retention and the admin log'),
+    ('can_manage_platform', 'Runtime settings, feature flags, announcements, jobs, mail, webhooks, invites, waitlist and referral rewards'),
+    ('can_manage_roles', 'Assign roles to users and change what each role grants')
+ON CONFLICT (name) DO UPDATE SET description = EXCLUDED.description;
+
+INSERT INTO role_permissions (role, permission)
+SELECT 'admin', name FROM permissions
+ON CONFLICT DO NOTHING;
+
+INSERT INTO role_permissions (role, permission) VALUES
+    ('moderator', 'can_ban'),
+    ('moderator', 'can_moderate_content'),
+    ('moderator', 'can_view_analytics')
+ON CONFLICT DO NOTHING;
+
+-- Roles outside the old fixed list become roles without permissions
+INSERT INTO roles (name)
+SELECT DISTINCT role FROM users WHERE role ~ '^[a-z][a-z0-9_]*$'
+ON CONFLICT (name) DO NOTHING;
+
+ALTER TABLE users DROP CONSTRAINT IF EXISTS check_user_role;
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_role_fkey;
+ALTER TABLE users ADD CONSTRAINT users_role_fkey FOREIGN KEY (role) REFERENCES roles(name) ON UPDATE CASCADE;
@@ -0,0 +1,29 @@
+-- Community moderation
+-- Owners and moderators of a community (chat_members.role, migration 058)
+-- can ban people from it and remove messages posted in it, and nowhere
+-- else. A ban removes the member and stops them joining again until it's
+-- lifted. What community moderators do is logged here, apart from the global
+-- admin_logs; staff acting through their global role are logged there.
+
+CREATE TABLE IF NOT EXISTS community_bans (
+    chat_room_id UUID NOT NULL REFERENCES chat_rooms(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    banned_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    reason TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (chat_room_id, user_id)
+);
+
+CREATE TABLE IF NOT EXISTS community_mod_logs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    chat_room_id UUID NOT NULL REFERENCES chat_rooms(id) ON DELETE CASCADE,
+    moderator_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    -- ban, unban, remove_member, remove_message or set_role
+    action VARCHAR(32) NOT NULL,
+    target_user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    target_message_id UUID,
+    details JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_community_mod_logs_room ON community_mod_logs(chat_room_id, created_at DESC);
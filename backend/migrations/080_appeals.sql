@@ -0,0 +1,49 @@
+-- Appeals
+-- A user can appeal each sanction against them once: a ban on their account,
+-- a takedown of their story or comment, or a moderation flag that was acted
+-- on against their text. appealable_sanctions lists what each user can
+-- appeal right now. Staff decide an appeal once; overturning it lifts the
+-- ban or takedown (or dismisses the flag), and the user is emailed the
+-- decision either way.
+
+CREATE TABLE IF NOT EXISTS appeals (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind VARCHAR(20) NOT NULL CHECK (kind IN ('ban', 'takedown', 'moderation_flag')),
+    -- The user_bans, geo_takedowns or moderation_flags row; no foreign key so
+    -- the appeal outlives it
+    subject_id UUID NOT NULL,
+    body TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'upheld', 'overturned')),
+    decided_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    -- Sent to the user with the decision
+    decision_note TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    decided_at TIMESTAMP,
+    UNIQUE (kind, subject_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_appeals_pending ON appeals(created_at) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_appeals_user ON appeals(user_id, created_at DESC);
+
+CREATE OR REPLACE VIEW appealable_sanctions AS
+SELECT 'ban'::text AS kind, b.id AS subject_id, b.user_id, b.reason,
+       NULL::text AS content_type, NULL::uuid AS content_id, b.banned_at AS created_at
+FROM user_bans b
+WHERE b.unbanned_at IS NULL
+UNION ALL
+SELECT 'takedown', t.id,
+       CASE t.content_type
+           WHEN 'story' THEN COALESCE(
+               (SELECT user_id FROM stories WHERE id = t.content_id),
+               (SELECT user_id FROM story_archive WHERE original_story_id = t.content_id LIMIT 1))
+           ELSE (SELECT user_id FROM story_comments WHERE id = t.content_id)
+       END,
+       t.legal_basis, t.content_type::text, t.content_id, t.created_at
+FROM geo_takedowns t
+WHERE t.lifted_at IS NULL
+UNION ALL
+SELECT 'moderation_flag', f.id, f.user_id, NULL,
+       f.content_type::text, f.content_id, COALESCE(f.reviewed_at, f.created_at)
+FROM moderation_flags f
+WHERE f.status = 'actioned' AND f.user_id IS NOT NULL;
@@ -0,0 +1,5 @@
+-- Notification paging
+-- Notifications are paged newest first with a cursor on (created_at, id), and
+-- old ones can be cleared in bulk; both walk this index.
+
+CREATE INDEX IF NOT EXISTS idx_notifications_user_created ON notifications(user_id, created_at DESC, id DESC);
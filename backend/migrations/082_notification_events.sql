@@ -0,0 +1,17 @@
+-- Notification events
+-- Every new notification is announced on the notification_created channel
+-- as "<user_id>:<notification_id>", whether application code or a trigger
+-- wrote it. Each API instance listens and pushes it to the user's sockets.
+
+CREATE OR REPLACE FUNCTION announce_notification()
+RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify('notification_created', NEW.user_id::text || ':' || NEW.id::text);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS notification_created ON notifications;
+CREATE TRIGGER notification_created
+    AFTER INSERT ON notifications
+    FOR EACH ROW EXECUTE FUNCTION announce_notification();
@@ -0,0 +1,92 @@
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::redis_client::RedisClient;
+
+// "Active now" / "Active 2h ago" for chat headers. Only mutuals see each
+// other's status, and only while both have it turned on (activity_visible,
+// migration 076): hiding yours hides everyone else's from you too. Online
+// state and last-seen come from Redis presence, which heartbeats keep fresh;
+// users.last_active_at fills in once the presence entry has expired.
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct ActivityStatus {
+    /// A socket is connected right now
+    pub online: bool,
+    /// When they were last connected; null if they never have been
+    #[serde(with = "crate::timestamps::option")]
+    pub last_active_at: Option<NaiveDateTime>,
+}
+
+/// Remember when a user was last around, for after their Redis presence is gone
+pub async fn touch(pool: &PgPool, user_id: Uuid) {
+    if let Err(e) = sqlx::query("UPDATE users SET last_active_at = NOW() WHERE id = $1")
+        .bind(user_id)
+        .execute(pool)
+        .await
+    {
+        eprintln!("❌ Failed to record last activity for {}: {:?}", user_id, e);
+    }
+}
+
+/// Activity status of each of `subjects` that `viewer` may see. Users whose
+/// status is hidden from the viewer are left out of the map.
+pub async fn visible_to(
+    pool: &PgPool,
+    redis: &Mutex<RedisClient>,
+    viewer: Uuid,
+    subjects: &[Uuid],
+) -> HashMap<Uuid, ActivityStatus> {
+    if subjects.is_empty() {
+        return HashMap::new();
+    }
+
+    let visible = sqlx::query_as::<_, (Uuid, Option<NaiveDateTime>)>(
+        r#"
+        SELECT u.id, u.last_active_at
+        FROM users u
+        WHERE u.id = ANY($2) AND activity_visible($1, u.id)
+        "#
+    )
+    .bind(viewer)
+    .bind(subjects)
+    .fetch_all(pool)
+    .await;
+    let visible = match visible {
+        Ok(visible) => visible,
+        Err(e) => {
+            eprintln!("❌ Activity status query failed: {:?}", e);
+            return HashMap::new();
+        }
+    };
+    if visible.is_empty() {
+        return HashMap::new();
+    }
+
+    let ids: Vec<Uuid> = visible.iter().map(|(id, _)| *id).collect();
+    let presence = redis.lock().await.get_presence(&ids).await.unwrap_or_else(|e| {
+        tracing::warn!("Failed to read presence: {}", e);
+        ids.iter().map(|_| None).collect()
+    });
+
+    visible
+        .into_iter()
+        .zip(presence)
+        .map(|((id, stored), presence)| {
+            let status = match presence {
+                Some(presence) => ActivityStatus {
+                    online: presence.online,
+                    // Heartbeats refresh presence while online, so now is closer
+                    last_active_at: Some(if presence.online { Utc::now() } else { presence.last_seen }.naive_utc()),
+                },
+                None => ActivityStatus { online: false, last_active_at: stored },
+            };
+            (id, status)
+        })
+        .collect()
+}
@@ -0,0 +1,490 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use bigdecimal::{BigDecimal, FromPrimitive};
+use chrono::{NaiveDate, NaiveDateTime};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::PgPool;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::permissions::{role_has, CanManageAds, Permission};
+use crate::AppState;
+
+// CPM (cost per thousand impressions) billing. A cpm campaign holds a prepaid
+// balance; the charge_cpm_impression trigger (migration 041) deducts
+// cpm_rate / 1000 per impression and pauses the campaign when the balance runs
+// out. Top-ups go through Stripe checkout and are credited when Stripe confirms
+// payment; a campaign paused for its budget resumes once it can pay again.
+
+pub const BILLING_MODELS: [&str; 2] = ["package", "cpm"];
+const MIN_CPM_RATE: f64 = 0.01;
+const MAX_CPM_RATE: f64 = 1000.0;
+const MIN_TOP_UP: f64 = 1.0;
+const MAX_TOP_UP: f64 = 100_000.0;
+// Stripe rejects webhook events signed longer ago than this
+const SIGNATURE_TOLERANCE_SECS: i64 = 300;
+
+/// Checks the billing fields of a new campaign and returns the model and rate to store
+pub fn check_billing(
+    billing_model: Option<&str>,
+    cpm_rate: Option<f64>,
+) -> Result<(String, Option<BigDecimal>), (StatusCode, String)> {
+    let model = billing_model.unwrap_or("package");
+    if !BILLING_MODELS.contains(&model) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("billing_model must be one of: {}", BILLING_MODELS.join(", ")),
+        ));
+    }
+    if model == "package" {
+        return Ok((model.to_string(), None));
+    }
+    let rate = cpm_rate.ok_or((StatusCode::BAD_REQUEST, "cpm_rate is required for cpm campaigns".to_string()))?;
+    Ok((model.to_string(), Some(cpm_rate_value(rate)?)))
+}
+
+pub fn cpm_rate_value(rate: f64) -> Result<BigDecimal, (StatusCode, String)> {
+    if !(MIN_CPM_RATE..=MAX_CPM_RATE).contains(&rate) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("cpm_rate must be between {} and {}", MIN_CPM_RATE, MAX_CPM_RATE),
+        ));
+    }
+    Ok(money(rate))
+}
+
+pub(crate) fn money(amount: f64) -> BigDecimal {
+    BigDecimal::from_f64(amount).unwrap_or_default().with_scale(2)
+}
+
+/// Credit a paid top-up to its campaign, resuming the campaign if it was paused
+/// for its budget. Top-ups already credited are skipped, so Stripe redelivering
+/// an event is harmless. Returns the campaign credited.
+pub async fn credit_top_up(pool: &PgPool, top_up_id: Uuid, session_id: Option<&str>) -> Result<Option<Uuid>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let top_up = sqlx::query_as::<_, (Uuid, BigDecimal)>(
+        r#"
+        UPDATE ad_budget_topups
+        SET status = 'paid', paid_at = NOW(), stripe_session_id = COALESCE($2, stripe_session_id)
+        WHERE id = $1 AND status = 'pending'
+        RETURNING ad_id, amount
+        "#
+    )
+    .bind(top_up_id)
+    .bind(session_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some((ad_id, amount)) = top_up else {
+        return Ok(None);
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE advertisements
+        SET budget_balance = budget_balance + $2,
+            status = CASE
+                WHEN status = 'paused' AND budget_exhausted_at IS NOT NULL
+                     AND budget_balance + $2 >= cpm_rate / 1000 THEN 'active'
+                ELSE status
+            END,
+            budget_exhausted_at = CASE
+                WHEN budget_balance + $2 >= cpm_rate / 1000 THEN NULL
+                ELSE budget_exhausted_at
+            END,
+            updated_at = NOW()
+        WHERE id = $1
+        "#
+    )
+    .bind(ad_id)
+    .bind(&amount)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    tracing::info!(ad_id = %ad_id, top_up_id = %top_up_id, amount = %amount, "Ad budget topped up");
+    Ok(Some(ad_id))
+}
+
+/// Credit the opening budget of a self-serve cpm campaign once the campaign is paid for
+pub async fn credit_pending_top_ups(pool: &PgPool, ad_id: Uuid, session_id: Option<&str>) -> Result<(), sqlx::Error> {
+    let pending = sqlx::query_scalar::<_, Uuid>("SELECT id FROM ad_budget_topups WHERE ad_id = $1 AND status = 'pending'")
+        .bind(ad_id)
+        .fetch_all(pool)
+        .await?;
+    for top_up_id in pending {
+        credit_top_up(pool, top_up_id, session_id).await?;
+    }
+    Ok(())
+}
+
+/// Record the opening budget of a self-serve cpm campaign, credited on payment
+pub async fn queue_opening_budget(pool: &PgPool, ad_id: Uuid, amount: f64, created_by: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO ad_budget_topups (ad_id, amount, created_by) VALUES ($1, $2, $3)")
+        .bind(ad_id)
+        .bind(money(amount))
+        .bind(created_by)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Stripe secret key, or None in development where payments are auto-approved
+pub(crate) fn stripe_secret_key() -> Option<String> {
+    std::env::var("STRIPE_SECRET_KEY")
+        .ok()
+        .filter(|k| !k.is_empty() && k != "sk_test_mock")
+}
+
+/// Check the Stripe-Signature header ("t=<unix time>,v1=<hex hmac>,...")
+/// against the raw body. Returns false for a bad or stale signature.
+pub fn verify_stripe_signature(secret: &str, header: &str, body: &str) -> bool {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", t)) => timestamp = t.parse::<i64>().ok(),
+            Some(("v1", sig)) => signatures.extend(hex::decode(sig).ok()),
+            _ => {}
+        }
+    }
+    let Some(timestamp) = timestamp else {
+        return false;
+    };
+    if (chrono::Utc::now().timestamp() - timestamp).abs() > SIGNATURE_TOLERANCE_SECS {
+        return false;
+    }
+
+    signatures.iter().any(|sig| {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+        mac.update(format!("{}.{}", timestamp, body).as_bytes());
+        mac.verify_slice(sig).is_ok()
+    })
+}
+
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .expect("Failed to build Stripe HTTP client")
+    })
+}
+
+#[derive(Deserialize)]
+pub(crate) struct StripeSession {
+    pub id: String,
+    pub url: Option<String>,
+}
+
+/// Open a Stripe checkout session charging `amount` for `product`. The
+/// payment is tagged with `reference`, e.g. ("top_up", id): the webhook finds
+/// it in the session's `top_up_id` metadata and the return URL gets
+/// `?top_up=<id>&status=...`.
+pub(crate) async fn create_checkout_session(
+    secret_key: &str,
+    product: &str,
+    amount: &BigDecimal,
+    reference: (&str, Uuid),
+    client_reference_id: Uuid,
+) -> Result<StripeSession, String> {
+    let return_url = std::env::var("STRIPE_RETURN_URL").map_err(|_| "STRIPE_RETURN_URL is not set".to_string())?;
+    let cents = (amount * BigDecimal::from(100)).with_scale(0).to_string();
+    let (kind, id) = reference;
+    let metadata_key = format!("metadata[{}_id]", kind);
+    let form = [
+        ("mode", "payment".to_string()),
+        ("success_url", format!("{}?{}={}&status=success", return_url, kind, id)),
+        ("cancel_url", format!("{}?{}={}&status=cancelled", return_url, kind, id)),
+        ("client_reference_id", client_reference_id.to_string()),
+        ("line_items[0][quantity]", "1".to_string()),
+        ("line_items[0][price_data][currency]", "usd".to_string()),
+        ("line_items[0][price_data][unit_amount]", cents),
+        ("line_items[0][price_data][product_data][name]", product.to_string()),
+        (metadata_key.as_str(), id.to_string()),
+    ];
+
+    let response = http_client()
+        .post("https://api.stripe.com/v1/checkout/sessions")
+        .basic_auth(secret_key, Some(""))
+        .form(&form)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Stripe returned {}: {}", status, body));
+    }
+    response.json::<StripeSession>().await.map_err(|e| e.to_string())
+}
+
+#[derive(sqlx::FromRow)]
+struct BillingAd {
+    created_by: Option<Uuid>,
+    billing_model: String,
+    status: String,
+}
+
+/// The campaign's billing details, if `user` created it or is an admin
+async fn billing_ad(pool: &PgPool, ad_id: Uuid, user: &AuthUser) -> Result<BillingAd, (StatusCode, String)> {
+    let ad = sqlx::query_as::<_, BillingAd>("SELECT created_by, billing_model, status FROM advertisements WHERE id = $1")
+        .bind(ad_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Ad not found".to_string()))?;
+    if ad.created_by != Some(user.id) && !role_has(pool, &user.role, CanManageAds::NAME).await {
+        return Err((StatusCode::FORBIDDEN, "Not your campaign".to_string()));
+    }
+    Ok(ad)
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TopUpRequest {
+    /// Amount in USD
+    pub amount: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TopUpResponse {
+    pub top_up_id: Uuid,
+    /// pending until Stripe confirms payment, paid once credited
+    pub status: String,
+    pub session_id: Option<String>,
+    /// Stripe checkout page to send the advertiser to
+    pub checkout_url: Option<String>,
+}
+
+// Add budget to a cpm campaign
+#[utoipa::path(
+    post,
+    path = "/api/v1/ads/{ad_id}/budget/top-up",
+    tag = "ads",
+    params(("ad_id" = Uuid, Path, description = "Ad ID")),
+    request_body = TopUpRequest,
+    responses(
+        (status = 200, body = TopUpResponse),
+        (status = 400, description = "Invalid amount or not a cpm campaign"),
+        (status = 403, description = "Not your campaign"),
+        (status = 404, description = "Ad not found"),
+        (status = 409, description = "The campaign itself is not paid for yet"),
+        (status = 502, description = "Stripe checkout could not be created"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn top_up_budget(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(ad_id): Path<Uuid>,
+    Json(payload): Json<TopUpRequest>,
+) -> Result<Json<TopUpResponse>, (StatusCode, String)> {
+    if !payload.amount.is_finite() || !(MIN_TOP_UP..=MAX_TOP_UP).contains(&payload.amount) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("amount must be between {} and {}", MIN_TOP_UP, MAX_TOP_UP),
+        ));
+    }
+
+    let ad = billing_ad(&state.pool, ad_id, &user).await?;
+    if ad.billing_model != "cpm" {
+        return Err((StatusCode::BAD_REQUEST, "Only cpm campaigns have a budget".to_string()));
+    }
+    if ad.status == "pending_payment" {
+        return Err((StatusCode::CONFLICT, "Pay for the campaign before topping it up".to_string()));
+    }
+
+    let amount = money(payload.amount);
+    let top_up_id = sqlx::query_scalar::<_, Uuid>(
+        "INSERT INTO ad_budget_topups (ad_id, amount, created_by) VALUES ($1, $2, $3) RETURNING id",
+    )
+    .bind(ad_id)
+    .bind(&amount)
+    .bind(user.id)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let Some(secret_key) = stripe_secret_key() else {
+        // Development mode - credit straight away
+        credit_top_up(&state.pool, top_up_id, None)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        return Ok(Json(TopUpResponse {
+            top_up_id,
+            status: "paid".to_string(),
+            session_id: None,
+            checkout_url: None,
+        }));
+    };
+
+    let session = create_checkout_session(&secret_key, "Ad campaign budget", &amount, ("top_up", top_up_id), ad_id)
+        .await
+        .map_err(|e| {
+            tracing::error!(ad_id = %ad_id, top_up_id = %top_up_id, error = %e, "Stripe checkout failed");
+            (StatusCode::BAD_GATEWAY, "Could not start checkout".to_string())
+        })?;
+
+    sqlx::query("UPDATE ad_budget_topups SET stripe_session_id = $2 WHERE id = $1")
+        .bind(top_up_id)
+        .bind(&session.id)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(TopUpResponse {
+        top_up_id,
+        status: "pending".to_string(),
+        session_id: Some(session.id),
+        checkout_url: session.url,
+    }))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SpendQuery {
+    /// Days of daily spend to return (default 30, max 365)
+    pub days: Option<i32>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct DailySpend {
+    pub day: NaiveDate,
+    pub impressions: i32,
+    pub spend: f64,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct BudgetTopUp {
+    pub id: Uuid,
+    pub amount: f64,
+    pub status: String,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps::option")]
+    pub paid_at: Option<NaiveDateTime>,
+}
+
+#[derive(sqlx::FromRow)]
+struct SpendTotals {
+    billing_model: String,
+    status: String,
+    cpm_rate: Option<f64>,
+    budget_balance: f64,
+    total_spend: f64,
+    budget_exhausted_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SpendReport {
+    pub ad_id: Uuid,
+    /// package or cpm
+    pub billing_model: String,
+    pub status: String,
+    pub cpm_rate: Option<f64>,
+    pub budget_balance: f64,
+    pub total_spend: f64,
+    /// Impressions the balance still pays for (cpm campaigns)
+    pub impressions_remaining: Option<i64>,
+    /// When the campaign was paused for running out of budget
+    #[serde(with = "crate::timestamps::option")]
+    pub budget_exhausted_at: Option<NaiveDateTime>,
+    /// Newest first
+    pub daily: Vec<DailySpend>,
+    /// Newest first
+    pub top_ups: Vec<BudgetTopUp>,
+}
+
+// Balance, spend per day and top-ups of a campaign
+#[utoipa::path(
+    get,
+    path = "/api/v1/ads/{ad_id}/spend",
+    tag = "ads",
+    params(("ad_id" = Uuid, Path, description = "Ad ID"), SpendQuery),
+    responses(
+        (status = 200, body = SpendReport),
+        (status = 403, description = "Not your campaign"),
+        (status = 404, description = "Ad not found"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_spend_report(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(ad_id): Path<Uuid>,
+    Query(params): Query<SpendQuery>,
+) -> Result<Json<SpendReport>, (StatusCode, String)> {
+    billing_ad(&state.pool, ad_id, &user).await?;
+    let days = params.days.unwrap_or(30).clamp(1, 365);
+
+    let totals = sqlx::query_as::<_, SpendTotals>(
+        r#"
+        SELECT billing_model, status, cpm_rate::float8 AS cpm_rate,
+               budget_balance::float8 AS budget_balance, total_spend::float8 AS total_spend,
+               budget_exhausted_at
+        FROM advertisements WHERE id = $1
+        "#
+    )
+    .bind(ad_id)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let daily = sqlx::query_as::<_, DailySpend>(
+        r#"
+        SELECT day, impressions, spend::float8 AS spend
+        FROM ad_spend_daily
+        WHERE ad_id = $1 AND day > CURRENT_DATE - $2
+        ORDER BY day DESC
+        "#
+    )
+    .bind(ad_id)
+    .bind(days)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let top_ups = sqlx::query_as::<_, BudgetTopUp>(
+        r#"
+        SELECT id, amount::float8 AS amount, status, created_at, paid_at
+        FROM ad_budget_topups
+        WHERE ad_id = $1
+        ORDER BY created_at DESC
+        "#
+    )
+    .bind(ad_id)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let impressions_remaining = totals
+        .cpm_rate
+        .filter(|rate| totals.billing_model == "cpm" && *rate > 0.0)
+        .map(|rate| (totals.budget_balance / (rate / 1000.0)).floor() as i64);
+
+    Ok(Json(SpendReport {
+        ad_id,
+        billing_model: totals.billing_model,
+        status: totals.status,
+        cpm_rate: totals.cpm_rate,
+        budget_balance: totals.budget_balance,
+        total_spend: totals.total_spend,
+        impressions_remaining,
+        budget_exhausted_at: totals.budget_exhausted_at,
+        daily,
+        top_ups,
+    }))
+}
@@ -0,0 +1,312 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use bigdecimal::{BigDecimal, FromPrimitive};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::permissions::{CanManageAds, Permitted};
+use crate::AppState;
+
+// Conversion tracking for ads. Recording a click returns a click_id (the
+// impression's ID) and a redirect URL carrying it as sm_click; the landing
+// page hands it back through the pixel or the conversion API. Attribution
+// rules live in migration 040.
+
+const DEFAULT_EVENT: &str = "conversion";
+const MAX_EVENT_LENGTH: usize = 50;
+const MAX_ORDER_ID_LENGTH: usize = 100;
+
+/// Query parameter that carries the click ID to the advertiser's landing page
+pub const CLICK_PARAM: &str = "sm_click";
+
+// Transparent 1x1 GIF
+const PIXEL_GIF: &[u8] = &[
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff,
+    0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00,
+    0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
+
+/// The advertiser's link with the click ID appended
+pub fn redirect_url(link_url: &str, click_id: Uuid) -> String {
+    let separator = if link_url.contains('?') { '&' } else { '?' };
+    match link_url.split_once('#') {
+        Some((base, fragment)) => format!("{}{}{}={}#{}", base, separator, CLICK_PARAM, click_id, fragment),
+        None => format!("{}{}{}={}", link_url, separator, CLICK_PARAM, click_id),
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ConversionInput {
+    /// sm_click value from the landing page URL
+    pub click_id: Option<Uuid>,
+    /// For conversions inside the app, instead of click_id
+    pub user_id: Option<Uuid>,
+    /// Lowercase letters, digits and underscores (default "conversion")
+    pub event: Option<String>,
+    pub value: Option<f64>,
+    /// ISO 4217 code
+    pub currency: Option<String>,
+    /// Advertiser's reference; a repeat of the same event and order is ignored
+    pub order_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ConversionResponse {
+    /// False when the click or impression is unknown, outside the window or fraudulent
+    pub attributed: bool,
+    /// click or view
+    pub attribution: Option<String>,
+    pub conversion_id: Option<Uuid>,
+    /// The same event and order was already recorded
+    pub duplicate: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct Attribution {
+    impression_id: Uuid,
+    user_id: Uuid,
+    attribution: Option<String>,
+}
+
+impl ConversionResponse {
+    fn unattributed() -> Self {
+        ConversionResponse { attributed: false, attribution: None, conversion_id: None, duplicate: false }
+    }
+}
+
+fn normalize_event(event: Option<&str>) -> Result<String, String> {
+    let event = event.map(|e| e.trim().to_lowercase()).unwrap_or_else(|| DEFAULT_EVENT.to_string());
+    if event.is_empty()
+        || event.len() > MAX_EVENT_LENGTH
+        || !event.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    {
+        return Err(format!(
+            "event must be 1-{} lowercase letters, digits or underscores",
+            MAX_EVENT_LENGTH
+        ));
+    }
+    Ok(event)
+}
+
+async fn record(pool: &PgPool, ad_id: Uuid, input: &ConversionInput, source: &str) -> Result<ConversionResponse, (StatusCode, String)> {
+    let event = normalize_event(input.event.as_deref()).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let order_id = input.order_id.as_deref().map(str::trim).unwrap_or_default();
+    if order_id.len() > MAX_ORDER_ID_LENGTH {
+        return Err((StatusCode::BAD_REQUEST, format!("order_id is limited to {} characters", MAX_ORDER_ID_LENGTH)));
+    }
+    let currency = input.currency.as_deref().map(|c| c.trim().to_uppercase());
+    if currency.as_deref().is_some_and(|c| c.len() != 3 || !c.chars().all(|ch| ch.is_ascii_uppercase())) {
+        return Err((StatusCode::BAD_REQUEST, "currency must be an ISO 4217 code".to_string()));
+    }
+    if input.value.is_some_and(|v| !v.is_finite() || v < 0.0) {
+        return Err((StatusCode::BAD_REQUEST, "value must be a non-negative number".to_string()));
+    }
+    if input.click_id.is_none() && input.user_id.is_none() {
+        return Err((StatusCode::BAD_REQUEST, "click_id or user_id is required".to_string()));
+    }
+
+    let attribution = sqlx::query_as::<_, Attribution>(
+        r#"
+        SELECT ai.id AS impression_id, ai.user_id,
+               CASE
+                   WHEN ai.fraud_reason IS NOT NULL THEN NULL
+                   WHEN ai.clicked AND ai.clicked_at > NOW() - make_interval(hours => a.attribution_window_hours) THEN 'click'
+                   WHEN ai.shown_at > NOW() - make_interval(hours => a.attribution_window_hours) THEN 'view'
+               END AS attribution
+        FROM ad_impressions ai
+        JOIN advertisements a ON a.id = ai.ad_id
+        WHERE ai.ad_id = $1
+          AND ($2::uuid IS NULL OR ai.id = $2)
+          AND ($3::uuid IS NULL OR ai.user_id = $3)
+        "#
+    )
+    .bind(ad_id)
+    .bind(input.click_id)
+    .bind(input.user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let Some(Attribution { impression_id, user_id, attribution: Some(attribution) }) = attribution else {
+        return Ok(ConversionResponse::unattributed());
+    };
+
+    let conversion_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO ad_conversions
+            (ad_id, impression_id, user_id, event, value, currency, order_id, attribution, source)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        ON CONFLICT (impression_id, event, order_id) DO NOTHING
+        RETURNING id
+        "#
+    )
+    .bind(ad_id)
+    .bind(impression_id)
+    .bind(user_id)
+    .bind(&event)
+    .bind(input.value.and_then(BigDecimal::from_f64))
+    .bind(&currency)
+    .bind(order_id)
+    .bind(&attribution)
+    .bind(source)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(ConversionResponse {
+        attributed: true,
+        attribution: Some(attribution),
+        duplicate: conversion_id.is_none(),
+        conversion_id,
+    })
+}
+
+// Report a conversion from the advertiser's server
+#[utoipa::path(
+    post,
+    path = "/api/v1/ads/{ad_id}/conversion",
+    tag = "ads",
+    params(("ad_id" = Uuid, Path, description = "Ad ID")),
+    request_body = ConversionInput,
+    responses(
+        (status = 200, body = ConversionResponse),
+        (status = 400, description = "Invalid conversion")
+    )
+)]
+pub async fn record_conversion(
+    State(state): State<Arc<AppState>>,
+    Path(ad_id): Path<Uuid>,
+    Json(input): Json<ConversionInput>,
+) -> Result<Json<ConversionResponse>, (StatusCode, String)> {
+    record(&state.pool, ad_id, &input, "api").await.map(Json)
+}
+
+// Conversion pixel for landing pages: <img src=".../pixel.gif?click_id=...">.
+// Always answers with the image so a bad request never breaks the page.
+#[utoipa::path(
+    get,
+    path = "/api/v1/ads/{ad_id}/pixel.gif",
+    tag = "ads",
+    params(("ad_id" = Uuid, Path, description = "Ad ID"), ConversionInput),
+    responses((status = 200, description = "1x1 transparent GIF", content_type = "image/gif"))
+)]
+pub async fn conversion_pixel(
+    State(state): State<Arc<AppState>>,
+    Path(ad_id): Path<Uuid>,
+    Query(input): Query<ConversionInput>,
+) -> impl IntoResponse {
+    if let Err((_, e)) = record(&state.pool, ad_id, &input, "pixel").await {
+        eprintln!("Conversion pixel for ad {} ignored: {}", ad_id, e);
+    }
+
+    (
+        [(header::CONTENT_TYPE, "image/gif"), (header::CACHE_CONTROL, "no-store")],
+        PIXEL_GIF,
+    )
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct ConversionEventStats {
+    pub event: String,
+    pub conversions: i64,
+    pub click_through: i64,
+    pub view_through: i64,
+    /// Sum of reported values, regardless of currency
+    pub total_value: f64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ConversionReport {
+    pub ad_id: Uuid,
+    pub attribution_window_hours: i32,
+    pub conversions: i64,
+    pub click_through: i64,
+    pub view_through: i64,
+    /// Click-through conversions per billable click
+    pub conversion_rate: f64,
+    pub total_value: f64,
+    pub by_event: Vec<ConversionEventStats>,
+}
+
+#[derive(sqlx::FromRow)]
+struct AdConversionSettings {
+    attribution_window_hours: i32,
+    billable_clicks: i32,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/ads/{ad_id}/analytics/conversions",
+    tag = "ads",
+    params(("ad_id" = Uuid, Path, description = "Ad ID")),
+    responses(
+        (status = 200, body = ConversionReport),
+        (status = 404, description = "Ad not found"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_conversion_report(
+    State(state): State<Arc<AppState>>,
+    _admin: Permitted<CanManageAds>,
+    Path(ad_id): Path<Uuid>,
+) -> Result<Json<ConversionReport>, (StatusCode, String)> {
+    let ad = sqlx::query_as::<_, AdConversionSettings>(
+        r#"
+        SELECT attribution_window_hours, GREATEST(click_count - fraudulent_clicks, 0) AS billable_clicks
+        FROM advertisements WHERE id = $1
+        "#
+    )
+    .bind(ad_id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Ad not found".to_string()))?;
+
+    let by_event = sqlx::query_as::<_, ConversionEventStats>(
+        r#"
+        SELECT event,
+               COUNT(*) AS conversions,
+               COUNT(*) FILTER (WHERE attribution = 'click') AS click_through,
+               COUNT(*) FILTER (WHERE attribution = 'view') AS view_through,
+               COALESCE(SUM(value), 0)::float8 AS total_value
+        FROM ad_conversions
+        WHERE ad_id = $1
+        GROUP BY event
+        ORDER BY conversions DESC
+        "#
+    )
+    .bind(ad_id)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let conversions = by_event.iter().map(|e| e.conversions).sum();
+    let click_through = by_event.iter().map(|e| e.click_through).sum();
+    let view_through = by_event.iter().map(|e| e.view_through).sum();
+    let total_value = by_event.iter().map(|e| e.total_value).sum();
+    let conversion_rate = if ad.billable_clicks > 0 {
+        (click_through as f64 / ad.billable_clicks as f64) * 100.0
+    } else {
+        0.0
+    };
+
+    Ok(Json(ConversionReport {
+        ad_id,
+        attribution_window_hours: ad.attribution_window_hours,
+        conversions,
+        click_through,
+        view_through,
+        conversion_rate,
+        total_value,
+        by_event,
+    }))
+}
@@ -0,0 +1,359 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::permissions::{CanManageAds, Permitted};
+use crate::AppState;
+
+// Click fraud detection. The detect_click_fraud job flags users whose click
+// rate across ads is implausible and IPs that many accounts click from; clicks
+// that are too fast or come from a flagged user or IP get a fraud_reason (see
+// ad_click_fraud_reason in migration 039) and are counted in
+// advertisements.fraudulent_clicks so billing can leave them out.
+
+// A user needs this many impressions in the window before their rate counts
+const USER_MIN_IMPRESSIONS: i64 = 20;
+// Share of impressions clicked above which a user is flagged
+const USER_MAX_CLICK_RATE: f64 = 0.5;
+const USER_WINDOW: &str = "7 days";
+// Distinct accounts clicking from one IP in the window before it is flagged
+const IP_MAX_USERS: i64 = 5;
+const IP_WINDOW: &str = "24 hours";
+// Clicks older than this are never re-judged
+const DISCOUNT_WINDOW: &str = "7 days";
+
+/// Client IP as reported by Cloudflare, else the first X-Forwarded-For hop
+pub fn client_ip(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("CF-Connecting-IP")
+        .and_then(|v| v.to_str().ok())
+        .or_else(|| {
+            headers
+                .get("X-Forwarded-For")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.split(',').next())
+        })
+        .map(|ip| ip.trim().to_string())
+        .filter(|ip| ip.parse::<std::net::IpAddr>().is_ok())
+}
+
+/// Mark fraudulent clicks that are not marked yet and add them to their
+/// campaigns' fraudulent_clicks. Narrow to one impression with `ad_id` and
+/// `user_id`. Returns the number of clicks discounted.
+pub async fn discount_clicks(pool: &PgPool, ad_id: Option<Uuid>, user_id: Option<Uuid>) -> Result<i64, sqlx::Error> {
+    let discounted = sqlx::query_scalar::<_, i32>(&format!(
+        r#"
+        WITH flagged AS (
+            UPDATE ad_impressions
+            SET fraud_reason = ad_click_fraud_reason(user_id, click_ip, shown_at, clicked_at)
+            WHERE clicked
+              AND fraud_reason IS NULL
+              AND clicked_at > NOW() - INTERVAL '{}'
+              AND ($1::uuid IS NULL OR ad_id = $1)
+              AND ($2::uuid IS NULL OR user_id = $2)
+              AND ad_click_fraud_reason(user_id, click_ip, shown_at, clicked_at) IS NOT NULL
+            RETURNING ad_id
+        ),
+        counted AS (
+            SELECT ad_id, COUNT(*)::int AS n FROM flagged GROUP BY ad_id
+        )
+        UPDATE advertisements a
+        SET fraudulent_clicks = a.fraudulent_clicks + c.n
+        FROM counted c
+        WHERE a.id = c.ad_id
+        RETURNING c.n
+        "#,
+        DISCOUNT_WINDOW
+    ))
+    .bind(ad_id)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(discounted.into_iter().map(i64::from).sum())
+}
+
+/// Job body: raise flags for new suspects, then discount their clicks
+pub async fn detect(pool: &PgPool) -> Result<Option<serde_json::Value>, String> {
+    let flagged_users = sqlx::query_scalar::<_, Uuid>(&format!(
+        r#"
+        INSERT INTO ad_fraud_flags (subject_type, subject, reason, details)
+        SELECT 'user', user_id::text, 'user_click_rate',
+               jsonb_build_object('impressions', COUNT(*), 'clicks', COUNT(*) FILTER (WHERE clicked))
+        FROM ad_impressions
+        WHERE shown_at > NOW() - INTERVAL '{}'
+        GROUP BY user_id
+        HAVING COUNT(*) >= $1
+           AND (COUNT(*) FILTER (WHERE clicked))::float8 / COUNT(*) > $2
+        ON CONFLICT (subject_type, subject) WHERE cleared_at IS NULL DO NOTHING
+        RETURNING id
+        "#,
+        USER_WINDOW
+    ))
+    .bind(USER_MIN_IMPRESSIONS)
+    .bind(USER_MAX_CLICK_RATE)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .len();
+
+    let flagged_ips = sqlx::query_scalar::<_, Uuid>(&format!(
+        r#"
+        INSERT INTO ad_fraud_flags (subject_type, subject, reason, details)
+        SELECT 'ip', click_ip, 'shared_ip',
+               jsonb_build_object('users', COUNT(DISTINCT user_id), 'clicks', COUNT(*))
+        FROM ad_impressions
+        WHERE clicked AND click_ip IS NOT NULL AND clicked_at > NOW() - INTERVAL '{}'
+        GROUP BY click_ip
+        HAVING COUNT(DISTINCT user_id) >= $1
+        ON CONFLICT (subject_type, subject) WHERE cleared_at IS NULL DO NOTHING
+        RETURNING id
+        "#,
+        IP_WINDOW
+    ))
+    .bind(IP_MAX_USERS)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .len();
+
+    let discounted = discount_clicks(pool, None, None).await.map_err(|e| e.to_string())?;
+    if flagged_users + flagged_ips > 0 || discounted > 0 {
+        tracing::warn!(flagged_users, flagged_ips, discounted, "Ad click fraud detected");
+    }
+
+    Ok(Some(serde_json::json!({
+        "flagged_users": flagged_users,
+        "flagged_ips": flagged_ips,
+        "clicks_discounted": discounted,
+    })))
+}
+
+// ============================================================================
+// ADMIN ENDPOINTS
+// ============================================================================
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct FraudReasonCount {
+    /// instant_click, flagged_user or flagged_ip
+    pub reason: String,
+    pub clicks: i64,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct FraudSuspect {
+    pub user_id: Uuid,
+    pub username: Option<String>,
+    pub click_ip: Option<String>,
+    pub reason: String,
+    #[serde(with = "crate::timestamps::option")]
+    pub clicked_at: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AdFraudReport {
+    pub ad_id: Uuid,
+    pub impressions: i32,
+    pub clicks: i32,
+    pub fraudulent_clicks: i32,
+    /// Clicks that count for billing
+    pub billable_clicks: i32,
+    pub billable_ctr: f64,
+    pub by_reason: Vec<FraudReasonCount>,
+    /// Most recent discounted clicks
+    pub recent: Vec<FraudSuspect>,
+}
+
+#[derive(sqlx::FromRow)]
+struct AdClickTotals {
+    current_impressions: i32,
+    click_count: i32,
+    fraudulent_clicks: i32,
+}
+
+// Fraudulent clicks on one campaign and why they were discounted
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/ads/{ad_id}/analytics/fraud",
+    tag = "ads",
+    params(("ad_id" = Uuid, Path, description = "Ad ID")),
+    responses(
+        (status = 200, body = AdFraudReport),
+        (status = 404, description = "Ad not found"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_ad_fraud_report(
+    State(state): State<Arc<AppState>>,
+    _admin: Permitted<CanManageAds>,
+    Path(ad_id): Path<Uuid>,
+) -> Result<Json<AdFraudReport>, (StatusCode, String)> {
+    let totals = sqlx::query_as::<_, AdClickTotals>(
+        "SELECT current_impressions, click_count, fraudulent_clicks FROM advertisements WHERE id = $1"
+    )
+    .bind(ad_id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Ad not found".to_string()))?;
+
+    let by_reason = sqlx::query_as::<_, FraudReasonCount>(
+        r#"
+        SELECT fraud_reason AS reason, COUNT(*) AS clicks
+        FROM ad_impressions
+        WHERE ad_id = $1 AND fraud_reason IS NOT NULL
+        GROUP BY fraud_reason
+        ORDER BY clicks DESC
+        "#
+    )
+    .bind(ad_id)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let recent = sqlx::query_as::<_, FraudSuspect>(
+        r#"
+        SELECT ai.user_id, u.username, ai.click_ip, ai.fraud_reason AS reason, ai.clicked_at
+        FROM ad_impressions ai
+        LEFT JOIN users u ON u.id = ai.user_id
+        WHERE ai.ad_id = $1 AND ai.fraud_reason IS NOT NULL
+        ORDER BY ai.clicked_at DESC NULLS LAST
+        LIMIT 50
+        "#
+    )
+    .bind(ad_id)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let billable_clicks = (totals.click_count - totals.fraudulent_clicks).max(0);
+    let billable_ctr = if totals.current_impressions > 0 {
+        (billable_clicks as f64 / totals.current_impressions as f64) * 100.0
+    } else {
+        0.0
+    };
+
+    Ok(Json(AdFraudReport {
+        ad_id,
+        impressions: totals.current_impressions,
+        clicks: totals.click_count,
+        fraudulent_clicks: totals.fraudulent_clicks,
+        billable_clicks,
+        billable_ctr,
+        by_reason,
+        recent,
+    }))
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct FraudFlag {
+    pub id: Uuid,
+    /// user or ip
+    pub subject_type: String,
+    pub subject: String,
+    pub reason: String,
+    pub details: serde_json::Value,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps::option")]
+    pub cleared_at: Option<NaiveDateTime>,
+    pub cleared_by: Option<Uuid>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FraudFlagsQuery {
+    /// Include cleared flags (default false)
+    pub include_cleared: Option<bool>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/ads/fraud/flags",
+    tag = "ads",
+    params(FraudFlagsQuery),
+    responses((status = 200, body = [FraudFlag]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_fraud_flags(
+    State(state): State<Arc<AppState>>,
+    _admin: Permitted<CanManageAds>,
+    Query(params): Query<FraudFlagsQuery>,
+) -> Result<Json<Vec<FraudFlag>>, StatusCode> {
+    let page = params.page.unwrap_or(1).max(1);
+    let per_page = params.per_page.unwrap_or(50).clamp(1, 100);
+
+    let flags = sqlx::query_as::<_, FraudFlag>(
+        r#"
+        SELECT * FROM ad_fraud_flags
+        WHERE $1 OR cleared_at IS NULL
+        ORDER BY created_at DESC
+        LIMIT $2 OFFSET $3
+        "#
+    )
+    .bind(params.include_cleared.unwrap_or(false))
+    .bind(per_page)
+    .bind((page - 1) * per_page)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(flags))
+}
+
+// Clear a false positive; clicks already discounted stay discounted
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/ads/fraud/flags/{flag_id}/clear",
+    tag = "ads",
+    params(("flag_id" = Uuid, Path, description = "Flag ID")),
+    responses(
+        (status = 200, body = FraudFlag),
+        (status = 404, description = "Flag not found or already cleared"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn clear_fraud_flag(
+    State(state): State<Arc<AppState>>,
+    admin: Permitted<CanManageAds>,
+    Path(flag_id): Path<Uuid>,
+) -> Result<Json<FraudFlag>, (StatusCode, String)> {
+    let flag = sqlx::query_as::<_, FraudFlag>(
+        r#"
+        UPDATE ad_fraud_flags SET cleared_at = NOW(), cleared_by = $2
+        WHERE id = $1 AND cleared_at IS NULL
+        RETURNING *
+        "#
+    )
+    .bind(flag_id)
+    .bind(admin.0.id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Flag not found or already cleared".to_string()))?;
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "clear_fraud_flag".to_string(),
+        None,
+        Some("ad_fraud_flag".to_string()),
+        Some(flag.id),
+        serde_json::json!({ "subject_type": flag.subject_type, "subject": flag.subject }),
+    )
+    .await;
+
+    Ok(Json(flag))
+}
@@ -0,0 +1,651 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::i18n::Message;
+use crate::permissions::{role_has, CanManageAds, Permission, Permitted};
+use crate::runtime_settings;
+use crate::AppState;
+
+// Ad review (migration 070). When a paid ad reaches pending_approval its
+// creative is snapshotted as the ad's next revision in ad_revisions, and the
+// reviewer's decision is stored on that revision along with internal notes
+// and, for rejections, the reason the advertiser is notified and emailed.
+// A rejected ad can be edited and resubmitted as a new revision; the queue
+// shows reviewers what changed since the last rejection. Time from submission
+// to decision, against the ads.review_sla_hours runtime setting, gives the
+// SLA metrics.
+
+const DEFAULT_QUEUE_LIMIT: i64 = 50;
+const MAX_QUEUE_LIMIT: i64 = 200;
+const MAX_REASON_CHARS: usize = 1000;
+const MAX_NOTES_CHARS: usize = 4000;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdCreative {
+    pub title: String,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+    pub link_url: Option<String>,
+    pub is_mature: bool,
+}
+
+/// A creative field that differs from the previous revision
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreativeChange {
+    pub field: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// The decision on the revision before the one waiting
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PreviousReview {
+    pub revision: i32,
+    pub rejection_reason: Option<String>,
+    pub notes: Option<String>,
+    pub reviewer_username: Option<String>,
+    #[serde(with = "crate::timestamps")]
+    pub decided_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReviewQueueItem {
+    pub ad_id: Uuid,
+    pub revision: i32,
+    pub creative: AdCreative,
+    pub target_countries: Vec<String>,
+    pub target_languages: Vec<String>,
+    pub billing_model: String,
+    pub package_type: Option<String>,
+    pub price: Option<f64>,
+    pub advertiser_id: Option<Uuid>,
+    pub advertiser_username: Option<String>,
+    pub contact_email: Option<String>,
+    #[serde(with = "crate::timestamps")]
+    pub submitted_at: NaiveDateTime,
+    pub waiting_seconds: i64,
+    /// Waiting longer than the review SLA
+    pub overdue: bool,
+    pub previous: Option<PreviousReview>,
+    /// Empty for a first submission
+    pub changes: Vec<CreativeChange>,
+}
+
+#[derive(sqlx::FromRow)]
+struct QueueRow {
+    ad_id: Uuid,
+    revision: i32,
+    title: String,
+    description: Option<String>,
+    image_url: Option<String>,
+    link_url: Option<String>,
+    is_mature: bool,
+    target_countries: Vec<String>,
+    target_languages: Vec<String>,
+    billing_model: String,
+    package_type: Option<String>,
+    price: Option<f64>,
+    advertiser_id: Option<Uuid>,
+    advertiser_username: Option<String>,
+    contact_email: Option<String>,
+    submitted_at: NaiveDateTime,
+    waiting_seconds: i64,
+    prev_title: Option<String>,
+    prev_description: Option<String>,
+    prev_image_url: Option<String>,
+    prev_link_url: Option<String>,
+    prev_is_mature: Option<bool>,
+    prev_rejection_reason: Option<String>,
+    prev_notes: Option<String>,
+    prev_reviewer_username: Option<String>,
+    prev_decided_at: Option<NaiveDateTime>,
+}
+
+impl QueueRow {
+    /// The creative fields that differ from the previous revision
+    fn changes(&self) -> Vec<CreativeChange> {
+        if self.prev_decided_at.is_none() {
+            return Vec::new();
+        }
+        [
+            ("title", self.prev_title.clone(), Some(self.title.clone())),
+            ("description", self.prev_description.clone(), self.description.clone()),
+            ("image_url", self.prev_image_url.clone(), self.image_url.clone()),
+            ("link_url", self.prev_link_url.clone(), self.link_url.clone()),
+            ("is_mature", self.prev_is_mature.map(|b| b.to_string()), Some(self.is_mature.to_string())),
+        ]
+        .into_iter()
+        .filter(|(_, before, after)| before != after)
+        .map(|(field, before, after)| CreativeChange { field: field.to_string(), before, after })
+        .collect()
+    }
+
+    fn into_item(self, sla_seconds: i64) -> ReviewQueueItem {
+        let changes = self.changes();
+        let previous = self.prev_decided_at.map(|decided_at| PreviousReview {
+            revision: self.revision - 1,
+            rejection_reason: self.prev_rejection_reason,
+            notes: self.prev_notes,
+            reviewer_username: self.prev_reviewer_username,
+            decided_at,
+        });
+        ReviewQueueItem {
+            ad_id: self.ad_id,
+            revision: self.revision,
+            creative: AdCreative {
+                title: self.title,
+                description: self.description,
+                image_url: self.image_url,
+                link_url: self.link_url,
+                is_mature: self.is_mature,
+            },
+            target_countries: self.target_countries,
+            target_languages: self.target_languages,
+            billing_model: self.billing_model,
+            package_type: self.package_type,
+            price: self.price,
+            advertiser_id: self.advertiser_id,
+            advertiser_username: self.advertiser_username,
+            contact_email: self.contact_email,
+            submitted_at: self.submitted_at,
+            waiting_seconds: self.waiting_seconds,
+            overdue: self.waiting_seconds > sla_seconds,
+            previous,
+            changes,
+        }
+    }
+}
+
+/// One submission of an ad's creative and what the review decided
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct AdRevision {
+    pub ad_id: Uuid,
+    pub revision: i32,
+    pub title: String,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+    pub link_url: Option<String>,
+    pub is_mature: bool,
+    #[serde(with = "crate::timestamps")]
+    pub submitted_at: NaiveDateTime,
+    /// approved or rejected; null while waiting for review
+    pub decision: Option<String>,
+    pub rejection_reason: Option<String>,
+    #[serde(with = "crate::timestamps::option")]
+    pub decided_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ApproveAdRequest {
+    /// Internal notes for other reviewers
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RejectAdRequest {
+    /// Why the ad was rejected; sent to the advertiser
+    pub reason: String,
+    /// Internal notes for other reviewers
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ResubmitAdRequest {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+    pub link_url: Option<String>,
+    pub is_mature: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReviewQueueQuery {
+    /// Ads to return, oldest submission first (default 50, max 200)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReviewMetricsQuery {
+    /// Days of decisions to measure (default 30, max 365)
+    pub days: Option<i32>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReviewMetrics {
+    pub sla_hours: i64,
+    pub pending: i64,
+    /// Pending for longer than the SLA
+    pub overdue: i64,
+    pub oldest_pending_seconds: Option<i64>,
+    pub days: i32,
+    pub approved: i64,
+    pub rejected: i64,
+    /// Decisions on a revision after the first
+    pub resubmissions: i64,
+    pub median_decision_seconds: Option<f64>,
+    pub p90_decision_seconds: Option<f64>,
+    /// Share of decisions made within the SLA, 0-100
+    pub within_sla_percentage: Option<f64>,
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    eprintln!("❌ Ad review query failed: {:?}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+}
+
+fn sla_hours() -> i64 {
+    runtime_settings::int(runtime_settings::AD_REVIEW_SLA_HOURS)
+}
+
+/// Snapshot an ad that reached pending_approval as its next revision. Does
+/// nothing while its current revision is still waiting, so repeated payment
+/// confirmations submit it once.
+pub async fn record_submission(pool: &PgPool, ad_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        WITH submitted AS (
+            UPDATE advertisements a
+            SET revision = a.revision + 1
+            WHERE a.id = $1
+              AND a.status = 'pending_approval'
+              AND NOT EXISTS (
+                  SELECT 1 FROM ad_revisions r
+                  WHERE r.ad_id = a.id AND r.revision = a.revision AND r.decided_at IS NULL
+              )
+            RETURNING a.id, a.revision, a.title, a.description, a.image_url, a.link_url, a.is_mature
+        )
+        INSERT INTO ad_revisions (ad_id, revision, title, description, image_url, link_url, is_mature)
+        SELECT id, revision, title, description, image_url, link_url, is_mature FROM submitted
+        "#
+    )
+    .bind(ad_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Tell the advertiser how the review went, in the app and by email
+async fn notify_advertiser(pool: &PgPool, ad_id: Uuid, message: Message, template: &str) {
+    let advertiser = sqlx::query_as::<_, (Option<Uuid>, String, Option<String>)>(
+        r#"
+        SELECT a.created_by, a.title, COALESCE(a.contact_email, u.email)
+        FROM advertisements a
+        LEFT JOIN users u ON u.id = a.created_by
+        WHERE a.id = $1
+        "#
+    )
+    .bind(ad_id)
+    .fetch_optional(pool)
+    .await;
+    let (user_id, title, email) = match advertiser {
+        Ok(Some(advertiser)) => advertiser,
+        Ok(None) => return,
+        Err(e) => {
+            eprintln!("❌ Failed to look up advertiser of ad {}: {:?}", ad_id, e);
+            return;
+        }
+    };
+    let message = message.arg("title", &title);
+
+    if let Some(user_id) = user_id {
+        if let Err(e) = sqlx::query(
+            "INSERT INTO notifications (user_id, type, message, message_key, message_args) VALUES ($1, 'ad_review', $2, $3, $4)"
+        )
+        .bind(user_id)
+        .bind(message.text())
+        .bind(message.key)
+        .bind(&message.args)
+        .execute(pool)
+        .await
+        {
+            eprintln!("❌ Failed to notify advertiser of ad {}: {:?}", ad_id, e);
+        }
+    }
+
+    // Placeholder addresses of phone and bot accounts can't receive mail
+    let Some(email) = email.filter(|email| !email.ends_with(".invalid")) else {
+        return;
+    };
+    if !crate::mailer::is_configured() {
+        return;
+    }
+    if let Err(e) = crate::mailer::queue(pool, &[email], template, &message.args).await {
+        eprintln!("❌ Failed to email advertiser of ad {}: {}", ad_id, e);
+    }
+}
+
+fn check_length(field: &str, value: Option<&str>, max: usize) -> Result<(), (StatusCode, String)> {
+    if value.is_some_and(|value| value.chars().count() > max) {
+        return Err((StatusCode::BAD_REQUEST, format!("{} must be at most {} characters", field, max)));
+    }
+    Ok(())
+}
+
+/// Record the decision on the revision of `ad_id` waiting for review and
+/// activate or reject the ad
+pub async fn decide(
+    state: &Arc<AppState>,
+    admin: &AuthUser,
+    ad_id: Uuid,
+    approve: bool,
+    reason: Option<&str>,
+    notes: Option<&str>,
+) -> Result<AdRevision, (StatusCode, String)> {
+    check_length("reason", reason, MAX_REASON_CHARS)?;
+    check_length("notes", notes, MAX_NOTES_CHARS)?;
+    let notes = notes.map(str::trim).filter(|notes| !notes.is_empty());
+
+    // Ads that reached review before they were snapshotted get their revision now
+    record_submission(&state.pool, ad_id).await.map_err(db_error)?;
+
+    let mut tx = state.pool.begin().await.map_err(db_error)?;
+    let revision: i32 = sqlx::query_scalar(
+        r#"
+        UPDATE advertisements
+        SET status = CASE WHEN $2 THEN 'active' ELSE 'rejected' END,
+            start_date = CASE WHEN $2 THEN NOW() ELSE start_date END
+        WHERE id = $1 AND status = 'pending_approval'
+        RETURNING revision
+        "#
+    )
+    .bind(ad_id)
+    .bind(approve)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::CONFLICT, "This ad isn't waiting for review".to_string()))?;
+
+    let decided = sqlx::query_as::<_, AdRevision>(
+        r#"
+        UPDATE ad_revisions
+        SET decision = CASE WHEN $3 THEN 'approved' ELSE 'rejected' END,
+            reviewer_id = $4, rejection_reason = $5, notes = $6, decided_at = NOW()
+        WHERE ad_id = $1 AND revision = $2
+        RETURNING ad_id, revision, title, description, image_url, link_url, is_mature,
+                  submitted_at, decision, rejection_reason, decided_at
+        "#
+    )
+    .bind(ad_id)
+    .bind(revision)
+    .bind(approve)
+    .bind(admin.id)
+    .bind(reason)
+    .bind(notes)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+
+    crate::admin::log_admin_action(
+        state,
+        admin.id,
+        if approve { "approve_ad" } else { "reject_ad" }.to_string(),
+        None,
+        Some("advertisement".to_string()),
+        Some(ad_id),
+        serde_json::json!({ "revision": revision, "reason": reason, "notes": notes }),
+    )
+    .await;
+
+    if approve {
+        notify_advertiser(&state.pool, ad_id, Message::new("ad_approved"), "ad_approved").await;
+    } else {
+        let message = Message::new("ad_rejected").arg("reason", reason.unwrap_or_default());
+        notify_advertiser(&state.pool, ad_id, message, "ad_rejected").await;
+    }
+
+    Ok(decided)
+}
+
+// Paid ads waiting for review, oldest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/ads/review-queue",
+    tag = "ads",
+    params(ReviewQueueQuery),
+    responses((status = 200, body = [ReviewQueueItem]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_review_queue(
+    _admin: Permitted<CanManageAds>,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ReviewQueueQuery>,
+) -> Result<Json<Vec<ReviewQueueItem>>, (StatusCode, String)> {
+    let limit = params.limit.unwrap_or(DEFAULT_QUEUE_LIMIT).clamp(1, MAX_QUEUE_LIMIT);
+    let rows = sqlx::query_as::<_, QueueRow>(
+        r#"
+        SELECT a.id AS ad_id, r.revision, r.title, r.description, r.image_url, r.link_url, r.is_mature,
+               a.target_countries, a.target_languages, a.billing_model, a.package_type,
+               a.price::float8 AS price, a.created_by AS advertiser_id, u.username AS advertiser_username,
+               a.contact_email, r.submitted_at,
+               EXTRACT(EPOCH FROM NOW() - r.submitted_at)::bigint AS waiting_seconds,
+               p.title AS prev_title, p.description AS prev_description, p.image_url AS prev_image_url,
+               p.link_url AS prev_link_url, p.is_mature AS prev_is_mature,
+               p.rejection_reason AS prev_rejection_reason, p.notes AS prev_notes,
+               pr.username AS prev_reviewer_username, p.decided_at AS prev_decided_at
+        FROM advertisements a
+        JOIN ad_revisions r ON r.ad_id = a.id AND r.revision = a.revision
+        LEFT JOIN ad_revisions p ON p.ad_id = a.id AND p.revision = a.revision - 1 AND p.decided_at IS NOT NULL
+        LEFT JOIN users u ON u.id = a.created_by
+        LEFT JOIN users pr ON pr.id = p.reviewer_id
+        WHERE a.status = 'pending_approval' AND r.decided_at IS NULL
+        ORDER BY r.submitted_at ASC
+        LIMIT $1
+        "#
+    )
+    .bind(limit)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    let sla_seconds = sla_hours() * 3600;
+    Ok(Json(rows.into_iter().map(|row| row.into_item(sla_seconds)).collect()))
+}
+
+// How quickly ads are reviewed against the SLA
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/ads/review-metrics",
+    tag = "ads",
+    params(ReviewMetricsQuery),
+    responses((status = 200, body = ReviewMetrics), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_review_metrics(
+    _admin: Permitted<CanManageAds>,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ReviewMetricsQuery>,
+) -> Result<Json<ReviewMetrics>, (StatusCode, String)> {
+    let days = params.days.unwrap_or(30).clamp(1, 365);
+    let sla_hours = sla_hours();
+
+    let (pending, overdue, oldest_pending_seconds) = sqlx::query_as::<_, (i64, i64, Option<i64>)>(
+        r#"
+        SELECT COUNT(*),
+               COUNT(*) FILTER (WHERE r.submitted_at < NOW() - make_interval(hours => $1)),
+               EXTRACT(EPOCH FROM NOW() - MIN(r.submitted_at))::bigint
+        FROM advertisements a
+        JOIN ad_revisions r ON r.ad_id = a.id AND r.revision = a.revision
+        WHERE a.status = 'pending_approval' AND r.decided_at IS NULL
+        "#
+    )
+    .bind(sla_hours as i32)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    let (approved, rejected, resubmissions, median, p90, within_sla) =
+        sqlx::query_as::<_, (i64, i64, i64, Option<f64>, Option<f64>, Option<f64>)>(
+            r#"
+            SELECT COUNT(*) FILTER (WHERE decision = 'approved'),
+                   COUNT(*) FILTER (WHERE decision = 'rejected'),
+                   COUNT(*) FILTER (WHERE revision > 1),
+                   percentile_cont(0.5) WITHIN GROUP (ORDER BY EXTRACT(EPOCH FROM decided_at - submitted_at)::float8),
+                   percentile_cont(0.9) WITHIN GROUP (ORDER BY EXTRACT(EPOCH FROM decided_at - submitted_at)::float8),
+                   (100.0 * COUNT(*) FILTER (WHERE decided_at - submitted_at <= make_interval(hours => $2))
+                       / NULLIF(COUNT(*), 0))::float8
+            FROM ad_revisions
+            WHERE decided_at > NOW() - make_interval(days => $1)
+            "#
+        )
+        .bind(days)
+        .bind(sla_hours as i32)
+        .fetch_one(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(ReviewMetrics {
+        sla_hours,
+        pending,
+        overdue,
+        oldest_pending_seconds,
+        days,
+        approved,
+        rejected,
+        resubmissions,
+        median_decision_seconds: median,
+        p90_decision_seconds: p90,
+        within_sla_percentage: within_sla,
+    }))
+}
+
+/// The ad, if `user` created it
+async fn own_ad(pool: &PgPool, ad_id: Uuid, user: &AuthUser) -> Result<String, (StatusCode, String)> {
+    let (created_by, status) = sqlx::query_as::<_, (Option<Uuid>, String)>(
+        "SELECT created_by, status FROM advertisements WHERE id = $1"
+    )
+    .bind(ad_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, "Ad not found".to_string()))?;
+    if created_by != Some(user.id) && !role_has(pool, &user.role, CanManageAds::NAME).await {
+        return Err((StatusCode::FORBIDDEN, "Not your campaign".to_string()));
+    }
+    Ok(status)
+}
+
+// Review history of a campaign, newest revision first
+#[utoipa::path(
+    get,
+    path = "/api/v1/ads/{ad_id}/reviews",
+    tag = "ads",
+    params(("ad_id" = Uuid, Path, description = "Ad ID")),
+    responses(
+        (status = 200, body = [AdRevision]),
+        (status = 403, description = "Not your campaign"),
+        (status = 404, description = "Ad not found"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_revisions(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(ad_id): Path<Uuid>,
+) -> Result<Json<Vec<AdRevision>>, (StatusCode, String)> {
+    own_ad(&state.pool, ad_id, &user).await?;
+
+    let revisions = sqlx::query_as::<_, AdRevision>(
+        r#"
+        SELECT ad_id, revision, title, description, image_url, link_url, is_mature,
+               submitted_at, decision, rejection_reason, decided_at
+        FROM ad_revisions
+        WHERE ad_id = $1
+        ORDER BY revision DESC
+        "#
+    )
+    .bind(ad_id)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(revisions))
+}
+
+// Edit a rejected campaign and send it back for review
+#[utoipa::path(
+    post,
+    path = "/api/v1/ads/{ad_id}/resubmit",
+    tag = "ads",
+    params(("ad_id" = Uuid, Path, description = "Ad ID")),
+    request_body = ResubmitAdRequest,
+    responses(
+        (status = 200, body = AdRevision),
+        (status = 403, description = "Not your campaign"),
+        (status = 404, description = "Ad not found"),
+        (status = 409, description = "Only rejected ads can be resubmitted"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn resubmit_ad(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(ad_id): Path<Uuid>,
+    Json(input): Json<ResubmitAdRequest>,
+) -> Result<Json<AdRevision>, (StatusCode, String)> {
+    let status = own_ad(&state.pool, ad_id, &user).await?;
+    if status != "rejected" {
+        return Err((StatusCode::CONFLICT, "Only rejected ads can be resubmitted".to_string()));
+    }
+    let title = input.title.as_deref().map(str::trim);
+    if title.is_some_and(|title| title.is_empty()) {
+        return Err((StatusCode::BAD_REQUEST, "Title can't be empty".to_string()));
+    }
+
+    let resubmitted = sqlx::query(
+        r#"
+        UPDATE advertisements
+        SET title = COALESCE($2, title),
+            description = COALESCE($3, description),
+            image_url = COALESCE($4, image_url),
+            link_url = COALESCE($5, link_url),
+            is_mature = COALESCE($6, is_mature),
+            status = 'pending_approval',
+            updated_at = NOW()
+        WHERE id = $1 AND status = 'rejected'
+        "#
+    )
+    .bind(ad_id)
+    .bind(title)
+    .bind(&input.description)
+    .bind(&input.image_url)
+    .bind(&input.link_url)
+    .bind(input.is_mature)
+    .execute(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+    if resubmitted.rows_affected() == 0 {
+        return Err((StatusCode::CONFLICT, "Only rejected ads can be resubmitted".to_string()));
+    }
+    record_submission(&state.pool, ad_id).await.map_err(db_error)?;
+
+    let revision = sqlx::query_as::<_, AdRevision>(
+        r#"
+        SELECT r.ad_id, r.revision, r.title, r.description, r.image_url, r.link_url, r.is_mature,
+               r.submitted_at, r.decision, r.rejection_reason, r.decided_at
+        FROM ad_revisions r
+        JOIN advertisements a ON a.id = r.ad_id AND a.revision = r.revision
+        WHERE r.ad_id = $1
+        "#
+    )
+    .bind(ad_id)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(revision))
+}
@@ -0,0 +1,94 @@
+use axum::http::HeaderMap;
+use uuid::Uuid;
+
+// Serve-time geo targeting for ads. Campaigns may list countries and
+// languages (see ad_geo_match in migration 038); the request's location and
+// language decide which of them are eligible, and every serving decision is
+// logged so advertisers' reach questions can be answered.
+
+const MAX_TARGETS: usize = 50;
+
+/// Where a request comes from, as far as ad targeting is concerned
+#[derive(Debug)]
+pub struct ServeContext {
+    /// ISO 3166-1 alpha-2
+    pub country: Option<String>,
+    /// ISO 639-1 code, lowercase
+    pub language: Option<String>,
+    /// cf-ipcountry, accept-language or unknown
+    pub country_source: &'static str,
+}
+
+impl ServeContext {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let preferred = headers
+            .get(axum::http::header::ACCEPT_LANGUAGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(preferred_language);
+        let language = preferred.as_ref().map(|(lang, _)| lang.clone());
+
+        if let Some(country) = crate::takedowns::request_country(headers) {
+            return ServeContext { country: Some(country), language, country_source: "cf-ipcountry" };
+        }
+        match preferred.and_then(|(_, region)| region) {
+            Some(region) => ServeContext { country: Some(region), language, country_source: "accept-language" },
+            None => ServeContext { country: None, language, country_source: "unknown" },
+        }
+    }
+}
+
+/// First language of an Accept-Language header, split into language and
+/// optional region ("en-US;q=0.9" -> ("en", Some("US")))
+fn preferred_language(header: &str) -> Option<(String, Option<String>)> {
+    let tag = header.split(',').next()?.split(';').next()?.trim();
+    let mut parts = tag.split(['-', '_']);
+    let language = parts.next()?.to_lowercase();
+    if !is_language_code(&language) {
+        return None;
+    }
+    let region = parts
+        .next()
+        .map(|r| r.to_uppercase())
+        .filter(|r| r.len() == 2 && r.chars().all(|c| c.is_ascii_uppercase()));
+    Some((language, region))
+}
+
+fn is_language_code(code: &str) -> bool {
+    (2..=3).contains(&code.len()) && code.chars().all(|c| c.is_ascii_lowercase())
+}
+
+/// Normalize and validate campaign targets: uppercase ISO 3166-1 alpha-2
+/// countries, lowercase ISO 639 languages, deduplicated
+pub fn normalize_targets(countries: &[String], languages: &[String]) -> Result<(Vec<String>, Vec<String>), String> {
+    let mut countries: Vec<String> = countries.iter().map(|c| c.trim().to_uppercase()).collect();
+    countries.sort();
+    countries.dedup();
+    if !countries.iter().all(|c| c.len() == 2 && c.chars().all(|ch| ch.is_ascii_uppercase())) {
+        return Err("target_countries must be ISO 3166-1 alpha-2 codes".to_string());
+    }
+
+    let mut languages: Vec<String> = languages.iter().map(|l| l.trim().to_lowercase()).collect();
+    languages.sort();
+    languages.dedup();
+    if !languages.iter().all(|l| is_language_code(l)) {
+        return Err("target_languages must be ISO 639 language codes".to_string());
+    }
+
+    if countries.len() > MAX_TARGETS || languages.len() > MAX_TARGETS {
+        return Err(format!("At most {} target countries and languages", MAX_TARGETS));
+    }
+    Ok((countries, languages))
+}
+
+/// Record which ads a placement served and the location they were chosen for
+pub fn log_decision(placement: &str, viewer_id: Uuid, ctx: &ServeContext, served: &[Uuid]) {
+    tracing::info!(
+        placement,
+        %viewer_id,
+        country = ctx.country.as_deref().unwrap_or(""),
+        country_source = ctx.country_source,
+        language = ctx.language.as_deref().unwrap_or(""),
+        served = ?served,
+        "Ad serving decision"
+    );
+}
@@ -4,11 +4,13 @@ use axum::{
     http::{StatusCode, header, request::Parts},
 };
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 use jsonwebtoken::{decode, DecodingKey, Validation};
 use std::sync::Arc;
 use chrono::{DateTime, Utc, NaiveDate};
 use bigdecimal::{BigDecimal, FromPrimitive};
+use crate::permissions::{role_has, CanBan, CanDeleteUsers, CanManageAds, CanManagePlatform, CanManageRoles, CanViewAnalytics, Permission, Permitted};
 
 // Claims structure for JWT
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -26,13 +28,22 @@ pub struct AuthUser {
     pub role: String,
 }
 
-// Admin user - requires admin role
+// Staff user - any role that grants at least one permission. Endpoints that
+// need a particular permission take crate::permissions::Permitted instead.
 #[derive(Debug, Clone)]
 pub struct AdminUser(pub AuthUser);
 
-// Extractor for authenticated users
+// Signed-in user who may be banned, for the few endpoints a banned user
+// still needs (appealing the ban). Everything else takes AuthUser.
+#[derive(Debug, Clone)]
+pub struct MaybeBannedUser {
+    pub user: AuthUser,
+    pub banned: bool,
+}
+
+// Extractor for signed-in users, banned or not
 #[async_trait]
-impl FromRequestParts<Arc<crate::AppState>> for AuthUser
+impl FromRequestParts<Arc<crate::AppState>> for MaybeBannedUser
 {
     type Rejection = (StatusCode, String);
 
@@ -81,17 +92,36 @@ impl FromRequestParts<Arc<crate::AppState>> for AuthUser
             (StatusCode::UNAUTHORIZED, "User not found".to_string())
         })?;
 
+        Ok(MaybeBannedUser {
+            user: AuthUser {
+                id: user.id,
+                username: user.username,
+                email: user.email,
+                role: user.role,
+            },
+            banned: user.is_banned,
+        })
+    }
+}
+
+// Extractor for authenticated users
+#[async_trait]
+impl FromRequestParts<Arc<crate::AppState>> for AuthUser
+{
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<crate::AppState>) -> Result<Self, Self::Rejection> {
+        let caller = MaybeBannedUser::from_request_parts(parts, state).await?;
+
         // Check if user is banned
-        if user.is_banned {
-            return Err((StatusCode::FORBIDDEN, "Your account has been banned".to_string()));
+        if caller.banned {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "Your account has been banned. You can appeal at /api/v1/appeals".to_string(),
+            ));
         }
 
-        Ok(AuthUser {
-            id: user.id,
-            username: user.username,
-            email: user.email,
-            role: user.role,
-        })
+        Ok(caller.user)
     }
 }
 
@@ -104,8 +134,7 @@ impl FromRequestParts<Arc<crate::AppState>> for AdminUser
     async fn from_request_parts(parts: &mut Parts, state: &Arc<crate::AppState>) -> Result<Self, Self::Rejection> {
         let user = AuthUser::from_request_parts(parts, state).await?;
 
-        // Check if user is admin or moderator
-        if user.role != "admin" && user.role != "moderator" {
+        if !crate::permissions::role_is_staff(&state.pool, &user.role).await {
             return Err((StatusCode::FORBIDDEN, "Admin access required".to_string()));
         }
 
@@ -113,12 +142,58 @@ impl FromRequestParts<Arc<crate::AppState>> for AdminUser
     }
 }
 
+// Caller for internal cron-style endpoints: either a scheduler presenting the
+// shared SERVICE_TOKEN in the X-Service-Token header, or a logged-in user
+// with can_manage_platform
+#[derive(Debug, Clone)]
+pub enum ServiceCaller {
+    Service,
+    Admin(AuthUser),
+}
+
+impl ServiceCaller {
+    pub fn user_id(&self) -> Option<Uuid> {
+        match self {
+            ServiceCaller::Service => None,
+            ServiceCaller::Admin(user) => Some(user.id),
+        }
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<crate::AppState>> for ServiceCaller
+{
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<crate::AppState>) -> Result<Self, Self::Rejection> {
+        if let Some(presented) = parts.headers.get("x-service-token").and_then(|h| h.to_str().ok()) {
+            let expected = std::env::var("SERVICE_TOKEN").unwrap_or_default();
+            if !expected.is_empty() && constant_time_eq(presented.as_bytes(), expected.as_bytes()) {
+                return Ok(ServiceCaller::Service);
+            }
+            return Err((StatusCode::UNAUTHORIZED, "Invalid service token".to_string()));
+        }
+
+        let user = Permitted::<CanManagePlatform>::from_request_parts(parts, state).await?.0;
+        Ok(ServiceCaller::Admin(user))
+    }
+}
+
+// Compare secrets without short-circuiting on the first mismatched byte
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 // ============================================================================
 // ADMIN API HANDLERS
 // ============================================================================
 
 // List all users with pagination and search
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct UserListQuery {
     page: Option<i64>,
     per_page: Option<i64>,
@@ -126,7 +201,7 @@ pub struct UserListQuery {
     role: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct UserInfo {
     id: Uuid,
     username: String,
@@ -136,12 +211,13 @@ pub struct UserInfo {
     follower_count: Option<i32>,
     following_count: Option<i32>,
     story_count: Option<i32>,
+    #[serde(with = "crate::timestamps::option")]
     created_at: Option<chrono::NaiveDateTime>,
     is_banned: bool,
     ban_reason: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct UserListResponse {
     users: Vec<UserInfo>,
     total: i64,
@@ -149,6 +225,14 @@ pub struct UserListResponse {
     per_page: i64,
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/users",
+    tag = "admin",
+    params(UserListQuery),
+    responses((status = 200, body = UserListResponse), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
 pub async fn list_users(
     admin: AdminUser,
     State(state): State<Arc<crate::AppState>>,
@@ -282,13 +366,22 @@ pub async fn list_users(
 }
 
 // Ban user
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct BanUserInput {
     reason: String,
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/users/{user_id}/ban",
+    tag = "admin",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    request_body = BanUserInput,
+    responses((status = 200, body = serde_json::Value), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
 pub async fn ban_user(
-    admin: AdminUser,
+    admin: Permitted<CanBan>,
     State(state): State<Arc<crate::AppState>>,
     Path(user_id): Path<Uuid>,
     Json(input): Json<BanUserInput>,
@@ -298,13 +391,15 @@ pub async fn ban_user(
         return Err((StatusCode::BAD_REQUEST, "Cannot ban yourself".to_string()));
     }
 
-    // Check if target user is admin (prevent banning other admins)
+    // Only those who can manage roles may ban someone who can
     let target_user = sqlx::query!("SELECT role FROM users WHERE id = $1", user_id)
         .fetch_one(state.pool.as_ref())
         .await
         .map_err(|_| (StatusCode::NOT_FOUND, "User not found".to_string()))?;
 
-    if target_user.role == "admin" && admin.0.role != "admin" {
+    if role_has(&state.pool, &target_user.role, CanManageRoles::NAME).await
+        && !role_has(&state.pool, &admin.0.role, CanManageRoles::NAME).await
+    {
         return Err((StatusCode::FORBIDDEN, "Cannot ban admin users".to_string()));
     }
 
@@ -344,8 +439,16 @@ pub async fn ban_user(
 }
 
 // Unban user
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/users/{user_id}/unban",
+    tag = "admin",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200, body = serde_json::Value), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
 pub async fn unban_user(
-    admin: AdminUser,
+    admin: Permitted<CanBan>,
     State(state): State<Arc<crate::AppState>>,
     Path(user_id): Path<Uuid>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
@@ -379,29 +482,41 @@ pub async fn unban_user(
 }
 
 // Change user role
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct ChangeRoleInput {
     role: String,
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/users/{user_id}/role",
+    tag = "admin",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    request_body = ChangeRoleInput,
+    responses((status = 200, body = serde_json::Value), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
 pub async fn change_user_role(
-    admin: AdminUser,
+    admin: Permitted<CanManageRoles>,
     State(state): State<Arc<crate::AppState>>,
     Path(user_id): Path<Uuid>,
     Json(input): Json<ChangeRoleInput>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    // Only admin can change roles
-    if admin.0.role != "admin" {
-        return Err((StatusCode::FORBIDDEN, "Only admins can change user roles".to_string()));
-    }
-
     // Validate role
-    if !["user", "admin", "moderator"].contains(&input.role.as_str()) {
+    let role_exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM roles WHERE name = $1)")
+        .bind(&input.role)
+        .fetch_one(state.pool.as_ref())
+        .await
+        .map_err(|e| {
+            eprintln!("Role lookup error: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to change role".to_string())
+        })?;
+    if !role_exists {
         return Err((StatusCode::BAD_REQUEST, "Invalid role".to_string()));
     }
 
-    // Prevent self-demotion
-    if admin.0.id == user_id && input.role != "admin" {
+    // Prevent locking yourself out of role management
+    if admin.0.id == user_id && !role_has(&state.pool, &input.role, CanManageRoles::NAME).await {
         return Err((StatusCode::BAD_REQUEST, "Cannot change your own role".to_string()));
     }
 
@@ -435,16 +550,19 @@ pub async fn change_user_role(
 }
 
 // Delete user (hard delete)
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/users/{user_id}",
+    tag = "admin",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200, body = serde_json::Value), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
 pub async fn delete_user(
-    admin: AdminUser,
+    admin: Permitted<CanDeleteUsers>,
     State(state): State<Arc<crate::AppState>>,
     Path(user_id): Path<Uuid>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    // Only admin can delete users
-    if admin.0.role != "admin" {
-        return Err((StatusCode::FORBIDDEN, "Only admins can delete users".to_string()));
-    }
-
     // Prevent self-deletion
     if admin.0.id == user_id {
         return Err((StatusCode::BAD_REQUEST, "Cannot delete yourself".to_string()));
@@ -476,7 +594,7 @@ pub async fn delete_user(
 }
 
 // Helper function to log admin actions
-async fn log_admin_action(
+pub(crate) async fn log_admin_action(
     state: &Arc<crate::AppState>,
     admin_id: Uuid,
     action: String,
@@ -500,14 +618,15 @@ async fn log_admin_action(
 }
 
 // Get admin logs
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct LogsQuery {
     page: Option<i64>,
     per_page: Option<i64>,
     action: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct AdminLogEntry {
     id: Uuid,
     admin_id: Option<Uuid>,
@@ -521,7 +640,7 @@ pub struct AdminLogEntry {
     created_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct LogsResponse {
     logs: Vec<AdminLogEntry>,
     total: i64,
@@ -529,8 +648,16 @@ pub struct LogsResponse {
     per_page: i64,
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/logs",
+    tag = "admin",
+    params(LogsQuery),
+    responses((status = 200, body = LogsResponse), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
 pub async fn get_admin_logs(
-    _admin: AdminUser,
+    _admin: Permitted<CanViewAnalytics>,
     State(state): State<Arc<crate::AppState>>,
     Query(params): Query<LogsQuery>,
 ) -> Result<Json<LogsResponse>, (StatusCode, String)> {
@@ -609,7 +736,7 @@ pub async fn get_admin_logs(
 // ANALYTICS HANDLERS
 // ============================================================================
 
-#[derive(Serialize)]
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
 pub struct AnalyticsSnapshot {
     date: NaiveDate,
     total_users: i32,
@@ -625,7 +752,182 @@ pub struct AnalyticsSnapshot {
     total_ad_clicks: i32,
 }
 
-#[derive(Serialize)]
+// Daily snapshot columns, in export order
+const ANALYTICS_METRICS: &[&str] = &[
+    "total_users",
+    "new_users",
+    "active_users",
+    "total_stories",
+    "new_stories",
+    "total_messages",
+    "new_messages",
+    "total_follows",
+    "new_follows",
+    "total_ad_impressions",
+    "total_ad_clicks",
+];
+
+impl AnalyticsSnapshot {
+    fn metric(&self, name: &str) -> i32 {
+        match name {
+            "total_users" => self.total_users,
+            "new_users" => self.new_users,
+            "active_users" => self.active_users,
+            "total_stories" => self.total_stories,
+            "new_stories" => self.new_stories,
+            "total_messages" => self.total_messages,
+            "new_messages" => self.new_messages,
+            "total_follows" => self.total_follows,
+            "new_follows" => self.new_follows,
+            "total_ad_impressions" => self.total_ad_impressions,
+            "total_ad_clicks" => self.total_ad_clicks,
+            _ => 0,
+        }
+    }
+}
+
+/// Parse a comma-separated metric list; all metrics when empty
+fn parse_metrics(list: Option<&str>) -> Result<Vec<&'static str>, String> {
+    let Some(list) = list.filter(|l| !l.trim().is_empty()) else {
+        return Ok(ANALYTICS_METRICS.to_vec());
+    };
+    let mut metrics = Vec::new();
+    for name in list.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        let metric = ANALYTICS_METRICS
+            .iter()
+            .find(|m| **m == name)
+            .ok_or_else(|| format!("Unknown metric '{}'; expected one of: {}", name, ANALYTICS_METRICS.join(", ")))?;
+        if !metrics.contains(metric) {
+            metrics.push(*metric);
+        }
+    }
+    Ok(metrics)
+}
+
+// Counts for each of `dates`, relative to the end of that day
+const ANALYTICS_DAYS_SQL: &str = r#"
+    SELECT
+        ds.date,
+        (SELECT COUNT(*)::int FROM users WHERE created_at::date <= ds.date) AS total_users,
+        (SELECT COUNT(*)::int FROM users WHERE created_at::date = ds.date) AS new_users,
+        (SELECT COUNT(DISTINCT user_id)::int FROM stories WHERE created_at::date = ds.date) AS active_users,
+        (SELECT COUNT(*)::int FROM stories WHERE created_at::date <= ds.date) AS total_stories,
+        (SELECT COUNT(*)::int FROM stories WHERE created_at::date = ds.date) AS new_stories,
+        (SELECT COUNT(*)::int FROM messages WHERE created_at::date <= ds.date) AS total_messages,
+        (SELECT COUNT(*)::int FROM messages WHERE created_at::date = ds.date) AS new_messages,
+        (SELECT COUNT(*)::int FROM follows WHERE created_at::date <= ds.date) AS total_follows,
+        (SELECT COUNT(*)::int FROM follows WHERE created_at::date = ds.date) AS new_follows,
+        (SELECT COUNT(*)::int FROM ad_impressions WHERE shown_at::date <= ds.date) AS total_ad_impressions,
+        (SELECT COUNT(*)::int FROM ad_impressions WHERE clicked = true AND clicked_at::date <= ds.date) AS total_ad_clicks
+    FROM unnest($1::date[]) AS ds(date)
+"#;
+
+async fn compute_analytics_days(pool: &sqlx::PgPool, dates: &[NaiveDate]) -> Result<Vec<AnalyticsSnapshot>, sqlx::Error> {
+    if dates.is_empty() {
+        return Ok(Vec::new());
+    }
+    sqlx::query_as::<_, AnalyticsSnapshot>(ANALYTICS_DAYS_SQL)
+        .bind(dates)
+        .fetch_all(pool)
+        .await
+}
+
+/// Snapshots for every day from `from` to `to` inclusive. Finished days come
+/// from analytics_daily; today, and any day the snapshot job has not written
+/// yet, are computed on the fly.
+async fn daily_snapshots(pool: &sqlx::PgPool, from: NaiveDate, to: NaiveDate) -> Result<Vec<AnalyticsSnapshot>, sqlx::Error> {
+    let pending_days: Vec<NaiveDate> = crate::query_metrics::observe(
+        "admin.analytics.pending_days",
+        sqlx::query_scalar::<_, NaiveDate>(
+            r#"
+            SELECT d::date FROM generate_series($1::date, $2::date, '1 day'::interval) d
+            WHERE d::date = CURRENT_DATE
+               OR NOT EXISTS (SELECT 1 FROM analytics_daily a WHERE a.date = d::date)
+            "#
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool),
+    )
+    .await?;
+
+    let mut snapshots = crate::query_metrics::observe(
+        "admin.analytics.daily",
+        sqlx::query_as::<_, AnalyticsSnapshot>(
+            r#"
+            SELECT date, total_users, new_users, active_users, total_stories, new_stories,
+                   total_messages, new_messages, total_follows, new_follows,
+                   total_ad_impressions, total_ad_clicks
+            FROM analytics_daily
+            WHERE date BETWEEN $1 AND $2 AND date < CURRENT_DATE
+            "#
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool),
+    )
+    .await?;
+
+    let computed = crate::query_metrics::observe(
+        "admin.analytics.compute_days",
+        compute_analytics_days(pool, &pending_days),
+    )
+    .await?;
+    snapshots.extend(computed);
+    snapshots.sort_by_key(|s| s.date);
+    Ok(snapshots)
+}
+
+/// Job body: write analytics_daily rows for finished days that are missing,
+/// going back up to a year. Finished days never change, so each day is
+/// computed once; the first run backfills the whole year.
+pub async fn snapshot_daily_analytics(pool: &sqlx::PgPool) -> Result<Option<serde_json::Value>, String> {
+    let missing: Vec<NaiveDate> = sqlx::query_scalar::<_, NaiveDate>(
+        r#"
+        SELECT d::date FROM generate_series(CURRENT_DATE - 365, CURRENT_DATE - 1, '1 day'::interval) d
+        WHERE NOT EXISTS (SELECT 1 FROM analytics_daily a WHERE a.date = d::date)
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let snapshots = crate::query_metrics::observe("admin.analytics.snapshot", compute_analytics_days(pool, &missing))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for s in &snapshots {
+        sqlx::query(
+            r#"
+            INSERT INTO analytics_daily
+                (date, total_users, new_users, active_users, total_stories, new_stories,
+                 total_messages, new_messages, total_follows, new_follows,
+                 total_ad_impressions, total_ad_clicks)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            ON CONFLICT (date) DO NOTHING
+            "#
+        )
+        .bind(s.date)
+        .bind(s.total_users)
+        .bind(s.new_users)
+        .bind(s.active_users)
+        .bind(s.total_stories)
+        .bind(s.new_stories)
+        .bind(s.total_messages)
+        .bind(s.new_messages)
+        .bind(s.total_follows)
+        .bind(s.new_follows)
+        .bind(s.total_ad_impressions)
+        .bind(s.total_ad_clicks)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(Some(serde_json::json!({ "days_written": snapshots.len() })))
+}
+
+#[derive(Serialize, ToSchema)]
 pub struct AnalyticsSummary {
     total_users: i64,
     total_stories: i64,
@@ -637,124 +939,187 @@ pub struct AnalyticsSummary {
     total_ad_clicks: i64,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct AnalyticsResponse {
     summary: AnalyticsSummary,
-    daily_snapshots: Vec<AnalyticsSnapshot>,
+    from: NaiveDate,
+    to: NaiveDate,
+    /// One object per day: `date` plus each requested metric
+    #[schema(value_type = Vec<AnalyticsSnapshot>)]
+    daily_snapshots: Vec<serde_json::Value>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct AnalyticsQuery {
+    /// Days back from `to` when `from` is not given (default 30)
     days: Option<i64>,
+    /// First day of the range (inclusive)
+    from: Option<NaiveDate>,
+    /// Last day of the range (inclusive, default today)
+    to: Option<NaiveDate>,
+    /// Comma-separated daily metrics to return; all when omitted
+    metrics: Option<String>,
+    /// json (default) or csv
+    format: Option<String>,
 }
 
+const MAX_ANALYTICS_RANGE_DAYS: i64 = 366;
+
+/// Resolve the requested range: `to` defaults to today, `from` to `days`
+/// before it; at most MAX_ANALYTICS_RANGE_DAYS long and never in the future
+fn analytics_range(params: &AnalyticsQuery) -> Result<(NaiveDate, NaiveDate), (StatusCode, String)> {
+    let today = Utc::now().date_naive();
+    let to = params.to.unwrap_or(today).min(today);
+    let from = match params.from {
+        Some(from) => from,
+        None => to - chrono::Duration::days(params.days.unwrap_or(30).clamp(1, 365)),
+    };
+    if from > to {
+        return Err((StatusCode::BAD_REQUEST, "from must not be after to".to_string()));
+    }
+    if (to - from).num_days() >= MAX_ANALYTICS_RANGE_DAYS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Date range is limited to {} days", MAX_ANALYTICS_RANGE_DAYS),
+        ));
+    }
+    Ok((from, to))
+}
+
+fn analytics_csv(snapshots: &[AnalyticsSnapshot], metrics: &[&str]) -> String {
+    let mut csv = format!("date,{}\n", metrics.join(","));
+    for snapshot in snapshots {
+        csv.push_str(&snapshot.date.to_string());
+        for metric in metrics {
+            csv.push_str(&format!(",{}", snapshot.metric(metric)));
+        }
+        csv.push('\n');
+    }
+    csv
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/analytics",
+    tag = "admin",
+    params(AnalyticsQuery),
+    responses(
+        (status = 200, body = AnalyticsResponse),
+        (status = 200, description = "Daily snapshots as CSV (format=csv)", content_type = "text/csv"),
+        (status = 400, description = "Bad date range, metric or format"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn get_analytics(
-    _admin: AdminUser,
+    _admin: Permitted<CanViewAnalytics>,
     State(state): State<Arc<crate::AppState>>,
     Query(params): Query<AnalyticsQuery>,
-) -> Result<Json<AnalyticsResponse>, (StatusCode, String)> {
-    let days = params.days.unwrap_or(30).clamp(1, 365);
+) -> Result<axum::response::Response, (StatusCode, String)> {
+    use axum::response::IntoResponse;
+
+    let (from, to) = analytics_range(&params)?;
+    let metrics = parse_metrics(params.metrics.as_deref()).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let format = params.format.as_deref().unwrap_or("json").to_lowercase();
+    if format != "json" && format != "csv" {
+        return Err((StatusCode::BAD_REQUEST, "format must be one of: json, csv".to_string()));
+    }
+    let pool = state.read_pool();
 
-    // Get summary stats
-    let total_users: i64 = sqlx::query_scalar!("SELECT COUNT(*) FROM users")
-        .fetch_one(state.pool.as_ref())
-        .await
-        .unwrap_or(Some(0))
-        .unwrap_or(0);
+    let snapshots = daily_snapshots(pool, from, to).await.map_err(|e| {
+        eprintln!("Analytics error: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch analytics".to_string())
+    })?;
 
-    let total_stories: i64 = sqlx::query_scalar!("SELECT COUNT(*) FROM stories")
-        .fetch_one(state.pool.as_ref())
-        .await
-        .unwrap_or(Some(0))
-        .unwrap_or(0);
+    if format == "csv" {
+        let disposition = format!("attachment; filename=\"analytics-{}-to-{}.csv\"", from, to);
+        return Ok((
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+                (header::CONTENT_DISPOSITION, disposition),
+            ],
+            analytics_csv(&snapshots, &metrics),
+        )
+            .into_response());
+    }
 
-    let total_messages: i64 = sqlx::query_scalar!("SELECT COUNT(*) FROM messages")
-        .fetch_one(state.pool.as_ref())
-        .await
-        .unwrap_or(Some(0))
-        .unwrap_or(0);
+    // Get summary stats
+    let total_users: i64 = crate::query_metrics::observe(
+        "admin.analytics.total_users",
+        sqlx::query_scalar!("SELECT COUNT(*) FROM users").fetch_one(pool),
+    )
+    .await
+    .unwrap_or(Some(0))
+    .unwrap_or(0);
 
-    let total_follows: i64 = sqlx::query_scalar!("SELECT COUNT(*) FROM follows")
-        .fetch_one(state.pool.as_ref())
-        .await
-        .unwrap_or(Some(0))
-        .unwrap_or(0);
+    let total_stories: i64 = crate::query_metrics::observe(
+        "admin.analytics.total_stories",
+        sqlx::query_scalar!("SELECT COUNT(*) FROM stories").fetch_one(pool),
+    )
+    .await
+    .unwrap_or(Some(0))
+    .unwrap_or(0);
 
-    let total_ads: i64 = sqlx::query_scalar!("SELECT COUNT(*) FROM advertisements")
-        .fetch_one(state.pool.as_ref())
-        .await
-        .unwrap_or(Some(0))
-        .unwrap_or(0);
+    let total_messages: i64 = crate::query_metrics::observe(
+        "admin.analytics.total_messages",
+        sqlx::query_scalar!("SELECT COUNT(*) FROM messages").fetch_one(pool),
+    )
+    .await
+    .unwrap_or(Some(0))
+    .unwrap_or(0);
 
-    let active_ads: i64 = sqlx::query_scalar!("SELECT COUNT(*) FROM advertisements WHERE status = 'active'")
-        .fetch_one(state.pool.as_ref())
-        .await
-        .unwrap_or(Some(0))
-        .unwrap_or(0);
+    let total_follows: i64 = crate::query_metrics::observe(
+        "admin.analytics.total_follows",
+        sqlx::query_scalar!("SELECT COUNT(*) FROM follows").fetch_one(pool),
+    )
+    .await
+    .unwrap_or(Some(0))
+    .unwrap_or(0);
 
-    let total_ad_impressions: i64 = sqlx::query_scalar!("SELECT COUNT(*) FROM ad_impressions")
-        .fetch_one(state.pool.as_ref())
-        .await
-        .unwrap_or(Some(0))
-        .unwrap_or(0);
+    let total_ads: i64 = crate::query_metrics::observe(
+        "admin.analytics.total_ads",
+        sqlx::query_scalar!("SELECT COUNT(*) FROM advertisements").fetch_one(pool),
+    )
+    .await
+    .unwrap_or(Some(0))
+    .unwrap_or(0);
 
-    let total_ad_clicks: i64 = sqlx::query_scalar!("SELECT COUNT(*) FROM ad_impressions WHERE clicked = true")
-        .fetch_one(state.pool.as_ref())
-        .await
-        .unwrap_or(Some(0))
-        .unwrap_or(0);
+    let active_ads: i64 = crate::query_metrics::observe(
+        "admin.analytics.active_ads",
+        sqlx::query_scalar!("SELECT COUNT(*) FROM advertisements WHERE status = 'active'").fetch_one(pool),
+    )
+    .await
+    .unwrap_or(Some(0))
+    .unwrap_or(0);
 
-    // Get daily snapshots (compute on-the-fly for now, can be pre-computed later)
-    let days_i32 = days as i32;
-    let daily_snapshots = sqlx::query!(
-        r#"
-        WITH date_series AS (
-            SELECT generate_series(
-                CURRENT_DATE - $1::integer,
-                CURRENT_DATE,
-                '1 day'::interval
-            )::date as date
-        )
-        SELECT
-            ds.date as "date!",
-            COALESCE((SELECT COUNT(*)::int FROM users WHERE created_at::date <= ds.date), 0) as "total_users!",
-            COALESCE((SELECT COUNT(*)::int FROM users WHERE created_at::date = ds.date), 0) as "new_users!",
-            COALESCE((SELECT COUNT(DISTINCT user_id)::int FROM stories WHERE created_at::date = ds.date), 0) as "active_users!",
-            COALESCE((SELECT COUNT(*)::int FROM stories WHERE created_at::date <= ds.date), 0) as "total_stories!",
-            COALESCE((SELECT COUNT(*)::int FROM stories WHERE created_at::date = ds.date), 0) as "new_stories!",
-            COALESCE((SELECT COUNT(*)::int FROM messages WHERE created_at::date <= ds.date), 0) as "total_messages!",
-            COALESCE((SELECT COUNT(*)::int FROM messages WHERE created_at::date = ds.date), 0) as "new_messages!",
-            COALESCE((SELECT COUNT(*)::int FROM follows WHERE created_at::date <= ds.date), 0) as "total_follows!",
-            COALESCE((SELECT COUNT(*)::int FROM follows WHERE created_at::date = ds.date), 0) as "new_follows!",
-            COALESCE((SELECT COUNT(*)::int FROM ad_impressions WHERE shown_at::date <= ds.date), 0) as "total_ad_impressions!",
-            COALESCE((SELECT COUNT(*)::int FROM ad_impressions WHERE clicked = true AND clicked_at::date <= ds.date), 0) as "total_ad_clicks!"
-        FROM date_series ds
-        ORDER BY ds.date
-        "#,
-        days_i32
+    let total_ad_impressions: i64 = crate::query_metrics::observe(
+        "admin.analytics.total_ad_impressions",
+        sqlx::query_scalar!("SELECT COUNT(*) FROM ad_impressions").fetch_one(pool),
     )
-    .fetch_all(state.pool.as_ref())
     .await
-    .map_err(|e| {
-        eprintln!("Analytics error: {:?}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch analytics".to_string())
-    })?
-    .into_iter()
-    .map(|row| AnalyticsSnapshot {
-        date: row.date,
-        total_users: row.total_users,
-        new_users: row.new_users,
-        active_users: row.active_users,
-        total_stories: row.total_stories,
-        new_stories: row.new_stories,
-        total_messages: row.total_messages,
-        new_messages: row.new_messages,
-        total_follows: row.total_follows,
-        new_follows: row.new_follows,
-        total_ad_impressions: row.total_ad_impressions,
-        total_ad_clicks: row.total_ad_clicks,
-    })
-    .collect();
+    .unwrap_or(Some(0))
+    .unwrap_or(0);
+
+    let total_ad_clicks: i64 = crate::query_metrics::observe(
+        "admin.analytics.total_ad_clicks",
+        sqlx::query_scalar!("SELECT COUNT(*) FROM ad_impressions WHERE clicked = true").fetch_one(pool),
+    )
+    .await
+    .unwrap_or(Some(0))
+    .unwrap_or(0);
+
+    let daily_snapshots = snapshots
+        .iter()
+        .map(|snapshot| {
+            let mut day = serde_json::Map::new();
+            day.insert("date".to_string(), serde_json::json!(snapshot.date));
+            for metric in &metrics {
+                day.insert(metric.to_string(), serde_json::json!(snapshot.metric(metric)));
+            }
+            serde_json::Value::Object(day)
+        })
+        .collect();
 
     Ok(Json(AnalyticsResponse {
         summary: AnalyticsSummary {
@@ -767,24 +1132,151 @@ pub async fn get_analytics(
             total_ad_impressions,
             total_ad_clicks,
         },
+        from,
+        to,
         daily_snapshots,
-    }))
+    })
+    .into_response())
+}
+
+const DEFAULT_REPORT_INTERVAL_DAYS: i64 = 7;
+
+fn report_summary_line(metric: &str, snapshots: &[AnalyticsSnapshot]) -> String {
+    let (Some(first), Some(last)) = (snapshots.first(), snapshots.last()) else {
+        return format!("{}: no data", metric);
+    };
+    if metric.starts_with("total_") {
+        // Running totals: where the period ended and how much it moved
+        let change = last.metric(metric) - first.metric(metric);
+        return format!("{}: {} ({:+} over the period)", metric, last.metric(metric), change);
+    }
+    let sum: i64 = snapshots.iter().map(|s| s.metric(metric) as i64).sum();
+    let average = sum as f64 / snapshots.len() as f64;
+    if metric == "active_users" {
+        // Distinct per day, so a sum would count people more than once
+        format!("{}: {:.1} per day", metric, average)
+    } else {
+        format!("{}: {} ({:.1} per day)", metric, sum, average)
+    }
+}
+
+/// Job body: email a summary of the last ANALYTICS_REPORT_INTERVAL_DAYS
+/// (default 7, 0 turns reports off) finished days to ANALYTICS_REPORT_RECIPIENTS,
+/// or to every admin when that is unset, covering ANALYTICS_REPORT_METRICS
+/// (default all). Runs hourly and sends only once the last report is a full
+/// interval old.
+pub async fn send_analytics_report(pool: &sqlx::PgPool) -> Result<Option<serde_json::Value>, String> {
+    let interval_days = std::env::var("ANALYTICS_REPORT_INTERVAL_DAYS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_REPORT_INTERVAL_DAYS);
+    if interval_days <= 0 || !crate::mailer::is_configured() {
+        return Ok(None);
+    }
+
+    let due: bool = sqlx::query_scalar(
+        "SELECT COALESCE(MAX(sent_at) <= NOW() - make_interval(days => $1), TRUE) FROM analytics_reports",
+    )
+    .bind(interval_days as i32)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    if !due {
+        return Ok(None);
+    }
+
+    let metrics = parse_metrics(std::env::var("ANALYTICS_REPORT_METRICS").ok().as_deref())?;
+    let mut recipients: Vec<String> = std::env::var("ANALYTICS_REPORT_RECIPIENTS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty())
+        .collect();
+    if recipients.is_empty() {
+        recipients = sqlx::query_scalar::<_, String>("SELECT email FROM users WHERE role = 'admin' ORDER BY created_at")
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    if recipients.is_empty() {
+        return Ok(Some(serde_json::json!({ "sent": false, "reason": "no recipients" })));
+    }
+
+    let to = Utc::now().date_naive() - chrono::Duration::days(1);
+    let from = to - chrono::Duration::days(interval_days - 1);
+    let snapshots = daily_snapshots(pool, from, to).await.map_err(|e| e.to_string())?;
+
+    let summary = metrics
+        .iter()
+        .map(|metric| report_summary_line(metric, &snapshots))
+        .collect::<Vec<_>>()
+        .join("\n");
+    crate::mailer::queue(
+        pool,
+        &recipients,
+        "analytics_report",
+        &serde_json::json!({ "from": from.to_string(), "to": to.to_string(), "summary": summary }),
+    )
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO analytics_reports (period_start, period_end, recipients, metrics) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(from)
+    .bind(to)
+    .bind(&recipients)
+    .bind(&metrics)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(Some(serde_json::json!({ "queued": true, "recipients": recipients.len(), "from": from, "to": to })))
 }
 
 // ============================================================================
 // ADVERTISEMENT HANDLERS
 // ============================================================================
 
-#[derive(Deserialize)]
+const DEFAULT_ATTRIBUTION_WINDOW_HOURS: i32 = 168;
+const MAX_ATTRIBUTION_WINDOW_HOURS: i32 = 2160;
+
+fn check_attribution_window(hours: i32) -> Result<(), (StatusCode, String)> {
+    if !(1..=MAX_ATTRIBUTION_WINDOW_HOURS).contains(&hours) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("attribution_window_hours must be between 1 and {}", MAX_ATTRIBUTION_WINDOW_HOURS),
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Deserialize, ToSchema)]
 pub struct CreateAdInput {
     title: String,
     description: Option<String>,
     image_url: Option<String>,
     link_url: Option<String>,
     target_impressions: i32,
+    /// 18+ creative, only served to adults
+    #[serde(default)]
+    is_mature: bool,
+    /// ISO 3166-1 alpha-2 countries to serve in; empty serves everywhere
+    #[serde(default)]
+    target_countries: Vec<String>,
+    /// ISO 639 languages to serve to; empty serves every language
+    #[serde(default)]
+    target_languages: Vec<String>,
+    /// How long after a click or view a conversion is credited (default 168)
+    attribution_window_hours: Option<i32>,
+    /// package (default, served until target_impressions) or cpm (charged per impression)
+    billing_model: Option<String>,
+    /// USD per thousand impressions, required for cpm
+    cpm_rate: Option<f64>,
+    /// Opening balance of a cpm campaign in USD, credited without payment
+    budget: Option<f64>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct AdCampaign {
     id: Uuid,
     title: String,
@@ -794,16 +1286,102 @@ pub struct AdCampaign {
     target_impressions: i32,
     current_impressions: i32,
     click_count: i32,
+    /// Clicks discounted as fraud
+    fraudulent_clicks: i32,
+    /// click_count minus fraudulent_clicks; what the campaign is billed on
+    billable_clicks: i32,
+    /// Billable clicks per impression
     ctr_percentage: f64,
     status: String,
     created_at: DateTime<Utc>,
     updated_at: DateTime<Utc>,
     expires_at: Option<DateTime<Utc>>,
+    is_mature: bool,
+    target_countries: Vec<String>,
+    target_languages: Vec<String>,
+    attribution_window_hours: i32,
+    billing_model: String,
+    cpm_rate: Option<f64>,
+    budget_balance: f64,
+    total_spend: f64,
+    created_by_username: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct AdCampaignRow {
+    id: Uuid,
+    title: String,
+    description: Option<String>,
+    image_url: Option<String>,
+    link_url: Option<String>,
+    target_impressions: i32,
+    current_impressions: i32,
+    click_count: i32,
+    fraudulent_clicks: i32,
+    status: String,
+    created_at: chrono::NaiveDateTime,
+    updated_at: chrono::NaiveDateTime,
+    expires_at: Option<chrono::NaiveDateTime>,
+    is_mature: bool,
+    target_countries: Vec<String>,
+    target_languages: Vec<String>,
+    attribution_window_hours: i32,
+    billing_model: String,
+    cpm_rate: Option<f64>,
+    budget_balance: f64,
+    total_spend: f64,
+    #[sqlx(default)]
     created_by_username: Option<String>,
 }
 
+impl From<AdCampaignRow> for AdCampaign {
+    fn from(row: AdCampaignRow) -> Self {
+        let billable_clicks = (row.click_count - row.fraudulent_clicks).max(0);
+        let ctr = if row.current_impressions > 0 {
+            (billable_clicks as f64 / row.current_impressions as f64) * 100.0
+        } else {
+            0.0
+        };
+
+        AdCampaign {
+            id: row.id,
+            title: row.title,
+            description: row.description,
+            image_url: row.image_url,
+            link_url: row.link_url,
+            target_impressions: row.target_impressions,
+            current_impressions: row.current_impressions,
+            click_count: row.click_count,
+            fraudulent_clicks: row.fraudulent_clicks,
+            billable_clicks,
+            ctr_percentage: ctr,
+            status: row.status,
+            created_at: row.created_at.and_utc(),
+            updated_at: row.updated_at.and_utc(),
+            expires_at: row.expires_at.map(|dt| dt.and_utc()),
+            is_mature: row.is_mature,
+            target_countries: row.target_countries,
+            target_languages: row.target_languages,
+            attribution_window_hours: row.attribution_window_hours,
+            billing_model: row.billing_model,
+            cpm_rate: row.cpm_rate,
+            budget_balance: row.budget_balance,
+            total_spend: row.total_spend,
+            created_by_username: row.created_by_username,
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/ads",
+    tag = "ads",
+    request_body = CreateAdInput,
+    responses((status = 200, body = AdCampaign), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
 pub async fn create_ad(
-    admin: AdminUser,
+    admin: Permitted<CanManageAds>,
     State(state): State<Arc<crate::AppState>>,
     Json(input): Json<CreateAdInput>,
 ) -> Result<Json<AdCampaign>, (StatusCode, String)> {
@@ -811,36 +1389,54 @@ pub async fn create_ad(
     println!("   Target impressions: {}", input.target_impressions);
     println!("   Image URL: {:?}", input.image_url);
 
-    if input.target_impressions < 1 {
+    let (billing_model, cpm_rate) = crate::ad_billing::check_billing(input.billing_model.as_deref(), input.cpm_rate)?;
+    if billing_model == "package" && input.target_impressions < 1 {
         return Err((StatusCode::BAD_REQUEST, "Target impressions must be at least 1".to_string()));
     }
+    let budget = input.budget.unwrap_or(0.0);
+    if !budget.is_finite() || budget < 0.0 || (budget > 0.0 && billing_model != "cpm") {
+        return Err((StatusCode::BAD_REQUEST, "budget must be a non-negative amount on a cpm campaign".to_string()));
+    }
+    let (target_countries, target_languages) =
+        crate::ad_targeting::normalize_targets(&input.target_countries, &input.target_languages)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let attribution_window_hours = input.attribution_window_hours.unwrap_or(DEFAULT_ATTRIBUTION_WINDOW_HOURS);
+    check_attribution_window(attribution_window_hours)?;
 
-    let ad = sqlx::query!(
+    let mut ad: AdCampaign = sqlx::query_as::<_, AdCampaignRow>(
         r#"
-        INSERT INTO advertisements (created_by, title, description, image_url, link_url, target_impressions)
-        VALUES ($1, $2, $3, $4, $5, $6)
+        INSERT INTO advertisements
+            (created_by, title, description, image_url, link_url, target_impressions, is_mature,
+             target_countries, target_languages, attribution_window_hours, billing_model, cpm_rate,
+             budget_balance)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
         RETURNING id, title, description, image_url, link_url, target_impressions, current_impressions,
-                  click_count, status, created_at, updated_at, expires_at
-        "#,
-        admin.0.id,
-        input.title,
-        input.description,
-        input.image_url,
-        input.link_url,
-        input.target_impressions
+                  click_count, fraudulent_clicks, status, created_at, updated_at, expires_at, is_mature,
+                  target_countries, target_languages, attribution_window_hours, billing_model,
+                  cpm_rate::float8 AS cpm_rate, budget_balance::float8 AS budget_balance,
+                  total_spend::float8 AS total_spend
+        "#
     )
+    .bind(admin.0.id)
+    .bind(&input.title)
+    .bind(&input.description)
+    .bind(&input.image_url)
+    .bind(&input.link_url)
+    .bind(input.target_impressions)
+    .bind(input.is_mature)
+    .bind(&target_countries)
+    .bind(&target_languages)
+    .bind(attribution_window_hours)
+    .bind(&billing_model)
+    .bind(&cpm_rate)
+    .bind(BigDecimal::from_f64(budget))
     .fetch_one(state.pool.as_ref())
     .await
     .map_err(|e| {
         eprintln!("Create ad error: {:?}", e);
         (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create advertisement".to_string())
-    })?;
-
-    let ctr = if ad.current_impressions > 0 {
-        (ad.click_count as f64 / ad.current_impressions as f64) * 100.0
-    } else {
-        0.0
-    };
+    })?
+    .into();
 
     // Log admin action
     log_admin_action(
@@ -850,40 +1446,52 @@ pub async fn create_ad(
         None,
         Some("advertisement".to_string()),
         Some(ad.id),
-        serde_json::json!({ "title": input.title, "target_impressions": input.target_impressions }),
+        serde_json::json!({
+            "title": input.title,
+            "target_impressions": input.target_impressions,
+            "is_mature": input.is_mature,
+            "target_countries": target_countries,
+            "target_languages": target_languages,
+            "billing_model": billing_model,
+            "cpm_rate": input.cpm_rate,
+            "budget": budget,
+        }),
     ).await;
 
     println!("✅ Ad campaign created successfully: {} ({})", ad.title, ad.id);
 
-    Ok(Json(AdCampaign {
-        id: ad.id,
-        title: ad.title,
-        description: ad.description,
-        image_url: ad.image_url,
-        link_url: ad.link_url,
-        target_impressions: ad.target_impressions,
-        current_impressions: ad.current_impressions,
-        click_count: ad.click_count,
-        ctr_percentage: ctr,
-        status: ad.status,
-        created_at: ad.created_at.and_utc(),
-        updated_at: ad.updated_at.and_utc(),
-        expires_at: ad.expires_at.map(|dt| dt.and_utc()),
-        created_by_username: Some(admin.0.username),
-    }))
+    ad.created_by_username = Some(admin.0.username);
+    Ok(Json(ad))
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, ToSchema)]
 pub struct UpdateAdInput {
     title: Option<String>,
     description: Option<String>,
     image_url: Option<String>,
     link_url: Option<String>,
     status: Option<String>,
+    is_mature: Option<bool>,
+    /// Replaces the campaign's countries; an empty list serves everywhere
+    target_countries: Option<Vec<String>>,
+    /// Replaces the campaign's languages; an empty list serves every language
+    target_languages: Option<Vec<String>>,
+    attribution_window_hours: Option<i32>,
+    /// New rate for a cpm campaign; applies to impressions from now on
+    cpm_rate: Option<f64>,
 }
 
+#[utoipa::path(
+    patch,
+    path = "/api/v1/admin/ads/{ad_id}",
+    tag = "ads",
+    params(("ad_id" = Uuid, Path, description = "Ad ID")),
+    request_body = UpdateAdInput,
+    responses((status = 200, body = serde_json::Value), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
 pub async fn update_ad(
-    admin: AdminUser,
+    admin: Permitted<CanManageAds>,
     State(state): State<Arc<crate::AppState>>,
     Path(ad_id): Path<Uuid>,
     Json(input): Json<UpdateAdInput>,
@@ -895,43 +1503,20 @@ pub async fn update_ad(
         }
     }
 
-    // Build dynamic update query
-    let mut updates = Vec::new();
-    let mut params = Vec::new();
-    let mut param_count = 1;
-
-    if let Some(title) = &input.title {
-        updates.push(format!("title = ${}", param_count));
-        params.push(title.clone());
-        param_count += 1;
-    }
-    if let Some(description) = &input.description {
-        updates.push(format!("description = ${}", param_count));
-        params.push(description.clone());
-        param_count += 1;
-    }
-    if let Some(image_url) = &input.image_url {
-        updates.push(format!("image_url = ${}", param_count));
-        params.push(image_url.clone());
-        param_count += 1;
-    }
-    if let Some(link_url) = &input.link_url {
-        updates.push(format!("link_url = ${}", param_count));
-        params.push(link_url.clone());
-        param_count += 1;
-    }
-    if let Some(status) = &input.status {
-        updates.push(format!("status = ${}", param_count));
-        params.push(status.clone());
-        param_count += 1;
-    }
-
-    if updates.is_empty() {
+    if input.title.is_none()
+        && input.description.is_none()
+        && input.image_url.is_none()
+        && input.link_url.is_none()
+        && input.status.is_none()
+        && input.is_mature.is_none()
+        && input.target_countries.is_none()
+        && input.target_languages.is_none()
+        && input.attribution_window_hours.is_none()
+        && input.cpm_rate.is_none()
+    {
         return Err((StatusCode::BAD_REQUEST, "No fields to update".to_string()));
     }
 
-    updates.push("updated_at = NOW()".to_string());
-
     // For simplicity, use individual update statements
     if let Some(ref title) = input.title {
         sqlx::query!("UPDATE advertisements SET title = $1, updated_at = NOW() WHERE id = $2", title, ad_id)
@@ -946,25 +1531,80 @@ pub async fn update_ad(
         sqlx::query!("UPDATE advertisements SET description = $1, updated_at = NOW() WHERE id = $2", description, ad_id)
             .execute(state.pool.as_ref())
             .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update advertisement".to_string()))?;
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update advertisement".to_string()))?;
     }
     if let Some(ref image_url) = input.image_url {
         sqlx::query!("UPDATE advertisements SET image_url = $1, updated_at = NOW() WHERE id = $2", image_url, ad_id)
             .execute(state.pool.as_ref())
             .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update advertisement".to_string()))?;
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update advertisement".to_string()))?;
     }
     if let Some(ref link_url) = input.link_url {
         sqlx::query!("UPDATE advertisements SET link_url = $1, updated_at = NOW() WHERE id = $2", link_url, ad_id)
             .execute(state.pool.as_ref())
             .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update advertisement".to_string()))?;
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update advertisement".to_string()))?;
     }
     if let Some(ref status) = input.status {
         sqlx::query!("UPDATE advertisements SET status = $1, updated_at = NOW() WHERE id = $2", status, ad_id)
             .execute(state.pool.as_ref())
             .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update advertisement".to_string()))?;
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update advertisement".to_string()))?;
+    }
+    if let Some(is_mature) = input.is_mature {
+        sqlx::query("UPDATE advertisements SET is_mature = $1, updated_at = NOW() WHERE id = $2")
+            .bind(is_mature)
+            .bind(ad_id)
+            .execute(state.pool.as_ref())
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update advertisement".to_string()))?;
+    }
+    if input.target_countries.is_some() || input.target_languages.is_some() {
+        let (target_countries, target_languages) = crate::ad_targeting::normalize_targets(
+            input.target_countries.as_deref().unwrap_or_default(),
+            input.target_languages.as_deref().unwrap_or_default(),
+        )
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+        sqlx::query(
+            r#"
+            UPDATE advertisements
+            SET target_countries = CASE WHEN $1 THEN $2 ELSE target_countries END,
+                target_languages = CASE WHEN $3 THEN $4 ELSE target_languages END,
+                updated_at = NOW()
+            WHERE id = $5
+            "#
+        )
+        .bind(input.target_countries.is_some())
+        .bind(&target_countries)
+        .bind(input.target_languages.is_some())
+        .bind(&target_languages)
+        .bind(ad_id)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update advertisement".to_string()))?;
+    }
+    if let Some(hours) = input.attribution_window_hours {
+        check_attribution_window(hours)?;
+        sqlx::query("UPDATE advertisements SET attribution_window_hours = $1, updated_at = NOW() WHERE id = $2")
+            .bind(hours)
+            .bind(ad_id)
+            .execute(state.pool.as_ref())
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update advertisement".to_string()))?;
+    }
+    if let Some(rate) = input.cpm_rate {
+        let rate = crate::ad_billing::cpm_rate_value(rate)?;
+        let updated = sqlx::query(
+            "UPDATE advertisements SET cpm_rate = $1, updated_at = NOW() WHERE id = $2 AND billing_model = 'cpm'",
+        )
+        .bind(&rate)
+        .bind(ad_id)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update advertisement".to_string()))?;
+        if updated.rows_affected() == 0 {
+            return Err((StatusCode::BAD_REQUEST, "cpm_rate only applies to cpm campaigns".to_string()));
+        }
     }
 
     // Log admin action
@@ -984,18 +1624,29 @@ pub async fn update_ad(
     })))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/ads",
+    tag = "ads",
+    params(UserListQuery),
+    responses((status = 200, body = [AdCampaign]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
 pub async fn list_ads(
-    _admin: AdminUser,
+    _admin: Permitted<CanManageAds>,
     State(state): State<Arc<crate::AppState>>,
-    Query(params): Query<UserListQuery>,
+    Query(_params): Query<UserListQuery>,
 ) -> Result<Json<Vec<AdCampaign>>, (StatusCode, String)> {
-    let ads = sqlx::query!(
+    let ads = sqlx::query_as::<_, AdCampaignRow>(
         r#"
         SELECT
             a.id, a.title, a.description, a.image_url, a.link_url,
-            a.target_impressions, a.current_impressions, a.click_count,
-            a.status, a.created_at, a.updated_at, a.expires_at,
-            u.username as "created_by_username?"
+            a.target_impressions, a.current_impressions, a.click_count, a.fraudulent_clicks,
+            a.status, a.created_at, a.updated_at, a.expires_at, a.is_mature,
+            a.target_countries, a.target_languages, a.attribution_window_hours,
+            a.billing_model, a.cpm_rate::float8 AS cpm_rate, a.budget_balance::float8 AS budget_balance,
+            a.total_spend::float8 AS total_spend,
+            u.username as created_by_username
         FROM advertisements a
         LEFT JOIN users u ON a.created_by = u.id
         ORDER BY a.created_at DESC
@@ -1008,37 +1659,22 @@ pub async fn list_ads(
         (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch advertisements".to_string())
     })?
     .into_iter()
-    .map(|row| {
-        let ctr = if row.current_impressions > 0 {
-            (row.click_count as f64 / row.current_impressions as f64) * 100.0
-        } else {
-            0.0
-        };
-
-        AdCampaign {
-            id: row.id,
-            title: row.title,
-            description: row.description,
-            image_url: row.image_url,
-            link_url: row.link_url,
-            target_impressions: row.target_impressions,
-            current_impressions: row.current_impressions,
-            click_count: row.click_count,
-            ctr_percentage: ctr,
-            status: row.status,
-            created_at: row.created_at.and_utc(),
-            updated_at: row.updated_at.and_utc(),
-            expires_at: row.expires_at.map(|dt| dt.and_utc()),
-            created_by_username: row.created_by_username,
-        }
-    })
+    .map(AdCampaign::from)
     .collect();
 
     Ok(Json(ads))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/ads/{ad_id}",
+    tag = "ads",
+    params(("ad_id" = Uuid, Path, description = "Ad ID")),
+    responses((status = 200, body = serde_json::Value), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
 pub async fn delete_ad(
-    admin: AdminUser,
+    admin: Permitted<CanManageAds>,
     State(state): State<Arc<crate::AppState>>,
     Path(ad_id): Path<Uuid>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
@@ -1071,7 +1707,7 @@ pub async fn delete_ad(
 // PUBLIC AD SERVING ENDPOINTS (for displaying ads to users)
 // ============================================================================
 
-#[derive(Serialize)]
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
 pub struct AdToShow {
     id: Uuid,
     title: String,
@@ -1081,26 +1717,41 @@ pub struct AdToShow {
 }
 
 // Get next ad to show to a user
+#[utoipa::path(
+    get,
+    path = "/api/v1/ads/next/{user_id}",
+    tag = "ads",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200, body = AdToShow))
+)]
 pub async fn get_next_ad(
     State(state): State<Arc<crate::AppState>>,
     Path(user_id): Path<Uuid>,
+    headers: axum::http::HeaderMap,
 ) -> Result<Json<Option<AdToShow>>, (StatusCode, String)> {
-    // Find active ads that user hasn't seen yet, ordered by priority (least impressions first)
-    let ad = sqlx::query!(
+    let ctx = crate::ad_targeting::ServeContext::from_headers(&headers);
+
+    // Find active ads that user hasn't seen yet and that target where they
+    // are, ordered by priority (least impressions first)
+    let ad = sqlx::query_as::<_, AdToShow>(
         r#"
         SELECT a.id, a.title, a.description, a.image_url, a.link_url
         FROM advertisements a
         WHERE a.status = 'active'
-          AND a.current_impressions < a.target_impressions
+          AND ad_in_budget(a.billing_model, a.current_impressions, a.target_impressions, a.budget_balance, a.cpm_rate)
           AND NOT EXISTS (
               SELECT 1 FROM ad_impressions ai
               WHERE ai.ad_id = a.id AND ai.user_id = $1
           )
+          AND (NOT a.is_mature OR user_is_adult($1))
+          AND ad_geo_match(a.target_countries, a.target_languages, $2, $3)
         ORDER BY a.current_impressions ASC, RANDOM()
         LIMIT 1
-        "#,
-        user_id
+        "#
     )
+    .bind(user_id)
+    .bind(&ctx.country)
+    .bind(&ctx.language)
     .fetch_optional(state.pool.as_ref())
     .await
     .map_err(|e| {
@@ -1108,64 +1759,70 @@ pub async fn get_next_ad(
         (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch ad".to_string())
     })?;
 
-    if let Some(ad) = ad {
-        Ok(Json(Some(AdToShow {
-            id: ad.id,
-            title: ad.title,
-            description: ad.description,
-            image_url: ad.image_url,
-            link_url: ad.link_url,
-        })))
-    } else {
-        Ok(Json(None))
-    }
+    crate::ad_targeting::log_decision("next_ad", user_id, &ctx, &ad.iter().map(|a| a.id).collect::<Vec<_>>());
+
+    Ok(Json(ad))
+}
+
+#[derive(sqlx::FromRow)]
+struct AdDemographics {
+    birthdate: Option<chrono::NaiveDate>,
+    gender: Option<String>,
+    country: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ClickedImpression {
+    id: Uuid,
+    country: Option<String>,
+    city: Option<String>,
+    link_url: Option<String>,
 }
 
 // Record ad impression (when ad is shown to user)
+#[utoipa::path(
+    post,
+    path = "/api/v1/ads/{ad_id}/impression/{user_id}",
+    tag = "ads",
+    params(("ad_id" = Uuid, Path, description = "Ad ID"), ("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200, body = serde_json::Value))
+)]
 pub async fn record_ad_impression(
     State(state): State<Arc<crate::AppState>>,
     Path((ad_id, user_id)): Path<(Uuid, Uuid)>,
     headers: axum::http::HeaderMap,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    // Extract device type from User-Agent
-    let user_agent = headers
-        .get(axum::http::header::USER_AGENT)
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("unknown");
-
-    let device_type = if user_agent.contains("Mobile") || user_agent.contains("Android") || user_agent.contains("iPhone") {
-        "mobile"
-    } else if user_agent.contains("Tablet") || user_agent.contains("iPad") {
-        "tablet"
-    } else {
-        "desktop"
-    };
-
-    // Extract location from CloudFlare headers (if using CF) or X-Forwarded-For
-    let country = headers
-        .get("CF-IPCountry")
-        .and_then(|v| v.to_str().ok())
-        .map(|c| c.chars().take(2).collect::<String>())
-        .unwrap_or("un".to_string());
+    let device_type = crate::insights::device_type(&headers);
 
     let city = headers
         .get("CF-IPCity")
         .and_then(|v| v.to_str().ok());
 
-    // Get user demographics
-    let user_demo = sqlx::query!(
+    // Get user demographics, limited to what the user agreed to share with ads
+    let user_demo = sqlx::query_as::<_, AdDemographics>(
         r#"
-        SELECT birthdate, gender
+        SELECT CASE WHEN share_age_with_ads THEN birthdate END AS birthdate,
+               CASE WHEN share_gender_with_ads THEN gender END AS gender,
+               CASE WHEN share_country_with_ads THEN country END AS country
         FROM users
         WHERE id = $1
-        "#,
-        user_id
+        "#
     )
+    .bind(user_id)
     .fetch_optional(state.pool.as_ref())
     .await
     .ok()
     .flatten();
 
+    // Extract location from CloudFlare headers (if using CF), falling back to
+    // the country on the profile
+    let country = headers
+        .get("CF-IPCountry")
+        .and_then(|v| v.to_str().ok())
+        .map(|c| c.chars().take(2).collect::<String>())
+        .or_else(|| user_demo.as_ref().and_then(|demo| demo.country.clone()))
+        .unwrap_or("un".to_string());
+
     let (age_range, gender) = if let Some(demo) = user_demo {
         let age_range = if let Some(birthdate) = demo.birthdate {
             sqlx::query_scalar!(
@@ -1185,22 +1842,23 @@ pub async fn record_ad_impression(
     };
 
     // Insert impression record with analytics data
-    sqlx::query!(
+    sqlx::query(
         r#"
         INSERT INTO ad_impressions (
-            ad_id, user_id, country, city, device_type, user_age_range, user_gender
+            ad_id, user_id, country, city, device_type, user_age_range, user_gender, ip_address
         )
-        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
         ON CONFLICT DO NOTHING
-        "#,
-        ad_id,
-        user_id,
-        country,
-        city,
-        device_type,
-        age_range,
-        gender
+        "#
     )
+    .bind(ad_id)
+    .bind(user_id)
+    .bind(&country)
+    .bind(city)
+    .bind(device_type)
+    .bind(&age_range)
+    .bind(&gender)
+    .bind(crate::ad_fraud::client_ip(&headers))
     .execute(state.pool.as_ref())
     .await
     .map_err(|e| {
@@ -1236,27 +1894,42 @@ pub async fn record_ad_impression(
 }
 
 // Record ad click
+#[utoipa::path(
+    post,
+    path = "/api/v1/ads/{ad_id}/click/{user_id}",
+    tag = "ads",
+    params(("ad_id" = Uuid, Path, description = "Ad ID"), ("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200, body = serde_json::Value))
+)]
 pub async fn record_ad_click(
     State(state): State<Arc<crate::AppState>>,
     Path((ad_id, user_id)): Path<(Uuid, Uuid)>,
+    headers: axum::http::HeaderMap,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    // Get the impression country/city for updating location aggregates
-    let impression = sqlx::query!(
-        "SELECT country, city FROM ad_impressions WHERE ad_id = $1 AND user_id = $2",
-        ad_id,
-        user_id
+    // Get the impression country/city for updating location aggregates, and
+    // its ID for conversion attribution
+    let impression = sqlx::query_as::<_, ClickedImpression>(
+        r#"
+        SELECT ai.id, ai.country, ai.city, a.link_url
+        FROM ad_impressions ai
+        JOIN advertisements a ON a.id = ai.ad_id
+        WHERE ai.ad_id = $1 AND ai.user_id = $2
+        "#
     )
+    .bind(ad_id)
+    .bind(user_id)
     .fetch_optional(state.pool.as_ref())
     .await
     .ok()
     .flatten();
 
     // Update impression record to mark as clicked
-    sqlx::query!(
-        "UPDATE ad_impressions SET clicked = true, clicked_at = NOW() WHERE ad_id = $1 AND user_id = $2",
-        ad_id,
-        user_id
+    sqlx::query(
+        "UPDATE ad_impressions SET clicked = true, clicked_at = NOW(), click_ip = $3 WHERE ad_id = $1 AND user_id = $2"
     )
+    .bind(ad_id)
+    .bind(user_id)
+    .bind(crate::ad_fraud::client_ip(&headers))
     .execute(state.pool.as_ref())
     .await
     .map_err(|e| {
@@ -1264,8 +1937,22 @@ pub async fn record_ad_click(
         (StatusCode::INTERNAL_SERVER_ERROR, "Failed to record click".to_string())
     })?;
 
+    // Too-fast clicks and clicks from flagged users or IPs are discounted
+    // right away; the detection job catches the rest
+    let fraudulent = crate::ad_fraud::discount_clicks(&state.pool, Some(ad_id), Some(user_id))
+        .await
+        .map_err(|e| eprintln!("Click fraud check error: {:?}", e))
+        .unwrap_or(0)
+        > 0;
+
+    // The advertiser's landing page reports conversions with the click ID
+    let click_id = impression.as_ref().map(|imp| imp.id);
+    let redirect_url = impression
+        .as_ref()
+        .and_then(|imp| imp.link_url.as_deref().map(|url| crate::ad_conversions::redirect_url(url, imp.id)));
+
     // Update location performance aggregates (increment clicks)
-    if let Some(imp) = impression {
+    if let Some(imp) = impression.filter(|_| !fraudulent) {
         sqlx::query!(
             r#"
             INSERT INTO ad_performance_by_location (ad_id, country, city, clicks)
@@ -1289,7 +1976,9 @@ pub async fn record_ad_click(
     }
 
     Ok(Json(serde_json::json!({
-        "success": true
+        "success": true,
+        "click_id": click_id,
+        "redirect_url": redirect_url
     })))
 }
 
@@ -1297,7 +1986,7 @@ pub async fn record_ad_click(
 // PUBLIC AD CREATION ENDPOINTS (Self-service advertising)
 // ============================================================================
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct PublicCreateAdInput {
     pub title: String,
     pub description: Option<String>,
@@ -1307,15 +1996,37 @@ pub struct PublicCreateAdInput {
     pub package_type: String,
     pub price: f64,
     pub contact_email: String,
+    /// 18+ creative, only served to adults
+    #[serde(default)]
+    pub is_mature: bool,
+    /// ISO 3166-1 alpha-2 countries to serve in, e.g. just your own for a local business
+    #[serde(default)]
+    pub target_countries: Vec<String>,
+    /// ISO 639 languages to serve to
+    #[serde(default)]
+    pub target_languages: Vec<String>,
+    /// How long after a click or view a conversion is credited (default 168)
+    pub attribution_window_hours: Option<i32>,
+    /// package (default) or cpm; for cpm the price is the opening budget
+    pub billing_model: Option<String>,
+    /// USD per thousand impressions, required for cpm
+    pub cpm_rate: Option<f64>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct PublicCreateAdResponse {
     pub ad_id: Uuid,
     pub status: String,
 }
 
 // Public endpoint for creating ads (requires authentication)
+#[utoipa::path(
+    post,
+    path = "/api/v1/ads/create",
+    tag = "ads",
+    request_body = PublicCreateAdInput,
+    responses((status = 200, body = PublicCreateAdResponse))
+)]
 pub async fn create_ad_public(
     State(state): State<Arc<crate::AppState>>,
     headers: axum::http::HeaderMap,
@@ -1353,26 +2064,43 @@ pub async fn create_ad_public(
     let user_id = token_data.claims.sub;
     println!("📢 Public ad creation: {} by user {}", input.title, user_id);
 
+    let (target_countries, target_languages) =
+        crate::ad_targeting::normalize_targets(&input.target_countries, &input.target_languages)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let attribution_window_hours = input.attribution_window_hours.unwrap_or(DEFAULT_ATTRIBUTION_WINDOW_HOURS);
+    check_attribution_window(attribution_window_hours)?;
+    let (billing_model, cpm_rate) = crate::ad_billing::check_billing(input.billing_model.as_deref(), input.cpm_rate)?;
+    if billing_model == "cpm" && !(input.price.is_finite() && input.price >= 1.0) {
+        return Err((StatusCode::BAD_REQUEST, "The opening budget (price) must be at least 1".to_string()));
+    }
+
     // Create ad with pending_payment status
-    let ad = sqlx::query!(
+    let ad_id: Uuid = sqlx::query_scalar(
         r#"
         INSERT INTO advertisements (
             created_by, title, description, image_url, link_url,
-            target_impressions, status, package_type, price, contact_email
+            target_impressions, status, package_type, price, contact_email, is_mature,
+            target_countries, target_languages, attribution_window_hours, billing_model, cpm_rate
         )
-        VALUES ($1, $2, $3, $4, $5, $6, 'pending_payment', $7, $8, $9)
+        VALUES ($1, $2, $3, $4, $5, $6, 'pending_payment', $7, $8, $9, $10, $11, $12, $13, $14, $15)
         RETURNING id
-        "#,
-        user_id,
-        input.title,
-        input.description,
-        input.image_url,
-        input.link_url,
-        input.target_impressions,
-        input.package_type,
-        BigDecimal::from_f64(input.price),
-        input.contact_email
+        "#
     )
+    .bind(user_id)
+    .bind(&input.title)
+    .bind(&input.description)
+    .bind(&input.image_url)
+    .bind(&input.link_url)
+    .bind(input.target_impressions)
+    .bind(&input.package_type)
+    .bind(BigDecimal::from_f64(input.price))
+    .bind(&input.contact_email)
+    .bind(input.is_mature)
+    .bind(&target_countries)
+    .bind(&target_languages)
+    .bind(attribution_window_hours)
+    .bind(&billing_model)
+    .bind(&cpm_rate)
     .fetch_one(state.pool.as_ref())
     .await
     .map_err(|e| {
@@ -1380,18 +2108,34 @@ pub async fn create_ad_public(
         (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create advertisement".to_string())
     })?;
 
+    if billing_model == "cpm" {
+        crate::ad_billing::queue_opening_budget(&state.pool, ad_id, input.price, user_id)
+            .await
+            .map_err(|e| {
+                eprintln!("Queue opening budget error: {:?}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create advertisement".to_string())
+            })?;
+    }
+
     Ok(Json(PublicCreateAdResponse {
-        ad_id: ad.id,
+        ad_id,
         status: "pending_payment".to_string(),
     }))
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct CheckoutSessionResponse {
     pub session_id: String,
 }
 
 // Create Stripe checkout session for ad payment
+#[utoipa::path(
+    post,
+    path = "/api/v1/ads/{ad_id}/checkout",
+    tag = "ads",
+    params(("ad_id" = Uuid, Path, description = "Ad ID")),
+    responses((status = 200, body = CheckoutSessionResponse))
+)]
 pub async fn create_checkout_session(
     State(state): State<Arc<crate::AppState>>,
     Path(ad_id): Path<Uuid>,
@@ -1408,7 +2152,7 @@ pub async fn create_checkout_session(
     .await
     .map_err(|_| (StatusCode::NOT_FOUND, "Ad not found or already paid".to_string()))?;
 
-    let price = ad.price.ok_or((StatusCode::BAD_REQUEST, "Ad has no price set".to_string()))?;
+    let _price = ad.price.ok_or((StatusCode::BAD_REQUEST, "Ad has no price set".to_string()))?;
 
     // In production, you would create a real Stripe checkout session here
     // For now, in development mode, auto-approve for testing
@@ -1423,6 +2167,12 @@ pub async fn create_checkout_session(
         .execute(state.pool.as_ref())
         .await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update ad".to_string()))?;
+        crate::ad_billing::credit_pending_top_ups(&state.pool, ad_id, None)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to credit ad budget".to_string()))?;
+        crate::ad_review::record_submission(&state.pool, ad_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update ad".to_string()))?;
 
         return Ok(Json(CheckoutSessionResponse {
             session_id: format!("cs_test_mock_{}", ad_id),
@@ -1438,21 +2188,41 @@ pub async fn create_checkout_session(
 }
 
 // Stripe webhook handler
+#[utoipa::path(
+    post,
+    path = "/api/v1/stripe/webhook",
+    tag = "ads",
+    request_body(content = String, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Success"),
+        (status = 400, description = "Missing or invalid signature"),
+        (status = 503, description = "Live Stripe key without a webhook secret")
+    )
+)]
 pub async fn stripe_webhook(
     State(state): State<Arc<crate::AppState>>,
     headers: axum::http::HeaderMap,
     body: String,
 ) -> Result<StatusCode, StatusCode> {
-    let _signature = headers
+    let signature = headers
         .get("stripe-signature")
         .and_then(|v| v.to_str().ok())
         .ok_or(StatusCode::BAD_REQUEST)?;
 
-    let _webhook_secret = std::env::var("STRIPE_WEBHOOK_SECRET")
-        .unwrap_or_else(|_| "whsec_test".to_string());
-
-    // TODO: Verify Stripe signature in production
-    // For now, just parse the event
+    // Events go unverified only in mock mode (no real STRIPE_SECRET_KEY); with
+    // real payments a missing webhook secret would let anyone forge a payment
+    match std::env::var("STRIPE_WEBHOOK_SECRET").ok().filter(|s| !s.is_empty()) {
+        Some(webhook_secret) if !crate::ad_billing::verify_stripe_signature(&webhook_secret, signature, &body) => {
+            eprintln!("Stripe webhook rejected: bad signature");
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        Some(_) => {}
+        None if crate::ad_billing::stripe_secret_key().is_some() => {
+            tracing::error!("Stripe webhook rejected: STRIPE_WEBHOOK_SECRET is not set");
+            return Err(StatusCode::SERVICE_UNAVAILABLE);
+        }
+        None => {}
+    }
 
     let event: serde_json::Value = serde_json::from_str(&body)
         .map_err(|_| StatusCode::BAD_REQUEST)?;
@@ -1461,6 +2231,25 @@ pub async fn stripe_webhook(
 
     match event_type {
         "checkout.session.completed" => {
+            let session = &event["data"]["object"];
+            let session_id = session["id"].as_str();
+
+            // Budget top-up of a cpm campaign
+            if let Some(top_up_id) = session["metadata"]["top_up_id"].as_str().and_then(|id| Uuid::parse_str(id).ok()) {
+                crate::ad_billing::credit_top_up(&state.pool, top_up_id, session_id)
+                    .await
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                return Ok(StatusCode::OK);
+            }
+
+            // Boosted story
+            if let Some(boost_id) = session["metadata"]["boost_id"].as_str().and_then(|id| Uuid::parse_str(id).ok()) {
+                crate::story_boosts::activate(&state.pool, boost_id, session_id)
+                    .await
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                return Ok(StatusCode::OK);
+            }
+
             // Extract ad_id from metadata
             if let Some(ad_id_str) = event["data"]["object"]["metadata"]["ad_id"].as_str() {
                 if let Ok(ad_id) = Uuid::parse_str(ad_id_str) {
@@ -1476,6 +2265,12 @@ pub async fn stripe_webhook(
                     .execute(state.pool.as_ref())
                     .await
                     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                    crate::ad_billing::credit_pending_top_ups(&state.pool, ad_id, session_id)
+                        .await
+                        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                    crate::ad_review::record_submission(&state.pool, ad_id)
+                        .await
+                        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
                     println!("✅ Ad {} payment confirmed, moved to pending_approval", ad_id);
                 }
@@ -1490,66 +2285,66 @@ pub async fn stripe_webhook(
 }
 
 // Admin approval endpoint
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/ads/{ad_id}/approve",
+    tag = "ads",
+    params(("ad_id" = Uuid, Path, description = "Ad ID")),
+    request_body(content = Option<crate::ad_review::ApproveAdRequest>),
+    responses(
+        (status = 200, body = crate::ad_review::AdRevision),
+        (status = 409, description = "Ad isn't waiting for review"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn approve_ad(
     State(state): State<Arc<crate::AppState>>,
-    _admin: AdminUser,
+    admin: Permitted<CanManageAds>,
     Path(ad_id): Path<Uuid>,
-) -> Result<StatusCode, (StatusCode, String)> {
-    // Update ad status to active
-    sqlx::query!(
-        "UPDATE advertisements SET status = 'active', start_date = NOW() WHERE id = $1",
-        ad_id
-    )
-    .execute(&*state.pool)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    // Log admin action
-    sqlx::query!(
-        "INSERT INTO admin_logs (admin_id, action, target_resource_type, target_resource_id) VALUES ($1, 'approve_ad', 'advertisement', $2)",
-        _admin.0.id,
-        ad_id
-    )
-    .execute(&*state.pool)
-    .await
-    .ok();
-
-    Ok(StatusCode::OK)
+    input: Option<Json<crate::ad_review::ApproveAdRequest>>,
+) -> Result<Json<crate::ad_review::AdRevision>, (StatusCode, String)> {
+    let notes = input.and_then(|Json(input)| input.notes);
+    crate::ad_review::decide(&state, &admin.0, ad_id, true, None, notes.as_deref())
+        .await
+        .map(Json)
 }
 
 // Admin rejection endpoint
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/ads/{ad_id}/reject",
+    tag = "ads",
+    params(("ad_id" = Uuid, Path, description = "Ad ID")),
+    request_body = crate::ad_review::RejectAdRequest,
+    responses(
+        (status = 200, body = crate::ad_review::AdRevision),
+        (status = 400, description = "Missing rejection reason"),
+        (status = 409, description = "Ad isn't waiting for review"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn reject_ad(
     State(state): State<Arc<crate::AppState>>,
-    _admin: AdminUser,
+    admin: Permitted<CanManageAds>,
     Path(ad_id): Path<Uuid>,
-) -> Result<StatusCode, (StatusCode, String)> {
-    // Update ad status to rejected
-    sqlx::query!(
-        "UPDATE advertisements SET status = 'rejected' WHERE id = $1",
-        ad_id
-    )
-    .execute(&*state.pool)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    // Log admin action
-    sqlx::query!(
-        "INSERT INTO admin_logs (admin_id, action, target_resource_type, target_resource_id) VALUES ($1, 'reject_ad', 'advertisement', $2)",
-        _admin.0.id,
-        ad_id
-    )
-    .execute(&*state.pool)
-    .await
-    .ok();
-
-    Ok(StatusCode::OK)
+    Json(input): Json<crate::ad_review::RejectAdRequest>,
+) -> Result<Json<crate::ad_review::AdRevision>, (StatusCode, String)> {
+    let reason = input.reason.trim();
+    if reason.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Give the advertiser a reason for the rejection".to_string()));
+    }
+    crate::ad_review::decide(&state, &admin.0, ad_id, false, Some(reason), input.notes.as_deref())
+        .await
+        .map(Json)
 }
 
 // ============================================================================
 // AD ANALYTICS ENDPOINTS
 // ============================================================================
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct AdLocationAnalytics {
     country: String,
     city: Option<String>,
@@ -1559,9 +2354,17 @@ pub struct AdLocationAnalytics {
 }
 
 // Get ad performance by location
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/ads/{ad_id}/analytics/location",
+    tag = "ads",
+    params(("ad_id" = Uuid, Path, description = "Ad ID")),
+    responses((status = 200, body = [AdLocationAnalytics]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
 pub async fn get_ad_location_analytics(
     State(state): State<Arc<crate::AppState>>,
-    _admin: AdminUser,
+    _admin: Permitted<CanManageAds>,
     Path(ad_id): Path<Uuid>,
 ) -> Result<Json<Vec<AdLocationAnalytics>>, (StatusCode, String)> {
     let analytics = sqlx::query_as!(
@@ -1580,14 +2383,14 @@ pub async fn get_ad_location_analytics(
         "#,
         ad_id
     )
-    .fetch_all(&*state.pool)
+    .fetch_all(state.read_pool())
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     Ok(Json(analytics))
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct AdDemographicsAnalytics {
     device_type: Option<String>,
     age_range: Option<String>,
@@ -1598,9 +2401,17 @@ pub struct AdDemographicsAnalytics {
 }
 
 // Get ad performance by demographics
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/ads/{ad_id}/analytics/demographics",
+    tag = "ads",
+    params(("ad_id" = Uuid, Path, description = "Ad ID")),
+    responses((status = 200, body = [AdDemographicsAnalytics]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
 pub async fn get_ad_demographics_analytics(
     State(state): State<Arc<crate::AppState>>,
-    _admin: AdminUser,
+    _admin: Permitted<CanManageAds>,
     Path(ad_id): Path<Uuid>,
 ) -> Result<Json<Vec<AdDemographicsAnalytics>>, (StatusCode, String)> {
     let analytics = sqlx::query_as!(
@@ -1624,7 +2435,7 @@ pub async fn get_ad_demographics_analytics(
         "#,
         ad_id
     )
-    .fetch_all(&*state.pool)
+    .fetch_all(state.read_pool())
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
@@ -0,0 +1,39 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+// Age-appropriate content rules, backed by the SQL helpers in migration 032:
+// - stories and ads marked is_mature only reach viewers whose birthdate makes
+//   them 18+ (feed queries filter with user_is_adult)
+// - adults can't start chats with minors who don't follow them, and minors
+//   aren't suggested to them in discovery (minor_contact_restricted)
+
+/// Parse a multipart/form flag such as `is_mature`
+pub fn parse_flag(value: &str) -> bool {
+    matches!(value.trim().to_lowercase().as_str(), "true" | "1" | "yes" | "on")
+}
+
+/// Reject a new chat if the creator may not contact any of its members
+pub async fn check_chat_members(pool: &PgPool, creator_id: Uuid, member_ids: &[Uuid]) -> axum::response::Result<()> {
+    let restricted: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT m FROM UNNEST($2::uuid[]) AS m WHERE m <> $1 AND minor_contact_restricted($1, m)"
+    )
+    .bind(creator_id)
+    .bind(member_ids)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        eprintln!("❌ Age gate check failed: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if !restricted.is_empty() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "You can't start a chat with a minor who doesn't follow you".to_string(),
+        )
+            .into());
+    }
+
+    Ok(())
+}
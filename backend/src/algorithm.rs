@@ -1,14 +1,17 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     Json,
 };
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 use std::sync::Arc;
 use crate::AppState;
+use crate::admin::ServiceCaller;
 use chrono::Utc;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct FeedQuery {
     #[serde(default = "default_limit")]
     pub limit: i64,
@@ -20,7 +23,7 @@ fn default_limit() -> i64 {
     20
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct PersonalizedStory {
     pub id: String,
     pub user_id: String,
@@ -28,97 +31,194 @@ pub struct PersonalizedStory {
     pub display_name: Option<String>,
     pub avatar_url: Option<String>,
     pub media_url: String,
+    pub playback_url: Option<String>,
     pub media_type: String,
     pub caption: Option<String>,
+    pub alt_text: Option<String>,
+    pub alt_text_generated: bool,
+    /// 18+ content, only shown to adult viewers
+    pub is_mature: bool,
     pub created_at: String,
+    pub expires_at: String,
     pub view_count: Option<i32>,
     pub like_count: Option<i32>,
     pub comment_count: Option<i32>,
     pub has_viewed: bool,
     pub has_liked: bool,
     pub score: f64,
+    /// Accepted co-author, shown next to the author
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub co_author_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub co_author_username: Option<String>,
+    /// Every item of a carousel story in order; empty for single-media stories
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub media_items: Vec<crate::story_media::StoryMediaItem>,
 }
 
-#[derive(Deserialize)]
+#[derive(sqlx::FromRow)]
+struct FeedStoryRow {
+    id: uuid::Uuid,
+    user_id: uuid::Uuid,
+    username: String,
+    display_name: Option<String>,
+    avatar_url: Option<String>,
+    media_url: String,
+    playback_url: Option<String>,
+    media_type: String,
+    caption: Option<String>,
+    alt_text: Option<String>,
+    alt_text_generated: bool,
+    is_mature: bool,
+    created_at: chrono::NaiveDateTime,
+    expires_at: chrono::NaiveDateTime,
+    view_count: Option<i32>,
+    like_count: Option<i32>,
+    comment_count: Option<i32>,
+    has_viewed: bool,
+    has_liked: bool,
+    score: f64,
+    co_author_id: Option<uuid::Uuid>,
+    co_author_username: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
 pub struct RecordInteractionRequest {
     pub interaction_type: String, // 'view', 'like', 'comment', 'skip'
     pub duration_seconds: Option<i32>,
 }
 
+// Stories for the personalized feed, best scored first
+const PERSONALIZED_FEED_SQL: &str = r#"
+    SELECT 
+        s.id,
+        s.user_id,
+        u.username,
+        u.display_name,
+        u.avatar_url,
+        s.media_url,
+        s.playback_url,
+        s.media_type,
+        s.caption,
+        s.alt_text,
+        s.alt_text_generated,
+        s.is_mature,
+        s.created_at,
+        s.expires_at,
+        s.view_count,
+        CASE WHEN s.hide_like_count AND s.user_id <> $1 AND sc.user_id IS DISTINCT FROM $1
+             THEN NULL ELSE s.like_count END AS like_count,
+        s.comment_count,
+        EXISTS(SELECT 1 FROM story_views WHERE story_id = s.id AND viewer_id = $1) as has_viewed,
+        EXISTS(SELECT 1 FROM story_likes WHERE story_id = s.id AND user_id = $1) as has_liked,
+        CAST(COALESCE(fs.score, 0.0) AS DOUBLE PRECISION) as score,
+        sc.user_id AS co_author_id,
+        cu.username AS co_author_username
+    FROM stories s
+    JOIN users u ON s.user_id = u.id
+    LEFT JOIN story_collaborators sc ON sc.story_id = s.id AND sc.status = 'accepted'
+    LEFT JOIN users cu ON cu.id = sc.user_id
+    LEFT JOIN feed_scores fs ON s.id = fs.story_id AND fs.user_id = $1
+    WHERE s.expires_at > NOW()
+      AND (NOT s.is_permanent OR s.created_at > NOW() - make_interval(hours => $5))
+      AND (NOT s.is_mature OR s.user_id = $1 OR user_is_adult($1))
+      AND NOT geo_blocked('story', s.id, $4)
+    ORDER BY fs.score DESC NULLS LAST, s.created_at DESC
+    LIMIT $2 OFFSET $3
+"#;
+
 // Get personalized feed using algorithm
+#[utoipa::path(
+    get,
+    path = "/api/v1/feed/personalized/{user_id}",
+    tag = "feed",
+    params(("user_id" = String, Path, description = "User ID"), FeedQuery),
+    responses((status = 200, body = [PersonalizedStory]))
+)]
 pub async fn get_personalized_feed(
     State(state): State<Arc<AppState>>,
     Path(user_id): Path<String>,
     Query(params): Query<FeedQuery>,
+    headers: HeaderMap,
 ) -> Result<Json<Vec<PersonalizedStory>>, StatusCode> {
+    let country = crate::takedowns::request_country(&headers);
     let user_uuid = uuid::Uuid::parse_str(&user_id)
         .map_err(|_| StatusCode::BAD_REQUEST)?;
 
     let limit = params.limit.min(50);
     let offset = params.offset;
 
-    // Calculate feed scores if not cached
-    let _ = calculate_feed_scores(state.clone(), user_uuid).await;
+    // Calculate feed scores if not cached. Scores written just now may not
+    // have reached the read replica yet, so that read goes to the primary.
+    let recalculated = calculate_feed_scores(state.clone(), user_uuid).await.unwrap_or(false);
+    let pool = if recalculated { state.pool.as_ref() } else { state.read_pool() };
 
     // Get stories ordered by score
-    let stories = sqlx::query!(
-        r#"
-        SELECT 
-            s.id,
-            s.user_id,
-            u.username,
-            u.display_name,
-            u.avatar_url,
-            s.media_url,
-            s.media_type,
-            s.caption,
-            s.created_at,
-            s.view_count,
-            s.like_count,
-            s.comment_count,
-            EXISTS(SELECT 1 FROM story_views WHERE story_id = s.id AND viewer_id = $1) as "has_viewed!",
-            EXISTS(SELECT 1 FROM story_likes WHERE story_id = s.id AND user_id = $1) as "has_liked!",
-            CAST(COALESCE(fs.score, 0.0) AS DOUBLE PRECISION) as "score!"
-        FROM stories s
-        JOIN users u ON s.user_id = u.id
-        LEFT JOIN feed_scores fs ON s.id = fs.story_id AND fs.user_id = $1
-        WHERE s.created_at > NOW() - INTERVAL '7 days'
-        ORDER BY fs.score DESC NULLS LAST, s.created_at DESC
-        LIMIT $2 OFFSET $3
-        "#,
-        user_uuid,
-        limit,
-        offset
+    let stories = crate::query_metrics::observe_statement(
+        "feed.personalized",
+        PERSONALIZED_FEED_SQL,
+        sqlx::query_as::<_, FeedStoryRow>(PERSONALIZED_FEED_SQL)
+            .bind(user_uuid)
+            .bind(limit)
+            .bind(offset)
+            .bind(&country)
+            .bind(crate::expiration::default_story_ttl_hours() as i32)
+            .fetch_all(pool),
     )
-    .fetch_all(&*state.pool)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let results = stories
+    let story_ids: Vec<uuid::Uuid> = stories.iter().map(|s| s.id).collect();
+    let mut items = crate::story_media::load(pool, &story_ids).await;
+
+    let mut results: Vec<PersonalizedStory> = stories
         .into_iter()
         .map(|s| PersonalizedStory {
+            media_items: items.remove(&s.id).unwrap_or_default(),
             id: s.id.to_string(),
             user_id: s.user_id.to_string(),
             username: s.username,
             display_name: s.display_name,
             avatar_url: s.avatar_url,
             media_url: s.media_url,
+            playback_url: s.playback_url,
             media_type: s.media_type,
             caption: s.caption,
-            created_at: s.created_at.and_utc().to_rfc3339(),
+            alt_text: s.alt_text,
+            alt_text_generated: s.alt_text_generated,
+            is_mature: s.is_mature,
+            created_at: crate::timestamps::format(s.created_at),
+            expires_at: crate::timestamps::format(s.expires_at),
             view_count: s.view_count,
             like_count: s.like_count,
             comment_count: s.comment_count,
             has_viewed: s.has_viewed,
             has_liked: s.has_liked,
-            score: s.score as f64,
+            score: s.score,
+            co_author_id: s.co_author_id.map(|id| id.to_string()),
+            co_author_username: s.co_author_username,
         })
         .collect();
 
+    for story in &mut results {
+        state.media_service.sign_in_place(&mut story.media_url).await;
+        for item in &mut story.media_items {
+            state.media_service.sign_in_place(&mut item.media_url).await;
+        }
+    }
+
     Ok(Json(results))
 }
 
 // Record user interaction for algorithm learning
+#[utoipa::path(
+    post,
+    path = "/api/v1/feed/interaction/{user_id}/{story_id}",
+    tag = "feed",
+    params(("user_id" = String, Path, description = "User ID"), ("story_id" = String, Path, description = "Story ID")),
+    request_body = RecordInteractionRequest,
+    responses((status = 200, description = "Success"))
+)]
 pub async fn record_interaction(
     State(state): State<Arc<AppState>>,
     Path((user_id, story_id)): Path<(String, String)>,
@@ -156,10 +256,11 @@ pub async fn record_interaction(
 }
 
 // Calculate feed scores for a user (internal function)
+/// Returns whether the scores were recalculated
 async fn calculate_feed_scores(
     state: Arc<AppState>,
     user_id: uuid::Uuid,
-) -> Result<(), sqlx::Error> {
+) -> Result<bool, sqlx::Error> {
     // Check if scores need recalculation (older than 1 hour)
     let needs_update = sqlx::query!(
         "SELECT COUNT(*) as count FROM feed_scores WHERE user_id = $1 AND calculated_at > NOW() - INTERVAL '1 hour'",
@@ -169,7 +270,7 @@ async fn calculate_feed_scores(
     .await?;
 
     if needs_update.count.unwrap_or(0) > 0 {
-        return Ok(()); // Scores are fresh
+        return Ok(false); // Scores are fresh
     }
 
     // Get user's following list
@@ -183,8 +284,10 @@ async fn calculate_feed_scores(
     let _following_ids: Vec<uuid::Uuid> = following.iter().map(|f| f.following_id).collect();
 
     // Get recent stories
-    let stories = sqlx::query!(
-        r#"
+    let stories = crate::query_metrics::observe(
+        "feed.scores.candidates",
+        sqlx::query!(
+            r#"
         SELECT 
             s.id,
             s.user_id,
@@ -196,24 +299,30 @@ async fn calculate_feed_scores(
         FROM stories s
         WHERE s.created_at > NOW() - INTERVAL '7 days'
         "#,
-        user_id
+            user_id
+        )
+        .fetch_all(&*state.pool),
     )
-    .fetch_all(&*state.pool)
     .await?;
 
+    // Weights admins can tune at runtime
+    let recency_points = crate::runtime_settings::float(crate::runtime_settings::FEED_RECENCY_POINTS);
+    let recency_decay_hours = crate::runtime_settings::float(crate::runtime_settings::FEED_RECENCY_DECAY_HOURS);
+    let following_bonus = crate::runtime_settings::float(crate::runtime_settings::FEED_FOLLOWING_BONUS);
+
     // Calculate scores for each story
     for story in stories {
         let mut score = 0.0;
 
-        // Recency score (0-10 points, newer = higher)
+        // Recency score (recency_points for a new story, decaying linearly to 0)
         let age_seconds = (Utc::now().timestamp() - story.created_at.and_utc().timestamp()) as f64;
         let age_hours = age_seconds / 3600.0;
-        let recency_score = (10.0_f64 - (age_hours / 16.8)).max(0.0); // Decay over 7 days
+        let recency_score = (recency_points * (1.0 - age_hours / recency_decay_hours)).max(0.0);
         score += recency_score;
 
-        // Following relationship (20 points if following)
+        // Following relationship
         if story.is_following {
-            score += 20.0;
+            score += following_bonus;
         }
 
         // Engagement score (likes, comments, views)
@@ -230,8 +339,10 @@ async fn calculate_feed_scores(
         score += (comments * 1.0).min(10.0); // Up to 10 points for comments
 
         // User's past interactions with this creator
-        let past_interactions = sqlx::query!(
-            r#"
+        let past_interactions = crate::query_metrics::observe(
+            "feed.scores.interactions",
+            sqlx::query!(
+                r#"
             SELECT interaction_type, COUNT(*) as count
             FROM user_interactions
             WHERE user_id = $1 AND story_id IN (
@@ -239,10 +350,11 @@ async fn calculate_feed_scores(
             )
             GROUP BY interaction_type
             "#,
-            user_id,
-            story.user_id
+                user_id,
+                story.user_id
+            )
+            .fetch_all(&*state.pool),
         )
-        .fetch_all(&*state.pool)
         .await?;
 
         for interaction in past_interactions {
@@ -271,21 +383,34 @@ async fn calculate_feed_scores(
         .await?;
     }
 
-    Ok(())
+    Ok(true)
 }
 
 // Background job to recalculate all feed scores (call via cron)
+#[utoipa::path(
+    post,
+    path = "/api/v1/feed/recalculate",
+    tag = "feed",
+    responses((status = 202, body = serde_json::Value), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []), ("service_token" = []))
+)]
 pub async fn recalculate_all_feeds(
+    caller: ServiceCaller,
     State(state): State<Arc<AppState>>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, String)> {
+    crate::jobs::enqueue_trigger(&state, crate::jobs::RECALCULATE_FEEDS, &caller).await
+}
+
+// Recalculate feed scores for every user (run by the job worker)
+pub async fn recalculate_feeds_for_all_users(state: Arc<AppState>) -> Result<usize, sqlx::Error> {
     let users = sqlx::query!("SELECT id FROM users")
         .fetch_all(&*state.pool)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .await?;
 
+    let count = users.len();
     for user in users {
         let _ = calculate_feed_scores(state.clone(), user.id).await;
     }
 
-    Ok(StatusCode::OK)
+    Ok(count)
 }
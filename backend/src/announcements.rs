@@ -0,0 +1,563 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::permissions::{CanManagePlatform, Permitted};
+use crate::websocket::{send_to_user, Connections, WsMessage};
+use crate::AppState;
+
+// Broadcast system notices. Admins create an announcement with an audience and
+// an optional schedule; a DELIVER_ANNOUNCEMENT job fans it out into the
+// notifications table at starts_at and pushes it to connected clients.
+// Translations of the title and body are shown to users whose locale has one.
+
+const MAX_TITLE_LENGTH: usize = 100;
+const MAX_BODY_LENGTH: usize = 2000;
+
+const ANNOUNCEMENT_COLUMNS: &str = "a.id, a.title, a.body, a.translations, a.link_url, a.audience, a.starts_at, a.expires_at, a.status, \
+     a.recipient_count, a.created_by, a.created_at, a.delivered_at, \
+     CASE WHEN a.status = 'delivered' \
+          THEN (SELECT COUNT(*) FROM notifications n WHERE n.announcement_id = a.id AND n.is_read)::int \
+          ELSE a.read_count END AS read_count";
+
+/// Who receives an announcement. Every filter that is set must match; an
+/// empty audience means all users (bots never receive announcements).
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct AnnouncementAudience {
+    /// Only users with one of these roles (see /admin/roles)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+    /// Only these users
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub user_ids: Vec<Uuid>,
+    /// Only accounts created at or after this time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(with = "crate::timestamps::option")]
+    pub joined_after: Option<NaiveDateTime>,
+    /// Only accounts created before this time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(with = "crate::timestamps::option")]
+    pub joined_before: Option<NaiveDateTime>,
+}
+
+/// Title and body in one language
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AnnouncementText {
+    pub title: String,
+    pub body: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct Announcement {
+    pub id: Uuid,
+    pub title: String,
+    pub body: String,
+    /// By locale
+    #[schema(value_type = BTreeMap<String, AnnouncementText>)]
+    pub translations: sqlx::types::Json<BTreeMap<String, AnnouncementText>>,
+    pub link_url: Option<String>,
+    #[schema(value_type = AnnouncementAudience)]
+    pub audience: sqlx::types::Json<AnnouncementAudience>,
+    #[serde(with = "crate::timestamps")]
+    pub starts_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps::option")]
+    pub expires_at: Option<NaiveDateTime>,
+    /// scheduled, delivered, expired or cancelled
+    pub status: String,
+    pub recipient_count: i32,
+    pub read_count: i32,
+    pub created_by: Option<Uuid>,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps::option")]
+    pub delivered_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateAnnouncementRequest {
+    pub title: String,
+    pub body: String,
+    /// Title and body in other languages, by locale (es, fr, ...)
+    #[serde(default)]
+    pub translations: BTreeMap<String, AnnouncementText>,
+    pub link_url: Option<String>,
+    #[serde(default)]
+    pub audience: AnnouncementAudience,
+    /// Deliver at this time instead of right away
+    #[serde(default, with = "crate::timestamps::option")]
+    pub starts_at: Option<NaiveDateTime>,
+    /// Withdraw the announcement from inboxes at this time
+    #[serde(default, with = "crate::timestamps::option")]
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+/// An announcement as its recipient sees it
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct UserAnnouncement {
+    pub id: Uuid,
+    pub notification_id: Uuid,
+    pub title: String,
+    pub body: String,
+    pub link_url: Option<String>,
+    #[serde(with = "crate::timestamps")]
+    pub starts_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps::option")]
+    pub expires_at: Option<NaiveDateTime>,
+    pub is_read: bool,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AnnouncementsQuery {
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AnnouncementsResponse {
+    announcements: Vec<Announcement>,
+    total: i64,
+    page: i64,
+    per_page: i64,
+}
+
+async fn fetch_announcement(pool: &PgPool, id: Uuid) -> Result<Option<Announcement>, sqlx::Error> {
+    sqlx::query_as::<_, Announcement>(&format!(
+        "SELECT {} FROM announcements a WHERE a.id = $1",
+        ANNOUNCEMENT_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+fn validate_text(title: &str, body: &str) -> Result<(), String> {
+    let title = title.trim();
+    if title.is_empty() || title.chars().count() > MAX_TITLE_LENGTH {
+        return Err(format!("Title must be 1-{} characters", MAX_TITLE_LENGTH));
+    }
+    let body = body.trim();
+    if body.is_empty() || body.chars().count() > MAX_BODY_LENGTH {
+        return Err(format!("Body must be 1-{} characters", MAX_BODY_LENGTH));
+    }
+    Ok(())
+}
+
+fn validate(payload: &CreateAnnouncementRequest, now: NaiveDateTime) -> Result<(), String> {
+    validate_text(&payload.title, &payload.body)?;
+    for (locale, text) in &payload.translations {
+        if locale == crate::i18n::DEFAULT_LOCALE || crate::i18n::supported(locale) != Some(locale.as_str()) {
+            return Err(format!(
+                "Translations must use one of: {}",
+                crate::i18n::available()
+                    .into_iter()
+                    .filter(|l| *l != crate::i18n::DEFAULT_LOCALE)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        validate_text(&text.title, &text.body).map_err(|e| format!("{} ({})", e, locale))?;
+    }
+    if let Some(link) = &payload.link_url {
+        if !link.starts_with("https://") && !link.starts_with('/') {
+            return Err("link_url must be an https URL or an in-app path".to_string());
+        }
+    }
+    let starts_at = payload.starts_at.unwrap_or(now);
+    if payload.expires_at.is_some_and(|expires_at| expires_at <= starts_at.max(now)) {
+        return Err("expires_at must be after starts_at and in the future".to_string());
+    }
+    Ok(())
+}
+
+// ============= Delivery =============
+
+#[derive(sqlx::FromRow)]
+struct Delivered {
+    id: Uuid,
+    user_id: Uuid,
+    locale: Option<String>,
+}
+
+/// Job body: fan an announcement out to its audience. Safe to retry; users who
+/// already have it are skipped.
+pub async fn deliver(
+    pool: &PgPool,
+    connections: &Connections,
+    payload: &serde_json::Value,
+) -> Result<Option<serde_json::Value>, String> {
+    let announcement_id = payload
+        .get("announcement_id")
+        .and_then(|v| v.as_str())
+        .and_then(|v| Uuid::parse_str(v).ok())
+        .ok_or_else(|| "Missing announcement_id".to_string())?;
+
+    let Some(announcement) = fetch_announcement(pool, announcement_id).await.map_err(|e| e.to_string())? else {
+        return Ok(Some(serde_json::json!({ "skipped": "deleted" })));
+    };
+    if announcement.status == "cancelled" || announcement.status == "expired" {
+        return Ok(Some(serde_json::json!({ "skipped": announcement.status })));
+    }
+    if announcement.expires_at.is_some_and(|at| at <= Utc::now().naive_utc()) {
+        sqlx::query("UPDATE announcements SET status = 'expired' WHERE id = $1")
+            .bind(announcement_id)
+            .execute(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        return Ok(Some(serde_json::json!({ "skipped": "expired before delivery" })));
+    }
+
+    let audience = &announcement.audience.0;
+    let delivered = sqlx::query_as::<_, Delivered>(
+        r#"
+        WITH delivered AS (
+            INSERT INTO notifications (user_id, type, message, announcement_id)
+            SELECT u.id, 'announcement', $2, $1
+            FROM users u
+            WHERE NOT u.is_bot
+              AND (cardinality($3::text[]) = 0 OR u.role = ANY($3))
+              AND (cardinality($4::uuid[]) = 0 OR u.id = ANY($4))
+              AND ($5::timestamp IS NULL OR u.created_at >= $5)
+              AND ($6::timestamp IS NULL OR u.created_at < $6)
+            ON CONFLICT (announcement_id, user_id) WHERE announcement_id IS NOT NULL DO NOTHING
+            RETURNING id, user_id
+        )
+        SELECT d.id, d.user_id, u.locale
+        FROM delivered d
+        JOIN users u ON u.id = d.user_id
+        "#
+    )
+    .bind(announcement_id)
+    .bind(format!("{}: {}", announcement.title, announcement.body))
+    .bind(&audience.roles)
+    .bind(&audience.user_ids)
+    .bind(audience.joined_after)
+    .bind(audience.joined_before)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    sqlx::query(
+        r#"
+        UPDATE announcements
+        SET status = 'delivered',
+            delivered_at = COALESCE(delivered_at, NOW()),
+            recipient_count = (SELECT COUNT(*) FROM notifications WHERE announcement_id = $1)
+        WHERE id = $1 AND status = 'scheduled'
+        "#
+    )
+    .bind(announcement_id)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let expires_at = announcement.expires_at.map(crate::timestamps::format);
+    let mut pushed = 0;
+    for row in &delivered {
+        if connections.contains_key(&row.user_id) {
+            let text = row
+                .locale
+                .as_deref()
+                .and_then(crate::i18n::supported)
+                .and_then(|locale| announcement.translations.get(locale));
+            let event = WsMessage::Announcement {
+                announcement_id,
+                notification_id: row.id,
+                title: text.map_or(&announcement.title, |t| &t.title).clone(),
+                body: text.map_or(&announcement.body, |t| &t.body).clone(),
+                link_url: announcement.link_url.clone(),
+                expires_at: expires_at.clone(),
+            };
+            send_to_user(connections, row.user_id, &event);
+            pushed += 1;
+        }
+    }
+
+    Ok(Some(serde_json::json!({ "recipients": delivered.len(), "pushed": pushed })))
+}
+
+/// Withdraw announcements past their expiry, keeping their read counts
+pub async fn expire(pool: &PgPool) -> Result<(), sqlx::Error> {
+    let expired = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE announcements a
+        SET status = 'expired',
+            read_count = (SELECT COUNT(*) FROM notifications n WHERE n.announcement_id = a.id AND n.is_read)
+        WHERE a.status = 'delivered' AND a.expires_at <= NOW()
+        RETURNING a.id
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !expired.is_empty() {
+        sqlx::query("DELETE FROM notifications WHERE announcement_id = ANY($1)")
+            .bind(&expired)
+            .execute(pool)
+            .await?;
+        println!("📢 Expired {} announcements", expired.len());
+    }
+
+    Ok(())
+}
+
+// ============= Admin API =============
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/announcements",
+    tag = "admin",
+    request_body = CreateAnnouncementRequest,
+    responses(
+        (status = 201, body = Announcement),
+        (status = 400, description = "Invalid announcement"),
+        (status = 403, description = "Only admins can broadcast announcements"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn create_announcement(
+    admin: Permitted<CanManagePlatform>,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CreateAnnouncementRequest>,
+) -> Result<(StatusCode, Json<Announcement>), (StatusCode, String)> {
+    let now = Utc::now().naive_utc();
+    validate(&payload, now).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let unknown_role = sqlx::query_scalar::<_, String>(
+        "SELECT name FROM unnest($1::text[]) AS name WHERE name NOT IN (SELECT name FROM roles) LIMIT 1"
+    )
+    .bind(&payload.audience.roles)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if let Some(role) = unknown_role {
+        return Err((StatusCode::BAD_REQUEST, format!("Unknown role: {}", role)));
+    }
+
+    let starts_at = payload.starts_at.unwrap_or(now).max(now);
+    let translations: BTreeMap<&String, AnnouncementText> = payload
+        .translations
+        .iter()
+        .map(|(locale, text)| {
+            (locale, AnnouncementText { title: text.title.trim().to_string(), body: text.body.trim().to_string() })
+        })
+        .collect();
+    let id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO announcements (title, body, translations, link_url, audience, starts_at, expires_at, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING id
+        "#
+    )
+    .bind(payload.title.trim())
+    .bind(payload.body.trim())
+    .bind(sqlx::types::Json(translations))
+    .bind(&payload.link_url)
+    .bind(sqlx::types::Json(&payload.audience))
+    .bind(starts_at)
+    .bind(payload.expires_at)
+    .bind(admin.0.id)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    crate::jobs::schedule(
+        &state.pool,
+        crate::jobs::DELIVER_ANNOUNCEMENT,
+        serde_json::json!({ "announcement_id": id }),
+        starts_at,
+        Some(admin.0.id),
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "create_announcement".to_string(),
+        None,
+        Some("announcement".to_string()),
+        Some(id),
+        serde_json::json!({ "title": payload.title.trim(), "starts_at": starts_at }),
+    )
+    .await;
+
+    let announcement = fetch_announcement(&state.pool, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Announcement vanished".to_string()))?;
+
+    Ok((StatusCode::CREATED, Json(announcement)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/announcements",
+    tag = "admin",
+    params(AnnouncementsQuery),
+    responses((status = 200, body = AnnouncementsResponse), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_announcements(
+    _admin: Permitted<CanManagePlatform>,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<AnnouncementsQuery>,
+) -> Result<Json<AnnouncementsResponse>, StatusCode> {
+    let page = params.page.unwrap_or(1).max(1);
+    let per_page = params.per_page.unwrap_or(50).clamp(1, 100);
+    let offset = (page - 1) * per_page;
+
+    let announcements = sqlx::query_as::<_, Announcement>(&format!(
+        "SELECT {} FROM announcements a ORDER BY a.starts_at DESC LIMIT $1 OFFSET $2",
+        ANNOUNCEMENT_COLUMNS
+    ))
+    .bind(per_page)
+    .bind(offset)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let total = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM announcements")
+        .fetch_one(state.pool.as_ref())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(AnnouncementsResponse { announcements, total, page, per_page }))
+}
+
+// Cancel a scheduled announcement, or retract one already delivered
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/announcements/{announcement_id}",
+    tag = "admin",
+    params(("announcement_id" = Uuid, Path, description = "Announcement ID")),
+    responses(
+        (status = 204, description = "Cancelled"),
+        (status = 404, description = "Announcement not found or already over"),
+        (status = 403, description = "Only admins can cancel announcements"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn cancel_announcement(
+    admin: Permitted<CanManagePlatform>,
+    State(state): State<Arc<AppState>>,
+    Path(announcement_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let cancelled = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE announcements a
+        SET status = 'cancelled',
+            read_count = (SELECT COUNT(*) FROM notifications n WHERE n.announcement_id = a.id AND n.is_read)
+        WHERE a.id = $1 AND a.status IN ('scheduled', 'delivered')
+        RETURNING a.id
+        "#
+    )
+    .bind(announcement_id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if cancelled.is_none() {
+        return Err((StatusCode::NOT_FOUND, "Announcement not found or already over".to_string()));
+    }
+
+    sqlx::query("DELETE FROM notifications WHERE announcement_id = $1")
+        .bind(announcement_id)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "cancel_announcement".to_string(),
+        None,
+        Some("announcement".to_string()),
+        Some(announcement_id),
+        serde_json::json!({}),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ============= User API =============
+
+// Live announcements for the caller, newest first (for banners / an inbox), in
+// their language when the announcement has a translation for it
+#[utoipa::path(
+    get,
+    path = "/api/v1/announcements",
+    tag = "notifications",
+    responses((status = 200, body = [UserAnnouncement]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_my_announcements(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<UserAnnouncement>>, StatusCode> {
+    let locale = crate::i18n::user_locale(&state.pool, user.id).await;
+    let announcements = sqlx::query_as::<_, UserAnnouncement>(
+        r#"
+        SELECT a.id, n.id AS notification_id,
+               COALESCE(a.translations -> $2 ->> 'title', a.title) AS title,
+               COALESCE(a.translations -> $2 ->> 'body', a.body) AS body,
+               a.link_url, a.starts_at, a.expires_at,
+               COALESCE(n.is_read, FALSE) AS is_read
+        FROM notifications n
+        JOIN announcements a ON a.id = n.announcement_id
+        WHERE n.user_id = $1
+          AND a.status = 'delivered'
+          AND (a.expires_at IS NULL OR a.expires_at > NOW())
+        ORDER BY a.starts_at DESC
+        LIMIT 50
+        "#
+    )
+    .bind(user.id)
+    .bind(locale)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(announcements))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/announcements/{announcement_id}/read",
+    tag = "notifications",
+    params(("announcement_id" = Uuid, Path, description = "Announcement ID")),
+    responses(
+        (status = 204, description = "Marked as read"),
+        (status = 404, description = "Announcement not found"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn mark_announcement_read(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(announcement_id): Path<Uuid>,
+) -> Result<StatusCode, StatusCode> {
+    let result = sqlx::query("UPDATE notifications SET is_read = TRUE WHERE announcement_id = $1 AND user_id = $2")
+        .bind(announcement_id)
+        .bind(user.id)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
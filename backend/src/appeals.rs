@@ -0,0 +1,432 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::admin::{AdminUser, MaybeBannedUser};
+use crate::permissions::{role_has, CanBan, CanModerateContent, Permission};
+use crate::AppState;
+
+// Appeals (migration 080). A user sees what they can appeal - a ban on their
+// account, a takedown of their story or comment, or a moderation flag acted
+// on against their text - and appeals each one once with a short
+// explanation. Banned users reach these endpoints through MaybeBannedUser,
+// since everything else turns them away. Staff decide an appeal in the
+// admin panel: upholding keeps the sanction, overturning lifts the ban or
+// takedown or dismisses the flag. Either way the user is emailed the
+// decision. Bans are decided by staff with can_ban, content by staff with
+// can_moderate_content; takedowns can only be overturned by admins, as with
+// lifting them directly.
+
+const KINDS: [&str; 3] = ["ban", "takedown", "moderation_flag"];
+const MAX_BODY_CHARS: usize = 2000;
+const MAX_NOTE_CHARS: usize = 2000;
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct Sanction {
+    /// ban, takedown or moderation_flag
+    pub kind: String,
+    /// Appeal it with this id
+    pub subject_id: Uuid,
+    /// Ban reason or the takedown's legal basis; none for flags
+    pub reason: Option<String>,
+    /// story or comment for takedowns; what the filter caught for flags
+    pub content_type: Option<String>,
+    pub content_id: Option<Uuid>,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct Appeal {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub kind: String,
+    pub subject_id: Uuid,
+    pub body: String,
+    /// pending, upheld or overturned
+    pub status: String,
+    pub decision_note: Option<String>,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps::option")]
+    pub decided_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AppealsOverview {
+    /// Sanctions not appealed yet
+    pub appealable: Vec<Sanction>,
+    /// The caller's appeals, newest first
+    pub appeals: Vec<Appeal>,
+}
+
+/// An appeal as staff see it, with who filed it and what it's against
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct AppealForReview {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub username: String,
+    pub kind: String,
+    pub subject_id: Uuid,
+    /// The sanction's reason, while it's still in place
+    pub reason: Option<String>,
+    pub content_type: Option<String>,
+    pub content_id: Option<Uuid>,
+    pub body: String,
+    pub status: String,
+    pub decided_by: Option<Uuid>,
+    pub decision_note: Option<String>,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps::option")]
+    pub decided_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SubmitAppealRequest {
+    /// ban, takedown or moderation_flag
+    pub kind: String,
+    pub subject_id: Uuid,
+    /// Why the decision should be reversed
+    pub body: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DecideAppealRequest {
+    /// upheld or overturned
+    pub decision: String,
+    /// Emailed to the user; required when upholding
+    pub note: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AppealsQuery {
+    /// pending (default), upheld or overturned
+    pub status: Option<String>,
+    pub kind: Option<String>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    eprintln!("❌ Appeals query failed: {:?}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+}
+
+// What the appeal is against, for the email
+fn describe(kind: &str, content_type: Option<&str>) -> String {
+    let content = match content_type {
+        Some("story") => "story",
+        Some("comment") => "comment",
+        Some("caption") => "caption",
+        Some("group_name") => "group name",
+        Some("live_title") => "live stream title",
+        Some("live_chat") => "live chat message",
+        _ => "post",
+    };
+    match kind {
+        "ban" => "the ban on your account".to_string(),
+        "takedown" => format!("the takedown of your {}", content),
+        _ => format!("the removal of your {}", content),
+    }
+}
+
+fn outcome(kind: &str) -> &'static str {
+    match kind {
+        "ban" => "You can sign in and use your account again.",
+        "takedown" => "It's visible again everywhere.",
+        _ => "It no longer counts against your account.",
+    }
+}
+
+// Email the user the decision; failures are logged, the decision stands
+async fn send_decision(pool: &PgPool, appeal: &Appeal, content_type: Option<&str>) {
+    let recipient = sqlx::query_as::<_, (String, String)>("SELECT username, email FROM users WHERE id = $1")
+        .bind(appeal.user_id)
+        .fetch_optional(pool)
+        .await;
+    let (username, email) = match recipient {
+        Ok(Some(recipient)) => recipient,
+        Ok(None) => return,
+        Err(e) => {
+            eprintln!("❌ Failed to look up who filed appeal {}: {:?}", appeal.id, e);
+            return;
+        }
+    };
+    // Placeholder addresses of phone and bot accounts can't receive mail
+    if email.ends_with(".invalid") || !crate::mailer::is_configured() {
+        return;
+    }
+
+    let template = if appeal.status == "overturned" { "appeal_overturned" } else { "appeal_upheld" };
+    let args = serde_json::json!({
+        "username": username,
+        "subject": describe(&appeal.kind, content_type),
+        "note": appeal.decision_note.as_deref().unwrap_or_default(),
+        "outcome": outcome(&appeal.kind),
+    });
+    if let Err(e) = crate::mailer::queue(pool, &[email], template, &args).await {
+        eprintln!("❌ Failed to email the decision on appeal {}: {}", appeal.id, e);
+    }
+}
+
+// What the caller can appeal, and how their appeals went. Works while banned.
+#[utoipa::path(
+    get,
+    path = "/api/v1/appeals",
+    tag = "appeals",
+    responses((status = 200, body = AppealsOverview), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_my_appeals(
+    caller: MaybeBannedUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<AppealsOverview>, (StatusCode, String)> {
+    let appealable = sqlx::query_as::<_, Sanction>(
+        r#"
+        SELECT s.kind, s.subject_id, s.reason, s.content_type, s.content_id, s.created_at
+        FROM appealable_sanctions s
+        WHERE s.user_id = $1
+          AND NOT EXISTS(SELECT 1 FROM appeals a WHERE a.kind = s.kind AND a.subject_id = s.subject_id)
+        ORDER BY s.created_at DESC
+        "#
+    )
+    .bind(caller.user.id)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    let appeals = sqlx::query_as::<_, Appeal>(
+        r#"
+        SELECT id, user_id, kind, subject_id, body, status, decision_note, created_at, decided_at
+        FROM appeals
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        "#
+    )
+    .bind(caller.user.id)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(AppealsOverview { appealable, appeals }))
+}
+
+// Appeal a sanction; each one can be appealed once
+#[utoipa::path(
+    post,
+    path = "/api/v1/appeals",
+    tag = "appeals",
+    request_body = SubmitAppealRequest,
+    responses(
+        (status = 201, body = Appeal),
+        (status = 400, description = "Unknown kind, or the explanation is empty or too long"),
+        (status = 404, description = "No such sanction against the caller"),
+        (status = 409, description = "Already appealed"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn submit_appeal(
+    caller: MaybeBannedUser,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<SubmitAppealRequest>,
+) -> Result<(StatusCode, Json<Appeal>), (StatusCode, String)> {
+    if !KINDS.contains(&payload.kind.as_str()) {
+        return Err((StatusCode::BAD_REQUEST, format!("kind must be one of: {}", KINDS.join(", "))));
+    }
+    let body = payload.body.trim();
+    if body.is_empty() || body.chars().count() > MAX_BODY_CHARS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Appeals need an explanation of at most {} characters", MAX_BODY_CHARS),
+        ));
+    }
+
+    let appealable = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM appealable_sanctions WHERE kind = $1 AND subject_id = $2 AND user_id = $3)"
+    )
+    .bind(&payload.kind)
+    .bind(payload.subject_id)
+    .bind(caller.user.id)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+    if !appealable {
+        return Err((StatusCode::NOT_FOUND, "Nothing to appeal".to_string()));
+    }
+
+    let appeal = sqlx::query_as::<_, Appeal>(
+        r#"
+        INSERT INTO appeals (user_id, kind, subject_id, body)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (kind, subject_id) DO NOTHING
+        RETURNING id, user_id, kind, subject_id, body, status, decision_note, created_at, decided_at
+        "#
+    )
+    .bind(caller.user.id)
+    .bind(&payload.kind)
+    .bind(payload.subject_id)
+    .bind(body)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::CONFLICT, "You've already appealed this".to_string()))?;
+
+    println!("📨 {} appealed {} {}", caller.user.username, appeal.kind, appeal.subject_id);
+    Ok((StatusCode::CREATED, Json(appeal)))
+}
+
+// Appeals for staff to decide, oldest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/appeals",
+    tag = "admin",
+    params(AppealsQuery),
+    responses((status = 200, body = [AppealForReview]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_appeals(
+    _admin: AdminUser,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<AppealsQuery>,
+) -> Result<Json<Vec<AppealForReview>>, (StatusCode, String)> {
+    let page = params.page.unwrap_or(1).max(1);
+    let per_page = params.per_page.unwrap_or(50).clamp(1, 100);
+
+    let appeals = sqlx::query_as::<_, AppealForReview>(
+        r#"
+        SELECT a.id, a.user_id, u.username, a.kind, a.subject_id, s.reason, s.content_type, s.content_id,
+               a.body, a.status, a.decided_by, a.decision_note, a.created_at, a.decided_at
+        FROM appeals a
+        JOIN users u ON u.id = a.user_id
+        LEFT JOIN appealable_sanctions s ON s.kind = a.kind AND s.subject_id = a.subject_id
+        WHERE a.status = $1 AND ($2::text IS NULL OR a.kind = $2)
+        ORDER BY a.created_at
+        LIMIT $3 OFFSET $4
+        "#
+    )
+    .bind(params.status.as_deref().unwrap_or("pending"))
+    .bind(&params.kind)
+    .bind(per_page)
+    .bind((page - 1) * per_page)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(appeals))
+}
+
+// Uphold or overturn an appeal; the user is emailed the decision
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/appeals/{appeal_id}/decide",
+    tag = "admin",
+    params(("appeal_id" = Uuid, Path, description = "Appeal ID")),
+    request_body = DecideAppealRequest,
+    responses(
+        (status = 200, body = Appeal),
+        (status = 400, description = "Unknown decision, or upholding without a note"),
+        (status = 403, description = "Caller can't decide this kind of appeal"),
+        (status = 404, description = "Appeal not found or already decided"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn decide_appeal(
+    admin: AdminUser,
+    State(state): State<Arc<AppState>>,
+    Path(appeal_id): Path<Uuid>,
+    Json(payload): Json<DecideAppealRequest>,
+) -> Result<Json<Appeal>, (StatusCode, String)> {
+    if payload.decision != "upheld" && payload.decision != "overturned" {
+        return Err((StatusCode::BAD_REQUEST, "decision must be one of: upheld, overturned".to_string()));
+    }
+    let note = payload.note.as_deref().map(str::trim).filter(|note| !note.is_empty());
+    if payload.decision == "upheld" && note.is_none() {
+        return Err((StatusCode::BAD_REQUEST, "Say why the decision stands; the user is sent this note".to_string()));
+    }
+    if note.is_some_and(|note| note.chars().count() > MAX_NOTE_CHARS) {
+        return Err((StatusCode::BAD_REQUEST, format!("note must be at most {} characters", MAX_NOTE_CHARS)));
+    }
+
+    let (kind, content_type) = sqlx::query_as::<_, (String, Option<String>)>(
+        r#"
+        SELECT a.kind, s.content_type FROM appeals a
+        LEFT JOIN appealable_sanctions s ON s.kind = a.kind AND s.subject_id = a.subject_id
+        WHERE a.id = $1 AND a.status = 'pending'
+        "#
+    )
+    .bind(appeal_id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, "Appeal not found or already decided".to_string()))?;
+
+    let needed = if kind == "ban" { CanBan::NAME } else { CanModerateContent::NAME };
+    if !role_has(&state.pool, &admin.0.role, needed).await {
+        return Err((StatusCode::FORBIDDEN, format!("Deciding {} appeals requires {}", kind, needed)));
+    }
+    if kind == "takedown" && payload.decision == "overturned" && admin.0.role != "admin" {
+        return Err((StatusCode::FORBIDDEN, "Only admins can lift takedowns".to_string()));
+    }
+
+    let mut tx = state.pool.begin().await.map_err(db_error)?;
+    let appeal = sqlx::query_as::<_, Appeal>(
+        r#"
+        UPDATE appeals SET status = $2, decision_note = $3, decided_by = $4, decided_at = NOW()
+        WHERE id = $1 AND status = 'pending'
+        RETURNING id, user_id, kind, subject_id, body, status, decision_note, created_at, decided_at
+        "#
+    )
+    .bind(appeal_id)
+    .bind(&payload.decision)
+    .bind(note)
+    .bind(admin.0.id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, "Appeal not found or already decided".to_string()))?;
+
+    if appeal.status == "overturned" {
+        let reverse = match appeal.kind.as_str() {
+            "ban" => "UPDATE user_bans SET active = false, unbanned_at = NOW(), unbanned_by = $2 WHERE id = $1 AND active = true",
+            "takedown" => {
+                "UPDATE geo_takedowns SET lifted_at = NOW(), lifted_by = $2, lift_reason = 'Overturned on appeal' \
+                 WHERE id = $1 AND lifted_at IS NULL"
+            }
+            _ => "UPDATE moderation_flags SET status = 'dismissed', reviewed_by = $2, reviewed_at = NOW() WHERE id = $1",
+        };
+        sqlx::query(reverse)
+            .bind(appeal.subject_id)
+            .bind(admin.0.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+    }
+    tx.commit().await.map_err(db_error)?;
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "decide_appeal".to_string(),
+        Some(appeal.user_id),
+        Some("appeal".to_string()),
+        Some(appeal.id),
+        serde_json::json!({ "kind": appeal.kind, "subject_id": appeal.subject_id, "decision": appeal.status }),
+    )
+    .await;
+
+    send_decision(&state.pool, &appeal, content_type.as_deref()).await;
+
+    Ok(Json(appeal))
+}
@@ -1,8 +1,9 @@
 use axum::{
     extract::{Json, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
 };
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 use jsonwebtoken::{encode, EncodingKey, Header};
 use argon2::{Argon2, PasswordHash, PasswordVerifier, PasswordHasher};
@@ -10,79 +11,201 @@ use rand_core::OsRng;
 use chrono::Utc;
 use std::sync::Arc;
 
+use crate::client_ip::ClientIp;
+
 #[derive(Serialize, Deserialize)]
 struct Claims {
     sub: Uuid,
     exp: usize,
 }
 
-#[derive(Deserialize)]
+// Accounts that signed up with a phone number get an address at this
+// domain until they add a real one (users.email can't be NULL)
+pub const PHONE_SIGNUP_EMAIL_DOMAIN: &str = "@phone.invalid";
+
+#[derive(Deserialize, ToSchema)]
 pub struct SignupInput {
     username: String,
-    email: String,
+    /// Required unless signing up with a phone number
+    email: Option<String>,
     password: String,
+    /// Turnstile or hCaptcha response, required when a CAPTCHA is configured
+    captcha_token: Option<String>,
+    /// Number a signup code was sent to (POST /phone/code), with the code
+    phone_number: Option<String>,
+    phone_code: Option<String>,
+    /// Code of the user who invited you
+    referral_code: Option<String>,
+    /// Required while signup is invite-only (GET /signup/mode)
+    invite_code: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct LoginInput {
     username: String,
     password: String,
 }
 
+#[derive(Deserialize, ToSchema)]
+pub struct PhoneLoginInput {
+    phone_number: String,
+    /// A login code sent to the number
+    code: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct PhoneRecoveryInput {
+    phone_number: String,
+    /// A recovery code sent to the number
+    code: String,
+    new_password: String,
+}
+
+fn issue_token(user_id: Uuid) -> Result<String, (StatusCode, String)> {
+    let claims = Claims {
+        sub: user_id,
+        exp: (Utc::now().timestamp() + 3600) as usize,
+    };
+
+    encode(&Header::default(), &claims, &EncodingKey::from_secret("supersecret".as_ref()))
+        .map_err(|e| {
+            eprintln!("Failed to generate token: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Authentication error".to_string())
+        })
+}
+
+fn hash_password(password: &str) -> Result<String, (StatusCode, String)> {
+    let salt = argon2::password_hash::SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| {
+            eprintln!("Failed to hash password: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create account".to_string())
+        })
+}
+
 // Signup handler
 #[axum::debug_handler]
+#[utoipa::path(
+    post,
+    path = "/api/v1/signup",
+    tag = "auth",
+    request_body = SignupInput,
+    responses(
+        (status = 200, body = LoginResponse),
+        (status = 400, description = "Username not allowed, invalid, disposable or blocked email, wrong phone code, unknown referral or used invite code, or failed CAPTCHA"),
+        (status = 403, description = "Signup is invite-only and no invite code was given"),
+        (status = 409, description = "Username, email or phone number already exists"),
+        (status = 429, description = "Too many signups from this network")
+    )
+)]
 pub async fn signup(
     State(state): State<Arc<crate::AppState>>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
     Json(payload): Json<SignupInput>,
 ) -> Result<Json<LoginResponse>, (StatusCode, String)> {
-    // Hash the password
-    let salt = argon2::password_hash::SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
-    let password_hash = argon2.hash_password(payload.password.as_bytes(), &salt)
-        .map_err(|e| {
-            eprintln!("Failed to hash password: {:?}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create account".to_string())
-        })?
-        .to_string();
+    if payload.email.is_none() && payload.phone_number.is_none() {
+        return Err((StatusCode::BAD_REQUEST, "Sign up with an email address or a phone number".to_string()));
+    }
+    crate::signup_guard::check(&state, ip, payload.email.as_deref(), payload.captcha_token.as_deref()).await?;
+    crate::username_policy::check(&state.pool, &payload.username, None).await?;
+    let invite_code = payload.invite_code.as_deref().filter(|code| !code.trim().is_empty());
+    if invite_code.is_none() && crate::invites::invite_only() {
+        return Err((StatusCode::FORBIDDEN, "An invite code is required to sign up".to_string()));
+    }
+
+    let phone = match (&payload.phone_number, &payload.phone_code) {
+        (Some(number), Some(code)) => {
+            let number = crate::phone::require_normalized(number)?;
+            let code = crate::phone::check_code(&state.pool, &number, "signup", code).await?;
+            Some((number, code.id))
+        }
+        (Some(_), None) => return Err((StatusCode::BAD_REQUEST, "phone_code is required with phone_number".to_string())),
+        _ => None,
+    };
+
+    let referrer_id = match payload.referral_code.as_deref().filter(|code| !code.trim().is_empty()) {
+        Some(code) => Some(
+            crate::referrals::referrer_for_code(&state.pool, code)
+                .await
+                .map_err(|e| {
+                    eprintln!("Failed to look up referral code: {:?}", e);
+                    (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create account".to_string())
+                })?
+                .ok_or((StatusCode::BAD_REQUEST, "Unknown referral code".to_string()))?,
+        ),
+        None => None,
+    };
+
+    let password_hash = hash_password(&payload.password)?;
+    let email = payload
+        .email
+        .clone()
+        .unwrap_or_else(|| format!("{}{}", payload.username.to_lowercase(), PHONE_SIGNUP_EMAIL_DOMAIN));
+
+    // The signup and invite codes are only used up if the account is created
+    let mut tx = state.pool.begin().await.map_err(|e| {
+        eprintln!("Failed to create user: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create account".to_string())
+    })?;
+    if let Some((_, code_id)) = &phone {
+        crate::phone::consume(&mut *tx, *code_id).await?;
+    }
+    let invite = match invite_code {
+        Some(code) => Some(crate::invites::claim(&mut *tx, code).await?),
+        None => None,
+    };
 
     // Insert user into database
-    let user = sqlx::query!("INSERT INTO users (username, email, password_hash) VALUES ($1, $2, $3) RETURNING id, username, email",
-        payload.username,
-        payload.email,
-        password_hash
+    let (user_id, username, email) = sqlx::query_as::<_, (Uuid, String, String)>(
+        r#"
+        INSERT INTO users (username, email, password_hash, phone_number, phone_verified_at)
+        VALUES ($1, $2, $3, $4, CASE WHEN $4::VARCHAR IS NULL THEN NULL ELSE NOW() END)
+        RETURNING id, username, email
+        "#
     )
-    .fetch_one(state.pool.as_ref())
+    .bind(&payload.username)
+    .bind(&email)
+    .bind(&password_hash)
+    .bind(phone.as_ref().map(|(number, _)| number))
+    .fetch_one(&mut *tx)
     .await
     .map_err(|e| {
         eprintln!("Failed to create user: {:?}", e);
         if e.to_string().contains("duplicate") || e.to_string().contains("unique") {
-            (StatusCode::CONFLICT, "Username or email already exists".to_string())
+            (StatusCode::CONFLICT, "Username, email or phone number already exists".to_string())
         } else {
             (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create account".to_string())
         }
     })?;
 
-    // Generate JWT token
-    let claims = Claims {
-        sub: user.id,
-        exp: (Utc::now().timestamp() + 3600) as usize,
-    };
+    if let Some(invite) = &invite {
+        crate::invites::record_redemption(&mut *tx, user_id, invite).await?;
+    }
 
-    let token = encode(&Header::default(), &claims, &EncodingKey::from_secret("supersecret".as_ref()))
-        .map_err(|e| {
-            eprintln!("Failed to generate token: {:?}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Authentication error".to_string())
-        })?;
+    tx.commit().await.map_err(|e| {
+        eprintln!("Failed to create user: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create account".to_string())
+    })?;
+
+    crate::referrals::record_fingerprints(&state.pool, user_id, &headers).await;
+    if let Some(referrer_id) = referrer_id {
+        if let Err(e) = crate::referrals::attribute(&state.pool, user_id, referrer_id).await {
+            eprintln!("Failed to attribute referral of {}: {:?}", user_id, e);
+        }
+    }
 
     Ok(Json(LoginResponse {
-        token,
-        user_id: user.id,
-        username: user.username,
-        email: user.email,
+        token: issue_token(user_id)?,
+        user_id,
+        username,
+        email,
     }))
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct LoginResponse {
     token: String,
     user_id: Uuid,
@@ -92,8 +215,16 @@ pub struct LoginResponse {
 
 // Login handler
 #[axum::debug_handler]
+#[utoipa::path(
+    post,
+    path = "/api/v1/login",
+    tag = "auth",
+    request_body = LoginInput,
+    responses((status = 200, body = LoginResponse))
+)]
 pub async fn login(
     State(state): State<Arc<crate::AppState>>,
+    headers: HeaderMap,
     Json(payload): Json<LoginInput>,
 ) -> Result<Json<LoginResponse>, (StatusCode, String)> {
     // Find user by username
@@ -119,22 +250,111 @@ pub async fn login(
             (StatusCode::UNAUTHORIZED, "Invalid username or password".to_string())
         })?;
 
-    // Generate JWT token
-    let claims = Claims {
-        sub: row.id,
-        exp: (Utc::now().timestamp() + 3600) as usize,
-    };
-
-    let token = encode(&Header::default(), &claims, &EncodingKey::from_secret("supersecret".as_ref()))
-        .map_err(|e| {
-            eprintln!("Failed to generate token: {:?}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Authentication error".to_string())
-        })?;
+    crate::retention::record_activity(&state.pool, row.id).await;
+    crate::referrals::record_fingerprints(&state.pool, row.id, &headers).await;
 
     Ok(Json(LoginResponse {
-        token,
+        token: issue_token(row.id)?,
         user_id: row.id,
         username: row.username,
         email: row.email,
     }))
 }
+
+/// Log in the account a phone code was sent for, as long as the number is
+/// still on it
+async fn phone_owner_login(
+    state: &crate::AppState,
+    code: &crate::phone::PhoneCode,
+    phone_number: &str,
+    headers: &HeaderMap,
+) -> Result<Json<LoginResponse>, (StatusCode, String)> {
+    let (user_id, username, email) = sqlx::query_as::<_, (Uuid, String, String)>(
+        "SELECT id, username, email FROM users WHERE id = $1 AND phone_number = $2"
+    )
+    .bind(code.user_id)
+    .bind(phone_number)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to load phone owner: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Authentication error".to_string())
+    })?
+    .ok_or((StatusCode::BAD_REQUEST, "Invalid or expired code".to_string()))?;
+
+    crate::retention::record_activity(&state.pool, user_id).await;
+    crate::referrals::record_fingerprints(&state.pool, user_id, headers).await;
+
+    Ok(Json(LoginResponse {
+        token: issue_token(user_id)?,
+        user_id,
+        username,
+        email,
+    }))
+}
+
+// Log in with a code sent to the account's phone number
+#[utoipa::path(
+    post,
+    path = "/api/v1/login/phone",
+    tag = "auth",
+    request_body = PhoneLoginInput,
+    responses(
+        (status = 200, body = LoginResponse),
+        (status = 400, description = "Invalid or expired code"),
+        (status = 429, description = "Too many wrong codes")
+    )
+)]
+pub async fn login_with_phone(
+    State(state): State<Arc<crate::AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<PhoneLoginInput>,
+) -> Result<Json<LoginResponse>, (StatusCode, String)> {
+    let phone_number = crate::phone::require_normalized(&payload.phone_number)?;
+    let code = crate::phone::check_code(&state.pool, &phone_number, "login", &payload.code).await?;
+    crate::phone::consume(state.pool.as_ref(), code.id).await?;
+
+    phone_owner_login(&state, &code, &phone_number, &headers).await
+}
+
+// Set a new password with a code sent to the account's phone number, and log in
+#[utoipa::path(
+    post,
+    path = "/api/v1/account-recovery/phone",
+    tag = "auth",
+    request_body = PhoneRecoveryInput,
+    responses(
+        (status = 200, body = LoginResponse),
+        (status = 400, description = "Invalid or expired code, or password too short"),
+        (status = 429, description = "Too many wrong codes")
+    )
+)]
+pub async fn recover_with_phone(
+    State(state): State<Arc<crate::AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<PhoneRecoveryInput>,
+) -> Result<Json<LoginResponse>, (StatusCode, String)> {
+    if payload.new_password.len() < 6 {
+        return Err((StatusCode::BAD_REQUEST, "Password must be at least 6 characters".to_string()));
+    }
+    let phone_number = crate::phone::require_normalized(&payload.phone_number)?;
+    let code = crate::phone::check_code(&state.pool, &phone_number, "recovery", &payload.code).await?;
+    crate::phone::consume(state.pool.as_ref(), code.id).await?;
+
+    // Nothing changes if the number moved to another account since the code was sent
+    let reset = sqlx::query("UPDATE users SET password_hash = $3 WHERE id = $1 AND phone_number = $2")
+        .bind(code.user_id)
+        .bind(&phone_number)
+        .bind(hash_password(&payload.new_password)?)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to reset password: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Authentication error".to_string())
+        })?;
+    if reset.rows_affected() == 0 {
+        return Err((StatusCode::BAD_REQUEST, "Invalid or expired code".to_string()));
+    }
+
+    phone_owner_login(&state, &code, &phone_number, &headers).await
+}
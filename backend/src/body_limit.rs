@@ -0,0 +1,48 @@
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http_body_util::Limited;
+
+use crate::runtime_settings::{self, MAX_REQUEST_KB, MAX_UPLOAD_REQUEST_MB};
+
+// Request body limits. API routes accept bodies up to the
+// requests.max_body_kb runtime setting, except the upload endpoints
+// registered with RouteTable::upload, which get requests.max_upload_mb. A
+// declared Content-Length over the limit is refused before any of the body is
+// read; chunked bodies are cut off once they pass it. HARD_LIMIT_BYTES, the
+// DefaultBodyLimit in main.rs, caps every route whatever the settings say.
+
+pub const HARD_LIMIT_BYTES: usize = 100 * 1024 * 1024;
+
+/// Middleware for ordinary routes
+pub async fn standard(request: Request, next: Next) -> Response {
+    let limit = runtime_settings::int(MAX_REQUEST_KB) as usize * 1024;
+    enforce(request, next, limit).await
+}
+
+/// Middleware for routes that take file uploads
+pub async fn uploads(request: Request, next: Next) -> Response {
+    let limit = runtime_settings::int(MAX_UPLOAD_REQUEST_MB) as usize * 1024 * 1024;
+    enforce(request, next, limit).await
+}
+
+async fn enforce(request: Request, next: Next, limit: usize) -> Response {
+    let limit = limit.min(HARD_LIMIT_BYTES);
+    let declared = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if declared.is_some_and(|length| length > limit as u64) {
+        return (StatusCode::PAYLOAD_TOO_LARGE, "Request body too large".to_string()).into_response();
+    }
+
+    // Extractors report the cut-off as 413 too
+    let (parts, body) = request.into_parts();
+    let request = Request::from_parts(parts, Body::new(Limited::new(body, limit)));
+    next.run(request).await
+}
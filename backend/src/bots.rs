@@ -0,0 +1,713 @@
+use argon2::password_hash::SaltString;
+use argon2::{Argon2, PasswordHasher};
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path, Query, State},
+    http::{header, request::Parts, StatusCode},
+    Json,
+};
+use chrono::NaiveDateTime;
+use dashmap::DashMap;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::Notify;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::chat::{MessageResponse, OutgoingMessage};
+use crate::webhooks::{CreatedWebhookResponse, EVENT_MESSAGE_RECEIVED};
+use crate::AppState;
+
+const MAX_BOTS_PER_OWNER: i64 = 20;
+// Longest a getUpdates call may hang waiting for new messages
+const MAX_POLL_TIMEOUT_SECONDS: u64 = 50;
+// Re-check the database this often while long-polling, to pick up updates
+// queued by other server instances
+const POLL_RECHECK_INTERVAL: Duration = Duration::from_secs(2);
+// Unfetched updates are dropped after this long
+const UPDATE_RETENTION_HOURS: i32 = 24;
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct Bot {
+    pub id: Uuid,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub bot_owner_id: Option<Uuid>,
+    #[serde(with = "crate::timestamps::option")]
+    pub created_at: Option<NaiveDateTime>,
+}
+
+const BOT_COLUMNS: &str = "id, username, display_name, avatar_url, bot_owner_id, created_at";
+
+/// A bot authenticated with `Authorization: Bot <token>`
+#[derive(Debug, Clone)]
+pub struct BotUser {
+    pub id: Uuid,
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for BotUser {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<AppState>) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bot "))
+            .ok_or((StatusCode::UNAUTHORIZED, "Missing bot token".to_string()))?;
+
+        let bot_id = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            SELECT u.id
+            FROM bot_tokens t
+            JOIN users u ON u.id = t.bot_id
+            WHERE t.token_hash = $1 AND t.revoked_at IS NULL AND u.is_bot
+            "#
+        )
+        .bind(hash_token(token))
+        .fetch_optional(state.pool.as_ref())
+        .await
+        .map_err(|e| {
+            eprintln!("Bot token lookup error: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Authentication error".to_string())
+        })?
+        .ok_or((StatusCode::UNAUTHORIZED, "Invalid bot token".to_string()))?;
+
+        Ok(BotUser { id: bot_id })
+    }
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn random_hex(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    OsRng.fill_bytes(&mut buf);
+    hex::encode(buf)
+}
+
+// Revoke the bot's current token and issue a new one. Only the hash is stored,
+// so the returned token can't be shown again.
+async fn issue_token(pool: &PgPool, bot_id: Uuid) -> Result<String, sqlx::Error> {
+    let token = format!("{}:{}", bot_id, random_hex(32));
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE bot_tokens SET revoked_at = NOW() WHERE bot_id = $1 AND revoked_at IS NULL")
+        .bind(bot_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("INSERT INTO bot_tokens (bot_id, token_hash) VALUES ($1, $2)")
+        .bind(bot_id)
+        .bind(hash_token(&token))
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(token)
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    eprintln!("Bot query error: {:?}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+}
+
+// ============================================================================
+// MESSAGE ROUTING
+// ============================================================================
+
+// One Notify per bot so long-polling requests wake up as soon as an update lands
+fn update_notifiers() -> &'static DashMap<Uuid, Arc<Notify>> {
+    static NOTIFIERS: OnceLock<DashMap<Uuid, Arc<Notify>>> = OnceLock::new();
+    NOTIFIERS.get_or_init(DashMap::new)
+}
+
+fn notifier(bot_id: Uuid) -> Arc<Notify> {
+    update_notifiers().entry(bot_id).or_default().clone()
+}
+
+fn is_addressed_to(content: Option<&str>, bot_username: &str) -> bool {
+    let Some(content) = content else {
+        return false;
+    };
+    let mention = format!("@{}", bot_username.to_lowercase());
+    content.trim_start().starts_with('/') || content.to_lowercase().contains(&mention)
+}
+
+/// Work out which chat members should hear about `message`. People always do;
+/// bots only get messages addressed to them (anything in a 1:1 chat, or a
+/// /command or @mention in a group) and never other bots' messages. Addressed
+/// bots without a webhook get the message queued for long-polling.
+pub async fn message_recipients(pool: &PgPool, message: &MessageResponse, member_ids: &[Uuid]) -> Vec<Uuid> {
+    let bots = sqlx::query_as::<_, (Uuid, String, bool)>(
+        r#"
+        SELECT u.id, u.username, r.is_group
+        FROM users u
+        JOIN chat_rooms r ON r.id = $2
+        WHERE u.id = ANY($1) AND u.is_bot
+        "#
+    )
+    .bind(member_ids)
+    .bind(message.chat_room_id)
+    .fetch_all(pool)
+    .await
+    .unwrap_or_else(|e| {
+        tracing::error!("Failed to look up bots in chat {}: {}", message.chat_room_id, e);
+        Vec::new()
+    });
+
+    let sender_is_bot = bots.iter().any(|(id, _, _)| *id == message.sender_id);
+    let addressed: Vec<Uuid> = bots
+        .iter()
+        .filter(|(id, username, is_group)| {
+            *id != message.sender_id
+                && !sender_is_bot
+                && (!*is_group || is_addressed_to(message.content.as_deref(), username))
+        })
+        .map(|(id, _, _)| *id)
+        .collect();
+
+    if !addressed.is_empty() {
+        queue_updates(pool, &addressed, message).await;
+    }
+
+    member_ids
+        .iter()
+        .copied()
+        .filter(|id| *id != message.sender_id)
+        .filter(|id| addressed.contains(id) || !bots.iter().any(|(bot_id, _, _)| bot_id == id))
+        .collect()
+}
+
+async fn queue_updates(pool: &PgPool, bot_ids: &[Uuid], message: &MessageResponse) {
+    let queued = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO bot_updates (bot_id, payload)
+        SELECT u.id, $2
+        FROM users u
+        WHERE u.id = ANY($1)
+          AND NOT EXISTS (
+              SELECT 1 FROM webhook_subscriptions s
+              WHERE s.user_id = u.id AND s.active AND $3 = ANY(s.event_types)
+          )
+        RETURNING bot_id
+        "#
+    )
+    .bind(bot_ids)
+    .bind(serde_json::json!({ "message": message }))
+    .bind(EVENT_MESSAGE_RECEIVED)
+    .fetch_all(pool)
+    .await;
+
+    match queued {
+        Ok(bot_ids) => {
+            for bot_id in bot_ids {
+                notifier(bot_id).notify_waiters();
+            }
+        }
+        Err(e) => tracing::error!("Failed to queue bot updates: {}", e),
+    }
+}
+
+/// Drop updates no bot came to collect (run by the expiration sweep)
+pub async fn purge_stale_updates(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM bot_updates WHERE created_at < NOW() - make_interval(hours => $1)")
+        .bind(UPDATE_RETENTION_HOURS)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+// ============================================================================
+// BOT MANAGEMENT (owner, JWT auth)
+// ============================================================================
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateBotRequest {
+    /// Must end in "bot", e.g. "reminder_bot"
+    username: String,
+    display_name: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BotTokenResponse {
+    pub bot: Bot,
+    /// Send as `Authorization: Bot <token>`; only shown once
+    pub token: String,
+}
+
+fn validate_bot_username(username: &str) -> Result<(), (StatusCode, String)> {
+    let valid_chars = username.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !(5..=50).contains(&username.len()) || !valid_chars || !username.to_lowercase().ends_with("bot") {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Bot usernames are 5-50 letters, digits or _ and must end in \"bot\"".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/bots",
+    tag = "bots",
+    request_body = CreateBotRequest,
+    responses((status = 200, body = BotTokenResponse), (status = 409, description = "Username taken"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn create_bot(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<CreateBotRequest>,
+) -> Result<Json<BotTokenResponse>, (StatusCode, String)> {
+    validate_bot_username(&req.username)?;
+    crate::username_policy::check(&state.pool, &req.username, None).await?;
+
+    let owned = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM users WHERE bot_owner_id = $1 AND is_bot")
+        .bind(user.id)
+        .fetch_one(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+    if owned >= MAX_BOTS_PER_OWNER {
+        return Err((StatusCode::BAD_REQUEST, format!("You can own at most {} bots", MAX_BOTS_PER_OWNER)));
+    }
+
+    // Bots never log in with a password; give them a random one nobody knows
+    let salt = SaltString::generate(&mut OsRng);
+    let password_hash = Argon2::default()
+        .hash_password(random_hex(32).as_bytes(), &salt)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create bot".to_string()))?
+        .to_string();
+
+    let bot = sqlx::query_as::<_, Bot>(&format!(
+        r#"
+        INSERT INTO users (username, email, password_hash, display_name, is_bot, bot_owner_id)
+        VALUES ($1, $2, $3, $4, TRUE, $5)
+        ON CONFLICT DO NOTHING
+        RETURNING {}
+        "#,
+        BOT_COLUMNS
+    ))
+    .bind(&req.username)
+    .bind(format!("{}@bots.invalid", req.username.to_lowercase()))
+    .bind(password_hash)
+    .bind(&req.display_name)
+    .bind(user.id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::CONFLICT, "Username already taken".to_string()))?;
+
+    let token = issue_token(&state.pool, bot.id).await.map_err(db_error)?;
+
+    Ok(Json(BotTokenResponse { bot, token }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/bots",
+    tag = "bots",
+    responses((status = 200, body = [Bot]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_bots(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<Bot>>, (StatusCode, String)> {
+    let bots = sqlx::query_as::<_, Bot>(&format!(
+        "SELECT {} FROM users WHERE bot_owner_id = $1 AND is_bot ORDER BY created_at",
+        BOT_COLUMNS
+    ))
+    .bind(user.id)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(bots))
+}
+
+async fn find_owned_bot(pool: &PgPool, owner_id: Uuid, bot_id: Uuid) -> Result<Bot, (StatusCode, String)> {
+    sqlx::query_as::<_, Bot>(&format!(
+        "SELECT {} FROM users WHERE id = $1 AND bot_owner_id = $2 AND is_bot",
+        BOT_COLUMNS
+    ))
+    .bind(bot_id)
+    .bind(owner_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, "Bot not found".to_string()))
+}
+
+// Replace the bot's token, e.g. after it leaked
+#[utoipa::path(
+    post,
+    path = "/api/v1/bots/{bot_id}/token",
+    tag = "bots",
+    params(("bot_id" = Uuid, Path, description = "Bot ID")),
+    responses((status = 200, body = BotTokenResponse), (status = 404, description = "Bot not found"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn rotate_bot_token(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(bot_id): Path<Uuid>,
+) -> Result<Json<BotTokenResponse>, (StatusCode, String)> {
+    let bot = find_owned_bot(&state.pool, user.id, bot_id).await?;
+    let token = issue_token(&state.pool, bot.id).await.map_err(db_error)?;
+
+    Ok(Json(BotTokenResponse { bot, token }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/bots/{bot_id}",
+    tag = "bots",
+    params(("bot_id" = Uuid, Path, description = "Bot ID")),
+    responses((status = 204, description = "Deleted"), (status = 404, description = "Bot not found"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn delete_bot(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(bot_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let bot = find_owned_bot(&state.pool, user.id, bot_id).await?;
+
+    sqlx::query("DELETE FROM users WHERE id = $1 AND is_bot")
+        .bind(bot.id)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AddBotRequest {
+    bot_id: Uuid,
+}
+
+async fn ensure_group_member(pool: &PgPool, chat_room_id: Uuid, user_id: Uuid) -> Result<(), (StatusCode, String)> {
+    let is_group = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT r.is_group
+        FROM chat_rooms r
+        JOIN chat_members m ON m.chat_room_id = r.id AND m.user_id = $2
+        WHERE r.id = $1
+        "#
+    )
+    .bind(chat_room_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::FORBIDDEN, "You are not a member of this chat".to_string()))?;
+
+    if !is_group {
+        return Err((StatusCode::BAD_REQUEST, "Bots can only be added to group chats".to_string()));
+    }
+    Ok(())
+}
+
+// Any member of a group chat can add a bot to it
+#[utoipa::path(
+    post,
+    path = "/api/v1/chats/{chat_room_id}/bots",
+    tag = "bots",
+    params(("chat_room_id" = Uuid, Path, description = "Chat room ID")),
+    request_body = AddBotRequest,
+    responses((status = 204, description = "Added"), (status = 404, description = "Bot not found"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn add_bot_to_chat(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(chat_room_id): Path<Uuid>,
+    Json(req): Json<AddBotRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    ensure_group_member(&state.pool, chat_room_id, user.id).await?;
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO chat_members (chat_room_id, user_id)
+        SELECT $1, id FROM users WHERE id = $2 AND is_bot
+        ON CONFLICT DO NOTHING
+        "#
+    )
+    .bind(chat_room_id)
+    .bind(req.bot_id)
+    .execute(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    if result.rows_affected() == 0 {
+        let is_bot = sqlx::query_scalar::<_, bool>("SELECT is_bot FROM users WHERE id = $1")
+            .bind(req.bot_id)
+            .fetch_optional(state.pool.as_ref())
+            .await
+            .map_err(db_error)?;
+        if is_bot != Some(true) {
+            return Err((StatusCode::NOT_FOUND, "Bot not found".to_string()));
+        }
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/chats/{chat_room_id}/bots/{bot_id}",
+    tag = "bots",
+    params(("chat_room_id" = Uuid, Path, description = "Chat room ID"), ("bot_id" = Uuid, Path, description = "Bot ID")),
+    responses((status = 204, description = "Removed"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn remove_bot_from_chat(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path((chat_room_id, bot_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    ensure_group_member(&state.pool, chat_room_id, user.id).await?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM chat_members
+        WHERE chat_room_id = $1 AND user_id = $2
+          AND user_id IN (SELECT id FROM users WHERE is_bot)
+        "#
+    )
+    .bind(chat_room_id)
+    .bind(bot_id)
+    .execute(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+    crate::cache::invalidate_membership(&state.redis, chat_room_id, bot_id).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ============================================================================
+// BOT API (bot token auth)
+// ============================================================================
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/bot/me",
+    tag = "bots",
+    responses((status = 200, body = Bot), (status = 401, description = "Missing or invalid bot token")),
+    security(("bot_token" = []))
+)]
+pub async fn get_me(
+    bot: BotUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Bot>, (StatusCode, String)> {
+    let bot = sqlx::query_as::<_, Bot>(&format!("SELECT {} FROM users WHERE id = $1", BOT_COLUMNS))
+        .bind(bot.id)
+        .fetch_one(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(bot))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UpdatesQuery {
+    /// Updates with a lower id are acknowledged and deleted
+    #[serde(default)]
+    offset: i64,
+    /// Seconds to wait for new updates when there are none (max 50)
+    #[serde(default)]
+    timeout: u64,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct BotUpdate {
+    pub update_id: i64,
+    pub payload: serde_json::Value,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+}
+
+// Long-poll for messages addressed to the bot. Pass the last update_id + 1 as
+// offset to acknowledge everything received so far.
+#[utoipa::path(
+    get,
+    path = "/api/v1/bot/updates",
+    tag = "bots",
+    params(UpdatesQuery),
+    responses((status = 200, body = [BotUpdate]), (status = 401, description = "Missing or invalid bot token")),
+    security(("bot_token" = []))
+)]
+pub async fn get_updates(
+    bot: BotUser,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<UpdatesQuery>,
+) -> Result<Json<Vec<BotUpdate>>, (StatusCode, String)> {
+    if params.offset > 0 {
+        sqlx::query("DELETE FROM bot_updates WHERE bot_id = $1 AND id < $2")
+            .bind(bot.id)
+            .bind(params.offset)
+            .execute(state.pool.as_ref())
+            .await
+            .map_err(db_error)?;
+    }
+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(params.timeout.min(MAX_POLL_TIMEOUT_SECONDS));
+    let notify = notifier(bot.id);
+
+    loop {
+        // Register interest before querying so an update landing in between isn't missed
+        let notified = notify.notified();
+
+        let updates = sqlx::query_as::<_, BotUpdate>(
+            r#"
+            SELECT id AS update_id, payload, created_at
+            FROM bot_updates
+            WHERE bot_id = $1 AND id >= $2
+            ORDER BY id
+            LIMIT 100
+            "#
+        )
+        .bind(bot.id)
+        .bind(params.offset)
+        .fetch_all(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+
+        let now = tokio::time::Instant::now();
+        if !updates.is_empty() || now >= deadline {
+            return Ok(Json(updates));
+        }
+
+        let wait = (deadline - now).min(POLL_RECHECK_INTERVAL);
+        let _ = tokio::time::timeout(wait, notified).await;
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct BotSendMessageRequest {
+    chat_room_id: Uuid,
+    content: Option<String>,
+    #[serde(default = "default_message_type")]
+    message_type: String,
+    media_url: Option<String>,
+}
+
+fn default_message_type() -> String {
+    "text".to_string()
+}
+
+// Reply in a chat the bot belongs to
+#[utoipa::path(
+    post,
+    path = "/api/v1/bot/messages",
+    tag = "bots",
+    request_body = BotSendMessageRequest,
+    responses((status = 200, body = MessageResponse), (status = 403, description = "Bot is not in this chat"), (status = 401, description = "Missing or invalid bot token")),
+    security(("bot_token" = []))
+)]
+pub async fn send_message(
+    bot: BotUser,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<BotSendMessageRequest>,
+) -> Result<Json<MessageResponse>, (StatusCode, String)> {
+    let is_member = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM chat_members WHERE chat_room_id = $1 AND user_id = $2)"
+    )
+    .bind(req.chat_room_id)
+    .bind(bot.id)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+    if !is_member {
+        return Err((StatusCode::FORBIDDEN, "Bot is not a member of this chat".to_string()));
+    }
+    crate::channels::check_send(&state.pool, bot.id, req.chat_room_id).await?;
+
+    let message = OutgoingMessage {
+        chat_room_id: req.chat_room_id,
+        message_type: req.message_type,
+        content: req.content,
+        media_url: req.media_url,
+        media_thumbnail_url: None,
+        view_once: false,
+        expires_at: None,
+        client_msg_id: None,
+    };
+    message.validate().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let response = crate::chat::deliver_message(&state.pool, &state.redis, &state.connections, &state.media_service, bot.id, message)
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(response))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SetBotWebhookRequest {
+    url: String,
+}
+
+// Receive messages by webhook instead of long-polling. Replaces any existing
+// bot webhook; while one is active, getUpdates stays empty.
+#[utoipa::path(
+    put,
+    path = "/api/v1/bot/webhook",
+    tag = "bots",
+    request_body = SetBotWebhookRequest,
+    responses((status = 200, body = CreatedWebhookResponse), (status = 401, description = "Missing or invalid bot token")),
+    security(("bot_token" = []))
+)]
+pub async fn set_webhook(
+    bot: BotUser,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<SetBotWebhookRequest>,
+) -> Result<Json<CreatedWebhookResponse>, (StatusCode, String)> {
+    crate::webhooks::validate_url(&req.url).await?;
+
+    sqlx::query("DELETE FROM webhook_subscriptions WHERE user_id = $1")
+        .bind(bot.id)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+
+    let created = crate::webhooks::insert_subscription(
+        &state.pool,
+        bot.id,
+        &req.url,
+        &[EVENT_MESSAGE_RECEIVED.to_string()],
+    )
+    .await?;
+
+    Ok(Json(created))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/bot/webhook",
+    tag = "bots",
+    responses((status = 204, description = "Removed"), (status = 401, description = "Missing or invalid bot token")),
+    security(("bot_token" = []))
+)]
+pub async fn delete_webhook(
+    bot: BotUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    sqlx::query("DELETE FROM webhook_subscriptions WHERE user_id = $1")
+        .bind(bot.id)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
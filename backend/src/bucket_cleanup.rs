@@ -1,18 +1,21 @@
-use aws_sdk_s3::Client as S3Client;
 use chrono::Utc;
 use sqlx::PgPool;
 use std::collections::HashSet;
 
-/// Clean up unused files from S3 bucket
+use crate::media::MediaService;
+
+// Staged uploads are stored or discarded within their request; anything
+// under staging/ this old was abandoned
+const STALE_STAGING_HOURS: i64 = 24;
+
+/// Clean up unused files from storage
 /// Removes:
 /// - Files older than 30 days that aren't in the database
-/// - Expired story files (24 hours after expiration)
+/// - Expired story files (24 hours after expiration), once no other story or
+///   message references the same content-addressed object
 /// - Orphaned temporary files
-pub async fn cleanup_unused_files(
-    s3_client: &S3Client,
-    bucket_name: &str,
-    pool: &PgPool,
-) -> Result<CleanupStats, String> {
+/// - Staged uploads and multipart uploads abandoned for a day
+pub async fn cleanup_unused_files(media: &MediaService, pool: &PgPool) -> Result<CleanupStats, String> {
     println!("🧹 Starting bucket cleanup...");
 
     let mut stats = CleanupStats {
@@ -21,30 +24,44 @@ pub async fn cleanup_unused_files(
         bytes_freed: 0,
     };
 
-    // Get all files in bucket
-    let objects = list_all_objects(s3_client, bucket_name).await?;
+    // Get all files in storage
+    let objects = media.storage.list().await?;
     stats.files_scanned = objects.len();
 
     println!("📊 Found {} files in bucket", objects.len());
 
     // Get all active media URLs from database
     let active_urls = get_active_media_urls(pool).await?;
-    let active_keys: HashSet<String> = active_urls.iter()
-        .filter_map(|url| extract_s3_key(url, bucket_name))
+    let mut active_keys: HashSet<String> = active_urls.iter()
+        .filter_map(|url| extract_s3_key(media, url))
         .collect();
+    active_keys.extend(get_referenced_object_keys(pool).await?);
 
     println!("✅ Found {} active files in database", active_keys.len());
 
     // Check expired stories
-    let expired_story_keys = get_expired_story_keys(pool).await?;
+    let expired_story_keys = get_expired_story_keys(pool, media).await?;
+    let expired_hls_prefixes = get_expired_hls_prefixes(pool).await?;
     println!("⏰ Found {} expired story files", expired_story_keys.len());
 
-    // Delete orphaned and expired files
-    for (key, size, last_modified) in objects {
-        let should_delete = if expired_story_keys.contains(&key) {
+    // Collect orphaned and expired files, then delete them in batches
+    let mut sizes: std::collections::HashMap<String, i64> = std::collections::HashMap::new();
+    for crate::storage::ListedObject { key, size, last_modified } in objects {
+        let should_delete = if expired_story_keys.contains(&key)
+            || expired_hls_prefixes.iter().any(|prefix| key.starts_with(prefix))
+        {
             // Delete expired stories (24 hours after expiration)
             println!("  🗑️ Deleting expired story: {}", key);
             true
+        } else if key.starts_with(crate::upload_stream::STAGING_PREFIX) {
+            // Left behind by an upload that failed before it was stored
+            let age_hours = (Utc::now() - last_modified).num_hours();
+            if age_hours >= STALE_STAGING_HOURS {
+                println!("  🗑️ Deleting stale staged upload: {}", key);
+                true
+            } else {
+                false
+            }
         } else if !active_keys.contains(&key) {
             // Delete if file is orphaned and older than 30 days
             let age_days = (Utc::now() - last_modified).num_days();
@@ -59,21 +76,42 @@ pub async fn cleanup_unused_files(
         };
 
         if should_delete {
-            match delete_object(s3_client, bucket_name, &key).await {
-                Ok(_) => {
-                    stats.files_deleted += 1;
-                    stats.bytes_freed += size;
-                    println!("    ✅ Deleted: {} ({} bytes)", key, size);
-                }
-                Err(e) => {
-                    eprintln!("    ❌ Failed to delete {}: {}", key, e);
-                }
+            sizes.insert(key, size);
+        }
+    }
+
+    let keys_to_delete: Vec<String> = sizes.keys().cloned().collect();
+    match media.delete_media_batch(&keys_to_delete).await {
+        Ok(deleted) => {
+            // Forget deleted content-addressed objects so new uploads store them again
+            if let Err(e) = sqlx::query("DELETE FROM media_objects WHERE s3_key = ANY($1) AND refcount = 0")
+                .bind(&deleted)
+                .execute(pool)
+                .await
+            {
+                eprintln!("    ❌ Failed to forget deleted media objects: {}", e);
             }
+            for key in deleted {
+                stats.files_deleted += 1;
+                stats.bytes_freed += sizes.get(&key).copied().unwrap_or(0);
+            }
+        }
+        Err(e) => {
+            eprintln!("    ❌ Failed to delete files: {}", e);
         }
     }
 
+    match media
+        .storage
+        .abort_stale_uploads(crate::upload_stream::STAGING_PREFIX, chrono::Duration::hours(STALE_STAGING_HOURS))
+        .await
+    {
+        Ok(aborted) => println!("📤 Aborted {} unfinished multipart uploads", aborted),
+        Err(e) => eprintln!("    ❌ Failed to abort stale multipart uploads: {}", e),
+    }
+
     // Clean up orphaned story records from database
-    let deleted_records = cleanup_orphaned_story_records(pool, s3_client, bucket_name).await?;
+    let deleted_records = cleanup_orphaned_story_records(pool, media).await?;
     println!("🗄️ Cleaned up {} orphaned story records", deleted_records);
 
     println!("✅ Cleanup complete:");
@@ -91,72 +129,33 @@ pub struct CleanupStats {
     pub bytes_freed: i64,
 }
 
-/// List all objects in bucket with metadata
-async fn list_all_objects(
-    s3_client: &S3Client,
-    bucket_name: &str,
-) -> Result<Vec<(String, i64, chrono::DateTime<Utc>)>, String> {
-    let mut objects = Vec::new();
-    let mut continuation_token: Option<String> = None;
-
-    loop {
-        let mut request = s3_client.list_objects_v2()
-            .bucket(bucket_name);
-
-        if let Some(token) = continuation_token {
-            request = request.continuation_token(token);
-        }
-
-        let response = request.send()
-            .await
-            .map_err(|e| format!("Failed to list objects: {}", e))?;
-
-        if let Some(contents) = response.contents {
-            for object in contents {
-                if let (Some(key), Some(size), Some(last_modified)) = (
-                    object.key,
-                    object.size,
-                    object.last_modified,
-                ) {
-                    let dt = chrono::DateTime::from_timestamp(
-                        last_modified.secs(),
-                        last_modified.subsec_nanos(),
-                    ).unwrap_or_else(|| Utc::now());
-
-                    objects.push((key, size, dt));
-                }
-            }
-        }
-
-        if !response.is_truncated.unwrap_or(false) {
-            break;
-        }
-
-        continuation_token = response.next_continuation_token;
-    }
-
-    Ok(objects)
-}
-
 /// Get all active media URLs from database
 async fn get_active_media_urls(pool: &PgPool) -> Result<Vec<String>, String> {
     let mut urls = Vec::new();
 
     // Get story media URLs
-    let stories = sqlx::query_as::<_, (String, Option<String>)>(
-        "SELECT media_url, thumbnail_url FROM stories WHERE expires_at > NOW()"
+    let stories = sqlx::query_as::<_, (String, Option<String>, Option<String>)>(
+        "SELECT media_url, thumbnail_url, preview_url FROM stories WHERE expires_at > NOW()"
     )
     .fetch_all(pool)
     .await
     .map_err(|e| format!("Failed to fetch active stories: {}", e))?;
 
-    for (media_url, thumbnail_url) in stories {
+    for (media_url, thumbnail_url, preview_url) in stories {
         urls.push(media_url);
-        if let Some(thumb) = thumbnail_url {
-            urls.push(thumb);
-        }
+        urls.extend(thumbnail_url);
+        urls.extend(preview_url);
     }
 
+    // Later items of carousel stories
+    let items = sqlx::query_scalar::<_, String>(
+        "SELECT m.media_url FROM story_media m JOIN stories s ON s.id = m.story_id WHERE s.expires_at > NOW()"
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch active carousel items: {}", e))?;
+    urls.extend(items);
+
     // Get profile pictures (avatar_url)
     let users = sqlx::query_as::<_, (Option<String>,)>(
         "SELECT avatar_url FROM users WHERE avatar_url IS NOT NULL"
@@ -171,97 +170,123 @@ async fn get_active_media_urls(pool: &PgPool) -> Result<Vec<String>, String> {
         }
     }
 
-    // Get post media URLs
-    let posts = sqlx::query_as::<_, (Option<Vec<String>>,)>(
-        "SELECT media_urls FROM posts WHERE media_urls IS NOT NULL"
+    // Community avatars
+    let rooms = sqlx::query_scalar::<_, String>("SELECT avatar_url FROM chat_rooms WHERE avatar_url IS NOT NULL")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch community avatars: {}", e))?;
+    urls.extend(rooms);
+
+    // Get archived stories (Memories)
+    let archived = sqlx::query_as::<_, (String, Option<String>)>(
+        "SELECT media_url, thumbnail_url FROM story_archive"
     )
     .fetch_all(pool)
     .await
-    .map_err(|e| format!("Failed to fetch posts: {}", e))?;
+    .map_err(|e| format!("Failed to fetch story archive: {}", e))?;
 
-    for (media_urls,) in posts {
-        if let Some(media_urls) = media_urls {
-            for url in media_urls {
-                urls.push(url);
-            }
+    for (media_url, thumbnail_url) in archived {
+        urls.push(media_url);
+        if let Some(thumb) = thumbnail_url {
+            urls.push(thumb);
         }
     }
 
     // Get message attachments
-    let messages = sqlx::query_as::<_, (Option<String>,)>(
-        "SELECT attachment_url FROM messages WHERE attachment_url IS NOT NULL AND created_at > NOW() - INTERVAL '90 days'"
+    let messages = sqlx::query_as::<_, (Option<String>, Option<String>)>(
+        "SELECT media_url, media_thumbnail_url FROM messages WHERE media_url IS NOT NULL AND deleted_at IS NULL"
     )
     .fetch_all(pool)
     .await
     .map_err(|e| format!("Failed to fetch messages: {}", e))?;
 
-    for (attachment_url,) in messages {
-        if let Some(url) = attachment_url {
-            urls.push(url);
-        }
+    for (media_url, thumbnail_url) in messages {
+        urls.extend(media_url);
+        urls.extend(thumbnail_url);
     }
 
     Ok(urls)
 }
 
-/// Get S3 keys for expired stories
-async fn get_expired_story_keys(pool: &PgPool) -> Result<HashSet<String>, String> {
-    let expired_stories = sqlx::query_as::<_, (String, Option<String>)>(
-        "SELECT media_url, thumbnail_url FROM stories WHERE expires_at < NOW() - INTERVAL '24 hours'"
+/// Keys of content-addressed objects that something still references, or that
+/// an upload handed out recently and may be attached any moment
+async fn get_referenced_object_keys(pool: &PgPool) -> Result<Vec<String>, String> {
+    sqlx::query_scalar::<_, String>(
+        "SELECT s3_key FROM media_objects WHERE refcount > 0 OR last_used_at > NOW() - INTERVAL '1 day'"
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch media objects: {}", e))
+}
+
+/// Get S3 keys for expired stories. Each story releases its media references
+/// once; shared objects only come back here when their last reference goes.
+async fn get_expired_story_keys(pool: &PgPool, media: &MediaService) -> Result<HashSet<String>, String> {
+    let expired_stories = sqlx::query_as::<_, (String, Option<String>, Option<String>, Vec<String>)>(
+        r#"
+        UPDATE stories SET media_released = TRUE
+        WHERE expires_at < NOW() - INTERVAL '24 hours' AND NOT media_released
+        RETURNING media_url, thumbnail_url, preview_url,
+                  ARRAY(SELECT m.media_url FROM story_media m WHERE m.story_id = stories.id AND m.position > 0)
+        "#
     )
     .fetch_all(pool)
     .await
     .map_err(|e| format!("Failed to fetch expired stories: {}", e))?;
 
-    let mut keys = HashSet::new();
+    let mut keys = Vec::new();
 
-    for (media_url, thumbnail_url) in expired_stories {
-        if let Some(key) = extract_s3_key_from_any_url(&media_url) {
-            keys.insert(key);
+    for (media_url, thumbnail_url, preview_url, item_urls) in expired_stories {
+        if let Some(key) = extract_s3_key_from_any_url(media, &media_url) {
+            keys.push(key);
         }
-        if let Some(thumb) = thumbnail_url {
-            if let Some(key) = extract_s3_key_from_any_url(&thumb) {
-                keys.insert(key);
+        for url in thumbnail_url.iter().chain(preview_url.iter()).chain(item_urls.iter()) {
+            if let Some(key) = extract_s3_key_from_any_url(media, url) {
+                keys.push(key);
             }
         }
     }
 
-    Ok(keys)
+    let mut conn = pool.acquire().await.map_err(|e| format!("Failed to release media objects: {}", e))?;
+    Ok(crate::media::release_keys(&mut conn, &keys).await?.into_iter().collect())
 }
 
-/// Delete an object from S3
-async fn delete_object(
-    s3_client: &S3Client,
-    bucket_name: &str,
-    key: &str,
-) -> Result<(), String> {
-    s3_client
-        .delete_object()
-        .bucket(bucket_name)
-        .key(key)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to delete object: {}", e))?;
+/// Key prefixes of HLS packages for expired stories. Segments aren't
+/// referenced individually, so the whole package goes with its story.
+async fn get_expired_hls_prefixes(pool: &PgPool) -> Result<Vec<String>, String> {
+    let stories = sqlx::query_as::<_, (uuid::Uuid, uuid::Uuid)>(
+        "SELECT id, user_id FROM stories WHERE playback_url IS NOT NULL AND expires_at < NOW() - INTERVAL '24 hours'"
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch expired HLS stories: {}", e))?;
 
-    Ok(())
+    Ok(stories
+        .into_iter()
+        .map(|(story_id, user_id)| format!("stories/{}/hls/{}/", user_id, story_id))
+        .collect())
 }
 
 /// Extract S3 key from URL
-fn extract_s3_key(url: &str, bucket_name: &str) -> Option<String> {
+fn extract_s3_key(media: &MediaService, url: &str) -> Option<String> {
+    // URLs under the configured public base (R2, local storage) map exactly
+    if let Some(key) = media.s3_key_from_url(url) {
+        return Some(key);
+    }
     // Handle both S3 and CloudFlare R2 URLs
-    if let Some(key) = url.strip_prefix(&format!("https://{}.s3.amazonaws.com/", bucket_name)) {
+    if let Some(key) = url.strip_prefix(&format!("https://{}.s3.amazonaws.com/", media.bucket_name)) {
         Some(key.to_string())
-    } else if let Some(key) = url.split('/').skip(3).collect::<Vec<_>>().join("/").into() {
-        Some(key)
     } else {
-        None
+        url.split('/').skip(3).collect::<Vec<_>>().join("/").into()
     }
 }
 
 /// Extract S3 key from any URL format
-fn extract_s3_key_from_any_url(url: &str) -> Option<String> {
+fn extract_s3_key_from_any_url(media: &MediaService, url: &str) -> Option<String> {
     // Try to extract key from various URL formats
-    if let Some(pos) = url.find(".amazonaws.com/") {
+    if let Some(key) = media.s3_key_from_url(url) {
+        Some(key)
+    } else if let Some(pos) = url.find(".amazonaws.com/") {
         Some(url[pos + 15..].to_string())
     } else if let Some(pos) = url.find(".r2.dev/") {
         Some(url[pos + 8..].to_string())
@@ -275,12 +300,8 @@ fn extract_s3_key_from_any_url(url: &str) -> Option<String> {
     }
 }
 
-/// Clean up orphaned story records (where S3 file doesn't exist)
-async fn cleanup_orphaned_story_records(
-    pool: &PgPool,
-    s3_client: &S3Client,
-    bucket_name: &str,
-) -> Result<i32, String> {
+/// Clean up orphaned story records (where the stored file doesn't exist)
+async fn cleanup_orphaned_story_records(pool: &PgPool, media: &MediaService) -> Result<i32, String> {
     use sqlx::Row;
 
     let expired_stories = sqlx::query(
@@ -296,15 +317,12 @@ async fn cleanup_orphaned_story_records(
         let story_id: uuid::Uuid = story.get("id");
         let media_url: String = story.get("media_url");
 
-        // Check if S3 object exists
-        if let Some(key) = extract_s3_key_from_any_url(&media_url) {
-            let exists = s3_client
-                .head_object()
-                .bucket(bucket_name)
-                .key(&key)
-                .send()
-                .await
-                .is_ok();
+        // Check if the object exists; a failed lookup proves nothing
+        if let Some(key) = extract_s3_key_from_any_url(media, &media_url) {
+            let exists = media.storage.exists(&key).await.unwrap_or_else(|e| {
+                eprintln!("    ❌ {}", e);
+                true
+            });
 
             if !exists {
                 // Delete orphaned record
@@ -321,26 +339,3 @@ async fn cleanup_orphaned_story_records(
 
     Ok(deleted_count)
 }
-
-/// Run cleanup on a schedule (called by a background task)
-pub async fn run_scheduled_cleanup(
-    s3_client: &S3Client,
-    bucket_name: &str,
-    pool: &PgPool,
-) {
-    loop {
-        println!("🕐 Running scheduled bucket cleanup...");
-
-        match cleanup_unused_files(s3_client, bucket_name, pool).await {
-            Ok(stats) => {
-                println!("✅ Cleanup successful: {:?}", stats);
-            }
-            Err(e) => {
-                eprintln!("❌ Cleanup failed: {}", e);
-            }
-        }
-
-        // Run every 6 hours
-        tokio::time::sleep(tokio::time::Duration::from_secs(6 * 60 * 60)).await;
-    }
-}
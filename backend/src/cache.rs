@@ -0,0 +1,105 @@
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::redis_client::RedisClient;
+
+// Cache-aside for hot read paths: profiles, a user's live stories and
+// discovery results. Entries expire on their own and are invalidated when
+// the underlying data changes. Lists with many variants (per viewer,
+// country, query) aren't deleted one by one; their keys carry a version
+// number that invalidation bumps, so old entries are simply never read
+// again. Background updates to a story (alt text, video renditions) just
+// wait out the TTL. Redis errors count as a miss.
+//
+// Chat membership, checked on every WebSocket frame about a chat, is cached
+// only when it holds, so joining takes effect at once. Leaving, kicks and
+// bans delete the entry; rooms and accounts deleted outright wait out
+// MEMBERSHIP_TTL_SECS.
+
+pub const PROFILE_TTL_SECS: u64 = 300;
+pub const STORIES_TTL_SECS: u64 = 60;
+pub const DISCOVERY_TTL_SECS: u64 = 60;
+pub const FEED_TTL_SECS: u64 = 300;
+pub const MEMBERSHIP_TTL_SECS: u64 = 60;
+
+// Outlives every entry that embeds the version
+const VERSION_TTL_SECS: i64 = 24 * 60 * 60;
+
+pub async fn get<T: DeserializeOwned>(redis: &Mutex<RedisClient>, key: &str) -> Option<T> {
+    let cached = redis.lock().await.cache_get(key).await.ok()??;
+    serde_json::from_str(&cached).ok()
+}
+
+pub async fn set<T: Serialize>(redis: &Mutex<RedisClient>, key: &str, value: &T, ttl_seconds: u64) {
+    if let Ok(json) = serde_json::to_string(value) {
+        let _ = redis.lock().await.cache_set(key, &json, ttl_seconds).await;
+    }
+}
+
+async fn version(redis: &Mutex<RedisClient>, scope: &str, id: Uuid) -> i64 {
+    let key = format!("cache_version:{}:{}", scope, id);
+    redis
+        .lock()
+        .await
+        .cache_get(&key)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0)
+}
+
+async fn bump_version(redis: &Mutex<RedisClient>, scope: &str, id: Uuid) {
+    let key = format!("cache_version:{}:{}", scope, id);
+    if let Err(e) = redis.lock().await.cache_incr(&key, VERSION_TTL_SECS).await {
+        tracing::warn!(key = %key, error = %e, "failed to invalidate cache");
+    }
+}
+
+pub fn profile_key(user_id: Uuid) -> String {
+    format!("cache:profile:{}", user_id)
+}
+
+pub fn membership_key(chat_room_id: Uuid, user_id: Uuid) -> String {
+    format!("cache:member:{}:{}", chat_room_id, user_id)
+}
+
+/// Key for a user's live stories as seen from one country
+pub async fn stories_key(redis: &Mutex<RedisClient>, user_id: Uuid, country: Option<&str>) -> String {
+    let version = version(redis, "stories", user_id).await;
+    format!("cache:stories:{}:{}:{}", user_id, version, country.unwrap_or("-"))
+}
+
+/// Key for a rendered feed (json or rss) of a user's public stories; shares
+/// the stories version so new and removed stories show up right away
+pub async fn feed_key(redis: &Mutex<RedisClient>, user_id: Uuid, format: &str) -> String {
+    let version = version(redis, "stories", user_id).await;
+    format!("cache:feed:{}:{}:{}", user_id, version, format)
+}
+
+/// Key for one discovery result for a viewer; `params` covers the query string
+pub async fn discovery_key(redis: &Mutex<RedisClient>, viewer_id: Uuid, kind: &str, params: &str) -> String {
+    let version = version(redis, "discovery", viewer_id).await;
+    format!("cache:discovery:{}:{}:{}:{}", kind, viewer_id, version, params)
+}
+
+/// Profile fields or counts changed
+pub async fn invalidate_profile(redis: &Mutex<RedisClient>, user_id: Uuid) {
+    let _ = redis.lock().await.cache_delete(&profile_key(user_id)).await;
+}
+
+/// The user left or was removed from the chat
+pub async fn invalidate_membership(redis: &Mutex<RedisClient>, chat_room_id: Uuid, user_id: Uuid) {
+    let _ = redis.lock().await.cache_delete(&membership_key(chat_room_id, user_id)).await;
+}
+
+/// A story of the user was posted, removed or changed
+pub async fn invalidate_stories(redis: &Mutex<RedisClient>, user_id: Uuid) {
+    bump_version(redis, "stories", user_id).await;
+}
+
+/// The viewer's follows changed, so their discovery results did too
+pub async fn invalidate_discovery(redis: &Mutex<RedisClient>, viewer_id: Uuid) {
+    bump_version(redis, "discovery", viewer_id).await;
+}
@@ -0,0 +1,499 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use chrono::NaiveDateTime;
+use openssl::{hash::MessageDigest, pkey::PKey, sign::Signer};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::websocket::{nack, send_to_user, Connections, ErrorCode, Outbox, WsMessage};
+use crate::AppState;
+
+// 1:1 audio and video calls (migration 061). The caller sends CallOffer in an
+// accepted 1:1 chat; the callee hears CallIncoming and replies with CallAnswer
+// or CallHangup. CallIce is relayed between the two until either hangs up or
+// drops off. A call nobody answers within RING_TIMEOUT is missed, and the
+// callee gets a missed-call notification.
+
+const RING_TIMEOUT: Duration = Duration::from_secs(45);
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct Call {
+    pub id: Uuid,
+    pub chat_room_id: Uuid,
+    pub caller_id: Uuid,
+    pub callee_id: Uuid,
+    /// audio or video
+    pub media: String,
+    /// ringing, accepted, declined, missed or ended
+    pub status: String,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps::option")]
+    pub answered_at: Option<NaiveDateTime>,
+    #[serde(with = "crate::timestamps::option")]
+    pub ended_at: Option<NaiveDateTime>,
+    pub duration_seconds: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct IceServer {
+    pub urls: Vec<String>,
+    pub username: Option<String>,
+    pub credential: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IceServersResponse {
+    pub ice_servers: Vec<IceServer>,
+    /// How long the credentials stay valid
+    pub ttl_seconds: u64,
+}
+
+/// TURN relay credentials, picked with TURN_PROVIDER
+#[derive(Debug, Clone, Copy)]
+enum TurnProvider {
+    /// Fixed TURN_USERNAME / TURN_CREDENTIAL for the servers in TURN_URLS
+    Static,
+    /// Short-lived credentials from TURN_SHARED_SECRET (the TURN REST API
+    /// coturn implements with use-auth-secret)
+    Coturn,
+    /// Network traversal tokens from TWILIO_ACCOUNT_SID / TWILIO_AUTH_TOKEN
+    Twilio,
+}
+
+impl TurnProvider {
+    fn from_env() -> Option<TurnProvider> {
+        match std::env::var("TURN_PROVIDER").ok()?.to_lowercase().as_str() {
+            "static" => Some(TurnProvider::Static),
+            "coturn" => Some(TurnProvider::Coturn),
+            "twilio" => Some(TurnProvider::Twilio),
+            other => {
+                tracing::warn!("Unknown TURN_PROVIDER {:?}; TURN disabled", other);
+                None
+            }
+        }
+    }
+}
+
+fn turn_urls() -> Vec<String> {
+    std::env::var("TURN_URLS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn turn_ttl_seconds() -> u64 {
+    std::env::var("TURN_TTL_SECONDS").ok().and_then(|v| v.parse().ok()).unwrap_or(3600)
+}
+
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("Failed to build TURN HTTP client")
+    })
+}
+
+async fn turn_credentials(provider: TurnProvider, user_id: Uuid) -> Result<IceServersResponse, String> {
+    match provider {
+        TurnProvider::Static => Ok(IceServersResponse {
+            ice_servers: vec![IceServer {
+                urls: turn_urls(),
+                username: std::env::var("TURN_USERNAME").ok(),
+                credential: std::env::var("TURN_CREDENTIAL").ok(),
+            }],
+            ttl_seconds: turn_ttl_seconds(),
+        }),
+        TurnProvider::Coturn => {
+            let secret = std::env::var("TURN_SHARED_SECRET").map_err(|_| "TURN_SHARED_SECRET is not set".to_string())?;
+            let ttl_seconds = turn_ttl_seconds();
+            let username = format!("{}:{}", chrono::Utc::now().timestamp() + ttl_seconds as i64, user_id);
+
+            let credential = (|| -> Result<Vec<u8>, openssl::error::ErrorStack> {
+                let key = PKey::hmac(secret.as_bytes())?;
+                let mut signer = Signer::new(MessageDigest::sha1(), &key)?;
+                signer.update(username.as_bytes())?;
+                signer.sign_to_vec()
+            })()
+            .map_err(|e| format!("Failed to sign TURN credential: {}", e))?;
+
+            Ok(IceServersResponse {
+                ice_servers: vec![IceServer {
+                    urls: turn_urls(),
+                    username: Some(username),
+                    credential: Some(BASE64.encode(credential)),
+                }],
+                ttl_seconds,
+            })
+        }
+        TurnProvider::Twilio => {
+            #[derive(Deserialize)]
+            struct Response {
+                ice_servers: Vec<Server>,
+                ttl: String,
+            }
+            #[derive(Deserialize)]
+            struct Server {
+                urls: String,
+                username: Option<String>,
+                credential: Option<String>,
+            }
+
+            let account_sid = std::env::var("TWILIO_ACCOUNT_SID").map_err(|_| "TWILIO_ACCOUNT_SID is not set".to_string())?;
+            let auth_token = std::env::var("TWILIO_AUTH_TOKEN").map_err(|_| "TWILIO_AUTH_TOKEN is not set".to_string())?;
+            let response: Response = http_client()
+                .post(format!("https://api.twilio.com/2010-04-01/Accounts/{}/Tokens.json", account_sid))
+                .basic_auth(&account_sid, Some(auth_token))
+                .form(&[("Ttl", turn_ttl_seconds().to_string())])
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| format!("Twilio token request failed: {}", e))?
+                .json()
+                .await
+                .map_err(|e| format!("Unexpected Twilio response: {}", e))?;
+
+            Ok(IceServersResponse {
+                ice_servers: response
+                    .ice_servers
+                    .into_iter()
+                    .map(|s| IceServer { urls: vec![s.urls], username: s.username, credential: s.credential })
+                    .collect(),
+                ttl_seconds: response.ttl.parse().unwrap_or_else(|_| turn_ttl_seconds()),
+            })
+        }
+    }
+}
+
+async fn notify_missed(pool: &PgPool, call_id: Uuid) {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO notifications (user_id, type, from_user_id, message, message_key, message_args)
+        SELECT c.callee_id, 'missed_call', c.caller_id, 'Missed ' || c.media || ' call from ' || u.username,
+               'missed_' || c.media || '_call', jsonb_build_object('username', u.username)
+        FROM calls c
+        JOIN users u ON u.id = c.caller_id
+        WHERE c.id = $1
+        "#
+    )
+    .bind(call_id)
+    .execute(pool)
+    .await;
+
+    if let Err(e) = result {
+        tracing::error!("Failed to notify missed call {}: {}", call_id, e);
+    }
+}
+
+/// End a ringing or connected call and tell both parties. `ended_by` is the
+/// party hanging up, or None when the call rang out.
+async fn finish_call(pool: &PgPool, connections: &Connections, call_id: Uuid, ended_by: Option<Uuid>) {
+    let finished = sqlx::query_as::<_, (Uuid, Uuid, String, Option<i32>)>(
+        r#"
+        UPDATE calls SET
+            status = CASE
+                WHEN status = 'accepted' THEN 'ended'
+                WHEN callee_id = $2 THEN 'declined'
+                ELSE 'missed'
+            END,
+            ended_at = NOW(),
+            duration_seconds = CASE WHEN status = 'accepted' THEN EXTRACT(EPOCH FROM NOW() - answered_at)::int END
+        WHERE id = $1
+          AND status IN ('ringing', 'accepted')
+          AND (($2::uuid IS NULL AND status = 'ringing') OR $2 IN (caller_id, callee_id))
+        RETURNING caller_id, callee_id, status, duration_seconds
+        "#
+    )
+    .bind(call_id)
+    .bind(ended_by)
+    .fetch_optional(pool)
+    .await;
+
+    let (caller_id, callee_id, status, duration_seconds) = match finished {
+        Ok(Some(finished)) => finished,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::error!("Failed to end call {}: {}", call_id, e);
+            return;
+        }
+    };
+
+    if status == "missed" {
+        notify_missed(pool, call_id).await;
+    }
+    let ended = WsMessage::CallEnded { call_id, status, duration_seconds };
+    send_to_user(connections, caller_id, &ended);
+    send_to_user(connections, callee_id, &ended);
+}
+
+/// Place a call, or say why it can't be placed
+async fn place_call(
+    pool: &Arc<PgPool>,
+    connections: &Connections,
+    caller_id: Uuid,
+    chat_room_id: Uuid,
+    media: String,
+    sdp: String,
+) -> Result<(), (ErrorCode, String)> {
+    if media != "audio" && media != "video" {
+        return Err((ErrorCode::PayloadInvalid, "media must be audio or video".to_string()));
+    }
+
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("Failed to place call in chat {}: {}", chat_room_id, e);
+        (ErrorCode::Internal, "Failed to place the call".to_string())
+    };
+
+    let callee_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        SELECT other.user_id
+        FROM chat_rooms r
+        JOIN chat_members me ON me.chat_room_id = r.id AND me.user_id = $2
+        JOIN chat_members other ON other.chat_room_id = r.id AND other.user_id <> $2
+        WHERE r.id = $1 AND NOT r.is_group AND r.state = 'accepted'
+        "#
+    )
+    .bind(chat_room_id)
+    .bind(caller_id)
+    .fetch_optional(pool.as_ref())
+    .await
+    .map_err(db_error)?
+    .ok_or((ErrorCode::Unauthorized, "Calls are only available in accepted 1:1 chats".to_string()))?;
+
+    let busy = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        SELECT u.id FROM unnest($1::uuid[]) AS u(id)
+        WHERE EXISTS (
+            SELECT 1 FROM calls WHERE status IN ('ringing', 'accepted') AND (caller_id = u.id OR callee_id = u.id)
+        )
+        "#
+    )
+    .bind(vec![caller_id, callee_id])
+    .fetch_all(pool.as_ref())
+    .await
+    .map_err(db_error)?;
+    if busy.contains(&caller_id) {
+        return Err((ErrorCode::Conflict, "You're already on a call".to_string()));
+    }
+    if busy.contains(&callee_id) {
+        return Err((ErrorCode::Conflict, "They're on another call".to_string()));
+    }
+
+    let (call_id, caller_username) = sqlx::query_as::<_, (Uuid, String)>(
+        r#"
+        WITH call AS (
+            INSERT INTO calls (chat_room_id, caller_id, callee_id, media) VALUES ($1, $2, $3, $4)
+            RETURNING id, caller_id
+        )
+        SELECT call.id, u.username FROM call JOIN users u ON u.id = call.caller_id
+        "#
+    )
+    .bind(chat_room_id)
+    .bind(caller_id)
+    .bind(callee_id)
+    .bind(&media)
+    .fetch_one(pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    send_to_user(connections, caller_id, &WsMessage::CallRinging { call_id, chat_room_id, callee_id });
+    send_to_user(
+        connections,
+        callee_id,
+        &WsMessage::CallIncoming { call_id, chat_room_id, caller_id, caller_username, media, sdp },
+    );
+
+    let pool = pool.clone();
+    let connections = connections.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(RING_TIMEOUT).await;
+        finish_call(&pool, &connections, call_id, None).await;
+    });
+
+    Ok(())
+}
+
+/// Handle the call frames of the WebSocket protocol
+pub async fn handle_ws_message(
+    msg: WsMessage,
+    request_id: Option<&str>,
+    outbox: &Outbox,
+    user_id: Uuid,
+    pool: &Arc<PgPool>,
+    connections: &Connections,
+) {
+    let error = |code: ErrorCode, message: &str| nack(outbox, request_id, code, message);
+
+    match msg {
+        WsMessage::CallOffer { chat_room_id, media, sdp } => {
+            if let Err((code, message)) = place_call(pool, connections, user_id, chat_room_id, media, sdp).await {
+                error(code, &message);
+            }
+        }
+
+        WsMessage::CallAnswer { call_id, sdp } => {
+            let caller_id = sqlx::query_scalar::<_, Uuid>(
+                r#"
+                UPDATE calls SET status = 'accepted', answered_at = NOW()
+                WHERE id = $1 AND callee_id = $2 AND status = 'ringing'
+                RETURNING caller_id
+                "#
+            )
+            .bind(call_id)
+            .bind(user_id)
+            .fetch_optional(pool.as_ref())
+            .await;
+
+            match caller_id {
+                Ok(Some(caller_id)) => send_to_user(connections, caller_id, &WsMessage::CallAnswered { call_id, sdp }),
+                Ok(None) => error(ErrorCode::NotFound, "This call is no longer ringing"),
+                Err(e) => {
+                    tracing::error!("Failed to answer call {}: {}", call_id, e);
+                    error(ErrorCode::Internal, "Failed to answer the call");
+                }
+            }
+        }
+
+        WsMessage::CallHangup { call_id } => {
+            finish_call(pool, connections, call_id, Some(user_id)).await;
+        }
+
+        WsMessage::CallIce { call_id, candidate, sdp_mid, sdp_mline_index } => {
+            let peer_id = sqlx::query_scalar::<_, Uuid>(
+                r#"
+                SELECT CASE WHEN caller_id = $2 THEN callee_id ELSE caller_id END
+                FROM calls
+                WHERE id = $1 AND status IN ('ringing', 'accepted') AND $2 IN (caller_id, callee_id)
+                "#
+            )
+            .bind(call_id)
+            .bind(user_id)
+            .fetch_optional(pool.as_ref())
+            .await;
+
+            match peer_id {
+                Ok(Some(peer_id)) => send_to_user(
+                    connections,
+                    peer_id,
+                    &WsMessage::CallIce { call_id, candidate, sdp_mid, sdp_mline_index },
+                ),
+                Ok(None) => error(ErrorCode::NotFound, "This call has ended"),
+                Err(e) => {
+                    tracing::error!("Failed to relay ICE for call {}: {}", call_id, e);
+                    error(ErrorCode::Internal, "Failed to relay the candidate");
+                }
+            }
+        }
+
+        _ => {}
+    }
+}
+
+/// The user's last socket closed: hang up any call they're in
+pub async fn disconnected(pool: &PgPool, connections: &Connections, user_id: Uuid) {
+    let active = sqlx::query_scalar::<_, Uuid>(
+        "SELECT id FROM calls WHERE status IN ('ringing', 'accepted') AND (caller_id = $1 OR callee_id = $1)"
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await;
+
+    match active {
+        Ok(active) => {
+            for call_id in active {
+                finish_call(pool, connections, call_id, Some(user_id)).await;
+            }
+        }
+        Err(e) => tracing::error!("Failed to look up calls of {}: {}", user_id, e),
+    }
+}
+
+// ICE servers for calls and live sessions, with short-lived TURN credentials
+#[utoipa::path(
+    get,
+    path = "/api/v1/calls/ice-servers",
+    tag = "chat",
+    responses(
+        (status = 200, body = IceServersResponse),
+        (status = 503, description = "No TURN provider is configured, or it failed"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_ice_servers(user: AuthUser) -> Result<Json<IceServersResponse>, (StatusCode, String)> {
+    let provider = TurnProvider::from_env()
+        .ok_or((StatusCode::SERVICE_UNAVAILABLE, "TURN_PROVIDER is not configured".to_string()))?;
+
+    let credentials = turn_credentials(provider, user.id).await.map_err(|e| {
+        tracing::error!("Failed to get TURN credentials: {}", e);
+        (StatusCode::SERVICE_UNAVAILABLE, "TURN credentials are unavailable".to_string())
+    })?;
+
+    Ok(Json(credentials))
+}
+
+// Call history of a chat, newest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/chats/{chat_room_id}/calls",
+    tag = "chat",
+    params(("chat_room_id" = Uuid, Path, description = "Chat room ID")),
+    responses(
+        (status = 200, body = [Call]),
+        (status = 403, description = "The caller isn't a member of the chat"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_calls(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(chat_room_id): Path<Uuid>,
+) -> Result<Json<Vec<Call>>, (StatusCode, String)> {
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("Failed to list calls of chat {}: {}", chat_room_id, e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    };
+
+    let is_member = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM chat_members WHERE chat_room_id = $1 AND user_id = $2)"
+    )
+    .bind(chat_room_id)
+    .bind(user.id)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+    if !is_member {
+        return Err((StatusCode::FORBIDDEN, "You are not a member of this chat".to_string()));
+    }
+
+    let calls = sqlx::query_as::<_, Call>(
+        r#"
+        SELECT id, chat_room_id, caller_id, callee_id, media, status, created_at, answered_at, ended_at, duration_seconds
+        FROM calls
+        WHERE chat_room_id = $1
+        ORDER BY created_at DESC
+        LIMIT 100
+        "#
+    )
+    .bind(chat_room_id)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(calls))
+}
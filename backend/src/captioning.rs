@@ -0,0 +1,252 @@
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+use std::sync::OnceLock;
+use std::time::Duration;
+use uuid::Uuid;
+
+// Fallback alt text for stories posted without one. Images are captioned and
+// videos transcribed by the backend picked with CAPTION_PROVIDER:
+//   openai - vision model for images, Whisper for video audio
+//   http   - POST {media_url, media_type, task} to CAPTION_API_URL, expects {text}
+// Leave it unset to only use author-written alt text.
+
+pub const MAX_ALT_TEXT_LENGTH: usize = 1000;
+// Whisper rejects larger uploads
+const MAX_TRANSCRIBE_BYTES: usize = 25 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+enum Provider {
+    OpenAi,
+    Http,
+}
+
+impl Provider {
+    fn from_env() -> Option<Provider> {
+        match std::env::var("CAPTION_PROVIDER").ok()?.to_lowercase().as_str() {
+            "openai" => Some(Provider::OpenAi),
+            "http" => Some(Provider::Http),
+            _ => None,
+        }
+    }
+}
+
+/// Whether automatic alt text is configured
+pub fn is_enabled() -> bool {
+    Provider::from_env().is_some()
+}
+
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(120))
+            .build()
+            .expect("Failed to build captioning HTTP client")
+    })
+}
+
+fn api_key() -> String {
+    std::env::var("CAPTION_API_KEY").unwrap_or_default()
+}
+
+async fn describe_image_openai(media_url: &str) -> Result<String, String> {
+    #[derive(Deserialize)]
+    struct Response {
+        choices: Vec<Choice>,
+    }
+    #[derive(Deserialize)]
+    struct Choice {
+        message: Message,
+    }
+    #[derive(Deserialize)]
+    struct Message {
+        content: Option<String>,
+    }
+
+    let model = std::env::var("CAPTION_IMAGE_MODEL").unwrap_or_else(|_| "gpt-4o-mini".to_string());
+    let response: Response = http_client()
+        .post("https://api.openai.com/v1/chat/completions")
+        .bearer_auth(api_key())
+        .json(&json!({
+            "model": model,
+            "max_tokens": 150,
+            "messages": [{
+                "role": "user",
+                "content": [
+                    {
+                        "type": "text",
+                        "text": "Write alt text for this image for a screen reader user: one or two plain sentences describing what is shown, including any visible text. No preamble."
+                    },
+                    { "type": "image_url", "image_url": { "url": media_url } }
+                ]
+            }]
+        }))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Captioning request failed: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Unexpected captioning response: {}", e))?;
+
+    response
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|c| c.message.content)
+        .ok_or_else(|| "Captioning returned no text".to_string())
+}
+
+async fn transcribe_video_openai(media_url: &str) -> Result<String, String> {
+    #[derive(Deserialize)]
+    struct Response {
+        text: String,
+    }
+
+    let video = http_client()
+        .get(media_url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Failed to download video: {}", e))?
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to download video: {}", e))?;
+    if video.len() > MAX_TRANSCRIBE_BYTES {
+        return Err(format!("Video too large to transcribe ({} bytes)", video.len()));
+    }
+
+    let file = reqwest::multipart::Part::bytes(video.to_vec())
+        .file_name("story.mp4")
+        .mime_str("video/mp4")
+        .map_err(|e| e.to_string())?;
+    let form = reqwest::multipart::Form::new()
+        .text("model", "whisper-1")
+        .part("file", file);
+
+    let response: Response = http_client()
+        .post("https://api.openai.com/v1/audio/transcriptions")
+        .bearer_auth(api_key())
+        .multipart(form)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Transcription request failed: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Unexpected transcription response: {}", e))?;
+
+    Ok(response.text)
+}
+
+async fn describe_http(media_url: &str, media_type: &str) -> Result<String, String> {
+    #[derive(Deserialize)]
+    struct Response {
+        text: String,
+    }
+
+    let url = std::env::var("CAPTION_API_URL").map_err(|_| "CAPTION_API_URL is not set".to_string())?;
+    let task = if media_type == "video" { "transcribe" } else { "caption" };
+
+    let response: Response = http_client()
+        .post(url)
+        .bearer_auth(api_key())
+        .json(&json!({ "media_url": media_url, "media_type": media_type, "task": task }))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Captioning request failed: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Unexpected captioning response: {}", e))?;
+
+    Ok(response.text)
+}
+
+async fn describe(provider: Provider, media_url: &str, media_type: &str) -> Result<String, String> {
+    let text = match (provider, media_type) {
+        (Provider::OpenAi, "video") => transcribe_video_openai(media_url).await?,
+        (Provider::OpenAi, _) => describe_image_openai(media_url).await?,
+        (Provider::Http, _) => describe_http(media_url, media_type).await?,
+    };
+
+    let text = text.trim();
+    if media_type == "video" && !text.is_empty() {
+        Ok(truncate(&format!("Video. Audio: {}", text)))
+    } else {
+        Ok(truncate(text))
+    }
+}
+
+fn truncate(text: &str) -> String {
+    match text.char_indices().nth(MAX_ALT_TEXT_LENGTH) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct StoryMedia {
+    media_url: String,
+    media_type: String,
+    alt_text: Option<String>,
+}
+
+/// Queue alt text generation for a story posted without any
+pub async fn enqueue(pool: &PgPool, story_id: Uuid) {
+    if !is_enabled() {
+        return;
+    }
+    if let Err(e) = crate::jobs::enqueue(pool, crate::jobs::GENERATE_ALT_TEXT, json!({ "story_id": story_id }), None).await {
+        eprintln!("❌ Failed to queue alt text for story {}: {:?}", story_id, e);
+    }
+}
+
+/// Job body: caption or transcribe the story's media and store it as alt text,
+/// unless the author added some in the meantime
+pub async fn generate_alt_text(
+    pool: &PgPool,
+    media: &crate::media::MediaService,
+    payload: &serde_json::Value,
+) -> Result<Option<serde_json::Value>, String> {
+    let provider = Provider::from_env().ok_or_else(|| "CAPTION_PROVIDER is not configured".to_string())?;
+    let story_id = payload
+        .get("story_id")
+        .and_then(|v| v.as_str())
+        .and_then(|v| Uuid::parse_str(v).ok())
+        .ok_or_else(|| "Missing story_id".to_string())?;
+
+    let story = sqlx::query_as::<_, StoryMedia>(
+        "SELECT media_url, media_type, alt_text FROM stories WHERE id = $1 AND expires_at > NOW()"
+    )
+    .bind(story_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let Some(story) = story else {
+        return Ok(Some(json!({ "skipped": "story gone" })));
+    };
+    if story.alt_text.is_some() {
+        return Ok(Some(json!({ "skipped": "has alt text" })));
+    }
+
+    // The provider fetches the media itself, which needs a signed link when the bucket is private
+    let media_url = media.sign_url(&story.media_url).await;
+    let alt_text = describe(provider, &media_url, &story.media_type).await?;
+    if alt_text.is_empty() {
+        return Ok(Some(json!({ "skipped": "nothing to describe" })));
+    }
+
+    sqlx::query(
+        "UPDATE stories SET alt_text = $2, alt_text_generated = TRUE WHERE id = $1 AND alt_text IS NULL"
+    )
+    .bind(story_id)
+    .bind(&alt_text)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(Some(json!({ "story_id": story_id, "length": alt_text.chars().count() })))
+}
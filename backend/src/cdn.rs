@@ -0,0 +1,217 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use uuid::Uuid;
+
+// CDN in front of the media bucket. CDN_URL is the domain public media URLs
+// are rewritten to when they're handed out (the database keeps bucket URLs,
+// so the CDN can be switched or dropped without touching stored data).
+// CDN_PROVIDER picks how cached copies are purged once an object is deleted
+// or overwritten: CloudFront invalidations (CLOUDFRONT_DISTRIBUTION_ID, signed
+// with the AWS_* credentials) or Cloudflare's purge API (CLOUDFLARE_ZONE_ID
+// and CLOUDFLARE_API_TOKEN). Without a provider URLs are still rewritten but
+// nothing is purged, so deleted media lingers until the CDN's TTL runs out.
+//
+// Purges are best effort: a failed purge is logged and the delete or upload
+// that caused it stands.
+
+const PURGE_TIMEOUT: Duration = Duration::from_secs(15);
+// CloudFront accepts up to 3000 paths in flight per distribution
+const CLOUDFRONT_BATCH: usize = 1000;
+// Cloudflare's limit on files per purge request (outside Enterprise plans)
+const CLOUDFLARE_BATCH: usize = 30;
+
+fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(PURGE_TIMEOUT)
+            .build()
+            .expect("Failed to build CDN HTTP client")
+    })
+}
+
+/// Purge backend, picked with CDN_PROVIDER
+#[derive(Debug, Clone)]
+enum Provider {
+    CloudFront { distribution_id: String },
+    Cloudflare { zone_id: String, api_token: String },
+}
+
+impl Provider {
+    fn from_env() -> Option<Provider> {
+        match env("CDN_PROVIDER").unwrap_or_default().to_lowercase().as_str() {
+            "" | "none" => None,
+            "cloudfront" => match env("CLOUDFRONT_DISTRIBUTION_ID") {
+                Some(distribution_id) => Some(Provider::CloudFront { distribution_id }),
+                None => {
+                    tracing::warn!("CDN_PROVIDER=cloudfront without CLOUDFRONT_DISTRIBUTION_ID; CDN purging disabled");
+                    None
+                }
+            },
+            "cloudflare" => match (env("CLOUDFLARE_ZONE_ID"), env("CLOUDFLARE_API_TOKEN")) {
+                (Some(zone_id), Some(api_token)) => Some(Provider::Cloudflare { zone_id, api_token }),
+                _ => {
+                    tracing::warn!(
+                        "CDN_PROVIDER=cloudflare needs CLOUDFLARE_ZONE_ID and CLOUDFLARE_API_TOKEN; CDN purging disabled"
+                    );
+                    None
+                }
+            },
+            other => {
+                tracing::warn!("Unknown CDN_PROVIDER {:?}; CDN purging disabled", other);
+                None
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Provider::CloudFront { .. } => "CloudFront",
+            Provider::Cloudflare { .. } => "Cloudflare",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Cdn {
+    base_url: String,
+    provider: Option<Provider>,
+}
+
+impl Cdn {
+    /// The configured CDN, or None when CDN_URL isn't set
+    pub fn from_env() -> Option<Cdn> {
+        let provider = Provider::from_env();
+        let Some(base_url) = env("CDN_URL") else {
+            if provider.is_some() {
+                tracing::warn!("CDN_PROVIDER is set but CDN_URL isn't; media is served without the CDN");
+            }
+            return None;
+        };
+        Some(Cdn {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            provider,
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        match &self.provider {
+            Some(provider) => format!("{} ({} purging)", self.base_url, provider.name()),
+            None => format!("{} (no purging)", self.base_url),
+        }
+    }
+
+    /// CDN URL for an object key
+    pub fn url(&self, key: &str) -> String {
+        format!("{}/{}", self.base_url, key)
+    }
+
+    /// Object key for a URL on the CDN domain
+    pub fn key_from_url(&self, url: &str) -> Option<String> {
+        let key = url.strip_prefix(&self.base_url)?.strip_prefix('/')?;
+        Some(key.split(['?', '#']).next().unwrap_or(key).to_string())
+    }
+
+    /// Drop cached copies of these objects. Failures are logged.
+    pub async fn purge(&self, keys: &[String]) {
+        let Some(provider) = &self.provider else {
+            return;
+        };
+        if keys.is_empty() {
+            return;
+        }
+
+        let urls: Vec<String> = keys.iter().map(|key| self.url(key)).collect();
+        let result = match provider {
+            Provider::CloudFront { distribution_id } => {
+                let mut result = Ok(());
+                for chunk in urls.chunks(CLOUDFRONT_BATCH) {
+                    result = result.and(invalidate_cloudfront(distribution_id, chunk).await);
+                }
+                result
+            }
+            Provider::Cloudflare { zone_id, api_token } => {
+                let mut result = Ok(());
+                for chunk in urls.chunks(CLOUDFLARE_BATCH) {
+                    result = result.and(purge_cloudflare(zone_id, api_token, chunk).await);
+                }
+                result
+            }
+        };
+
+        match result {
+            Ok(()) => tracing::info!("Purged {} object(s) from {}", keys.len(), provider.name()),
+            Err(e) => tracing::error!("CDN purge of {} object(s) failed: {}", keys.len(), e),
+        }
+    }
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// One CloudFront invalidation for a batch of URLs, by their paths on the
+// distribution (which include any path prefix in CDN_URL)
+async fn invalidate_cloudfront(distribution_id: &str, urls: &[String]) -> Result<(), String> {
+    let paths: Vec<String> = urls
+        .iter()
+        .filter_map(|url| reqwest::Url::parse(url).ok())
+        .map(|url| url.path().to_string())
+        .collect();
+    let items: String = paths
+        .iter()
+        .map(|path| format!("<Path>{}</Path>", xml_escape(path)))
+        .collect();
+    let body = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?><InvalidationBatch xmlns="http://cloudfront.amazonaws.com/doc/2020-05-31/"><Paths><Quantity>{}</Quantity><Items>{}</Items></Paths><CallerReference>{}</CallerReference></InvalidationBatch>"#,
+        paths.len(),
+        items,
+        Uuid::new_v4()
+    );
+
+    // CloudFront is a global service, signed for us-east-1
+    let host = "cloudfront.amazonaws.com";
+    let path = format!("/2020-05-31/distribution/{}/invalidation", distribution_id);
+    let headers = crate::sigv4::sign("POST", host, &path, "us-east-1", "cloudfront", "text/xml", &body)?;
+    let mut request = http_client().post(format!("https://{}{}", host, path));
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+    let response = request
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| format!("CloudFront request failed: {}", e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let detail = response.text().await.unwrap_or_default();
+        return Err(format!("CloudFront answered {}: {}", status, detail));
+    }
+    Ok(())
+}
+
+async fn purge_cloudflare(zone_id: &str, api_token: &str, urls: &[String]) -> Result<(), String> {
+    let response = http_client()
+        .post(format!("https://api.cloudflare.com/client/v4/zones/{}/purge_cache", zone_id))
+        .bearer_auth(api_token)
+        .json(&serde_json::json!({ "files": urls }))
+        .send()
+        .await
+        .map_err(|e| format!("Cloudflare request failed: {}", e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let detail = response.text().await.unwrap_or_default();
+        return Err(format!("Cloudflare answered {}: {}", status, detail));
+    }
+    Ok(())
+}
@@ -0,0 +1,552 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::websocket::WsMessage;
+use crate::AppState;
+
+// Broadcast channels (room_type = 'channel', migration 059). A creator runs
+// one channel; followers subscribe by joining it as members. Only the owner
+// posts, through the usual send paths, and each post fans out to subscribers
+// over the WebSocket (deliver_message) and as a notification. Subscribers can
+// only react.
+
+const DESCRIPTION_MAX_LENGTH: usize = 500;
+const EMOJI_MAX_CHARS: usize = 8;
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct Channel {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub avatar_url: Option<String>,
+    pub owner_id: Uuid,
+    pub owner_username: String,
+    pub subscriber_count: i64,
+    /// Whether the caller is subscribed (always true for the owner)
+    pub subscribed: bool,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateChannelRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ReactRequest {
+    pub emoji: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct ReactionCount {
+    pub emoji: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReactionSummary {
+    pub message_id: Uuid,
+    /// Most used first
+    pub reactions: Vec<ReactionCount>,
+    pub my_reaction: Option<String>,
+}
+
+// $1 is always the caller
+const CHANNEL_COLUMNS: &str = r#"
+    r.id, r.name, r.description, r.avatar_url, r.created_by AS owner_id, u.username AS owner_username,
+    (SELECT COUNT(*) FROM chat_members m WHERE m.chat_room_id = r.id AND m.user_id <> r.created_by) AS subscriber_count,
+    EXISTS (SELECT 1 FROM chat_members me WHERE me.chat_room_id = r.id AND me.user_id = $1) AS subscribed,
+    r.created_at
+    FROM chat_rooms r
+    JOIN users u ON u.id = r.created_by
+"#;
+
+async fn fetch_channel(pool: &PgPool, room_id: Uuid, viewer_id: Uuid) -> Result<Option<Channel>, sqlx::Error> {
+    sqlx::query_as::<_, Channel>(&format!(
+        "SELECT {} WHERE r.id = $2 AND r.room_type = 'channel'",
+        CHANNEL_COLUMNS
+    ))
+    .bind(viewer_id)
+    .bind(room_id)
+    .fetch_optional(pool)
+    .await
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    eprintln!("❌ Channel query failed: {:?}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+}
+
+fn not_found() -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, "Channel not found".to_string())
+}
+
+/// Refuse a message to a channel from anyone but its owner. Other rooms pass.
+pub async fn check_send(pool: &PgPool, sender_id: Uuid, chat_room_id: Uuid) -> Result<(), (StatusCode, String)> {
+    let owner_id = sqlx::query_scalar::<_, Uuid>("SELECT created_by FROM chat_rooms WHERE id = $1 AND room_type = 'channel'")
+        .bind(chat_room_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?;
+
+    match owner_id {
+        Some(owner_id) if owner_id != sender_id => Err((
+            StatusCode::FORBIDDEN,
+            "Only the channel owner can post here; subscribers can react".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Notify every subscriber of a new channel post. Does nothing for
+/// messages outside channels.
+pub async fn notify_subscribers(pool: &PgPool, chat_room_id: Uuid, sender_id: Uuid) {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO notifications (user_id, type, from_user_id, message, message_key, message_args)
+        SELECT cm.user_id, 'channel_post', r.created_by, 'New post in ' || r.name,
+               'channel_post', jsonb_build_object('channel', r.name)
+        FROM chat_rooms r
+        JOIN chat_members cm ON cm.chat_room_id = r.id AND cm.user_id <> r.created_by
+        WHERE r.id = $1 AND r.room_type = 'channel' AND r.created_by = $2
+        "#
+    )
+    .bind(chat_room_id)
+    .bind(sender_id)
+    .execute(pool)
+    .await;
+
+    if let Err(e) = result {
+        eprintln!("❌ Failed to notify subscribers of channel {}: {:?}", chat_room_id, e);
+    }
+}
+
+/// The channel's owner, if the caller is subscribed to it (or owns it)
+async fn require_subscriber(pool: &PgPool, room_id: Uuid, user_id: Uuid) -> Result<Uuid, (StatusCode, String)> {
+    let (owner_id, subscribed) = sqlx::query_as::<_, (Uuid, bool)>(
+        r#"
+        SELECT r.created_by,
+               EXISTS (SELECT 1 FROM chat_members cm WHERE cm.chat_room_id = r.id AND cm.user_id = $2)
+        FROM chat_rooms r
+        WHERE r.id = $1 AND r.room_type = 'channel'
+        "#
+    )
+    .bind(room_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?
+    .ok_or_else(not_found)?;
+
+    if !subscribed {
+        return Err((StatusCode::FORBIDDEN, "Subscribe to this channel first".to_string()));
+    }
+    Ok(owner_id)
+}
+
+/// A live post in the channel
+async fn require_post(pool: &PgPool, room_id: Uuid, message_id: Uuid) -> Result<(), (StatusCode, String)> {
+    let exists = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM messages WHERE id = $1 AND chat_room_id = $2 AND deleted_at IS NULL)"
+    )
+    .bind(message_id)
+    .bind(room_id)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+
+    if !exists {
+        return Err((StatusCode::NOT_FOUND, "Post not found".to_string()));
+    }
+    Ok(())
+}
+
+// Start the caller's broadcast channel
+#[utoipa::path(
+    post,
+    path = "/api/v1/channels",
+    tag = "channels",
+    request_body = CreateChannelRequest,
+    responses(
+        (status = 201, body = Channel),
+        (status = 400, description = "Missing name, description too long, or language that isn't allowed"),
+        (status = 403, description = "Channels need a creator or business account"),
+        (status = 409, description = "The caller already has a channel"),
+        (status = 429, description = "Too many chats created this hour"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn create_channel(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CreateChannelRequest>,
+) -> axum::response::Result<(StatusCode, Json<Channel>)> {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Channels need a name".to_string()).into());
+    }
+    let description = payload.description.as_deref().map(str::trim).filter(|d| !d.is_empty());
+    if description.is_some_and(|d| d.chars().count() > DESCRIPTION_MAX_LENGTH) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Descriptions are limited to {} characters", DESCRIPTION_MAX_LENGTH),
+        )
+            .into());
+    }
+
+    let account_type = sqlx::query_scalar::<_, String>("SELECT account_type FROM users WHERE id = $1")
+        .bind(user.id)
+        .fetch_one(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+    if account_type == "personal" {
+        return Err((StatusCode::FORBIDDEN, "Channels need a creator or business account").into());
+    }
+
+    crate::quotas::check_chat_creation(&state.pool, user.id).await?;
+    let screened_name = crate::text_moderation::screen(&state.pool, name).await?;
+    let screened_description = match description {
+        Some(description) => Some(crate::text_moderation::screen(&state.pool, description).await?),
+        None => None,
+    };
+
+    let mut tx = state.pool.begin().await.map_err(db_error)?;
+    let room_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO chat_rooms (is_group, room_type, name, description, avatar_url, created_by)
+        VALUES (TRUE, 'channel', $1, $2, $3, $4)
+        RETURNING id
+        "#
+    )
+    .bind(&screened_name.text)
+    .bind(screened_description.as_ref().map(|s| s.text.as_str()))
+    .bind(payload.avatar_url.as_deref().map(str::trim).filter(|a| !a.is_empty()))
+    .bind(user.id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(ref db) if db.is_unique_violation() => {
+            (StatusCode::CONFLICT, "You already have a channel".to_string())
+        }
+        e => db_error(e),
+    })?;
+
+    sqlx::query("INSERT INTO chat_members (chat_room_id, user_id, role) VALUES ($1, $2, 'owner')")
+        .bind(room_id)
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+
+    for screened in std::iter::once(&screened_name).chain(screened_description.iter()) {
+        screened
+            .record(&state.pool, crate::text_moderation::CONTENT_GROUP_NAME, room_id, user.id)
+            .await;
+    }
+
+    let channel = fetch_channel(&state.pool, room_id, user.id)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Channel vanished".to_string()))?;
+
+    Ok((StatusCode::CREATED, Json(channel)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/channels/{room_id}",
+    tag = "channels",
+    params(("room_id" = Uuid, Path, description = "Channel chat room ID")),
+    responses((status = 200, body = Channel), (status = 404, description = "No such channel"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_channel(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<Channel>, (StatusCode, String)> {
+    let channel = fetch_channel(&state.pool, room_id, user.id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(not_found)?;
+    Ok(Json(channel))
+}
+
+// The channel a creator runs, for the subscribe button on their profile
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/{user_id}/channel",
+    tag = "channels",
+    params(("user_id" = Uuid, Path, description = "Creator's user ID")),
+    responses((status = 200, body = Channel), (status = 404, description = "The user has no channel"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_user_channel(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(owner_id): Path<Uuid>,
+) -> Result<Json<Channel>, (StatusCode, String)> {
+    let channel = sqlx::query_as::<_, Channel>(&format!(
+        "SELECT {} WHERE r.created_by = $2 AND r.room_type = 'channel'",
+        CHANNEL_COLUMNS
+    ))
+    .bind(user.id)
+    .bind(owner_id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(db_error)?
+    .ok_or_else(not_found)?;
+    Ok(Json(channel))
+}
+
+// Followers of the owner can subscribe
+#[utoipa::path(
+    post,
+    path = "/api/v1/channels/{room_id}/subscription",
+    tag = "channels",
+    params(("room_id" = Uuid, Path, description = "Channel chat room ID")),
+    responses(
+        (status = 200, body = Channel),
+        (status = 403, description = "The caller doesn't follow the channel owner"),
+        (status = 404, description = "No such channel"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn subscribe(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<Channel>, (StatusCode, String)> {
+    let channel = fetch_channel(&state.pool, room_id, user.id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(not_found)?;
+    if channel.subscribed {
+        return Ok(Json(channel));
+    }
+
+    let follows = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM follows WHERE follower_id = $1 AND following_id = $2)"
+    )
+    .bind(user.id)
+    .bind(channel.owner_id)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+    if !follows {
+        return Err((StatusCode::FORBIDDEN, format!("Follow {} to subscribe to their channel", channel.owner_username)));
+    }
+
+    sqlx::query("INSERT INTO chat_members (chat_room_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+        .bind(room_id)
+        .bind(user.id)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+
+    let channel = fetch_channel(&state.pool, room_id, user.id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(not_found)?;
+    Ok(Json(channel))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/channels/{room_id}/subscription",
+    tag = "channels",
+    params(("room_id" = Uuid, Path, description = "Channel chat room ID")),
+    responses(
+        (status = 204, description = "Unsubscribed"),
+        (status = 400, description = "The owner can't unsubscribe from their own channel"),
+        (status = 404, description = "No such channel"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn unsubscribe(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(room_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let owner_id = sqlx::query_scalar::<_, Uuid>("SELECT created_by FROM chat_rooms WHERE id = $1 AND room_type = 'channel'")
+        .bind(room_id)
+        .fetch_optional(state.pool.as_ref())
+        .await
+        .map_err(db_error)?
+        .ok_or_else(not_found)?;
+    if owner_id == user.id {
+        return Err((StatusCode::BAD_REQUEST, "You can't unsubscribe from your own channel".to_string()));
+    }
+
+    sqlx::query("DELETE FROM chat_members WHERE chat_room_id = $1 AND user_id = $2")
+        .bind(room_id)
+        .bind(user.id)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+    crate::cache::invalidate_membership(&state.redis, room_id, user.id).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// React to a post; reacting again replaces the earlier reaction
+#[utoipa::path(
+    put,
+    path = "/api/v1/channels/{room_id}/messages/{message_id}/reaction",
+    tag = "channels",
+    params(("room_id" = Uuid, Path, description = "Channel chat room ID"), ("message_id" = Uuid, Path, description = "Post ID")),
+    request_body = ReactRequest,
+    responses(
+        (status = 204, description = "Reaction saved"),
+        (status = 400, description = "Not an emoji"),
+        (status = 403, description = "The caller isn't subscribed"),
+        (status = 404, description = "No such channel or post"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn react(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path((room_id, message_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<ReactRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let emoji = payload.emoji.trim();
+    if emoji.is_empty()
+        || emoji.chars().count() > EMOJI_MAX_CHARS
+        || emoji.chars().any(|c| c.is_ascii_alphabetic() || c.is_whitespace())
+    {
+        return Err((StatusCode::BAD_REQUEST, "Reactions must be a single emoji".to_string()));
+    }
+
+    let owner_id = require_subscriber(&state.pool, room_id, user.id).await?;
+    require_post(&state.pool, room_id, message_id).await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO message_reactions (message_id, user_id, emoji) VALUES ($1, $2, $3)
+        ON CONFLICT (message_id, user_id) DO UPDATE SET emoji = EXCLUDED.emoji, created_at = NOW()
+        "#
+    )
+    .bind(message_id)
+    .bind(user.id)
+    .bind(emoji)
+    .execute(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    crate::websocket::send_to_user(
+        &state.connections,
+        owner_id,
+        &WsMessage::MessageReaction { message_id, chat_room_id: room_id, user_id: user.id, emoji: Some(emoji.to_string()) },
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/channels/{room_id}/messages/{message_id}/reaction",
+    tag = "channels",
+    params(("room_id" = Uuid, Path, description = "Channel chat room ID"), ("message_id" = Uuid, Path, description = "Post ID")),
+    responses(
+        (status = 204, description = "Reaction removed"),
+        (status = 403, description = "The caller isn't subscribed"),
+        (status = 404, description = "No such channel"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn remove_reaction(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path((room_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let owner_id = require_subscriber(&state.pool, room_id, user.id).await?;
+
+    let result = sqlx::query(
+        r#"
+        DELETE FROM message_reactions r
+        USING messages m
+        WHERE r.message_id = $1 AND r.user_id = $2 AND m.id = r.message_id AND m.chat_room_id = $3
+        "#
+    )
+    .bind(message_id)
+    .bind(user.id)
+    .bind(room_id)
+    .execute(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    if result.rows_affected() > 0 {
+        crate::websocket::send_to_user(
+            &state.connections,
+            owner_id,
+            &WsMessage::MessageReaction { message_id, chat_room_id: room_id, user_id: user.id, emoji: None },
+        );
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/channels/{room_id}/messages/{message_id}/reactions",
+    tag = "channels",
+    params(("room_id" = Uuid, Path, description = "Channel chat room ID"), ("message_id" = Uuid, Path, description = "Post ID")),
+    responses(
+        (status = 200, body = ReactionSummary),
+        (status = 403, description = "The caller isn't subscribed"),
+        (status = 404, description = "No such channel or post"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_reactions(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path((room_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ReactionSummary>, (StatusCode, String)> {
+    require_subscriber(&state.pool, room_id, user.id).await?;
+    require_post(&state.pool, room_id, message_id).await?;
+
+    let reactions = sqlx::query_as::<_, ReactionCount>(
+        r#"
+        SELECT emoji, COUNT(*) AS count
+        FROM message_reactions
+        WHERE message_id = $1
+        GROUP BY emoji
+        ORDER BY count DESC, emoji
+        "#
+    )
+    .bind(message_id)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    let my_reaction = sqlx::query_scalar::<_, String>("SELECT emoji FROM message_reactions WHERE message_id = $1 AND user_id = $2")
+        .bind(message_id)
+        .bind(user.id)
+        .fetch_optional(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(ReactionSummary { message_id, reactions, my_reaction }))
+}
@@ -1,67 +1,118 @@
 use axum::{
     extract::{Json, State, Path, Query},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
+    response::Redirect,
 };
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
+use std::collections::HashMap;
 use std::sync::Arc;
 use chrono::NaiveDateTime;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct CreateChatRequest {
     pub creator_id: Uuid, // User creating the chat
     pub is_group: bool,
     pub name: Option<String>,
     pub member_ids: Vec<Uuid>, // User IDs to add to chat
+    /// Members encrypt message content client-side; the server won't process it
+    #[serde(default)]
+    pub is_e2ee: bool,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct ChatRoomResponse {
     pub id: Uuid,
     pub name: Option<String>,
     pub is_group: bool,
+    #[serde(with = "crate::timestamps")]
     pub created_at: NaiveDateTime,
+    /// pending while a 1:1 chat waits on its message request, accepted otherwise
+    pub state: String,
+    /// Messages are removed this long after they're sent; null keeps them
+    pub retention_seconds: Option<i32>,
     pub members: Vec<ChatMemberResponse>,
     pub last_message: Option<MessageResponse>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(sqlx::FromRow)]
+struct ChatRoomRow {
+    id: Uuid,
+    name: Option<String>,
+    is_group: bool,
+    created_at: NaiveDateTime,
+    state: String,
+    retention_seconds: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct ChatMemberResponse {
     pub user_id: Uuid,
     pub username: String,
+    #[serde(with = "crate::timestamps")]
     pub joined_at: NaiveDateTime,
+    /// Online status and last activity; null unless you're mutuals and both
+    /// of you share your activity status
+    pub activity: Option<crate::activity_status::ActivityStatus>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, ToSchema)]
 pub struct MessageResponse {
     pub id: Uuid,
     pub chat_room_id: Uuid,
     pub sender_id: Uuid,
     pub sender_username: String,
+    /// text, image, video, gif, sticker, story (content is the story id) or
+    /// unsent (taken back by the sender, without content or media)
     pub message_type: String,
     pub content: Option<String>,
     pub media_url: Option<String>,
     pub media_thumbnail_url: Option<String>,
     pub view_once: bool,
     pub is_ephemeral: bool,
+    #[serde(with = "crate::timestamps::option")]
     pub expires_at: Option<NaiveDateTime>,
+    #[serde(with = "crate::timestamps")]
     pub created_at: NaiveDateTime,
     pub is_viewed: bool,
     pub is_read: bool,
     pub is_saved: bool,
+    /// The viewer's muted word this message contains, matched when it was
+    /// sent; clients show it folded behind a warning
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[sqlx(default)]
+    pub muted_word: Option<String>,
+    /// On your own messages: sent, delivered (on a device of every other
+    /// member) or read (by every other member). Unset on others' messages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[sqlx(default)]
+    pub delivery_state: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct GetMessagesQuery {
     pub limit: Option<i64>,
     pub before: Option<Uuid>, // Message ID for pagination
 }
 
 // Create a new chat room
+#[utoipa::path(
+    post,
+    path = "/api/v1/chats",
+    tag = "chat",
+    request_body = CreateChatRequest,
+    responses(
+        (status = 200, body = ChatRoomResponse),
+        (status = 403, description = "A member is a minor who doesn't follow the creator, or their guardian limits who can message them"),
+        (status = 429, description = "Too many chats created this hour")
+    )
+)]
 pub async fn create_chat(
     State(state): State<Arc<crate::AppState>>,
     Json(payload): Json<CreateChatRequest>,
-) -> Result<Json<ChatRoomResponse>, StatusCode> {
+) -> axum::response::Result<Json<ChatRoomResponse>> {
     let pool = &state.pool;
     let creator_id = payload.creator_id;
 
@@ -98,13 +149,14 @@ pub async fn create_chat(
                 user_id: r.user_id,
                 username: r.username,
                 joined_at: r.joined_at,
+                activity: None,
             })
             .collect();
 
-            let existing_room = sqlx::query!(
-                "SELECT id, name, is_group, created_at FROM chat_rooms WHERE id = $1",
-                chat_id
+            let existing_room = sqlx::query_as::<_, ChatRoomRow>(
+                "SELECT id, name, is_group, created_at, state, retention_seconds FROM chat_rooms WHERE id = $1"
             )
+            .bind(chat_id)
             .fetch_one(pool.as_ref())
             .await
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -114,12 +166,26 @@ pub async fn create_chat(
                 name: existing_room.name,
                 is_group: existing_room.is_group,
                 created_at: existing_room.created_at,
+                state: existing_room.state,
+                retention_seconds: existing_room.retention_seconds,
                 members,
                 last_message: None,
             }));
         }
     }
 
+    crate::quotas::check_chat_creation(pool, creator_id).await?;
+    crate::age_gate::check_chat_members(pool, creator_id, &payload.member_ids).await?;
+    crate::supervision::check_chat_members(pool, creator_id, &payload.member_ids).await?;
+    let requests = crate::message_requests::check_new_chat(pool, creator_id, &payload.member_ids).await?;
+    let screened_name = match &payload.name {
+        Some(name) if payload.is_group => Some(crate::text_moderation::screen(pool, name).await?),
+        _ => None,
+    };
+
+    // Room and members are created together or not at all
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
     // Create chat room (name is NULL for 1:1 chats)
     let chat_room = sqlx::query!(
         r#"
@@ -128,13 +194,21 @@ pub async fn create_chat(
         RETURNING id, name, is_group, created_at, updated_at
         "#,
         payload.is_group,
-        if payload.is_group { payload.name } else { None },
+        screened_name.as_ref().map(|s| s.text.clone()),
         creator_id
     )
-    .fetch_one(pool.as_ref())
+    .fetch_one(&mut *tx)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    if payload.is_e2ee {
+        sqlx::query("UPDATE chat_rooms SET is_e2ee = TRUE WHERE id = $1")
+            .bind(chat_room.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
     // Add creator as member
     let mut all_member_ids = payload.member_ids.clone();
     all_member_ids.push(creator_id);
@@ -145,11 +219,25 @@ pub async fn create_chat(
             chat_room.id,
             member_id
         )
-        .execute(pool.as_ref())
+        .execute(&mut *tx)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     }
 
+    // Members whose DM privacy doesn't allow the creator get a message request
+    if !requests.is_empty() {
+        crate::message_requests::create_requests(&mut tx, chat_room.id, creator_id, &requests)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if let Some(screened) = &screened_name {
+        screened
+            .record(pool, crate::text_moderation::CONTENT_GROUP_NAME, chat_room.id, creator_id)
+            .await;
+    }
+
     // Fetch members
     let members = sqlx::query!(
         r#"
@@ -168,6 +256,7 @@ pub async fn create_chat(
         user_id: r.user_id,
         username: r.username,
         joined_at: r.joined_at,
+        activity: None,
     })
     .collect();
 
@@ -176,27 +265,39 @@ pub async fn create_chat(
         name: chat_room.name,
         is_group: chat_room.is_group,
         created_at: chat_room.created_at,
+        state: if !payload.is_group && !requests.is_empty() { "pending" } else { "accepted" }.to_string(),
+        retention_seconds: None,
         members,
         last_message: None,
     }))
 }
 
 // Get user's chat rooms
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/{user_id}/chats",
+    tag = "chat",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200, body = [ChatRoomResponse]), (status = 304, description = "Unchanged since the ETag in If-None-Match"))
+)]
 pub async fn get_user_chats(
     State(state): State<Arc<crate::AppState>>,
     Path(user_id): Path<Uuid>,
 ) -> Result<Json<Vec<ChatRoomResponse>>, StatusCode> {
     let pool = &state.pool;
-    let chat_rooms = sqlx::query!(
+    // Chats waiting on the user's answer to a message request live in /message-requests
+    let chat_rooms = sqlx::query_as::<_, ChatRoomRow>(
         r#"
-        SELECT DISTINCT cr.id, cr.name, cr.is_group, cr.created_at, cr.updated_at
+        SELECT cr.id, cr.name, cr.is_group, cr.created_at, cr.state, cr.retention_seconds
         FROM chat_rooms cr
         JOIN chat_members cm ON cr.id = cm.chat_room_id
         WHERE cm.user_id = $1
+          AND NOT EXISTS(SELECT 1 FROM message_requests
+                         WHERE chat_room_id = cr.id AND recipient_id = $1 AND status = 'pending')
         ORDER BY cr.updated_at DESC
-        "#,
-        user_id
+        "#
     )
+    .bind(user_id)
     .fetch_all(pool.as_ref())
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -222,6 +323,7 @@ pub async fn get_user_chats(
             user_id: r.user_id,
             username: r.username,
             joined_at: r.joined_at,
+            activity: None,
         })
         .collect();
 
@@ -235,56 +337,73 @@ pub async fn get_user_chats(
         };
 
         // Get last message
-        let last_msg = sqlx::query!(
+        let last_msg = sqlx::query_as::<_, MessageResponse>(
             r#"
-            SELECT m.id, m.sender_id, u.username as sender_username,
+            SELECT m.id, m.chat_room_id, m.sender_id, u.username as sender_username,
                    m.message_type, m.content, m.media_url, m.media_thumbnail_url,
                    m.view_once, m.is_ephemeral, m.expires_at, m.created_at,
-                   EXISTS(SELECT 1 FROM saved_messages WHERE message_id = m.id AND user_id = $2) as "is_saved!"
+                   FALSE AS is_viewed, FALSE AS is_read,
+                   EXISTS(SELECT 1 FROM saved_messages WHERE message_id = m.id AND user_id = $2) as is_saved,
+                   (SELECT muted_word FROM message_recipients WHERE message_id = m.id AND user_id = $2) AS muted_word,
+                   CASE WHEN m.sender_id = $2 THEN message_delivery_state(m.id) END AS delivery_state
             FROM messages m
             JOIN users u ON m.sender_id = u.id
             WHERE m.chat_room_id = $1 AND m.deleted_at IS NULL
+              AND NOT EXISTS(SELECT 1 FROM message_recipients WHERE message_id = m.id AND user_id = $2 AND hidden_at IS NOT NULL)
             ORDER BY m.created_at DESC
             LIMIT 1
-            "#,
-            room.id,
-            user_id
+            "#
         )
+        .bind(room.id)
+        .bind(user_id)
         .fetch_optional(pool.as_ref())
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .map(|r| MessageResponse {
-            id: r.id,
-            chat_room_id: room.id,
-            sender_id: r.sender_id,
-            sender_username: r.sender_username,
-            message_type: r.message_type,
-            content: r.content,
-            media_url: r.media_url,
-            media_thumbnail_url: r.media_thumbnail_url,
-            view_once: r.view_once,
-            is_ephemeral: r.is_ephemeral,
-            expires_at: r.expires_at,
-            created_at: r.created_at,
-            is_viewed: false,
-            is_read: false,
-            is_saved: r.is_saved,
-        });
+        // Hide messages that expired but haven't been swept yet; saved ones are retained
+        .filter(|r| r.is_saved || !crate::expiration::is_expired(r.expires_at));
+        let last_msg = match last_msg {
+            Some(mut msg) => {
+                msg.sign_media(&state.media_service, user_id).await;
+                Some(msg)
+            }
+            None => None,
+        };
 
         responses.push(ChatRoomResponse {
             id: room.id,
             name: chat_name,
             is_group: room.is_group,
             created_at: room.created_at,
+            state: room.state,
+            retention_seconds: room.retention_seconds,
             members,
             last_message: last_msg,
         });
     }
 
+    let mut others: Vec<Uuid> = responses
+        .iter()
+        .flat_map(|room| room.members.iter().map(|m| m.user_id))
+        .filter(|id| *id != user_id)
+        .collect();
+    others.sort();
+    others.dedup();
+    let activity = crate::activity_status::visible_to(&state.pool, &state.redis, user_id, &others).await;
+    for member in responses.iter_mut().flat_map(|room| room.members.iter_mut()) {
+        member.activity = activity.get(&member.user_id).cloned();
+    }
+
     Ok(Json(responses))
 }
 
 // Get messages for a chat room
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/{user_id}/chats/{chat_room_id}/messages",
+    tag = "chat",
+    params(("user_id" = Uuid, Path, description = "User ID"), ("chat_room_id" = Uuid, Path, description = "Chat room ID"), GetMessagesQuery),
+    responses((status = 200, body = [MessageResponse]))
+)]
 pub async fn get_messages(
     State(state): State<Arc<crate::AppState>>,
     Path((user_id, chat_room_id)): Path<(Uuid, Uuid)>,
@@ -304,56 +423,281 @@ pub async fn get_messages(
         None
     };
 
-    // Fetch messages with optional before filter
-    let messages = sqlx::query!(
+    // Fetch messages with optional before filter, minus those hidden from this member
+    let mut response: Vec<MessageResponse> = sqlx::query_as::<_, MessageResponse>(
         r#"
         SELECT m.id, m.chat_room_id, m.sender_id, u.username as sender_username,
                m.message_type, m.content, m.media_url, m.media_thumbnail_url,
                m.view_once, m.is_ephemeral, m.expires_at, m.created_at,
-               EXISTS(SELECT 1 FROM message_views WHERE message_id = m.id AND user_id = $2) as "is_viewed!",
-               EXISTS(SELECT 1 FROM message_reads WHERE message_id = m.id AND user_id = $2) as "is_read!",
-               EXISTS(SELECT 1 FROM saved_messages WHERE message_id = m.id AND user_id = $2) as "is_saved!"
+               EXISTS(SELECT 1 FROM message_views WHERE message_id = m.id AND user_id = $2) as is_viewed,
+               EXISTS(SELECT 1 FROM message_reads WHERE message_id = m.id AND user_id = $2) as is_read,
+               EXISTS(SELECT 1 FROM saved_messages WHERE message_id = m.id AND user_id = $2) as is_saved,
+               (SELECT muted_word FROM message_recipients WHERE message_id = m.id AND user_id = $2) AS muted_word,
+               CASE WHEN m.sender_id = $2 THEN message_delivery_state(m.id) END AS delivery_state
         FROM messages m
         JOIN users u ON m.sender_id = u.id
         WHERE m.chat_room_id = $1 AND m.deleted_at IS NULL
+              AND NOT EXISTS(SELECT 1 FROM message_recipients WHERE message_id = m.id AND user_id = $2 AND hidden_at IS NOT NULL)
               AND ($3::timestamp IS NULL OR m.created_at < $3)
         ORDER BY m.created_at DESC
         LIMIT $4
-        "#,
-        chat_room_id,
-        user_id,
-        before_time,
-        limit
+        "#
+    )
+    .bind(chat_room_id)
+    .bind(user_id)
+    .bind(before_time)
+    .bind(limit)
+    .fetch_all(pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .into_iter()
+    // Hide messages that expired but haven't been swept yet; saved ones are retained
+    .filter(|r| r.is_saved || !crate::expiration::is_expired(r.expires_at))
+    .collect();
+
+    for message in &mut response {
+        message.sign_media(&state.media_service, user_id).await;
+    }
+
+    // Fetching a message delivers it
+    let message_ids: Vec<Uuid> = response.iter().map(|m| m.id).collect();
+    let delivered = sqlx::query_as::<_, (Uuid, NaiveDateTime)>(
+        r#"
+        UPDATE message_recipients SET delivered_at = NOW()
+        WHERE user_id = $1 AND message_id = ANY($2) AND delivered_at IS NULL
+        RETURNING message_id, delivered_at
+        "#
     )
+    .bind(user_id)
+    .bind(&message_ids)
     .fetch_all(pool.as_ref())
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    for (message_id, delivered_at) in delivered {
+        if let Some(message) = response.iter().find(|m| m.id == message_id) {
+            announce_delivery(&state.connections, message.sender_id, message_id, user_id, delivered_at);
+        }
+    }
+
+    Ok(Json(response))
+}
+
+// Delete a message for the caller only; the other members keep it. Once
+// every member has deleted it, it's removed for good on the next sweep.
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/{user_id}/messages/{message_id}/delete-for-me",
+    tag = "chat",
+    params(("user_id" = Uuid, Path, description = "User ID"), ("message_id" = Uuid, Path, description = "Message ID")),
+    responses(
+        (status = 200, description = "Success"),
+        (status = 403, description = "Not your account, or not a member of this chat"),
+        (status = 404, description = "Message not found"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn delete_message_for_me(
+    user: crate::admin::AuthUser,
+    State(state): State<Arc<crate::AppState>>,
+    Path((user_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    if user.id != user_id {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let is_member = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(SELECT 1 FROM chat_members cm WHERE cm.chat_room_id = m.chat_room_id AND cm.user_id = $2)
+        FROM messages m
+        WHERE m.id = $1 AND m.deleted_at IS NULL
+        "#
+    )
+    .bind(message_id)
+    .bind(user_id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+    if !is_member {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let mut tx = state.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Deleting drops the user's save too, so it no longer keeps the message alive
+    sqlx::query("DELETE FROM saved_messages WHERE message_id = $1 AND user_id = $2")
+        .bind(message_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    sqlx::query("SELECT hide_message_for($1, $2, 'deleted')")
+        .bind(message_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Nobody can see it any more: hand it to the expiration sweep, which
+    // deletes retained messages nobody has saved along with their media
+    sqlx::query(
+        r#"
+        UPDATE messages m SET retained_at = COALESCE(m.retained_at, NOW())
+        WHERE m.id = $1
+          AND NOT EXISTS (
+              SELECT 1 FROM chat_members cm
+              WHERE cm.chat_room_id = m.chat_room_id
+                AND NOT EXISTS (
+                    SELECT 1 FROM message_recipients r
+                    WHERE r.message_id = m.id AND r.user_id = cm.user_id AND r.hidden_at IS NOT NULL
+                )
+          )
+        "#
+    )
+    .bind(message_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // The user's other devices drop it too
+    let event = crate::websocket::WsMessage::MessageExpired { message_id };
+    crate::websocket::send_to_user(&state.connections, user_id, &event);
+
+    Ok(StatusCode::OK)
+}
+
+/// How long after sending a message its sender can still unsend it
+pub const UNSEND_WINDOW_SECONDS: f64 = 30.0;
+
+#[derive(sqlx::FromRow)]
+struct UnsentMessage {
+    chat_room_id: Uuid,
+    sender_id: Uuid,
+    media_url: Option<String>,
+    media_thumbnail_url: Option<String>,
+}
+
+/// Take back a message the user sent within the unsend window, for every
+/// member. Its content, media and saves go; the row stays in the chat as an
+/// 'unsent' placeholder (migration 066) and members are told with
+/// MessageDeleted. Errors are meant for the sender.
+pub async fn unsend_message(
+    pool: &sqlx::PgPool,
+    connections: &crate::websocket::Connections,
+    media: &crate::media::MediaService,
+    user_id: Uuid,
+    message_id: Uuid,
+) -> Result<(), (crate::websocket::ErrorCode, String)> {
+    use crate::websocket::ErrorCode;
+
+    retract(pool, connections, media, user_id, message_id, None)
+        .await
+        .map_err(|message| (ErrorCode::Internal, message))?
+        .ok_or_else(|| (ErrorCode::Unauthorized, "This message can no longer be unsent".to_string()))?;
+    println!("↩️  Message {} unsent by {}", message_id, user_id);
+    Ok(())
+}
+
+/// Remove a message posted in a community on a moderator's behalf, the same
+/// way its sender could unsend it but without the time limit. Returns the
+/// sender, or None if there's no such message in the room.
+pub async fn remove_community_message(
+    pool: &sqlx::PgPool,
+    connections: &crate::websocket::Connections,
+    media: &crate::media::MediaService,
+    moderator_id: Uuid,
+    chat_room_id: Uuid,
+    message_id: Uuid,
+) -> Result<Option<Uuid>, String> {
+    retract(pool, connections, media, moderator_id, message_id, Some(chat_room_id)).await
+}
+
+// Turn a message into an 'unsent' placeholder. Without `moderated_room` only
+// the sender may, within the unsend window; with it, anyone acting for that
+// room may at any time. Returns the message's sender.
+async fn retract(
+    pool: &sqlx::PgPool,
+    connections: &crate::websocket::Connections,
+    media: &crate::media::MediaService,
+    actor_id: Uuid,
+    message_id: Uuid,
+    moderated_room: Option<Uuid>,
+) -> Result<Option<Uuid>, String> {
+    let failed = |e: sqlx::Error| {
+        tracing::error!("Failed to unsend message {}: {}", message_id, e);
+        "Failed to unsend message".to_string()
+    };
+
+    let mut tx = pool.begin().await.map_err(failed)?;
+    // RETURNING only sees the new row, so the media URLs come from the locked old one
+    let unsent = sqlx::query_as::<_, UnsentMessage>(
+        r#"
+        UPDATE messages m
+        SET message_type = 'unsent', content = NULL, media_url = NULL, media_thumbnail_url = NULL, unsent_at = NOW()
+        FROM (SELECT id, media_url, media_thumbnail_url FROM messages WHERE id = $1 FOR UPDATE) old
+        WHERE m.id = old.id
+          AND m.deleted_at IS NULL
+          AND m.unsent_at IS NULL
+          AND CASE WHEN $4::uuid IS NULL
+                   THEN m.sender_id = $2 AND m.created_at > NOW() - make_interval(secs => $3)
+                   ELSE m.chat_room_id = $4
+              END
+        RETURNING m.chat_room_id, m.sender_id, old.media_url, old.media_thumbnail_url
+        "#
+    )
+    .bind(message_id)
+    .bind(actor_id)
+    .bind(UNSEND_WINDOW_SECONDS)
+    .bind(moderated_room)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(failed)?;
+    let Some(unsent) = unsent else {
+        return Ok(None);
+    };
 
-    let response: Vec<MessageResponse> = messages
+    // Nobody keeps a copy
+    sqlx::query("DELETE FROM saved_messages WHERE message_id = $1")
+        .bind(message_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(failed)?;
+
+    let s3_keys: Vec<String> = [unsent.media_url.as_deref(), unsent.media_thumbnail_url.as_deref()]
         .into_iter()
-        .map(|r| MessageResponse {
-            id: r.id,
-            chat_room_id: r.chat_room_id,
-            sender_id: r.sender_id,
-            sender_username: r.sender_username,
-            message_type: r.message_type,
-            content: r.content,
-            media_url: r.media_url,
-            media_thumbnail_url: r.media_thumbnail_url,
-            view_once: r.view_once,
-            is_ephemeral: r.is_ephemeral,
-            expires_at: r.expires_at,
-            created_at: r.created_at,
-            is_viewed: r.is_viewed,
-            is_read: r.is_read,
-            is_saved: r.is_saved,
-        })
+        .flatten()
+        .filter_map(|url| media.s3_key_from_url(url))
         .collect();
+    let outbox_entry = crate::media_outbox::release_and_queue(&mut tx, &s3_keys, "unsent").await.map_err(|e| {
+        tracing::error!("Failed to release media of unsent message {}: {}", message_id, e);
+        "Failed to unsend message".to_string()
+    })?;
 
-    Ok(Json(response))
+    tx.commit().await.map_err(failed)?;
+    crate::media_outbox::flush(pool, media, outbox_entry).await;
+
+    let event = crate::websocket::WsMessage::MessageDeleted {
+        message_id,
+        chat_room_id: unsent.chat_room_id,
+        unsent_by: actor_id,
+    };
+    crate::websocket::broadcast_to_room(pool, connections, unsent.chat_room_id, &event).await;
+
+    Ok(Some(unsent.sender_id))
 }
 
 // Mark message as viewed (triggers auto-delete for view_once messages)
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/{user_id}/messages/{message_id}/view",
+    tag = "chat",
+    params(("user_id" = Uuid, Path, description = "User ID"), ("message_id" = Uuid, Path, description = "Message ID")),
+    responses((status = 200, description = "Success"))
+)]
 pub async fn mark_message_viewed(
     State(state): State<Arc<crate::AppState>>,
     Path((user_id, message_id)): Path<(Uuid, Uuid)>,
@@ -377,13 +721,124 @@ pub async fn mark_message_viewed(
     Ok(StatusCode::OK)
 }
 
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MessageMediaQuery {
+    /// Set to true for the thumbnail instead of the full media
+    #[serde(default)]
+    pub thumbnail: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct MessageMediaRow {
+    sender_id: Uuid,
+    media_url: Option<String>,
+    media_thumbnail_url: Option<String>,
+    view_once: bool,
+    expires_at: Option<NaiveDateTime>,
+    is_member: bool,
+    is_viewed: bool,
+    is_saved: bool,
+}
+
+// Stable link to a message's media: checks the caller may still see it, then
+// redirects to a short-lived signed URL
+#[utoipa::path(
+    get,
+    path = "/api/v1/messages/{message_id}/media",
+    tag = "chat",
+    params(("message_id" = Uuid, Path, description = "Message ID"), MessageMediaQuery),
+    responses(
+        (status = 307, description = "Redirect to a short-lived media URL"),
+        (status = 403, description = "Not a member of this chat"),
+        (status = 404, description = "Message or media not found"),
+        (status = 410, description = "View-once media was already opened"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_message_media(
+    user: crate::admin::AuthUser,
+    State(state): State<Arc<crate::AppState>>,
+    Path(message_id): Path<Uuid>,
+    Query(params): Query<MessageMediaQuery>,
+) -> Result<Redirect, StatusCode> {
+    let message = sqlx::query_as::<_, MessageMediaRow>(
+        r#"
+        SELECT m.sender_id, m.media_url, m.media_thumbnail_url, m.view_once, m.expires_at,
+               EXISTS(SELECT 1 FROM chat_members WHERE chat_room_id = m.chat_room_id AND user_id = $2) AS is_member,
+               EXISTS(SELECT 1 FROM message_views WHERE message_id = m.id AND user_id = $2) AS is_viewed,
+               EXISTS(SELECT 1 FROM saved_messages WHERE message_id = m.id AND user_id = $2) AS is_saved
+        FROM messages m
+        WHERE m.id = $1 AND m.deleted_at IS NULL
+          AND NOT EXISTS(SELECT 1 FROM message_recipients WHERE message_id = m.id AND user_id = $2 AND hidden_at IS NOT NULL)
+        "#
+    )
+    .bind(message_id)
+    .bind(user.id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .filter(|m| m.is_saved || !crate::expiration::is_expired(m.expires_at))
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    if !message.is_member {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if message.view_once && message.is_viewed && !message.is_saved && message.sender_id != user.id {
+        return Err(StatusCode::GONE);
+    }
+
+    let url = if params.thumbnail { message.media_thumbnail_url } else { message.media_url }
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    // Always sign here, even when listings hand out permanent URLs
+    let media = &state.media_service;
+    let target = match media.s3_key_from_url(&media.canonical_url(&url)) {
+        Some(key) => media.presign_get(&key, media.signed_url_ttl).await.map_err(|e| {
+            eprintln!("❌ {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?,
+        None => url,
+    };
+
+    Ok(Redirect::temporary(&target))
+}
+
 // Save a message (prevents auto-delete)
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/{user_id}/messages/{message_id}/save",
+    tag = "chat",
+    params(("user_id" = Uuid, Path, description = "User ID"), ("message_id" = Uuid, Path, description = "Message ID")),
+    responses((status = 200, description = "Success"), (status = 404, description = "Message not found or no longer visible"))
+)]
 pub async fn save_message(
     State(state): State<Arc<crate::AppState>>,
     Path((user_id, message_id)): Path<(Uuid, Uuid)>,
 ) -> Result<StatusCode, StatusCode> {
     let pool = &state.pool;
 
+    // Only messages the user can still see; a retained message hidden from
+    // them can't be brought back by saving it
+    let visible = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM messages m
+            WHERE m.id = $1 AND m.deleted_at IS NULL
+              AND NOT EXISTS(SELECT 1 FROM message_recipients WHERE message_id = m.id AND user_id = $2 AND hidden_at IS NOT NULL)
+        )
+        "#
+    )
+    .bind(message_id)
+    .bind(user_id)
+    .fetch_one(pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !visible {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
     sqlx::query!(
         r#"
         INSERT INTO saved_messages (message_id, user_id)
@@ -400,7 +855,14 @@ pub async fn save_message(
     Ok(StatusCode::OK)
 }
 
-// Unsave a message (allows auto-delete again)
+// Unsave a message (allows auto-delete again; a retained message is hidden from the user right away)
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/{user_id}/messages/{message_id}/unsave",
+    tag = "chat",
+    params(("user_id" = Uuid, Path, description = "User ID"), ("message_id" = Uuid, Path, description = "Message ID")),
+    responses((status = 200, description = "Success"))
+)]
 pub async fn unsave_message(
     State(state): State<Arc<crate::AppState>>,
     Path((user_id, message_id)): Path<(Uuid, Uuid)>,
@@ -419,29 +881,431 @@ pub async fn unsave_message(
     Ok(StatusCode::OK)
 }
 
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SavedMessagesQuery {
+    pub limit: Option<i64>,
+    /// Message ID of the last saved message on the previous page
+    pub before: Option<Uuid>,
+    /// Only messages from this chat
+    pub chat_room_id: Option<Uuid>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SavedMessage {
+    #[serde(flatten)]
+    pub message: MessageResponse,
+    #[serde(with = "crate::timestamps")]
+    pub saved_at: NaiveDateTime,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SavedChatGroup {
+    pub chat_room_id: Uuid,
+    pub chat_name: Option<String>,
+    pub is_group: bool,
+    /// Most recently saved first
+    pub messages: Vec<SavedMessage>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SavedMessagesResponse {
+    /// Ordered by their most recently saved message
+    pub chats: Vec<SavedChatGroup>,
+    /// Pass as `before` for the next page; absent on the last page
+    pub next_before: Option<Uuid>,
+}
+
+#[derive(sqlx::FromRow)]
+struct SavedMessageRow {
+    id: Uuid,
+    chat_room_id: Uuid,
+    chat_name: Option<String>,
+    is_group: bool,
+    sender_id: Uuid,
+    sender_username: String,
+    message_type: String,
+    content: Option<String>,
+    media_url: Option<String>,
+    media_thumbnail_url: Option<String>,
+    view_once: bool,
+    is_ephemeral: bool,
+    expires_at: Option<NaiveDateTime>,
+    created_at: NaiveDateTime,
+    is_viewed: bool,
+    is_read: bool,
+    saved_at: NaiveDateTime,
+}
+
+// Messages the user saved, grouped by chat. Saved messages outlive their
+// expiry and view-once deletion, so they're listed regardless of either.
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/{user_id}/saved-messages",
+    tag = "chat",
+    params(("user_id" = Uuid, Path, description = "User ID"), SavedMessagesQuery),
+    responses(
+        (status = 200, body = SavedMessagesResponse),
+        (status = 403, description = "Not your account"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_saved_messages(
+    user: crate::admin::AuthUser,
+    State(state): State<Arc<crate::AppState>>,
+    Path(user_id): Path<Uuid>,
+    Query(params): Query<SavedMessagesQuery>,
+) -> Result<Json<SavedMessagesResponse>, StatusCode> {
+    if user.id != user_id {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let limit = params.limit.unwrap_or(50).clamp(1, 100);
+
+    // Fetch one extra row to know whether another page follows
+    let mut rows = sqlx::query_as::<_, SavedMessageRow>(
+        r#"
+        SELECT m.id, m.chat_room_id, cr.name AS chat_name, cr.is_group,
+               m.sender_id, u.username AS sender_username,
+               m.message_type, m.content, m.media_url, m.media_thumbnail_url,
+               m.view_once, m.is_ephemeral, m.expires_at, m.created_at,
+               EXISTS(SELECT 1 FROM message_views WHERE message_id = m.id AND user_id = $1) AS is_viewed,
+               EXISTS(SELECT 1 FROM message_reads WHERE message_id = m.id AND user_id = $1) AS is_read,
+               sm.saved_at
+        FROM saved_messages sm
+        JOIN messages m ON m.id = sm.message_id
+        JOIN chat_rooms cr ON cr.id = m.chat_room_id
+        JOIN users u ON u.id = m.sender_id
+        WHERE sm.user_id = $1
+          AND m.deleted_at IS NULL
+          AND EXISTS(SELECT 1 FROM chat_members WHERE chat_room_id = m.chat_room_id AND user_id = $1)
+          AND ($2::uuid IS NULL OR m.chat_room_id = $2)
+          AND ($3::uuid IS NULL OR (sm.saved_at, sm.message_id) < (
+              SELECT saved_at, message_id FROM saved_messages WHERE message_id = $3 AND user_id = $1
+          ))
+        ORDER BY sm.saved_at DESC, sm.message_id DESC
+        LIMIT $4
+        "#
+    )
+    .bind(user_id)
+    .bind(params.chat_room_id)
+    .bind(params.before)
+    .bind(limit + 1)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let has_more = rows.len() as i64 > limit;
+    rows.truncate(limit as usize);
+    let next_before = if has_more { rows.last().map(|r| r.id) } else { None };
+
+    // 1:1 chats are named after the other member, as in the chat list
+    let direct_chats: Vec<Uuid> = rows.iter().filter(|r| !r.is_group).map(|r| r.chat_room_id).collect();
+    let direct_names: HashMap<Uuid, String> = sqlx::query_as::<_, (Uuid, String)>(
+        r#"
+        SELECT cm.chat_room_id, u.username
+        FROM chat_members cm
+        JOIN users u ON u.id = cm.user_id
+        WHERE cm.chat_room_id = ANY($1) AND cm.user_id != $2
+        "#
+    )
+    .bind(&direct_chats)
+    .bind(user_id)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .into_iter()
+    .collect();
+
+    let mut chats: Vec<SavedChatGroup> = Vec::new();
+    for row in rows {
+        let mut message = MessageResponse {
+            id: row.id,
+            chat_room_id: row.chat_room_id,
+            sender_id: row.sender_id,
+            sender_username: row.sender_username,
+            message_type: row.message_type,
+            content: row.content,
+            media_url: row.media_url,
+            media_thumbnail_url: row.media_thumbnail_url,
+            view_once: row.view_once,
+            is_ephemeral: row.is_ephemeral,
+            expires_at: row.expires_at,
+            created_at: row.created_at,
+            is_viewed: row.is_viewed,
+            is_read: row.is_read,
+            is_saved: true,
+            muted_word: None,
+            delivery_state: None,
+        };
+        message.sign_media(&state.media_service, user_id).await;
+        let saved = SavedMessage { message, saved_at: row.saved_at };
+
+        match chats.iter_mut().find(|c| c.chat_room_id == row.chat_room_id) {
+            Some(group) => group.messages.push(saved),
+            None => chats.push(SavedChatGroup {
+                chat_room_id: row.chat_room_id,
+                chat_name: if row.is_group { row.chat_name } else { direct_names.get(&row.chat_room_id).cloned() },
+                is_group: row.is_group,
+                messages: vec![saved],
+            }),
+        }
+    }
+
+    Ok(Json(SavedMessagesResponse { chats, next_before }))
+}
+
 // Send a message via HTTP (also broadcasts via WebSocket)
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct SendMessageRequest {
     pub chat_room_id: Uuid,
     pub content: Option<String>,
+    /// text, image, video, gif or sticker
     pub message_type: String,
     pub media_url: Option<String>,
     pub media_thumbnail_url: Option<String>,
     pub view_once: bool,
     pub expires_in_seconds: Option<i64>,
+    /// Echoed back to the sender in the NewMessage event
+    pub client_msg_id: Option<String>,
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/{user_id}/messages/send",
+    tag = "chat",
+    params(
+        ("user_id" = Uuid, Path, description = "User ID"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Retries with the same key return the original message instead of sending it again")
+    ),
+    request_body = SendMessageRequest,
+    responses(
+        (status = 200, body = MessageResponse),
+        (status = 409, description = "A request with this idempotency key is still being processed")
+    )
+)]
 pub async fn send_message_http(
     State(state): State<Arc<crate::AppState>>,
     Path(user_id): Path<Uuid>,
+    headers: HeaderMap,
     Json(payload): Json<SendMessageRequest>,
 ) -> Result<Json<MessageResponse>, StatusCode> {
-    let pool = &state.pool;
+    use crate::idempotency::{self, Reservation, SCOPE_SEND_MESSAGE};
 
     // Calculate expiration
-    let expires_at = payload.expires_in_seconds.map(|seconds| {
-        (chrono::Utc::now() + chrono::Duration::seconds(seconds)).naive_utc()
-    });
+    let expires_at = crate::expiration::message_expires_at(payload.expires_in_seconds)
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let message = OutgoingMessage {
+        chat_room_id: payload.chat_room_id,
+        message_type: payload.message_type,
+        content: payload.content,
+        media_url: payload.media_url,
+        media_thumbnail_url: payload.media_thumbnail_url,
+        view_once: payload.view_once,
+        expires_at,
+        client_msg_id: payload.client_msg_id,
+    };
+    message.validate().map_err(|_| StatusCode::BAD_REQUEST)?;
+    crate::message_requests::check_send(&state.pool, user_id, message.chat_room_id)
+        .await
+        .map_err(|(status, _)| status)?;
+    crate::channels::check_send(&state.pool, user_id, message.chat_room_id)
+        .await
+        .map_err(|(status, _)| status)?;
+
+    let Some(key) = idempotency::key_from_headers(&headers)? else {
+        return deliver_message(&state.pool, &state.redis, &state.connections, &state.media_service, user_id, message)
+            .await
+            .map(Json)
+            .map_err(|e| {
+                eprintln!("❌ Failed to send message: {:?}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            });
+    };
+
+    match idempotency::reserve::<MessageResponse>(&state.pool, user_id, SCOPE_SEND_MESSAGE, &key)
+        .await
+        .map_err(|e| {
+            eprintln!("❌ Failed to reserve idempotency key: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })? {
+        Reservation::New => {}
+        Reservation::Replay(mut original) => {
+            // The stored copy's signed URLs may have expired
+            original.sign_media(&state.media_service, user_id).await;
+            return Ok(Json(original));
+        }
+        Reservation::InProgress => return Err(StatusCode::CONFLICT),
+    }
+
+    match deliver_message(&state.pool, &state.redis, &state.connections, &state.media_service, user_id, message).await {
+        Ok(response) => {
+            if let Err(e) = idempotency::complete(&state.pool, user_id, SCOPE_SEND_MESSAGE, &key, &response).await {
+                eprintln!("❌ Failed to store idempotent response: {:?}", e);
+            }
+            Ok(Json(response))
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to send message: {:?}", e);
+            idempotency::release(&state.pool, user_id, SCOPE_SEND_MESSAGE, &key).await;
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// A message about to be sent, from either REST or WebSocket
+pub struct OutgoingMessage {
+    pub chat_room_id: Uuid,
+    pub message_type: String,
+    pub content: Option<String>,
+    pub media_url: Option<String>,
+    pub media_thumbnail_url: Option<String>,
+    pub view_once: bool,
+    pub expires_at: Option<NaiveDateTime>,
+    /// Sender's local id for the optimistic copy, only echoed to the sender
+    pub client_msg_id: Option<String>,
+}
+
+impl OutgoingMessage {
+    /// Check the type/content combination before it reaches the database.
+    /// gif and sticker messages must use media from the GIF provider.
+    pub fn validate(&self) -> Result<(), String> {
+        match self.message_type.as_str() {
+            "text" if self.content.as_deref().is_some_and(|c| !c.trim().is_empty()) => Ok(()),
+            "text" => Err("Text messages need content".to_string()),
+            "image" | "video" if self.media_url.is_some() => Ok(()),
+            "gif" | "sticker" if self.media_url.as_deref().is_some_and(crate::gifs::is_provider_url) => Ok(()),
+            "image" | "video" | "gif" | "sticker" => {
+                Err(format!("{} messages need a valid media_url", self.message_type))
+            }
+            other => Err(format!("Unknown message type: {}", other)),
+        }
+    }
+}
+
+/// Tell a message's sender it reached one of `recipient_id`'s devices
+fn announce_delivery(
+    connections: &crate::websocket::Connections,
+    sender_id: Uuid,
+    message_id: Uuid,
+    recipient_id: Uuid,
+    delivered_at: NaiveDateTime,
+) {
+    if recipient_id == sender_id {
+        return;
+    }
+    let event = crate::websocket::WsMessage::MessageDelivered {
+        message_id,
+        user_id: recipient_id,
+        delivered_at: crate::timestamps::format(delivered_at),
+    };
+    crate::websocket::send_to_user(connections, sender_id, &event);
+}
+
+impl MessageResponse {
+    /// WebSocket event announcing this message. `client_msg_id` is only set
+    /// on the copy that goes back to the sender.
+    pub fn to_ws_event(&self, client_msg_id: Option<String>) -> crate::websocket::WsMessage {
+        crate::websocket::WsMessage::NewMessage {
+            id: self.id,
+            chat_room_id: self.chat_room_id,
+            sender_id: self.sender_id,
+            sender_username: self.sender_username.clone(),
+            message_type: self.message_type.clone(),
+            content: self.content.clone(),
+            media_url: self.media_url.clone(),
+            media_thumbnail_url: self.media_thumbnail_url.clone(),
+            view_once: self.view_once,
+            expires_at: self.expires_at.map(crate::timestamps::format),
+            created_at: crate::timestamps::format(self.created_at),
+            client_msg_id,
+            muted_word: self.muted_word.clone(),
+        }
+    }
+}
+
+impl MessageResponse {
+    /// Swap stored media URLs for ones the viewer can load. A view-once
+    /// message someone else sent loses its media after the viewer opened it,
+    /// unless the viewer saved it.
+    pub async fn sign_media(&mut self, media: &crate::media::MediaService, viewer_id: Uuid) {
+        if self.view_once && self.is_viewed && !self.is_saved && self.sender_id != viewer_id {
+            self.media_url = None;
+            self.media_thumbnail_url = None;
+            return;
+        }
+        media.sign_opt(&mut self.media_url).await;
+        media.sign_opt(&mut self.media_thumbnail_url).await;
+    }
+}
+
+/// Recipients whose muted words the message contains, with the word matched,
+/// recorded on their message_recipients row so fetches fold it too
+async fn fold_for_muted_words(
+    pool: &sqlx::PgPool,
+    message: &MessageResponse,
+    members: &[(Uuid, bool)],
+) -> HashMap<Uuid, String> {
+    let Some(content) = message.content.as_deref().filter(|_| message.message_type == "text") else {
+        return HashMap::new();
+    };
+    // Encrypted chats are opaque to the server; their clients filter locally
+    let is_e2ee = sqlx::query_scalar::<_, bool>("SELECT is_e2ee FROM chat_rooms WHERE id = $1")
+        .bind(message.chat_room_id)
+        .fetch_one(pool)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to load chat {}: {}", message.chat_room_id, e);
+            true
+        });
+    if is_e2ee {
+        return HashMap::new();
+    }
+    let recipient_ids: Vec<Uuid> = members.iter().map(|&(id, _)| id).filter(|&id| id != message.sender_id).collect();
+    let muted: HashMap<Uuid, String> = crate::muted_words::load_many(pool, &recipient_ids)
+        .await
+        .into_iter()
+        .filter_map(|(member_id, phrases)| Some((member_id, crate::muted_words::find_match(&phrases, content)?.to_string())))
+        .collect();
+    if muted.is_empty() {
+        return muted;
+    }
+
+    let (user_ids, words): (Vec<Uuid>, Vec<String>) = muted.iter().map(|(id, word)| (*id, word.clone())).unzip();
+    if let Err(e) = sqlx::query(
+        r#"
+        UPDATE message_recipients r SET muted_word = m.word
+        FROM UNNEST($2::uuid[], $3::text[]) AS m(user_id, word)
+        WHERE r.message_id = $1 AND r.user_id = m.user_id
+        "#
+    )
+    .bind(message.id)
+    .bind(&user_ids)
+    .bind(&words)
+    .execute(pool)
+    .await
+    {
+        tracing::error!("Failed to record muted words for message {}: {}", message.id, e);
+    }
+    muted
+}
+
+/// Store a message and push it to every chat member. Members without a live
+/// socket get their unread counter bumped instead, and members yet to answer
+/// a message request for the chat get neither. Channel posts also notify
+/// every subscriber.
+pub async fn deliver_message(
+    pool: &sqlx::PgPool,
+    redis: &tokio::sync::Mutex<crate::redis_client::RedisClient>,
+    connections: &crate::websocket::Connections,
+    media: &crate::media::MediaService,
+    user_id: Uuid,
+    mut message: OutgoingMessage,
+) -> Result<MessageResponse, sqlx::Error> {
+    // Clients may send back the signed URL an upload returned; store the permanent one
+    message.media_url = message.media_url.map(|url| media.canonical_url(&url));
+    message.media_thumbnail_url = message.media_thumbnail_url.map(|url| media.canonical_url(&url));
 
     // Insert message into database
     let record = sqlx::query!(
@@ -451,76 +1315,134 @@ pub async fn send_message_http(
         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
         RETURNING id, created_at
         "#,
-        payload.chat_room_id,
+        message.chat_room_id,
         user_id,
-        payload.message_type,
-        payload.content,
-        payload.media_url,
-        payload.media_thumbnail_url,
-        payload.view_once,
-        expires_at
+        message.message_type,
+        message.content,
+        message.media_url,
+        message.media_thumbnail_url,
+        message.view_once,
+        message.expires_at
     )
-    .fetch_one(pool.as_ref())
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .fetch_one(pool)
+    .await?;
+    crate::live_stats::count_message(redis).await;
+
+    // The message holds its media until it expires or is deleted
+    let media_urls: Vec<&str> = message.media_url.iter().chain(&message.media_thumbnail_url).map(String::as_str).collect();
+    media.acquire(pool, &media_urls).await;
 
     // Get sender username
     let sender = sqlx::query!("SELECT username FROM users WHERE id = $1", user_id)
-        .fetch_one(pool.as_ref())
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .fetch_one(pool)
+        .await?;
 
-    // Get all members of the chat room
-    let members = sqlx::query!(
-        "SELECT user_id FROM chat_members WHERE chat_room_id = $1",
-        payload.chat_room_id
+    // Get all members of the chat room; those yet to answer a message request
+    // only see it in their requests inbox
+    let members = sqlx::query_as::<_, (Uuid, bool)>(
+        r#"
+        SELECT cm.user_id,
+               EXISTS(SELECT 1 FROM message_requests
+                      WHERE chat_room_id = cm.chat_room_id AND recipient_id = cm.user_id AND status = 'pending') AS pending
+        FROM chat_members cm
+        WHERE cm.chat_room_id = $1
+        "#
     )
-    .fetch_all(pool.as_ref())
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .bind(message.chat_room_id)
+    .fetch_all(pool)
+    .await?;
 
-    // Broadcast to all chat members via WebSocket
-    use crate::websocket::WsMessage;
-    let broadcast_msg = WsMessage::NewMessage {
+    let mut response = MessageResponse {
         id: record.id,
-        chat_room_id: payload.chat_room_id,
+        chat_room_id: message.chat_room_id,
         sender_id: user_id,
-        sender_username: sender.username.clone(),
-        message_type: payload.message_type.clone(),
-        content: payload.content.clone(),
-        media_url: payload.media_url.clone(),
-        media_thumbnail_url: payload.media_thumbnail_url.clone(),
-        view_once: payload.view_once,
-        created_at: record.created_at.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
+        sender_username: sender.username,
+        message_type: message.message_type,
+        content: message.content,
+        media_url: message.media_url,
+        media_thumbnail_url: message.media_thumbnail_url,
+        view_once: message.view_once,
+        is_ephemeral: message.expires_at.is_some(),
+        expires_at: message.expires_at,
+        created_at: record.created_at,
+        is_viewed: false,
+        is_read: false,
+        is_saved: false,
+        muted_word: None,
+        delivery_state: None,
     };
-    let msg_json = serde_json::to_string(&broadcast_msg).unwrap();
+    // Every recipient is a member who hasn't viewed it yet, so one signed copy serves all
+    response.sign_media(media, user_id).await;
+
+    let muted = fold_for_muted_words(pool, &response, &members).await;
 
-    for member in &members {
-        if let Some(conn) = state.connections.get(&member.user_id) {
-            let _ = conn.send(msg_json.clone());
+    // Broadcast to all chat members (including sender) via WebSocket. The
+    // sender's copy carries client_msg_id so it can replace its pending bubble.
+    let msg_json = serde_json::to_string(&response.to_ws_event(None)).unwrap();
+    let sender_json = message
+        .client_msg_id
+        .map(|id| serde_json::to_string(&response.to_ws_event(Some(id))).unwrap());
+
+    let mut delivered = Vec::new();
+    for &(member_id, pending) in &members {
+        // No push or unread count for a pending request
+        if pending {
+            continue;
+        }
+        // Sending fails when no socket is subscribed (e.g. the last one is
+        // closing), in which case nothing reached a device
+        let sent = connections.get(&member_id).map(|conn| match &sender_json {
+            Some(json) if member_id == user_id => conn.send(json.clone()).is_ok(),
+            _ => match muted.get(&member_id) {
+                Some(word) => {
+                    let folded = MessageResponse { muted_word: Some(word.clone()), ..response.clone() };
+                    conn.send(serde_json::to_string(&folded.to_ws_event(None)).unwrap()).is_ok()
+                }
+                None => conn.send(msg_json.clone()).is_ok(),
+            },
+        });
+        if sent == Some(true) {
+            delivered.push(member_id);
         } else {
             // User is offline, increment unread counter
-            let mut redis_guard = state.redis.lock().await;
-            let _ = redis_guard.increment_unread(member.user_id, payload.chat_room_id).await;
+            let mut redis_guard = redis.lock().await;
+            let _ = redis_guard.increment_unread(member_id, message.chat_room_id).await;
         }
     }
 
-    // Return the message response
-    Ok(Json(MessageResponse {
-        id: record.id,
-        chat_room_id: payload.chat_room_id,
-        sender_id: user_id,
-        sender_username: sender.username,
-        message_type: payload.message_type,
-        content: payload.content,
-        media_url: payload.media_url,
-        media_thumbnail_url: payload.media_thumbnail_url,
-        view_once: payload.view_once,
-        is_ephemeral: expires_at.is_some(),
-        expires_at,
-        created_at: record.created_at,
-        is_viewed: false,
-        is_read: false,
-        is_saved: false,
-    }))
+    // Members with a live socket have it now; the rest get it on their next fetch
+    let delivered = sqlx::query_as::<_, (Uuid, NaiveDateTime)>(
+        r#"
+        UPDATE message_recipients SET delivered_at = NOW()
+        WHERE message_id = $1 AND user_id = ANY($2) AND delivered_at IS NULL
+        RETURNING user_id, delivered_at
+        "#
+    )
+    .bind(record.id)
+    .bind(&delivered)
+    .fetch_all(pool)
+    .await
+    .unwrap_or_else(|e| {
+        tracing::error!("Failed to record delivery of message {}: {}", record.id, e);
+        Vec::new()
+    });
+    for &(member_id, delivered_at) in &delivered {
+        announce_delivery(connections, user_id, record.id, member_id, delivered_at);
+    }
+    let recipients = members.iter().filter(|&&(member_id, _)| member_id != user_id).count();
+    response.delivery_state = Some(if recipients > 0 && delivered.len() == recipients { "delivered" } else { "sent" }.to_string());
+
+    // Bots only hear about messages addressed to them
+    let member_ids: Vec<Uuid> = members.iter().map(|&(member_id, _)| member_id).collect();
+    let recipients = crate::bots::message_recipients(pool, &response, &member_ids).await;
+    crate::webhooks::dispatch(
+        pool,
+        &recipients,
+        crate::webhooks::EVENT_MESSAGE_RECEIVED,
+        serde_json::to_value(&response).unwrap_or_default(),
+    )
+    .await;
+    crate::channels::notify_subscribers(pool, response.chat_room_id, user_id).await;
+
+    Ok(response)
 }
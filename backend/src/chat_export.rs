@@ -0,0 +1,353 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::media::MediaService;
+use crate::storage::ObjectMeta;
+
+// Export of one conversation as a JSON or HTML file. The file holds what the
+// user can see in the chat right now: no expired or hidden messages, and no
+// content from view-once messages others sent unless the user saved them. It
+// is written to the bucket and handed out as a presigned link; both expire
+// after EXPORT_TTL_HOURS.
+
+const EXPORT_TTL_HOURS: i64 = 24;
+const MAX_EXPORT_MESSAGES: i64 = 20_000;
+const FORMATS: [&str; 2] = ["json", "html"];
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ChatExportRequest {
+    /// json (default) or html
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChatExportResponse {
+    pub export_id: Uuid,
+    pub format: String,
+    pub message_count: i32,
+    /// Presigned link to the file
+    pub download_url: String,
+    #[serde(with = "crate::timestamps")]
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(sqlx::FromRow)]
+struct ExportChat {
+    name: Option<String>,
+    is_group: bool,
+    is_e2ee: bool,
+    is_member: bool,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+struct ExportedMessage {
+    id: Uuid,
+    sender_id: Uuid,
+    sender_username: String,
+    message_type: String,
+    content: Option<String>,
+    media_url: Option<String>,
+    view_once: bool,
+    /// View-once content the user may not see here was left out
+    #[sqlx(default)]
+    withheld: bool,
+    is_saved: bool,
+    #[serde(with = "crate::timestamps")]
+    created_at: NaiveDateTime,
+}
+
+#[derive(Serialize)]
+struct ExportFile<'a> {
+    chat_room_id: Uuid,
+    chat_name: Option<&'a str>,
+    is_group: bool,
+    /// Content is ciphertext the server can't read
+    is_e2ee: bool,
+    members: &'a [String],
+    exported_by: &'a str,
+    #[serde(with = "crate::timestamps")]
+    exported_at: NaiveDateTime,
+    /// Oldest first
+    messages: &'a [ExportedMessage],
+}
+
+async fn visible_messages(pool: &PgPool, chat_room_id: Uuid, user_id: Uuid) -> Result<Vec<ExportedMessage>, sqlx::Error> {
+    sqlx::query_as::<_, ExportedMessage>(
+        r#"
+        SELECT m.id, m.sender_id, u.username AS sender_username, m.message_type, m.content, m.media_url,
+               m.view_once, m.created_at,
+               EXISTS(SELECT 1 FROM saved_messages WHERE message_id = m.id AND user_id = $2) AS is_saved
+        FROM messages m
+        JOIN users u ON u.id = m.sender_id
+        WHERE m.chat_room_id = $1
+          AND m.deleted_at IS NULL
+          AND NOT EXISTS(SELECT 1 FROM message_recipients WHERE message_id = m.id AND user_id = $2 AND hidden_at IS NOT NULL)
+          AND (m.expires_at IS NULL OR m.expires_at > NOW()
+               OR EXISTS(SELECT 1 FROM saved_messages WHERE message_id = m.id AND user_id = $2))
+        ORDER BY m.created_at ASC
+        LIMIT $3
+        "#
+    )
+    .bind(chat_room_id)
+    .bind(user_id)
+    .bind(MAX_EXPORT_MESSAGES)
+    .fetch_all(pool)
+    .await
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn render_html(file: &ExportFile) -> String {
+    let title = escape_html(file.chat_name.unwrap_or("Chat"));
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{title}</title>\
+         <style>body{{font-family:sans-serif;max-width:720px;margin:2em auto}}\
+         .msg{{margin:.5em 0}}.meta{{color:#777;font-size:.85em}}.withheld{{color:#999;font-style:italic}}</style>\
+         </head><body>\n<h1>{title}</h1>\n<p class=\"meta\">Members: {members}<br>Exported by {by} on {at} UTC</p>\n",
+        title = title,
+        members = escape_html(&file.members.join(", ")),
+        by = escape_html(file.exported_by),
+        at = file.exported_at.format("%Y-%m-%d %H:%M"),
+    );
+    if file.is_e2ee {
+        html.push_str("<p class=\"meta\">This chat is end-to-end encrypted; message content is shown as stored.</p>\n");
+    }
+
+    for message in file.messages {
+        html.push_str(&format!(
+            "<div class=\"msg\"><span class=\"meta\">{} &middot; {}</span><br>",
+            escape_html(&message.sender_username),
+            message.created_at.format("%Y-%m-%d %H:%M"),
+        ));
+        if message.withheld {
+            html.push_str("<span class=\"withheld\">View-once message</span>");
+        } else {
+            if let Some(content) = &message.content {
+                html.push_str(&escape_html(content).replace('\n', "<br>"));
+            }
+            if let Some(url) = &message.media_url {
+                html.push_str(&format!(
+                    " <a href=\"{}\">[{}]</a>",
+                    escape_html(url),
+                    escape_html(&message.message_type)
+                ));
+            }
+        }
+        html.push_str("</div>\n");
+    }
+
+    html.push_str("</body></html>\n");
+    html
+}
+
+// Export a conversation the user is a member of
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/{user_id}/chats/{chat_room_id}/export",
+    tag = "chat",
+    params(("user_id" = Uuid, Path, description = "User ID"), ("chat_room_id" = Uuid, Path, description = "Chat room ID")),
+    request_body = ChatExportRequest,
+    responses(
+        (status = 200, body = ChatExportResponse),
+        (status = 400, description = "Unknown format"),
+        (status = 403, description = "Not your account, or not a member of this chat"),
+        (status = 404, description = "Chat not found"),
+        (status = 429, description = "Too many exports today"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn export_chat(
+    user: AuthUser,
+    State(state): State<Arc<crate::AppState>>,
+    Path((user_id, chat_room_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<ChatExportRequest>,
+) -> axum::response::Result<Json<ChatExportResponse>> {
+    if user.id != user_id {
+        return Err(StatusCode::FORBIDDEN.into());
+    }
+    let format = payload.format.as_deref().unwrap_or("json").to_lowercase();
+    if !FORMATS.contains(&format.as_str()) {
+        return Err((StatusCode::BAD_REQUEST, format!("format must be one of: {}", FORMATS.join(", "))).into());
+    }
+
+    let pool = state.pool.as_ref();
+    let chat = sqlx::query_as::<_, ExportChat>(
+        r#"
+        SELECT cr.name, cr.is_group, cr.is_e2ee,
+               EXISTS(SELECT 1 FROM chat_members WHERE chat_room_id = cr.id AND user_id = $2) AS is_member
+        FROM chat_rooms cr
+        WHERE cr.id = $1
+        "#
+    )
+    .bind(chat_room_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+    if !chat.is_member {
+        return Err(StatusCode::FORBIDDEN.into());
+    }
+
+    crate::quotas::check_chat_export(pool, user_id).await?;
+
+    let members = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT u.username FROM chat_members cm
+        JOIN users u ON u.id = cm.user_id
+        WHERE cm.chat_room_id = $1
+        ORDER BY u.username
+        "#
+    )
+    .bind(chat_room_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let ttl = Duration::from_secs(EXPORT_TTL_HOURS as u64 * 3600);
+    let media = &state.media_service;
+    let mut messages = visible_messages(pool, chat_room_id, user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    for message in &mut messages {
+        // Exporting mustn't become a way to open view-once messages
+        if message.view_once && message.sender_id != user_id && !message.is_saved {
+            message.withheld = true;
+            message.content = None;
+            message.media_url = None;
+            continue;
+        }
+        // Links in the file last as long as the file itself
+        if let Some(url) = &message.media_url {
+            let url = media.canonical_url(url);
+            if let Some(key) = media.s3_key_from_url(&url) {
+                message.media_url = Some(media.presign_get(&key, ttl).await.unwrap_or(url));
+            }
+        }
+    }
+
+    let chat_name = if chat.is_group {
+        chat.name.clone()
+    } else {
+        members.iter().find(|m| **m != user.username).cloned()
+    };
+    let file = ExportFile {
+        chat_room_id,
+        chat_name: chat_name.as_deref(),
+        is_group: chat.is_group,
+        is_e2ee: chat.is_e2ee,
+        members: &members,
+        exported_by: &user.username,
+        exported_at: Utc::now().naive_utc(),
+        messages: &messages,
+    };
+    let (body, content_type) = match format.as_str() {
+        "html" => (render_html(&file).into_bytes(), "text/html; charset=utf-8"),
+        _ => (
+            serde_json::to_vec_pretty(&file).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+            "application/json",
+        ),
+    };
+
+    let export_id = Uuid::new_v4();
+    let s3_key = format!("exports/{}/{}.{}", user_id, export_id, format);
+    let meta = ObjectMeta {
+        content_disposition: Some(format!("attachment; filename=\"chat-export-{}.{}\"", export_id, format)),
+        ..ObjectMeta::typed(content_type)
+    };
+    media
+        .storage
+        .put(&s3_key, body.clone(), &meta)
+        .await
+        .map_err(|e| {
+            eprintln!("❌ Failed to upload chat export: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let expires_at = sqlx::query_scalar::<_, NaiveDateTime>(
+        r#"
+        INSERT INTO chat_exports (id, user_id, chat_room_id, format, s3_key, message_count, size_bytes, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, NOW() + make_interval(hours => $8))
+        RETURNING expires_at
+        "#
+    )
+    .bind(export_id)
+    .bind(user_id)
+    .bind(chat_room_id)
+    .bind(&format)
+    .bind(&s3_key)
+    .bind(messages.len() as i32)
+    .bind(body.len() as i64)
+    .bind(EXPORT_TTL_HOURS as i32)
+    .fetch_one(pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let download_url = media.presign_get(&s3_key, ttl).await.map_err(|e| {
+        eprintln!("❌ {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(ChatExportResponse {
+        export_id,
+        format,
+        message_count: messages.len() as i32,
+        download_url,
+        expires_at,
+    }))
+}
+
+/// Delete exports past their expiry, file first
+pub async fn purge_expired(pool: &PgPool, media: &MediaService) -> Result<(), sqlx::Error> {
+    let expired = sqlx::query_as::<_, (Uuid, String)>("SELECT id, s3_key FROM chat_exports WHERE expires_at < NOW() LIMIT 1000")
+        .fetch_all(pool)
+        .await?;
+    if expired.is_empty() {
+        return Ok(());
+    }
+
+    let keys: Vec<String> = expired.iter().map(|(_, key)| key.clone()).collect();
+    let deleted = match media.delete_media_batch(&keys).await {
+        Ok(deleted) => deleted,
+        Err(e) => {
+            eprintln!("Error deleting expired chat exports: {}", e);
+            return Ok(());
+        }
+    };
+
+    let ids: Vec<Uuid> = expired
+        .into_iter()
+        .filter(|(_, key)| deleted.contains(key))
+        .map(|(id, _)| id)
+        .collect();
+    sqlx::query("DELETE FROM chat_exports WHERE id = ANY($1)")
+        .bind(&ids)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,182 @@
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{request::Parts, HeaderMap, StatusCode},
+};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::OnceLock;
+
+// The address a request came from, for rate limits keyed on IP. Forwarding
+// headers (CF-Connecting-IP, X-Forwarded-For) are written by whoever sends
+// the request, so they are only believed when the connection itself comes
+// from a proxy listed in TRUSTED_PROXIES (comma-separated addresses or CIDR
+// ranges, e.g. "10.0.0.0/8,127.0.0.1"). Otherwise the TCP peer is the client.
+
+#[derive(Debug, Clone, Copy)]
+struct Network {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Network {
+    fn parse(value: &str) -> Option<Self> {
+        let (addr, prefix) = match value.split_once('/') {
+            Some((addr, prefix)) => (addr.trim().parse::<IpAddr>().ok()?, Some(prefix.trim().parse::<u8>().ok()?)),
+            None => (value.trim().parse::<IpAddr>().ok()?, None),
+        };
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        (prefix <= max).then_some(Self { addr, prefix })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        // IPv4 peers of a dual-stack listener show up as ::ffff:a.b.c.d
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            ip => ip,
+        };
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+fn parse_networks(value: &str) -> Vec<Network> {
+    value
+        .split(',')
+        .filter(|v| !v.trim().is_empty())
+        .filter_map(|v| {
+            let network = Network::parse(v);
+            if network.is_none() {
+                tracing::warn!("Ignoring invalid TRUSTED_PROXIES entry {:?}", v.trim());
+            }
+            network
+        })
+        .collect()
+}
+
+fn trusted_proxies() -> &'static [Network] {
+    static PROXIES: OnceLock<Vec<Network>> = OnceLock::new();
+    PROXIES.get_or_init(|| parse_networks(&std::env::var("TRUSTED_PROXIES").unwrap_or_default()))
+}
+
+fn resolve(peer: IpAddr, headers: &HeaderMap, trusted: &[Network]) -> IpAddr {
+    let is_trusted = |ip: IpAddr| trusted.iter().any(|network| network.contains(ip));
+    if !is_trusted(peer) {
+        return peer;
+    }
+
+    if let Some(ip) = headers
+        .get("CF-Connecting-IP")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<IpAddr>().ok())
+    {
+        return ip;
+    }
+
+    // Walk X-Forwarded-For from the nearest hop back; the first address not
+    // added by one of our own proxies is the client. Anything left of it
+    // could have been made up.
+    let mut client = peer;
+    let hops = headers
+        .get_all("X-Forwarded-For")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .collect::<Vec<_>>();
+    for hop in hops.iter().rev() {
+        let Ok(ip) = hop.trim().parse::<IpAddr>() else {
+            break;
+        };
+        client = ip;
+        if !is_trusted(ip) {
+            break;
+        }
+    }
+    client
+}
+
+/// Extractor for the client's address. Needs the server to be run with
+/// `into_make_service_with_connect_info::<SocketAddr>()`.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub IpAddr);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+            .ok_or_else(|| {
+                tracing::error!("Peer address unavailable; serve with connect info");
+                (StatusCode::INTERNAL_SERVER_ERROR, "Couldn't determine client address".to_string())
+            })?;
+        Ok(ClientIp(resolve(peer, &parts.headers, trusted_proxies())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn network_parsing_and_matching() {
+        let networks = parse_networks("10.0.0.0/8, 192.168.1.7,fd00::/8,bogus,10.0.0.0/33");
+        assert_eq!(networks.len(), 3);
+        assert!(networks[0].contains(ip("10.200.3.4")));
+        assert!(!networks[0].contains(ip("11.0.0.1")));
+        assert!(networks[0].contains(ip("::ffff:10.1.2.3")));
+        assert!(networks[1].contains(ip("192.168.1.7")));
+        assert!(!networks[1].contains(ip("192.168.1.8")));
+        assert!(networks[2].contains(ip("fd12::1")));
+        assert!(!networks[2].contains(ip("fe80::1")));
+        assert!(parse_networks("0.0.0.0/0")[0].contains(ip("8.8.8.8")));
+    }
+
+    #[test]
+    fn untrusted_peer_ignores_forwarding_headers() {
+        let forged = headers(&[("CF-Connecting-IP", "1.2.3.4"), ("X-Forwarded-For", "5.6.7.8")]);
+        assert_eq!(resolve(ip("203.0.113.9"), &forged, &parse_networks("10.0.0.0/8")), ip("203.0.113.9"));
+        assert_eq!(resolve(ip("203.0.113.9"), &forged, &[]), ip("203.0.113.9"));
+    }
+
+    #[test]
+    fn trusted_proxy_forwards_the_client() {
+        let trusted = parse_networks("10.0.0.0/8");
+        let peer = ip("10.0.0.2");
+        assert_eq!(resolve(peer, &headers(&[("CF-Connecting-IP", "1.2.3.4")]), &trusted), ip("1.2.3.4"));
+        // A client-supplied first hop is skipped in favour of the address our
+        // proxies saw
+        let chain = headers(&[("X-Forwarded-For", "9.9.9.9, 198.51.100.4, 10.0.0.5")]);
+        assert_eq!(resolve(peer, &chain, &trusted), ip("198.51.100.4"));
+        let split = headers(&[("X-Forwarded-For", "9.9.9.9"), ("X-Forwarded-For", "198.51.100.4")]);
+        assert_eq!(resolve(peer, &split, &trusted), ip("198.51.100.4"));
+        // Garbage stops the walk at the last good hop
+        let garbage = headers(&[("X-Forwarded-For", "nonsense, 10.0.0.5")]);
+        assert_eq!(resolve(peer, &garbage, &trusted), ip("10.0.0.5"));
+        assert_eq!(resolve(peer, &HeaderMap::new(), &trusted), peer);
+    }
+}
@@ -0,0 +1,1297 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::NaiveDateTime;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::i18n::Message;
+use crate::permissions::{in_community, CanBan, CanModerateContent, Permission, Scope};
+use crate::AppState;
+
+// Communities are public group chats (room_type = 'public', migration 058).
+// Anyone can find them through discovery or a join link; rooms with join
+// approval on hold new members in community_join_requests until a moderator
+// lets them in. The owner manages roles; moderators manage members, requests
+// and the room's details.
+//
+// Owners and moderators can also ban people from their community and remove
+// messages posted in it (migration 079). Those powers come from the
+// permissions layer: can_ban and can_moderate_content reach into communities
+// the caller moderates, and staff whose role grants them everywhere can use
+// them in any community. Moderator actions go to community_mod_logs, which
+// the community's moderators can read; staff actions go to admin_logs.
+
+const DESCRIPTION_MAX_LENGTH: usize = 500;
+const BAN_REASON_MAX_LENGTH: usize = 500;
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct Community {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub avatar_url: Option<String>,
+    pub join_approval: bool,
+    pub member_count: i64,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+    /// The caller's role (owner, moderator or member), if they're in the community
+    pub my_role: Option<String>,
+    /// Whether the caller is waiting on approval to join
+    pub join_requested: bool,
+    /// Code for the join link; only shown to members
+    pub invite_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct CommunityMember {
+    pub user_id: Uuid,
+    pub username: String,
+    pub role: String,
+    #[serde(with = "crate::timestamps")]
+    pub joined_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct JoinRequest {
+    pub user_id: Uuid,
+    pub username: String,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateCommunityRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub avatar_url: Option<String>,
+    #[serde(default)]
+    pub join_approval: bool,
+}
+
+/// Fields left out stay as they are; an empty description or avatar clears it
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateCommunityRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub avatar_url: Option<String>,
+    pub join_approval: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetRoleRequest {
+    /// moderator or member; owner hands the community over and makes the caller a moderator
+    pub role: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BanMemberRequest {
+    /// Shown to the community's moderators, not to the banned user
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct CommunityBan {
+    pub user_id: Uuid,
+    pub username: String,
+    pub banned_by: Option<Uuid>,
+    pub reason: Option<String>,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct ModLogEntry {
+    pub id: Uuid,
+    /// Null once the moderator's account is gone
+    pub moderator_id: Option<Uuid>,
+    pub moderator_username: Option<String>,
+    /// ban, unban, remove_member, remove_message or set_role
+    pub action: String,
+    pub target_user_id: Option<Uuid>,
+    pub target_message_id: Option<Uuid>,
+    pub details: serde_json::Value,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ModLogQuery {
+    /// Only entries with this action
+    pub action: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DiscoverQuery {
+    /// Matched against community names and descriptions
+    pub q: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+// $1 is always the caller
+const COMMUNITY_COLUMNS: &str = r#"
+    r.id, r.name, r.description, r.avatar_url, r.join_approval, r.created_at,
+    (SELECT COUNT(*) FROM chat_members m WHERE m.chat_room_id = r.id) AS member_count,
+    me.role AS my_role,
+    EXISTS (SELECT 1 FROM community_join_requests j WHERE j.chat_room_id = r.id AND j.user_id = $1) AS join_requested,
+    CASE WHEN me.role IS NOT NULL THEN r.invite_code END AS invite_code
+    FROM chat_rooms r
+    LEFT JOIN chat_members me ON me.chat_room_id = r.id AND me.user_id = $1
+"#;
+
+async fn fetch_community(pool: &PgPool, room_id: Uuid, viewer_id: Uuid) -> Result<Option<Community>, sqlx::Error> {
+    sqlx::query_as::<_, Community>(&format!(
+        "SELECT {} WHERE r.id = $2 AND r.room_type = 'public'",
+        COMMUNITY_COLUMNS
+    ))
+    .bind(viewer_id)
+    .bind(room_id)
+    .fetch_optional(pool)
+    .await
+}
+
+fn rank(role: &str) -> u8 {
+    match role {
+        "owner" => 2,
+        "moderator" => 1,
+        _ => 0,
+    }
+}
+
+fn random_code() -> String {
+    let mut buf = [0u8; 12];
+    OsRng.fill_bytes(&mut buf);
+    hex::encode(buf)
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    eprintln!("❌ Community query failed: {:?}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+}
+
+fn not_found() -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, "Community not found".to_string())
+}
+
+/// The caller's role in a community, refused unless it's at least `min_role`
+async fn require_role(pool: &PgPool, room_id: Uuid, user_id: Uuid, min_role: &str) -> Result<String, (StatusCode, String)> {
+    let membership = sqlx::query_as::<_, (Option<String>,)>(
+        r#"
+        SELECT cm.role FROM chat_rooms r
+        LEFT JOIN chat_members cm ON cm.chat_room_id = r.id AND cm.user_id = $2
+        WHERE r.id = $1 AND r.room_type = 'public'
+        "#
+    )
+    .bind(room_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?
+    .ok_or_else(not_found)?;
+
+    match membership.0 {
+        Some(role) if rank(&role) >= rank(min_role) => Ok(role),
+        Some(_) => Err((StatusCode::FORBIDDEN, format!("Only a community {} can do this", min_role))),
+        None => Err((StatusCode::FORBIDDEN, "You're not a member of this community".to_string())),
+    }
+}
+
+/// How the caller may use `P` in a community; refused when the community
+/// doesn't exist or they have no such power there
+async fn require_power<P: Permission>(pool: &PgPool, room_id: Uuid, user: &AuthUser) -> Result<Scope, (StatusCode, String)> {
+    let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM chat_rooms WHERE id = $1 AND room_type = 'public')")
+        .bind(room_id)
+        .fetch_one(pool)
+        .await
+        .map_err(db_error)?;
+    if !exists {
+        return Err(not_found());
+    }
+    in_community::<P>(pool, user, room_id)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::FORBIDDEN, "Only the community's moderators can do this".to_string()))
+}
+
+async fn member_role(pool: &PgPool, room_id: Uuid, user_id: Uuid) -> Result<Option<String>, (StatusCode, String)> {
+    sqlx::query_scalar::<_, String>("SELECT role FROM chat_members WHERE chat_room_id = $1 AND user_id = $2")
+        .bind(room_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)
+}
+
+// One moderation action, for whichever log it belongs in
+struct ModAction {
+    action: &'static str,
+    target_user_id: Option<Uuid>,
+    target_message_id: Option<Uuid>,
+    details: serde_json::Value,
+}
+
+/// Record a moderation action: in the community's own log when the caller
+/// acted as its moderator, in admin_logs when they acted as staff
+async fn log_moderation(state: &Arc<AppState>, scope: Scope, moderator_id: Uuid, room_id: Uuid, entry: ModAction) {
+    match scope {
+        Scope::Global => {
+            let mut details = entry.details;
+            if let (Some(message_id), Some(details)) = (entry.target_message_id, details.as_object_mut()) {
+                details.insert("message_id".to_string(), serde_json::json!(message_id));
+            }
+            crate::admin::log_admin_action(
+                state,
+                moderator_id,
+                format!("community_{}", entry.action),
+                entry.target_user_id,
+                Some("community".to_string()),
+                Some(room_id),
+                details,
+            )
+            .await;
+        }
+        Scope::Community => {
+            if let Err(e) = sqlx::query(
+                r#"
+                INSERT INTO community_mod_logs (chat_room_id, moderator_id, action, target_user_id, target_message_id, details)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                "#
+            )
+            .bind(room_id)
+            .bind(moderator_id)
+            .bind(entry.action)
+            .bind(entry.target_user_id)
+            .bind(entry.target_message_id)
+            .bind(&entry.details)
+            .execute(state.pool.as_ref())
+            .await
+            {
+                eprintln!("❌ Failed to log community moderation in {}: {:?}", room_id, e);
+            }
+        }
+    }
+}
+
+async fn notify(pool: &PgPool, user_id: Uuid, kind: &str, from_user_id: Uuid, message: Message) {
+    if let Err(e) = sqlx::query(
+        "INSERT INTO notifications (user_id, type, from_user_id, message, message_key, message_args) VALUES ($1, $2, $3, $4, $5, $6)"
+    )
+    .bind(user_id)
+    .bind(kind)
+    .bind(from_user_id)
+    .bind(message.text())
+    .bind(message.key)
+    .bind(&message.args)
+    .execute(pool)
+    .await
+    {
+        eprintln!("❌ Failed to notify {} about a community: {:?}", user_id, e);
+    }
+}
+
+fn check_description(description: &str) -> Result<(), (StatusCode, String)> {
+    if description.chars().count() > DESCRIPTION_MAX_LENGTH {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Descriptions are limited to {} characters", DESCRIPTION_MAX_LENGTH),
+        ));
+    }
+    Ok(())
+}
+
+/// Add the caller to a community, or file a join request if it needs approval
+async fn join(state: &AppState, room_id: Uuid, user: &AuthUser) -> Result<(StatusCode, Json<Community>), (StatusCode, String)> {
+    let community = fetch_community(&state.pool, room_id, user.id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(not_found)?;
+    if community.my_role.is_some() || community.join_requested {
+        let status = if community.join_requested { StatusCode::ACCEPTED } else { StatusCode::OK };
+        return Ok((status, Json(community)));
+    }
+
+    let banned = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM community_bans WHERE chat_room_id = $1 AND user_id = $2)")
+        .bind(room_id)
+        .bind(user.id)
+        .fetch_one(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+    if banned {
+        return Err((StatusCode::FORBIDDEN, "You've been banned from this community".to_string()));
+    }
+
+    if community.join_approval {
+        sqlx::query("INSERT INTO community_join_requests (chat_room_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+            .bind(room_id)
+            .bind(user.id)
+            .execute(state.pool.as_ref())
+            .await
+            .map_err(db_error)?;
+    } else {
+        sqlx::query("INSERT INTO chat_members (chat_room_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+            .bind(room_id)
+            .bind(user.id)
+            .execute(state.pool.as_ref())
+            .await
+            .map_err(db_error)?;
+    }
+
+    let community = fetch_community(&state.pool, room_id, user.id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(not_found)?;
+    let status = if community.join_requested { StatusCode::ACCEPTED } else { StatusCode::OK };
+    Ok((status, Json(community)))
+}
+
+// Start a community; the caller becomes its owner
+#[utoipa::path(
+    post,
+    path = "/api/v1/communities",
+    tag = "communities",
+    request_body = CreateCommunityRequest,
+    responses(
+        (status = 201, body = Community),
+        (status = 400, description = "Missing name, description too long, or language that isn't allowed"),
+        (status = 429, description = "Too many chats created this hour"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn create_community(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CreateCommunityRequest>,
+) -> axum::response::Result<(StatusCode, Json<Community>)> {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Communities need a name".to_string()).into());
+    }
+    let description = payload.description.as_deref().map(str::trim).filter(|d| !d.is_empty());
+    if let Some(description) = description {
+        check_description(description)?;
+    }
+
+    crate::quotas::check_chat_creation(&state.pool, user.id).await?;
+    let screened_name = crate::text_moderation::screen(&state.pool, name).await?;
+    let screened_description = match description {
+        Some(description) => Some(crate::text_moderation::screen(&state.pool, description).await?),
+        None => None,
+    };
+
+    let mut tx = state.pool.begin().await.map_err(db_error)?;
+    let room_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO chat_rooms (is_group, room_type, name, description, avatar_url, join_approval, invite_code, created_by)
+        VALUES (TRUE, 'public', $1, $2, $3, $4, $5, $6)
+        RETURNING id
+        "#
+    )
+    .bind(&screened_name.text)
+    .bind(screened_description.as_ref().map(|s| s.text.as_str()))
+    .bind(payload.avatar_url.as_deref().map(str::trim).filter(|a| !a.is_empty()))
+    .bind(payload.join_approval)
+    .bind(random_code())
+    .bind(user.id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(db_error)?;
+
+    sqlx::query("INSERT INTO chat_members (chat_room_id, user_id, role) VALUES ($1, $2, 'owner')")
+        .bind(room_id)
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+
+    screened_name
+        .record(&state.pool, crate::text_moderation::CONTENT_GROUP_NAME, room_id, user.id)
+        .await;
+    if let Some(screened) = &screened_description {
+        screened
+            .record(&state.pool, crate::text_moderation::CONTENT_GROUP_NAME, room_id, user.id)
+            .await;
+    }
+
+    let community = fetch_community(&state.pool, room_id, user.id)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Community vanished".to_string()))?;
+
+    Ok((StatusCode::CREATED, Json(community)))
+}
+
+// Find communities to join, largest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/communities/discover",
+    tag = "communities",
+    params(DiscoverQuery),
+    responses((status = 200, body = [Community]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn discover_communities(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<DiscoverQuery>,
+) -> Result<Json<Vec<Community>>, (StatusCode, String)> {
+    let limit = params.limit.unwrap_or(20).clamp(1, 50);
+    let offset = params.offset.unwrap_or(0).max(0);
+    let pattern = params
+        .q
+        .as_deref()
+        .map(str::trim)
+        .filter(|q| !q.is_empty())
+        .map(|q| format!("%{}%", q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")));
+
+    let communities = sqlx::query_as::<_, Community>(&format!(
+        r#"
+        SELECT {}
+        WHERE r.room_type = 'public'
+          AND ($2::text IS NULL OR r.name ILIKE $2 OR r.description ILIKE $2)
+        ORDER BY member_count DESC, r.created_at DESC
+        LIMIT $3 OFFSET $4
+        "#,
+        COMMUNITY_COLUMNS
+    ))
+    .bind(user.id)
+    .bind(pattern)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(state.read_pool())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(communities))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/communities/{room_id}",
+    tag = "communities",
+    params(("room_id" = Uuid, Path, description = "Community chat room ID")),
+    responses((status = 200, body = Community), (status = 404, description = "No such community"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_community(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<Community>, (StatusCode, String)> {
+    let community = fetch_community(&state.pool, room_id, user.id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(not_found)?;
+    Ok(Json(community))
+}
+
+// Preview the community behind a join link
+#[utoipa::path(
+    get,
+    path = "/api/v1/communities/invite/{invite_code}",
+    tag = "communities",
+    params(("invite_code" = String, Path, description = "Code from the join link")),
+    responses((status = 200, body = Community), (status = 404, description = "Link is invalid or was reset"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_community_by_invite(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(invite_code): Path<String>,
+) -> Result<Json<Community>, (StatusCode, String)> {
+    let community = sqlx::query_as::<_, Community>(&format!(
+        "SELECT {} WHERE r.invite_code = $2 AND r.room_type = 'public'",
+        COMMUNITY_COLUMNS
+    ))
+    .bind(user.id)
+    .bind(invite_code)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(db_error)?
+    .ok_or_else(not_found)?;
+    Ok(Json(community))
+}
+
+// Join a community, or ask to if it needs approval
+#[utoipa::path(
+    post,
+    path = "/api/v1/communities/{room_id}/join",
+    tag = "communities",
+    params(("room_id" = Uuid, Path, description = "Community chat room ID")),
+    responses(
+        (status = 200, body = Community, description = "Joined, or already a member"),
+        (status = 202, body = Community, description = "Waiting on a moderator to approve"),
+        (status = 403, description = "Caller is banned from the community"),
+        (status = 404, description = "No such community"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn join_community(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(room_id): Path<Uuid>,
+) -> Result<(StatusCode, Json<Community>), (StatusCode, String)> {
+    join(&state, room_id, &user).await
+}
+
+// Join through a link; approval still applies
+#[utoipa::path(
+    post,
+    path = "/api/v1/communities/invite/{invite_code}/join",
+    tag = "communities",
+    params(("invite_code" = String, Path, description = "Code from the join link")),
+    responses(
+        (status = 200, body = Community, description = "Joined, or already a member"),
+        (status = 202, body = Community, description = "Waiting on a moderator to approve"),
+        (status = 403, description = "Caller is banned from the community"),
+        (status = 404, description = "Link is invalid or was reset"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn join_community_by_invite(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(invite_code): Path<String>,
+) -> Result<(StatusCode, Json<Community>), (StatusCode, String)> {
+    let room_id = sqlx::query_scalar::<_, Uuid>("SELECT id FROM chat_rooms WHERE invite_code = $1 AND room_type = 'public'")
+        .bind(invite_code)
+        .fetch_optional(state.pool.as_ref())
+        .await
+        .map_err(db_error)?
+        .ok_or_else(not_found)?;
+    join(&state, room_id, &user).await
+}
+
+// Change the community's name, description, avatar or join approval
+#[utoipa::path(
+    patch,
+    path = "/api/v1/communities/{room_id}",
+    tag = "communities",
+    params(("room_id" = Uuid, Path, description = "Community chat room ID")),
+    request_body = UpdateCommunityRequest,
+    responses(
+        (status = 200, body = Community),
+        (status = 400, description = "Empty name, description too long, or language that isn't allowed"),
+        (status = 403, description = "Caller isn't a moderator"),
+        (status = 404, description = "No such community"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_community(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(room_id): Path<Uuid>,
+    Json(payload): Json<UpdateCommunityRequest>,
+) -> Result<Json<Community>, (StatusCode, String)> {
+    require_role(&state.pool, room_id, user.id, "moderator").await?;
+
+    let screened_name = match payload.name.as_deref().map(str::trim) {
+        Some("") => return Err((StatusCode::BAD_REQUEST, "Communities need a name".to_string())),
+        Some(name) => Some(crate::text_moderation::screen(&state.pool, name).await?),
+        None => None,
+    };
+    let screened_description = match payload.description.as_deref().map(str::trim) {
+        Some("") => None,
+        Some(description) => {
+            check_description(description)?;
+            Some(crate::text_moderation::screen(&state.pool, description).await?)
+        }
+        None => None,
+    };
+    let avatar_url = payload.avatar_url.as_deref().map(str::trim);
+
+    let previous_avatar = sqlx::query_scalar::<_, Option<String>>(
+        r#"
+        UPDATE chat_rooms r SET
+            name = COALESCE($2, r.name),
+            description = CASE WHEN $3 THEN $4 ELSE r.description END,
+            avatar_url = CASE WHEN $5 THEN NULLIF($6, '') ELSE r.avatar_url END,
+            join_approval = COALESCE($7, r.join_approval),
+            updated_at = NOW()
+        FROM (SELECT avatar_url AS old_avatar_url FROM chat_rooms WHERE id = $1 FOR UPDATE) old
+        WHERE r.id = $1
+        RETURNING old.old_avatar_url
+        "#
+    )
+    .bind(room_id)
+    .bind(screened_name.as_ref().map(|s| s.text.as_str()))
+    .bind(payload.description.is_some())
+    .bind(screened_description.as_ref().map(|s| s.text.as_str()))
+    .bind(avatar_url.is_some())
+    .bind(avatar_url)
+    .bind(payload.join_approval)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(db_error)?
+    .flatten();
+
+    // The old avatar may still be cached on the CDN
+    if let Some(old) = previous_avatar.filter(|old| avatar_url.is_some_and(|new| new != old)) {
+        state.media_service.purge_urls(&[old.as_str()]).await;
+    }
+
+    for screened in screened_name.iter().chain(screened_description.iter()) {
+        screened
+            .record(&state.pool, crate::text_moderation::CONTENT_GROUP_NAME, room_id, user.id)
+            .await;
+    }
+
+    let community = fetch_community(&state.pool, room_id, user.id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(not_found)?;
+    Ok(Json(community))
+}
+
+// Replace the join link; the old one stops working
+#[utoipa::path(
+    post,
+    path = "/api/v1/communities/{room_id}/invite-link",
+    tag = "communities",
+    params(("room_id" = Uuid, Path, description = "Community chat room ID")),
+    responses(
+        (status = 200, body = Community),
+        (status = 403, description = "Caller isn't a moderator"),
+        (status = 404, description = "No such community"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn reset_invite_link(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<Community>, (StatusCode, String)> {
+    require_role(&state.pool, room_id, user.id, "moderator").await?;
+
+    sqlx::query("UPDATE chat_rooms SET invite_code = $2 WHERE id = $1")
+        .bind(room_id)
+        .bind(random_code())
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+
+    let community = fetch_community(&state.pool, room_id, user.id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(not_found)?;
+    Ok(Json(community))
+}
+
+// Members, owner and moderators first
+#[utoipa::path(
+    get,
+    path = "/api/v1/communities/{room_id}/members",
+    tag = "communities",
+    params(("room_id" = Uuid, Path, description = "Community chat room ID")),
+    responses((status = 200, body = [CommunityMember]), (status = 404, description = "No such community"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_members(
+    _user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<Vec<CommunityMember>>, (StatusCode, String)> {
+    let members = sqlx::query_as::<_, CommunityMember>(
+        r#"
+        SELECT cm.user_id, u.username, cm.role, cm.joined_at
+        FROM chat_members cm
+        JOIN chat_rooms r ON r.id = cm.chat_room_id
+        JOIN users u ON u.id = cm.user_id
+        WHERE cm.chat_room_id = $1 AND r.room_type = 'public'
+        ORDER BY CASE cm.role WHEN 'owner' THEN 0 WHEN 'moderator' THEN 1 ELSE 2 END, cm.joined_at
+        "#
+    )
+    .bind(room_id)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    if members.is_empty() {
+        return Err(not_found());
+    }
+    Ok(Json(members))
+}
+
+// The owner promotes or demotes a member, or hands over the community
+#[utoipa::path(
+    put,
+    path = "/api/v1/communities/{room_id}/members/{user_id}/role",
+    tag = "communities",
+    params(("room_id" = Uuid, Path, description = "Community chat room ID"), ("user_id" = Uuid, Path, description = "Member's user ID")),
+    request_body = SetRoleRequest,
+    responses(
+        (status = 204, description = "Role changed"),
+        (status = 400, description = "Unknown role, or changing your own"),
+        (status = 403, description = "Caller isn't the owner"),
+        (status = 404, description = "No such community or member"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn set_member_role(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path((room_id, member_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<SetRoleRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if !matches!(payload.role.as_str(), "owner" | "moderator" | "member") {
+        return Err((StatusCode::BAD_REQUEST, "Role must be owner, moderator or member".to_string()));
+    }
+    if member_id == user.id {
+        return Err((StatusCode::BAD_REQUEST, "You can't change your own role".to_string()));
+    }
+    require_role(&state.pool, room_id, user.id, "owner").await?;
+
+    let mut tx = state.pool.begin().await.map_err(db_error)?;
+    let result = sqlx::query("UPDATE chat_members SET role = $3 WHERE chat_room_id = $1 AND user_id = $2")
+        .bind(room_id)
+        .bind(member_id)
+        .bind(&payload.role)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Member not found".to_string()));
+    }
+    // A community has one owner; handing it over leaves the old owner a moderator
+    if payload.role == "owner" {
+        sqlx::query("UPDATE chat_members SET role = 'moderator' WHERE chat_room_id = $1 AND user_id = $2")
+            .bind(room_id)
+            .bind(user.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+    }
+    tx.commit().await.map_err(db_error)?;
+
+    log_moderation(
+        &state,
+        Scope::Community,
+        user.id,
+        room_id,
+        ModAction {
+            action: "set_role",
+            target_user_id: Some(member_id),
+            target_message_id: None,
+            details: serde_json::json!({ "role": payload.role }),
+        },
+    )
+    .await;
+
+    if payload.role != "member" {
+        let community_name = sqlx::query_scalar::<_, Option<String>>("SELECT name FROM chat_rooms WHERE id = $1")
+            .bind(room_id)
+            .fetch_one(state.pool.as_ref())
+            .await
+            .map_err(db_error)?
+            .unwrap_or_default();
+        notify(
+            &state.pool,
+            member_id,
+            "community_role",
+            user.id,
+            Message::new(if payload.role == "owner" { "community_role_owner" } else { "community_role_moderator" })
+                .arg("username", &user.username)
+                .arg("community", &community_name),
+        )
+        .await;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Leave a community, or (as a moderator) remove someone ranked below you
+#[utoipa::path(
+    delete,
+    path = "/api/v1/communities/{room_id}/members/{user_id}",
+    tag = "communities",
+    params(("room_id" = Uuid, Path, description = "Community chat room ID"), ("user_id" = Uuid, Path, description = "Member's user ID")),
+    responses(
+        (status = 204, description = "Removed"),
+        (status = 400, description = "The owner has to hand the community over before leaving"),
+        (status = 403, description = "Caller can't remove this member"),
+        (status = 404, description = "No such community or member"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn remove_member(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path((room_id, member_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let caller_role = require_role(&state.pool, room_id, user.id, "member").await?;
+
+    if member_id == user.id {
+        if caller_role == "owner" {
+            return Err((StatusCode::BAD_REQUEST, "Make someone else the owner before leaving".to_string()));
+        }
+    } else {
+        let member_role = sqlx::query_scalar::<_, String>("SELECT role FROM chat_members WHERE chat_room_id = $1 AND user_id = $2")
+            .bind(room_id)
+            .bind(member_id)
+            .fetch_optional(state.pool.as_ref())
+            .await
+            .map_err(db_error)?
+            .ok_or((StatusCode::NOT_FOUND, "Member not found".to_string()))?;
+        if rank(&caller_role) < rank("moderator") || rank(&member_role) >= rank(&caller_role) {
+            return Err((StatusCode::FORBIDDEN, "You can't remove this member".to_string()));
+        }
+    }
+
+    sqlx::query("DELETE FROM chat_members WHERE chat_room_id = $1 AND user_id = $2")
+        .bind(room_id)
+        .bind(member_id)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+    crate::cache::invalidate_membership(&state.redis, room_id, member_id).await;
+
+    if member_id != user.id {
+        log_moderation(
+            &state,
+            Scope::Community,
+            user.id,
+            room_id,
+            ModAction {
+                action: "remove_member",
+                target_user_id: Some(member_id),
+                target_message_id: None,
+                details: serde_json::json!({}),
+            },
+        )
+        .await;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// People waiting to join, oldest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/communities/{room_id}/requests",
+    tag = "communities",
+    params(("room_id" = Uuid, Path, description = "Community chat room ID")),
+    responses(
+        (status = 200, body = [JoinRequest]),
+        (status = 403, description = "Caller isn't a moderator"),
+        (status = 404, description = "No such community"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_join_requests(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<Vec<JoinRequest>>, (StatusCode, String)> {
+    require_role(&state.pool, room_id, user.id, "moderator").await?;
+
+    let requests = sqlx::query_as::<_, JoinRequest>(
+        r#"
+        SELECT j.user_id, u.username, j.created_at
+        FROM community_join_requests j
+        JOIN users u ON u.id = j.user_id
+        WHERE j.chat_room_id = $1
+        ORDER BY j.created_at
+        "#
+    )
+    .bind(room_id)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(requests))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/communities/{room_id}/requests/{user_id}/approve",
+    tag = "communities",
+    params(("room_id" = Uuid, Path, description = "Community chat room ID"), ("user_id" = Uuid, Path, description = "Requester's user ID")),
+    responses(
+        (status = 204, description = "Approved; the requester is now a member"),
+        (status = 403, description = "Caller isn't a moderator"),
+        (status = 404, description = "No such community or request"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn approve_join_request(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path((room_id, requester_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_role(&state.pool, room_id, user.id, "moderator").await?;
+
+    let mut tx = state.pool.begin().await.map_err(db_error)?;
+    let result = sqlx::query("DELETE FROM community_join_requests WHERE chat_room_id = $1 AND user_id = $2")
+        .bind(room_id)
+        .bind(requester_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Request not found".to_string()));
+    }
+    sqlx::query("INSERT INTO chat_members (chat_room_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+        .bind(room_id)
+        .bind(requester_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+
+    let community_name = sqlx::query_scalar::<_, Option<String>>("SELECT name FROM chat_rooms WHERE id = $1")
+        .bind(room_id)
+        .fetch_one(state.pool.as_ref())
+        .await
+        .map_err(db_error)?
+        .unwrap_or_default();
+    notify(
+        &state.pool,
+        requester_id,
+        "community_join_approved",
+        user.id,
+        Message::new("community_join_approved").arg("community", &community_name),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// A moderator turns a request down, or the requester withdraws it
+#[utoipa::path(
+    post,
+    path = "/api/v1/communities/{room_id}/requests/{user_id}/decline",
+    tag = "communities",
+    params(("room_id" = Uuid, Path, description = "Community chat room ID"), ("user_id" = Uuid, Path, description = "Requester's user ID")),
+    responses(
+        (status = 204, description = "Declined or withdrawn"),
+        (status = 403, description = "Caller isn't a moderator or the requester"),
+        (status = 404, description = "No such community or request"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn decline_join_request(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path((room_id, requester_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if requester_id != user.id {
+        require_role(&state.pool, room_id, user.id, "moderator").await?;
+    }
+
+    let result = sqlx::query("DELETE FROM community_join_requests WHERE chat_room_id = $1 AND user_id = $2")
+        .bind(room_id)
+        .bind(requester_id)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Request not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Remove someone from the community and keep them from joining again
+#[utoipa::path(
+    post,
+    path = "/api/v1/communities/{room_id}/bans/{user_id}",
+    tag = "communities",
+    params(("room_id" = Uuid, Path, description = "Community chat room ID"), ("user_id" = Uuid, Path, description = "User to ban")),
+    request_body(content = Option<BanMemberRequest>),
+    responses(
+        (status = 204, description = "Banned; they're no longer a member"),
+        (status = 400, description = "Banning yourself, or the reason is too long"),
+        (status = 403, description = "Caller can't ban in this community, or can't ban this member"),
+        (status = 404, description = "No such community or user"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn ban_member(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path((room_id, target_id)): Path<(Uuid, Uuid)>,
+    input: Option<Json<BanMemberRequest>>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if target_id == user.id {
+        return Err((StatusCode::BAD_REQUEST, "You can't ban yourself".to_string()));
+    }
+    let reason = input
+        .and_then(|Json(input)| input.reason)
+        .map(|reason| reason.trim().to_string())
+        .filter(|reason| !reason.is_empty());
+    if reason.as_ref().is_some_and(|reason| reason.chars().count() > BAN_REASON_MAX_LENGTH) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Ban reasons are limited to {} characters", BAN_REASON_MAX_LENGTH),
+        ));
+    }
+    let scope = require_power::<CanBan>(&state.pool, room_id, &user).await?;
+
+    let target_role = member_role(&state.pool, room_id, target_id).await?;
+    if target_role.is_none() {
+        let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE id = $1)")
+            .bind(target_id)
+            .fetch_one(state.pool.as_ref())
+            .await
+            .map_err(db_error)?;
+        if !exists {
+            return Err((StatusCode::NOT_FOUND, "User not found".to_string()));
+        }
+    }
+    let target_rank = target_role.as_deref().map_or(0, rank);
+    // Staff can ban moderators, but nobody bans the owner out of their own community
+    if target_rank == rank("owner") {
+        return Err((StatusCode::FORBIDDEN, "The owner can't be banned".to_string()));
+    }
+    if scope == Scope::Community {
+        let caller_role = member_role(&state.pool, room_id, user.id).await?.unwrap_or_default();
+        if target_rank >= rank(&caller_role) {
+            return Err((StatusCode::FORBIDDEN, "You can't ban this member".to_string()));
+        }
+    }
+
+    let mut tx = state.pool.begin().await.map_err(db_error)?;
+    sqlx::query("DELETE FROM chat_members WHERE chat_room_id = $1 AND user_id = $2")
+        .bind(room_id)
+        .bind(target_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    sqlx::query("DELETE FROM community_join_requests WHERE chat_room_id = $1 AND user_id = $2")
+        .bind(room_id)
+        .bind(target_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    sqlx::query(
+        r#"
+        INSERT INTO community_bans (chat_room_id, user_id, banned_by, reason)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (chat_room_id, user_id) DO UPDATE SET banned_by = EXCLUDED.banned_by, reason = EXCLUDED.reason
+        "#
+    )
+    .bind(room_id)
+    .bind(target_id)
+    .bind(user.id)
+    .bind(&reason)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+    crate::cache::invalidate_membership(&state.redis, room_id, target_id).await;
+
+    log_moderation(
+        &state,
+        scope,
+        user.id,
+        room_id,
+        ModAction {
+            action: "ban",
+            target_user_id: Some(target_id),
+            target_message_id: None,
+            details: serde_json::json!({ "reason": reason, "was_member": target_role.is_some() }),
+        },
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/communities/{room_id}/bans/{user_id}",
+    tag = "communities",
+    params(("room_id" = Uuid, Path, description = "Community chat room ID"), ("user_id" = Uuid, Path, description = "Banned user's ID")),
+    responses(
+        (status = 204, description = "Unbanned; they can join again"),
+        (status = 403, description = "Caller can't ban in this community"),
+        (status = 404, description = "No such community or ban"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn unban_member(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path((room_id, target_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let scope = require_power::<CanBan>(&state.pool, room_id, &user).await?;
+
+    let result = sqlx::query("DELETE FROM community_bans WHERE chat_room_id = $1 AND user_id = $2")
+        .bind(room_id)
+        .bind(target_id)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Ban not found".to_string()));
+    }
+
+    log_moderation(
+        &state,
+        scope,
+        user.id,
+        room_id,
+        ModAction {
+            action: "unban",
+            target_user_id: Some(target_id),
+            target_message_id: None,
+            details: serde_json::json!({}),
+        },
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Who's banned, most recent first
+#[utoipa::path(
+    get,
+    path = "/api/v1/communities/{room_id}/bans",
+    tag = "communities",
+    params(("room_id" = Uuid, Path, description = "Community chat room ID")),
+    responses(
+        (status = 200, body = [CommunityBan]),
+        (status = 403, description = "Caller can't ban in this community"),
+        (status = 404, description = "No such community"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_bans(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<Vec<CommunityBan>>, (StatusCode, String)> {
+    require_power::<CanBan>(&state.pool, room_id, &user).await?;
+
+    let bans = sqlx::query_as::<_, CommunityBan>(
+        r#"
+        SELECT b.user_id, u.username, b.banned_by, b.reason, b.created_at
+        FROM community_bans b
+        JOIN users u ON u.id = b.user_id
+        WHERE b.chat_room_id = $1
+        ORDER BY b.created_at DESC
+        "#
+    )
+    .bind(room_id)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(bans))
+}
+
+// Take down a message posted in the community, whenever it was sent. Members
+// see it turn into an unsent placeholder.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/communities/{room_id}/messages/{message_id}",
+    tag = "communities",
+    params(("room_id" = Uuid, Path, description = "Community chat room ID"), ("message_id" = Uuid, Path, description = "Message ID")),
+    responses(
+        (status = 204, description = "Removed"),
+        (status = 403, description = "Caller can't moderate this community"),
+        (status = 404, description = "No such community, or no such message in it"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn remove_message(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path((room_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let scope = require_power::<CanModerateContent>(&state.pool, room_id, &user).await?;
+
+    let sender_id = crate::chat::remove_community_message(&state.pool, &state.connections, &state.media_service, user.id, room_id, message_id)
+        .await
+        .map_err(|message| (StatusCode::INTERNAL_SERVER_ERROR, message))?
+        .ok_or((StatusCode::NOT_FOUND, "Message not found".to_string()))?;
+
+    log_moderation(
+        &state,
+        scope,
+        user.id,
+        room_id,
+        ModAction {
+            action: "remove_message",
+            target_user_id: Some(sender_id),
+            target_message_id: Some(message_id),
+            details: serde_json::json!({}),
+        },
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// What the community's owner and moderators have done, newest first. Staff
+// actions are in the admin log instead.
+#[utoipa::path(
+    get,
+    path = "/api/v1/communities/{room_id}/mod-log",
+    tag = "communities",
+    params(("room_id" = Uuid, Path, description = "Community chat room ID"), ModLogQuery),
+    responses(
+        (status = 200, body = [ModLogEntry]),
+        (status = 403, description = "Caller isn't a moderator"),
+        (status = 404, description = "No such community"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_mod_log(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(room_id): Path<Uuid>,
+    Query(params): Query<ModLogQuery>,
+) -> Result<Json<Vec<ModLogEntry>>, (StatusCode, String)> {
+    require_power::<CanModerateContent>(&state.pool, room_id, &user).await?;
+    let limit = params.limit.unwrap_or(50).clamp(1, 100);
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    let entries = sqlx::query_as::<_, ModLogEntry>(
+        r#"
+        SELECT l.id, l.moderator_id, u.username AS moderator_username, l.action,
+               l.target_user_id, l.target_message_id, l.details, l.created_at
+        FROM community_mod_logs l
+        LEFT JOIN users u ON u.id = l.moderator_id
+        WHERE l.chat_room_id = $1 AND ($2::text IS NULL OR l.action = $2)
+        ORDER BY l.created_at DESC
+        LIMIT $3 OFFSET $4
+        "#
+    )
+    .bind(room_id)
+    .bind(params.action)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(entries))
+}
@@ -0,0 +1,90 @@
+use axum::{
+    body::{to_bytes, Body, HttpBody},
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use flate2::{write::GzEncoder, Compression};
+use std::io::Write;
+
+// Gzip for API responses. Only text payloads (JSON, HTML, plain text) are
+// compressed, and only past a size where it pays off; media is served from
+// the bucket and never passes through here. Brotli isn't offered yet; every
+// client accepts gzip.
+
+// Smaller bodies go out as-is
+const MIN_COMPRESSED_BODY: usize = 1024;
+// Larger bodies (or ones of unknown size) aren't buffered for compression
+const MAX_COMPRESSED_BODY: usize = 16 * 1024 * 1024;
+
+pub async fn gzip(request: Request, next: Next) -> Response {
+    let accepts_gzip = request
+        .headers()
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(accepts_gzip);
+
+    let response = next.run(request).await;
+    let too_large = response.body().size_hint().upper().is_none_or(|n| n > MAX_COMPRESSED_BODY as u64);
+    if !accepts_gzip || too_large || !compressible(&response) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, MAX_COMPRESSED_BODY).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("❌ Failed to buffer response for compression: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    parts.headers.append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    if bytes.len() < MIN_COMPRESSED_BODY {
+        return Response::from_parts(parts, Body::from(bytes));
+    }
+
+    let mut encoder = GzEncoder::new(Vec::with_capacity(bytes.len() / 4), Compression::fast());
+    let compressed = match encoder.write_all(&bytes).and_then(|_| encoder.finish()) {
+        Ok(compressed) => compressed,
+        Err(e) => {
+            eprintln!("❌ Failed to gzip response: {}", e);
+            return Response::from_parts(parts, Body::from(bytes));
+        }
+    };
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+    Response::from_parts(parts, Body::from(compressed))
+}
+
+/// Accept-Encoding lists gzip without refusing it (q=0)
+fn accepts_gzip(accept_encoding: &str) -> bool {
+    accept_encoding.split(',').any(|coding| {
+        let mut params = coding.split(';').map(str::trim);
+        let name = params.next().unwrap_or_default();
+        let refused = params.any(|p| p.replace(' ', "") == "q=0" || p.replace(' ', "") == "q=0.0");
+        (name.eq_ignore_ascii_case("gzip") || name == "*") && !refused
+    })
+}
+
+fn compressible(response: &Response) -> bool {
+    if response.status() == StatusCode::SWITCHING_PROTOCOLS
+        || response.status() == StatusCode::NO_CONTENT
+        || response.status() == StatusCode::NOT_MODIFIED
+        || response.headers().contains_key(header::CONTENT_ENCODING)
+    {
+        return false;
+    }
+
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|content_type| {
+            content_type.starts_with("application/json")
+                || content_type.starts_with("text/")
+                || content_type.starts_with("application/javascript")
+        })
+}
@@ -0,0 +1,294 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::admin::ServiceCaller;
+use crate::permissions::{CanManagePlatform, Permitted};
+use crate::AppState;
+
+// Reconciliation of the denormalized counters. Triggers keep them in step with
+// their source tables, but rows written before a trigger existed, failed
+// migrations or manual fixes leave them wrong with nothing to correct them.
+// Each counter is recomputed from its source table; rows that disagree are
+// rewritten and reported. A write racing the recount can still be off by one
+// until the next run.
+
+const MAX_SAMPLES: usize = 20;
+
+/// Rows of `table` the run covers; $1 is a user ID, $2 a story ID, both optional
+const USER_SCOPE: &str = "$2::uuid IS NULL AND ($1::uuid IS NULL OR t.id = $1)";
+const STORY_SCOPE: &str = "($1::uuid IS NULL OR t.user_id = $1) AND ($2::uuid IS NULL OR t.id = $2)";
+const COMMENT_SCOPE: &str = "($1::uuid IS NULL OR EXISTS (SELECT 1 FROM stories s WHERE s.id = t.story_id AND s.user_id = $1)) \
+                             AND ($2::uuid IS NULL OR t.story_id = $2)";
+
+struct Counter {
+    name: &'static str,
+    table: &'static str,
+    column: &'static str,
+    /// Correlated count of the source rows for `t`
+    actual: &'static str,
+    scope: &'static str,
+}
+
+const COUNTERS: &[Counter] = &[
+    Counter {
+        name: "users.follower_count",
+        table: "users",
+        column: "follower_count",
+        actual: "SELECT COUNT(*)::int FROM follows f WHERE f.following_id = t.id",
+        scope: USER_SCOPE,
+    },
+    Counter {
+        name: "users.following_count",
+        table: "users",
+        column: "following_count",
+        actual: "SELECT COUNT(*)::int FROM follows f WHERE f.follower_id = t.id",
+        scope: USER_SCOPE,
+    },
+    Counter {
+        name: "users.story_count",
+        table: "users",
+        column: "story_count",
+        actual: "SELECT COUNT(*)::int FROM stories s WHERE s.user_id = t.id",
+        scope: USER_SCOPE,
+    },
+    Counter {
+        name: "stories.like_count",
+        table: "stories",
+        column: "like_count",
+        actual: "SELECT COUNT(*)::int FROM story_likes l WHERE l.story_id = t.id",
+        scope: STORY_SCOPE,
+    },
+    Counter {
+        name: "stories.comment_count",
+        table: "stories",
+        column: "comment_count",
+        actual: "SELECT COUNT(*)::int FROM story_comments c WHERE c.story_id = t.id",
+        scope: STORY_SCOPE,
+    },
+    Counter {
+        name: "story_comments.reply_count",
+        table: "story_comments",
+        column: "reply_count",
+        actual: "SELECT COUNT(*)::int FROM story_comments r WHERE r.parent_comment_id = t.id",
+        scope: COMMENT_SCOPE,
+    },
+];
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct CounterDrift {
+    pub id: Uuid,
+    /// Value before the fix
+    pub stored: Option<i32>,
+    pub actual: i32,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CounterReport {
+    /// table.column
+    pub counter: String,
+    pub rows_fixed: usize,
+    /// Sum of |stored - actual| over the fixed rows
+    pub total_drift: i64,
+    /// Up to 20 of the fixed rows
+    pub samples: Vec<CounterDrift>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReconcileReport {
+    pub rows_fixed: usize,
+    pub counters: Vec<CounterReport>,
+}
+
+impl ReconcileReport {
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+async fn reconcile_counter(
+    pool: &PgPool,
+    counter: &Counter,
+    user_id: Option<Uuid>,
+    story_id: Option<Uuid>,
+) -> Result<CounterReport, sqlx::Error> {
+    let sql = format!(
+        r#"
+        WITH recount AS (
+            SELECT t.id, t.{column} AS stored, ({actual}) AS actual
+            FROM {table} t
+            WHERE {scope}
+        )
+        UPDATE {table} t SET {column} = d.actual
+        FROM recount d
+        WHERE t.id = d.id AND d.stored IS DISTINCT FROM d.actual
+        RETURNING t.id, d.stored, d.actual
+        "#,
+        table = counter.table,
+        column = counter.column,
+        actual = counter.actual,
+        scope = counter.scope,
+    );
+
+    let mut fixed = crate::query_metrics::observe_statement(
+        counter.name,
+        &sql,
+        sqlx::query_as::<_, CounterDrift>(&sql)
+            .bind(user_id)
+            .bind(story_id)
+            .fetch_all(pool),
+    )
+    .await?;
+
+    let rows_fixed = fixed.len();
+    let total_drift = fixed
+        .iter()
+        .map(|d| (d.stored.unwrap_or(0) as i64 - d.actual as i64).abs())
+        .sum();
+    fixed.truncate(MAX_SAMPLES);
+
+    Ok(CounterReport {
+        counter: counter.name.to_string(),
+        rows_fixed,
+        total_drift,
+        samples: fixed,
+    })
+}
+
+/// Recompute every counter in scope and fix the rows that drifted
+async fn reconcile(pool: &PgPool, user_id: Option<Uuid>, story_id: Option<Uuid>) -> Result<ReconcileReport, sqlx::Error> {
+    let mut counters = Vec::with_capacity(COUNTERS.len());
+    for counter in COUNTERS {
+        let report = reconcile_counter(pool, counter, user_id, story_id).await?;
+        if report.rows_fixed > 0 {
+            tracing::warn!(
+                counter = counter.name,
+                rows_fixed = report.rows_fixed,
+                total_drift = report.total_drift,
+                "Counter drift fixed"
+            );
+        }
+        counters.push(report);
+    }
+
+    Ok(ReconcileReport {
+        rows_fixed: counters.iter().map(|c| c.rows_fixed).sum(),
+        counters,
+    })
+}
+
+/// Job body: reconcile every counter across all rows
+pub async fn reconcile_all(pool: &PgPool) -> Result<Option<serde_json::Value>, String> {
+    let report = reconcile(pool, None, None).await.map_err(|e| e.to_string())?;
+    Ok(Some(report.to_json()))
+}
+
+// Queue a full reconciliation (admin/cron endpoint)
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/counters/reconcile",
+    tag = "admin",
+    responses((status = 202, body = serde_json::Value), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []), ("service_token" = []))
+)]
+pub async fn trigger_reconcile(
+    caller: ServiceCaller,
+    State(state): State<Arc<AppState>>,
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, String)> {
+    crate::jobs::enqueue_trigger(&state, crate::jobs::RECONCILE_COUNTERS, &caller).await
+}
+
+// Reconcile one user's counters and those of their stories and comments on them
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/counters/users/{user_id}/reconcile",
+    tag = "admin",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    responses(
+        (status = 200, body = ReconcileReport),
+        (status = 404, description = "User not found"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn reconcile_user(
+    admin: Permitted<CanManagePlatform>,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<ReconcileReport>, (StatusCode, String)> {
+    let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE id = $1)")
+        .bind(user_id)
+        .fetch_one(state.pool.as_ref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !exists {
+        return Err((StatusCode::NOT_FOUND, "User not found".to_string()));
+    }
+
+    let report = reconcile(&state.pool, Some(user_id), None)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "reconcile_counters".to_string(),
+        Some(user_id),
+        Some("user".to_string()),
+        Some(user_id),
+        report.to_json(),
+    )
+    .await;
+
+    Ok(Json(report))
+}
+
+// Reconcile one story's counters and the reply counts of its comments
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/counters/stories/{story_id}/reconcile",
+    tag = "admin",
+    params(("story_id" = Uuid, Path, description = "Story ID")),
+    responses(
+        (status = 200, body = ReconcileReport),
+        (status = 404, description = "Story not found"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn reconcile_story(
+    admin: Permitted<CanManagePlatform>,
+    State(state): State<Arc<AppState>>,
+    Path(story_id): Path<Uuid>,
+) -> Result<Json<ReconcileReport>, (StatusCode, String)> {
+    let owner = sqlx::query_scalar::<_, Uuid>("SELECT user_id FROM stories WHERE id = $1")
+        .bind(story_id)
+        .fetch_optional(state.pool.as_ref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Story not found".to_string()))?;
+
+    let report = reconcile(&state.pool, None, Some(story_id))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "reconcile_counters".to_string(),
+        Some(owner),
+        Some("story".to_string()),
+        Some(story_id),
+        report.to_json(),
+    )
+    .await;
+
+    Ok(Json(report))
+}
@@ -1,6 +1,12 @@
-use sqlx::{PgPool, postgres::PgPoolOptions};
+use sqlx::{PgPool, postgres::{PgConnectOptions, PgPoolOptions}};
 use std::env;
 
+/// Connection options with the session time zone pinned to UTC, so NOW()
+/// lands in TIMESTAMP columns as UTC whatever the server's zone is
+fn utc_options(url: &str) -> Result<PgConnectOptions, sqlx::Error> {
+    Ok(url.parse::<PgConnectOptions>()?.options([("timezone", "UTC")]))
+}
+
 pub async fn init_pool() -> PgPool {
     // Check if DATABASE_URL is set (updated to use correct PostgreSQL database)
     let database_url = match env::var("DATABASE_URL") {
@@ -23,14 +29,16 @@ pub async fn init_pool() -> PgPool {
         }
     };
     
-    let database_url = database_url;
-    
     println!("Attempting to connect to database...");
     
+    let options = match utc_options(&database_url) {
+        Ok(options) => options,
+        Err(e) => panic!("Invalid DATABASE_URL: {:?}", e),
+    };
     let pool = PgPoolOptions::new()
         .max_connections(20)
         .acquire_timeout(std::time::Duration::from_secs(10))
-        .connect(&database_url)
+        .connect_with(options)
         .await;
     
     match pool {
@@ -44,3 +52,105 @@ pub async fn init_pool() -> PgPool {
         }
     }
 }
+
+// Reads past this replication lag go to the primary instead
+const DEFAULT_REPLICA_MAX_LAG_SECS: f64 = 30.0;
+const REPLICA_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Optional read replica (DATABASE_READ_URL) for read-heavy endpoints. A
+/// background check marks it unusable while it's unreachable or lagging,
+/// and reads fall back to the primary until it recovers.
+pub struct ReadReplica {
+    pool: PgPool,
+    healthy: std::sync::atomic::AtomicBool,
+    max_lag_secs: f64,
+}
+
+impl ReadReplica {
+    /// The replica when it's healthy, otherwise `primary`
+    pub fn pool_or<'a>(&'a self, primary: &'a PgPool) -> &'a PgPool {
+        if self.healthy.load(std::sync::atomic::Ordering::Relaxed) {
+            &self.pool
+        } else {
+            primary
+        }
+    }
+
+    /// Whether the last background check found it usable
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    async fn check(&self) {
+        // Lag only counts while WAL is still waiting to be replayed; an idle
+        // primary leaves the last replay timestamp old without any lag
+        let lag = sqlx::query_scalar::<_, f64>(
+            r#"
+            SELECT CASE
+                WHEN NOT pg_is_in_recovery() OR pg_last_wal_receive_lsn() = pg_last_wal_replay_lsn() THEN 0
+                ELSE COALESCE(EXTRACT(EPOCH FROM NOW() - pg_last_xact_replay_timestamp()), 0)
+            END::float8
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        let healthy = match lag {
+            Ok(lag) if lag <= self.max_lag_secs => true,
+            Ok(lag) => {
+                tracing::warn!(lag_secs = lag, "read replica is lagging; reading from the primary");
+                false
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "read replica unavailable; reading from the primary");
+                false
+            }
+        };
+
+        let was_healthy = self.healthy.swap(healthy, std::sync::atomic::Ordering::Relaxed);
+        if healthy && !was_healthy {
+            tracing::info!("read replica is serving reads");
+        }
+    }
+}
+
+/// Connect to the read replica if DATABASE_READ_URL is set and start its
+/// health check. Connections are opened lazily, so a replica that's down at
+/// startup doesn't hold up the server.
+pub fn init_read_replica() -> Option<std::sync::Arc<ReadReplica>> {
+    let url = env::var("DATABASE_READ_URL").ok().filter(|url| !url.is_empty())?;
+    let max_lag_secs = env::var("REPLICA_MAX_LAG_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_REPLICA_MAX_LAG_SECS);
+
+    let pool = match utc_options(&url).map(|options| {
+        PgPoolOptions::new()
+            .max_connections(20)
+            .acquire_timeout(std::time::Duration::from_secs(3))
+            .connect_lazy_with(options)
+    }) {
+        Ok(pool) => pool,
+        Err(e) => {
+            eprintln!("✗ Invalid DATABASE_READ_URL, reading from the primary: {}", e);
+            return None;
+        }
+    };
+    println!("✓ Read replica configured (max lag {}s)", max_lag_secs);
+
+    let replica = std::sync::Arc::new(ReadReplica {
+        pool,
+        healthy: std::sync::atomic::AtomicBool::new(false),
+        max_lag_secs,
+    });
+
+    let monitor = replica.clone();
+    tokio::spawn(async move {
+        loop {
+            monitor.check().await;
+            tokio::time::sleep(REPLICA_CHECK_INTERVAL).await;
+        }
+    });
+
+    Some(replica)
+}
@@ -4,17 +4,21 @@ use axum::{
     Json,
 };
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 use std::sync::Arc;
 use crate::AppState;
+use crate::admin::ServiceCaller;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct SearchQuery {
     pub q: String,
     #[serde(default = "default_limit")]
     pub limit: i64,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct LimitQuery {
     #[serde(default = "default_limit")]
     pub limit: i64,
@@ -24,7 +28,7 @@ fn default_limit() -> i64 {
     20
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct UserSearchResult {
     pub id: String,
     pub username: String,
@@ -35,7 +39,25 @@ pub struct UserSearchResult {
     pub is_following: bool,
 }
 
+#[derive(sqlx::FromRow)]
+struct UserRow {
+    id: uuid::Uuid,
+    username: String,
+    display_name: Option<String>,
+    avatar_url: Option<String>,
+    bio: Option<String>,
+    follower_count: Option<i64>,
+    is_following: bool,
+}
+
 // Search users by username, display name, or bio
+#[utoipa::path(
+    get,
+    path = "/api/v1/discovery/search/{viewer_id}",
+    tag = "discovery",
+    params(("viewer_id" = String, Path, description = "Viewer ID"), SearchQuery),
+    responses((status = 200, body = [UserSearchResult]))
+)]
 pub async fn search_users(
     State(state): State<Arc<AppState>>,
     Path(viewer_id): Path<String>,
@@ -47,7 +69,12 @@ pub async fn search_users(
     let search_term = format!("%{}%", params.q.to_lowercase());
     let limit = params.limit.min(50); // Cap at 50 results
 
-    let users = sqlx::query!(
+    let key = crate::cache::discovery_key(&state.redis, viewer_uuid, "search", &format!("{}:{}", limit, params.q.to_lowercase())).await;
+    if let Some(cached) = crate::cache::get(&state.redis, &key).await {
+        return Ok(Json(cached));
+    }
+
+    let users = sqlx::query_as::<_, UserRow>(
         r#"
         SELECT 
             u.id,
@@ -59,7 +86,7 @@ pub async fn search_users(
             EXISTS(
                 SELECT 1 FROM follows 
                 WHERE follower_id = $1 AND following_id = u.id
-            ) as "is_following!"
+            ) as is_following
         FROM users u
         LEFT JOIN follows f ON u.id = f.following_id
         WHERE 
@@ -68,19 +95,21 @@ pub async fn search_users(
                 LOWER(u.display_name) LIKE $2 OR
                 LOWER(u.bio) LIKE $2
             )
+            AND NOT minor_contact_restricted($1, u.id)
+            AND NOT supervision_hides_from($1, u.id)
         GROUP BY u.id
         ORDER BY follower_count DESC, u.username ASC
         LIMIT $3
-        "#,
-        viewer_uuid,
-        search_term,
-        limit
+        "#
     )
-    .fetch_all(&*state.pool)
+    .bind(viewer_uuid)
+    .bind(search_term)
+    .bind(limit)
+    .fetch_all(state.read_pool())
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let results = users
+    let results: Vec<UserSearchResult> = users
         .into_iter()
         .map(|u| UserSearchResult {
             id: u.id.to_string(),
@@ -93,10 +122,18 @@ pub async fn search_users(
         })
         .collect();
 
+    crate::cache::set(&state.redis, &key, &results, crate::cache::DISCOVERY_TTL_SECS).await;
     Ok(Json(results))
 }
 
 // Get popular/suggested users (fallback to all users if popular_users view is empty)
+#[utoipa::path(
+    get,
+    path = "/api/v1/discovery/popular/{viewer_id}",
+    tag = "discovery",
+    params(("viewer_id" = String, Path, description = "Viewer ID"), LimitQuery),
+    responses((status = 200, body = [UserSearchResult]))
+)]
 pub async fn get_popular_users(
     State(state): State<Arc<AppState>>,
     Path(viewer_id): Path<String>,
@@ -107,8 +144,13 @@ pub async fn get_popular_users(
 
     let limit = params.limit.min(50);
 
+    let key = crate::cache::discovery_key(&state.redis, viewer_uuid, "popular", &limit.to_string()).await;
+    if let Some(cached) = crate::cache::get(&state.redis, &key).await {
+        return Ok(Json(cached));
+    }
+
     // Try popular_users view first, fallback to all users
-    let users = sqlx::query!(
+    let users = sqlx::query_as::<_, UserRow>(
         r#"
         SELECT 
             u.id,
@@ -120,25 +162,27 @@ pub async fn get_popular_users(
             EXISTS(
                 SELECT 1 FROM follows 
                 WHERE follower_id = $1 AND following_id = u.id
-            ) as "is_following!"
+            ) as is_following
         FROM users u
         LEFT JOIN follows f ON u.id = f.following_id
         WHERE u.id != $1
+          AND NOT minor_contact_restricted($1, u.id)
+          AND NOT supervision_hides_from($1, u.id)
         GROUP BY u.id
         ORDER BY follower_count DESC, u.created_at DESC
         LIMIT $2
-        "#,
-        viewer_uuid,
-        limit
+        "#
     )
-    .fetch_all(&*state.pool)
+    .bind(viewer_uuid)
+    .bind(limit)
+    .fetch_all(state.read_pool())
     .await
     .map_err(|e| {
         eprintln!("❌ Error fetching popular users: {:?}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    let results = users
+    let results: Vec<UserSearchResult> = users
         .into_iter()
         .map(|u| UserSearchResult {
             id: u.id.to_string(),
@@ -151,10 +195,18 @@ pub async fn get_popular_users(
         })
         .collect();
 
+    crate::cache::set(&state.redis, &key, &results, crate::cache::DISCOVERY_TTL_SECS).await;
     Ok(Json(results))
 }
 
 // Get suggested users based on mutual follows
+#[utoipa::path(
+    get,
+    path = "/api/v1/discovery/suggested/{viewer_id}",
+    tag = "discovery",
+    params(("viewer_id" = String, Path, description = "Viewer ID"), LimitQuery),
+    responses((status = 200, body = [UserSearchResult]))
+)]
 pub async fn get_suggested_users(
     State(state): State<Arc<AppState>>,
     Path(viewer_id): Path<String>,
@@ -165,8 +217,13 @@ pub async fn get_suggested_users(
 
     let limit = params.limit.min(50);
 
+    let key = crate::cache::discovery_key(&state.redis, viewer_uuid, "suggested", &limit.to_string()).await;
+    if let Some(cached) = crate::cache::get(&state.redis, &key).await {
+        return Ok(Json(cached));
+    }
+
     // Find users followed by people the viewer follows, but not followed by viewer
-    let users = sqlx::query!(
+    let users = sqlx::query_as::<_, UserRow>(
         r#"
         SELECT 
             u.id,
@@ -175,7 +232,7 @@ pub async fn get_suggested_users(
             u.avatar_url,
             u.bio,
             COUNT(DISTINCT f1.follower_id) as follower_count,
-            false as "is_following!"
+            false as is_following
         FROM users u
         JOIN follows f2 ON u.id = f2.following_id
         JOIN follows f1 ON f2.follower_id = f1.following_id
@@ -184,21 +241,23 @@ pub async fn get_suggested_users(
             f1.follower_id = $1
             AND u.id != $1
             AND direct.id IS NULL
+            AND NOT minor_contact_restricted($1, u.id)
+            AND NOT supervision_hides_from($1, u.id)
         GROUP BY u.id
         ORDER BY follower_count DESC, u.username ASC
         LIMIT $2
-        "#,
-        viewer_uuid,
-        limit
+        "#
     )
-    .fetch_all(&*state.pool)
+    .bind(viewer_uuid)
+    .bind(limit)
+    .fetch_all(state.read_pool())
     .await
     .map_err(|e| {
         eprintln!("❌ Error fetching suggested users: {:?}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    let results = users
+    let results: Vec<UserSearchResult> = users
         .into_iter()
         .map(|u| UserSearchResult {
             id: u.id.to_string(),
@@ -211,15 +270,24 @@ pub async fn get_suggested_users(
         })
         .collect();
 
+    crate::cache::set(&state.redis, &key, &results, crate::cache::DISCOVERY_TTL_SECS).await;
     Ok(Json(results))
 }
 
 // Upload profile picture
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct UpdateAvatarRequest {
     pub avatar_url: String,
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/discovery/avatar/{user_id}",
+    tag = "discovery",
+    params(("user_id" = String, Path, description = "User ID")),
+    request_body = UpdateAvatarRequest,
+    responses((status = 200, description = "Success"))
+)]
 pub async fn update_avatar(
     State(state): State<Arc<AppState>>,
     Path(user_id): Path<String>,
@@ -228,26 +296,50 @@ pub async fn update_avatar(
     let user_uuid = uuid::Uuid::parse_str(&user_id)
         .map_err(|_| StatusCode::BAD_REQUEST)?;
 
-    sqlx::query!(
-        "UPDATE users SET avatar_url = $1 WHERE id = $2",
-        payload.avatar_url,
-        user_uuid
+    let previous = sqlx::query_scalar::<_, Option<String>>(
+        r#"
+        UPDATE users u SET avatar_url = $1
+        FROM (SELECT avatar_url AS old_avatar_url FROM users WHERE id = $2 FOR UPDATE) old
+        WHERE u.id = $2
+        RETURNING old.old_avatar_url
+        "#
     )
-    .execute(&*state.pool)
+    .bind(&payload.avatar_url)
+    .bind(user_uuid)
+    .fetch_optional(&*state.pool)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    crate::cache::invalidate_profile(&state.redis, user_uuid).await;
+
+    // Don't let the CDN keep serving the old picture
+    if let Some(old) = previous.flatten().filter(|old| *old != payload.avatar_url) {
+        state.media_service.purge_urls(&[old.as_str()]).await;
+    }
+
     Ok(StatusCode::OK)
 }
 
 // Refresh popular users materialized view (admin/cron endpoint)
+#[utoipa::path(
+    post,
+    path = "/api/v1/discovery/refresh-popular",
+    tag = "discovery",
+    responses((status = 202, body = serde_json::Value), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []), ("service_token" = []))
+)]
 pub async fn refresh_popular_users_view(
+    caller: ServiceCaller,
     State(state): State<Arc<AppState>>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, String)> {
+    crate::jobs::enqueue_trigger(&state, crate::jobs::REFRESH_POPULAR_USERS, &caller).await
+}
+
+// Refresh the popular_users materialized view (run by the job worker)
+pub async fn refresh_popular_users(pool: &sqlx::PgPool) -> Result<(), sqlx::Error> {
     sqlx::query!("SELECT refresh_popular_users()")
-        .execute(&*state.pool)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .execute(pool)
+        .await?;
 
-    Ok(StatusCode::OK)
+    Ok(())
 }
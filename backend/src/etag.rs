@@ -0,0 +1,68 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+
+// Conditional GETs for endpoints clients poll (profiles, notifications, the
+// chat list). The ETag is a hash of the response body, so handlers don't
+// need to know about it; a client that sends the ETag back in If-None-Match
+// gets an empty 304 when nothing changed.
+
+// Larger bodies are passed through without an ETag
+const MAX_HASHED_BODY: usize = 4 * 1024 * 1024;
+
+/// Middleware for a route: tag successful GET responses and answer 304 when
+/// the client already has the current version
+pub async fn conditional_get(request: Request, next: Next) -> Response {
+    if request.method() != Method::GET {
+        return next.run(request).await;
+    }
+
+    let if_none_match = request.headers().get(header::IF_NONE_MATCH).cloned();
+    let response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, MAX_HASHED_BODY).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("❌ Failed to buffer response for ETag: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let etag = format!("\"{}\"", hex::encode(&Sha256::digest(&bytes)[..16]));
+    let Ok(etag_value) = HeaderValue::from_str(&etag) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    // Per-user data: clients may keep it but must revalidate
+    parts.headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("private, no-cache"));
+    parts.headers.insert(header::ETAG, etag_value.clone());
+
+    if if_none_match.is_some_and(|value| matches(&value, &etag)) {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ETAG, etag_value);
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("private, no-cache"));
+        return (StatusCode::NOT_MODIFIED, headers).into_response();
+    }
+
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+/// If-None-Match holds a list of tags (weak ones compare equal too) or `*`
+fn matches(if_none_match: &HeaderValue, etag: &str) -> bool {
+    let Ok(value) = if_none_match.to_str() else {
+        return false;
+    };
+    value
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag)
+}
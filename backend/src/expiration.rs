@@ -1,149 +1,380 @@
+use chrono::{NaiveDate, NaiveDateTime, Utc};
 use sqlx::PgPool;
 use std::sync::Arc;
-use tokio::time::{interval, Duration};
+use uuid::Uuid;
 use crate::media::MediaService;
+use crate::websocket::{self, Connections, WsMessage};
+
+// Longest self-destruct timer a sender can pick for a message
+pub const MAX_MESSAGE_TTL_SECONDS: i64 = 7 * 24 * 60 * 60;
+
+// Chat members get a TimeToExpire push this long before a message is removed
+pub const EXPIRY_WARNING_SECONDS: i64 = 60;
+
+/// Turn a client supplied `expires_in_seconds` into an absolute expiry time.
+/// Used by every path that creates messages so REST and WebSocket agree.
+pub fn message_expires_at(expires_in_seconds: Option<i64>) -> Result<Option<NaiveDateTime>, String> {
+    match expires_in_seconds {
+        None => Ok(None),
+        Some(seconds) if seconds <= 0 || seconds > MAX_MESSAGE_TTL_SECONDS => Err(format!(
+            "expires_in_seconds must be between 1 and {}",
+            MAX_MESSAGE_TTL_SECONDS
+        )),
+        Some(seconds) => Ok(Some((Utc::now() + chrono::Duration::seconds(seconds)).naive_utc())),
+    }
+}
+
+/// Hours of a story's lifetime when the author doesn't pick one (a runtime
+/// setting). Permanent stories stay in feeds for this long as well.
+pub fn default_story_ttl_hours() -> i64 {
+    crate::runtime_settings::int(crate::runtime_settings::STORY_TTL_HOURS)
+}
+
+/// Expiry time for a story created now with the default lifetime
+pub fn story_expires_at() -> NaiveDateTime {
+    StoryTtl::Default.expires_at()
+}
+
+// Lifetimes an author can pick for a story, as sent in the `ttl` form field
+pub const STORY_TTL_OPTIONS: &[(&str, i64)] = &[("1h", 1), ("6h", 6), ("24h", 24), ("48h", 48)];
+pub const PERMANENT_STORY_TTL: &str = "permanent";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoryTtl {
+    Default,
+    Hours(i64),
+    /// Only for creator and business accounts
+    Permanent,
+}
+
+impl StoryTtl {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim().to_lowercase();
+        if value.is_empty() {
+            return Ok(StoryTtl::Default);
+        }
+        if value == PERMANENT_STORY_TTL {
+            return Ok(StoryTtl::Permanent);
+        }
+        STORY_TTL_OPTIONS
+            .iter()
+            .find(|(name, _)| *name == value)
+            .map(|(_, hours)| StoryTtl::Hours(*hours))
+            .ok_or_else(|| {
+                let names: Vec<&str> = STORY_TTL_OPTIONS.iter().map(|(name, _)| *name).collect();
+                format!("ttl must be one of: {}, {}", names.join(", "), PERMANENT_STORY_TTL)
+            })
+    }
+
+    pub fn is_permanent(self) -> bool {
+        self == StoryTtl::Permanent
+    }
+
+    /// Expiry time for a story created now. Permanent stories get a date far
+    /// enough out that expiry checks never catch them.
+    pub fn expires_at(self) -> NaiveDateTime {
+        let hours = match self {
+            StoryTtl::Default => default_story_ttl_hours(),
+            StoryTtl::Hours(hours) => hours,
+            StoryTtl::Permanent => {
+                return NaiveDate::from_ymd_opt(9999, 12, 31)
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+                    .unwrap_or(NaiveDateTime::MAX);
+            }
+        };
+        (Utc::now() + chrono::Duration::hours(hours)).naive_utc()
+    }
+}
+
+/// True once content is past its expiry, even if the sweeper hasn't removed it yet
+pub fn is_expired(expires_at: Option<NaiveDateTime>) -> bool {
+    expires_at.is_some_and(|at| at <= Utc::now().naive_utc())
+}
+
+#[derive(sqlx::FromRow)]
+struct ExpiringMessage {
+    id: Uuid,
+    chat_room_id: Uuid,
+    expires_at: NaiveDateTime,
+}
+
+#[derive(sqlx::FromRow)]
+struct ExpiredMessage {
+    id: Uuid,
+    chat_room_id: Uuid,
+    media_url: Option<String>,
+    media_thumbnail_url: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct HiddenMessage {
+    message_id: Uuid,
+    user_id: Uuid,
+}
+
+#[derive(sqlx::FromRow)]
+struct ExpiredMedia {
+    s3_key: String,
+    thumbnail_s3_key: Option<String>,
+}
+
+// Housekeeping that other modules hang off the sweep is logged and skipped
+// when it fails, so one broken table can't hold up the rest of the pass
+fn log_failure<T>(step: &str, result: Result<T, sqlx::Error>) {
+    if let Err(e) = result {
+        eprintln!("❌ Expiration sweep: {} failed: {:?}", step, e);
+    }
+}
 
 pub struct ExpirationService {
     pool: Arc<PgPool>,
     media_service: Arc<MediaService>,
+    connections: Connections,
 }
 
 impl ExpirationService {
-    pub fn new(pool: Arc<PgPool>, media_service: Arc<MediaService>) -> Self {
+    pub fn new(pool: Arc<PgPool>, media_service: Arc<MediaService>, connections: Connections) -> Self {
         Self {
             pool,
             media_service,
+            connections,
         }
     }
 
-    /// Start background task to clean up expired messages
-    pub async fn start(self: Arc<Self>) {
-        let mut ticker = interval(Duration::from_secs(60)); // Check every minute
+    /// Run one expiration pass (scheduled through the job queue)
+    pub async fn run(&self) -> Result<(), sqlx::Error> {
+        self.apply_room_retention().await?;
+        self.warn_expiring_messages().await?;
+        self.cleanup_expired_messages().await?;
+        self.cleanup_expired_media().await?;
+        log_failure("memories archive", crate::memories::archive_expired_stories(&self.pool, &self.media_service).await);
+        log_failure("story boost expiry", crate::story_boosts::end_expired(&self.pool).await);
+        log_failure("idempotency key purge", crate::idempotency::purge_expired(&self.pool).await);
+        log_failure("phone code purge", crate::phone::purge_expired(&self.pool).await);
+        log_failure("webhook delivery purge", crate::webhooks::purge_old_deliveries(&self.pool).await);
+        log_failure("bot update purge", crate::bots::purge_stale_updates(&self.pool).await);
+        log_failure("announcement expiry", crate::announcements::expire(&self.pool).await);
+        log_failure("supervision expiry", crate::supervision::expire(&self.pool).await);
+        log_failure("chat export purge", crate::chat_export::purge_expired(&self.pool, &self.media_service).await);
+        log_failure("federated story retraction", crate::federation::retract_stories(&self.pool).await);
+        Ok(())
+    }
+
+    /// Give messages in rooms with a retention policy (migration 067) an
+    /// expires_at once they're within the warning window of the room's limit,
+    /// leaving ones due to expire sooner alone. Stamping them this late keeps
+    /// a loosened or cleared policy from deleting anything already sent.
+    async fn apply_room_retention(&self) -> Result<(), sqlx::Error> {
+        let stamped = sqlx::query(
+            r#"
+            UPDATE messages m
+            SET expires_at = m.created_at + make_interval(secs => r.retention_seconds)
+            FROM chat_rooms r
+            WHERE r.id = m.chat_room_id
+              AND r.retention_seconds IS NOT NULL
+              AND m.created_at + make_interval(secs => r.retention_seconds) <= NOW() + make_interval(secs => $1)
+              AND (m.expires_at IS NULL OR m.expires_at > m.created_at + make_interval(secs => r.retention_seconds))
+              AND m.deleted_at IS NULL
+              AND m.retained_at IS NULL
+            "#
+        )
+        .bind(EXPIRY_WARNING_SECONDS as f64)
+        .execute(self.pool.as_ref())
+        .await?;
 
-        loop {
-            ticker.tick().await;
-            if let Err(e) = self.cleanup_expired_messages().await {
-                eprintln!("Error cleaning up expired messages: {}", e);
-            }
-            if let Err(e) = self.cleanup_expired_media().await {
-                eprintln!("Error cleaning up expired media: {}", e);
-            }
+        if stamped.rows_affected() > 0 {
+            println!("🗑️ Room retention reached {} messages", stamped.rows_affected());
         }
+
+        Ok(())
     }
 
-    /// Delete expired messages (Snapchat-style expiration)
-    async fn cleanup_expired_messages(&self) -> Result<(), sqlx::Error> {
-        // Find expired messages
-        let expired_messages = sqlx::query!(
+    /// Push a TimeToExpire event for messages about to disappear so clients
+    /// can show a countdown. Each message is only announced once.
+    async fn warn_expiring_messages(&self) -> Result<(), sqlx::Error> {
+        let expiring = sqlx::query_as::<_, ExpiringMessage>(
             r#"
-            SELECT id, media_url
-            FROM messages
+            UPDATE messages
+            SET expiry_warned_at = NOW()
             WHERE expires_at IS NOT NULL
-              AND expires_at < NOW()
+              AND expires_at > NOW()
+              AND expires_at <= NOW() + make_interval(secs => $1)
+              AND expiry_warned_at IS NULL
               AND deleted_at IS NULL
+            RETURNING id, chat_room_id, expires_at
             "#
         )
+        .bind(EXPIRY_WARNING_SECONDS as f64)
         .fetch_all(self.pool.as_ref())
         .await?;
 
-        println!("Found {} expired messages to delete", expired_messages.len());
-
-        for msg in expired_messages {
-            // Soft delete the message
-            sqlx::query!(
-                "UPDATE messages SET deleted_at = NOW() WHERE id = $1",
-                msg.id
-            )
-            .execute(self.pool.as_ref())
-            .await?;
-
-            // Delete associated media from S3 if exists
-            if let Some(media_url) = &msg.media_url {
-                if let Some(s3_key) = extract_s3_key(media_url) {
-                    let _ = self.media_service.delete_media(&s3_key).await;
-                }
-            }
-
-            println!("Deleted expired message: {}", msg.id);
+        let now = Utc::now().naive_utc();
+        for msg in expiring {
+            let event = WsMessage::TimeToExpire {
+                message_id: msg.id,
+                chat_room_id: msg.chat_room_id,
+                expires_at: crate::timestamps::format(msg.expires_at),
+                seconds_remaining: (msg.expires_at - now).num_seconds().max(0),
+            };
+            websocket::broadcast_to_room(&self.pool, &self.connections, msg.chat_room_id, &event).await;
         }
 
         Ok(())
     }
 
-    /// Delete expired media files from S3
-    async fn cleanup_expired_media(&self) -> Result<(), sqlx::Error> {
-        let expired_media = sqlx::query!(
+    /// Delete expired messages (Snapchat-style expiration).
+    /// One set-based UPDATE marks every expired row, then the media goes in S3 batches.
+    async fn cleanup_expired_messages(&self) -> Result<(), sqlx::Error> {
+        self.retain_saved_messages().await?;
+
+        // Expired messages nobody saved, and retained ones whose savers all unsaved them
+        let expired_messages = sqlx::query_as::<_, ExpiredMessage>(
             r#"
-            SELECT id, s3_key, thumbnail_s3_key
-            FROM media
-            WHERE expires_at IS NOT NULL
-              AND expires_at < NOW()
+            UPDATE messages
+            SET deleted_at = NOW()
+            WHERE ((expires_at IS NOT NULL AND expires_at < NOW()) OR retained_at IS NOT NULL)
+              AND deleted_at IS NULL
+              AND NOT EXISTS (SELECT 1 FROM saved_messages sm WHERE sm.message_id = messages.id)
+            RETURNING id, chat_room_id, media_url, media_thumbnail_url
             "#
         )
         .fetch_all(self.pool.as_ref())
         .await?;
 
-        println!("Found {} expired media files to delete", expired_media.len());
-
-        for media in expired_media {
-            // Delete from S3
-            let _ = self.media_service.delete_media(&media.s3_key).await;
+        if expired_messages.is_empty() {
+            return Ok(());
+        }
 
-            if let Some(thumb_key) = &media.thumbnail_s3_key {
-                let _ = self.media_service.delete_media(thumb_key).await;
-            }
+        println!("🗑️ Expired {} messages", expired_messages.len());
 
-            // Delete from database
-            sqlx::query!("DELETE FROM media WHERE id = $1", media.id)
-                .execute(self.pool.as_ref())
-                .await?;
+        // Delete associated media from S3
+        let s3_keys: Vec<String> = expired_messages
+            .iter()
+            .flat_map(|msg| [msg.media_url.as_deref(), msg.media_thumbnail_url.as_deref()])
+            .flatten()
+            .filter_map(|url| self.media_service.s3_key_from_url(url))
+            .collect();
+        self.delete_s3_keys(&s3_keys).await;
 
-            println!("Deleted expired media: {}", media.id);
+        // Let open chats drop the messages right away
+        for msg in &expired_messages {
+            let event = WsMessage::MessageExpired { message_id: msg.id };
+            websocket::broadcast_to_room(&self.pool, &self.connections, msg.chat_room_id, &event).await;
         }
 
         Ok(())
     }
 
-    /// Delete view-once messages that have been viewed
-    pub async fn cleanup_viewed_view_once_messages(&self) -> Result<(), sqlx::Error> {
-        let viewed_messages = sqlx::query!(
+    /// Expired messages someone saved are retained for the savers and hidden
+    /// from the other members (see retire_message in migration 043)
+    async fn retain_saved_messages(&self) -> Result<(), sqlx::Error> {
+        let hidden = sqlx::query_as::<_, HiddenMessage>(
             r#"
-            SELECT DISTINCT m.id, m.media_url
-            FROM messages m
-            JOIN message_views mv ON m.id = mv.message_id
-            WHERE m.view_once = TRUE
-              AND m.deleted_at IS NULL
+            WITH retained AS (
+                UPDATE messages m
+                SET retained_at = NOW()
+                WHERE m.expires_at IS NOT NULL
+                  AND m.expires_at < NOW()
+                  AND m.deleted_at IS NULL
+                  AND m.retained_at IS NULL
+                  AND EXISTS (SELECT 1 FROM saved_messages sm WHERE sm.message_id = m.id)
+                RETURNING m.id, m.chat_room_id
+            )
+            INSERT INTO message_recipients (message_id, user_id, hidden_at, hidden_reason)
+            SELECT r.id, cm.user_id, NOW(), 'retired'
+            FROM retained r
+            JOIN chat_members cm ON cm.chat_room_id = r.chat_room_id
+            WHERE NOT EXISTS (
+                SELECT 1 FROM saved_messages sm WHERE sm.message_id = r.id AND sm.user_id = cm.user_id
+            )
+            ON CONFLICT (message_id, user_id) DO UPDATE
+            SET hidden_at = COALESCE(message_recipients.hidden_at, EXCLUDED.hidden_at),
+                hidden_reason = COALESCE(message_recipients.hidden_reason, EXCLUDED.hidden_reason)
+            RETURNING message_id, user_id
             "#
         )
         .fetch_all(self.pool.as_ref())
         .await?;
 
-        for msg in viewed_messages {
-            // Soft delete
-            sqlx::query!(
-                "UPDATE messages SET deleted_at = NOW() WHERE id = $1",
-                msg.id
-            )
-            .execute(self.pool.as_ref())
-            .await?;
-
-            // Delete media from S3
-            if let Some(media_url) = &msg.media_url {
-                if let Some(s3_key) = extract_s3_key(media_url) {
-                    let _ = self.media_service.delete_media(&s3_key).await;
-                }
-            }
+        // Only the members it's hidden from see it go
+        for row in &hidden {
+            let event = WsMessage::MessageExpired { message_id: row.message_id };
+            websocket::send_to_user(&self.connections, row.user_id, &event);
+        }
+
+        Ok(())
+    }
 
-            println!("Deleted view-once message after viewing: {}", msg.id);
+    /// Delete expired media files from S3
+    async fn cleanup_expired_media(&self) -> Result<(), sqlx::Error> {
+        let expired_media = sqlx::query_as::<_, ExpiredMedia>(
+            r#"
+            DELETE FROM media
+            WHERE expires_at IS NOT NULL
+              AND expires_at < NOW()
+            RETURNING s3_key, thumbnail_s3_key
+            "#
+        )
+        .fetch_all(self.pool.as_ref())
+        .await?;
+
+        if expired_media.is_empty() {
+            return Ok(());
         }
 
+        println!("🗑️ Expired {} media files", expired_media.len());
+
+        let s3_keys: Vec<String> = expired_media
+            .into_iter()
+            .flat_map(|media| std::iter::once(media.s3_key).chain(media.thumbnail_s3_key))
+            .collect();
+        self.delete_s3_keys(&s3_keys).await;
+
         Ok(())
     }
+
+    async fn delete_s3_keys(&self, s3_keys: &[String]) {
+        if s3_keys.is_empty() {
+            return;
+        }
+
+        // The expired rows are already gone, so the deletion goes through the
+        // outbox to be retried if storage is unreachable
+        let queued = match self.pool.acquire().await {
+            Ok(mut conn) => crate::media_outbox::release_and_queue(&mut conn, s3_keys, "expired").await,
+            Err(e) => Err(e.to_string()),
+        };
+        match queued {
+            Ok(entry) => crate::media_outbox::flush(&self.pool, &self.media_service, entry).await,
+            Err(e) => eprintln!("Error deleting expired objects: {}", e),
+        }
+    }
 }
 
-/// Extract S3 key from full URL
-fn extract_s3_key(url: &str) -> Option<String> {
-    url.split(".s3.amazonaws.com/")
-        .nth(1)
-        .map(|s| s.to_string())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_story_ttls() {
+        assert_eq!(StoryTtl::parse(""), Ok(StoryTtl::Default));
+        assert_eq!(StoryTtl::parse("  "), Ok(StoryTtl::Default));
+        assert_eq!(StoryTtl::parse("1h"), Ok(StoryTtl::Hours(1)));
+        assert_eq!(StoryTtl::parse(" 48H "), Ok(StoryTtl::Hours(48)));
+        assert_eq!(StoryTtl::parse("Permanent"), Ok(StoryTtl::Permanent));
+    }
+
+    #[test]
+    fn rejects_unknown_story_ttls() {
+        for value in ["12h", "2d", "24", "forever"] {
+            let error = StoryTtl::parse(value).unwrap_err();
+            assert_eq!(error, "ttl must be one of: 1h, 6h, 24h, 48h, permanent");
+        }
+    }
+
+    #[test]
+    fn permanent_stories_never_expire() {
+        assert!(!is_expired(Some(StoryTtl::Permanent.expires_at())));
+        assert!(StoryTtl::Hours(1).expires_at() < StoryTtl::Hours(48).expires_at());
+    }
 }
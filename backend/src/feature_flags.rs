@@ -0,0 +1,510 @@
+use axum::{
+    extract::{FromRequestParts, Path, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::admin::{AdminUser, AuthUser};
+use crate::permissions::{CanManagePlatform, Permitted};
+use crate::AppState;
+
+// Global switch that turns away non-admin traffic with a 503
+pub const MAINTENANCE_MODE: &str = "maintenance_mode";
+
+// Flags are read on hot paths, so keep them in Redis for a short while.
+// Admin writes invalidate the cache straight away.
+const CACHE_TTL_SECONDS: u64 = 30;
+const ALL_FLAGS_CACHE_KEY: &str = "feature_flags:all";
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct FeatureFlag {
+    pub id: Uuid,
+    pub key: String,
+    pub description: Option<String>,
+    pub enabled: bool,
+    pub rollout_percentage: i32,
+    /// Users who always get the flag while it is enabled, regardless of rollout
+    pub user_ids: Vec<Uuid>,
+    pub metadata: serde_json::Value,
+    pub updated_by: Option<Uuid>,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps")]
+    pub updated_at: NaiveDateTime,
+}
+
+impl FeatureFlag {
+    /// Whether the flag is on for `user_id`. Anonymous callers only see
+    /// flags that are rolled out to everyone.
+    pub fn is_enabled_for(&self, user_id: Option<Uuid>) -> bool {
+        if !self.enabled {
+            return false;
+        }
+
+        match user_id {
+            Some(id) if self.user_ids.contains(&id) => true,
+            Some(id) => rollout_bucket(&self.key, id) < self.rollout_percentage,
+            None => self.rollout_percentage >= 100,
+        }
+    }
+}
+
+// Stable 0-99 bucket per (flag, user), so a user stays on the same side of a
+// rollout across requests and servers while each flag gets its own split
+fn rollout_bucket(key: &str, user_id: Uuid) -> i32 {
+    // FNV-1a
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in key.as_bytes().iter().chain(user_id.as_bytes()) {
+        hash ^= *byte as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    (hash % 100) as i32
+}
+
+fn cache_key(key: &str) -> String {
+    format!("feature_flag:{}", key)
+}
+
+/// Load one flag, from Redis when possible. Missing flags are cached as well
+/// so unknown keys don't hit the database on every call.
+pub async fn get_flag(state: &AppState, key: &str) -> Option<FeatureFlag> {
+    {
+        let mut redis = state.redis.lock().await;
+        if let Ok(Some(cached)) = redis.cache_get(&cache_key(key)).await {
+            if let Ok(flag) = serde_json::from_str::<Option<FeatureFlag>>(&cached) {
+                return flag;
+            }
+        }
+    }
+
+    let flag = match sqlx::query_as::<_, FeatureFlag>("SELECT * FROM feature_flags WHERE key = $1")
+        .bind(key)
+        .fetch_optional(state.pool.as_ref())
+        .await
+    {
+        Ok(flag) => flag,
+        Err(e) => {
+            tracing::error!("Failed to load feature flag {}: {}", key, e);
+            return None;
+        }
+    };
+
+    if let Ok(json) = serde_json::to_string(&flag) {
+        let mut redis = state.redis.lock().await;
+        let _ = redis.cache_set(&cache_key(key), &json, CACHE_TTL_SECONDS).await;
+    }
+
+    flag
+}
+
+async fn all_flags(state: &AppState) -> Result<Vec<FeatureFlag>, sqlx::Error> {
+    {
+        let mut redis = state.redis.lock().await;
+        if let Ok(Some(cached)) = redis.cache_get(ALL_FLAGS_CACHE_KEY).await {
+            if let Ok(flags) = serde_json::from_str::<Vec<FeatureFlag>>(&cached) {
+                return Ok(flags);
+            }
+        }
+    }
+
+    let flags = sqlx::query_as::<_, FeatureFlag>("SELECT * FROM feature_flags ORDER BY key")
+        .fetch_all(state.pool.as_ref())
+        .await?;
+
+    if let Ok(json) = serde_json::to_string(&flags) {
+        let mut redis = state.redis.lock().await;
+        let _ = redis.cache_set(ALL_FLAGS_CACHE_KEY, &json, CACHE_TTL_SECONDS).await;
+    }
+
+    Ok(flags)
+}
+
+async fn invalidate(state: &AppState, key: &str) {
+    let mut redis = state.redis.lock().await;
+    let _ = redis.cache_delete(&cache_key(key)).await;
+    let _ = redis.cache_delete(ALL_FLAGS_CACHE_KEY).await;
+}
+
+// ============================================================================
+// MAINTENANCE MODE
+// ============================================================================
+
+/// Middleware: while maintenance mode is on, API and WebSocket requests get a
+/// 503 unless they come from an admin (AdminUser: any role that grants a
+/// permission). Login stays open so admins can sign in and switch
+/// maintenance off again.
+pub async fn maintenance_guard(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !is_guarded_path(request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let Some(flag) = get_flag(&state, MAINTENANCE_MODE).await.filter(|flag| flag.enabled) else {
+        return next.run(request).await;
+    };
+
+    let (mut parts, body) = request.into_parts();
+    if AdminUser::from_request_parts(&mut parts, &state).await.is_ok() {
+        return next.run(Request::from_parts(parts, body)).await;
+    }
+
+    maintenance_response(&flag)
+}
+
+fn is_guarded_path(path: &str) -> bool {
+    match path.strip_prefix("/api/v1").or_else(|| path.strip_prefix("/api")) {
+        Some(rest) => !matches!(rest, "/login" | "/openapi.json" | "/docs" | "/ws-schema"),
+        None => path.starts_with("/ws/"),
+    }
+}
+
+fn maintenance_response(flag: &FeatureFlag) -> Response {
+    let message = flag
+        .metadata
+        .get("message")
+        .and_then(|m| m.as_str())
+        .unwrap_or("We are doing some maintenance and will be back shortly.");
+    let retry_after = flag.metadata.get("retry_after_seconds").and_then(|r| r.as_u64());
+
+    let mut response = (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(serde_json::json!({
+            "error": "maintenance",
+            "message": message,
+            "retry_after_seconds": retry_after,
+        })),
+    )
+        .into_response();
+
+    if let Some(seconds) = retry_after {
+        if let Ok(value) = HeaderValue::from_str(&seconds.to_string()) {
+            response.headers_mut().insert(header::RETRY_AFTER, value);
+        }
+    }
+
+    response
+}
+
+// ============================================================================
+// HANDLERS
+// ============================================================================
+
+#[derive(Serialize, ToSchema)]
+pub struct FeatureFlagsResponse {
+    /// Every known flag, evaluated for the caller
+    pub flags: HashMap<String, bool>,
+}
+
+// Flags as seen by the calling user (or an anonymous client)
+#[utoipa::path(
+    get,
+    path = "/api/v1/feature-flags",
+    tag = "feature-flags",
+    responses((status = 200, body = FeatureFlagsResponse)),
+    security((), ("bearer_auth" = []))
+)]
+pub async fn get_feature_flags(
+    user: Option<AuthUser>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<FeatureFlagsResponse>, StatusCode> {
+    let user_id = user.map(|u| u.id);
+
+    let flags = all_flags(&state).await.map_err(|e| {
+        eprintln!("Feature flag lookup error: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(FeatureFlagsResponse {
+        flags: flags
+            .iter()
+            .map(|flag| (flag.key.clone(), flag.is_enabled_for(user_id)))
+            .collect(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/feature-flags",
+    tag = "feature-flags",
+    responses((status = 200, body = [FeatureFlag]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_flags(
+    _admin: Permitted<CanManagePlatform>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<FeatureFlag>>, (StatusCode, String)> {
+    let flags = sqlx::query_as::<_, FeatureFlag>("SELECT * FROM feature_flags ORDER BY key")
+        .fetch_all(state.pool.as_ref())
+        .await
+        .map_err(|e| {
+            eprintln!("List feature flags error: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load feature flags".to_string())
+        })?;
+
+    Ok(Json(flags))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateFeatureFlagRequest {
+    key: String,
+    description: Option<String>,
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default = "default_rollout")]
+    rollout_percentage: i32,
+    #[serde(default)]
+    user_ids: Vec<Uuid>,
+    metadata: Option<serde_json::Value>,
+}
+
+fn default_rollout() -> i32 {
+    100
+}
+
+fn validate_flag(key: &str, rollout_percentage: i32) -> Result<(), (StatusCode, String)> {
+    if key.is_empty()
+        || key.len() > 100
+        || !key.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Flag keys must be 1-100 characters of a-z, 0-9 and _".to_string(),
+        ));
+    }
+    if !(0..=100).contains(&rollout_percentage) {
+        return Err((StatusCode::BAD_REQUEST, "rollout_percentage must be between 0 and 100".to_string()));
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/feature-flags",
+    tag = "feature-flags",
+    request_body = CreateFeatureFlagRequest,
+    responses(
+        (status = 200, body = FeatureFlag),
+        (status = 409, description = "A flag with this key already exists"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn create_flag(
+    admin: Permitted<CanManagePlatform>,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<CreateFeatureFlagRequest>,
+) -> Result<Json<FeatureFlag>, (StatusCode, String)> {
+    validate_flag(&req.key, req.rollout_percentage)?;
+
+    let flag = sqlx::query_as::<_, FeatureFlag>(
+        r#"
+        INSERT INTO feature_flags (key, description, enabled, rollout_percentage, user_ids, metadata, updated_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (key) DO NOTHING
+        RETURNING *
+        "#
+    )
+    .bind(&req.key)
+    .bind(&req.description)
+    .bind(req.enabled)
+    .bind(req.rollout_percentage)
+    .bind(&req.user_ids)
+    .bind(req.metadata.unwrap_or_else(|| serde_json::json!({})))
+    .bind(admin.0.id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(|e| {
+        eprintln!("Create feature flag error: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create feature flag".to_string())
+    })?
+    .ok_or((StatusCode::CONFLICT, format!("Feature flag {} already exists", req.key)))?;
+
+    invalidate(&state, &flag.key).await;
+    log_flag_change(&state, &admin, "create_feature_flag", &flag).await;
+
+    Ok(Json(flag))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateFeatureFlagRequest {
+    description: Option<String>,
+    enabled: Option<bool>,
+    rollout_percentage: Option<i32>,
+    user_ids: Option<Vec<Uuid>>,
+    metadata: Option<serde_json::Value>,
+}
+
+// Partial update: omitted fields keep their current value
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/feature-flags/{key}",
+    tag = "feature-flags",
+    params(("key" = String, Path, description = "Flag key")),
+    request_body = UpdateFeatureFlagRequest,
+    responses((status = 200, body = FeatureFlag), (status = 404, description = "Flag not found"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_flag(
+    admin: Permitted<CanManagePlatform>,
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<String>,
+    Json(req): Json<UpdateFeatureFlagRequest>,
+) -> Result<Json<FeatureFlag>, (StatusCode, String)> {
+    if let Some(rollout) = req.rollout_percentage {
+        validate_flag(&key, rollout)?;
+    }
+
+    let flag = sqlx::query_as::<_, FeatureFlag>(
+        r#"
+        UPDATE feature_flags
+        SET description = COALESCE($2, description),
+            enabled = COALESCE($3, enabled),
+            rollout_percentage = COALESCE($4, rollout_percentage),
+            user_ids = COALESCE($5, user_ids),
+            metadata = COALESCE($6, metadata),
+            updated_by = $7,
+            updated_at = NOW()
+        WHERE key = $1
+        RETURNING *
+        "#
+    )
+    .bind(&key)
+    .bind(&req.description)
+    .bind(req.enabled)
+    .bind(req.rollout_percentage)
+    .bind(&req.user_ids)
+    .bind(&req.metadata)
+    .bind(admin.0.id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(|e| {
+        eprintln!("Update feature flag error: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update feature flag".to_string())
+    })?
+    .ok_or((StatusCode::NOT_FOUND, "Feature flag not found".to_string()))?;
+
+    invalidate(&state, &key).await;
+    log_flag_change(&state, &admin, "update_feature_flag", &flag).await;
+
+    Ok(Json(flag))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/feature-flags/{key}",
+    tag = "feature-flags",
+    params(("key" = String, Path, description = "Flag key")),
+    responses((status = 200, body = serde_json::Value), (status = 404, description = "Flag not found"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn delete_flag(
+    admin: Permitted<CanManagePlatform>,
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let flag = sqlx::query_as::<_, FeatureFlag>("DELETE FROM feature_flags WHERE key = $1 RETURNING *")
+        .bind(&key)
+        .fetch_optional(state.pool.as_ref())
+        .await
+        .map_err(|e| {
+            eprintln!("Delete feature flag error: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete feature flag".to_string())
+        })?
+        .ok_or((StatusCode::NOT_FOUND, "Feature flag not found".to_string()))?;
+
+    invalidate(&state, &key).await;
+    log_flag_change(&state, &admin, "delete_feature_flag", &flag).await;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "message": "Feature flag deleted"
+    })))
+}
+
+async fn log_flag_change(state: &Arc<AppState>, admin: &Permitted<CanManagePlatform>, action: &str, flag: &FeatureFlag) {
+    crate::admin::log_admin_action(
+        state,
+        admin.0.id,
+        action.to_string(),
+        None,
+        Some("feature_flag".to_string()),
+        Some(flag.id),
+        serde_json::json!({
+            "key": flag.key,
+            "enabled": flag.enabled,
+            "rollout_percentage": flag.rollout_percentage,
+            "user_count": flag.user_ids.len(),
+        }),
+    ).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flag(enabled: bool, rollout_percentage: i32, user_ids: Vec<Uuid>) -> FeatureFlag {
+        let now = chrono::Utc::now().naive_utc();
+        FeatureFlag {
+            id: Uuid::nil(),
+            key: "new_camera".to_string(),
+            description: None,
+            enabled,
+            rollout_percentage,
+            user_ids,
+            metadata: serde_json::json!({}),
+            updated_by: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn rollout_bucket_is_stable_and_in_range() {
+        for n in 0..1000u128 {
+            let user_id = Uuid::from_u128(n);
+            let bucket = rollout_bucket("new_camera", user_id);
+            assert!((0..100).contains(&bucket));
+            assert_eq!(bucket, rollout_bucket("new_camera", user_id));
+        }
+    }
+
+    #[test]
+    fn rollout_bucket_splits_users_evenly() {
+        let users: Vec<Uuid> = (0..10_000u128).map(|n| Uuid::from_u128(n * 7919)).collect();
+        let in_half = users.iter().filter(|id| rollout_bucket("new_camera", **id) < 50).count();
+        assert!((4_500..=5_500).contains(&in_half), "{} of 10000 users in a 50% rollout", in_half);
+    }
+
+    #[test]
+    fn rollout_bucket_differs_per_flag() {
+        let differing = (0..1000u128)
+            .map(Uuid::from_u128)
+            .filter(|id| rollout_bucket("new_camera", *id) != rollout_bucket("dark_mode", *id))
+            .count();
+        assert!(differing > 900);
+    }
+
+    #[test]
+    fn listed_users_bypass_the_rollout() {
+        let user_id = Uuid::from_u128(42);
+        assert!(flag(true, 0, vec![user_id]).is_enabled_for(Some(user_id)));
+        assert!(!flag(false, 100, vec![user_id]).is_enabled_for(Some(user_id)));
+    }
+
+    #[test]
+    fn anonymous_callers_need_a_full_rollout() {
+        assert!(!flag(true, 99, Vec::new()).is_enabled_for(None));
+        assert!(flag(true, 100, Vec::new()).is_enabled_for(None));
+    }
+}
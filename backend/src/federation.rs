@@ -0,0 +1,774 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use openssl::{hash::MessageDigest, pkey::PKey, rsa::Rsa, sign::{Signer, Verifier}};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::media::MediaService;
+use crate::AppState;
+
+// ActivityPub federation, opt-in per account and only when FEDERATION_DOMAIN
+// names the public host. A federated account is a Person actor at
+// /ap/users/{id}, found through WebFinger as @username@domain. Remote
+// servers follow it through its inbox; its public stories go out to them as
+// Create activities and are withdrawn with Delete once they expire, are
+// removed or stop qualifying (opt-out, 18+, takedown).
+//
+// Requests to the inbox must carry an HTTP signature from the sending actor;
+// our deliveries are signed the same way with the account's RSA key. The
+// actor document stays up after opting out so remote servers can still
+// verify the withdrawals.
+
+const AS_CONTEXT: &str = "https://www.w3.org/ns/activitystreams";
+const SECURITY_CONTEXT: &str = "https://w3id.org/security/v1";
+const PUBLIC_AUDIENCE: &str = "https://www.w3.org/ns/activitystreams#Public";
+const ACTIVITY_JSON: &str = "application/activity+json";
+
+const RSA_BITS: u32 = 2048;
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+// Signed requests dated further than this from now are refused
+const MAX_SIGNATURE_SKEW_SECS: i64 = 12 * 60 * 60;
+const OUTBOX_LIMIT: i64 = 20;
+
+
+/// Public host actors live on; federation is off without it
+pub fn domain() -> Option<&'static str> {
+    static DOMAIN: OnceLock<Option<String>> = OnceLock::new();
+    DOMAIN
+        .get_or_init(|| std::env::var("FEDERATION_DOMAIN").ok().filter(|d| !d.is_empty()))
+        .as_deref()
+}
+
+fn actor_url(domain: &str, user_id: Uuid) -> String {
+    format!("https://{}/ap/users/{}", domain, user_id)
+}
+
+fn story_url(domain: &str, story_id: Uuid) -> String {
+    format!("https://{}/ap/stories/{}", domain, story_id)
+}
+
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(FETCH_TIMEOUT)
+            // A redirect could point the request at an internal address
+            .redirect(reqwest::redirect::Policy::none())
+            .dns_resolver(Arc::new(crate::webhooks::PublicResolver))
+            .build()
+            .expect("Failed to build federation HTTP client")
+    })
+}
+
+fn activity_response(content_type: &'static str, body: serde_json::Value) -> Response {
+    ([(header::CONTENT_TYPE, content_type)], body.to_string()).into_response()
+}
+
+fn db_error(e: sqlx::Error) -> StatusCode {
+    eprintln!("❌ Federation query failed: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+// ============================================================================
+// KEYS
+// ============================================================================
+
+/// Give the account a signing key unless it already has one
+pub async fn ensure_key(pool: &PgPool, user_id: Uuid) -> Result<(), String> {
+    let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM federation_keys WHERE user_id = $1)")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    if exists {
+        return Ok(());
+    }
+
+    let (public_pem, private_pem) = tokio::task::spawn_blocking(|| -> Result<(String, String), openssl::error::ErrorStack> {
+        let rsa = Rsa::generate(RSA_BITS)?;
+        let public_pem = String::from_utf8_lossy(&rsa.public_key_to_pem()?).into_owned();
+        let private_pem = String::from_utf8_lossy(&rsa.private_key_to_pem()?).into_owned();
+        Ok((public_pem, private_pem))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("Failed to generate signing key: {}", e))?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO federation_keys (user_id, public_key_pem, private_key_pem)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id) DO NOTHING
+        "#
+    )
+    .bind(user_id)
+    .bind(public_pem)
+    .bind(private_pem)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+fn sign(private_key_pem: &str, data: &str) -> Result<String, openssl::error::ErrorStack> {
+    let key = PKey::private_key_from_pem(private_key_pem.as_bytes())?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+    signer.update(data.as_bytes())?;
+    Ok(BASE64.encode(signer.sign_to_vec()?))
+}
+
+fn digest(body: &[u8]) -> String {
+    format!("SHA-256={}", BASE64.encode(Sha256::digest(body)))
+}
+
+// ============================================================================
+// OBJECTS
+// ============================================================================
+
+#[derive(sqlx::FromRow)]
+struct LocalActor {
+    id: Uuid,
+    username: String,
+    display_name: Option<String>,
+    bio: Option<String>,
+    avatar_url: Option<String>,
+    federated: bool,
+    public_key_pem: String,
+}
+
+async fn find_actor(pool: &PgPool, user_id: Uuid) -> Result<LocalActor, StatusCode> {
+    sqlx::query_as::<_, LocalActor>(
+        r#"
+        SELECT u.id, u.username, u.display_name, u.bio, u.avatar_url, u.federated, k.public_key_pem
+        FROM users u
+        JOIN federation_keys k ON k.user_id = u.id
+        WHERE u.id = $1
+        "#
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?
+    .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[derive(sqlx::FromRow)]
+struct PublicStory {
+    id: Uuid,
+    user_id: Uuid,
+    media_url: String,
+    media_type: String,
+    caption: Option<String>,
+    alt_text: Option<String>,
+    created_at: NaiveDateTime,
+    expires_at: NaiveDateTime,
+}
+
+async fn public_stories(pool: &PgPool, user_id: Option<Uuid>, story_id: Option<Uuid>) -> Result<Vec<PublicStory>, sqlx::Error> {
+    sqlx::query_as::<_, PublicStory>(&format!(
+        r#"
+        SELECT s.id, s.user_id, s.media_url, s.media_type, s.caption, s.alt_text, s.created_at, s.expires_at
+        FROM stories s
+        JOIN users u ON u.id = s.user_id
+        WHERE ($1::uuid IS NULL OR s.user_id = $1)
+          AND ($2::uuid IS NULL OR s.id = $2)
+          AND s.expires_at > NOW()
+          AND u.federated AND {}
+        ORDER BY s.created_at DESC
+        LIMIT $3
+        "#,
+        crate::stories::PUBLIC_STORY
+    ))
+    .bind(user_id)
+    .bind(story_id)
+    .bind(OUTBOX_LIMIT)
+    .fetch_all(pool)
+    .await
+}
+
+async fn note(media: &MediaService, domain: &str, story: &PublicStory) -> serde_json::Value {
+    let actor = actor_url(domain, story.user_id);
+    let attachment_type = if story.media_type == "video" { "Video" } else { "Image" };
+
+    serde_json::json!({
+        "id": story_url(domain, story.id),
+        "type": "Note",
+        "attributedTo": actor,
+        "content": story.caption.as_deref().map(crate::chat_export::escape_html).unwrap_or_default(),
+        "published": story.created_at.and_utc().to_rfc3339(),
+        "to": [PUBLIC_AUDIENCE],
+        "cc": [format!("{}/followers", actor)],
+        "attachment": [{
+            "type": attachment_type,
+            // Remote servers fetch the media when the activity arrives
+            "url": media.sign_url_until(&story.media_url, story.expires_at).await,
+            "name": story.alt_text,
+        }],
+    })
+}
+
+fn create_activity(domain: &str, story: &PublicStory, note: serde_json::Value) -> serde_json::Value {
+    let actor = actor_url(domain, story.user_id);
+    serde_json::json!({
+        "@context": AS_CONTEXT,
+        "id": format!("{}#create", story_url(domain, story.id)),
+        "type": "Create",
+        "actor": actor,
+        "published": story.created_at.and_utc().to_rfc3339(),
+        "to": [PUBLIC_AUDIENCE],
+        "cc": [format!("{}/followers", actor)],
+        "object": note,
+    })
+}
+
+fn delete_activity(domain: &str, user_id: Uuid, story_id: Uuid) -> serde_json::Value {
+    let id = story_url(domain, story_id);
+    serde_json::json!({
+        "@context": AS_CONTEXT,
+        "id": format!("{}#delete", id),
+        "type": "Delete",
+        "actor": actor_url(domain, user_id),
+        "to": [PUBLIC_AUDIENCE],
+        "object": { "id": id, "type": "Tombstone" },
+    })
+}
+
+// ============================================================================
+// PUBLISHING
+// ============================================================================
+
+/// Queue a signed delivery of `activity` to every remote follower of the
+/// account, once per server where they share an inbox
+async fn send_to_followers(pool: &PgPool, user_id: Uuid, activity: serde_json::Value) {
+    let inboxes = sqlx::query_scalar::<_, String>(
+        "SELECT DISTINCT COALESCE(shared_inbox_url, inbox_url) FROM federation_followers WHERE user_id = $1"
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await;
+
+    match inboxes {
+        Ok(inboxes) => {
+            for inbox in inboxes {
+                send(pool, user_id, &inbox, &activity).await;
+            }
+        }
+        Err(e) => tracing::error!("Failed to load remote followers of {}: {}", user_id, e),
+    }
+}
+
+async fn send(pool: &PgPool, user_id: Uuid, inbox: &str, activity: &serde_json::Value) {
+    let queued = crate::jobs::enqueue(
+        pool,
+        crate::jobs::DELIVER_ACTIVITY,
+        serde_json::json!({ "user_id": user_id, "inbox": inbox, "activity": activity }),
+        None,
+    )
+    .await;
+
+    if let Err(e) = queued {
+        tracing::error!("Failed to enqueue activity delivery to {}: {}", inbox, e);
+    }
+}
+
+/// Send a new story to its author's remote followers if it federates.
+/// Failures are logged and never fail the caller's request.
+pub async fn publish_story(state: &AppState, story_id: Uuid) {
+    let Some(domain) = domain() else {
+        return;
+    };
+
+    let story = match public_stories(&state.pool, None, Some(story_id)).await {
+        Ok(mut stories) => match stories.pop() {
+            Some(story) => story,
+            None => return,
+        },
+        Err(e) => {
+            tracing::error!("Failed to load story {} for federation: {}", story_id, e);
+            return;
+        }
+    };
+
+    let recorded = sqlx::query(
+        r#"
+        INSERT INTO federated_stories (story_id, user_id, expires_at)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (story_id) DO NOTHING
+        "#
+    )
+    .bind(story.id)
+    .bind(story.user_id)
+    .bind(story.expires_at)
+    .execute(state.pool.as_ref())
+    .await;
+    if let Err(e) = recorded {
+        tracing::error!("Failed to record federated story {}: {}", story_id, e);
+        return;
+    }
+
+    let note = note(&state.media_service, domain, &story).await;
+    send_to_followers(&state.pool, story.user_id, create_activity(domain, &story, note)).await;
+}
+
+/// Withdraw published stories that expired, were deleted or no longer
+/// federate (run by the expiration sweep)
+pub async fn retract_stories(pool: &PgPool) -> Result<(), sqlx::Error> {
+    let Some(domain) = domain() else {
+        return Ok(());
+    };
+
+    let retracted = sqlx::query_as::<_, (Uuid, Uuid)>(&format!(
+        r#"
+        UPDATE federated_stories f
+        SET retracted_at = NOW()
+        WHERE f.retracted_at IS NULL
+          AND (f.expires_at <= NOW()
+               OR NOT EXISTS (SELECT 1 FROM stories s JOIN users u ON u.id = s.user_id
+                              WHERE s.id = f.story_id AND u.federated AND {}))
+        RETURNING f.story_id, f.user_id
+        "#,
+        crate::stories::PUBLIC_STORY
+    ))
+    .fetch_all(pool)
+    .await?;
+
+    for (story_id, user_id) in retracted {
+        send_to_followers(pool, user_id, delete_activity(domain, user_id, story_id)).await;
+    }
+
+    Ok(())
+}
+
+/// POST one signed activity to a remote inbox (run by the job worker).
+/// Errors make the job retry with backoff.
+pub async fn deliver(pool: &PgPool, payload: &serde_json::Value) -> Result<Option<serde_json::Value>, String> {
+    let domain = domain().ok_or("Federation is turned off")?;
+    let user_id = payload
+        .get("user_id")
+        .and_then(|id| id.as_str())
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .ok_or("Missing user_id")?;
+    let inbox = payload.get("inbox").and_then(|i| i.as_str()).ok_or("Missing inbox")?;
+    let activity = payload.get("activity").ok_or("Missing activity")?;
+
+    let private_key_pem = sqlx::query_scalar::<_, String>("SELECT private_key_pem FROM federation_keys WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    // Account deleted since the activity was queued
+    let Some(private_key_pem) = private_key_pem else {
+        return Ok(Some(serde_json::json!({ "skipped": true })));
+    };
+
+    crate::webhooks::validate_url(inbox).await.map_err(|(_, e)| e)?;
+    let url = reqwest::Url::parse(inbox).map_err(|e| e.to_string())?;
+    let host = match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => return Err("Inbox URL has no host".to_string()),
+    };
+    let target = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+
+    let body = activity.to_string();
+    let date = Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+    let digest = digest(body.as_bytes());
+    let signing_string = format!("(request-target): post {}\nhost: {}\ndate: {}\ndigest: {}", target, host, date, digest);
+    let signature = sign(&private_key_pem, &signing_string).map_err(|e| format!("Failed to sign activity: {}", e))?;
+
+    let response = http_client()
+        .post(url)
+        .header("content-type", ACTIVITY_JSON)
+        .header("date", date)
+        .header("digest", digest)
+        .header(
+            "signature",
+            format!(
+                r#"keyId="{}#main-key",algorithm="rsa-sha256",headers="(request-target) host date digest",signature="{}""#,
+                actor_url(domain, user_id),
+                signature
+            ),
+        )
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| format!("Request failed: {}", e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("Inbox responded with {}", status));
+    }
+    Ok(Some(serde_json::json!({ "response_status": status.as_u16() })))
+}
+
+// ============================================================================
+// SIGNATURE VERIFICATION
+// ============================================================================
+
+struct RemoteActor {
+    id: String,
+    inbox: String,
+    shared_inbox: Option<String>,
+}
+
+async fn fetch_object(url: &str) -> Result<serde_json::Value, String> {
+    crate::webhooks::validate_url(url).await.map_err(|(_, e)| e)?;
+    let response = http_client()
+        .get(url)
+        .header("accept", ACTIVITY_JSON)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+    if !response.status().is_success() {
+        return Err(format!("Fetching {} returned {}", url, response.status()));
+    }
+    response.json().await.map_err(|e| format!("Invalid object at {}: {}", url, e))
+}
+
+// Scheme, host and port of a URL, for same-origin checks
+fn origin(url: &str) -> Option<(String, String, Option<u16>)> {
+    let url = reqwest::Url::parse(url).ok()?;
+    Some((url.scheme().to_string(), url.host_str()?.to_lowercase(), url.port_or_known_default()))
+}
+
+/// Check the HTTP signature on an inbox request and return the actor who
+/// signed it. The key is fetched from the actor's server every time, and the
+/// key and actor must both live on the keyId's origin, so a server can only
+/// sign for its own actors.
+async fn verify_request(headers: &HeaderMap, target: &str, body: &[u8]) -> Result<RemoteActor, String> {
+    let header_str = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+    let params: HashMap<&str, &str> = header_str("signature")
+        .ok_or("Missing Signature header")?
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim().trim_matches('"')))
+        .collect();
+    let key_id = params.get("keyId").ok_or("Signature has no keyId")?;
+    let signature = BASE64
+        .decode(params.get("signature").ok_or("Signature has no signature")?)
+        .map_err(|_| "Signature isn't base64")?;
+    let signed_headers: Vec<&str> = params.get("headers").copied().unwrap_or("date").split_whitespace().collect();
+    for required in ["(request-target)", "host", "date", "digest"] {
+        if !signed_headers.contains(&required) {
+            return Err(format!("Signature doesn't cover {}", required));
+        }
+    }
+
+    let date = DateTime::parse_from_rfc2822(header_str("date").ok_or("Missing Date header")?)
+        .map_err(|_| "Invalid Date header")?;
+    if (Utc::now() - date.with_timezone(&Utc)).num_seconds().abs() > MAX_SIGNATURE_SKEW_SECS {
+        return Err("Signature date is too far from now".to_string());
+    }
+
+    let expected_digest = BASE64.encode(Sha256::digest(body));
+    let digest_matches = header_str("digest")
+        .ok_or("Missing Digest header")?
+        .split(',')
+        .filter_map(|d| d.trim().split_once('='))
+        .any(|(algorithm, value)| algorithm.eq_ignore_ascii_case("SHA-256") && value == expected_digest);
+    if !digest_matches {
+        return Err("Digest doesn't match the body".to_string());
+    }
+
+    let signing_string = signed_headers
+        .iter()
+        .map(|name| match *name {
+            "(request-target)" => Ok(format!("(request-target): post {}", target)),
+            name => header_str(name)
+                .map(|value| format!("{}: {}", name, value))
+                .ok_or(format!("Signed header {} is missing", name)),
+        })
+        .collect::<Result<Vec<_>, String>>()?
+        .join("\n");
+
+    // The key id is the actor with a fragment, or a key document naming its owner
+    let key_url = key_id.split('#').next().unwrap_or(key_id);
+    let key_origin = origin(key_url).ok_or("keyId isn't a URL")?;
+    let key_doc = fetch_object(key_url).await?;
+    let key_doc_id = key_doc.get("id").and_then(|id| id.as_str()).ok_or("Key document has no id")?;
+    if key_doc_id.split('#').next() != Some(key_url) {
+        return Err("Key document's id doesn't match its URL".to_string());
+    }
+    let (actor, key) = match key_doc.get("publicKey") {
+        Some(key) => (key_doc.clone(), key.clone()),
+        None => {
+            let owner = key_doc.get("owner").and_then(|o| o.as_str()).ok_or("Key has no owner")?;
+            if origin(owner).as_ref() != Some(&key_origin) {
+                return Err("Key owner is on another origin than the key".to_string());
+            }
+            let actor = fetch_object(owner).await?;
+            if actor.get("id").and_then(|id| id.as_str()) != Some(owner) {
+                return Err("Actor's id doesn't match its URL".to_string());
+            }
+            (actor, key_doc.clone())
+        }
+    };
+
+    let actor_id = actor.get("id").and_then(|id| id.as_str()).ok_or("Actor has no id")?;
+    if origin(actor_id).as_ref() != Some(&key_origin) {
+        return Err("Actor is on another origin than the key".to_string());
+    }
+    if key.get("owner").and_then(|o| o.as_str()) != Some(actor_id) {
+        return Err("Key doesn't belong to the actor".to_string());
+    }
+    let pem = key.get("publicKeyPem").and_then(|p| p.as_str()).ok_or("Key has no PEM")?;
+
+    let verified = PKey::public_key_from_pem(pem.as_bytes())
+        .and_then(|public_key| {
+            let mut verifier = Verifier::new(MessageDigest::sha256(), &public_key)?;
+            verifier.update(signing_string.as_bytes())?;
+            verifier.verify(&signature)
+        })
+        .map_err(|e| format!("Failed to verify signature: {}", e))?;
+    if !verified {
+        return Err("Signature doesn't verify".to_string());
+    }
+
+    Ok(RemoteActor {
+        id: actor_id.to_string(),
+        inbox: actor.get("inbox").and_then(|i| i.as_str()).ok_or("Actor has no inbox")?.to_string(),
+        shared_inbox: actor
+            .get("endpoints")
+            .and_then(|e| e.get("sharedInbox"))
+            .and_then(|i| i.as_str())
+            .map(str::to_string),
+    })
+}
+
+// ============================================================================
+// HANDLERS
+// ============================================================================
+
+#[derive(Deserialize)]
+pub struct WebfingerQuery {
+    pub resource: String,
+}
+
+// GET /.well-known/webfinger?resource=acct:username@domain
+pub async fn webfinger(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<WebfingerQuery>,
+) -> Result<Response, StatusCode> {
+    let domain = domain().ok_or(StatusCode::NOT_FOUND)?;
+    let account = query.resource.strip_prefix("acct:").ok_or(StatusCode::BAD_REQUEST)?;
+    let (username, host) = account.rsplit_once('@').ok_or(StatusCode::BAD_REQUEST)?;
+    if !host.eq_ignore_ascii_case(domain) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let (user_id, username) = sqlx::query_as::<_, (Uuid, String)>(
+        "SELECT id, username FROM users WHERE LOWER(username) = LOWER($1) AND federated"
+    )
+    .bind(username)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(db_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let actor = actor_url(domain, user_id);
+    Ok(activity_response(
+        "application/jrd+json",
+        serde_json::json!({
+            "subject": format!("acct:{}@{}", username, domain),
+            "aliases": [actor],
+            "links": [{ "rel": "self", "type": ACTIVITY_JSON, "href": actor }],
+        }),
+    ))
+}
+
+// GET /ap/users/{user_id}
+pub async fn actor(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Response, StatusCode> {
+    let domain = domain().ok_or(StatusCode::NOT_FOUND)?;
+    let actor = find_actor(&state.pool, user_id).await?;
+    let id = actor_url(domain, actor.id);
+    let icon = match &actor.avatar_url {
+        Some(url) => Some(serde_json::json!({ "type": "Image", "url": state.media_service.sign_url(url).await })),
+        None => None,
+    };
+
+    Ok(activity_response(
+        ACTIVITY_JSON,
+        serde_json::json!({
+            "@context": [AS_CONTEXT, SECURITY_CONTEXT],
+            "id": id,
+            "type": "Person",
+            "preferredUsername": actor.username,
+            "name": actor.display_name.as_deref().unwrap_or(&actor.username),
+            "summary": actor.bio.as_deref().map(crate::chat_export::escape_html),
+            "icon": icon,
+            "inbox": format!("{}/inbox", id),
+            "outbox": format!("{}/outbox", id),
+            "followers": format!("{}/followers", id),
+            "manuallyApprovesFollowers": false,
+            "discoverable": actor.federated,
+            "publicKey": {
+                "id": format!("{}#main-key", id),
+                "owner": id,
+                "publicKeyPem": actor.public_key_pem,
+            },
+        }),
+    ))
+}
+
+// GET /ap/users/{user_id}/outbox: the account's live public stories
+pub async fn outbox(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Response, StatusCode> {
+    let domain = domain().ok_or(StatusCode::NOT_FOUND)?;
+    let actor = find_actor(&state.pool, user_id).await?;
+    let stories = public_stories(&state.pool, Some(actor.id), None).await.map_err(db_error)?;
+
+    let mut items = Vec::with_capacity(stories.len());
+    for story in &stories {
+        let note = note(&state.media_service, domain, story).await;
+        items.push(create_activity(domain, story, note));
+    }
+
+    Ok(activity_response(
+        ACTIVITY_JSON,
+        serde_json::json!({
+            "@context": AS_CONTEXT,
+            "id": format!("{}/outbox", actor_url(domain, actor.id)),
+            "type": "OrderedCollection",
+            "totalItems": items.len(),
+            "orderedItems": items,
+        }),
+    ))
+}
+
+// GET /ap/users/{user_id}/followers: only the count is public
+pub async fn followers(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Response, StatusCode> {
+    let domain = domain().ok_or(StatusCode::NOT_FOUND)?;
+    let actor = find_actor(&state.pool, user_id).await?;
+    let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM federation_followers WHERE user_id = $1")
+        .bind(actor.id)
+        .fetch_one(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+
+    Ok(activity_response(
+        ACTIVITY_JSON,
+        serde_json::json!({
+            "@context": AS_CONTEXT,
+            "id": format!("{}/followers", actor_url(domain, actor.id)),
+            "type": "OrderedCollection",
+            "totalItems": count,
+        }),
+    ))
+}
+
+// GET /ap/stories/{story_id}
+pub async fn story(
+    State(state): State<Arc<AppState>>,
+    Path(story_id): Path<Uuid>,
+) -> Result<Response, StatusCode> {
+    let domain = domain().ok_or(StatusCode::NOT_FOUND)?;
+    let story = public_stories(&state.pool, None, Some(story_id))
+        .await
+        .map_err(db_error)?
+        .pop()
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let mut note = note(&state.media_service, domain, &story).await;
+    note["@context"] = serde_json::json!(AS_CONTEXT);
+    Ok(activity_response(ACTIVITY_JSON, note))
+}
+
+// POST /ap/users/{user_id}/inbox: Follow and Undo(Follow) from remote actors
+pub async fn inbox(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, StatusCode> {
+    let domain = domain().ok_or(StatusCode::NOT_FOUND)?;
+    let local = find_actor(&state.pool, user_id).await?;
+    let activity: serde_json::Value = serde_json::from_slice(&body).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let target = uri.path_and_query().map(|p| p.as_str()).unwrap_or(uri.path());
+    let remote = verify_request(&headers, target, &body).await.map_err(|e| {
+        eprintln!("⚠️ Rejected inbox delivery for {}: {}", user_id, e);
+        StatusCode::UNAUTHORIZED
+    })?;
+    if activity.get("actor").and_then(|a| a.as_str()) != Some(remote.id.as_str()) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let local_url = actor_url(domain, local.id);
+    match activity.get("type").and_then(|t| t.as_str()) {
+        Some("Follow") => {
+            if activity.get("object").and_then(|o| o.as_str()) != Some(local_url.as_str()) {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            if !local.federated {
+                return Err(StatusCode::FORBIDDEN);
+            }
+
+            sqlx::query(
+                r#"
+                INSERT INTO federation_followers (user_id, actor_id, inbox_url, shared_inbox_url)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (user_id, actor_id)
+                DO UPDATE SET inbox_url = EXCLUDED.inbox_url, shared_inbox_url = EXCLUDED.shared_inbox_url
+                "#
+            )
+            .bind(local.id)
+            .bind(&remote.id)
+            .bind(&remote.inbox)
+            .bind(&remote.shared_inbox)
+            .execute(state.pool.as_ref())
+            .await
+            .map_err(db_error)?;
+
+            let accept = serde_json::json!({
+                "@context": AS_CONTEXT,
+                "id": format!("{}#accepts/{}", local_url, Uuid::new_v4()),
+                "type": "Accept",
+                "actor": local_url,
+                "object": activity,
+            });
+            send(&state.pool, local.id, &remote.inbox, &accept).await;
+        }
+        // We only ever accept follows, so any Undo from a follower ends the follow
+        Some("Undo") => {
+            let undone = activity.get("object");
+            let is_follow = undone.and_then(|o| o.get("type")).and_then(|t| t.as_str()).is_none_or(|t| t == "Follow");
+            if is_follow {
+                sqlx::query("DELETE FROM federation_followers WHERE user_id = $1 AND actor_id = $2")
+                    .bind(local.id)
+                    .bind(&remote.id)
+                    .execute(state.pool.as_ref())
+                    .await
+                    .map_err(db_error)?;
+            }
+        }
+        // Everything else (likes, replies, ...) is ignored for now
+        _ => {}
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
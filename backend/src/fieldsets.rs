@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
+
+// Sparse fieldsets: list endpoints take `?fields=a,b,c` and return only
+// those attributes of each item, so clients don't download what they never
+// render. `id` is always kept; unknown names are ignored.
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FieldsQuery {
+    /// Comma-separated attributes to return for each item; all when omitted
+    pub fields: Option<String>,
+}
+
+impl FieldsQuery {
+    /// Serialize `items`, keeping only the requested attributes
+    pub fn select<T: Serialize>(&self, items: &[T]) -> Result<serde_json::Value, serde_json::Error> {
+        let value = serde_json::to_value(items)?;
+        let Some(fields) = self.fields.as_deref().filter(|f| !f.trim().is_empty()) else {
+            return Ok(value);
+        };
+        let wanted: Vec<&str> = fields.split(',').map(str::trim).collect();
+
+        let serde_json::Value::Array(mut items) = value else {
+            return Ok(value);
+        };
+        for item in &mut items {
+            if let serde_json::Value::Object(map) = item {
+                map.retain(|key, _| key == "id" || wanted.contains(&key.as_str()));
+            }
+        }
+        Ok(serde_json::Value::Array(items))
+    }
+}
@@ -0,0 +1,344 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::admin::AuthUser;
+use crate::AppState;
+
+// Proxies GIPHY so the API key never reaches clients. Results are cached in
+// Redis because popular searches repeat constantly and the key is rate limited.
+const GIPHY_API_BASE: &str = "https://api.giphy.com/v1";
+const SEARCH_CACHE_TTL_SECONDS: u64 = 600;
+const TRENDING_CACHE_TTL_SECONDS: u64 = 300;
+const DEFAULT_LIMIT: u32 = 24;
+const MAX_LIMIT: u32 = 50;
+// GIPHY rejects offsets past this
+const MAX_OFFSET: u32 = 4999;
+const MAX_QUERY_LENGTH: usize = 50;
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Gifs,
+    Stickers,
+}
+
+impl Kind {
+    fn path(self) -> &'static str {
+        match self {
+            Kind::Gifs => "gifs",
+            Kind::Stickers => "stickers",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct GifResult {
+    /// Provider id; clients may send it as the content of gif/sticker messages
+    pub id: String,
+    pub title: String,
+    /// Full size animation, used as the message media_url
+    pub url: String,
+    /// Small rendition for the picker grid
+    pub preview_url: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct GifSearchResponse {
+    pub results: Vec<GifResult>,
+    /// Pass as offset to fetch the next page; absent on the last page
+    pub next_offset: Option<u32>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GifSearchQuery {
+    q: String,
+    limit: Option<u32>,
+    offset: Option<u32>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GifTrendingQuery {
+    limit: Option<u32>,
+    offset: Option<u32>,
+}
+
+// Subset of the GIPHY response we use
+#[derive(Deserialize)]
+struct GiphyResponse {
+    data: Vec<GiphyItem>,
+    pagination: GiphyPagination,
+}
+
+#[derive(Deserialize)]
+struct GiphyPagination {
+    #[serde(default)]
+    total_count: u32,
+    count: u32,
+    offset: u32,
+}
+
+#[derive(Deserialize)]
+struct GiphyItem {
+    id: String,
+    #[serde(default)]
+    title: String,
+    images: GiphyImages,
+}
+
+#[derive(Deserialize)]
+struct GiphyImages {
+    original: GiphyImage,
+    fixed_width_small: Option<GiphyImage>,
+}
+
+#[derive(Deserialize)]
+struct GiphyImage {
+    url: String,
+    #[serde(default)]
+    width: String,
+    #[serde(default)]
+    height: String,
+}
+
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .expect("Failed to build GIF provider HTTP client")
+    })
+}
+
+fn content_rating() -> String {
+    std::env::var("GIF_CONTENT_RATING").unwrap_or_else(|_| "pg-13".to_string())
+}
+
+/// Whether `url` points at the GIF provider's CDN. gif and sticker messages
+/// must, so they can't be used to embed arbitrary links.
+pub fn is_provider_url(url: &str) -> bool {
+    reqwest::Url::parse(url).is_ok_and(|url| {
+        url.scheme() == "https"
+            && url
+                .host_str()
+                .is_some_and(|host| host == "giphy.com" || host.ends_with(".giphy.com"))
+    })
+}
+
+fn page(limit: Option<u32>, offset: Option<u32>) -> Result<(u32, u32), (StatusCode, String)> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let offset = offset.unwrap_or(0);
+    if offset > MAX_OFFSET {
+        return Err((StatusCode::BAD_REQUEST, format!("offset can be at most {}", MAX_OFFSET)));
+    }
+    Ok((limit, offset))
+}
+
+async fn fetch(
+    state: &AppState,
+    kind: Kind,
+    endpoint: &str,
+    query: Option<&str>,
+    limit: u32,
+    offset: u32,
+    cache_ttl: u64,
+) -> Result<GifSearchResponse, (StatusCode, String)> {
+    let api_key = std::env::var("GIPHY_API_KEY")
+        .map_err(|_| (StatusCode::SERVICE_UNAVAILABLE, "GIF search is not configured".to_string()))?;
+    let rating = content_rating();
+
+    let cache_key = format!(
+        "gifs:{}:{}:{}:{}:{}:{}",
+        kind.path(),
+        endpoint,
+        rating,
+        query.unwrap_or(""),
+        limit,
+        offset
+    );
+    {
+        let mut redis = state.redis.lock().await;
+        if let Ok(Some(cached)) = redis.cache_get(&cache_key).await {
+            if let Ok(response) = serde_json::from_str::<GifSearchResponse>(&cached) {
+                return Ok(response);
+            }
+        }
+    }
+
+    let mut params = vec![
+        ("api_key", api_key),
+        ("limit", limit.to_string()),
+        ("offset", offset.to_string()),
+        ("rating", rating),
+    ];
+    if let Some(q) = query {
+        params.push(("q", q.to_string()));
+    }
+
+    let giphy = http_client()
+        .get(format!("{}/{}/{}", GIPHY_API_BASE, kind.path(), endpoint))
+        .query(&params)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| {
+            tracing::error!("GIF provider request failed: {}", e.without_url());
+            (StatusCode::BAD_GATEWAY, "GIF provider unavailable".to_string())
+        })?
+        .json::<GiphyResponse>()
+        .await
+        .map_err(|e| {
+            tracing::error!("Unexpected GIF provider response: {}", e.without_url());
+            (StatusCode::BAD_GATEWAY, "GIF provider unavailable".to_string())
+        })?;
+
+    let next = giphy.pagination.offset + giphy.pagination.count;
+    let response = GifSearchResponse {
+        next_offset: (giphy.pagination.count > 0 && next < giphy.pagination.total_count && next <= MAX_OFFSET)
+            .then_some(next),
+        results: giphy
+            .data
+            .into_iter()
+            .map(|item| {
+                let preview = item.images.fixed_width_small.as_ref().unwrap_or(&item.images.original);
+                GifResult {
+                    preview_url: preview.url.clone(),
+                    width: item.images.original.width.parse().unwrap_or(0),
+                    height: item.images.original.height.parse().unwrap_or(0),
+                    url: item.images.original.url,
+                    id: item.id,
+                    title: item.title,
+                }
+            })
+            .collect(),
+    };
+
+    if let Ok(json) = serde_json::to_string(&response) {
+        let mut redis = state.redis.lock().await;
+        let _ = redis.cache_set(&cache_key, &json, cache_ttl).await;
+    }
+
+    Ok(response)
+}
+
+async fn search(
+    state: &AppState,
+    kind: Kind,
+    params: GifSearchQuery,
+) -> Result<Json<GifSearchResponse>, (StatusCode, String)> {
+    let q = params.q.trim().to_lowercase();
+    if q.is_empty() || q.chars().count() > MAX_QUERY_LENGTH {
+        return Err((StatusCode::BAD_REQUEST, format!("q must be 1-{} characters", MAX_QUERY_LENGTH)));
+    }
+    let (limit, offset) = page(params.limit, params.offset)?;
+
+    fetch(state, kind, "search", Some(&q), limit, offset, SEARCH_CACHE_TTL_SECONDS)
+        .await
+        .map(Json)
+}
+
+async fn trending(
+    state: &AppState,
+    kind: Kind,
+    params: GifTrendingQuery,
+) -> Result<Json<GifSearchResponse>, (StatusCode, String)> {
+    let (limit, offset) = page(params.limit, params.offset)?;
+
+    fetch(state, kind, "trending", None, limit, offset, TRENDING_CACHE_TTL_SECONDS)
+        .await
+        .map(Json)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/media/gifs/search",
+    tag = "media",
+    params(GifSearchQuery),
+    responses(
+        (status = 200, body = GifSearchResponse),
+        (status = 502, description = "GIF provider unavailable"),
+        (status = 503, description = "GIF search is not configured"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn search_gifs(
+    _user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<GifSearchQuery>,
+) -> Result<Json<GifSearchResponse>, (StatusCode, String)> {
+    search(&state, Kind::Gifs, params).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/media/gifs/trending",
+    tag = "media",
+    params(GifTrendingQuery),
+    responses(
+        (status = 200, body = GifSearchResponse),
+        (status = 502, description = "GIF provider unavailable"),
+        (status = 503, description = "GIF search is not configured"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn trending_gifs(
+    _user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<GifTrendingQuery>,
+) -> Result<Json<GifSearchResponse>, (StatusCode, String)> {
+    trending(&state, Kind::Gifs, params).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/media/stickers/search",
+    tag = "media",
+    params(GifSearchQuery),
+    responses(
+        (status = 200, body = GifSearchResponse),
+        (status = 502, description = "GIF provider unavailable"),
+        (status = 503, description = "GIF search is not configured"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn search_stickers(
+    _user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<GifSearchQuery>,
+) -> Result<Json<GifSearchResponse>, (StatusCode, String)> {
+    search(&state, Kind::Stickers, params).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/media/stickers/trending",
+    tag = "media",
+    params(GifTrendingQuery),
+    responses(
+        (status = 200, body = GifSearchResponse),
+        (status = 502, description = "GIF provider unavailable"),
+        (status = 503, description = "GIF search is not configured"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn trending_stickers(
+    _user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<GifTrendingQuery>,
+) -> Result<Json<GifSearchResponse>, (StatusCode, String)> {
+    trending(&state, Kind::Stickers, params).await
+}
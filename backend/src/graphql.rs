@@ -0,0 +1,736 @@
+use async_graphql::dataloader::{DataLoader, Loader};
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Object, Schema, SimpleObject};
+use axum::{extract::State, http::HeaderMap, Json};
+use chrono::NaiveDateTime;
+use sqlx::{FromRow, Row};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::chat::MessageResponse;
+use crate::stories::Story;
+use crate::AppState;
+
+// GraphQL gateway for the mobile client, at POST /api/v1/graphql. A screen
+// that takes several REST calls (a profile with its stories, follow state and
+// streak; the chat list with members and last messages) is one query here.
+// It's read-only: writes stay on the REST endpoints. Anything resolved per
+// row - a story's author, a user's stories, a chat's members - goes through a
+// DataLoader, so each kind costs one query per request however many rows ask
+// for it. The caller is the bearer token's user, and the visibility rules are
+// the REST endpoints': 18+ stories only for adults and their author,
+// takedowns by the request's country. GET /api/v1/graphql/schema returns the
+// SDL for client code generation.
+
+const MAX_DEPTH: usize = 12;
+const MAX_COMPLEXITY: usize = 1000;
+const DEFAULT_PAGE: i32 = 50;
+const MAX_PAGE: i32 = 100;
+
+type GatewaySchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+fn schema() -> &'static GatewaySchema {
+    static SCHEMA: OnceLock<GatewaySchema> = OnceLock::new();
+    SCHEMA.get_or_init(|| {
+        Schema::build(Query, EmptyMutation, EmptySubscription)
+            .limit_depth(MAX_DEPTH)
+            .limit_complexity(MAX_COMPLEXITY)
+            .finish()
+    })
+}
+
+// The signed-in caller and where they're asking from
+struct Viewer {
+    id: Uuid,
+    country: Option<String>,
+}
+
+// Errors reach clients without the database's details
+fn db_error(e: impl std::fmt::Display) -> async_graphql::Error {
+    eprintln!("❌ GraphQL query failed: {}", e);
+    async_graphql::Error::new("Database error")
+}
+
+fn state<'a>(ctx: &Context<'a>) -> &'a Arc<AppState> {
+    ctx.data_unchecked::<Arc<AppState>>()
+}
+
+fn viewer<'a>(ctx: &Context<'a>) -> &'a Viewer {
+    ctx.data_unchecked::<Viewer>()
+}
+
+// ============================================================================
+// LOADERS
+// ============================================================================
+
+#[derive(Clone, FromRow)]
+struct UserRow {
+    id: Uuid,
+    username: String,
+    display_name: Option<String>,
+    avatar_url: Option<String>,
+    bio: Option<String>,
+    about: Option<String>,
+    profile_link: Option<String>,
+    follower_count: Option<i32>,
+    following_count: Option<i32>,
+    story_count: Option<i32>,
+}
+
+struct UserLoader(Arc<AppState>);
+
+impl Loader<Uuid> for UserLoader {
+    type Value = UserRow;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, UserRow>, Self::Error> {
+        let users = sqlx::query_as::<_, UserRow>(
+            r#"
+            SELECT id, username, display_name, avatar_url, bio, about, profile_link,
+                   follower_count, following_count, story_count
+            FROM users
+            WHERE id = ANY($1)
+            "#
+        )
+        .bind(ids)
+        .fetch_all(self.0.pool.as_ref())
+        .await?;
+        Ok(users.into_iter().map(|user| (user.id, user)).collect())
+    }
+}
+
+// Whether the viewer follows each user
+struct FollowLoader(Arc<AppState>, Uuid);
+
+impl Loader<Uuid> for FollowLoader {
+    type Value = bool;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, bool>, Self::Error> {
+        let followed = sqlx::query_scalar::<_, Uuid>(
+            "SELECT following_id FROM follows WHERE follower_id = $1 AND following_id = ANY($2)"
+        )
+        .bind(self.1)
+        .bind(ids)
+        .fetch_all(self.0.pool.as_ref())
+        .await?;
+        Ok(ids.iter().map(|id| (*id, followed.contains(id))).collect())
+    }
+}
+
+/// Streak between the caller and another user
+#[derive(Clone, Default, SimpleObject, FromRow)]
+struct Streak {
+    current_streak: i32,
+    longest_streak: i32,
+    last_interaction_date: Option<String>,
+}
+
+// The viewer's streak with each user; users without one are left out
+struct StreakLoader(Arc<AppState>, Uuid);
+
+impl Loader<Uuid> for StreakLoader {
+    type Value = Streak;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, Streak>, Self::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT CASE WHEN user1_id = $1 THEN user2_id ELSE user1_id END AS other_user_id,
+                   current_streak, longest_streak, last_interaction_date::TEXT AS last_interaction_date
+            FROM user_streaks
+            WHERE (user1_id = $1 AND user2_id = ANY($2)) OR (user2_id = $1 AND user1_id = ANY($2))
+            "#
+        )
+        .bind(self.1)
+        .bind(ids)
+        .fetch_all(self.0.pool.as_ref())
+        .await?;
+        rows.iter()
+            .map(|row| Ok((row.try_get("other_user_id")?, Streak::from_row(row)?)))
+            .collect::<Result<_, sqlx::Error>>()
+            .map_err(Arc::new)
+    }
+}
+
+// Live stories by each user, co-authored ones included, newest first
+struct StoriesLoader(Arc<AppState>, Uuid, Option<String>);
+
+impl Loader<Uuid> for StoriesLoader {
+    type Value = Vec<Arc<Story>>;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, Vec<Arc<Story>>>, Self::Error> {
+        let Self(state, viewer_id, country) = self;
+        let rows = sqlx::query(
+            r#"
+            SELECT a.user_id AS author_id,
+                   s.id, s.user_id, s.media_url, s.playback_url, s.media_type, s.thumbnail_url, s.preview_url,
+                   s.caption, s.alt_text, s.alt_text_generated, s.is_mature, s.view_count, s.like_count,
+                   s.comment_count, s.created_at, s.expires_at, u.username,
+                   sc.user_id AS co_author_id, cu.username AS co_author_username, s.hide_like_count
+            FROM story_authors a
+            JOIN stories s ON s.id = a.story_id
+            JOIN users u ON u.id = s.user_id
+            LEFT JOIN story_collaborators sc ON sc.story_id = s.id AND sc.status = 'accepted'
+            LEFT JOIN users cu ON cu.id = sc.user_id
+            WHERE a.user_id = ANY($1)
+              AND s.expires_at > NOW()
+              AND (NOT s.is_mature OR s.user_id = $2 OR user_is_adult($2))
+              AND NOT geo_blocked('story', s.id, $3)
+            ORDER BY s.created_at DESC
+            "#
+        )
+        .bind(ids)
+        .bind(viewer_id)
+        .bind(country)
+        .fetch_all(state.read_pool())
+        .await?;
+
+        let authors: Vec<Uuid> = rows.iter().map(|row| row.try_get("author_id")).collect::<Result<_, _>>()?;
+        let mut stories: Vec<Story> = rows.iter().map(Story::from_row).collect::<Result<_, _>>()?;
+        crate::story_media::attach(&state.pool, &mut stories).await;
+
+        let mut by_author: HashMap<Uuid, Vec<Arc<Story>>> = HashMap::new();
+        for (author, mut story) in authors.into_iter().zip(stories) {
+            story.hide_likes_from(Some(*viewer_id));
+            story.sign_media(&state.media_service).await;
+            by_author.entry(author).or_default().push(Arc::new(story));
+        }
+        Ok(by_author)
+    }
+}
+
+// Member ids of each chat, in join order
+struct MembersLoader(Arc<AppState>);
+
+impl Loader<Uuid> for MembersLoader {
+    type Value = Vec<Uuid>;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, Vec<Uuid>>, Self::Error> {
+        let members = sqlx::query_as::<_, (Uuid, Uuid)>(
+            "SELECT chat_room_id, user_id FROM chat_members WHERE chat_room_id = ANY($1) ORDER BY joined_at"
+        )
+        .bind(ids)
+        .fetch_all(self.0.pool.as_ref())
+        .await?;
+
+        let mut by_room: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for (room_id, user_id) in members {
+            by_room.entry(room_id).or_default().push(user_id);
+        }
+        Ok(by_room)
+    }
+}
+
+// Latest message of each chat the viewer can still see, as the chat list
+// shows it
+struct LastMessageLoader(Arc<AppState>, Uuid);
+
+impl Loader<Uuid> for LastMessageLoader {
+    type Value = MessageResponse;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, MessageResponse>, Self::Error> {
+        let Self(state, viewer_id) = self;
+        let messages = sqlx::query_as::<_, MessageResponse>(
+            r#"
+            SELECT DISTINCT ON (m.chat_room_id)
+                   m.id, m.chat_room_id, m.sender_id, u.username as sender_username,
+                   m.message_type, m.content, m.media_url, m.media_thumbnail_url,
+                   m.view_once, m.is_ephemeral, m.expires_at, m.created_at,
+                   FALSE AS is_viewed, FALSE AS is_read,
+                   EXISTS(SELECT 1 FROM saved_messages WHERE message_id = m.id AND user_id = $2) as is_saved,
+                   (SELECT muted_word FROM message_recipients WHERE message_id = m.id AND user_id = $2) AS muted_word,
+                   CASE WHEN m.sender_id = $2 THEN message_delivery_state(m.id) END AS delivery_state
+            FROM messages m
+            JOIN users u ON m.sender_id = u.id
+            WHERE m.chat_room_id = ANY($1) AND m.deleted_at IS NULL
+              AND NOT EXISTS(SELECT 1 FROM message_recipients WHERE message_id = m.id AND user_id = $2 AND hidden_at IS NOT NULL)
+            ORDER BY m.chat_room_id, m.created_at DESC
+            "#
+        )
+        .bind(ids)
+        .bind(viewer_id)
+        .fetch_all(state.pool.as_ref())
+        .await?;
+
+        let mut by_room = HashMap::new();
+        // Expired messages not swept yet are hidden; saved ones are retained
+        for mut message in messages.into_iter().filter(|m| m.is_saved || !crate::expiration::is_expired(m.expires_at)) {
+            message.sign_media(&state.media_service, *viewer_id).await;
+            by_room.insert(message.chat_room_id, message);
+        }
+        Ok(by_room)
+    }
+}
+
+async fn load_user(ctx: &Context<'_>, id: Uuid) -> async_graphql::Result<Option<User>> {
+    let user = ctx.data_unchecked::<DataLoader<UserLoader>>().load_one(id).await.map_err(db_error)?;
+    Ok(user.map(User))
+}
+
+// ============================================================================
+// TYPES
+// ============================================================================
+
+struct User(UserRow);
+
+#[Object]
+impl User {
+    async fn id(&self) -> Uuid {
+        self.0.id
+    }
+
+    async fn username(&self) -> &str {
+        &self.0.username
+    }
+
+    async fn display_name(&self) -> Option<&str> {
+        self.0.display_name.as_deref()
+    }
+
+    async fn avatar_url(&self, ctx: &Context<'_>) -> Option<String> {
+        let mut url = self.0.avatar_url.clone();
+        state(ctx).media_service.sign_opt(&mut url).await;
+        url
+    }
+
+    async fn bio(&self) -> Option<&str> {
+        self.0.bio.as_deref()
+    }
+
+    async fn about(&self) -> Option<&str> {
+        self.0.about.as_deref()
+    }
+
+    async fn profile_link(&self) -> Option<&str> {
+        self.0.profile_link.as_deref()
+    }
+
+    async fn follower_count(&self) -> i32 {
+        self.0.follower_count.unwrap_or(0)
+    }
+
+    async fn following_count(&self) -> i32 {
+        self.0.following_count.unwrap_or(0)
+    }
+
+    async fn story_count(&self) -> i32 {
+        self.0.story_count.unwrap_or(0)
+    }
+
+    /// Whether the caller follows this user
+    async fn is_following(&self, ctx: &Context<'_>) -> async_graphql::Result<bool> {
+        let following = ctx.data_unchecked::<DataLoader<FollowLoader>>().load_one(self.0.id).await.map_err(db_error)?;
+        Ok(following.unwrap_or(false))
+    }
+
+    /// The caller's streak with this user; zero when they have none
+    async fn streak(&self, ctx: &Context<'_>) -> async_graphql::Result<Streak> {
+        let streak = ctx.data_unchecked::<DataLoader<StreakLoader>>().load_one(self.0.id).await.map_err(db_error)?;
+        Ok(streak.unwrap_or_default())
+    }
+
+    /// Live stories, including ones this user co-authored, newest first
+    async fn stories(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<StoryNode>> {
+        let stories = ctx.data_unchecked::<DataLoader<StoriesLoader>>().load_one(self.0.id).await.map_err(db_error)?;
+        Ok(stories.unwrap_or_default().into_iter().map(StoryNode).collect())
+    }
+}
+
+#[derive(SimpleObject)]
+struct StoryMediaItem {
+    id: Uuid,
+    media_url: String,
+    media_type: String,
+    alt_text: Option<String>,
+}
+
+struct StoryNode(Arc<Story>);
+
+#[Object(name = "Story")]
+impl StoryNode {
+    async fn id(&self) -> Uuid {
+        self.0.id
+    }
+
+    async fn author(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<User>> {
+        load_user(ctx, self.0.user_id).await
+    }
+
+    /// Accepted co-author, if any
+    async fn co_author(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<User>> {
+        match self.0.co_author_id {
+            Some(id) => load_user(ctx, id).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn media_url(&self) -> &str {
+        &self.0.media_url
+    }
+
+    /// HLS master playlist for videos once packaged; prefer it over mediaUrl
+    async fn playback_url(&self) -> Option<&str> {
+        self.0.playback_url.as_deref()
+    }
+
+    async fn media_type(&self) -> &str {
+        &self.0.media_type
+    }
+
+    async fn thumbnail_url(&self) -> Option<&str> {
+        self.0.thumbnail_url.as_deref()
+    }
+
+    async fn preview_url(&self) -> Option<&str> {
+        self.0.preview_url.as_deref()
+    }
+
+    /// Every item of a carousel story in order; empty for single-media stories
+    async fn media_items(&self) -> Vec<StoryMediaItem> {
+        self.0
+            .media_items
+            .iter()
+            .map(|item| StoryMediaItem {
+                id: item.id,
+                media_url: item.media_url.clone(),
+                media_type: item.media_type.clone(),
+                alt_text: item.alt_text.clone(),
+            })
+            .collect()
+    }
+
+    async fn caption(&self) -> Option<&str> {
+        self.0.caption.as_deref()
+    }
+
+    async fn alt_text(&self) -> Option<&str> {
+        self.0.alt_text.as_deref()
+    }
+
+    async fn is_mature(&self) -> bool {
+        self.0.is_mature
+    }
+
+    async fn view_count(&self) -> i32 {
+        self.0.view_count.unwrap_or(0)
+    }
+
+    /// Null when the author hid it
+    async fn like_count(&self) -> Option<i32> {
+        self.0.like_count
+    }
+
+    async fn comment_count(&self) -> i32 {
+        self.0.comment_count.unwrap_or(0)
+    }
+
+    async fn created_at(&self) -> String {
+        crate::timestamps::format(self.0.created_at)
+    }
+
+    async fn expires_at(&self) -> String {
+        crate::timestamps::format(self.0.expires_at)
+    }
+}
+
+#[derive(FromRow)]
+struct ChatRow {
+    id: Uuid,
+    name: Option<String>,
+    is_group: bool,
+    created_at: NaiveDateTime,
+    state: String,
+    retention_seconds: Option<i32>,
+}
+
+struct Chat(ChatRow);
+
+#[Object]
+impl Chat {
+    async fn id(&self) -> Uuid {
+        self.0.id
+    }
+
+    /// The group's name, or the other member's username in a 1:1 chat
+    async fn name(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<String>> {
+        if self.0.is_group {
+            return Ok(self.0.name.clone());
+        }
+        let members = ctx.data_unchecked::<DataLoader<MembersLoader>>().load_one(self.0.id).await.map_err(db_error)?;
+        let Some(other) = members.unwrap_or_default().into_iter().find(|id| *id != viewer(ctx).id) else {
+            return Ok(self.0.name.clone());
+        };
+        Ok(load_user(ctx, other).await?.map(|user| user.0.username))
+    }
+
+    async fn is_group(&self) -> bool {
+        self.0.is_group
+    }
+
+    /// pending while a 1:1 chat waits on its message request, accepted otherwise
+    async fn state(&self) -> &str {
+        &self.0.state
+    }
+
+    /// Messages are removed this long after they're sent; null keeps them
+    async fn retention_seconds(&self) -> Option<i32> {
+        self.0.retention_seconds
+    }
+
+    async fn created_at(&self) -> String {
+        crate::timestamps::format(self.0.created_at)
+    }
+
+    async fn members(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<User>> {
+        let ids = ctx.data_unchecked::<DataLoader<MembersLoader>>().load_one(self.0.id).await.map_err(db_error)?;
+        let ids = ids.unwrap_or_default();
+        let mut users = ctx.data_unchecked::<DataLoader<UserLoader>>().load_many(ids.iter().copied()).await.map_err(db_error)?;
+        Ok(ids.iter().filter_map(|id| users.remove(id)).map(User).collect())
+    }
+
+    async fn last_message(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Message>> {
+        let message = ctx.data_unchecked::<DataLoader<LastMessageLoader>>().load_one(self.0.id).await.map_err(db_error)?;
+        Ok(message.map(Message))
+    }
+}
+
+struct Message(MessageResponse);
+
+#[Object]
+impl Message {
+    async fn id(&self) -> Uuid {
+        self.0.id
+    }
+
+    async fn sender(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<User>> {
+        load_user(ctx, self.0.sender_id).await
+    }
+
+    /// text, image, video, gif, sticker, story (content is the story id) or unsent
+    async fn message_type(&self) -> &str {
+        &self.0.message_type
+    }
+
+    async fn content(&self) -> Option<&str> {
+        self.0.content.as_deref()
+    }
+
+    async fn media_url(&self) -> Option<&str> {
+        self.0.media_url.as_deref()
+    }
+
+    async fn media_thumbnail_url(&self) -> Option<&str> {
+        self.0.media_thumbnail_url.as_deref()
+    }
+
+    async fn view_once(&self) -> bool {
+        self.0.view_once
+    }
+
+    async fn is_saved(&self) -> bool {
+        self.0.is_saved
+    }
+
+    /// The caller's muted word this message contains
+    async fn muted_word(&self) -> Option<&str> {
+        self.0.muted_word.as_deref()
+    }
+
+    /// sent, delivered or read on the caller's own messages
+    async fn delivery_state(&self) -> Option<&str> {
+        self.0.delivery_state.as_deref()
+    }
+
+    async fn created_at(&self) -> String {
+        crate::timestamps::format(self.0.created_at)
+    }
+
+    async fn expires_at(&self) -> Option<String> {
+        self.0.expires_at.map(crate::timestamps::format)
+    }
+}
+
+struct Notification(crate::notifications::Notification);
+
+#[Object]
+impl Notification {
+    async fn id(&self) -> &str {
+        &self.0.id
+    }
+
+    #[graphql(name = "type")]
+    async fn notification_type(&self) -> &str {
+        &self.0.notification_type
+    }
+
+    /// social, system or ads
+    async fn category(&self) -> &str {
+        &self.0.category
+    }
+
+    #[graphql(name = "fromUser")]
+    async fn sender(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<User>> {
+        match self.0.from_user_id.as_deref().and_then(|id| Uuid::parse_str(id).ok()) {
+            Some(id) => load_user(ctx, id).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn story_id(&self) -> Option<&str> {
+        self.0.story_id.as_deref()
+    }
+
+    async fn comment_id(&self) -> Option<&str> {
+        self.0.comment_id.as_deref()
+    }
+
+    async fn message(&self) -> Option<&str> {
+        self.0.message.as_deref()
+    }
+
+    async fn is_read(&self) -> bool {
+        self.0.is_read
+    }
+
+    async fn created_at(&self) -> &str {
+        &self.0.created_at
+    }
+}
+
+#[derive(SimpleObject)]
+struct NotificationPage {
+    items: Vec<Notification>,
+    /// Pass as `after` for the next page; null on the last one
+    next_cursor: Option<String>,
+    unread_count: i64,
+}
+
+// ============================================================================
+// QUERY ROOT
+// ============================================================================
+
+struct Query;
+
+#[Object]
+impl Query {
+    /// The signed-in user
+    async fn me(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<User>> {
+        load_user(ctx, viewer(ctx).id).await
+    }
+
+    async fn user(&self, ctx: &Context<'_>, id: Uuid) -> async_graphql::Result<Option<User>> {
+        load_user(ctx, id).await
+    }
+
+    /// The caller's chats, most recently active first. Chats waiting on the
+    /// caller's answer to a message request are left out.
+    async fn chats(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default_with = "DEFAULT_PAGE")] first: i32,
+    ) -> async_graphql::Result<Vec<Chat>> {
+        let chats = sqlx::query_as::<_, ChatRow>(
+            r#"
+            SELECT cr.id, cr.name, cr.is_group, cr.created_at, cr.state, cr.retention_seconds
+            FROM chat_rooms cr
+            JOIN chat_members cm ON cr.id = cm.chat_room_id
+            WHERE cm.user_id = $1
+              AND NOT EXISTS(SELECT 1 FROM message_requests
+                             WHERE chat_room_id = cr.id AND recipient_id = $1 AND status = 'pending')
+            ORDER BY cr.updated_at DESC
+            LIMIT $2
+            "#
+        )
+        .bind(viewer(ctx).id)
+        .bind(first.clamp(1, MAX_PAGE) as i64)
+        .fetch_all(state(ctx).pool.as_ref())
+        .await
+        .map_err(db_error)?;
+        Ok(chats.into_iter().map(Chat).collect())
+    }
+
+    /// The caller's notifications, newest first
+    async fn notifications(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default_with = "DEFAULT_PAGE")] first: i32,
+        after: Option<String>,
+        #[graphql(desc = "social, system or ads")] category: Option<String>,
+        #[graphql(name = "type")] notification_type: Option<String>,
+    ) -> async_graphql::Result<NotificationPage> {
+        let pool = state(ctx).pool.as_ref();
+        let user_id = viewer(ctx).id;
+        let params = crate::notifications::NotificationsQuery {
+            limit: first.clamp(1, MAX_PAGE).into(),
+            cursor: after,
+            category,
+            notification_type,
+        };
+        let (items, next_cursor) = crate::notifications::fetch_page(pool, user_id, &params)
+            .await
+            .map_err(|_| async_graphql::Error::new("Invalid cursor or category"))?;
+        let unread_count = crate::notifications::unread_count(pool, user_id).await.map_err(db_error)?;
+
+        Ok(NotificationPage {
+            items: items.into_iter().map(Notification).collect(),
+            next_cursor,
+            unread_count,
+        })
+    }
+}
+
+// ============================================================================
+// HANDLERS
+// ============================================================================
+
+// Run a GraphQL query as the signed-in user
+#[utoipa::path(
+    post,
+    path = "/api/v1/graphql",
+    tag = "graphql",
+    request_body(content = serde_json::Value, description = "GraphQL request: query, optional variables and operationName"),
+    responses(
+        (status = 200, body = serde_json::Value, description = "GraphQL response; failures are listed in its errors"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn execute(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    let viewer = Viewer {
+        id: user.id,
+        country: crate::takedowns::request_country(&headers),
+    };
+    // Loaders cache per request, so they're built fresh each time
+    let request = request
+        .data(DataLoader::new(UserLoader(state.clone()), tokio::spawn))
+        .data(DataLoader::new(FollowLoader(state.clone(), viewer.id), tokio::spawn))
+        .data(DataLoader::new(StreakLoader(state.clone(), viewer.id), tokio::spawn))
+        .data(DataLoader::new(StoriesLoader(state.clone(), viewer.id, viewer.country.clone()), tokio::spawn))
+        .data(DataLoader::new(MembersLoader(state.clone()), tokio::spawn))
+        .data(DataLoader::new(LastMessageLoader(state.clone(), viewer.id), tokio::spawn))
+        .data(viewer)
+        .data(state);
+
+    Json(schema().execute(request).await)
+}
+
+// The gateway's schema in GraphQL SDL
+#[utoipa::path(
+    get,
+    path = "/api/v1/graphql/schema",
+    tag = "graphql",
+    responses((status = 200, body = String, content_type = "text/plain"))
+)]
+pub async fn sdl() -> String {
+    schema().sdl()
+}
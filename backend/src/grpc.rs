@@ -0,0 +1,398 @@
+// tonic's service traits and interceptors return Result<_, Status>, and
+// Status is large
+#![allow(clippy::result_large_err)]
+
+use axum::http::StatusCode;
+use chrono::NaiveDateTime;
+use sqlx::FromRow;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+use crate::chat::{MessageResponse, OutgoingMessage};
+use crate::AppState;
+
+// gRPC API for internal services (a recommendation service, moderation
+// workers) that would otherwise page through the REST API: users, messaging
+// and media, defined in proto/internal.proto. It listens on GRPC_PORT, apart
+// from the public HTTP port, and only when both GRPC_PORT and SERVICE_TOKEN
+// are set. Every call must carry SERVICE_TOKEN in the x-service-token
+// metadata, the same shared secret the REST service endpoints accept.
+
+mod proto {
+    tonic::include_proto!("internal.v1");
+}
+
+use proto::media_server::{Media, MediaServer};
+use proto::messaging_server::{Messaging, MessagingServer};
+use proto::users_server::{Users, UsersServer};
+
+const DEFAULT_FOLLOWING_PAGE: u32 = 500;
+const MAX_FOLLOWING_PAGE: u32 = 5000;
+const MAX_BATCH: usize = 1000;
+
+/// Serve the gRPC API in the background when GRPC_PORT is set
+pub fn spawn(state: Arc<AppState>) {
+    let Some(port) = std::env::var("GRPC_PORT").ok().filter(|v| !v.is_empty()) else {
+        return;
+    };
+    if service_token().is_none() {
+        tracing::warn!("GRPC_PORT is set but SERVICE_TOKEN isn't; gRPC API disabled");
+        return;
+    }
+    let host = std::env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
+    let addr: SocketAddr = match format!("{}:{}", host, port).parse() {
+        Ok(addr) => addr,
+        Err(e) => {
+            tracing::error!("Invalid gRPC address {}:{}: {}", host, port, e);
+            return;
+        }
+    };
+
+    let service = Service(state);
+    let server = tonic::transport::Server::builder()
+        .add_service(UsersServer::with_interceptor(service.clone(), authenticate))
+        .add_service(MessagingServer::with_interceptor(service.clone(), authenticate))
+        .add_service(MediaServer::with_interceptor(service, authenticate));
+    tokio::spawn(async move {
+        tracing::info!("gRPC API listening on {}", addr);
+        if let Err(e) = server.serve(addr).await {
+            tracing::error!("gRPC server stopped: {}", e);
+        }
+    });
+}
+
+fn service_token() -> Option<String> {
+    std::env::var("SERVICE_TOKEN").ok().filter(|v| !v.is_empty())
+}
+
+// Interceptor on every service: the caller must present SERVICE_TOKEN
+fn authenticate(request: Request<()>) -> Result<Request<()>, Status> {
+    let expected = service_token().ok_or_else(|| Status::unauthenticated("Service token required"))?;
+    let presented = request
+        .metadata()
+        .get("x-service-token")
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| Status::unauthenticated("Service token required"))?;
+    if !crate::admin::constant_time_eq(presented.as_bytes(), expected.as_bytes()) {
+        return Err(Status::unauthenticated("Invalid service token"));
+    }
+    Ok(request)
+}
+
+#[derive(Clone)]
+struct Service(Arc<AppState>);
+
+fn parse_id(field: &str, value: &str) -> Result<Uuid, Status> {
+    Uuid::parse_str(value).map_err(|_| Status::invalid_argument(format!("{} must be a UUID", field)))
+}
+
+// Callers get a status without the database's details
+fn db_error(e: sqlx::Error) -> Status {
+    tracing::error!("gRPC query failed: {:?}", e);
+    Status::internal("Database error")
+}
+
+// Errors from checks shared with the REST handlers
+fn from_http(status: StatusCode, message: String) -> Status {
+    match status {
+        StatusCode::BAD_REQUEST => Status::invalid_argument(message),
+        StatusCode::FORBIDDEN => Status::permission_denied(message),
+        StatusCode::NOT_FOUND => Status::not_found(message),
+        StatusCode::CONFLICT => Status::failed_precondition(message),
+        StatusCode::TOO_MANY_REQUESTS => Status::resource_exhausted(message),
+        _ => Status::internal(message),
+    }
+}
+
+// ============================================================================
+// Users
+// ============================================================================
+
+#[derive(FromRow)]
+struct UserRow {
+    id: Uuid,
+    username: String,
+    display_name: Option<String>,
+    avatar_url: Option<String>,
+    bio: Option<String>,
+    role: String,
+    account_type: String,
+    is_bot: bool,
+    follower_count: Option<i32>,
+    following_count: Option<i32>,
+    story_count: Option<i32>,
+    created_at: NaiveDateTime,
+}
+
+impl Service {
+    async fn load_users(&self, ids: &[Uuid]) -> Result<Vec<proto::User>, Status> {
+        let rows = sqlx::query_as::<_, UserRow>(
+            r#"
+            SELECT id, username, display_name, avatar_url, bio, role, account_type, is_bot,
+                   follower_count, following_count, story_count, created_at
+            FROM users
+            WHERE id = ANY($1)
+            "#
+        )
+        .bind(ids)
+        .fetch_all(self.0.read_pool())
+        .await
+        .map_err(db_error)?;
+
+        let mut users = Vec::with_capacity(rows.len());
+        for row in rows {
+            let mut avatar_url = row.avatar_url;
+            self.0.media_service.sign_opt(&mut avatar_url).await;
+            users.push(proto::User {
+                id: row.id.to_string(),
+                username: row.username,
+                display_name: row.display_name,
+                avatar_url,
+                bio: row.bio,
+                role: row.role,
+                account_type: row.account_type,
+                is_bot: row.is_bot,
+                follower_count: row.follower_count.unwrap_or(0),
+                following_count: row.following_count.unwrap_or(0),
+                story_count: row.story_count.unwrap_or(0),
+                created_at: crate::timestamps::format(row.created_at),
+            });
+        }
+        Ok(users)
+    }
+}
+
+#[tonic::async_trait]
+impl Users for Service {
+    async fn get_user(&self, request: Request<proto::GetUserRequest>) -> Result<Response<proto::User>, Status> {
+        let id = parse_id("id", &request.get_ref().id)?;
+        let user = self.load_users(&[id]).await?.pop().ok_or_else(|| Status::not_found("User not found"))?;
+        Ok(Response::new(user))
+    }
+
+    async fn batch_get_users(
+        &self,
+        request: Request<proto::BatchGetUsersRequest>,
+    ) -> Result<Response<proto::BatchGetUsersResponse>, Status> {
+        let ids = &request.get_ref().ids;
+        if ids.len() > MAX_BATCH {
+            return Err(Status::invalid_argument(format!("At most {} ids per call", MAX_BATCH)));
+        }
+        let ids = ids.iter().map(|id| parse_id("ids", id)).collect::<Result<Vec<_>, _>>()?;
+        let users = self.load_users(&ids).await?;
+        Ok(Response::new(proto::BatchGetUsersResponse { users }))
+    }
+
+    async fn list_following(
+        &self,
+        request: Request<proto::ListFollowingRequest>,
+    ) -> Result<Response<proto::ListFollowingResponse>, Status> {
+        let request = request.into_inner();
+        let user_id = parse_id("user_id", &request.user_id)?;
+        let after = match request.after.as_str() {
+            "" => None,
+            after => Some(parse_id("after", after)?),
+        };
+        let limit = match request.limit {
+            0 => DEFAULT_FOLLOWING_PAGE,
+            limit => limit.min(MAX_FOLLOWING_PAGE),
+        };
+
+        let ids = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            SELECT following_id FROM follows
+            WHERE follower_id = $1 AND ($2::uuid IS NULL OR following_id > $2)
+            ORDER BY following_id
+            LIMIT $3
+            "#
+        )
+        .bind(user_id)
+        .bind(after)
+        .bind(limit as i64)
+        .fetch_all(self.0.read_pool())
+        .await
+        .map_err(db_error)?;
+
+        let next_after = match ids.last() {
+            Some(last) if ids.len() == limit as usize => last.to_string(),
+            _ => String::new(),
+        };
+        Ok(Response::new(proto::ListFollowingResponse {
+            user_ids: ids.iter().map(Uuid::to_string).collect(),
+            next_after,
+        }))
+    }
+}
+
+// ============================================================================
+// Messaging
+// ============================================================================
+
+fn message_to_proto(message: MessageResponse) -> proto::Message {
+    proto::Message {
+        id: message.id.to_string(),
+        chat_room_id: message.chat_room_id.to_string(),
+        sender_id: message.sender_id.to_string(),
+        sender_username: message.sender_username,
+        message_type: message.message_type,
+        content: message.content,
+        media_url: message.media_url,
+        media_thumbnail_url: message.media_thumbnail_url,
+        view_once: message.view_once,
+        expires_at: message.expires_at.map(crate::timestamps::format),
+        created_at: crate::timestamps::format(message.created_at),
+    }
+}
+
+#[tonic::async_trait]
+impl Messaging for Service {
+    async fn send_message(
+        &self,
+        request: Request<proto::SendMessageRequest>,
+    ) -> Result<Response<proto::Message>, Status> {
+        let request = request.into_inner();
+        let sender_id = parse_id("sender_id", &request.sender_id)?;
+        let chat_room_id = parse_id("chat_room_id", &request.chat_room_id)?;
+        let state = &self.0;
+
+        let is_member = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM chat_members WHERE chat_room_id = $1 AND user_id = $2)"
+        )
+        .bind(chat_room_id)
+        .bind(sender_id)
+        .fetch_one(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+        if !is_member {
+            return Err(Status::permission_denied("Sender is not a member of this chat"));
+        }
+
+        let message = OutgoingMessage {
+            chat_room_id,
+            message_type: request.message_type,
+            content: request.content,
+            media_url: request.media_url,
+            media_thumbnail_url: request.media_thumbnail_url,
+            view_once: false,
+            expires_at: None,
+            client_msg_id: None,
+        };
+        message.validate().map_err(Status::invalid_argument)?;
+        crate::message_requests::check_send(&state.pool, sender_id, chat_room_id)
+            .await
+            .map_err(|(status, message)| from_http(status, message))?;
+        crate::channels::check_send(&state.pool, sender_id, chat_room_id)
+            .await
+            .map_err(|(status, message)| from_http(status, message))?;
+
+        let response = crate::chat::deliver_message(
+            &state.pool,
+            &state.redis,
+            &state.connections,
+            &state.media_service,
+            sender_id,
+            message,
+        )
+        .await
+        .map_err(db_error)?;
+        Ok(Response::new(message_to_proto(response)))
+    }
+
+    async fn get_message(&self, request: Request<proto::GetMessageRequest>) -> Result<Response<proto::Message>, Status> {
+        let id = parse_id("id", &request.get_ref().id)?;
+        let mut message = sqlx::query_as::<_, MessageResponse>(
+            r#"
+            SELECT m.id, m.chat_room_id, m.sender_id, u.username AS sender_username,
+                   m.message_type, m.content, m.media_url, m.media_thumbnail_url,
+                   m.view_once, m.expires_at IS NOT NULL AS is_ephemeral, m.expires_at, m.created_at,
+                   FALSE AS is_viewed, FALSE AS is_read, FALSE AS is_saved
+            FROM messages m
+            JOIN users u ON u.id = m.sender_id
+            WHERE m.id = $1
+            "#
+        )
+        .bind(id)
+        .fetch_optional(self.0.pool.as_ref())
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| Status::not_found("Message not found"))?;
+
+        let media = &self.0.media_service;
+        media.sign_opt(&mut message.media_url).await;
+        media.sign_opt(&mut message.media_thumbnail_url).await;
+        Ok(Response::new(message_to_proto(message)))
+    }
+}
+
+// ============================================================================
+// Media
+// ============================================================================
+
+#[derive(FromRow)]
+struct ObjectRow {
+    sha256: String,
+    s3_key: String,
+    content_type: Option<String>,
+    size_bytes: i64,
+    refcount: i32,
+    scan_status: String,
+    uploaded_by: Option<Uuid>,
+    created_at: NaiveDateTime,
+}
+
+#[tonic::async_trait]
+impl Media for Service {
+    async fn get_object(&self, request: Request<proto::GetObjectRequest>) -> Result<Response<proto::MediaObject>, Status> {
+        let url = request.into_inner().url;
+        let media = &self.0.media_service;
+        // Bare object keys are looked up as they are
+        let key = if url.contains("://") || url.starts_with('/') {
+            media
+                .s3_key_from_url(&media.canonical_url(&url))
+                .ok_or_else(|| Status::invalid_argument("Not a media URL"))?
+        } else {
+            url
+        };
+
+        let object = sqlx::query_as::<_, ObjectRow>(
+            r#"
+            SELECT sha256, s3_key, content_type, size_bytes, refcount, scan_status, uploaded_by, created_at
+            FROM media_objects
+            WHERE s3_key = $1
+            "#
+        )
+        .bind(&key)
+        .fetch_optional(self.0.pool.as_ref())
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| Status::not_found("Media object not found"))?;
+
+        Ok(Response::new(proto::MediaObject {
+            s3_key: object.s3_key,
+            sha256: object.sha256,
+            content_type: object.content_type,
+            size_bytes: object.size_bytes,
+            refcount: object.refcount,
+            scan_status: object.scan_status,
+            uploaded_by: object.uploaded_by.map(|id| id.to_string()),
+            created_at: crate::timestamps::format(object.created_at),
+        }))
+    }
+
+    async fn sign_urls(
+        &self,
+        request: Request<proto::SignUrlsRequest>,
+    ) -> Result<Response<proto::SignUrlsResponse>, Status> {
+        let urls = request.into_inner().urls;
+        if urls.len() > MAX_BATCH {
+            return Err(Status::invalid_argument(format!("At most {} URLs per call", MAX_BATCH)));
+        }
+        let mut signed = Vec::with_capacity(urls.len());
+        for url in &urls {
+            signed.push(self.0.media_service.sign_url(url).await);
+        }
+        Ok(Response::new(proto::SignUrlsResponse { urls: signed }))
+    }
+}
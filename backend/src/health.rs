@@ -0,0 +1,117 @@
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::AppState;
+
+// Health endpoints for the load balancer and orchestrator.
+//   /health/live  - the process is up and serving; never touches dependencies,
+//                   so a database outage doesn't get every instance restarted
+//   /health/ready - Postgres, Redis and the media bucket each answer within
+//                   CHECK_TIMEOUT; 503 with per-dependency status otherwise
+// /health is kept as an alias of /health/live for existing probes. The read
+// replica is reported but never fails readiness, since reads fall back to the
+// primary while it's down.
+
+const SERVICE: &str = "relays.social";
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Serialize)]
+pub struct DependencyStatus {
+    status: &'static str,
+    /// Unset for the replica, whose status comes from its background check
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl DependencyStatus {
+    fn is_up(&self) -> bool {
+        self.status == "up"
+    }
+}
+
+#[derive(Serialize)]
+pub struct Dependencies {
+    postgres: DependencyStatus,
+    redis: DependencyStatus,
+    storage: DependencyStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    read_replica: Option<DependencyStatus>,
+}
+
+#[derive(Serialize)]
+pub struct Readiness {
+    status: &'static str,
+    service: &'static str,
+    timestamp: String,
+    dependencies: Dependencies,
+}
+
+async fn check<F, E>(probe: F) -> DependencyStatus
+where
+    F: Future<Output = Result<(), E>>,
+    E: std::fmt::Display,
+{
+    let started = Instant::now();
+    let result = tokio::time::timeout(CHECK_TIMEOUT, probe).await;
+    let latency_ms = Some(started.elapsed().as_millis() as u64);
+    let error = match result {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(_) => Some(format!("No answer within {}s", CHECK_TIMEOUT.as_secs())),
+    };
+    DependencyStatus {
+        status: if error.is_none() { "up" } else { "down" },
+        latency_ms,
+        error,
+    }
+}
+
+pub async fn live() -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "status": "healthy",
+        "service": SERVICE,
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    }))
+}
+
+pub async fn ready(State(state): State<Arc<AppState>>) -> (StatusCode, Json<Readiness>) {
+    let postgres = check(async {
+        sqlx::query_scalar::<_, i32>("SELECT 1").fetch_one(state.pool.as_ref()).await.map(|_| ())
+    });
+    // The lock is part of the wait: a connection stuck on a dead server holds it
+    let redis = check(async { state.redis.lock().await.ping().await });
+    let storage = check(state.media_service.storage.check());
+    let (postgres, redis, storage) = tokio::join!(postgres, redis, storage);
+
+    let read_replica = state.read_replica.as_ref().map(|replica| DependencyStatus {
+        status: if replica.is_healthy() { "up" } else { "down" },
+        latency_ms: None,
+        error: (!replica.is_healthy()).then(|| "Unreachable or lagging; reads go to the primary".to_string()),
+    });
+
+    let ready = postgres.is_up() && redis.is_up() && storage.is_up();
+    if !ready {
+        tracing::warn!(
+            postgres = postgres.status,
+            redis = redis.status,
+            storage = storage.status,
+            "readiness check failed"
+        );
+    }
+
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (
+        status,
+        Json(Readiness {
+            status: if ready { "ready" } else { "unavailable" },
+            service: SERVICE,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            dependencies: Dependencies { postgres, redis, storage, read_replica },
+        }),
+    )
+}
@@ -0,0 +1,265 @@
+use axum::{
+    body::{to_bytes, Body, HttpBody},
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+// Server messages in the reader's language. Catalogs live in locales/<lang>.json
+// and are compiled in:
+//   messages  system notification texts by key, with {name} arguments
+//   errors    translations of the English error strings handlers return,
+//             keyed by the English text; {} stands for a formatted value
+// Requests get a locale from Accept-Language. Error bodies are translated on
+// the way out by `localize`; notifications are stored with their key and
+// arguments and rendered when listed, in the reader's saved locale if they
+// have one. Anything missing from a catalog stays in English.
+
+pub const DEFAULT_LOCALE: &str = "en";
+
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.json")),
+    ("es", include_str!("../locales/es.json")),
+    ("fr", include_str!("../locales/fr.json")),
+    ("de", include_str!("../locales/de.json")),
+    ("pt", include_str!("../locales/pt.json")),
+];
+
+// Error bodies larger than this are passed through untranslated
+const MAX_TRANSLATED_BODY: usize = 64 * 1024;
+
+#[derive(Default, Deserialize)]
+struct Catalog {
+    #[serde(default)]
+    messages: HashMap<String, String>,
+    #[serde(default)]
+    errors: HashMap<String, String>,
+}
+
+fn catalogs() -> &'static HashMap<&'static str, Catalog> {
+    static CATALOGS_BY_LOCALE: OnceLock<HashMap<&'static str, Catalog>> = OnceLock::new();
+    CATALOGS_BY_LOCALE.get_or_init(|| {
+        CATALOGS
+            .iter()
+            .map(|(locale, json)| {
+                let catalog = serde_json::from_str(json)
+                    .unwrap_or_else(|e| panic!("Invalid message catalog locales/{}.json: {}", locale, e));
+                (*locale, catalog)
+            })
+            .collect()
+    })
+}
+
+/// Locales with a catalog
+pub fn available() -> Vec<&'static str> {
+    CATALOGS.iter().map(|(locale, _)| *locale).collect()
+}
+
+/// The supported locale for a language tag ("pt-BR" -> "pt"), if any
+pub fn supported(tag: &str) -> Option<&'static str> {
+    let primary = tag.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+    CATALOGS.iter().map(|(locale, _)| *locale).find(|locale| *locale == primary)
+}
+
+/// Best supported locale for an Accept-Language header, by q-value
+pub fn negotiate(accept_language: &str) -> Option<&'static str> {
+    let mut ranges: Vec<(&str, f32)> = accept_language
+        .split(',')
+        .filter_map(|range| {
+            let mut params = range.split(';').map(str::trim);
+            let tag = params.next().filter(|tag| !tag.is_empty())?;
+            let q = params
+                .find_map(|p| p.strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.parse::<f32>().ok())?;
+            (q > 0.0).then_some((tag, q))
+        })
+        .collect();
+    // Stable, so equal weights keep the client's order
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranges.into_iter().find_map(|(tag, _)| supported(tag))
+}
+
+tokio::task_local! {
+    static LOCALE: &'static str;
+}
+
+/// Locale of the current request; the default outside one
+pub fn current() -> &'static str {
+    LOCALE.try_with(|locale| *locale).unwrap_or(DEFAULT_LOCALE)
+}
+
+/// A user's saved locale, or the current request's when they haven't picked one
+pub async fn user_locale(pool: &PgPool, user_id: Uuid) -> &'static str {
+    let saved = sqlx::query_scalar::<_, Option<String>>("SELECT locale FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to load locale of {}: {}", user_id, e);
+            None
+        })
+        .flatten();
+
+    saved.as_deref().and_then(supported).unwrap_or_else(current)
+}
+
+/// Render the message `key` in `locale`, falling back to English and then to
+/// the key itself. `{name}` is replaced with the string or number in `args`.
+pub fn render(locale: &str, key: &str, args: &serde_json::Value) -> String {
+    let catalogs = catalogs();
+    let template = [locale, DEFAULT_LOCALE]
+        .iter()
+        .find_map(|locale| catalogs.get(locale)?.messages.get(key))
+        .map(String::as_str)
+        .unwrap_or(key);
+    fill(template, args)
+}
+
+/// Replace each `{name}` in `template` with the string or number in `args`
+pub fn fill(template: &str, args: &serde_json::Value) -> String {
+    let Some(args) = args.as_object() else {
+        return template.to_string();
+    };
+    args.iter().fold(template.to_string(), |text, (name, value)| {
+        let value = match value {
+            serde_json::Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        text.replace(&format!("{{{}}}", name), &value)
+    })
+}
+
+/// A system message: a catalog key plus its arguments, kept together so the
+/// text can be rendered again in another language later
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub key: &'static str,
+    pub args: serde_json::Value,
+}
+
+impl Message {
+    pub fn new(key: &'static str) -> Self {
+        Self { key, args: serde_json::json!({}) }
+    }
+
+    pub fn arg(mut self, name: &str, value: impl ToString) -> Self {
+        self.args[name] = serde_json::Value::String(value.to_string());
+        self
+    }
+
+    /// The English text, stored alongside the key
+    pub fn text(&self) -> String {
+        render(DEFAULT_LOCALE, self.key, &self.args)
+    }
+}
+
+/// Translate an English error string into `locale`
+pub fn translate_error(locale: &str, text: &str) -> Option<String> {
+    let errors = &catalogs().get(locale)?.errors;
+    if let Some(translated) = errors.get(text) {
+        return Some(translated.clone());
+    }
+
+    errors.iter().find_map(|(english, translated)| {
+        let values = match_pattern(english, text)?;
+        let mut parts = translated.split("{}");
+        let mut out = parts.next().unwrap_or_default().to_string();
+        let mut values = values.into_iter();
+        for part in parts {
+            out.push_str(values.next().unwrap_or_default());
+            out.push_str(part);
+        }
+        Some(out)
+    })
+}
+
+/// The values standing in for each {} when `text` matches `pattern`
+fn match_pattern<'a>(pattern: &str, text: &'a str) -> Option<Vec<&'a str>> {
+    if !pattern.contains("{}") {
+        return None;
+    }
+    let mut parts = pattern.split("{}");
+    let mut rest = text.strip_prefix(parts.next()?)?;
+    let parts: Vec<&str> = parts.collect();
+    let mut values = Vec::with_capacity(parts.len());
+    for (i, part) in parts.iter().enumerate() {
+        let end = if i == parts.len() - 1 {
+            rest.strip_suffix(part)?.len()
+        } else {
+            rest.find(part).filter(|_| !part.is_empty())?
+        };
+        let value = &rest[..end];
+        if value.is_empty() {
+            return None;
+        }
+        values.push(value);
+        rest = &rest[end + part.len()..];
+    }
+    Some(values)
+}
+
+/// Middleware: pick the request's locale from Accept-Language and translate
+/// plain-text error bodies (and the "message" of JSON ones) into it
+pub async fn localize(request: Request, next: Next) -> Response {
+    let locale = request
+        .headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(negotiate)
+        .unwrap_or(DEFAULT_LOCALE);
+    let is_api = request.uri().path().starts_with("/api");
+
+    let mut response = LOCALE.scope(locale, next.run(request)).await;
+    if is_api {
+        response.headers_mut().append(header::VARY, HeaderValue::from_static("accept-language"));
+    }
+
+    let status = response.status();
+    let small = response.body().size_hint().upper().is_some_and(|n| n <= MAX_TRANSLATED_BODY as u64);
+    if locale == DEFAULT_LOCALE || !(status.is_client_error() || status.is_server_error()) || !small {
+        return response;
+    }
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    if !content_type.starts_with("text/plain") && !content_type.starts_with("application/json") {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, MAX_TRANSLATED_BODY).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("❌ Failed to buffer error response for translation: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let translated = if content_type.starts_with("text/plain") {
+        std::str::from_utf8(&bytes).ok().and_then(|text| translate_error(locale, text))
+    } else {
+        serde_json::from_slice::<serde_json::Value>(&bytes).ok().and_then(|mut json| {
+            let message = json.get("message")?.as_str()?;
+            json["message"] = serde_json::Value::String(translate_error(locale, message)?);
+            Some(json.to_string())
+        })
+    };
+
+    match translated {
+        Some(translated) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            parts.headers.insert(header::CONTENT_LANGUAGE, HeaderValue::from_static(locale));
+            Response::from_parts(parts, Body::from(translated))
+        }
+        None => Response::from_parts(parts, Body::from(bytes)),
+    }
+}
@@ -0,0 +1,143 @@
+use axum::http::{HeaderMap, StatusCode};
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+// Retries with the same key within this window replay the original response
+pub const IDEMPOTENCY_WINDOW_HOURS: i64 = 24;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+const MAX_KEY_LENGTH: usize = 255;
+
+// Scopes keep keys for different kinds of resources apart
+pub const SCOPE_SEND_MESSAGE: &str = "send_message";
+pub const SCOPE_CREATE_STORY: &str = "create_story";
+
+pub enum Reservation<T> {
+    /// First time this key is seen: process the request, then `complete` it
+    New,
+    /// The request already succeeded; return this instead of creating a duplicate
+    Replay(T),
+    /// Another request with this key hasn't finished yet
+    InProgress,
+}
+
+/// Read and validate the `Idempotency-Key` header, if the client sent one
+pub fn key_from_headers(headers: &HeaderMap) -> Result<Option<String>, StatusCode> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+
+    let key = value.to_str().map_err(|_| StatusCode::BAD_REQUEST)?.trim();
+    validate_key(key).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    Ok(Some(key.to_string()))
+}
+
+pub fn validate_key(key: &str) -> Result<(), String> {
+    if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+        return Err(format!("Idempotency key must be 1 to {} characters", MAX_KEY_LENGTH));
+    }
+    Ok(())
+}
+
+/// Claim `key` for this request. Keys older than the window are reclaimed,
+/// so a key can be reused after 24h.
+pub async fn reserve<T: DeserializeOwned>(
+    pool: &PgPool,
+    user_id: Uuid,
+    scope: &str,
+    key: &str,
+) -> Result<Reservation<T>, sqlx::Error> {
+    let claimed = sqlx::query_scalar::<_, i32>(
+        r#"
+        INSERT INTO idempotency_keys (user_id, scope, idempotency_key)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, scope, idempotency_key) DO UPDATE
+            SET response = NULL, created_at = NOW()
+            WHERE idempotency_keys.created_at < NOW() - make_interval(hours => $4)
+        RETURNING 1
+        "#
+    )
+    .bind(user_id)
+    .bind(scope)
+    .bind(key)
+    .bind(IDEMPOTENCY_WINDOW_HOURS as i32)
+    .fetch_optional(pool)
+    .await?;
+
+    if claimed.is_some() {
+        return Ok(Reservation::New);
+    }
+
+    let response = sqlx::query_scalar::<_, Option<serde_json::Value>>(
+        "SELECT response FROM idempotency_keys WHERE user_id = $1 AND scope = $2 AND idempotency_key = $3"
+    )
+    .bind(user_id)
+    .bind(scope)
+    .bind(key)
+    .fetch_optional(pool)
+    .await?
+    .flatten();
+
+    match response.map(serde_json::from_value::<T>) {
+        Some(Ok(original)) => Ok(Reservation::Replay(original)),
+        Some(Err(e)) => {
+            tracing::error!("Stored idempotent response for {} is unreadable: {}", scope, e);
+            Ok(Reservation::InProgress)
+        }
+        None => Ok(Reservation::InProgress),
+    }
+}
+
+/// Store the response for a reserved key so retries get it back
+pub async fn complete<T: Serialize>(
+    pool: &PgPool,
+    user_id: Uuid,
+    scope: &str,
+    key: &str,
+    response: &T,
+) -> Result<(), sqlx::Error> {
+    let response = serde_json::to_value(response).unwrap_or(serde_json::Value::Null);
+
+    sqlx::query(
+        "UPDATE idempotency_keys SET response = $4 WHERE user_id = $1 AND scope = $2 AND idempotency_key = $3"
+    )
+    .bind(user_id)
+    .bind(scope)
+    .bind(key)
+    .bind(response)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Drop a reservation after the request failed so the client can retry
+pub async fn release(pool: &PgPool, user_id: Uuid, scope: &str, key: &str) {
+    let result = sqlx::query(
+        "DELETE FROM idempotency_keys WHERE user_id = $1 AND scope = $2 AND idempotency_key = $3 AND response IS NULL"
+    )
+    .bind(user_id)
+    .bind(scope)
+    .bind(key)
+    .execute(pool)
+    .await;
+
+    if let Err(e) = result {
+        tracing::error!("Failed to release idempotency key for {}: {}", scope, e);
+    }
+}
+
+/// Delete keys past the replay window (run by the expiration sweep)
+pub async fn purge_expired(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "DELETE FROM idempotency_keys WHERE created_at < NOW() - make_interval(hours => $1)"
+    )
+    .bind(IDEMPOTENCY_WINDOW_HOURS as i32)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
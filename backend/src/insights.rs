@@ -0,0 +1,492 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::AppState;
+
+// Insights for creator and business accounts. Story views and profile visits
+// record the viewer's country and device; the roll_up_insights job folds them
+// into per-day aggregates (migration 045) before the stories expire, and the
+// insights endpoint only reads those aggregates.
+
+pub const ACCOUNT_TYPES: [&str; 3] = ["personal", "creator", "business"];
+// Raw profile visits and per-day reach rows are kept this long
+const RAW_RETENTION_DAYS: i32 = 90;
+const TOP_STORIES: i64 = 10;
+// Views committed late must still land in a later roll-up window
+const ROLLUP_LAG_SECS: f64 = 60.0;
+
+/// mobile, tablet or desktop, from the User-Agent
+pub fn device_type(headers: &HeaderMap) -> &'static str {
+    let user_agent = headers
+        .get(axum::http::header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("unknown");
+
+    if user_agent.contains("Mobile") || user_agent.contains("Android") || user_agent.contains("iPhone") {
+        "mobile"
+    } else if user_agent.contains("Tablet") || user_agent.contains("iPad") {
+        "tablet"
+    } else {
+        "desktop"
+    }
+}
+
+/// Note a visit to a creator or business profile; other profiles are skipped
+pub async fn record_profile_visit(pool: &PgPool, profile_id: Uuid, visitor_id: Uuid, headers: &HeaderMap) {
+    if profile_id == visitor_id {
+        return;
+    }
+    let result = sqlx::query(
+        r#"
+        INSERT INTO profile_visits (profile_id, visitor_id, country, device_type)
+        SELECT id, $2, $3, $4 FROM users
+        WHERE id = $1 AND account_type <> 'personal'
+          AND EXISTS(SELECT 1 FROM users WHERE id = $2)
+        "#
+    )
+    .bind(profile_id)
+    .bind(visitor_id)
+    .bind(crate::takedowns::request_country(headers))
+    .bind(device_type(headers))
+    .execute(pool)
+    .await;
+
+    if let Err(e) = result {
+        tracing::warn!(profile_id = %profile_id, error = %e, "failed to record profile visit");
+    }
+}
+
+/// Fold story views and profile visits since the last roll-up into the
+/// insights aggregates, refresh follower counts and story totals for today,
+/// and prune raw rows past retention
+pub async fn roll_up(pool: &PgPool) -> Result<Option<serde_json::Value>, String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+    let (from, to) = sqlx::query_as::<_, (NaiveDateTime, NaiveDateTime)>(
+        "SELECT rolled_up_to, NOW()::timestamp - make_interval(secs => $1) FROM insights_rollup FOR UPDATE"
+    )
+    .bind(ROLLUP_LAG_SECS)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let views = if to > from {
+        roll_up_window(&mut tx, from, to).await.map_err(|e| e.to_string())?
+    } else {
+        0
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO insights_daily (user_id, date, followers, new_followers, reach)
+        SELECT u.id, CURRENT_DATE, COALESCE(u.follower_count, 0),
+               (SELECT COUNT(*) FROM follows WHERE following_id = u.id AND created_at >= CURRENT_DATE),
+               (SELECT COUNT(*) FROM insights_reach WHERE user_id = u.id AND date = CURRENT_DATE)
+        FROM users u
+        WHERE u.account_type <> 'personal'
+        ON CONFLICT (user_id, date) DO UPDATE
+        SET followers = EXCLUDED.followers, new_followers = EXCLUDED.new_followers, reach = EXCLUDED.reach
+        "#
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // Yesterday's reach may have grown from views committed after midnight
+    sqlx::query(
+        r#"
+        UPDATE insights_daily d
+        SET reach = (SELECT COUNT(*) FROM insights_reach r WHERE r.user_id = d.user_id AND r.date = d.date)
+        WHERE d.date = CURRENT_DATE - 1
+        "#
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO story_insights (story_id, user_id, media_type, caption, views, likes, comments, posted_at)
+        SELECT s.id, s.user_id, s.media_type, s.caption,
+               (SELECT COUNT(*) FROM story_views sv WHERE sv.story_id = s.id AND sv.viewer_id <> s.user_id),
+               COALESCE(s.like_count, 0), COALESCE(s.comment_count, 0), s.created_at
+        FROM stories s
+        JOIN users u ON u.id = s.user_id
+        WHERE u.account_type <> 'personal'
+        ON CONFLICT (story_id) DO UPDATE
+        SET caption = EXCLUDED.caption, views = EXCLUDED.views, likes = EXCLUDED.likes,
+            comments = EXCLUDED.comments, updated_at = NOW()
+        "#
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    sqlx::query("DELETE FROM insights_reach WHERE date < CURRENT_DATE - $1")
+        .bind(RAW_RETENTION_DAYS)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    sqlx::query("DELETE FROM profile_visits WHERE visited_at < NOW() - make_interval(days => $1)")
+        .bind(RAW_RETENTION_DAYS)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(Some(serde_json::json!({ "views_rolled_up": views })))
+}
+
+async fn roll_up_window(
+    conn: &mut sqlx::PgConnection,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> Result<i64, sqlx::Error> {
+    // Views of creator and business stories in the window, the author's own excluded
+    sqlx::query(
+        r#"
+        CREATE TEMP TABLE window_views ON COMMIT DROP AS
+        SELECT s.user_id, sv.viewer_id, sv.viewed_at::date AS date,
+               EXTRACT(HOUR FROM sv.viewed_at)::int AS hour,
+               COALESCE(sv.country, 'unknown') AS country,
+               COALESCE(sv.device_type, 'unknown') AS device_type
+        FROM story_views sv
+        JOIN stories s ON s.id = sv.story_id
+        JOIN users u ON u.id = s.user_id
+        WHERE u.account_type <> 'personal'
+          AND sv.viewer_id <> s.user_id
+          AND sv.viewed_at > $1 AND sv.viewed_at <= $2
+        "#
+    )
+    .bind(from)
+    .bind(to)
+    .execute(&mut *conn)
+    .await?;
+
+    let views = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM window_views")
+        .fetch_one(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO insights_daily (user_id, date, story_views)
+        SELECT user_id, date, COUNT(*) FROM window_views GROUP BY user_id, date
+        ON CONFLICT (user_id, date) DO UPDATE
+        SET story_views = insights_daily.story_views + EXCLUDED.story_views
+        "#
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO insights_breakdown (user_id, date, dimension, value, views)
+        SELECT user_id, date, dimension, value, COUNT(*)
+        FROM (
+            SELECT user_id, date, 'hour' AS dimension, hour::text AS value FROM window_views
+            UNION ALL
+            SELECT user_id, date, 'country', country FROM window_views
+            UNION ALL
+            SELECT user_id, date, 'device', device_type FROM window_views
+        ) v
+        GROUP BY user_id, date, dimension, value
+        ON CONFLICT (user_id, date, dimension, value) DO UPDATE
+        SET views = insights_breakdown.views + EXCLUDED.views
+        "#
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO insights_reach (user_id, date, viewer_id)
+        SELECT DISTINCT user_id, date, viewer_id FROM window_views
+        ON CONFLICT DO NOTHING
+        "#
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO insights_daily (user_id, date, profile_visits)
+        SELECT profile_id, visited_at::date, COUNT(*)
+        FROM profile_visits
+        WHERE visited_at > $1 AND visited_at <= $2
+        GROUP BY profile_id, visited_at::date
+        ON CONFLICT (user_id, date) DO UPDATE
+        SET profile_visits = insights_daily.profile_visits + EXCLUDED.profile_visits
+        "#
+    )
+    .bind(from)
+    .bind(to)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query("UPDATE insights_rollup SET rolled_up_to = $1")
+        .bind(to)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(views)
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AccountTypeInput {
+    /// personal, creator or business
+    pub account_type: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AccountTypeResponse {
+    pub account_type: String,
+}
+
+// Switch between a personal, creator and business account
+#[utoipa::path(
+    put,
+    path = "/api/v1/users/{user_id}/account-type",
+    tag = "insights",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    request_body = AccountTypeInput,
+    responses(
+        (status = 200, body = AccountTypeResponse),
+        (status = 400, description = "Unknown account type"),
+        (status = 403, description = "Not your account"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn set_account_type(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+    Json(input): Json<AccountTypeInput>,
+) -> Result<Json<AccountTypeResponse>, (StatusCode, String)> {
+    if user.id != user_id {
+        return Err((StatusCode::FORBIDDEN, "Not your account".to_string()));
+    }
+    let account_type = input.account_type.trim().to_lowercase();
+    if !ACCOUNT_TYPES.contains(&account_type.as_str()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("account_type must be one of: {}", ACCOUNT_TYPES.join(", ")),
+        ));
+    }
+
+    sqlx::query("UPDATE users SET account_type = $2 WHERE id = $1")
+        .bind(user_id)
+        .bind(&account_type)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(AccountTypeResponse { account_type }))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct InsightsQuery {
+    /// Days to cover, today included (default 30, max 90)
+    pub days: Option<i32>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct InsightsDay {
+    pub date: NaiveDate,
+    pub followers: i32,
+    pub new_followers: i32,
+    pub story_views: i32,
+    pub reach: i32,
+    pub profile_visits: i32,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct ViewsBreakdown {
+    /// Hour of day (UTC, 0-23), country code or device type
+    pub value: String,
+    pub views: i64,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct TopStory {
+    pub story_id: Uuid,
+    pub media_type: String,
+    pub caption: Option<String>,
+    pub views: i32,
+    pub likes: i32,
+    pub comments: i32,
+    #[serde(with = "crate::timestamps")]
+    pub posted_at: NaiveDateTime,
+    /// Still live (not expired or deleted)
+    pub is_live: bool,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct InsightsSummary {
+    /// Distinct accounts that viewed a story in the period
+    pub reach: i64,
+    pub story_views: i64,
+    pub profile_visits: i64,
+    pub followers: i32,
+    /// Follower change over the period
+    pub follower_growth: i32,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct InsightsResponse {
+    pub account_type: String,
+    pub days: i32,
+    pub summary: InsightsSummary,
+    /// Oldest first; the follower growth curve
+    pub daily: Vec<InsightsDay>,
+    pub views_by_hour: Vec<ViewsBreakdown>,
+    pub views_by_country: Vec<ViewsBreakdown>,
+    pub views_by_device: Vec<ViewsBreakdown>,
+    /// Most viewed stories posted in the period
+    pub top_stories: Vec<TopStory>,
+    /// Views and visits up to this time are included
+    #[serde(with = "crate::timestamps")]
+    pub updated_at: NaiveDateTime,
+}
+
+async fn breakdown(pool: &PgPool, user_id: Uuid, days: i32, dimension: &str) -> Result<Vec<ViewsBreakdown>, sqlx::Error> {
+    sqlx::query_as::<_, ViewsBreakdown>(
+        r#"
+        SELECT value, SUM(views)::bigint AS views
+        FROM insights_breakdown
+        WHERE user_id = $1 AND dimension = $3 AND date > CURRENT_DATE - $2
+        GROUP BY value
+        ORDER BY views DESC, value
+        "#
+    )
+    .bind(user_id)
+    .bind(days)
+    .bind(dimension)
+    .fetch_all(pool)
+    .await
+}
+
+// Insights for the caller's creator or business account
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/{user_id}/insights",
+    tag = "insights",
+    params(("user_id" = Uuid, Path, description = "User ID"), InsightsQuery),
+    responses(
+        (status = 200, body = InsightsResponse),
+        (status = 403, description = "Not your account, or not a creator or business account"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_insights(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+    Query(params): Query<InsightsQuery>,
+) -> Result<Json<InsightsResponse>, (StatusCode, String)> {
+    if user.id != user_id {
+        return Err((StatusCode::FORBIDDEN, "Not your account".to_string()));
+    }
+    let pool = state.read_pool();
+    let internal = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+
+    let account_type = sqlx::query_scalar::<_, String>("SELECT account_type FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .map_err(internal)?;
+    if account_type == "personal" {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Insights are available to creator and business accounts".to_string(),
+        ));
+    }
+    let days = params.days.unwrap_or(30).clamp(1, RAW_RETENTION_DAYS);
+
+    let daily = sqlx::query_as::<_, InsightsDay>(
+        r#"
+        SELECT date, followers, new_followers, story_views, reach, profile_visits
+        FROM insights_daily
+        WHERE user_id = $1 AND date > CURRENT_DATE - $2
+        ORDER BY date
+        "#
+    )
+    .bind(user_id)
+    .bind(days)
+    .fetch_all(pool)
+    .await
+    .map_err(internal)?;
+
+    let summary = sqlx::query_as::<_, InsightsSummary>(
+        r#"
+        SELECT
+            (SELECT COUNT(DISTINCT viewer_id) FROM insights_reach WHERE user_id = $1 AND date > CURRENT_DATE - $2) AS reach,
+            COALESCE((SELECT SUM(story_views) FROM insights_daily WHERE user_id = $1 AND date > CURRENT_DATE - $2), 0)::bigint AS story_views,
+            COALESCE((SELECT SUM(profile_visits) FROM insights_daily WHERE user_id = $1 AND date > CURRENT_DATE - $2), 0)::bigint AS profile_visits,
+            COALESCE(u.follower_count, 0) AS followers,
+            COALESCE(u.follower_count, 0) - COALESCE(
+                (SELECT followers FROM insights_daily
+                 WHERE user_id = $1 AND date <= CURRENT_DATE - $2 AND followers > 0
+                 ORDER BY date DESC LIMIT 1),
+                (SELECT followers FROM insights_daily
+                 WHERE user_id = $1 AND date > CURRENT_DATE - $2 AND followers > 0
+                 ORDER BY date LIMIT 1),
+                COALESCE(u.follower_count, 0)
+            ) AS follower_growth
+        FROM users u
+        WHERE u.id = $1
+        "#
+    )
+    .bind(user_id)
+    .bind(days)
+    .fetch_one(pool)
+    .await
+    .map_err(internal)?;
+
+    let top_stories = sqlx::query_as::<_, TopStory>(
+        r#"
+        SELECT si.story_id, si.media_type, si.caption, si.views, si.likes, si.comments, si.posted_at,
+               EXISTS(SELECT 1 FROM stories s WHERE s.id = si.story_id AND s.expires_at > NOW()) AS is_live
+        FROM story_insights si
+        WHERE si.user_id = $1 AND si.posted_at > NOW() - make_interval(days => $2)
+        ORDER BY si.views DESC, si.likes DESC
+        LIMIT $3
+        "#
+    )
+    .bind(user_id)
+    .bind(days)
+    .bind(TOP_STORIES)
+    .fetch_all(pool)
+    .await
+    .map_err(internal)?;
+
+    let updated_at = sqlx::query_scalar::<_, NaiveDateTime>("SELECT rolled_up_to FROM insights_rollup")
+        .fetch_one(pool)
+        .await
+        .map_err(internal)?;
+
+    Ok(Json(InsightsResponse {
+        account_type,
+        days,
+        summary,
+        daily,
+        views_by_hour: breakdown(pool, user_id, days, "hour").await.map_err(internal)?,
+        views_by_country: breakdown(pool, user_id, days, "country").await.map_err(internal)?,
+        views_by_device: breakdown(pool, user_id, days, "device").await.map_err(internal)?,
+        top_stories,
+        updated_at,
+    }))
+}
@@ -0,0 +1,729 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::NaiveDateTime;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::permissions::{CanManagePlatform, Permitted};
+use crate::runtime_settings;
+use crate::AppState;
+
+// Invite-only signups (migration 073). While the signup.invite_only runtime
+// setting is 1, signup needs an invite code; with it off a code is optional
+// but still recorded. Admins generate codes in labelled batches, and every
+// user can create single-use codes up to their allowance: invites.per_user,
+// or users.invite_allowance when an admin set one. Redeeming a code uses it up
+// in the signup transaction, and invite_redemptions keeps who invited whom for
+// the invite graph stats.
+
+const CODE_LENGTH: usize = 10;
+// No 0/O or 1/I, so codes survive being read out loud
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const MAX_BATCH_SIZE: i64 = 1000;
+const MAX_LABEL_LENGTH: usize = 100;
+const TOP_INVITERS: i64 = 20;
+// Deep enough for any real invite chain; stops the graph walk on bad data
+const MAX_GENERATIONS: i32 = 100;
+
+const CODE_COLUMNS: &str = "c.id, c.code, c.max_uses, c.uses, c.expires_at, c.revoked_at, c.created_at";
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct InviteCode {
+    pub id: Uuid,
+    pub code: String,
+    pub max_uses: i32,
+    pub uses: i32,
+    #[serde(with = "crate::timestamps::option")]
+    pub expires_at: Option<NaiveDateTime>,
+    #[serde(with = "crate::timestamps::option")]
+    pub revoked_at: Option<NaiveDateTime>,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct InvitedUser {
+    pub user_id: Uuid,
+    pub username: String,
+    #[serde(with = "crate::timestamps")]
+    pub joined_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MyInvites {
+    /// Codes you may create in total
+    pub allowance: i64,
+    pub remaining: i64,
+    pub codes: Vec<InviteCode>,
+    /// People who signed up with your codes
+    pub invited: Vec<InvitedUser>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SignupMode {
+    /// Whether signup needs an invite code
+    pub invite_only: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ValidateInviteRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ValidateInviteResponse {
+    /// Whether the code can be used to sign up right now
+    pub valid: bool,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct InviteBatch {
+    pub id: Uuid,
+    pub label: String,
+    pub created_by: Option<Uuid>,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+    pub codes: i64,
+    /// Accounts created with the batch's codes
+    pub redemptions: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct InviteBatchDetail {
+    pub batch: InviteBatch,
+    pub codes: Vec<InviteCode>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateBatchRequest {
+    /// What the codes are for, e.g. "Press kit"
+    pub label: String,
+    /// Number of codes, 1 to 1000
+    pub count: i64,
+    /// Signups each code allows, 1 by default
+    pub max_uses: Option<i32>,
+    /// Days until the codes stop working; never when omitted
+    pub expires_in_days: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct InviteAllowanceRequest {
+    /// Codes the user may create in total; null to go back to invites.per_user
+    pub allowance: Option<i32>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct InviteGeneration {
+    /// 1 for people invited by admin batches or by accounts that needed no
+    /// invite, 2 for the people they invited, and so on
+    pub generation: i32,
+    pub users: i64,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct TopInviter {
+    pub user_id: Uuid,
+    pub username: String,
+    pub invited: i64,
+    /// Invitees who have posted a story or sent a message
+    pub active_invitees: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct InviteStats {
+    pub invite_only: bool,
+    pub codes: i64,
+    /// Codes used at least once
+    pub used_codes: i64,
+    pub redemptions: i64,
+    pub from_batches: i64,
+    pub from_users: i64,
+    pub generations: Vec<InviteGeneration>,
+    pub top_inviters: Vec<TopInviter>,
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    eprintln!("❌ Invite query failed: {:?}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+}
+
+fn generate_code() -> String {
+    let mut buf = [0u8; CODE_LENGTH];
+    OsRng.fill_bytes(&mut buf);
+    buf.iter()
+        .map(|b| CODE_ALPHABET[*b as usize % CODE_ALPHABET.len()] as char)
+        .collect()
+}
+
+fn normalize(code: &str) -> String {
+    code.trim().to_uppercase()
+}
+
+/// Whether signup currently needs an invite code
+pub fn invite_only() -> bool {
+    runtime_settings::int(runtime_settings::SIGNUP_INVITE_ONLY) == 1
+}
+
+/// A code taken by a signup in progress
+pub struct Claimed {
+    code_id: Uuid,
+    inviter_id: Option<Uuid>,
+    batch_id: Option<Uuid>,
+}
+
+/// Use up one signup from `code`. Run in the signup transaction, so the use
+/// is given back if the account isn't created.
+pub async fn claim<'e, E>(executor: E, code: &str) -> Result<Claimed, (StatusCode, String)>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    let claimed = sqlx::query_as::<_, (Uuid, Option<Uuid>, Option<Uuid>)>(
+        r#"
+        UPDATE invite_codes
+        SET uses = uses + 1
+        WHERE code = $1
+          AND uses < max_uses
+          AND revoked_at IS NULL
+          AND (expires_at IS NULL OR expires_at > NOW())
+        RETURNING id, owner_id, batch_id
+        "#
+    )
+    .bind(normalize(code))
+    .fetch_optional(executor)
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::BAD_REQUEST, "Invalid or used invite code".to_string()))?;
+
+    Ok(Claimed {
+        code_id: claimed.0,
+        inviter_id: claimed.1,
+        batch_id: claimed.2,
+    })
+}
+
+/// Record that the new account `user_id` signed up with a claimed code
+pub async fn record_redemption<'e, E>(executor: E, user_id: Uuid, claimed: &Claimed) -> Result<(), (StatusCode, String)>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    sqlx::query("INSERT INTO invite_redemptions (user_id, code_id, inviter_id, batch_id) VALUES ($1, $2, $3, $4)")
+        .bind(user_id)
+        .bind(claimed.code_id)
+        .bind(claimed.inviter_id)
+        .bind(claimed.batch_id)
+        .execute(executor)
+        .await
+        .map_err(db_error)?;
+    Ok(())
+}
+
+async fn allowance(pool: &PgPool, user_id: Uuid) -> Result<i64, sqlx::Error> {
+    let own = sqlx::query_scalar::<_, Option<i32>>("SELECT invite_allowance FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(own.map_or_else(|| runtime_settings::int(runtime_settings::INVITES_PER_USER), i64::from))
+}
+
+// Whether signup needs an invite code
+#[utoipa::path(
+    get,
+    path = "/api/v1/signup/mode",
+    tag = "auth",
+    responses((status = 200, body = SignupMode))
+)]
+pub async fn get_signup_mode() -> Json<SignupMode> {
+    Json(SignupMode { invite_only: invite_only() })
+}
+
+// Check an invite code before signing up with it
+#[utoipa::path(
+    post,
+    path = "/api/v1/invites/validate",
+    tag = "auth",
+    request_body = ValidateInviteRequest,
+    responses((status = 200, body = ValidateInviteResponse))
+)]
+pub async fn validate_invite(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ValidateInviteRequest>,
+) -> Result<Json<ValidateInviteResponse>, (StatusCode, String)> {
+    let valid = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM invite_codes
+            WHERE code = $1
+              AND uses < max_uses
+              AND revoked_at IS NULL
+              AND (expires_at IS NULL OR expires_at > NOW())
+        )
+        "#
+    )
+    .bind(normalize(&payload.code))
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+    Ok(Json(ValidateInviteResponse { valid }))
+}
+
+// Your invite codes, how many more you can create, and who joined with them
+#[utoipa::path(
+    get,
+    path = "/api/v1/invites/{user_id}",
+    tag = "users",
+    params(("user_id" = String, Path, description = "Your user ID")),
+    responses(
+        (status = 200, body = MyInvites),
+        (status = 403, description = "Not your account")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_my_invites(
+    State(state): State<Arc<AppState>>,
+    user: AuthUser,
+    Path(user_id): Path<String>,
+) -> Result<Json<MyInvites>, (StatusCode, String)> {
+    let user_id = crate::settings::require_self(&user, &user_id)?;
+    let pool = state.pool.as_ref();
+
+    let allowance = allowance(pool, user_id).await.map_err(db_error)?;
+    let codes = sqlx::query_as::<_, InviteCode>(&format!(
+        "SELECT {} FROM invite_codes c WHERE c.owner_id = $1 ORDER BY c.created_at DESC",
+        CODE_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)?;
+
+    let invited = sqlx::query_as::<_, InvitedUser>(
+        r#"
+        SELECT u.id AS user_id, u.username, r.created_at AS joined_at
+        FROM invite_redemptions r
+        JOIN users u ON u.id = r.user_id
+        WHERE r.inviter_id = $1
+        ORDER BY r.created_at DESC
+        "#
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(MyInvites {
+        allowance,
+        remaining: (allowance - codes.len() as i64).max(0),
+        codes,
+        invited,
+    }))
+}
+
+// Create a single-use invite code out of your allowance
+#[utoipa::path(
+    post,
+    path = "/api/v1/invites/{user_id}",
+    tag = "users",
+    params(("user_id" = String, Path, description = "Your user ID")),
+    responses(
+        (status = 200, body = InviteCode),
+        (status = 403, description = "Not your account"),
+        (status = 409, description = "No invites left")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn create_invite(
+    State(state): State<Arc<AppState>>,
+    user: AuthUser,
+    Path(user_id): Path<String>,
+) -> Result<Json<InviteCode>, (StatusCode, String)> {
+    let user_id = crate::settings::require_self(&user, &user_id)?;
+    let allowance = allowance(&state.pool, user_id).await.map_err(db_error)?;
+
+    loop {
+        let created = sqlx::query_as::<_, InviteCode>(&format!(
+            r#"
+            INSERT INTO invite_codes AS c (code, owner_id)
+            SELECT $1, $2
+            WHERE (SELECT COUNT(*) FROM invite_codes WHERE owner_id = $2) < $3
+            RETURNING {}
+            "#,
+            CODE_COLUMNS
+        ))
+        .bind(generate_code())
+        .bind(user_id)
+        .bind(allowance)
+        .fetch_optional(state.pool.as_ref())
+        .await;
+        match created {
+            Ok(Some(code)) => return Ok(Json(code)),
+            Ok(None) => return Err((StatusCode::CONFLICT, "You have no invites left".to_string())),
+            // Another code already has it; draw again
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => continue,
+            Err(e) => return Err(db_error(e)),
+        }
+    }
+}
+
+const BATCH_SUMMARY: &str = r#"
+    SELECT
+        b.id, b.label, b.created_by, b.created_at,
+        (SELECT COUNT(*) FROM invite_codes c WHERE c.batch_id = b.id) AS codes,
+        (SELECT COUNT(*) FROM invite_redemptions r WHERE r.batch_id = b.id) AS redemptions
+    FROM invite_batches b
+"#;
+
+async fn batch_detail(pool: &PgPool, batch_id: Uuid) -> Result<Option<InviteBatchDetail>, sqlx::Error> {
+    let Some(batch) = sqlx::query_as::<_, InviteBatch>(&format!("{} WHERE b.id = $1", BATCH_SUMMARY))
+        .bind(batch_id)
+        .fetch_optional(pool)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let codes = sqlx::query_as::<_, InviteCode>(&format!(
+        "SELECT {} FROM invite_codes c WHERE c.batch_id = $1 ORDER BY c.code",
+        CODE_COLUMNS
+    ))
+    .bind(batch_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(Some(InviteBatchDetail { batch, codes }))
+}
+
+/// Create a batch of `count` fresh codes
+pub(crate) async fn insert_batch(
+    conn: &mut sqlx::PgConnection,
+    label: &str,
+    created_by: Uuid,
+    count: i64,
+    max_uses: i32,
+    expires_in_days: Option<i32>,
+) -> Result<Uuid, sqlx::Error> {
+    let batch_id = sqlx::query_scalar::<_, Uuid>(
+        "INSERT INTO invite_batches (label, created_by) VALUES ($1, $2) RETURNING id"
+    )
+    .bind(label)
+    .bind(created_by)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    // Codes that collide with existing ones are skipped and drawn again
+    let mut created = 0;
+    while created < count {
+        let codes: Vec<String> = (created..count).map(|_| generate_code()).collect();
+        created += sqlx::query(
+            r#"
+            INSERT INTO invite_codes (code, batch_id, max_uses, expires_at)
+            SELECT code, $2, $3, NOW() + make_interval(days => $4)
+            FROM unnest($1::text[]) AS code
+            ON CONFLICT (code) DO NOTHING
+            "#
+        )
+        .bind(&codes)
+        .bind(batch_id)
+        .bind(max_uses)
+        .bind(expires_in_days)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as i64;
+    }
+    Ok(batch_id)
+}
+
+// Generate a batch of invite codes
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/invites/batches",
+    tag = "admin",
+    request_body = CreateBatchRequest,
+    responses(
+        (status = 200, body = InviteBatchDetail),
+        (status = 400, description = "Invalid label, count, uses or expiry"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn create_batch(
+    State(state): State<Arc<AppState>>,
+    admin: Permitted<CanManagePlatform>,
+    Json(payload): Json<CreateBatchRequest>,
+) -> Result<Json<InviteBatchDetail>, (StatusCode, String)> {
+    let label = payload.label.trim();
+    if label.is_empty() || label.chars().count() > MAX_LABEL_LENGTH {
+        return Err((StatusCode::BAD_REQUEST, format!("label must be 1-{} characters", MAX_LABEL_LENGTH)));
+    }
+    if !(1..=MAX_BATCH_SIZE).contains(&payload.count) {
+        return Err((StatusCode::BAD_REQUEST, format!("count must be between 1 and {}", MAX_BATCH_SIZE)));
+    }
+    let max_uses = payload.max_uses.unwrap_or(1);
+    if max_uses < 1 {
+        return Err((StatusCode::BAD_REQUEST, "max_uses must be at least 1".to_string()));
+    }
+    if payload.expires_in_days.is_some_and(|days| days < 1) {
+        return Err((StatusCode::BAD_REQUEST, "expires_in_days must be at least 1".to_string()));
+    }
+
+    let mut tx = state.pool.begin().await.map_err(db_error)?;
+    let batch_id = insert_batch(&mut tx, label, admin.0.id, payload.count, max_uses, payload.expires_in_days)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "create_invite_batch".to_string(),
+        None,
+        Some("invite_batch".to_string()),
+        Some(batch_id),
+        serde_json::json!({
+            "label": label,
+            "count": payload.count,
+            "max_uses": max_uses,
+            "expires_in_days": payload.expires_in_days,
+        }),
+    )
+    .await;
+
+    let detail = batch_detail(&state.pool, batch_id)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Batch not found".to_string()))?;
+    Ok(Json(detail))
+}
+
+// List invite code batches with how many signups each brought in
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/invites/batches",
+    tag = "admin",
+    responses((status = 200, body = [InviteBatch]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_batches(
+    State(state): State<Arc<AppState>>,
+    _admin: Permitted<CanManagePlatform>,
+) -> Result<Json<Vec<InviteBatch>>, (StatusCode, String)> {
+    let batches = sqlx::query_as::<_, InviteBatch>(&format!("{} ORDER BY b.created_at DESC", BATCH_SUMMARY))
+        .fetch_all(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+    Ok(Json(batches))
+}
+
+// A batch and its codes
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/invites/batches/{batch_id}",
+    tag = "admin",
+    params(("batch_id" = Uuid, Path, description = "Batch ID")),
+    responses(
+        (status = 200, body = InviteBatchDetail),
+        (status = 404, description = "Batch not found"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_batch(
+    State(state): State<Arc<AppState>>,
+    _admin: Permitted<CanManagePlatform>,
+    Path(batch_id): Path<Uuid>,
+) -> Result<Json<InviteBatchDetail>, (StatusCode, String)> {
+    let detail = batch_detail(&state.pool, batch_id)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Batch not found".to_string()))?;
+    Ok(Json(detail))
+}
+
+// Stop an invite code from being used again. Accounts already created with it stay.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/invites/codes/{code_id}/revoke",
+    tag = "admin",
+    params(("code_id" = Uuid, Path, description = "Invite code ID")),
+    responses(
+        (status = 204, description = "Code revoked"),
+        (status = 404, description = "Invite code not found"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn revoke_code(
+    State(state): State<Arc<AppState>>,
+    admin: Permitted<CanManagePlatform>,
+    Path(code_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let revoked = sqlx::query("UPDATE invite_codes SET revoked_at = COALESCE(revoked_at, NOW()) WHERE id = $1")
+        .bind(code_id)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(db_error)?
+        .rows_affected();
+    if revoked == 0 {
+        return Err((StatusCode::NOT_FOUND, "Invite code not found".to_string()));
+    }
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "revoke_invite_code".to_string(),
+        None,
+        Some("invite_code".to_string()),
+        Some(code_id),
+        serde_json::json!({}),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Give a user their own invite allowance, or put them back on invites.per_user
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/users/{user_id}/invite-allowance",
+    tag = "admin",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    request_body = InviteAllowanceRequest,
+    responses(
+        (status = 204, description = "Allowance updated"),
+        (status = 400, description = "Negative allowance"),
+        (status = 404, description = "User not found"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn set_invite_allowance(
+    State(state): State<Arc<AppState>>,
+    admin: Permitted<CanManagePlatform>,
+    Path(user_id): Path<Uuid>,
+    Json(payload): Json<InviteAllowanceRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if payload.allowance.is_some_and(|allowance| allowance < 0) {
+        return Err((StatusCode::BAD_REQUEST, "allowance can't be negative".to_string()));
+    }
+    let updated = sqlx::query("UPDATE users SET invite_allowance = $2 WHERE id = $1")
+        .bind(user_id)
+        .bind(payload.allowance)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(db_error)?
+        .rows_affected();
+    if updated == 0 {
+        return Err((StatusCode::NOT_FOUND, "User not found".to_string()));
+    }
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "set_invite_allowance".to_string(),
+        Some(user_id),
+        None,
+        None,
+        serde_json::json!({ "allowance": payload.allowance }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// How invites spread: codes used, generations of the invite graph, top inviters
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/invites/stats",
+    tag = "admin",
+    responses((status = 200, body = InviteStats), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_invite_stats(
+    State(state): State<Arc<AppState>>,
+    _admin: Permitted<CanManagePlatform>,
+) -> Result<Json<InviteStats>, (StatusCode, String)> {
+    let pool = state.pool.as_ref();
+
+    let (codes, used_codes) = sqlx::query_as::<_, (i64, i64)>(
+        "SELECT COUNT(*), COUNT(*) FILTER (WHERE uses > 0) FROM invite_codes"
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+
+    let (redemptions, from_batches, from_users) = sqlx::query_as::<_, (i64, i64, i64)>(
+        r#"
+        SELECT
+            COUNT(*),
+            COUNT(*) FILTER (WHERE batch_id IS NOT NULL),
+            COUNT(*) FILTER (WHERE inviter_id IS NOT NULL)
+        FROM invite_redemptions
+        "#
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+
+    // Roots are invitees whose inviter didn't need an invite (or was deleted)
+    let generations = sqlx::query_as::<_, InviteGeneration>(
+        r#"
+        WITH RECURSIVE graph AS (
+            SELECT r.user_id, 1 AS generation
+            FROM invite_redemptions r
+            WHERE r.inviter_id IS NULL
+               OR NOT EXISTS (SELECT 1 FROM invite_redemptions p WHERE p.user_id = r.inviter_id)
+            UNION ALL
+            SELECT r.user_id, g.generation + 1
+            FROM invite_redemptions r
+            JOIN graph g ON r.inviter_id = g.user_id
+            WHERE g.generation < $1
+        )
+        SELECT generation, COUNT(*) AS users
+        FROM graph
+        GROUP BY generation
+        ORDER BY generation
+        "#
+    )
+    .bind(MAX_GENERATIONS)
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)?;
+
+    let top_inviters = sqlx::query_as::<_, TopInviter>(
+        r#"
+        SELECT
+            u.id AS user_id,
+            u.username,
+            COUNT(*) AS invited,
+            COUNT(*) FILTER (WHERE
+                EXISTS (SELECT 1 FROM stories s WHERE s.user_id = r.user_id)
+                OR EXISTS (SELECT 1 FROM messages m WHERE m.sender_id = r.user_id)
+            ) AS active_invitees
+        FROM invite_redemptions r
+        JOIN users u ON u.id = r.inviter_id
+        GROUP BY u.id, u.username
+        ORDER BY invited DESC, u.username
+        LIMIT $1
+        "#
+    )
+    .bind(TOP_INVITERS)
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(InviteStats {
+        invite_only: invite_only(),
+        codes,
+        used_codes,
+        redemptions,
+        from_batches,
+        from_users,
+        generations,
+        top_inviters,
+    }))
+}
@@ -0,0 +1,746 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tokio::time::{interval, sleep, timeout, Duration};
+use uuid::Uuid;
+
+use crate::admin::{AuthUser, ServiceCaller};
+use crate::expiration::ExpirationService;
+use crate::permissions::{CanManagePlatform, Permitted};
+use crate::AppState;
+
+// Job types understood by the worker pool
+pub const EXPIRE_CONTENT: &str = "expire_content";
+pub const BUCKET_CLEANUP: &str = "bucket_cleanup";
+pub const RECALCULATE_FEEDS: &str = "recalculate_feeds";
+pub const REFRESH_POPULAR_USERS: &str = "refresh_popular_users";
+pub const DELIVER_WEBHOOK: &str = "deliver_webhook";
+pub const GENERATE_ALT_TEXT: &str = "generate_alt_text";
+pub const GENERATE_STORY_PREVIEWS: &str = "generate_story_previews";
+pub const PACKAGE_STORY_HLS: &str = "package_story_hls";
+pub const DELIVER_ANNOUNCEMENT: &str = "deliver_announcement";
+pub const SNAPSHOT_ANALYTICS: &str = "snapshot_analytics";
+pub const RECONCILE_COUNTERS: &str = "reconcile_counters";
+pub const PROCESS_MEDIA_OUTBOX: &str = "process_media_outbox";
+pub const DETECT_CLICK_FRAUD: &str = "detect_click_fraud";
+pub const ROLL_UP_INSIGHTS: &str = "roll_up_insights";
+pub const DELIVER_ACTIVITY: &str = "deliver_activity";
+pub const SEND_ANALYTICS_REPORT: &str = "send_analytics_report";
+pub const COMPUTE_RETENTION: &str = "compute_retention";
+pub const DELIVER_MAIL: &str = "deliver_mail";
+pub const PROCESS_REFERRALS: &str = "process_referrals";
+
+// Job types admins and services may trigger by hand
+const TRIGGERABLE_JOBS: &[&str] = &[
+    EXPIRE_CONTENT,
+    BUCKET_CLEANUP,
+    RECALCULATE_FEEDS,
+    REFRESH_POPULAR_USERS,
+    SNAPSHOT_ANALYTICS,
+    RECONCILE_COUNTERS,
+    DETECT_CLICK_FRAUD,
+    ROLL_UP_INSIGHTS,
+    COMPUTE_RETENTION,
+    PROCESS_REFERRALS,
+];
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const JOB_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+const DEFAULT_MAX_ATTEMPTS: i32 = 5;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+pub struct Job {
+    pub id: Uuid,
+    pub job_type: String,
+    pub payload: serde_json::Value,
+    pub status: String,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub last_error: Option<String>,
+    pub result: Option<serde_json::Value>,
+    pub unique_key: Option<String>,
+    #[serde(with = "crate::timestamps")]
+    pub run_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps::option")]
+    pub locked_at: Option<NaiveDateTime>,
+    pub locked_by: Option<String>,
+    pub created_by: Option<Uuid>,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps")]
+    pub updated_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps::option")]
+    pub completed_at: Option<NaiveDateTime>,
+}
+
+// ============================================================================
+// ENQUEUEING
+// ============================================================================
+
+/// Enqueue a job to run as soon as a worker is free
+pub async fn enqueue(
+    pool: &PgPool,
+    job_type: &str,
+    payload: serde_json::Value,
+    created_by: Option<Uuid>,
+) -> Result<Uuid, sqlx::Error> {
+    schedule(pool, job_type, payload, chrono::Utc::now().naive_utc(), created_by).await
+}
+
+/// Enqueue a job to run at (or after) a specific time
+pub async fn schedule(
+    pool: &PgPool,
+    job_type: &str,
+    payload: serde_json::Value,
+    run_at: NaiveDateTime,
+    created_by: Option<Uuid>,
+) -> Result<Uuid, sqlx::Error> {
+    sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO background_jobs (job_type, payload, run_at, max_attempts, created_by)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#
+    )
+    .bind(job_type)
+    .bind(payload)
+    .bind(run_at)
+    .bind(DEFAULT_MAX_ATTEMPTS)
+    .bind(created_by)
+    .fetch_one(pool)
+    .await
+}
+
+/// Enqueue a job unless one with the same key is already pending or running.
+/// Returns None when an equivalent job is already queued.
+pub async fn enqueue_unique(
+    pool: &PgPool,
+    job_type: &str,
+    unique_key: &str,
+    payload: serde_json::Value,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO background_jobs (job_type, payload, unique_key, max_attempts)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (unique_key) WHERE status IN ('pending', 'running') DO NOTHING
+        RETURNING id
+        "#
+    )
+    .bind(job_type)
+    .bind(payload)
+    .bind(unique_key)
+    .bind(DEFAULT_MAX_ATTEMPTS)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Enqueue a job type on a fixed interval (replaces ad-hoc spawn loops)
+pub fn schedule_recurring(pool: Arc<PgPool>, job_type: &'static str, every: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = interval(every);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = enqueue_unique(&pool, job_type, job_type, serde_json::json!({})).await {
+                eprintln!("Failed to enqueue recurring job {}: {}", job_type, e);
+            }
+        }
+    });
+}
+
+/// Queue a maintenance job on behalf of a cron caller. Repeated triggers while
+/// one is still queued are collapsed into the existing job.
+pub async fn enqueue_trigger(
+    state: &Arc<AppState>,
+    job_type: &'static str,
+    caller: &ServiceCaller,
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, String)> {
+    let job_id = enqueue_unique(&state.pool, job_type, job_type, serde_json::json!({}))
+        .await
+        .map_err(|e| {
+            eprintln!("Enqueue {} error: {:?}", job_type, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to enqueue job".to_string())
+        })?;
+
+    if let (Some(admin_id), Some(job_id)) = (caller.user_id(), job_id) {
+        crate::admin::log_admin_action(
+            state,
+            admin_id,
+            "enqueue_job".to_string(),
+            None,
+            Some("job".to_string()),
+            Some(job_id),
+            serde_json::json!({ "job_type": job_type }),
+        ).await;
+    }
+
+    Ok((StatusCode::ACCEPTED, Json(serde_json::json!({
+        "success": true,
+        "job_id": job_id,
+        "already_queued": job_id.is_none()
+    }))))
+}
+
+// ============================================================================
+// WORKER POOL
+// ============================================================================
+
+/// Spawn the worker pool plus the stale-lock reaper
+pub fn start_workers(state: Arc<AppState>, worker_count: usize) {
+    for i in 0..worker_count {
+        let state = state.clone();
+        let worker_name = format!("worker-{}-{}", std::process::id(), i);
+        tokio::spawn(async move {
+            worker_loop(state, worker_name).await;
+        });
+    }
+
+    let pool = state.pool.clone();
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(60));
+        loop {
+            ticker.tick().await;
+            if let Err(e) = release_stale_jobs(&pool).await {
+                eprintln!("Failed to release stale jobs: {}", e);
+            }
+        }
+    });
+}
+
+async fn worker_loop(state: Arc<AppState>, worker_name: String) {
+    loop {
+        match claim_next_job(&state.pool, &worker_name).await {
+            Ok(Some(job)) => {
+                let outcome = match timeout(JOB_TIMEOUT, run_job(&state, &job)).await {
+                    Ok(result) => result,
+                    Err(_) => Err("Job timed out".to_string()),
+                };
+
+                if let Err(e) = finish_job(&state.pool, &job, outcome).await {
+                    eprintln!("Failed to record result for job {}: {}", job.id, e);
+                }
+            }
+            Ok(None) => sleep(POLL_INTERVAL).await,
+            Err(e) => {
+                eprintln!("Job worker {} failed to poll queue: {}", worker_name, e);
+                sleep(POLL_INTERVAL * 5).await;
+            }
+        }
+    }
+}
+
+/// Atomically claim the next due job (SKIP LOCKED lets workers run side by side)
+async fn claim_next_job(pool: &PgPool, worker_name: &str) -> Result<Option<Job>, sqlx::Error> {
+    sqlx::query_as::<_, Job>(
+        r#"
+        UPDATE background_jobs
+        SET status = 'running',
+            attempts = attempts + 1,
+            locked_at = NOW(),
+            locked_by = $1,
+            updated_at = NOW()
+        WHERE id = (
+            SELECT id FROM background_jobs
+            WHERE status = 'pending' AND run_at <= NOW()
+            ORDER BY run_at ASC
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING *
+        "#
+    )
+    .bind(worker_name)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Dispatch a job to its handler. Handlers return an optional JSON result.
+async fn run_job(state: &Arc<AppState>, job: &Job) -> Result<Option<serde_json::Value>, String> {
+    match job.job_type.as_str() {
+        EXPIRE_CONTENT => {
+            ExpirationService::new(state.pool.clone(), state.media_service.clone(), state.connections.clone())
+                .run()
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(None)
+        }
+        BUCKET_CLEANUP => {
+            let stats = crate::bucket_cleanup::cleanup_unused_files(&state.media_service, &state.pool).await?;
+            Ok(Some(serde_json::json!({
+                "files_scanned": stats.files_scanned,
+                "files_deleted": stats.files_deleted,
+                "bytes_freed": stats.bytes_freed,
+            })))
+        }
+        RECALCULATE_FEEDS => {
+            let users = crate::algorithm::recalculate_feeds_for_all_users(state.clone())
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(Some(serde_json::json!({ "users_processed": users })))
+        }
+        REFRESH_POPULAR_USERS => {
+            crate::discovery::refresh_popular_users(&state.pool)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(None)
+        }
+        DELIVER_WEBHOOK => {
+            crate::webhooks::deliver(&state.pool, &job.payload, job.attempts >= job.max_attempts).await
+        }
+        GENERATE_ALT_TEXT => crate::captioning::generate_alt_text(&state.pool, &state.media_service, &job.payload).await,
+        GENERATE_STORY_PREVIEWS => crate::video_render::generate_story_previews(state, &job.payload).await,
+        PACKAGE_STORY_HLS => crate::video_render::package_story_hls(state, &job.payload).await,
+        DELIVER_ANNOUNCEMENT => crate::announcements::deliver(&state.pool, &state.connections, &job.payload).await,
+        SNAPSHOT_ANALYTICS => crate::admin::snapshot_daily_analytics(&state.pool).await,
+        RECONCILE_COUNTERS => crate::counters::reconcile_all(&state.pool).await,
+        PROCESS_MEDIA_OUTBOX => crate::media_outbox::process_pending(&state.pool, &state.media_service).await,
+        DETECT_CLICK_FRAUD => crate::ad_fraud::detect(&state.pool).await,
+        ROLL_UP_INSIGHTS => crate::insights::roll_up(&state.pool).await,
+        DELIVER_ACTIVITY => crate::federation::deliver(&state.pool, &job.payload).await,
+        SEND_ANALYTICS_REPORT => crate::admin::send_analytics_report(&state.pool).await,
+        COMPUTE_RETENTION => crate::retention::compute(&state.pool).await,
+        DELIVER_MAIL => crate::mailer::deliver(&state.pool, &job.payload, job.attempts >= job.max_attempts).await,
+        PROCESS_REFERRALS => crate::referrals::process(&state.pool).await,
+        other => Err(format!("Unknown job type: {}", other)),
+    }
+}
+
+async fn finish_job(
+    pool: &PgPool,
+    job: &Job,
+    outcome: Result<Option<serde_json::Value>, String>,
+) -> Result<(), sqlx::Error> {
+    match outcome {
+        Ok(result) => {
+            sqlx::query(
+                r#"
+                UPDATE background_jobs
+                SET status = 'completed', result = $2, last_error = NULL,
+                    locked_at = NULL, locked_by = NULL,
+                    completed_at = NOW(), updated_at = NOW()
+                WHERE id = $1
+                "#
+            )
+            .bind(job.id)
+            .bind(result)
+            .execute(pool)
+            .await?;
+        }
+        Err(error) => {
+            let exhausted = job.attempts >= job.max_attempts;
+            eprintln!(
+                "Job {} ({}) failed on attempt {}/{}: {}",
+                job.id, job.job_type, job.attempts, job.max_attempts, error
+            );
+
+            sqlx::query(
+                r#"
+                UPDATE background_jobs
+                SET status = CASE WHEN $2 THEN 'failed' ELSE 'pending' END,
+                    last_error = $3,
+                    run_at = NOW() + make_interval(secs => $4),
+                    locked_at = NULL, locked_by = NULL,
+                    updated_at = NOW()
+                WHERE id = $1
+                "#
+            )
+            .bind(job.id)
+            .bind(exhausted)
+            .bind(error)
+            .bind(retry_delay_seconds(job.attempts) as f64)
+            .execute(pool)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Exponential backoff: 30s, 60s, 120s, ... capped at one hour
+fn retry_delay_seconds(attempts: i32) -> i64 {
+    let exponent = attempts.clamp(0, 10) as u32;
+    (30 * 2_i64.pow(exponent)).min(3600)
+}
+
+/// Put jobs whose worker died mid-run back on the queue, or fail them once
+/// they've used up their attempts so a job that kills its worker isn't
+/// retried forever
+async fn release_stale_jobs(pool: &PgPool) -> Result<(), sqlx::Error> {
+    let (released, failed) = sqlx::query_as::<_, (i64, i64)>(
+        r#"
+        WITH stale AS (
+            UPDATE background_jobs
+            SET status = CASE WHEN attempts >= max_attempts THEN 'failed' ELSE 'pending' END,
+                last_error = CASE WHEN attempts >= max_attempts THEN 'Worker died while running the job' ELSE last_error END,
+                locked_at = NULL, locked_by = NULL, updated_at = NOW()
+            WHERE status = 'running'
+              AND locked_at < NOW() - INTERVAL '45 minutes'
+            RETURNING status
+        )
+        SELECT COUNT(*) FILTER (WHERE status = 'pending'), COUNT(*) FILTER (WHERE status = 'failed')
+        FROM stale
+        "#
+    )
+    .fetch_one(pool)
+    .await?;
+
+    if released > 0 {
+        println!("♻️ Released {} stale jobs back to the queue", released);
+    }
+    if failed > 0 {
+        eprintln!("❌ Failed {} stale jobs that ran out of attempts", failed);
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// JOB STATUS API
+// ============================================================================
+
+#[derive(Serialize, ToSchema)]
+pub struct JobStatusResponse {
+    pub id: Uuid,
+    pub job_type: String,
+    pub status: String,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub result: Option<serde_json::Value>,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps::option")]
+    pub completed_at: Option<NaiveDateTime>,
+}
+
+// Get the status of a job the caller enqueued
+#[utoipa::path(
+    get,
+    path = "/api/v1/jobs/{job_id}",
+    tag = "jobs",
+    params(("job_id" = Uuid, Path, description = "Job ID")),
+    responses((status = 200, body = JobStatusResponse), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_job_status(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(job_id): Path<Uuid>,
+) -> Result<Json<JobStatusResponse>, (StatusCode, String)> {
+    let job = fetch_job(&state.pool, job_id).await?;
+
+    let is_staff = crate::permissions::role_is_staff(&state.pool, &user.role).await;
+    if job.created_by != Some(user.id) && !is_staff {
+        return Err((StatusCode::NOT_FOUND, "Job not found".to_string()));
+    }
+
+    Ok(Json(JobStatusResponse {
+        id: job.id,
+        job_type: job.job_type,
+        status: job.status,
+        attempts: job.attempts,
+        last_error: job.last_error,
+        result: job.result,
+        created_at: job.created_at,
+        completed_at: job.completed_at,
+    }))
+}
+
+async fn fetch_job(pool: &PgPool, job_id: Uuid) -> Result<Job, (StatusCode, String)> {
+    sqlx::query_as::<_, Job>("SELECT * FROM background_jobs WHERE id = $1")
+        .bind(job_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Job lookup error: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch job".to_string())
+        })?
+        .ok_or((StatusCode::NOT_FOUND, "Job not found".to_string()))
+}
+
+// ============================================================================
+// ADMIN VISIBILITY
+// ============================================================================
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct JobListQuery {
+    page: Option<i64>,
+    per_page: Option<i64>,
+    status: Option<String>,
+    job_type: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct JobListResponse {
+    jobs: Vec<Job>,
+    total: i64,
+    page: i64,
+    per_page: i64,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct JobStats {
+    job_type: String,
+    status: String,
+    count: i64,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/jobs",
+    tag = "jobs",
+    params(JobListQuery),
+    responses((status = 200, body = JobListResponse), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_jobs(
+    _admin: Permitted<CanManagePlatform>,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<JobListQuery>,
+) -> Result<Json<JobListResponse>, (StatusCode, String)> {
+    let page = params.page.unwrap_or(1).max(1);
+    let per_page = params.per_page.unwrap_or(50).clamp(1, 100);
+    let offset = (page - 1) * per_page;
+
+    let jobs = sqlx::query_as::<_, Job>(
+        r#"
+        SELECT * FROM background_jobs
+        WHERE ($1::varchar IS NULL OR status = $1)
+          AND ($2::varchar IS NULL OR job_type = $2)
+        ORDER BY created_at DESC
+        LIMIT $3 OFFSET $4
+        "#
+    )
+    .bind(&params.status)
+    .bind(&params.job_type)
+    .bind(per_page)
+    .bind(offset)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(|e| {
+        eprintln!("List jobs error: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch jobs".to_string())
+    })?;
+
+    let total = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT COUNT(*) FROM background_jobs
+        WHERE ($1::varchar IS NULL OR status = $1)
+          AND ($2::varchar IS NULL OR job_type = $2)
+        "#
+    )
+    .bind(&params.status)
+    .bind(&params.job_type)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(|e| {
+        eprintln!("Count jobs error: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to count jobs".to_string())
+    })?;
+
+    Ok(Json(JobListResponse {
+        jobs,
+        total,
+        page,
+        per_page,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/jobs/stats",
+    tag = "jobs",
+    responses((status = 200, body = [JobStats]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_job_stats(
+    _admin: Permitted<CanManagePlatform>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<JobStats>>, (StatusCode, String)> {
+    let stats = sqlx::query_as::<_, JobStats>(
+        r#"
+        SELECT job_type, status, COUNT(*) as count
+        FROM background_jobs
+        GROUP BY job_type, status
+        ORDER BY job_type, status
+        "#
+    )
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(|e| {
+        eprintln!("Job stats error: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch job stats".to_string())
+    })?;
+
+    Ok(Json(stats))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/jobs/{job_id}",
+    tag = "jobs",
+    params(("job_id" = Uuid, Path, description = "Job ID")),
+    responses((status = 200, body = Job), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_job(
+    _admin: Permitted<CanManagePlatform>,
+    State(state): State<Arc<AppState>>,
+    Path(job_id): Path<Uuid>,
+) -> Result<Json<Job>, (StatusCode, String)> {
+    Ok(Json(fetch_job(&state.pool, job_id).await?))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct EnqueueJobRequest {
+    job_type: String,
+    payload: Option<serde_json::Value>,
+}
+
+// Trigger a job manually (e.g. run bucket cleanup now instead of waiting for the schedule)
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/jobs",
+    tag = "jobs",
+    request_body = EnqueueJobRequest,
+    responses((status = 200, body = serde_json::Value), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn create_job(
+    admin: Permitted<CanManagePlatform>,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<EnqueueJobRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    if !TRIGGERABLE_JOBS.contains(&req.job_type.as_str()) {
+        return Err((StatusCode::BAD_REQUEST, format!("Unknown job type: {}", req.job_type)));
+    }
+
+    let job_id = enqueue(
+        &state.pool,
+        &req.job_type,
+        req.payload.unwrap_or_else(|| serde_json::json!({})),
+        Some(admin.0.id),
+    )
+    .await
+    .map_err(|e| {
+        eprintln!("Enqueue job error: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to enqueue job".to_string())
+    })?;
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "enqueue_job".to_string(),
+        None,
+        Some("job".to_string()),
+        Some(job_id),
+        serde_json::json!({ "job_type": req.job_type }),
+    ).await;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "job_id": job_id
+    })))
+}
+
+// Re-queue a failed or cancelled job with a fresh attempt budget
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/jobs/{job_id}/retry",
+    tag = "jobs",
+    params(("job_id" = Uuid, Path, description = "Job ID")),
+    responses((status = 200, body = serde_json::Value), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn retry_job(
+    admin: Permitted<CanManagePlatform>,
+    State(state): State<Arc<AppState>>,
+    Path(job_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let updated = sqlx::query(
+        r#"
+        UPDATE background_jobs
+        SET status = 'pending', attempts = 0, run_at = NOW(), last_error = NULL, updated_at = NOW()
+        WHERE id = $1 AND status IN ('failed', 'cancelled')
+        "#
+    )
+    .bind(job_id)
+    .execute(state.pool.as_ref())
+    .await
+    .map_err(|e| {
+        eprintln!("Retry job error: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to retry job".to_string())
+    })?
+    .rows_affected();
+
+    if updated == 0 {
+        return Err((StatusCode::CONFLICT, "Only failed or cancelled jobs can be retried".to_string()));
+    }
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "retry_job".to_string(),
+        None,
+        Some("job".to_string()),
+        Some(job_id),
+        serde_json::json!({}),
+    ).await;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "message": "Job re-queued"
+    })))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/jobs/{job_id}/cancel",
+    tag = "jobs",
+    params(("job_id" = Uuid, Path, description = "Job ID")),
+    responses((status = 200, body = serde_json::Value), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn cancel_job(
+    admin: Permitted<CanManagePlatform>,
+    State(state): State<Arc<AppState>>,
+    Path(job_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let updated = sqlx::query(
+        "UPDATE background_jobs SET status = 'cancelled', updated_at = NOW() WHERE id = $1 AND status = 'pending'"
+    )
+    .bind(job_id)
+    .execute(state.pool.as_ref())
+    .await
+    .map_err(|e| {
+        eprintln!("Cancel job error: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to cancel job".to_string())
+    })?
+    .rows_affected();
+
+    if updated == 0 {
+        return Err((StatusCode::CONFLICT, "Only pending jobs can be cancelled".to_string()));
+    }
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "cancel_job".to_string(),
+        None,
+        Some("job".to_string()),
+        Some(job_id),
+        serde_json::json!({}),
+    ).await;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "message": "Job cancelled"
+    })))
+}
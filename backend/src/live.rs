@@ -0,0 +1,679 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{Duration, NaiveDateTime, Utc};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::websocket::{nack, send_to_user, Connections, ErrorCode, Outbox, WsMessage};
+use crate::AppState;
+
+// Live streaming (migration 060). Video travels peer to peer over WebRTC;
+// this module runs the sessions around it:
+//   1. the host starts a session and their followers are notified
+//   2. a viewer asks for a join token and presents it with LiveJoin
+//   3. the host hears LiveViewerJoined and sends an offer; LiveSdp and LiveIce
+//      are relayed between the host and that viewer only
+//   4. chat is stored and pushed to everyone in the session
+// A host whose last socket drops ends their session; a viewer's leaves it.
+
+const TITLE_MAX_LENGTH: usize = 200;
+const CHAT_MAX_LENGTH: usize = 500;
+const JOIN_TOKEN_TTL_MINUTES: i64 = 5;
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct LiveSession {
+    pub id: Uuid,
+    pub host_id: Uuid,
+    pub host_username: String,
+    pub title: Option<String>,
+    /// live or ended
+    pub status: String,
+    pub viewer_count: i64,
+    pub peak_viewers: i32,
+    #[serde(with = "crate::timestamps")]
+    pub started_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps::option")]
+    pub ended_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct StartLiveRequest {
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct JoinTokenResponse {
+    pub session_id: Uuid,
+    /// Send with a LiveJoin frame on the WebSocket; single use
+    pub join_token: String,
+    #[serde(with = "crate::timestamps")]
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct LiveChatMessage {
+    pub id: Uuid,
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+    pub username: String,
+    pub content: String,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SendLiveChatRequest {
+    pub content: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LiveChatQuery {
+    pub limit: Option<i64>,
+    /// Message ID for pagination
+    pub before: Option<Uuid>,
+}
+
+const SESSION_COLUMNS: &str = r#"
+    l.id, l.host_id, u.username AS host_username, l.title, l.status,
+    (SELECT COUNT(*) FROM live_viewers v WHERE v.session_id = l.id AND v.joined_at IS NOT NULL AND v.left_at IS NULL) AS viewer_count,
+    l.peak_viewers, l.started_at, l.ended_at
+    FROM live_sessions l
+    JOIN users u ON u.id = l.host_id
+"#;
+
+async fn fetch_session(pool: &PgPool, session_id: Uuid) -> Result<Option<LiveSession>, sqlx::Error> {
+    sqlx::query_as::<_, LiveSession>(&format!("SELECT {} WHERE l.id = $1", SESSION_COLUMNS))
+        .bind(session_id)
+        .fetch_optional(pool)
+        .await
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn random_token() -> String {
+    let mut buf = [0u8; 32];
+    OsRng.fill_bytes(&mut buf);
+    hex::encode(buf)
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    eprintln!("❌ Live query failed: {:?}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+}
+
+fn not_found() -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, "Live session not found".to_string())
+}
+
+/// The host and every viewer currently in the session
+async fn participants(pool: &PgPool, session_id: Uuid) -> Vec<Uuid> {
+    let result = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        SELECT host_id FROM live_sessions WHERE id = $1
+        UNION
+        SELECT user_id FROM live_viewers WHERE session_id = $1 AND joined_at IS NOT NULL AND left_at IS NULL
+        "#
+    )
+    .bind(session_id)
+    .fetch_all(pool)
+    .await;
+
+    result.unwrap_or_else(|e| {
+        tracing::error!("Failed to load participants of live session {}: {}", session_id, e);
+        Vec::new()
+    })
+}
+
+/// End a live session and tell its viewers
+async fn end_session(pool: &PgPool, connections: &Connections, session_id: Uuid) -> Result<(), sqlx::Error> {
+    let viewers = participants(pool, session_id).await;
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE live_sessions SET status = 'ended', ended_at = NOW() WHERE id = $1 AND status = 'live'")
+        .bind(session_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE live_viewers SET left_at = NOW() WHERE session_id = $1 AND joined_at IS NOT NULL AND left_at IS NULL")
+        .bind(session_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    let ended = WsMessage::LiveEnded { session_id };
+    for user_id in viewers {
+        send_to_user(connections, user_id, &ended);
+    }
+    Ok(())
+}
+
+/// Notify the host's followers, pushing to those who are online
+async fn notify_followers(state: &AppState, session: &LiveSession) {
+    let message = crate::i18n::Message::new("live_started").arg("username", &session.host_username);
+    let followers = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO notifications (user_id, type, from_user_id, message, message_key, message_args)
+        SELECT follower_id, 'live_started', $1, $2, $3, $4
+        FROM follows
+        WHERE following_id = $1
+        RETURNING user_id
+        "#
+    )
+    .bind(session.host_id)
+    .bind(message.text())
+    .bind(message.key)
+    .bind(&message.args)
+    .fetch_all(state.pool.as_ref())
+    .await;
+
+    match followers {
+        Ok(followers) => {
+            let started = WsMessage::LiveStarted {
+                session_id: session.id,
+                host_id: session.host_id,
+                host_username: session.host_username.clone(),
+                title: session.title.clone(),
+            };
+            for follower_id in followers {
+                send_to_user(&state.connections, follower_id, &started);
+            }
+        }
+        Err(e) => eprintln!("❌ Failed to notify followers of {} going live: {:?}", session.host_id, e),
+    }
+}
+
+/// Whether `from` and `to` are the host and a joined viewer of a live session
+async fn can_relay(pool: &PgPool, session_id: Uuid, from: Uuid, to: Uuid) -> bool {
+    let result = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM live_sessions l
+            JOIN live_viewers v ON v.session_id = l.id
+            WHERE l.id = $1 AND l.status = 'live'
+              AND v.joined_at IS NOT NULL AND v.left_at IS NULL
+              AND ((l.host_id = $2 AND v.user_id = $3) OR (v.user_id = $2 AND l.host_id = $3))
+        )
+        "#
+    )
+    .bind(session_id)
+    .bind(from)
+    .bind(to)
+    .fetch_one(pool)
+    .await;
+
+    result.unwrap_or_else(|e| {
+        tracing::error!("Failed to check live relay for session {}: {}", session_id, e);
+        false
+    })
+}
+
+/// Handle the live frames of the WebSocket protocol
+pub async fn handle_ws_message(
+    msg: WsMessage,
+    request_id: Option<&str>,
+    outbox: &Outbox,
+    user_id: Uuid,
+    pool: &PgPool,
+    connections: &Connections,
+) {
+    let error = |code: ErrorCode, message: &str| nack(outbox, request_id, code, message);
+
+    match msg {
+        WsMessage::LiveJoin { session_id, join_token } => {
+            let joined = sqlx::query_as::<_, (Uuid, String)>(
+                r#"
+                UPDATE live_viewers v
+                SET joined_at = NOW(), left_at = NULL, token_hash = NULL, token_expires_at = NULL
+                FROM live_sessions l, users u
+                WHERE v.session_id = $1 AND v.user_id = $2 AND v.token_hash = $3 AND v.token_expires_at > NOW()
+                  AND l.id = v.session_id AND l.status = 'live' AND u.id = v.user_id
+                RETURNING l.host_id, u.username
+                "#
+            )
+            .bind(session_id)
+            .bind(user_id)
+            .bind(hash_token(&join_token))
+            .fetch_optional(pool)
+            .await;
+
+            let (host_id, username) = match joined {
+                Ok(Some(joined)) => joined,
+                Ok(None) => return error(ErrorCode::Unauthorized, "Join token is invalid or expired"),
+                Err(e) => {
+                    tracing::error!("Failed to join live session {}: {}", session_id, e);
+                    return error(ErrorCode::Internal, "Failed to join the live session");
+                }
+            };
+
+            if let Err(e) = sqlx::query(
+                r#"
+                UPDATE live_sessions SET peak_viewers = GREATEST(peak_viewers, (
+                    SELECT COUNT(*) FROM live_viewers WHERE session_id = $1 AND joined_at IS NOT NULL AND left_at IS NULL
+                )::int)
+                WHERE id = $1
+                "#
+            )
+            .bind(session_id)
+            .execute(pool)
+            .await
+            {
+                tracing::error!("Failed to update peak viewers of live session {}: {}", session_id, e);
+            }
+
+            send_to_user(connections, host_id, &WsMessage::LiveViewerJoined { session_id, user_id, username });
+        }
+
+        WsMessage::LiveSdp { session_id, peer_id, sdp_type, sdp } => {
+            if sdp_type != "offer" && sdp_type != "answer" {
+                return error(ErrorCode::PayloadInvalid, "sdp_type must be offer or answer");
+            }
+            if !can_relay(pool, session_id, user_id, peer_id).await {
+                return error(ErrorCode::NotAMember, "That peer isn't in this live session");
+            }
+            send_to_user(connections, peer_id, &WsMessage::LiveSdp { session_id, peer_id: user_id, sdp_type, sdp });
+        }
+
+        WsMessage::LiveIce { session_id, peer_id, candidate, sdp_mid, sdp_mline_index } => {
+            if !can_relay(pool, session_id, user_id, peer_id).await {
+                return error(ErrorCode::NotAMember, "That peer isn't in this live session");
+            }
+            send_to_user(
+                connections,
+                peer_id,
+                &WsMessage::LiveIce { session_id, peer_id: user_id, candidate, sdp_mid, sdp_mline_index },
+            );
+        }
+
+        _ => {}
+    }
+}
+
+/// The user's last socket closed: end the session they host, leave any they watch
+pub async fn disconnected(pool: &PgPool, connections: &Connections, user_id: Uuid) {
+    let hosted = sqlx::query_scalar::<_, Uuid>("SELECT id FROM live_sessions WHERE host_id = $1 AND status = 'live'")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await;
+    match hosted {
+        Ok(Some(session_id)) => {
+            if let Err(e) = end_session(pool, connections, session_id).await {
+                tracing::error!("Failed to end live session {}: {}", session_id, e);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => tracing::error!("Failed to look up live session of {}: {}", user_id, e),
+    }
+
+    let left = sqlx::query_as::<_, (Uuid, Uuid)>(
+        r#"
+        UPDATE live_viewers v SET left_at = NOW()
+        FROM live_sessions l
+        WHERE v.user_id = $1 AND v.joined_at IS NOT NULL AND v.left_at IS NULL
+          AND l.id = v.session_id AND l.status = 'live'
+        RETURNING v.session_id, l.host_id
+        "#
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await;
+    match left {
+        Ok(left) => {
+            for (session_id, host_id) in left {
+                send_to_user(connections, host_id, &WsMessage::LiveViewerLeft { session_id, user_id });
+            }
+        }
+        Err(e) => tracing::error!("Failed to leave live sessions of {}: {}", user_id, e),
+    }
+}
+
+// Go live; followers get a notification
+#[utoipa::path(
+    post,
+    path = "/api/v1/live",
+    tag = "live",
+    request_body = StartLiveRequest,
+    responses(
+        (status = 201, body = LiveSession),
+        (status = 400, description = "Title too long or language that isn't allowed"),
+        (status = 409, description = "The caller is already live"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn start_live(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<StartLiveRequest>,
+) -> Result<(StatusCode, Json<LiveSession>), (StatusCode, String)> {
+    let title = payload.title.as_deref().map(str::trim).filter(|t| !t.is_empty());
+    if title.is_some_and(|t| t.chars().count() > TITLE_MAX_LENGTH) {
+        return Err((StatusCode::BAD_REQUEST, format!("Titles are limited to {} characters", TITLE_MAX_LENGTH)));
+    }
+    let screened_title = match title {
+        Some(title) => Some(crate::text_moderation::screen(&state.pool, title).await?),
+        None => None,
+    };
+
+    let session_id = sqlx::query_scalar::<_, Uuid>("INSERT INTO live_sessions (host_id, title) VALUES ($1, $2) RETURNING id")
+        .bind(user.id)
+        .bind(screened_title.as_ref().map(|s| s.text.as_str()))
+        .fetch_one(state.pool.as_ref())
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(ref db) if db.is_unique_violation() => {
+                (StatusCode::CONFLICT, "You're already live".to_string())
+            }
+            e => db_error(e),
+        })?;
+
+    if let Some(screened) = &screened_title {
+        screened
+            .record(&state.pool, crate::text_moderation::CONTENT_LIVE_TITLE, session_id, user.id)
+            .await;
+    }
+
+    let session = fetch_session(&state.pool, session_id)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Live session vanished".to_string()))?;
+    notify_followers(&state, &session).await;
+
+    Ok((StatusCode::CREATED, Json(session)))
+}
+
+// Stop streaming; viewers get LiveEnded
+#[utoipa::path(
+    post,
+    path = "/api/v1/live/{session_id}/end",
+    tag = "live",
+    params(("session_id" = Uuid, Path, description = "Live session ID")),
+    responses(
+        (status = 204, description = "Ended"),
+        (status = 404, description = "No live session of the caller's"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn end_live(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let is_live = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM live_sessions WHERE id = $1 AND host_id = $2 AND status = 'live')"
+    )
+    .bind(session_id)
+    .bind(user.id)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+    if !is_live {
+        return Err(not_found());
+    }
+
+    end_session(&state.pool, &state.connections, session_id).await.map_err(db_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Live sessions of people the caller follows, busiest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/live",
+    tag = "live",
+    responses((status = 200, body = [LiveSession]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_live(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<LiveSession>>, (StatusCode, String)> {
+    let sessions = sqlx::query_as::<_, LiveSession>(&format!(
+        r#"
+        SELECT {}
+        WHERE l.status = 'live'
+          AND l.host_id IN (SELECT following_id FROM follows WHERE follower_id = $1)
+        ORDER BY viewer_count DESC, l.started_at DESC
+        "#,
+        SESSION_COLUMNS
+    ))
+    .bind(user.id)
+    .fetch_all(state.read_pool())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(sessions))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/live/{session_id}",
+    tag = "live",
+    params(("session_id" = Uuid, Path, description = "Live session ID")),
+    responses((status = 200, body = LiveSession), (status = 404, description = "No such session"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_live(
+    _user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<Uuid>,
+) -> Result<Json<LiveSession>, (StatusCode, String)> {
+    let session = fetch_session(&state.pool, session_id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(not_found)?;
+    Ok(Json(session))
+}
+
+// Get a token to join as a viewer; it replaces any earlier one
+#[utoipa::path(
+    post,
+    path = "/api/v1/live/{session_id}/join",
+    tag = "live",
+    params(("session_id" = Uuid, Path, description = "Live session ID")),
+    responses(
+        (status = 200, body = JoinTokenResponse),
+        (status = 400, description = "The host can't watch their own session"),
+        (status = 404, description = "No live session with this ID"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn join_live(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<Uuid>,
+) -> Result<Json<JoinTokenResponse>, (StatusCode, String)> {
+    let host_id = sqlx::query_scalar::<_, Uuid>("SELECT host_id FROM live_sessions WHERE id = $1 AND status = 'live'")
+        .bind(session_id)
+        .fetch_optional(state.pool.as_ref())
+        .await
+        .map_err(db_error)?
+        .ok_or_else(not_found)?;
+    if host_id == user.id {
+        return Err((StatusCode::BAD_REQUEST, "You can't watch your own live session".to_string()));
+    }
+
+    let join_token = random_token();
+    let expires_at = (Utc::now() + Duration::minutes(JOIN_TOKEN_TTL_MINUTES)).naive_utc();
+    sqlx::query(
+        r#"
+        INSERT INTO live_viewers (session_id, user_id, token_hash, token_expires_at) VALUES ($1, $2, $3, $4)
+        ON CONFLICT (session_id, user_id) DO UPDATE SET token_hash = EXCLUDED.token_hash, token_expires_at = EXCLUDED.token_expires_at
+        "#
+    )
+    .bind(session_id)
+    .bind(user.id)
+    .bind(hash_token(&join_token))
+    .bind(expires_at)
+    .execute(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(JoinTokenResponse { session_id, join_token, expires_at }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/live/{session_id}/leave",
+    tag = "live",
+    params(("session_id" = Uuid, Path, description = "Live session ID")),
+    responses((status = 204, description = "Left"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn leave_live(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let host_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE live_viewers v SET left_at = NOW()
+        FROM live_sessions l
+        WHERE v.session_id = $1 AND v.user_id = $2 AND v.joined_at IS NOT NULL AND v.left_at IS NULL
+          AND l.id = v.session_id
+        RETURNING l.host_id
+        "#
+    )
+    .bind(session_id)
+    .bind(user.id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    if let Some(host_id) = host_id {
+        send_to_user(&state.connections, host_id, &WsMessage::LiveViewerLeft { session_id, user_id: user.id });
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Chat history of a session, newest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/live/{session_id}/chat",
+    tag = "live",
+    params(("session_id" = Uuid, Path, description = "Live session ID"), LiveChatQuery),
+    responses((status = 200, body = [LiveChatMessage]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_live_chat(
+    _user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<Uuid>,
+    Query(params): Query<LiveChatQuery>,
+) -> Result<Json<Vec<LiveChatMessage>>, (StatusCode, String)> {
+    let limit = params.limit.unwrap_or(50).clamp(1, 200);
+
+    let messages = sqlx::query_as::<_, LiveChatMessage>(
+        r#"
+        SELECT c.id, c.session_id, c.user_id, u.username, c.content, c.created_at
+        FROM live_chat_messages c
+        JOIN users u ON u.id = c.user_id
+        WHERE c.session_id = $1
+          AND ($2::uuid IS NULL OR c.created_at < (SELECT created_at FROM live_chat_messages WHERE id = $2))
+        ORDER BY c.created_at DESC
+        LIMIT $3
+        "#
+    )
+    .bind(session_id)
+    .bind(params.before)
+    .bind(limit)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(messages))
+}
+
+// Post to the live chat; only the host and joined viewers can
+#[utoipa::path(
+    post,
+    path = "/api/v1/live/{session_id}/chat",
+    tag = "live",
+    params(("session_id" = Uuid, Path, description = "Live session ID")),
+    request_body = SendLiveChatRequest,
+    responses(
+        (status = 201, body = LiveChatMessage),
+        (status = 400, description = "Empty, too long, or language that isn't allowed"),
+        (status = 403, description = "The caller isn't in the session"),
+        (status = 404, description = "No live session with this ID"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn send_live_chat(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<Uuid>,
+    Json(payload): Json<SendLiveChatRequest>,
+) -> Result<(StatusCode, Json<LiveChatMessage>), (StatusCode, String)> {
+    let content = payload.content.trim();
+    if content.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Messages need content".to_string()));
+    }
+    if content.chars().count() > CHAT_MAX_LENGTH {
+        return Err((StatusCode::BAD_REQUEST, format!("Messages are limited to {} characters", CHAT_MAX_LENGTH)));
+    }
+
+    let in_session = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT l.host_id = $2 OR EXISTS (
+            SELECT 1 FROM live_viewers v
+            WHERE v.session_id = l.id AND v.user_id = $2 AND v.joined_at IS NOT NULL AND v.left_at IS NULL
+        )
+        FROM live_sessions l
+        WHERE l.id = $1 AND l.status = 'live'
+        "#
+    )
+    .bind(session_id)
+    .bind(user.id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(db_error)?
+    .ok_or_else(not_found)?;
+    if !in_session {
+        return Err((StatusCode::FORBIDDEN, "Join the live session to chat".to_string()));
+    }
+
+    let screened = crate::text_moderation::screen(&state.pool, content).await?;
+    let message = sqlx::query_as::<_, LiveChatMessage>(
+        r#"
+        INSERT INTO live_chat_messages (session_id, user_id, content) VALUES ($1, $2, $3)
+        RETURNING id, session_id, user_id, $4::text AS username, content, created_at
+        "#
+    )
+    .bind(session_id)
+    .bind(user.id)
+    .bind(&screened.text)
+    .bind(&user.username)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+    screened
+        .record(&state.pool, crate::text_moderation::CONTENT_LIVE_CHAT, message.id, user.id)
+        .await;
+
+    let event = WsMessage::LiveChatMessage {
+        id: message.id,
+        session_id,
+        user_id: user.id,
+        username: user.username.clone(),
+        content: message.content.clone(),
+        created_at: crate::timestamps::format(message.created_at),
+    };
+    for participant in participants(&state.pool, session_id).await {
+        send_to_user(&state.connections, participant, &event);
+    }
+
+    Ok((StatusCode::CREATED, Json(message)))
+}
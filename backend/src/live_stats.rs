@@ -0,0 +1,183 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::permissions::{CanViewAnalytics, Permitted};
+use crate::redis_client::RedisClient;
+use crate::websocket::Connections;
+use crate::AppState;
+
+// Live concurrency for the admin dashboard. Socket counts come from this
+// server's connection map, online users from Redis presence and message
+// throughput from a per-minute Redis counter. Once a minute a sample of all
+// three is appended to a Redis stream that keeps the last 24 hours. Socket lag
+// counts are this server's since it started.
+
+const HISTORY_KEY: &str = "analytics:live";
+const HISTORY_RETENTION_MS: i64 = 24 * 60 * 60 * 1000;
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_HISTORY_MINUTES: i64 = 60;
+const MAX_HISTORY_MINUTES: i64 = 24 * 60;
+
+fn messages_key(minute: i64) -> String {
+    format!("live:messages:{}", minute)
+}
+
+fn current_minute() -> i64 {
+    Utc::now().timestamp() / 60
+}
+
+/// Count a sent message towards this minute's throughput
+pub async fn count_message(redis: &Mutex<RedisClient>) {
+    let mut redis = redis.lock().await;
+    let _ = redis.cache_incr(&messages_key(current_minute()), 180).await;
+}
+
+/// Open sockets and the distinct users behind them, on this server
+fn socket_counts(connections: &Connections) -> (i64, i64) {
+    connections.iter().fold((0, 0), |(sockets, users), entry| {
+        let receivers = entry.value().receiver_count() as i64;
+        (sockets + receivers, users + i64::from(receivers > 0))
+    })
+}
+
+async fn messages_in_minute(redis: &mut RedisClient, minute: i64) -> i64 {
+    redis
+        .cache_get(&messages_key(minute))
+        .await
+        .ok()
+        .flatten()
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Append a sample to the history stream every minute
+pub async fn record_samples(connections: Connections, redis: Arc<Mutex<RedisClient>>) {
+    let mut ticker = tokio::time::interval(SAMPLE_INTERVAL);
+
+    loop {
+        ticker.tick().await;
+
+        let (sockets, connected_users) = socket_counts(&connections);
+        let mut redis = redis.lock().await;
+        let online_users = redis.count_online_users().await.unwrap_or(0);
+        // The minute that just finished; the current one is still filling up
+        let messages = messages_in_minute(&mut redis, current_minute() - 1).await;
+        let min_id = Utc::now().timestamp_millis() - HISTORY_RETENTION_MS;
+        let fields = [
+            ("websocket_connections", sockets),
+            ("connected_users", connected_users),
+            ("online_users", online_users),
+            ("messages_per_minute", messages),
+        ];
+        if let Err(e) = redis.stream_add(HISTORY_KEY, min_id, &fields).await {
+            tracing::warn!("Failed to record live stats sample: {}", e);
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LiveStatsQuery {
+    /// Minutes of history to return (default 60, max 1440)
+    minutes: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct LiveStatsSample {
+    at: DateTime<Utc>,
+    websocket_connections: i64,
+    connected_users: i64,
+    online_users: i64,
+    messages_per_minute: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct LiveStats {
+    /// Open WebSocket connections on this server
+    websocket_connections: i64,
+    /// Distinct users with at least one socket on this server
+    connected_users: i64,
+    /// Users online anywhere, from Redis presence
+    online_users: i64,
+    /// Messages sent in the last full minute
+    messages_last_minute: i64,
+    /// Messages sent so far this minute
+    messages_this_minute: i64,
+    /// Times a socket on this server fell behind its event buffer
+    lagged_sockets: u64,
+    /// Events those sockets missed
+    skipped_events: u64,
+    /// Lagging sockets closed under websocket.disconnect_on_lag
+    lag_disconnects: u64,
+    /// One sample per minute, oldest first
+    history: Vec<LiveStatsSample>,
+}
+
+// Current connection, presence and message throughput figures
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/analytics/live",
+    tag = "admin",
+    params(LiveStatsQuery),
+    responses((status = 200, body = LiveStats), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_live_stats(
+    _admin: Permitted<CanViewAnalytics>,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<LiveStatsQuery>,
+) -> Result<Json<LiveStats>, (StatusCode, String)> {
+    let minutes = params.minutes.unwrap_or(DEFAULT_HISTORY_MINUTES).clamp(1, MAX_HISTORY_MINUTES);
+    let (websocket_connections, connected_users) = socket_counts(&state.connections);
+    let lag = crate::websocket::lag_counts();
+
+    let mut redis = state.redis.lock().await;
+    let redis_error = |e: redis::RedisError| {
+        eprintln!("Live stats error: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch live stats".to_string())
+    };
+    let online_users = redis.count_online_users().await.map_err(redis_error)?;
+    let minute = current_minute();
+    let messages_last_minute = messages_in_minute(&mut redis, minute - 1).await;
+    let messages_this_minute = messages_in_minute(&mut redis, minute).await;
+
+    let since = Utc::now().timestamp_millis() - minutes * 60 * 1000;
+    let entries = redis.stream_range(HISTORY_KEY, since).await.map_err(redis_error)?;
+    drop(redis);
+
+    let history = entries
+        .into_iter()
+        .filter_map(|(id, fields)| {
+            let millis = id.split('-').next()?.parse::<i64>().ok()?;
+            let field = |name: &str| fields.iter().find(|(f, _)| f == name).map_or(0, |(_, v)| *v);
+            Some(LiveStatsSample {
+                at: DateTime::from_timestamp_millis(millis)?,
+                websocket_connections: field("websocket_connections"),
+                connected_users: field("connected_users"),
+                online_users: field("online_users"),
+                messages_per_minute: field("messages_per_minute"),
+            })
+        })
+        .collect();
+
+    Ok(Json(LiveStats {
+        websocket_connections,
+        connected_users,
+        online_users,
+        messages_last_minute,
+        messages_this_minute,
+        lagged_sockets: lag.lagged_sockets,
+        skipped_events: lag.skipped_events,
+        lag_disconnects: lag.disconnects,
+        history,
+    }))
+}
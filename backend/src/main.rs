@@ -2,43 +2,127 @@ use axum::{
     Router,
     routing::{post, get},
     response::Html,
-    Json,
     extract::DefaultBodyLimit,
 };
 use std::sync::Arc;
 use tokio::net::TcpListener;
 use tower_http::cors::{CorsLayer, Any};
-use axum::http::HeaderValue;
 use tower_http::services::ServeDir;
 use dashmap::DashMap;
 
 mod auth;
+mod client_ip;
 mod db;
 mod redis_client;
 mod websocket;
 mod chat;
+mod room_retention;
 mod media;
+mod media_proxy;
+mod mime_sniff;
+mod upload_stream;
+mod gifs;
+mod graphql;
+mod grpc;
+mod translation;
+mod captioning;
+mod scanning;
+mod mailer;
+mod sms;
+mod phone;
 mod expiration;
 mod stories;
+mod story_boosts;
+mod referrals;
+mod invites;
+mod waitlist;
+mod story_media;
+mod story_collaborators;
+mod story_permissions;
+mod communities;
+mod appeals;
+mod channels;
+mod live;
+mod calls;
 mod social;
+mod muted_words;
 mod settings;
+mod self_check;
+mod sigv4;
+mod storage;
+mod age_gate;
+mod supervision;
+mod takedowns;
+mod query_metrics;
+mod counters;
+mod media_outbox;
+mod ad_targeting;
+mod ad_fraud;
+mod ad_conversions;
+mod ad_billing;
+mod ad_review;
+mod chat_export;
+mod insights;
+mod retention;
+mod live_stats;
+mod signup_guard;
+mod username_policy;
+mod text_moderation;
+mod runtime_settings;
+mod message_requests;
+mod cache;
+mod cdn;
+mod etag;
+mod body_limit;
+mod compression;
+mod fieldsets;
+mod federation;
+mod syndication;
+mod public_pages;
 mod discovery;
 mod algorithm;
 mod streaks;
 mod notifications;
+mod announcements;
+mod activity_status;
 mod admin;
+mod permissions;
 mod video_render;
 mod bucket_cleanup;
+mod idempotency;
+mod feature_flags;
+mod health;
+mod quotas;
+mod webhooks;
+mod bots;
+mod jobs;
+mod memories;
+mod versioning;
+mod timestamps;
+mod i18n;
+mod openapi;
 
 use redis_client::RedisClient;
 use media::MediaService;
-use expiration::ExpirationService;
+use versioning::ApiVersion;
 
 pub struct AppState {
     pool: Arc<sqlx::PgPool>,
     redis: Arc<tokio::sync::Mutex<RedisClient>>,
     media_service: Arc<MediaService>,
     connections: websocket::Connections,
+    read_replica: Option<Arc<db::ReadReplica>>,
+}
+
+impl AppState {
+    /// Pool for read-heavy endpoints that tolerate slightly stale data: the
+    /// read replica when one is configured and healthy, the primary otherwise
+    fn read_pool(&self) -> &sqlx::PgPool {
+        match &self.read_replica {
+            Some(replica) => replica.pool_or(&self.pool),
+            None => &self.pool,
+        }
+    }
 }
 
 async fn serve_login() -> Html<String> {
@@ -90,23 +174,363 @@ async fn serve_advertise() -> Html<String> {
     Html(html)
 }
 
-async fn health_check() -> Json<serde_json::Value> {
-    Json(serde_json::json!({
-        "status": "healthy",
-        "service": "relays.social",
-        "timestamp": chrono::Utc::now().to_rfc3339()
-    }))
+// Every REST endpoint, relative to the version prefix. Mounted under /api/v1
+// and, for existing clients, the deprecated unversioned /api alias.
+fn api_routes() -> versioning::RouteTable {
+    versioning::RouteTable::new()
+        // Auth endpoints
+        .route("/signup", post(auth::signup))
+        .route("/signup/mode", get(invites::get_signup_mode))
+        .route("/invites/validate", post(invites::validate_invite))
+        .route("/waitlist", post(waitlist::join_waitlist))
+        .route("/waitlist/position", get(waitlist::get_position))
+        .route("/login", post(auth::login))
+        .route("/login/phone", post(auth::login_with_phone))
+        .route("/account-recovery/phone", post(auth::recover_with_phone))
+
+        // Phone numbers
+        .route("/phone/code", post(phone::request_code))
+        .route("/phone/verify", post(phone::verify_phone))
+        .route("/contacts/match", post(phone::match_contacts))
+
+        // Chat endpoints
+        .route("/chats", post(chat::create_chat))
+        .route("/users/:user_id/chats", get(chat::get_user_chats).layer(axum::middleware::from_fn(etag::conditional_get)))
+        .route("/users/:user_id/chats/:chat_room_id/messages", get(chat::get_messages))
+        .route("/users/:user_id/messages/send", post(chat::send_message_http))
+        .route("/users/:user_id/messages/:message_id/view", post(chat::mark_message_viewed))
+        .route("/users/:user_id/messages/:message_id/save", post(chat::save_message))
+        .route("/users/:user_id/messages/:message_id/unsave", axum::routing::delete(chat::unsave_message))
+        .route("/users/:user_id/saved-messages", get(chat::get_saved_messages))
+        .route("/users/:user_id/messages/:message_id/delete-for-me", post(chat::delete_message_for_me))
+        .route("/users/:user_id/chats/:chat_room_id/export", post(chat_export::export_chat))
+
+        // Public story feeds; the segment is a username (the router needs one
+        // parameter name per position)
+        .route("/users/:user_id/feed.json", get(syndication::get_json_feed))
+        .route("/users/:user_id/feed.rss", get(syndication::get_rss_feed))
+
+        .route("/message-requests", get(message_requests::list_requests))
+        .route("/message-requests/:request_id/accept", post(message_requests::accept_request))
+        .route("/message-requests/:request_id/decline", post(message_requests::decline_request))
+        .route("/messages/:message_id/media", get(chat::get_message_media))
+        .route("/messages/:message_id/translate", post(translation::translate_message))
+
+        // Media upload endpoints (with the upload body limit)
+        .upload("/media/upload", post(media::upload_image))
+        .upload("/media/upload-multipart", post(media::upload_multipart))
+        .route("/media/gifs/search", get(gifs::search_gifs))
+        .route("/media/gifs/trending", get(gifs::trending_gifs))
+        .route("/media/stickers/search", get(gifs::search_stickers))
+        .route("/media/stickers/trending", get(gifs::trending_stickers))
+        .route("/media/*key", get(media_proxy::get_media))
+
+        // Stories endpoints (story creation and rendering take uploads)
+        .upload("/stories/create", post(stories::create_story_multipart))
+        .upload("/stories/render", post(video_render::render_video))
+        .route("/stories/proxy/*s3_key", get(video_render::proxy_rendered_video))
+        .route("/stories/user/:user_id", get(stories::get_user_stories))
+        .route("/stories/feed/:viewer_id", get(stories::get_feed_stories))
+        .route("/stories/by-user/:viewer_id", get(stories::get_stories_by_user))
+        .route("/stories/:story_id/view/:viewer_id", post(stories::mark_story_viewed))
+        .route("/stories/:story_id/items/:position/view/:viewer_id", post(story_media::mark_item_viewed))
+        .route("/stories/:story_id/collaborators", post(story_collaborators::invite_collaborator))
+        .route("/stories/collaborations", get(story_collaborators::list_collaborations))
+        .route("/stories/collaborations/:collaboration_id", axum::routing::delete(story_collaborators::end_collaboration))
+        .route("/stories/collaborations/:collaboration_id/accept", post(story_collaborators::accept_collaboration))
+        .route("/stories/collaborations/:collaboration_id/decline", post(story_collaborators::decline_collaboration))
+        .route("/stories/:story_id/permissions", get(story_permissions::get_permissions).put(story_permissions::update_permissions))
+        .route("/stories/:story_id/share", post(story_permissions::share_story))
+        .route("/stories/:story_id/delete/:user_id", axum::routing::delete(stories::delete_story))
+        .route("/stories/:story_id/translate", post(translation::translate_story_caption))
+        .route("/stories/:story_id/boost", post(story_boosts::boost_story))
+
+        // Story archive (Memories) endpoints
+        .route("/memories", get(memories::list_memories))
+        .route("/memories/settings", get(memories::get_memory_settings))
+        .route("/memories/settings", axum::routing::put(memories::update_memory_settings))
+        .route("/memories/:memory_id", get(memories::get_memory))
+        .route("/memories/:memory_id", axum::routing::delete(memories::delete_memory))
+        .route("/memories/:memory_id/reshare", post(memories::reshare_memory))
+        .route("/highlights", post(memories::create_highlight))
+        .route("/highlights/:highlight_id", get(memories::get_highlight_items))
+        .route("/highlights/:highlight_id", axum::routing::delete(memories::delete_highlight))
+        .route("/highlights/:highlight_id/items", post(memories::add_highlight_item))
+        .route("/highlights/:highlight_id/items/:memory_id", axum::routing::delete(memories::remove_highlight_item))
+
+        // Social endpoints - Follows
+        .route("/social/follow/:follower_id/:following_id", post(social::follow_user))
+        .route("/social/unfollow/:follower_id/:following_id", post(social::unfollow_user))
+        .route("/social/follow-stats/:user_id/:viewer_id", get(social::get_follow_stats))
+        .route("/social/followers/:user_id/:viewer_id", get(social::get_followers))
+        .route("/social/following/:user_id/:viewer_id", get(social::get_following))
+
+        // Social endpoints - Likes
+        .route("/social/like/:story_id/:user_id", post(social::like_story))
+        .route("/social/unlike/:story_id/:user_id", post(social::unlike_story))
+        .route("/social/likes/:story_id", get(social::get_story_likes))
+
+        // Social endpoints - Comments
+        .route("/social/comment/:story_id/:user_id", post(social::add_comment))
+        .route("/social/comments/:story_id", get(social::get_story_comments))
+        .route("/social/comment/delete/:comment_id/:user_id", axum::routing::delete(social::delete_comment))
+        
+        // Social endpoints - Comment Replies
+        .route("/social/reply/:story_id/:user_id", post(social::add_reply))
+        .route("/social/replies/:comment_id", get(social::get_comment_replies))
+
+        // Muted words, for comments and messages
+        .route("/muted-words", get(muted_words::list_muted_words).post(muted_words::add_muted_word))
+        .route("/muted-words/:word_id", axum::routing::delete(muted_words::delete_muted_word))
+
+        // Profile endpoints
+        .route("/profile/:user_id/:viewer_id", get(social::get_user_profile).layer(axum::middleware::from_fn(etag::conditional_get)))
+        .route("/users/:user_id/account-type", axum::routing::put(insights::set_account_type))
+        .route("/users/:user_id/insights", get(insights::get_insights))
+        .route("/profile/:user_id/stories", get(social::get_user_stories))
+        .route("/profile/:user_id/highlights", get(memories::get_user_highlights))
+        .route("/profile/:user_id/update", post(social::update_user_profile))
+
+        // Settings endpoints
+        .route("/settings/:user_id", get(settings::get_user_settings))
+        .route("/settings/:user_id/username", post(settings::update_username))
+        .route("/settings/:user_id/email", post(settings::update_email))
+        .route("/settings/:user_id/phone", get(phone::get_phone_settings).put(phone::update_phone_settings).delete(phone::remove_phone))
+        .route("/settings/:user_id/password", post(settings::change_password))
+        .route("/settings/:user_id/delete", axum::routing::delete(settings::delete_account))
+        .route("/settings/:user_id/usage", get(settings::get_usage))
+        .route("/settings/:user_id/demographics", get(settings::get_demographics).put(settings::update_demographics))
+        .route("/settings/:user_id/dm-privacy", get(settings::get_dm_privacy).put(settings::update_dm_privacy))
+        .route("/settings/:user_id/comment-audience", get(settings::get_comment_audience).put(settings::update_comment_audience))
+        .route("/settings/:user_id/locale", get(settings::get_locale).put(settings::update_locale))
+        .route("/settings/:user_id/federation", get(settings::get_federation).put(settings::update_federation))
+        .route("/appeals", get(appeals::list_my_appeals).post(appeals::submit_appeal))
+        .route("/settings/:user_id/activity-status", get(settings::get_activity_status).put(settings::update_activity_status))
+
+        // Guardian supervision endpoints
+        .route("/supervision", get(supervision::list_links))
+        .route("/supervision/requests", post(supervision::request_supervision))
+        .route("/supervision/:link_id", axum::routing::delete(supervision::end_link))
+        .route("/supervision/:link_id/accept", post(supervision::accept_link))
+        .route("/supervision/:link_id/decline", post(supervision::decline_link))
+        .route("/supervision/:link_id/limits", axum::routing::put(supervision::update_limits))
+        .route("/supervision/:link_id/activity", get(supervision::get_activity))
+
+        // Discovery endpoints
+        .route("/discovery/search/:viewer_id", get(discovery::search_users))
+        .route("/discovery/popular/:viewer_id", get(discovery::get_popular_users))
+        .route("/discovery/suggested/:viewer_id", get(discovery::get_suggested_users))
+        .route("/discovery/avatar/:user_id", post(discovery::update_avatar))
+        .route("/discovery/refresh-popular", post(discovery::refresh_popular_users_view))
+
+        // Algorithm/Feed endpoints
+        .route("/feed/personalized/:user_id", get(algorithm::get_personalized_feed))
+        .route("/feed/interaction/:user_id/:story_id", post(algorithm::record_interaction))
+        .route("/feed/recalculate", post(algorithm::recalculate_all_feeds))
+
+        // Streak endpoints
+        .route("/streaks/update/:user1_id/:user2_id", post(streaks::update_streak))
+        .route("/streaks/:user1_id/:user2_id", get(streaks::get_streak))
+        .route("/streaks/user/:user_id", get(streaks::get_user_streaks))
+
+        // GraphQL gateway for the mobile client
+        .route("/graphql", post(graphql::execute))
+        .route("/graphql/schema", get(graphql::sdl))
+
+        // Notification endpoints
+        .route("/notifications/:user_id", get(notifications::get_notifications).layer(axum::middleware::from_fn(etag::conditional_get)).delete(notifications::clear_notifications))
+        .route("/notifications/:user_id/unread", get(notifications::get_unread_count).layer(axum::middleware::from_fn(etag::conditional_get)))
+        .route("/notifications/:user_id/:notification_id/read", post(notifications::mark_notification_read))
+        .route("/notifications/:user_id/read-all", post(notifications::mark_all_notifications_read))
+        .route("/notifications/:user_id/:notification_id", axum::routing::delete(notifications::delete_notification))
+        .route("/announcements", get(announcements::list_my_announcements))
+        .route("/announcements/:announcement_id/read", post(announcements::mark_announcement_read))
+
+        // Admin endpoints (protected by the AdminUser and Permitted extractors)
+        .route("/admin/users", get(admin::list_users))
+        .route("/admin/users/:user_id/ban", post(admin::ban_user))
+        .route("/admin/users/:user_id/unban", post(admin::unban_user))
+        .route("/admin/users/:user_id/role", post(admin::change_user_role))
+        .route("/admin/users/:user_id", axum::routing::delete(admin::delete_user))
+        .route("/admin/roles", get(permissions::list_roles).post(permissions::create_role))
+        .route("/admin/roles/:name", axum::routing::put(permissions::update_role).delete(permissions::delete_role))
+        .route("/admin/permissions", get(permissions::list_permissions))
+        .route("/admin/logs", get(admin::get_admin_logs))
+        .route("/admin/analytics", get(admin::get_analytics))
+        .route("/admin/analytics/retention", get(retention::get_retention))
+        .route("/admin/analytics/live", get(live_stats::get_live_stats))
+        .route("/admin/ads", get(admin::list_ads))
+        .route("/admin/ads", post(admin::create_ad))
+        .route("/admin/ads/:ad_id", axum::routing::patch(admin::update_ad))
+        .route("/admin/ads/:ad_id", axum::routing::delete(admin::delete_ad))
+        .route("/admin/ads/review-queue", get(ad_review::get_review_queue))
+        .route("/admin/ads/review-metrics", get(ad_review::get_review_metrics))
+        .route("/admin/ads/:ad_id/approve", post(admin::approve_ad))
+        .route("/admin/ads/:ad_id/reject", post(admin::reject_ad))
+        .route("/admin/ads/:ad_id/analytics/location", get(admin::get_ad_location_analytics))
+        .route("/admin/ads/:ad_id/analytics/demographics", get(admin::get_ad_demographics_analytics))
+        .route("/admin/ads/:ad_id/analytics/fraud", get(ad_fraud::get_ad_fraud_report))
+        .route("/admin/ads/:ad_id/analytics/conversions", get(ad_conversions::get_conversion_report))
+        .route("/admin/signup/blocked-domains", get(signup_guard::list_blocked_domains).post(signup_guard::block_domain))
+        .route("/admin/signup/blocked-domains/:domain", axum::routing::delete(signup_guard::unblock_domain))
+        .route("/admin/usernames/reserved", get(username_policy::list_reserved).post(username_policy::reserve))
+        .route("/admin/usernames/reserved/:name", axum::routing::delete(username_policy::release))
+        .route("/admin/referral-rewards", get(referrals::list_rewards).post(referrals::create_reward))
+        .route("/admin/referral-rewards/:reward_id", axum::routing::delete(referrals::delete_reward))
+        .route("/admin/invites/batches", get(invites::list_batches).post(invites::create_batch))
+        .route("/admin/invites/batches/:batch_id", get(invites::get_batch))
+        .route("/admin/invites/codes/:code_id/revoke", post(invites::revoke_code))
+        .route("/admin/invites/stats", get(invites::get_invite_stats))
+        .route("/admin/waitlist", get(waitlist::list_waitlist))
+        .route("/admin/waitlist/release", post(waitlist::release_waitlist))
+        .route("/admin/users/:user_id/invite-allowance", axum::routing::put(invites::set_invite_allowance))
+        .route("/admin/moderation/terms", get(text_moderation::list_terms).post(text_moderation::add_term))
+        .route("/admin/moderation/terms/:term", axum::routing::delete(text_moderation::remove_term))
+        .route("/admin/moderation/flags", get(text_moderation::list_flags))
+        .route("/admin/moderation/flags/:flag_id/review", post(text_moderation::review_flag))
+        .route("/admin/ads/fraud/flags", get(ad_fraud::list_fraud_flags))
+        .route("/admin/ads/fraud/flags/:flag_id/clear", post(ad_fraud::clear_fraud_flag))
+
+        // Background job endpoints
+        .route("/admin/jobs", get(jobs::list_jobs))
+        .route("/admin/jobs", post(jobs::create_job))
+        .route("/admin/jobs/stats", get(jobs::get_job_stats))
+        .route("/admin/jobs/:job_id", get(jobs::get_job))
+        .route("/admin/jobs/:job_id/retry", post(jobs::retry_job))
+        .route("/admin/jobs/:job_id/cancel", post(jobs::cancel_job))
+        .route("/jobs/:job_id", get(jobs::get_job_status))
+
+        // Feature flags
+        .route("/feature-flags", get(feature_flags::get_feature_flags))
+        .route("/admin/feature-flags", get(feature_flags::list_flags))
+        .route("/admin/feature-flags", post(feature_flags::create_flag))
+        .route("/admin/feature-flags/:key", axum::routing::put(feature_flags::update_flag))
+        .route("/admin/feature-flags/:key", axum::routing::delete(feature_flags::delete_flag))
+        .route("/admin/settings", get(runtime_settings::list_settings))
+        .route("/admin/settings/:key", axum::routing::put(runtime_settings::update_setting).delete(runtime_settings::reset_setting))
+        .route("/admin/settings/:key/history", get(runtime_settings::get_setting_history))
+
+        // Webhook endpoints
+        .route("/webhooks", get(webhooks::list_webhooks))
+        .route("/webhooks", post(webhooks::create_webhook))
+        .route("/webhooks/:webhook_id", axum::routing::patch(webhooks::update_webhook))
+        .route("/webhooks/:webhook_id", axum::routing::delete(webhooks::delete_webhook))
+        .route("/webhooks/:webhook_id/test", post(webhooks::test_webhook))
+        .route("/webhooks/:webhook_id/deliveries", get(webhooks::list_deliveries))
+        .route("/webhooks/:webhook_id/deliveries/:delivery_id/redeliver", post(webhooks::redeliver))
+        .route("/admin/announcements", get(announcements::list_announcements))
+        .route("/admin/announcements", post(announcements::create_announcement))
+        .route("/admin/announcements/:announcement_id", axum::routing::delete(announcements::cancel_announcement))
+        .route("/admin/takedowns", get(takedowns::list_takedowns).post(takedowns::create_takedown))
+        .route("/admin/takedowns/:takedown_id/lift", post(takedowns::lift_takedown))
+        .route("/admin/appeals", get(appeals::list_appeals))
+        .route("/admin/appeals/:appeal_id/decide", post(appeals::decide_appeal))
+        .route("/admin/diagnostics/queries", get(query_metrics::get_query_diagnostics))
+        .route("/admin/counters/reconcile", post(counters::trigger_reconcile))
+        .route("/admin/counters/users/:user_id/reconcile", post(counters::reconcile_user))
+        .route("/admin/counters/stories/:story_id/reconcile", post(counters::reconcile_story))
+        .route("/admin/media/quarantine", get(scanning::list_quarantine))
+        .route("/admin/media/quarantine/:sha256/release", post(scanning::release_quarantined))
+        .route("/admin/webhooks/deliveries", get(webhooks::admin_list_deliveries))
+        .route("/admin/mail/deliveries", get(mailer::list_deliveries))
+        .route("/admin/mail/suppressions", get(mailer::list_suppressions).post(mailer::add_suppression))
+        .route("/admin/mail/suppressions/:email", axum::routing::delete(mailer::remove_suppression))
+        .route("/mail/events/:provider", post(mailer::receive_event))
+        .route("/bots", get(bots::list_bots))
+        .route("/bots", post(bots::create_bot))
+        .route("/bots/:bot_id", axum::routing::delete(bots::delete_bot))
+        .route("/bots/:bot_id/token", post(bots::rotate_bot_token))
+        .route("/communities", post(communities::create_community))
+        .route("/communities/discover", get(communities::discover_communities))
+        .route("/communities/invite/:invite_code", get(communities::get_community_by_invite))
+        .route("/communities/invite/:invite_code/join", post(communities::join_community_by_invite))
+        .route("/communities/:room_id", get(communities::get_community).patch(communities::update_community))
+        .route("/communities/:room_id/join", post(communities::join_community))
+        .route("/communities/:room_id/invite-link", post(communities::reset_invite_link))
+        .route("/communities/:room_id/members", get(communities::list_members))
+        .route("/communities/:room_id/members/:user_id", axum::routing::delete(communities::remove_member))
+        .route("/communities/:room_id/members/:user_id/role", axum::routing::put(communities::set_member_role))
+        .route("/communities/:room_id/requests", get(communities::list_join_requests))
+        .route("/communities/:room_id/requests/:user_id/approve", post(communities::approve_join_request))
+        .route("/communities/:room_id/requests/:user_id/decline", post(communities::decline_join_request))
+        .route("/communities/:room_id/bans", get(communities::list_bans))
+        .route("/communities/:room_id/bans/:user_id", post(communities::ban_member).delete(communities::unban_member))
+        .route("/communities/:room_id/messages/:message_id", axum::routing::delete(communities::remove_message))
+        .route("/communities/:room_id/mod-log", get(communities::get_mod_log))
+        .route("/channels", post(channels::create_channel))
+        .route("/channels/:room_id", get(channels::get_channel))
+        .route("/users/:user_id/channel", get(channels::get_user_channel))
+        .route("/channels/:room_id/subscription", post(channels::subscribe).delete(channels::unsubscribe))
+        .route("/channels/:room_id/messages/:message_id/reaction", axum::routing::put(channels::react).delete(channels::remove_reaction))
+        .route("/channels/:room_id/messages/:message_id/reactions", get(channels::get_reactions))
+        .route("/calls/ice-servers", get(calls::get_ice_servers))
+        .route("/chats/:chat_room_id/retention", axum::routing::put(room_retention::set_retention))
+        .route("/chats/:chat_room_id/calls", get(calls::list_calls))
+        .route("/live", get(live::list_live).post(live::start_live))
+        .route("/live/:session_id", get(live::get_live))
+        .route("/live/:session_id/end", post(live::end_live))
+        .route("/live/:session_id/join", post(live::join_live))
+        .route("/live/:session_id/leave", post(live::leave_live))
+        .route("/live/:session_id/chat", get(live::get_live_chat).post(live::send_live_chat))
+        .route("/chats/:chat_room_id/bots", post(bots::add_bot_to_chat))
+        .route("/chats/:chat_room_id/bots/:bot_id", axum::routing::delete(bots::remove_bot_from_chat))
+        .route("/bot/me", get(bots::get_me))
+        .route("/bot/updates", get(bots::get_updates))
+        .route("/bot/messages", post(bots::send_message))
+        .route("/bot/webhook", axum::routing::put(bots::set_webhook))
+        .route("/bot/webhook", axum::routing::delete(bots::delete_webhook))
+
+        // Public ad endpoints (for showing ads to users)
+        .route("/ads/next/:user_id", get(admin::get_next_ad))
+        .route("/ads/:ad_id/impression/:user_id", post(admin::record_ad_impression))
+        .route("/ads/:ad_id/click/:user_id", post(admin::record_ad_click))
+        .route("/ads/:ad_id/conversion", post(ad_conversions::record_conversion))
+        .route("/ads/:ad_id/pixel.gif", get(ad_conversions::conversion_pixel))
+        .route("/ads/:ad_id/budget/top-up", post(ad_billing::top_up_budget))
+        .route("/ads/:ad_id/spend", get(ad_billing::get_spend_report))
+        .route("/ads/:ad_id/reviews", get(ad_review::list_revisions))
+        .route("/ads/:ad_id/resubmit", post(ad_review::resubmit_ad))
+
+        // Boosted stories
+        .route("/boosts", get(story_boosts::list_boosts))
+        .route("/boosts/:boost_id", get(story_boosts::get_boost_report))
+        .route("/boosts/:boost_id/cancel", post(story_boosts::cancel_boost))
+        .route("/boosts/:boost_id/impression", post(story_boosts::record_impression))
+        .route("/boosts/:boost_id/click", post(story_boosts::record_click))
+
+        // Referrals
+        .route("/referrals/:user_id", get(referrals::get_dashboard))
+        .route("/users/:user_id/badges", get(referrals::get_badges))
+
+        // Invites
+        .route("/invites/:user_id", get(invites::get_my_invites).post(invites::create_invite))
+
+        // Self-service ad creation endpoints
+        .route("/ads/create", post(admin::create_ad_public))
+        .route("/ads/:ad_id/checkout", post(admin::create_checkout_session))
+        .route("/stripe/webhook", post(admin::stripe_webhook))
 }
 
 #[tokio::main]
 async fn main() {
     dotenvy::dotenv().ok(); // Load .env because Rust refuses otherwise
 
+    // tracing output, filtered by RUST_LOG; this crate logs at info and up,
+    // dependencies only warnings
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn,backend=info")),
+        )
+        .init();
+
     println!(" Starting RelayHub server...");
+    self_check::environment();
 
     // Initialize database pool
     let pool = Arc::new(db::init_pool().await);
     println!(" Database connected");
+    let read_replica = db::init_read_replica();
 
     // Initialize Redis
     let redis_url = std::env::var("REDIS_URL")
@@ -120,40 +544,54 @@ async fn main() {
     let media_service = Arc::new(MediaService::new().await);
     println!("✓ S3 media service initialized");
 
+    // Fail fast on a missing migration or an unusable bucket
+    self_check::dependencies(&pool, &media_service).await;
+
     // Initialize WebSocket connections map
     let connections = Arc::new(DashMap::new());
 
+    // Load admin overrides before serving, then keep them current
+    runtime_settings::refresh(&pool, &redis).await;
+    tokio::spawn(runtime_settings::keep_fresh(pool.clone(), redis.clone()));
+    println!("✓ Runtime settings loaded");
+
     // Create app state
     let state = Arc::new(AppState {
         pool: pool.clone(),
         redis: redis.clone(),
         media_service: media_service.clone(),
         connections: connections.clone(),
+        read_replica,
     });
 
-    // Start background expiration service
-    let expiration_service = Arc::new(ExpirationService::new(
-        pool.clone(),
-        media_service.clone(),
-    ));
-    let expiration_service_clone = expiration_service.clone();
-    tokio::spawn(async move {
-        expiration_service_clone.start().await;
-    });
-    println!("✓ Message expiration service started");
-
-    // Start background bucket cleanup service
-    let cleanup_s3_client = media_service.s3_client.clone();
-    let cleanup_bucket = media_service.bucket_name.clone();
-    let cleanup_pool = pool.clone();
-    tokio::spawn(async move {
-        bucket_cleanup::run_scheduled_cleanup(
-            &cleanup_s3_client,
-            &cleanup_bucket,
-            &cleanup_pool,
-        ).await;
-    });
-    println!("✓ Bucket cleanup service started");
+    // Start background job workers and recurring jobs
+    let worker_count = std::env::var("JOB_WORKERS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(4);
+    jobs::start_workers(state.clone(), worker_count);
+    jobs::schedule_recurring(pool.clone(), jobs::EXPIRE_CONTENT, std::time::Duration::from_secs(60));
+    jobs::schedule_recurring(pool.clone(), jobs::BUCKET_CLEANUP, std::time::Duration::from_secs(6 * 60 * 60));
+    jobs::schedule_recurring(pool.clone(), jobs::REFRESH_POPULAR_USERS, std::time::Duration::from_secs(15 * 60));
+    // Hourly so yesterday's analytics row lands soon after midnight; days already written are skipped
+    jobs::schedule_recurring(pool.clone(), jobs::SNAPSHOT_ANALYTICS, std::time::Duration::from_secs(60 * 60));
+    jobs::schedule_recurring(pool.clone(), jobs::RECONCILE_COUNTERS, std::time::Duration::from_secs(24 * 60 * 60));
+    jobs::schedule_recurring(pool.clone(), jobs::PROCESS_MEDIA_OUTBOX, std::time::Duration::from_secs(5 * 60));
+    jobs::schedule_recurring(pool.clone(), jobs::DETECT_CLICK_FRAUD, std::time::Duration::from_secs(15 * 60));
+    jobs::schedule_recurring(pool.clone(), jobs::ROLL_UP_INSIGHTS, std::time::Duration::from_secs(60 * 60));
+    jobs::schedule_recurring(pool.clone(), jobs::PROCESS_REFERRALS, std::time::Duration::from_secs(5 * 60));
+    jobs::schedule_recurring(pool.clone(), jobs::COMPUTE_RETENTION, std::time::Duration::from_secs(24 * 60 * 60));
+    // Checked hourly; the report goes out once the previous one is an interval old
+    jobs::schedule_recurring(pool.clone(), jobs::SEND_ANALYTICS_REPORT, std::time::Duration::from_secs(60 * 60));
+    println!("✓ Background job workers started ({} workers)", worker_count);
+
+    // Reap WebSocket entries whose sockets died without cleaning up
+    tokio::spawn(websocket::reap_stale_connections(connections.clone(), redis.clone()));
+    tokio::spawn(live_stats::record_samples(connections.clone(), redis.clone()));
+    // Push notifications to connected users as they're written
+    tokio::spawn(notifications::push_new(pool.clone(), connections.clone()));
+    // Internal gRPC API for other services, when GRPC_PORT is set
+    grpc::spawn(state.clone());
 
     // Build router
     let app = Router::new()
@@ -166,129 +604,49 @@ async fn main() {
         .route("/admin-panel", get(serve_admin_panel))
         .route("/advertise", get(serve_advertise))
 
-        // Auth endpoints
-        .route("/api/signup", post(auth::signup))
-        .route("/api/login", post(auth::login))
-
-        // Chat endpoints
-        .route("/api/chats", post(chat::create_chat))
-        .route("/api/users/:user_id/chats", get(chat::get_user_chats))
-        .route("/api/users/:user_id/chats/:chat_room_id/messages", get(chat::get_messages))
-        .route("/api/users/:user_id/messages/send", post(chat::send_message_http))
-        .route("/api/users/:user_id/messages/:message_id/view", post(chat::mark_message_viewed))
-        .route("/api/users/:user_id/messages/:message_id/save", post(chat::save_message))
-        .route("/api/users/:user_id/messages/:message_id/unsave", axum::routing::delete(chat::unsave_message))
-
-        // Media upload endpoints (with increased body limit for file uploads)
-        .route("/api/media/upload", post(media::upload_image))
-        .route("/api/media/upload-multipart", post(media::upload_multipart))
-
-        // Stories endpoints (also needs increased limit for media uploads)
-        .route("/api/stories/create", post(stories::create_story_multipart))
-        .route("/api/stories/render", post(video_render::render_video))
-        .route("/api/stories/proxy/*s3_key", get(video_render::proxy_rendered_video))
-        .route("/api/stories/user/:user_id", get(stories::get_user_stories))
-        .route("/api/stories/feed/:viewer_id", get(stories::get_feed_stories))
-        .route("/api/stories/by-user/:viewer_id", get(stories::get_stories_by_user))
-        .route("/api/stories/:story_id/view/:viewer_id", post(stories::mark_story_viewed))
-        .route("/api/stories/:story_id/delete/:user_id", axum::routing::delete(stories::delete_story))
-
-        // Social endpoints - Follows
-        .route("/api/social/follow/:follower_id/:following_id", post(social::follow_user))
-        .route("/api/social/unfollow/:follower_id/:following_id", post(social::unfollow_user))
-        .route("/api/social/follow-stats/:user_id/:viewer_id", get(social::get_follow_stats))
-        .route("/api/social/followers/:user_id/:viewer_id", get(social::get_followers))
-        .route("/api/social/following/:user_id/:viewer_id", get(social::get_following))
-
-        // Social endpoints - Likes
-        .route("/api/social/like/:story_id/:user_id", post(social::like_story))
-        .route("/api/social/unlike/:story_id/:user_id", post(social::unlike_story))
-        .route("/api/social/likes/:story_id", get(social::get_story_likes))
-
-        // Social endpoints - Comments
-        .route("/api/social/comment/:story_id/:user_id", post(social::add_comment))
-        .route("/api/social/comments/:story_id", get(social::get_story_comments))
-        .route("/api/social/comment/delete/:comment_id/:user_id", axum::routing::delete(social::delete_comment))
-        
-        // Social endpoints - Comment Replies
-        .route("/api/social/reply/:story_id/:user_id", post(social::add_reply))
-        .route("/api/social/replies/:comment_id", get(social::get_comment_replies))
-
-        // Profile endpoints
-        .route("/api/profile/:user_id/:viewer_id", get(social::get_user_profile))
-        .route("/api/profile/:user_id/stories", get(social::get_user_stories))
-        .route("/api/profile/:user_id/update", post(social::update_user_profile))
-
-        // Settings endpoints
-        .route("/api/settings/:user_id", get(settings::get_user_settings))
-        .route("/api/settings/:user_id/username", post(settings::update_username))
-        .route("/api/settings/:user_id/email", post(settings::update_email))
-        .route("/api/settings/:user_id/password", post(settings::change_password))
-        .route("/api/settings/:user_id/delete", axum::routing::delete(settings::delete_account))
-
-        // Discovery endpoints
-        .route("/api/discovery/search/:viewer_id", get(discovery::search_users))
-        .route("/api/discovery/popular/:viewer_id", get(discovery::get_popular_users))
-        .route("/api/discovery/suggested/:viewer_id", get(discovery::get_suggested_users))
-        .route("/api/discovery/avatar/:user_id", post(discovery::update_avatar))
-        .route("/api/discovery/refresh-popular", post(discovery::refresh_popular_users_view))
-
-        // Algorithm/Feed endpoints
-        .route("/api/feed/personalized/:user_id", get(algorithm::get_personalized_feed))
-        .route("/api/feed/interaction/:user_id/:story_id", post(algorithm::record_interaction))
-        .route("/api/feed/recalculate", post(algorithm::recalculate_all_feeds))
-
-        // Streak endpoints
-        .route("/api/streaks/update/:user1_id/:user2_id", post(streaks::update_streak))
-        .route("/api/streaks/:user1_id/:user2_id", get(streaks::get_streak))
-        .route("/api/streaks/user/:user_id", get(streaks::get_user_streaks))
-
-        // Notification endpoints
-        .route("/api/notifications/:user_id", get(notifications::get_notifications))
-        .route("/api/notifications/:user_id/unread", get(notifications::get_unread_count))
-        .route("/api/notifications/:user_id/:notification_id/read", post(notifications::mark_notification_read))
-        .route("/api/notifications/:user_id/read-all", post(notifications::mark_all_notifications_read))
-        .route("/api/notifications/:user_id/:notification_id", axum::routing::delete(notifications::delete_notification))
-
-        // Admin endpoints (protected by AdminUser extractor)
-        .route("/api/admin/users", get(admin::list_users))
-        .route("/api/admin/users/:user_id/ban", post(admin::ban_user))
-        .route("/api/admin/users/:user_id/unban", post(admin::unban_user))
-        .route("/api/admin/users/:user_id/role", post(admin::change_user_role))
-        .route("/api/admin/users/:user_id", axum::routing::delete(admin::delete_user))
-        .route("/api/admin/logs", get(admin::get_admin_logs))
-        .route("/api/admin/analytics", get(admin::get_analytics))
-        .route("/api/admin/ads", get(admin::list_ads))
-        .route("/api/admin/ads", post(admin::create_ad))
-        .route("/api/admin/ads/:ad_id", axum::routing::patch(admin::update_ad))
-        .route("/api/admin/ads/:ad_id", axum::routing::delete(admin::delete_ad))
-        .route("/api/admin/ads/:ad_id/approve", post(admin::approve_ad))
-        .route("/api/admin/ads/:ad_id/reject", post(admin::reject_ad))
-        .route("/api/admin/ads/:ad_id/analytics/location", get(admin::get_ad_location_analytics))
-        .route("/api/admin/ads/:ad_id/analytics/demographics", get(admin::get_ad_demographics_analytics))
-
-        // Public ad endpoints (for showing ads to users)
-        .route("/api/ads/next/:user_id", get(admin::get_next_ad))
-        .route("/api/ads/:ad_id/impression/:user_id", post(admin::record_ad_impression))
-        .route("/api/ads/:ad_id/click/:user_id", post(admin::record_ad_click))
-
-        // Self-service ad creation endpoints
-        .route("/api/ads/create", post(admin::create_ad_public))
-        .route("/api/ads/:ad_id/checkout", post(admin::create_checkout_session))
-        .route("/api/stripe/webhook", post(admin::stripe_webhook))
-
-        // Health check endpoint
-        .route("/health", get(health_check))
+        // Public profile and story pages and sitemaps for sharing and search engines
+        .route("/u/:username", get(public_pages::profile_page))
+        .route("/s/:story_id", get(public_pages::story_page))
+        .route("/sitemap.xml", get(public_pages::sitemap_index))
+        .route("/sitemaps/profiles/:page", get(public_pages::profiles_sitemap))
+        .route("/robots.txt", get(public_pages::robots_txt))
+
+        // API documentation
+        .route("/api/openapi.json", get(openapi::openapi_json))
+        .route("/api/docs", get(openapi::swagger_ui))
+        .route("/api/ws-schema", get(websocket::ws_schema))
+
+        // REST API (versioned, plus the legacy unversioned alias)
+        .nest("/api/v1", api_routes().into_router(ApiVersion::V1))
+        .nest("/api", api_routes().into_router(ApiVersion::Legacy))
+
+        // ActivityPub federation (404 unless FEDERATION_DOMAIN is set)
+        .route("/.well-known/webfinger", get(federation::webfinger))
+        .route("/ap/users/:user_id", get(federation::actor))
+        .route("/ap/users/:user_id/inbox", post(federation::inbox).layer(axum::middleware::from_fn(body_limit::standard)))
+        .route("/ap/users/:user_id/outbox", get(federation::outbox))
+        .route("/ap/users/:user_id/followers", get(federation::followers))
+        .route("/ap/stories/:story_id", get(federation::story))
+
+        // Health checks: liveness (also at /health) and dependency readiness
+        .route("/health", get(health::live))
+        .route("/health/live", get(health::live))
+        .route("/health/ready", get(health::ready))
 
         // WebSocket endpoint
         .route("/ws/:user_id", get(websocket::ws_handler))
 
-        .layer(DefaultBodyLimit::max(100 * 1024 * 1024)) // 100MB limit for uploads
+        .layer(axum::middleware::from_fn_with_state(state.clone(), feature_flags::maintenance_guard))
+        .layer(axum::middleware::from_fn(i18n::localize))
+        .layer(axum::middleware::from_fn(compression::gzip))
+        // Ceiling for every route; API routes have their own limits below it
+        .layer(DefaultBodyLimit::max(body_limit::HARD_LIMIT_BYTES))
         .layer(
             CorsLayer::new()
                 .allow_origin(Any)
                 .allow_methods(Any)
                 .allow_headers(Any)
+                .expose_headers(Any)
                 .allow_credentials(false)
         )
         .with_state(state)
@@ -305,7 +663,8 @@ async fn main() {
     println!("📱 WebSocket endpoint: ws://{}/ws/:user_id", addr);
     println!("💬 Ready for Snapchat-style messaging!\n");
 
-    axum::serve(listener, app)
+    // Peer addresses feed client_ip::ClientIp
+    axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>())
         .await
         .unwrap();
 }
@@ -3,13 +3,83 @@ use axum::{
     http::StatusCode,
 };
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 use std::sync::Arc;
-use aws_sdk_s3::Client as S3Client;
-use aws_sdk_s3::primitives::ByteStream;
 use base64::{Engine as _, engine::general_purpose};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use crate::cdn::Cdn;
+use crate::storage::{ObjectMeta, Provider, Storage};
+use crate::upload_stream::StagedUpload;
+
+// Presigned URLs may not outlive this (S3 SigV4 limit)
+const MAX_SIGNED_URL_TTL_SECONDS: u64 = 7 * 24 * 3600;
+// Unreferenced objects handed out by an upload this recently are kept, since
+// the client is probably about to attach them to a message or story
+const UNREFERENCED_GRACE_PERIOD: &str = "1 hour";
+
+/// Why an upload couldn't be stored
+#[derive(Debug)]
+pub enum StoreError {
+    /// The malware scanner flagged the file; it was quarantined
+    Infected(String),
+    /// The scanner couldn't be reached, so the file wasn't accepted
+    ScannerUnavailable(String),
+    /// Not an allowed format, or over its size limit
+    InvalidFormat(crate::mime_sniff::FormatError),
+    /// The request body couldn't be read, or was cut off
+    Upload(axum::extract::multipart::MultipartError),
+    Failed(String),
+}
+
+impl From<crate::mime_sniff::FormatError> for StoreError {
+    fn from(e: crate::mime_sniff::FormatError) -> Self {
+        StoreError::InvalidFormat(e)
+    }
+}
+
+impl StoreError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            StoreError::Infected(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            StoreError::ScannerUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            StoreError::InvalidFormat(e) => e.status(),
+            StoreError::Upload(e) => e.status(),
+            StoreError::Failed(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl std::fmt::Display for StoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StoreError::Infected(signature) => write!(f, "File rejected by malware scan ({})", signature),
+            StoreError::ScannerUnavailable(e) => write!(f, "Malware scanner unavailable: {}", e),
+            StoreError::InvalidFormat(e) => e.fmt(f),
+            StoreError::Upload(e) => f.write_str(&e.body_text()),
+            StoreError::Failed(e) => f.write_str(e),
+        }
+    }
+}
+
+/// Key of a content-addressed object
+pub fn content_key(sha256: &str, extension: &str) -> String {
+    format!("media/{}/{}.{}", &sha256[..2], sha256, extension)
+}
 
-#[derive(Serialize, Deserialize)]
+// Where new content goes: infected files are kept apart in quarantine/
+fn object_key(sha256: &str, extension: &str, verdict: &crate::scanning::Verdict) -> String {
+    match verdict {
+        crate::scanning::Verdict::Infected(_) => format!("quarantine/{}.{}", sha256, extension),
+        _ => content_key(sha256, extension),
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct UploadResponse {
     pub media_id: Uuid,
     pub url: String,
@@ -17,7 +87,17 @@ pub struct UploadResponse {
     pub file_type: String,
 }
 
-#[derive(Serialize, Deserialize)]
+impl UploadResponse {
+    // Clients may send the signed URLs straight back as message media;
+    // deliver_message stores their permanent form
+    async fn signed(mut self, media: &MediaService) -> Self {
+        media.sign_in_place(&mut self.url).await;
+        media.sign_opt(&mut self.thumbnail_url).await;
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct UploadImageRequest {
     pub image_data: String, // Base64 encoded image from webcam
     pub file_type: String,  // e.g., "image/jpeg"
@@ -25,120 +105,327 @@ pub struct UploadImageRequest {
 }
 
 pub struct MediaService {
-    pub s3_client: S3Client,
+    pub storage: Box<dyn Storage>,
+    /// The S3 bucket, which bucket URLs (and presigned ones) carry
     pub bucket_name: String,
     pub public_url_base: Option<String>,
+    /// Hand out short-lived presigned URLs instead of permanent object URLs
+    /// (MEDIA_SIGNED_URLS). Only meaningful once the bucket is private.
+    pub signed_urls: bool,
+    pub signed_url_ttl: Duration,
+    /// Hand out /api/v1/media/<key> paths served by media_proxy instead of
+    /// bucket URLs (MEDIA_PROXY_URLS); takes precedence over signed URLs
+    pub proxy_urls: bool,
+    /// CDN that unsigned public URLs are rewritten to, and that deleted or
+    /// overwritten objects are purged from
+    pub cdn: Option<Cdn>,
 }
 
 impl MediaService {
     pub async fn new() -> Self {
-        let config = aws_config::defaults(aws_config::BehaviorVersion::latest())
-            .load()
-            .await;
-
-        // Check if using Cloudflare R2 (or other S3-compatible service)
-        let s3_client = if let Ok(r2_endpoint) = std::env::var("R2_ENDPOINT") {
-            println!("✓ Using Cloudflare R2 at {}", r2_endpoint);
-            println!("  AWS_REGION: {}", std::env::var("AWS_REGION").unwrap_or_else(|_| "not set".to_string()));
-            println!("  AWS_ACCESS_KEY_ID: {}", if std::env::var("AWS_ACCESS_KEY_ID").is_ok() { "set" } else { "NOT SET" });
-            println!("  AWS_SECRET_ACCESS_KEY: {}", if std::env::var("AWS_SECRET_ACCESS_KEY").is_ok() { "set" } else { "NOT SET" });
-
-            // Configure S3 client with custom endpoint for R2
-            let s3_config = aws_sdk_s3::config::Builder::from(&config)
-                .endpoint_url(r2_endpoint)
-                .force_path_style(true) // R2 requires path-style URLs
-                .build();
-
-            S3Client::from_conf(s3_config)
-        } else {
-            // Standard AWS S3
-            println!("✓ Using AWS S3");
-            S3Client::new(&config)
-        };
-
+        let provider = Provider::from_env();
         let bucket_name = std::env::var("S3_BUCKET_NAME")
             .unwrap_or_else(|_| "relayhub-media".to_string());
 
-        // Get public URL base (for R2 public buckets or custom domains)
-        let public_url_base = std::env::var("R2_PUBLIC_URL").ok();
+        let storage: Box<dyn Storage> = match provider {
+            Provider::S3 => Box::new(crate::storage::S3Storage::from_env(&bucket_name).await),
+            Provider::Local => Box::new(crate::storage::LocalStorage::from_env()),
+        };
+
+        // Get public URL base (for R2 public buckets or custom domains).
+        // Local objects have no URL of their own, only the proxy's.
+        let public_url_base = std::env::var("R2_PUBLIC_URL").ok().or_else(|| match provider {
+            Provider::S3 => None,
+            Provider::Local => {
+                let site = std::env::var("PUBLIC_BASE_URL").unwrap_or_else(|_| {
+                    format!("http://localhost:{}", std::env::var("PORT").unwrap_or_else(|_| "3000".to_string()))
+                });
+                Some(format!("{}{}", site.trim_end_matches('/'), crate::media_proxy::PROXY_PATH.trim_end_matches('/')))
+            }
+        });
 
-        println!("✓ S3/R2 bucket: {}", bucket_name);
         println!("✓ Public URL base: {}", public_url_base.as_ref().unwrap_or(&"not set".to_string()));
 
+        // Local storage can't presign, and is only reachable through the proxy
+        let signed_urls = provider == Provider::S3 && std::env::var("MEDIA_SIGNED_URLS").is_ok_and(|v| v == "true");
+        let signed_url_ttl = Duration::from_secs(
+            std::env::var("MEDIA_URL_TTL_SECONDS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(900)
+                .clamp(1, MAX_SIGNED_URL_TTL_SECONDS),
+        );
+        if signed_urls {
+            println!("✓ Signed media URLs (TTL {}s)", signed_url_ttl.as_secs());
+        }
+        let proxy_urls = provider == Provider::Local || std::env::var("MEDIA_PROXY_URLS").is_ok_and(|v| v == "true");
+        if proxy_urls {
+            println!("✓ Media served through {}", crate::media_proxy::PROXY_PATH);
+        }
+        // Signed and proxied URLs carry their own access checks, which a
+        // shared CDN cache would bypass
+        let cdn = Cdn::from_env();
+        if let Some(cdn) = &cdn {
+            if signed_urls || proxy_urls {
+                println!("⚠️ CDN {} only purges; signed or proxied media URLs aren't rewritten to it", cdn.describe());
+            } else {
+                println!("✓ Media served from CDN {}", cdn.describe());
+            }
+        }
+
         Self {
-            s3_client,
+            storage,
             bucket_name,
             public_url_base,
+            signed_urls,
+            signed_url_ttl,
+            proxy_urls,
+            cdn,
+        }
+    }
+
+    /// Store `data` under its content hash, reusing the existing object when the
+    /// same bytes were uploaded before. New content is scanned for malware first;
+    /// infected files are quarantined instead of stored. Returns the public URL.
+    pub async fn store_object(
+        &self,
+        pool: &PgPool,
+        data: &[u8],
+        content_type: Option<&str>,
+        extension: &str,
+        uploaded_by: Option<Uuid>,
+    ) -> Result<String, StoreError> {
+        let sha256 = hex::encode(Sha256::digest(data));
+        if let Some(url) = self.reuse_existing(pool, &sha256, uploaded_by, crate::scanning::scan(data)).await? {
+            return Ok(url);
+        }
+
+        let verdict = crate::scanning::scan(data).await.map_err(StoreError::ScannerUnavailable)?;
+        let s3_key = object_key(&sha256, extension, &verdict);
+
+        // Identical bytes always map to the same key, so concurrent uploads of
+        // the same file just write the same object twice
+        let meta = content_type.map(ObjectMeta::typed).unwrap_or_default();
+        self.storage.put(&s3_key, data.to_vec(), &meta).await.map_err(StoreError::Failed)?;
+
+        self.record_object(pool, &sha256, &s3_key, content_type, data.len(), verdict, uploaded_by).await
+    }
+
+    /// store_object for a file streamed to a staging key: the staged object is
+    /// copied to its content-addressed key, or dropped when that content is
+    /// already stored. The staging key is gone afterwards either way.
+    pub async fn store_staged(
+        &self,
+        pool: &PgPool,
+        staged: StagedUpload,
+        uploaded_by: Option<Uuid>,
+    ) -> Result<String, StoreError> {
+        let result = self.promote_staged(pool, &staged, uploaded_by).await;
+        self.discard_staged([&staged]).await;
+        result
+    }
+
+    async fn promote_staged(&self, pool: &PgPool, staged: &StagedUpload, uploaded_by: Option<Uuid>) -> Result<String, StoreError> {
+        let verdict = staged.verdict.clone();
+        if let Some(url) = self.reuse_existing(pool, &staged.sha256, uploaded_by, async { verdict }).await? {
+            return Ok(url);
+        }
+
+        let verdict = staged.verdict.clone().map_err(StoreError::ScannerUnavailable)?;
+        let s3_key = object_key(&staged.sha256, staged.format.extension, &verdict);
+        self.copy_media(&staged.staging_key, &s3_key).await.map_err(StoreError::Failed)?;
+
+        let content_type = Some(staged.format.content_type);
+        self.record_object(pool, &staged.sha256, &s3_key, content_type, staged.size, verdict, uploaded_by).await
+    }
+
+    /// Delete staged files that won't be stored
+    pub async fn discard_staged<'a>(&self, staged: impl IntoIterator<Item = &'a StagedUpload>) {
+        let keys: Vec<String> = staged.into_iter().map(|s| s.staging_key.clone()).collect();
+        if keys.is_empty() {
+            return;
+        }
+        // Staged files were never handed out, so there's nothing to purge
+        if let Err(e) = self.storage.delete(&keys).await {
+            eprintln!("❌ Failed to delete staged uploads: {}", e);
+        }
+    }
+
+    // The stored copy of this content, if there is one. Content stored before
+    // scanning was turned on gets `verdict` recorded; infected content is refused.
+    async fn reuse_existing(
+        &self,
+        pool: &PgPool,
+        sha256: &str,
+        uploaded_by: Option<Uuid>,
+        verdict: impl std::future::Future<Output = Result<crate::scanning::Verdict, String>>,
+    ) -> Result<Option<String>, StoreError> {
+        let existing = sqlx::query_as::<_, (String, String, Option<String>)>(
+            "UPDATE media_objects SET last_used_at = NOW() WHERE sha256 = $1 RETURNING s3_key, scan_status, scan_signature"
+        )
+        .bind(sha256)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| StoreError::Failed(format!("Failed to look up media object: {}", e)))?;
+
+        match existing {
+            Some((_, status, signature)) if status == crate::scanning::STATUS_INFECTED => {
+                let signature = signature.unwrap_or_default();
+                crate::scanning::alert_admins(pool, sha256, &signature, uploaded_by).await;
+                Err(StoreError::Infected(signature))
+            }
+            Some((s3_key, status, _)) if status == crate::scanning::STATUS_UNSCANNED => {
+                self.record_verdict(pool, sha256, verdict.await, uploaded_by).await?;
+                Ok(Some(self.public_url(&s3_key)))
+            }
+            Some((s3_key, _, _)) => Ok(Some(self.public_url(&s3_key))),
+            None => Ok(None),
+        }
+    }
+
+    // Record a newly written object. Infected files were written to quarantine
+    // and are reported to admins instead of returned.
+    #[allow(clippy::too_many_arguments)]
+    async fn record_object(
+        &self,
+        pool: &PgPool,
+        sha256: &str,
+        s3_key: &str,
+        content_type: Option<&str>,
+        size: usize,
+        verdict: crate::scanning::Verdict,
+        uploaded_by: Option<Uuid>,
+    ) -> Result<String, StoreError> {
+        let (scan_status, signature) = match &verdict {
+            crate::scanning::Verdict::Infected(signature) => (crate::scanning::STATUS_INFECTED, Some(signature.as_str())),
+            crate::scanning::Verdict::Clean => (crate::scanning::STATUS_CLEAN, None),
+            crate::scanning::Verdict::Skipped => (crate::scanning::STATUS_UNSCANNED, None),
+        };
+
+        let s3_key = sqlx::query_scalar::<_, String>(
+            r#"
+            INSERT INTO media_objects
+            (sha256, s3_key, content_type, size_bytes, scan_status, scan_signature, scanned_at, uploaded_by)
+            VALUES ($1, $2, $3, $4, $5, $6, CASE WHEN $5 = 'unscanned' THEN NULL ELSE NOW() END, $7)
+            ON CONFLICT (sha256) DO UPDATE SET last_used_at = NOW()
+            RETURNING s3_key
+            "#
+        )
+        .bind(sha256)
+        .bind(s3_key)
+        .bind(content_type)
+        .bind(size as i64)
+        .bind(scan_status)
+        .bind(signature)
+        .bind(uploaded_by)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| StoreError::Failed(format!("Failed to record media object: {}", e)))?;
+
+        if let crate::scanning::Verdict::Infected(signature) = verdict {
+            crate::scanning::alert_admins(pool, sha256, &signature, uploaded_by).await;
+            return Err(StoreError::Infected(signature));
+        }
+
+        Ok(self.public_url(&s3_key))
+    }
+
+    // Record the verdict for an object that was stored unscanned
+    async fn record_verdict(
+        &self,
+        pool: &PgPool,
+        sha256: &str,
+        verdict: Result<crate::scanning::Verdict, String>,
+        uploaded_by: Option<Uuid>,
+    ) -> Result<(), StoreError> {
+        let (status, signature) = match verdict.map_err(StoreError::ScannerUnavailable)? {
+            crate::scanning::Verdict::Skipped => return Ok(()),
+            crate::scanning::Verdict::Clean => (crate::scanning::STATUS_CLEAN, None),
+            crate::scanning::Verdict::Infected(signature) => (crate::scanning::STATUS_INFECTED, Some(signature)),
+        };
+
+        sqlx::query(
+            "UPDATE media_objects SET scan_status = $2, scan_signature = $3, scanned_at = NOW() WHERE sha256 = $1"
+        )
+        .bind(sha256)
+        .bind(status)
+        .bind(&signature)
+        .execute(pool)
+        .await
+        .map_err(|e| StoreError::Failed(format!("Failed to record scan result: {}", e)))?;
+
+        match signature {
+            // Already distributed under its public key; admins decide what to do with it
+            Some(signature) => {
+                crate::scanning::alert_admins(pool, sha256, &signature, uploaded_by).await;
+                Err(StoreError::Infected(signature))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Take a reference on every stored object among `urls`. Call this for
+    /// each row that starts pointing at media; URLs outside the content store
+    /// are ignored.
+    pub async fn acquire(&self, pool: &PgPool, urls: &[&str]) {
+        let keys: Vec<String> = urls.iter().filter_map(|url| self.s3_key_from_url(url)).collect();
+        if keys.is_empty() {
+            return;
+        }
+
+        if let Err(e) = sqlx::query(
+            "UPDATE media_objects SET refcount = refcount + 1, last_used_at = NOW() WHERE s3_key = ANY($1)"
+        )
+        .bind(&keys)
+        .execute(pool)
+        .await
+        {
+            eprintln!("❌ Failed to reference media objects: {:?}", e);
         }
     }
 
     pub async fn upload_base64_image(
         &self,
-        user_id: Uuid,
+        pool: &PgPool,
+        _user_id: Uuid,
         base64_data: &str,
         file_type: &str,
         _expires_in_seconds: Option<i64>,
-    ) -> Result<UploadResponse, String> {
+    ) -> Result<UploadResponse, StoreError> {
         // Decode base64 image
         let image_data = general_purpose::STANDARD.decode(base64_data)
-            .map_err(|e| format!("Failed to decode base64: {}", e))?;
-
-        // Generate unique S3 key
-        let file_extension = match file_type {
-            "image/jpeg" | "image/jpg" => "jpg",
-            "image/png" => "png",
-            "image/webp" => "webp",
-            _ => "jpg",
-        };
+            .map_err(|e| StoreError::Failed(format!("Failed to decode base64: {}", e)))?;
+
+        // The declared type only matters for logging; the bytes decide
+        let format = crate::mime_sniff::validate(&image_data, None)?;
+        if file_type != format.content_type {
+            println!("ℹ️ Upload declared as {} is {}", file_type, format.content_type);
+        }
 
         let media_id = Uuid::new_v4();
-        let s3_key = format!("messages/{}/{}.{}", user_id, media_id, file_extension);
-
-        // Upload to S3
-        let byte_stream = ByteStream::from(image_data.clone());
-
-        // Upload to S3/R2
-        let put_request = self.s3_client
-            .put_object()
-            .bucket(&self.bucket_name)
-            .key(&s3_key)
-            .body(byte_stream)
-            .content_type(file_type);
-
-        // Note: Expiration is handled by the database and background cleanup service
-        // S3 object lifecycle policies can also be configured in the bucket settings
-        put_request.send().await
-            .map_err(|e| format!("Failed to upload to S3/R2: {}", e))?;
-
-        // Generate public URL
-        let url = if let Some(ref public_base) = self.public_url_base {
-            // Use R2 public URL or custom domain
-            format!("{}/{}", public_base.trim_end_matches('/'), s3_key)
-        } else {
-            // Standard S3 URL
-            format!(
-                "https://{}.s3.amazonaws.com/{}",
-                self.bucket_name, s3_key
-            )
-        };
+
+        // Note: Expiration is handled by the database and background cleanup service,
+        // which releases the message's reference once it expires
+        let url = self.store_object(pool, &image_data, Some(format.content_type), format.extension, None).await?;
 
         // Generate thumbnail for large images
-        let thumbnail_url = self.create_thumbnail(&image_data, user_id, media_id, file_type).await.ok();
+        let thumbnail_url = match format.kind {
+            crate::mime_sniff::MediaKind::Image => self.create_thumbnail(pool, &image_data).await.ok(),
+            crate::mime_sniff::MediaKind::Video => None,
+        };
 
         Ok(UploadResponse {
             media_id,
             url,
             thumbnail_url,
-            file_type: file_type.to_string(),
+            file_type: format.content_type.to_string(),
         })
     }
 
     async fn create_thumbnail(
         &self,
+        pool: &PgPool,
         image_data: &[u8],
-        user_id: Uuid,
-        media_id: Uuid,
-        _file_type: &str,
     ) -> Result<String, String> {
         // Load image
         let img = image::load_from_memory(image_data)
@@ -156,48 +443,241 @@ impl MediaService {
             )
             .map_err(|e| format!("Failed to encode thumbnail: {}", e))?;
 
-        // Upload thumbnail to S3
-        let thumbnail_key = format!("messages/{}/{}_thumb.jpg", user_id, media_id);
-        let byte_stream = ByteStream::from(buffer);
-
-        self.s3_client
-            .put_object()
-            .bucket(&self.bucket_name)
-            .key(&thumbnail_key)
-            .body(byte_stream)
-            .content_type("image/jpeg")
-            .send()
+        // Thumbnails of identical images are identical too, so they dedupe the same way
+        self.store_object(pool, &buffer, Some("image/jpeg"), "jpg", None)
             .await
-            .map_err(|e| format!("Failed to upload thumbnail: {}", e))?;
+            .map_err(|e| format!("Failed to upload thumbnail: {}", e))
+    }
+
+    /// Copy of an object within storage. Returns the public URL of the copy.
+    pub async fn copy_media(&self, source_key: &str, dest_key: &str) -> Result<String, String> {
+        self.storage.copy(source_key, dest_key).await?;
+        Ok(self.public_url(dest_key))
+    }
 
-        let thumbnail_url = if let Some(ref public_base) = self.public_url_base {
-            // Use R2 public URL or custom domain
-            format!("{}/{}", public_base.trim_end_matches('/'), thumbnail_key)
+    /// Public URL for an object key (R2 public base or standard S3)
+    pub fn public_url(&self, s3_key: &str) -> String {
+        if let Some(ref public_base) = self.public_url_base {
+            format!("{}/{}", public_base.trim_end_matches('/'), s3_key)
         } else {
-            // Standard S3 URL
-            format!(
-                "https://{}.s3.amazonaws.com/{}",
-                self.bucket_name, thumbnail_key
-            )
+            format!("https://{}.s3.amazonaws.com/{}", self.bucket_name, s3_key)
+        }
+    }
+
+    /// Delete many objects at once and purge them from the CDN. Returns the
+    /// keys that were deleted.
+    pub async fn delete_media_batch(&self, s3_keys: &[String]) -> Result<Vec<String>, String> {
+        let deleted = self.storage.delete(s3_keys).await?;
+        self.purge(&deleted).await;
+        Ok(deleted)
+    }
+
+    /// Drop cached CDN copies of objects that were deleted or overwritten
+    pub async fn purge(&self, s3_keys: &[String]) {
+        if let Some(cdn) = &self.cdn {
+            cdn.purge(s3_keys).await;
+        }
+    }
+
+    /// Purge the objects behind media URLs (e.g. an avatar that was replaced).
+    /// URLs outside storage are skipped.
+    pub async fn purge_urls(&self, urls: &[&str]) {
+        if self.cdn.is_none() {
+            return;
+        }
+        let keys: Vec<String> = urls
+            .iter()
+            .filter_map(|url| self.s3_key_from_url(&self.canonical_url(url)))
+            .collect();
+        self.purge(&keys).await;
+    }
+
+    // Public URL as handed to clients: on the CDN when there is one
+    fn delivery_url(&self, url: String) -> String {
+        let Some(cdn) = &self.cdn else {
+            return url;
         };
+        match self.s3_key_from_url(&url) {
+            Some(key) => cdn.url(&key),
+            None => url,
+        }
+    }
 
-        Ok(thumbnail_url)
+    /// Presigned GET URL for an object, valid for `ttl`
+    pub async fn presign_get(&self, s3_key: &str, ttl: Duration) -> Result<String, String> {
+        self.storage.presign_get(s3_key, ttl).await
     }
 
-    pub async fn delete_media(&self, s3_key: &str) -> Result<(), String> {
-        self.s3_client
-            .delete_object()
-            .bucket(&self.bucket_name)
-            .key(s3_key)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to delete from S3: {}", e))?;
+    /// URL to hand a client for stored media: a fresh presigned URL when signing
+    /// is on, the CDN or permanent URL otherwise. Already-signed URLs are
+    /// re-signed, and anything outside the bucket (GIF provider, proxy paths)
+    /// passes through.
+    pub async fn sign_url(&self, url: &str) -> String {
+        let url = self.canonical_url(url);
+        if !self.signed_urls && !self.proxy_urls {
+            return self.delivery_url(url);
+        }
+        let Some(key) = self.s3_key_from_url(&url) else {
+            return url;
+        };
+        if self.proxy_urls {
+            return format!("{}{}", crate::media_proxy::PROXY_PATH, key);
+        }
+
+        match self.presign_get(&key, self.signed_url_ttl).await {
+            Ok(signed) => signed,
+            Err(e) => {
+                eprintln!("❌ {}", e);
+                url
+            }
+        }
+    }
+
+    /// Like sign_url, but the link stays valid until `until`, or for a week at
+    /// most (for copies that leave the app: feeds, federated posts)
+    pub async fn sign_url_until(&self, url: &str, until: chrono::NaiveDateTime) -> String {
+        let url = self.canonical_url(url);
+        if !self.signed_urls {
+            return if self.proxy_urls { url } else { self.delivery_url(url) };
+        }
+        let Some(key) = self.s3_key_from_url(&url) else {
+            return url;
+        };
+        let ttl = (until - chrono::Utc::now().naive_utc())
+            .to_std()
+            .unwrap_or_default()
+            .clamp(Duration::from_secs(60), Duration::from_secs(MAX_SIGNED_URL_TTL_SECONDS));
+        self.presign_get(&key, ttl).await.unwrap_or(url)
+    }
+
+    pub async fn sign_in_place(&self, url: &mut String) {
+        *url = self.sign_url(url).await;
+    }
+
+    pub async fn sign_opt(&self, url: &mut Option<String>) {
+        if let Some(url) = url {
+            *url = self.sign_url(url).await;
+        }
+    }
+
+    /// Permanent URL for media a client sends back (e.g. the signed URL an
+    /// upload returned, or its CDN URL), so nothing that expires or depends on
+    /// the CDN gets stored
+    pub fn canonical_url(&self, url: &str) -> String {
+        if let Some(key) = url.strip_prefix(crate::media_proxy::PROXY_PATH) {
+            return self.public_url(key.split(['?', '#']).next().unwrap_or(key));
+        }
+        if let Some(key) = self.cdn.as_ref().and_then(|cdn| cdn.key_from_url(url)) {
+            return self.public_url(&key);
+        }
+        if !url.contains("X-Amz-Signature=") {
+            return url.to_string();
+        }
+        let Ok(parsed) = reqwest::Url::parse(url) else {
+            return url.to_string();
+        };
+
+        let path = parsed.path().trim_start_matches('/');
+        let bucket_prefix = format!("{}/", self.bucket_name);
+        let key = if parsed.host_str().is_some_and(|h| h.starts_with(&format!("{}.", self.bucket_name))) {
+            // Virtual-hosted style: https://<bucket>.s3.<region>.amazonaws.com/<key>
+            path
+        } else if let Some(key) = path.strip_prefix(&bucket_prefix) {
+            // Path style (R2 and custom endpoints): https://<endpoint>/<bucket>/<key>
+            key
+        } else {
+            return url.to_string();
+        };
+
+        self.public_url(key)
+    }
+
+    /// Map a public media URL (R2 public base, CDN or S3 style) back to its object key
+    pub fn s3_key_from_url(&self, url: &str) -> Option<String> {
+        if let Some(key) = self.cdn.as_ref().and_then(|cdn| cdn.key_from_url(url)) {
+            return Some(key);
+        }
+        if let Some(base) = &self.public_url_base {
+            if let Some(key) = url.strip_prefix(base.trim_end_matches('/')) {
+                return Some(key.trim_start_matches('/').to_string());
+            }
+        }
 
-        Ok(())
+        url.split(".s3.amazonaws.com/")
+            .nth(1)
+            .map(|s| s.to_string())
     }
 }
 
+/// Drop one reference per occurrence of a key in `s3_keys`. Returns the keys
+/// that may be deleted from storage: objects whose last reference went away,
+/// plus keys the content store doesn't track (per-story renders, legacy uploads).
+/// Pass a transaction to release as part of the caller's writes.
+pub async fn release_keys(conn: &mut sqlx::PgConnection, s3_keys: &[String]) -> Result<Vec<String>, String> {
+    if s3_keys.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut counts: HashMap<&str, i32> = HashMap::new();
+    for key in s3_keys {
+        *counts.entry(key.as_str()).or_default() += 1;
+    }
+    let keys: Vec<String> = counts.keys().map(|k| k.to_string()).collect();
+    let releases: Vec<i32> = keys.iter().map(|k| counts[k.as_str()]).collect();
+
+    let tracked: Vec<String> = sqlx::query_scalar::<_, String>(
+        r#"
+        UPDATE media_objects m
+        SET refcount = GREATEST(m.refcount - r.n, 0)
+        FROM UNNEST($1::text[], $2::int[]) AS r(s3_key, n)
+        WHERE m.s3_key = r.s3_key
+        RETURNING m.s3_key
+        "#
+    )
+    .bind(&keys)
+    .bind(&releases)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| format!("Failed to release media objects: {}", e))?;
+
+    // The refcount check makes this safe against a reference taken in between
+    let unreferenced = sqlx::query_scalar::<_, String>(&format!(
+        r#"
+        DELETE FROM media_objects
+        WHERE s3_key = ANY($1)
+          AND refcount = 0
+          AND last_used_at < NOW() - INTERVAL '{}'
+        RETURNING s3_key
+        "#,
+        UNREFERENCED_GRACE_PERIOD
+    ))
+    .bind(&tracked)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| format!("Failed to remove media objects: {}", e))?;
+
+    let tracked: HashSet<String> = tracked.into_iter().collect();
+    Ok(keys
+        .into_iter()
+        .filter(|key| !tracked.contains(key))
+        .chain(unreferenced)
+        .collect())
+}
+
 // HTTP handler for uploading images (e.g., from webcam)
+#[utoipa::path(
+    post,
+    path = "/api/v1/media/upload",
+    tag = "media",
+    request_body = UploadImageRequest,
+    responses(
+        (status = 200, body = UploadResponse),
+        (status = 413, description = "File too large for its format"),
+        (status = 415, description = "Unsupported file format"),
+        (status = 422, description = "File rejected by malware scan"),
+        (status = 503, description = "Malware scanner unavailable")
+    )
+)]
 pub async fn upload_image(
     State(state): State<Arc<crate::AppState>>,
     Json(payload): Json<UploadImageRequest>,
@@ -207,6 +687,7 @@ pub async fn upload_image(
 
     let result = state.media_service
         .upload_base64_image(
+            &state.pool,
             user_id,
             &payload.image_data,
             &payload.file_type,
@@ -215,51 +696,68 @@ pub async fn upload_image(
         .await
         .map_err(|e| {
             eprintln!("Upload error: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            e.status()
         })?;
 
-    Ok(Json(result))
+    Ok(Json(result.signed(&state.media_service).await))
 }
 
 // HTTP handler for multipart form uploads
+#[utoipa::path(
+    post,
+    path = "/api/v1/media/upload-multipart",
+    tag = "media",
+    request_body(content = String, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, body = UploadResponse),
+        (status = 413, description = "File too large for its format"),
+        (status = 415, description = "Unsupported file format"),
+        (status = 422, description = "File rejected by malware scan"),
+        (status = 503, description = "Malware scanner unavailable")
+    )
+)]
 pub async fn upload_multipart(
     State(state): State<Arc<crate::AppState>>,
     mut multipart: Multipart,
 ) -> Result<Json<UploadResponse>, StatusCode> {
     println!("📤 Received multipart upload request");
-    let user_id = Uuid::new_v4(); // TODO: Get from auth
 
     while let Ok(Some(field)) = multipart.next_field().await {
         let name = field.name().unwrap_or("").to_string();
         println!("📎 Processing field: {}", name);
 
         if name == "file" {
-            let content_type = field.content_type().unwrap_or("image/jpeg").to_string();
-            println!("📷 File content type: {}", content_type);
-
-            let data = match field.bytes().await {
-                Ok(bytes) => bytes,
-                Err(e) => {
-                    eprintln!("❌ Failed to read file data: {}", e);
-                    return Err(StatusCode::BAD_REQUEST);
-                }
+            // The declared type only matters for logging; the bytes decide
+            let declared = field.content_type().map(str::to_string);
+            let media = &state.media_service;
+            let mut staged = crate::upload_stream::stage(media, field, None, true).await.map_err(|e| {
+                eprintln!("❌ Upload error: {}", e);
+                e.status()
+            })?;
+            if declared.as_deref().is_some_and(|declared| declared != staged.format.content_type) {
+                println!("ℹ️ Upload declared as {} is {}", declared.unwrap_or_default(), staged.format.content_type);
+            }
+            println!("📦 File size: {} bytes", staged.size);
+
+            let format = staged.format;
+            let image_data = staged.data.take();
+            let url = media.store_staged(&state.pool, staged, None).await.map_err(|e| {
+                eprintln!("❌ Upload error: {}", e);
+                e.status()
+            })?;
+            let thumbnail_url = match image_data {
+                Some(data) => media.create_thumbnail(&state.pool, &data).await.ok(),
+                None => None,
+            };
+            let result = UploadResponse {
+                media_id: Uuid::new_v4(),
+                url,
+                thumbnail_url,
+                file_type: format.content_type.to_string(),
             };
-
-            println!("📦 File size: {} bytes", data.len());
-
-            // Convert to base64 for processing
-            let base64_data = general_purpose::STANDARD.encode(&data);
-
-            let result = state.media_service
-                .upload_base64_image(user_id, &base64_data, &content_type, None)
-                .await
-                .map_err(|e| {
-                    eprintln!("❌ Upload error: {}", e);
-                    StatusCode::INTERNAL_SERVER_ERROR
-                })?;
 
             println!("✅ Upload successful: {}", result.url);
-            return Ok(Json(result));
+            return Ok(Json(result.signed(media).await));
         }
     }
 
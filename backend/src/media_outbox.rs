@@ -0,0 +1,154 @@
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::media::MediaService;
+
+// Storage side effects of database changes. A flow that frees media releases
+// its references and queues the freed keys in the same transaction as its own
+// writes, then flushes the entry once committed. A rollback therefore never
+// deletes anything, and a failed S3 call leaves the entry for the
+// process_media_outbox job instead of an orphaned object.
+
+const MAX_ATTEMPTS: i32 = 10;
+const BATCH_SIZE: i64 = 100;
+// Processed entries are kept this long for debugging
+const RETENTION: &str = "7 days";
+
+#[derive(sqlx::FromRow)]
+struct OutboxEntry {
+    id: Uuid,
+    s3_keys: Vec<String>,
+}
+
+/// Release `s3_keys` on `conn` (normally the caller's transaction) and queue
+/// the objects nothing references any more. Returns the entry to flush after
+/// commit, or None when nothing became deletable.
+pub async fn release_and_queue(
+    conn: &mut PgConnection,
+    s3_keys: &[String],
+    reason: &str,
+) -> Result<Option<Uuid>, String> {
+    let deletable = crate::media::release_keys(&mut *conn, s3_keys).await?;
+    if deletable.is_empty() {
+        return Ok(None);
+    }
+
+    sqlx::query_scalar::<_, Uuid>(
+        "INSERT INTO media_deletion_outbox (s3_keys, reason) VALUES ($1, $2) RETURNING id"
+    )
+    .bind(&deletable)
+    .bind(reason)
+    .fetch_one(conn)
+    .await
+    .map(Some)
+    .map_err(|e| format!("Failed to queue media deletion: {}", e))
+}
+
+/// Delete a committed entry's objects now. Failures are recorded and left to the job.
+pub async fn flush(pool: &PgPool, media_service: &MediaService, entry_id: Option<Uuid>) {
+    let Some(entry_id) = entry_id else {
+        return;
+    };
+
+    let entry = sqlx::query_as::<_, OutboxEntry>(
+        "SELECT id, s3_keys FROM media_deletion_outbox WHERE id = $1 AND processed_at IS NULL"
+    )
+    .bind(entry_id)
+    .fetch_optional(pool)
+    .await;
+
+    match entry {
+        Ok(Some(entry)) => {
+            process_entry(pool, media_service, entry).await;
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("Failed to load media outbox entry {}: {}", entry_id, e),
+    }
+}
+
+/// Try one entry; returns whether every key is gone
+async fn process_entry(pool: &PgPool, media_service: &MediaService, entry: OutboxEntry) -> bool {
+    let (remaining, error) = match media_service.delete_media_batch(&entry.s3_keys).await {
+        Ok(deleted) => {
+            let remaining: Vec<String> = entry.s3_keys.into_iter().filter(|k| !deleted.contains(k)).collect();
+            let error = format!("{} objects not deleted", remaining.len());
+            (remaining, error)
+        }
+        Err(e) => (entry.s3_keys, e),
+    };
+
+    let done = remaining.is_empty();
+    let result = if done {
+        sqlx::query("UPDATE media_deletion_outbox SET s3_keys = '{}', processed_at = NOW() WHERE id = $1")
+            .bind(entry.id)
+            .execute(pool)
+            .await
+    } else {
+        eprintln!("Media deletion {} failed: {}", entry.id, error);
+        // Exponential backoff: 1, 2, 4 ... minutes
+        sqlx::query(
+            r#"
+            UPDATE media_deletion_outbox
+            SET s3_keys = $2,
+                attempts = attempts + 1,
+                last_error = $3,
+                next_attempt_at = NOW() + INTERVAL '1 minute' * power(2, attempts)
+            WHERE id = $1
+            "#
+        )
+        .bind(entry.id)
+        .bind(&remaining)
+        .bind(&error)
+        .execute(pool)
+        .await
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to update media outbox entry {}: {}", entry.id, e);
+    }
+
+    done
+}
+
+/// Job body: retry due entries and drop old processed ones. Entries that run
+/// out of attempts stay unprocessed for inspection; bucket cleanup eventually
+/// removes their objects as orphans.
+pub async fn process_pending(pool: &PgPool, media_service: &MediaService) -> Result<Option<serde_json::Value>, String> {
+    let due = sqlx::query_as::<_, OutboxEntry>(
+        r#"
+        SELECT id, s3_keys FROM media_deletion_outbox
+        WHERE processed_at IS NULL AND attempts < $1 AND next_attempt_at <= NOW()
+        ORDER BY next_attempt_at
+        LIMIT $2
+        "#
+    )
+    .bind(MAX_ATTEMPTS)
+    .bind(BATCH_SIZE)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut processed = 0;
+    let mut failed = 0;
+    for entry in due {
+        if process_entry(pool, media_service, entry).await {
+            processed += 1;
+        } else {
+            failed += 1;
+        }
+    }
+
+    let purged = sqlx::query(&format!(
+        "DELETE FROM media_deletion_outbox WHERE processed_at < NOW() - INTERVAL '{}'",
+        RETENTION
+    ))
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .rows_affected();
+
+    Ok(Some(serde_json::json!({
+        "processed": processed,
+        "failed": failed,
+        "purged": purged,
+    })))
+}
@@ -0,0 +1,290 @@
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::Response,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::permissions::{role_has, CanModerateContent, Permission};
+use crate::storage::{FetchError, ReadConditions};
+use crate::AppState;
+
+// Media served from the app's own origin, for clients that can't load bucket
+// URLs (CORS) or that hold media behind auth. GET /media/*key streams the
+// object from storage as it arrives; Range and conditional headers go through
+// so players can seek and caches can revalidate. With local storage this is
+// the only way media is served.
+//
+// Who may fetch an object follows what it belongs to:
+//   - message media: members of a chat it was sent in, as get_message_media
+//   - story media, posters, previews and HLS renditions: anyone while the
+//     story is up and not withheld in their country; 18+ stories need an
+//     adult viewer. The author can always fetch their own.
+//   - exports/<user>/ and archive/<user>/: that user only
+//   - anything else (avatars, ad images, fresh uploads): anyone
+// Staff with can_moderate_content may fetch everything except staged and
+// quarantined objects, which are never served. With MEDIA_PROXY_URLS=true the
+// API hands out these paths in place of bucket URLs.
+
+pub const PROXY_PATH: &str = "/api/v1/media/";
+const NEVER_SERVED: [&str; 2] = [crate::upload_stream::STAGING_PREFIX, "quarantine/"];
+const OWNER_ONLY: [&str; 2] = ["exports/", "archive/"];
+// Content-addressed objects never change, whoever may see them
+const IMMUTABLE_MAX_AGE: u64 = 31_536_000;
+const MUTABLE_MAX_AGE: u64 = 3600;
+
+// Whether a response may be shared between viewers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    Public,
+    Restricted,
+}
+
+#[derive(sqlx::FromRow)]
+struct StoryRef {
+    user_id: Uuid,
+    is_mature: bool,
+    live: bool,
+    withheld: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct MessageRefs {
+    referenced: bool,
+    visible: bool,
+}
+
+// stories/<user>/hls/<story>/... holds a story's HLS renditions, which
+// aren't referenced by URL one by one
+fn hls_story_id(key: &str) -> Option<Uuid> {
+    let mut segments = key.split('/');
+    match (segments.next(), segments.next(), segments.next(), segments.next()) {
+        (Some("stories"), Some(_), Some("hls"), Some(story_id)) => Uuid::parse_str(story_id).ok(),
+        _ => None,
+    }
+}
+
+fn owner_of(key: &str) -> Option<Option<Uuid>> {
+    let rest = OWNER_ONLY.iter().find_map(|prefix| key.strip_prefix(prefix))?;
+    Some(rest.split('/').next().and_then(|id| Uuid::parse_str(id).ok()))
+}
+
+async fn authorize(
+    state: &AppState,
+    key: &str,
+    viewer: Option<&AuthUser>,
+    headers: &HeaderMap,
+) -> Result<Access, StatusCode> {
+    if NEVER_SERVED.iter().any(|prefix| key.starts_with(prefix)) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    if let Some(viewer) = viewer {
+        if role_has(&state.pool, &viewer.role, CanModerateContent::NAME).await {
+            return Ok(Access::Restricted);
+        }
+    }
+    let denied = if viewer.is_some() { StatusCode::FORBIDDEN } else { StatusCode::UNAUTHORIZED };
+    let viewer_id = viewer.map(|v| v.id);
+
+    if let Some(owner) = owner_of(key) {
+        return match owner {
+            Some(owner) if viewer_id == Some(owner) => Ok(Access::Restricted),
+            _ => Err(denied),
+        };
+    }
+
+    let url = state.media_service.public_url(key);
+    let stories = sqlx::query_as::<_, StoryRef>(
+        r#"
+        SELECT s.user_id, s.is_mature, s.expires_at > NOW() AS live, geo_blocked('story', s.id, $3) AS withheld
+        FROM stories s
+        WHERE s.media_url = $1 OR s.thumbnail_url = $1 OR s.preview_url = $1 OR s.id = $2
+           OR EXISTS(SELECT 1 FROM story_media sm WHERE sm.story_id = s.id AND sm.media_url = $1)
+        "#
+    )
+    .bind(&url)
+    .bind(hls_story_id(key))
+    .bind(crate::takedowns::request_country(headers))
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    let messages = sqlx::query_as::<_, MessageRefs>(
+        r#"
+        SELECT
+            EXISTS(SELECT 1 FROM messages m WHERE m.media_url = $1 OR m.media_thumbnail_url = $1) AS referenced,
+            EXISTS(
+                SELECT 1 FROM messages m
+                WHERE (m.media_url = $1 OR m.media_thumbnail_url = $1)
+                  AND m.deleted_at IS NULL
+                  AND EXISTS(SELECT 1 FROM chat_members WHERE chat_room_id = m.chat_room_id AND user_id = $2)
+                  AND NOT EXISTS(SELECT 1 FROM message_recipients WHERE message_id = m.id AND user_id = $2 AND hidden_at IS NOT NULL)
+                  AND (m.expires_at IS NULL OR m.expires_at > NOW()
+                       OR EXISTS(SELECT 1 FROM saved_messages WHERE message_id = m.id AND user_id = $2))
+                  AND NOT (m.view_once AND m.sender_id <> $2
+                           AND EXISTS(SELECT 1 FROM message_views WHERE message_id = m.id AND user_id = $2)
+                           AND NOT EXISTS(SELECT 1 FROM saved_messages WHERE message_id = m.id AND user_id = $2))
+            ) AS visible
+        "#
+    )
+    .bind(&url)
+    .bind(viewer_id)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    if stories.is_empty() && !messages.referenced {
+        return Ok(Access::Public);
+    }
+    if messages.visible || stories.iter().any(|s| viewer_id == Some(s.user_id)) {
+        return Ok(Access::Restricted);
+    }
+
+    if !stories.iter().any(|s| s.live && !s.withheld) {
+        return Err(denied);
+    }
+    if stories.iter().any(|s| s.live && !s.withheld && !s.is_mature) {
+        return Ok(Access::Restricted);
+    }
+    let viewer_is_adult = match viewer_id {
+        Some(viewer_id) => sqlx::query_scalar::<_, Option<bool>>("SELECT user_is_adult($1)")
+            .bind(viewer_id)
+            .fetch_one(state.pool.as_ref())
+            .await
+            .map_err(db_error)?
+            .unwrap_or(false),
+        None => false,
+    };
+    if viewer_is_adult {
+        Ok(Access::Restricted)
+    } else {
+        Err(denied)
+    }
+}
+
+fn db_error(e: sqlx::Error) -> StatusCode {
+    eprintln!("❌ Media proxy query failed: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+fn cache_control(key: &str, access: Access) -> String {
+    let scope = match access {
+        Access::Public => "public",
+        Access::Restricted => "private",
+    };
+    if key.starts_with("media/") {
+        format!("{}, max-age={}, immutable", scope, IMMUTABLE_MAX_AGE)
+    } else {
+        format!("{}, max-age={}", scope, MUTABLE_MAX_AGE)
+    }
+}
+
+fn header_str(headers: &HeaderMap, name: header::HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+/// Stream an object from storage, forwarding Range and conditional
+/// request headers to it and its answer back
+pub async fn stream_object(state: &AppState, key: &str, headers: &HeaderMap, cache_control: &str) -> Result<Response, StatusCode> {
+    let conditions = ReadConditions {
+        range: header_str(headers, header::RANGE).map(str::to_string),
+        if_none_match: header_str(headers, header::IF_NONE_MATCH).map(str::to_string),
+        if_match: header_str(headers, header::IF_RANGE)
+            .filter(|v| v.starts_with('"') || v.starts_with("W/"))
+            .map(str::to_string),
+    };
+
+    let object = match state.media_service.storage.fetch(key, &conditions).await {
+        Ok(object) => object,
+        Err(FetchError::NotModified) => {
+            return Response::builder()
+                .status(StatusCode::NOT_MODIFIED)
+                .header(header::CACHE_CONTROL, cache_control)
+                .body(Body::empty())
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+        }
+        Err(FetchError::RangeNotSatisfiable) => return Err(StatusCode::RANGE_NOT_SATISFIABLE),
+        // If-Range no longer matches: send the whole object instead
+        Err(FetchError::PreconditionFailed) => {
+            let mut headers = headers.clone();
+            headers.remove(header::RANGE);
+            headers.remove(header::IF_RANGE);
+            return Box::pin(stream_object(state, key, &headers, cache_control)).await;
+        }
+        Err(FetchError::NotFound) => return Err(StatusCode::NOT_FOUND),
+        Err(FetchError::Failed(e)) => {
+            eprintln!("❌ Failed to fetch {} from storage: {}", key, e);
+            return Err(StatusCode::BAD_GATEWAY);
+        }
+    };
+
+    let mut response = Response::builder()
+        .status(if object.content_range.is_some() { StatusCode::PARTIAL_CONTENT } else { StatusCode::OK })
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::CACHE_CONTROL, cache_control)
+        .header(header::VARY, "Authorization")
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff");
+    let content_type = object
+        .meta
+        .content_type
+        .as_deref()
+        .or_else(|| crate::mime_sniff::content_type_from_url(key))
+        .unwrap_or("application/octet-stream");
+    response = response.header(header::CONTENT_TYPE, content_type);
+    if let Some(disposition) = &object.meta.content_disposition {
+        response = response.header(header::CONTENT_DISPOSITION, disposition);
+    }
+    if let Some(length) = object.content_length {
+        response = response.header(header::CONTENT_LENGTH, length);
+    }
+    if let Some(range) = &object.content_range {
+        response = response.header(header::CONTENT_RANGE, range);
+    }
+    if let Some(etag) = &object.etag {
+        response = response.header(header::ETAG, etag);
+    }
+    if let Some(modified) = object.last_modified {
+        response = response.header(header::LAST_MODIFIED, modified.format("%a, %d %b %Y %H:%M:%S GMT").to_string());
+    }
+
+    // Chunks are passed on as storage yields them; nothing is buffered here
+    response.body(Body::from_stream(object.body)).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+// Serve stored media from the API origin
+#[utoipa::path(
+    get,
+    path = "/api/v1/media/{key}",
+    tag = "media",
+    params(
+        ("key" = String, Path, description = "Object key, e.g. media/ab/ab12….jpg"),
+        ("Range" = Option<String>, Header, description = "Byte range, passed through to storage")
+    ),
+    responses(
+        (status = 200, description = "The object's bytes"),
+        (status = 206, description = "The requested byte range"),
+        (status = 304, description = "Not modified since the given ETag"),
+        (status = 401, description = "The media is private; sign in to fetch it"),
+        (status = 403, description = "Not allowed to see this media"),
+        (status = 404, description = "No such object"),
+        (status = 416, description = "Range not satisfiable")
+    ),
+    security((), ("bearer_auth" = []))
+)]
+pub async fn get_media(
+    viewer: Option<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let key = key.trim_start_matches('/');
+    if key.is_empty() || key.split('/').any(|segment| segment == "..") {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let access = authorize(&state, key, viewer.as_ref(), &headers).await?;
+    stream_object(&state, key, &headers, &cache_control(key, access)).await
+}
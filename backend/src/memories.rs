@@ -0,0 +1,689 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::media::MediaService;
+use crate::AppState;
+
+// ============= Story Archive ("Memories") =============
+
+// Stories archived per expiration pass
+const ARCHIVE_BATCH_SIZE: i64 = 100;
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct Memory {
+    pub id: Uuid,
+    pub original_story_id: Uuid,
+    pub media_url: String,
+    pub media_type: String,
+    pub thumbnail_url: Option<String>,
+    pub caption: Option<String>,
+    pub alt_text: Option<String>,
+    pub alt_text_generated: bool,
+    pub is_mature: bool,
+    pub view_count: i32,
+    pub like_count: i32,
+    pub comment_count: i32,
+    #[serde(with = "crate::timestamps")]
+    pub original_created_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps")]
+    pub archived_at: NaiveDateTime,
+}
+
+impl Memory {
+    /// Replace stored media URLs with short-lived signed ones
+    pub async fn sign_media(&mut self, media: &MediaService) {
+        media.sign_in_place(&mut self.media_url).await;
+        media.sign_opt(&mut self.thumbnail_url).await;
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct ExpiredStory {
+    id: Uuid,
+    user_id: Uuid,
+    media_url: String,
+    media_type: String,
+    thumbnail_url: Option<String>,
+    caption: Option<String>,
+    alt_text: Option<String>,
+    alt_text_generated: bool,
+    is_mature: bool,
+    view_count: Option<i32>,
+    like_count: Option<i32>,
+    comment_count: Option<i32>,
+    created_at: NaiveDateTime,
+}
+
+/// Copy freshly expired stories of opted-in authors into their archive.
+/// Only looks at the last 24h of expirations, before bucket cleanup removes the originals.
+pub async fn archive_expired_stories(pool: &PgPool, media_service: &MediaService) -> Result<usize, sqlx::Error> {
+    let stories = sqlx::query_as::<_, ExpiredStory>(
+        r#"
+        SELECT s.id, s.user_id, s.media_url, s.media_type, s.thumbnail_url, s.caption,
+               s.alt_text, s.alt_text_generated, s.is_mature, s.view_count, s.like_count, s.comment_count, s.created_at
+        FROM stories s
+        JOIN users u ON u.id = s.user_id
+        WHERE u.archive_stories = TRUE
+          AND s.expires_at < NOW()
+          AND s.expires_at > NOW() - INTERVAL '24 hours'
+          AND NOT EXISTS(SELECT 1 FROM story_archive a WHERE a.original_story_id = s.id)
+        ORDER BY s.expires_at ASC
+        LIMIT $1
+        "#
+    )
+    .bind(ARCHIVE_BATCH_SIZE)
+    .fetch_all(pool)
+    .await?;
+
+    let mut archived = 0;
+
+    for story in stories {
+        let media_url = match archive_copy(media_service, story.user_id, story.id, &story.media_url).await {
+            Ok(url) => url,
+            Err(e) => {
+                eprintln!("Failed to archive story {}: {}", story.id, e);
+                continue;
+            }
+        };
+
+        let thumbnail_url = match &story.thumbnail_url {
+            Some(thumb) => archive_copy(media_service, story.user_id, story.id, thumb).await.ok(),
+            None => None,
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO story_archive
+            (user_id, original_story_id, media_url, media_type, thumbnail_url, caption,
+             alt_text, alt_text_generated, is_mature, view_count, like_count, comment_count, original_created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            ON CONFLICT (original_story_id) DO NOTHING
+            "#
+        )
+        .bind(story.user_id)
+        .bind(story.id)
+        .bind(media_url)
+        .bind(story.media_type)
+        .bind(thumbnail_url)
+        .bind(story.caption)
+        .bind(story.alt_text)
+        .bind(story.alt_text_generated)
+        .bind(story.is_mature)
+        .bind(story.view_count.unwrap_or(0))
+        .bind(story.like_count.unwrap_or(0))
+        .bind(story.comment_count.unwrap_or(0))
+        .bind(story.created_at)
+        .execute(pool)
+        .await?;
+
+        archived += 1;
+    }
+
+    if archived > 0 {
+        println!("🗂️ Archived {} expired stories to memories", archived);
+    }
+
+    Ok(archived)
+}
+
+// Copy a story object under archive/<user>/<story>/ so it outlives story cleanup
+async fn archive_copy(media_service: &MediaService, user_id: Uuid, story_id: Uuid, url: &str) -> Result<String, String> {
+    let source_key = media_service
+        .s3_key_from_url(url)
+        .ok_or_else(|| format!("Unrecognized media URL: {}", url))?;
+    let filename = source_key.rsplit('/').next().unwrap_or(&source_key);
+    let dest_key = format!("archive/{}/{}/{}", user_id, story_id, filename);
+
+    media_service.copy_media(&source_key, &dest_key).await
+}
+
+async fn fetch_memory(pool: &PgPool, memory_id: Uuid, user_id: Uuid) -> Result<Memory, StatusCode> {
+    sqlx::query_as::<_, Memory>(
+        r#"
+        SELECT id, original_story_id, media_url, media_type, thumbnail_url, caption,
+               alt_text, alt_text_generated, is_mature, view_count, like_count, comment_count, original_created_at, archived_at
+        FROM story_archive
+        WHERE id = $1 AND user_id = $2
+        "#
+    )
+    .bind(memory_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)
+}
+
+// ============= Memories API =============
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MemoriesQuery {
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct MemoriesResponse {
+    memories: Vec<Memory>,
+    total: i64,
+    page: i64,
+    per_page: i64,
+}
+
+// Browse the caller's archived stories, newest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/memories",
+    tag = "memories",
+    params(MemoriesQuery),
+    responses((status = 200, body = MemoriesResponse), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_memories(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<MemoriesQuery>,
+) -> Result<Json<MemoriesResponse>, StatusCode> {
+    let page = params.page.unwrap_or(1).max(1);
+    let per_page = params.per_page.unwrap_or(30).clamp(1, 100);
+    let offset = (page - 1) * per_page;
+
+    let mut memories = sqlx::query_as::<_, Memory>(
+        r#"
+        SELECT id, original_story_id, media_url, media_type, thumbnail_url, caption,
+               alt_text, alt_text_generated, is_mature, view_count, like_count, comment_count, original_created_at, archived_at
+        FROM story_archive
+        WHERE user_id = $1
+        ORDER BY original_created_at DESC
+        LIMIT $2 OFFSET $3
+        "#
+    )
+    .bind(user.id)
+    .bind(per_page)
+    .bind(offset)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let total = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM story_archive WHERE user_id = $1")
+        .bind(user.id)
+        .fetch_one(state.pool.as_ref())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    for memory in &mut memories {
+        memory.sign_media(&state.media_service).await;
+    }
+
+    Ok(Json(MemoriesResponse {
+        memories,
+        total,
+        page,
+        per_page,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/memories/{memory_id}",
+    tag = "memories",
+    params(("memory_id" = Uuid, Path, description = "Memory ID")),
+    responses((status = 200, body = Memory), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_memory(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(memory_id): Path<Uuid>,
+) -> Result<Json<Memory>, StatusCode> {
+    let mut memory = fetch_memory(&state.pool, memory_id, user.id).await?;
+    memory.sign_media(&state.media_service).await;
+    Ok(Json(memory))
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct MemorySettings {
+    pub enabled: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/memories/settings",
+    tag = "memories",
+    responses((status = 200, body = MemorySettings), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_memory_settings(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<MemorySettings>, StatusCode> {
+    let enabled = sqlx::query_scalar::<_, bool>("SELECT archive_stories FROM users WHERE id = $1")
+        .bind(user.id)
+        .fetch_one(state.pool.as_ref())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(MemorySettings { enabled }))
+}
+
+// Opt in or out of archiving stories when they expire
+#[utoipa::path(
+    put,
+    path = "/api/v1/memories/settings",
+    tag = "memories",
+    request_body = MemorySettings,
+    responses((status = 200, body = MemorySettings), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_memory_settings(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<MemorySettings>,
+) -> Result<Json<MemorySettings>, StatusCode> {
+    sqlx::query("UPDATE users SET archive_stories = $1 WHERE id = $2")
+        .bind(payload.enabled)
+        .bind(user.id)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(payload))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ReshareResponse {
+    pub story_id: Uuid,
+    pub media_url: String,
+    #[serde(with = "crate::timestamps")]
+    pub expires_at: NaiveDateTime,
+}
+
+// Post an archived story again as a brand new story
+#[utoipa::path(
+    post,
+    path = "/api/v1/memories/{memory_id}/reshare",
+    tag = "memories",
+    params(("memory_id" = Uuid, Path, description = "Memory ID")),
+    responses((status = 200, body = ReshareResponse), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn reshare_memory(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(memory_id): Path<Uuid>,
+) -> Result<Json<ReshareResponse>, StatusCode> {
+    let memory = fetch_memory(&state.pool, memory_id, user.id).await?;
+
+    // The new story gets its own copy so story cleanup never touches the archive
+    let story_id = Uuid::new_v4();
+    let source_key = state
+        .media_service
+        .s3_key_from_url(&memory.media_url)
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+    let extension = source_key.rsplit('.').next().unwrap_or("jpg");
+    let dest_key = format!("stories/{}/story_{}.{}", user.id, story_id, extension);
+
+    let media_url = state
+        .media_service
+        .copy_media(&source_key, &dest_key)
+        .await
+        .map_err(|e| {
+            eprintln!("❌ Reshare copy failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let expires_at = crate::expiration::story_expires_at();
+
+    sqlx::query(
+        r#"
+        INSERT INTO stories (id, user_id, media_url, media_type, caption, alt_text, alt_text_generated, is_mature, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#
+    )
+    .bind(story_id)
+    .bind(user.id)
+    .bind(&media_url)
+    .bind(&memory.media_type)
+    .bind(&memory.caption)
+    .bind(&memory.alt_text)
+    .bind(memory.alt_text_generated)
+    .bind(memory.is_mature)
+    .bind(expires_at)
+    .execute(state.pool.as_ref())
+    .await
+    .map_err(|e| {
+        eprintln!("❌ Reshare insert failed: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    crate::video_render::enqueue_video_processing(&state.pool, story_id, &memory.media_type).await;
+
+    crate::webhooks::dispatch(
+        &state.pool,
+        &[user.id],
+        crate::webhooks::EVENT_STORY_CREATED,
+        serde_json::json!({
+            "story_id": story_id,
+            "user_id": user.id,
+            "media_url": media_url,
+            "media_type": memory.media_type,
+            "caption": memory.caption,
+            "alt_text": memory.alt_text,
+            "is_mature": memory.is_mature,
+            "expires_at": expires_at.and_utc(),
+        }),
+    )
+    .await;
+
+    Ok(Json(ReshareResponse {
+        story_id,
+        media_url: state.media_service.sign_url(&media_url).await,
+        expires_at,
+    }))
+}
+
+// Permanently delete an archived story and its media
+#[utoipa::path(
+    delete,
+    path = "/api/v1/memories/{memory_id}",
+    tag = "memories",
+    params(("memory_id" = Uuid, Path, description = "Memory ID")),
+    responses((status = 200, description = "Success"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn delete_memory(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(memory_id): Path<Uuid>,
+) -> Result<StatusCode, StatusCode> {
+    let memory = fetch_memory(&state.pool, memory_id, user.id).await?;
+
+    let keys: Vec<String> = std::iter::once(memory.media_url.as_str())
+        .chain(memory.thumbnail_url.as_deref())
+        .filter_map(|url| state.media_service.s3_key_from_url(url))
+        .collect();
+
+    let mut tx = state.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    sqlx::query("DELETE FROM story_archive WHERE id = $1 AND user_id = $2")
+        .bind(memory_id)
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let outbox_entry = crate::media_outbox::release_and_queue(&mut tx, &keys, "memory_deleted")
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to release memory media: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    crate::media_outbox::flush(&state.pool, &state.media_service, outbox_entry).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ============= Highlights =============
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct Highlight {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub title: String,
+    pub cover_url: Option<String>,
+    pub item_count: i64,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateHighlightRequest {
+    pub title: String,
+    #[serde(default)]
+    pub memory_ids: Vec<Uuid>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AddHighlightItemRequest {
+    pub memory_id: Uuid,
+}
+
+// List a user's highlights (public, shown on the profile)
+#[utoipa::path(
+    get,
+    path = "/api/v1/profile/{user_id}/highlights",
+    tag = "memories",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200, body = [Highlight]))
+)]
+pub async fn get_user_highlights(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<Vec<Highlight>>, StatusCode> {
+    let mut highlights = sqlx::query_as::<_, Highlight>(
+        r#"
+        SELECT h.id, h.user_id, h.title,
+               COALESCE(h.cover_url, (
+                   SELECT COALESCE(a.thumbnail_url, a.media_url)
+                   FROM story_highlight_items hi
+                   JOIN story_archive a ON a.id = hi.memory_id
+                   WHERE hi.highlight_id = h.id AND NOT a.is_mature
+                   ORDER BY hi.added_at ASC
+                   LIMIT 1
+               )) as cover_url,
+               (SELECT COUNT(*) FROM story_highlight_items hi WHERE hi.highlight_id = h.id) as item_count,
+               h.created_at
+        FROM story_highlights h
+        WHERE h.user_id = $1
+        ORDER BY h.created_at DESC
+        "#
+    )
+    .bind(user_id)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    for highlight in &mut highlights {
+        state.media_service.sign_opt(&mut highlight.cover_url).await;
+    }
+
+    Ok(Json(highlights))
+}
+
+// Items inside a highlight, in the order they were added
+#[utoipa::path(
+    get,
+    path = "/api/v1/highlights/{highlight_id}",
+    tag = "memories",
+    params(("highlight_id" = Uuid, Path, description = "Highlight ID")),
+    responses((status = 200, body = [Memory])),
+    security((), ("bearer_auth" = []))
+)]
+pub async fn get_highlight_items(
+    viewer: Option<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Path(highlight_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<Memory>>, StatusCode> {
+    let mut items = sqlx::query_as::<_, Memory>(
+        r#"
+        SELECT a.id, a.original_story_id, a.media_url, a.media_type, a.thumbnail_url, a.caption,
+               a.alt_text, a.alt_text_generated, a.is_mature, a.view_count, a.like_count, a.comment_count, a.original_created_at, a.archived_at
+        FROM story_highlight_items hi
+        JOIN story_archive a ON a.id = hi.memory_id
+        WHERE hi.highlight_id = $1
+          AND (NOT a.is_mature OR a.user_id = $2 OR user_is_adult($2))
+          AND NOT geo_blocked('story', a.original_story_id, $3)
+        ORDER BY hi.added_at ASC
+        "#
+    )
+    .bind(highlight_id)
+    .bind(viewer.map(|v| v.id))
+    .bind(crate::takedowns::request_country(&headers))
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    for item in &mut items {
+        item.sign_media(&state.media_service).await;
+    }
+
+    Ok(Json(items))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/highlights",
+    tag = "memories",
+    request_body = CreateHighlightRequest,
+    responses((status = 200, body = serde_json::Value), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn create_highlight(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CreateHighlightRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let title = payload.title.trim();
+    if title.is_empty() || title.chars().count() > 50 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let highlight_id = sqlx::query_scalar::<_, Uuid>(
+        "INSERT INTO story_highlights (user_id, title) VALUES ($1, $2) RETURNING id"
+    )
+    .bind(user.id)
+    .bind(title)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    for memory_id in payload.memory_ids {
+        add_item(&state.pool, highlight_id, memory_id, user.id).await?;
+    }
+
+    Ok(Json(serde_json::json!({ "highlight_id": highlight_id })))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/highlights/{highlight_id}/items",
+    tag = "memories",
+    params(("highlight_id" = Uuid, Path, description = "Highlight ID")),
+    request_body = AddHighlightItemRequest,
+    responses((status = 200, description = "Success"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn add_highlight_item(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(highlight_id): Path<Uuid>,
+    Json(payload): Json<AddHighlightItemRequest>,
+) -> Result<StatusCode, StatusCode> {
+    ensure_highlight_owner(&state.pool, highlight_id, user.id).await?;
+    add_item(&state.pool, highlight_id, payload.memory_id, user.id).await?;
+    Ok(StatusCode::OK)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/highlights/{highlight_id}/items/{memory_id}",
+    tag = "memories",
+    params(("highlight_id" = Uuid, Path, description = "Highlight ID"), ("memory_id" = Uuid, Path, description = "Memory ID")),
+    responses((status = 200, description = "Success"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn remove_highlight_item(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path((highlight_id, memory_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    ensure_highlight_owner(&state.pool, highlight_id, user.id).await?;
+
+    sqlx::query("DELETE FROM story_highlight_items WHERE highlight_id = $1 AND memory_id = $2")
+        .bind(highlight_id)
+        .bind(memory_id)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/highlights/{highlight_id}",
+    tag = "memories",
+    params(("highlight_id" = Uuid, Path, description = "Highlight ID")),
+    responses((status = 200, description = "Success"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn delete_highlight(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(highlight_id): Path<Uuid>,
+) -> Result<StatusCode, StatusCode> {
+    let deleted = sqlx::query("DELETE FROM story_highlights WHERE id = $1 AND user_id = $2")
+        .bind(highlight_id)
+        .bind(user.id)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .rows_affected();
+
+    if deleted == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn ensure_highlight_owner(pool: &PgPool, highlight_id: Uuid, user_id: Uuid) -> Result<(), StatusCode> {
+    let owned = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM story_highlights WHERE id = $1 AND user_id = $2)"
+    )
+    .bind(highlight_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if owned { Ok(()) } else { Err(StatusCode::NOT_FOUND) }
+}
+
+// Only the author's own memories can go into their highlights
+async fn add_item(pool: &PgPool, highlight_id: Uuid, memory_id: Uuid, user_id: Uuid) -> Result<(), StatusCode> {
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO story_highlight_items (highlight_id, memory_id)
+        SELECT $1, a.id FROM story_archive a WHERE a.id = $2 AND a.user_id = $3
+        ON CONFLICT DO NOTHING
+        "#
+    )
+    .bind(highlight_id)
+    .bind(memory_id)
+    .bind(user_id)
+    .execute(pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if inserted.rows_affected() == 0 {
+        // Either already present or not one of the caller's memories
+        fetch_memory(pool, memory_id, user_id).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,318 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::websocket::WsMessage;
+use crate::AppState;
+
+// DM privacy. dm_permission() (migration 046) decides whether someone may
+// message an account given its dm_privacy setting. People it doesn't allow
+// can still start a chat, but it arrives as a message request the recipient
+// accepts or declines; declining leaves the chat and stops that sender from
+// starting new ones. Accounts set to nobody can't be messaged at all.
+//
+// Until the recipient answers, the chat sits in their requests instead of
+// their chat list and its messages are neither pushed nor counted as unread.
+// A 1:1 chat's state (migration 047) stays pending until it's accepted.
+
+pub const DM_PRIVACY: [&str; 4] = ["everyone", "followers", "mutuals", "nobody"];
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    eprintln!("❌ Message request query failed: {:?}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+}
+
+#[derive(sqlx::FromRow)]
+struct MemberPermission {
+    user_id: Uuid,
+    username: String,
+    permission: Option<String>,
+    declined: bool,
+}
+
+/// Check a new chat against each member's DM privacy. Returns the members
+/// the chat must reach as a message request; members who don't take
+/// messages from the creator at all reject the chat.
+pub async fn check_new_chat(pool: &PgPool, creator_id: Uuid, member_ids: &[Uuid]) -> axum::response::Result<Vec<Uuid>> {
+    let members = sqlx::query_as::<_, MemberPermission>(
+        r#"
+        SELECT u.id AS user_id, u.username, dm_permission($1, u.id) AS permission,
+               EXISTS(SELECT 1 FROM message_requests
+                      WHERE sender_id = $1 AND recipient_id = u.id AND status = 'declined') AS declined
+        FROM users u
+        WHERE u.id = ANY($2) AND u.id <> $1
+        "#
+    )
+    .bind(creator_id)
+    .bind(member_ids)
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)?;
+
+    let mut requests = Vec::new();
+    for member in members {
+        match member.permission.as_deref() {
+            _ if member.declined => {
+                return Err((StatusCode::FORBIDDEN, format!("{} declined your message request", member.username)).into());
+            }
+            Some("denied") => {
+                return Err((StatusCode::FORBIDDEN, format!("{} isn't accepting messages", member.username)).into());
+            }
+            Some("request") => requests.push(member.user_id),
+            _ => {}
+        }
+    }
+
+    Ok(requests)
+}
+
+/// Record message requests for the members of a chat being created
+pub async fn create_requests(
+    conn: &mut sqlx::PgConnection,
+    chat_room_id: Uuid,
+    sender_id: Uuid,
+    recipient_ids: &[Uuid],
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO message_requests (chat_room_id, sender_id, recipient_id)
+        SELECT $1, $2, UNNEST($3::uuid[])
+        ON CONFLICT (chat_room_id, recipient_id) DO NOTHING
+        "#
+    )
+    .bind(chat_room_id)
+    .bind(sender_id)
+    .bind(recipient_ids)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+#[derive(sqlx::FromRow)]
+struct DirectChatPeer {
+    peer_id: Uuid,
+    peer_created: bool,
+    peer_has_written: bool,
+    /// The peer's request in this chat, if any
+    request_status: Option<String>,
+    permission: Option<String>,
+}
+
+/// Check a message about to be sent in a 1:1 chat against the other
+/// member's DM privacy. A conversation the other member started or replied
+/// to stays open; otherwise a sender the privacy setting doesn't allow turns
+/// the chat into a message request (and can keep writing into it until it's
+/// declined). Group chats are gated when members are added.
+pub async fn check_send(pool: &PgPool, sender_id: Uuid, chat_room_id: Uuid) -> Result<(), (StatusCode, String)> {
+    let peers = sqlx::query_as::<_, DirectChatPeer>(
+        r#"
+        SELECT cm.user_id AS peer_id,
+               cr.created_by = cm.user_id AS peer_created,
+               EXISTS(SELECT 1 FROM messages WHERE chat_room_id = cr.id AND sender_id = cm.user_id) AS peer_has_written,
+               (SELECT status FROM message_requests WHERE chat_room_id = cr.id AND recipient_id = cm.user_id) AS request_status,
+               dm_permission($1, cm.user_id) AS permission
+        FROM chat_rooms cr
+        JOIN chat_members cm ON cm.chat_room_id = cr.id AND cm.user_id <> $1
+        WHERE cr.id = $2 AND NOT cr.is_group
+        "#
+    )
+    .bind(sender_id)
+    .bind(chat_room_id)
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)?;
+
+    let [peer] = peers.as_slice() else {
+        return Ok(());
+    };
+
+    match peer.request_status.as_deref() {
+        Some("declined") => return Err((StatusCode::FORBIDDEN, "Your message request was declined".to_string())),
+        Some(_) => return Ok(()),
+        None if peer.peer_created || peer.peer_has_written => return Ok(()),
+        None => {}
+    }
+
+    match peer.permission.as_deref() {
+        Some("denied") => Err((StatusCode::FORBIDDEN, "This account isn't accepting messages".to_string())),
+        Some("request") => {
+            let mut conn = pool.acquire().await.map_err(db_error)?;
+            create_requests(&mut conn, chat_room_id, sender_id, &[peer.peer_id])
+                .await
+                .map_err(db_error)
+        }
+        _ => Ok(()),
+    }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct MessageRequest {
+    pub id: Uuid,
+    pub chat_room_id: Uuid,
+    pub is_group: bool,
+    /// Group name; None for 1:1 chats
+    pub chat_name: Option<String>,
+    pub sender_id: Uuid,
+    pub sender_username: String,
+    pub sender_avatar_url: Option<String>,
+    /// pending, accepted or declined
+    pub status: String,
+    /// Messages waiting in the chat
+    pub message_count: i64,
+    /// Latest text message, for a preview
+    pub preview: Option<String>,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps::option")]
+    pub responded_at: Option<NaiveDateTime>,
+}
+
+async fn fetch_requests(pool: &PgPool, recipient_id: Uuid, request_id: Option<Uuid>) -> Result<Vec<MessageRequest>, sqlx::Error> {
+    sqlx::query_as::<_, MessageRequest>(
+        r#"
+        SELECT r.id, r.chat_room_id, cr.is_group, cr.name AS chat_name,
+               r.sender_id, u.username AS sender_username, u.avatar_url AS sender_avatar_url,
+               r.status, r.created_at, r.responded_at,
+               (SELECT COUNT(*) FROM messages m
+                WHERE m.chat_room_id = r.chat_room_id AND m.deleted_at IS NULL
+                  AND (m.expires_at IS NULL OR m.expires_at > NOW())) AS message_count,
+               (SELECT m.content FROM messages m
+                WHERE m.chat_room_id = r.chat_room_id AND m.deleted_at IS NULL
+                  AND m.message_type = 'text' AND NOT m.view_once
+                  AND (m.expires_at IS NULL OR m.expires_at > NOW())
+                  AND NOT EXISTS(SELECT 1 FROM message_recipients
+                                 WHERE message_id = m.id AND user_id = $1 AND hidden_at IS NOT NULL)
+                ORDER BY m.created_at DESC
+                LIMIT 1) AS preview
+        FROM message_requests r
+        JOIN chat_rooms cr ON cr.id = r.chat_room_id
+        JOIN users u ON u.id = r.sender_id
+        WHERE r.recipient_id = $1
+          AND (($2::uuid IS NULL AND r.status = 'pending') OR r.id = $2)
+        ORDER BY r.created_at DESC
+        "#
+    )
+    .bind(recipient_id)
+    .bind(request_id)
+    .fetch_all(pool)
+    .await
+}
+
+// Pending message requests for the caller
+#[utoipa::path(
+    get,
+    path = "/api/v1/message-requests",
+    tag = "chat",
+    responses(
+        (status = 200, body = Vec<MessageRequest>),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_requests(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<MessageRequest>>, (StatusCode, String)> {
+    fetch_requests(&state.pool, user.id, None).await.map(Json).map_err(db_error)
+}
+
+// Accept a message request; the chat becomes a regular conversation
+#[utoipa::path(
+    post,
+    path = "/api/v1/message-requests/{request_id}/accept",
+    tag = "chat",
+    params(("request_id" = Uuid, Path, description = "Message request ID")),
+    responses(
+        (status = 200, body = MessageRequest),
+        (status = 404, description = "No pending request for the caller"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn accept_request(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(request_id): Path<Uuid>,
+) -> Result<Json<MessageRequest>, (StatusCode, String)> {
+    let chat_room_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE message_requests SET status = 'accepted', responded_at = NOW()
+        WHERE id = $1 AND recipient_id = $2 AND status = 'pending'
+        RETURNING chat_room_id
+        "#
+    )
+    .bind(request_id)
+    .bind(user.id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, "Message request not found".to_string()))?;
+
+    let event = WsMessage::MessageRequestAccepted { chat_room_id, user_id: user.id };
+    crate::websocket::broadcast_to_room(&state.pool, &state.connections, chat_room_id, &event).await;
+
+    fetch_requests(&state.pool, user.id, Some(request_id))
+        .await
+        .map_err(db_error)?
+        .pop()
+        .map(Json)
+        .ok_or((StatusCode::NOT_FOUND, "Message request not found".to_string()))
+}
+
+// Decline a message request: the caller leaves the chat and the sender
+// can't start another one with them
+#[utoipa::path(
+    post,
+    path = "/api/v1/message-requests/{request_id}/decline",
+    tag = "chat",
+    params(("request_id" = Uuid, Path, description = "Message request ID")),
+    responses(
+        (status = 204, description = "Declined"),
+        (status = 404, description = "No pending request for the caller"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn decline_request(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(request_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let mut tx = state.pool.begin().await.map_err(db_error)?;
+
+    let chat_room_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE message_requests SET status = 'declined', responded_at = NOW()
+        WHERE id = $1 AND recipient_id = $2 AND status = 'pending'
+        RETURNING chat_room_id
+        "#
+    )
+    .bind(request_id)
+    .bind(user.id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, "Message request not found".to_string()))?;
+
+    sqlx::query("DELETE FROM chat_members WHERE chat_room_id = $1 AND user_id = $2")
+        .bind(chat_room_id)
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+
+    tx.commit().await.map_err(db_error)?;
+    crate::cache::invalidate_membership(&state.redis, chat_room_id, user.id).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
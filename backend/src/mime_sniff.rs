@@ -0,0 +1,237 @@
+use axum::http::StatusCode;
+
+// Server-side format detection for uploads. The client's content type and file
+// name are ignored: the format comes from the file's magic bytes, and the
+// stored object gets the matching content type and extension.
+
+use crate::runtime_settings::{MAX_GIF_MB, MAX_IMAGE_MB, MAX_VIDEO_MB};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Image,
+    Video,
+}
+
+impl MediaKind {
+    /// Value stored in stories.media_type / messages.message_type
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MediaKind::Image => "image",
+            MediaKind::Video => "video",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Format {
+    pub kind: MediaKind,
+    pub content_type: &'static str,
+    pub extension: &'static str,
+    /// Runtime setting holding the size limit, in MB
+    size_setting: &'static str,
+}
+
+impl Format {
+    pub fn max_bytes(&self) -> usize {
+        crate::runtime_settings::int(self.size_setting).max(0) as usize * 1024 * 1024
+    }
+}
+
+const JPEG: Format = Format { kind: MediaKind::Image, content_type: "image/jpeg", extension: "jpg", size_setting: MAX_IMAGE_MB };
+const PNG: Format = Format { kind: MediaKind::Image, content_type: "image/png", extension: "png", size_setting: MAX_IMAGE_MB };
+const GIF: Format = Format { kind: MediaKind::Image, content_type: "image/gif", extension: "gif", size_setting: MAX_GIF_MB };
+const WEBP: Format = Format { kind: MediaKind::Image, content_type: "image/webp", extension: "webp", size_setting: MAX_IMAGE_MB };
+const MP4: Format = Format { kind: MediaKind::Video, content_type: "video/mp4", extension: "mp4", size_setting: MAX_VIDEO_MB };
+const QUICKTIME: Format = Format { kind: MediaKind::Video, content_type: "video/quicktime", extension: "mov", size_setting: MAX_VIDEO_MB };
+const WEBM: Format = Format { kind: MediaKind::Video, content_type: "video/webm", extension: "webm", size_setting: MAX_VIDEO_MB };
+
+// ISO base media brands we accept as MP4 video
+const MP4_BRANDS: &[&[u8; 4]] = &[b"isom", b"iso2", b"iso4", b"iso5", b"iso6", b"mp41", b"mp42", b"avc1", b"M4V ", b"dash"];
+
+#[derive(Debug)]
+pub enum FormatError {
+    Unsupported,
+    WrongKind { expected: MediaKind, found: &'static str },
+    TooLarge { content_type: &'static str, max_bytes: usize },
+}
+
+impl FormatError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            FormatError::Unsupported | FormatError::WrongKind { .. } => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            FormatError::TooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+        }
+    }
+}
+
+impl std::fmt::Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormatError::Unsupported => {
+                f.write_str("Unsupported file format (allowed: JPEG, PNG, GIF, WebP, MP4, MOV, WebM)")
+            }
+            FormatError::WrongKind { expected, found } => {
+                let expected = match expected {
+                    MediaKind::Image => "an image",
+                    MediaKind::Video => "a video",
+                };
+                write!(f, "Expected {} file but got {}", expected, found)
+            }
+            FormatError::TooLarge { content_type, max_bytes } => {
+                write!(f, "{} files can be at most {} MB", content_type, max_bytes / (1024 * 1024))
+            }
+        }
+    }
+}
+
+/// Content type of a stored object from the extension it was saved under
+pub fn content_type_from_url(url: &str) -> Option<&'static str> {
+    let path = url.split(['?', '#']).next()?;
+    let extension = path.rsplit_once('.')?.1;
+    [JPEG, PNG, GIF, WEBP, MP4, QUICKTIME, WEBM]
+        .iter()
+        .find(|format| format.extension.eq_ignore_ascii_case(extension))
+        .map(|format| format.content_type)
+}
+
+/// Detect the format from the leading bytes
+pub fn sniff(data: &[u8]) -> Option<Format> {
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some(JPEG);
+    }
+    if data.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]) {
+        return Some(PNG);
+    }
+    if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        return Some(GIF);
+    }
+    if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        return Some(WEBP);
+    }
+    if data.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        return Some(WEBM);
+    }
+    // ISO base media: [size]["ftyp"][major brand]
+    if data.len() >= 12 && &data[4..8] == b"ftyp" {
+        let brand = &data[8..12];
+        if brand == b"qt  " {
+            return Some(QUICKTIME);
+        }
+        if MP4_BRANDS.iter().any(|b| brand == &b[..]) {
+            return Some(MP4);
+        }
+    }
+    None
+}
+
+/// Sniff an upload and check it is an allowed format of the expected kind,
+/// within that format's size limit
+pub fn validate(data: &[u8], expected: Option<MediaKind>) -> Result<Format, FormatError> {
+    let format = sniff(data).ok_or(FormatError::Unsupported)?;
+    if let Some(expected) = expected {
+        if format.kind != expected {
+            return Err(FormatError::WrongKind { expected, found: format.content_type });
+        }
+    }
+    let max_bytes = format.max_bytes();
+    if data.len() > max_bytes {
+        return Err(FormatError::TooLarge { content_type: format.content_type, max_bytes });
+    }
+    Ok(format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An ISO base media header with the given major brand
+    fn ftyp(brand: &[u8; 4]) -> Vec<u8> {
+        let mut data = vec![0x00, 0x00, 0x00, 0x18];
+        data.extend_from_slice(b"ftyp");
+        data.extend_from_slice(brand);
+        data.extend_from_slice(&[0x00; 12]);
+        data
+    }
+
+    fn sniffed(data: &[u8]) -> Option<&'static str> {
+        sniff(data).map(|format| format.content_type)
+    }
+
+    #[test]
+    fn recognises_each_accepted_signature() {
+        assert_eq!(sniffed(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, b'J', b'F']), Some("image/jpeg"));
+        assert_eq!(sniffed(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0x00]), Some("image/png"));
+        assert_eq!(sniffed(b"GIF87a\x01\x00"), Some("image/gif"));
+        assert_eq!(sniffed(b"GIF89a\x01\x00"), Some("image/gif"));
+        assert_eq!(sniffed(b"RIFF\x24\x00\x00\x00WEBPVP8 "), Some("image/webp"));
+        assert_eq!(sniffed(&[0x1A, 0x45, 0xDF, 0xA3, 0x9F, 0x42]), Some("video/webm"));
+        assert_eq!(sniffed(&ftyp(b"qt  ")), Some("video/quicktime"));
+        for brand in MP4_BRANDS {
+            assert_eq!(sniffed(&ftyp(brand)), Some("video/mp4"), "brand {:?}", std::str::from_utf8(*brand));
+        }
+    }
+
+    #[test]
+    fn formats_carry_their_kind_and_extension() {
+        let jpeg = sniff(&[0xFF, 0xD8, 0xFF]).unwrap();
+        assert_eq!((jpeg.kind, jpeg.extension), (MediaKind::Image, "jpg"));
+        let mov = sniff(&ftyp(b"qt  ")).unwrap();
+        assert_eq!((mov.kind, mov.extension), (MediaKind::Video, "mov"));
+    }
+
+    #[test]
+    fn rejects_truncated_headers() {
+        assert_eq!(sniffed(&[]), None);
+        assert_eq!(sniffed(&[0xFF, 0xD8]), None);
+        assert_eq!(sniffed(&[0x89, b'P', b'N', b'G']), None);
+        assert_eq!(sniffed(b"GIF8"), None);
+        assert_eq!(sniffed(b"RIFF\x24\x00\x00\x00WEB"), None);
+        assert_eq!(sniffed(&[0x1A, 0x45, 0xDF]), None);
+        assert_eq!(sniffed(&ftyp(b"isom")[..11]), None);
+    }
+
+    #[test]
+    fn rejects_mismatched_signatures() {
+        // RIFF containers other than WebP, e.g. WAV and AVI
+        assert_eq!(sniffed(b"RIFF\x24\x00\x00\x00WAVEfmt "), None);
+        assert_eq!(sniffed(b"RIFF\x24\x00\x00\x00AVI LIST"), None);
+        // ISO base media with brands we don't take (HEIC, 3GP)
+        assert_eq!(sniffed(&ftyp(b"heic")), None);
+        assert_eq!(sniffed(&ftyp(b"3gp4")), None);
+        // ftyp in the wrong place
+        assert_eq!(sniffed(b"ftypisom\x00\x00\x00\x00"), None);
+        assert_eq!(sniffed(b"GIF90a"), None);
+        assert_eq!(sniffed(b"%PDF-1.7"), None);
+        assert_eq!(sniffed(b"<svg xmlns=\"http://www.w3.org/2000/svg\">"), None);
+        assert_eq!(sniffed(&[0x50, 0x4B, 0x03, 0x04]), None);
+    }
+
+    #[test]
+    fn validates_kind_and_size() {
+        let png = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+        assert_eq!(validate(&png, Some(MediaKind::Image)).unwrap().content_type, "image/png");
+        assert_eq!(validate(&png, None).unwrap().content_type, "image/png");
+
+        let error = validate(&png, Some(MediaKind::Video)).unwrap_err();
+        assert!(matches!(error, FormatError::WrongKind { expected: MediaKind::Video, found: "image/png" }));
+        assert_eq!(error.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let error = validate(b"not media", None).unwrap_err();
+        assert!(matches!(error, FormatError::Unsupported));
+        assert_eq!(error.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let mut gif = b"GIF89a".to_vec();
+        gif.resize(GIF.max_bytes() + 1, 0);
+        let error = validate(&gif, None).unwrap_err();
+        assert!(matches!(error, FormatError::TooLarge { content_type: "image/gif", .. }));
+        assert_eq!(error.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn content_type_follows_the_stored_extension() {
+        assert_eq!(content_type_from_url("https://cdn.example.com/media/a.JPG?X-Amz-Signature=abc"), Some("image/jpeg"));
+        assert_eq!(content_type_from_url("/media/clip.webm#t=1"), Some("video/webm"));
+        assert_eq!(content_type_from_url("/media/file.exe"), None);
+        assert_eq!(content_type_from_url("/media/noextension"), None);
+    }
+}
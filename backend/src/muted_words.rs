@@ -0,0 +1,225 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::AppState;
+
+// Muted words (migration 065). A phrase matches text that contains it as
+// whole words, ignoring case and extra whitespace: "big spoiler" matches
+// "BIG  spoiler ahead!" but not "big spoilers".
+//   comments  add_comment/add_reply hide a comment containing one of the
+//             story author's phrases from everyone but the commenter
+//   messages  deliver_message marks a message containing one of a
+//             recipient's phrases with muted_word for that recipient, and
+//             clients show it folded behind a warning. End-to-end encrypted
+//             chats are left to the clients.
+// Matching happens when the comment or message is written; muting a word
+// later doesn't fold what already arrived.
+
+pub const MAX_MUTED_WORDS: i64 = 200;
+pub const MAX_PHRASE_LENGTH: usize = 100;
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct MutedWord {
+    pub id: Uuid,
+    pub phrase: String,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AddMutedWordRequest {
+    pub phrase: String,
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    eprintln!("❌ Muted words query failed: {:?}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+}
+
+/// Lowercase with runs of whitespace as one space, the form phrases are stored in
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// The first of `phrases` that `text` contains as whole words
+pub fn find_match<'a>(phrases: &'a [String], text: &str) -> Option<&'a str> {
+    let text = normalize(text);
+    phrases.iter().map(String::as_str).find(|phrase| contains_phrase(&text, phrase))
+}
+
+fn contains_phrase(text: &str, phrase: &str) -> bool {
+    !phrase.is_empty()
+        && text.match_indices(phrase).any(|(start, _)| {
+            let before = text[..start].chars().next_back();
+            let after = text[start + phrase.len()..].chars().next();
+            !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+        })
+}
+
+/// A user's muted phrases
+pub async fn load(pool: &PgPool, user_id: Uuid) -> Vec<String> {
+    sqlx::query_scalar::<_, String>("SELECT phrase FROM muted_words WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to load muted words of {}: {}", user_id, e);
+            Vec::new()
+        })
+}
+
+/// Muted phrases of each of `user_ids` that has any
+pub async fn load_many(pool: &PgPool, user_ids: &[Uuid]) -> HashMap<Uuid, Vec<String>> {
+    let rows = sqlx::query_as::<_, (Uuid, String)>("SELECT user_id, phrase FROM muted_words WHERE user_id = ANY($1)")
+        .bind(user_ids)
+        .fetch_all(pool)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to load muted words: {}", e);
+            Vec::new()
+        });
+
+    let mut phrases: HashMap<Uuid, Vec<String>> = HashMap::new();
+    for (user_id, phrase) in rows {
+        phrases.entry(user_id).or_default().push(phrase);
+    }
+    phrases
+}
+
+/// Whether a comment on `story_id` should be hidden by its author's muted words
+pub async fn hides_comment(pool: &PgPool, story_id: Uuid, comment_text: &str) -> bool {
+    let author_id = match sqlx::query_scalar::<_, Uuid>("SELECT user_id FROM stories WHERE id = $1")
+        .bind(story_id)
+        .fetch_optional(pool)
+        .await
+    {
+        Ok(Some(author_id)) => author_id,
+        Ok(None) => return false,
+        Err(e) => {
+            tracing::error!("Failed to load author of story {}: {}", story_id, e);
+            return false;
+        }
+    };
+    find_match(&load(pool, author_id).await, comment_text).is_some()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/muted-words",
+    tag = "social",
+    responses((status = 200, body = [MutedWord]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_muted_words(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<MutedWord>>, (StatusCode, String)> {
+    let words = sqlx::query_as::<_, MutedWord>(
+        "SELECT id, phrase, created_at FROM muted_words WHERE user_id = $1 ORDER BY phrase"
+    )
+    .bind(user.id)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(words))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/muted-words",
+    tag = "social",
+    request_body = AddMutedWordRequest,
+    responses(
+        (status = 201, body = MutedWord),
+        (status = 400, description = "Empty or too long phrase, or too many muted words"),
+        (status = 409, description = "Already muted"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn add_muted_word(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<AddMutedWordRequest>,
+) -> Result<(StatusCode, Json<MutedWord>), (StatusCode, String)> {
+    let phrase = normalize(&payload.phrase);
+    if phrase.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Phrase can't be empty".to_string()));
+    }
+    if phrase.chars().count() > MAX_PHRASE_LENGTH {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Muted phrases can be at most {} characters", MAX_PHRASE_LENGTH),
+        ));
+    }
+
+    let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM muted_words WHERE user_id = $1")
+        .bind(user.id)
+        .fetch_one(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+    if count >= MAX_MUTED_WORDS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("You can mute at most {} words", MAX_MUTED_WORDS),
+        ));
+    }
+
+    let word = sqlx::query_as::<_, MutedWord>(
+        "INSERT INTO muted_words (user_id, phrase) VALUES ($1, $2) RETURNING id, phrase, created_at"
+    )
+    .bind(user.id)
+    .bind(&phrase)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(ref db) if db.is_unique_violation() => {
+            (StatusCode::CONFLICT, "You already muted this phrase".to_string())
+        }
+        e => db_error(e),
+    })?;
+
+    Ok((StatusCode::CREATED, Json(word)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/muted-words/{word_id}",
+    tag = "social",
+    params(("word_id" = Uuid, Path, description = "Muted word ID")),
+    responses(
+        (status = 204, description = "Unmuted"),
+        (status = 404, description = "No such muted word of the caller's"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn delete_muted_word(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(word_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let deleted = sqlx::query("DELETE FROM muted_words WHERE id = $1 AND user_id = $2")
+        .bind(word_id)
+        .bind(user.id)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+    if deleted.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Muted word not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
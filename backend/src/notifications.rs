@@ -3,26 +3,102 @@ use axum::{
     http::StatusCode,
     Json,
 };
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 use std::sync::Arc;
+use std::time::Duration;
+use crate::admin::AuthUser;
+use crate::websocket::{send_to_user, Connections, WsMessage};
 use crate::AppState;
 
-#[derive(Deserialize)]
-pub struct LimitQuery {
+// Notification types by category, for filtering. Anything not listed here
+// (follows, likes, comments, community and channel activity, calls...)
+// counts as social, so new kinds of activity need no entry.
+const ADS_TYPES: [&str; 1] = ["ad_review"];
+const SYSTEM_TYPES: [&str; 7] = [
+    "announcement",
+    "security_alert",
+    "referral_reward",
+    "supervision_request",
+    "supervision_accepted",
+    "supervision_ended",
+    "supervision_limits",
+];
+
+fn category(notification_type: &str) -> &'static str {
+    if ADS_TYPES.contains(&notification_type) {
+        "ads"
+    } else if SYSTEM_TYPES.contains(&notification_type) {
+        "system"
+    } else {
+        "social"
+    }
+}
+
+// Types a category filter matches, and whether it matches everything but them
+fn category_filter(category: Option<&str>) -> Result<(Vec<&'static str>, bool), StatusCode> {
+    match category {
+        None => Ok((Vec::new(), true)),
+        Some("ads") => Ok((ADS_TYPES.to_vec(), false)),
+        Some("system") => Ok((SYSTEM_TYPES.to_vec(), false)),
+        Some("social") => Ok(([ADS_TYPES.as_slice(), SYSTEM_TYPES.as_slice()].concat(), true)),
+        Some(_) => Err(StatusCode::BAD_REQUEST),
+    }
+}
+
+// Cursors are the last notification's (created_at, id), opaque to clients
+fn encode_cursor(created_at: NaiveDateTime, id: uuid::Uuid) -> String {
+    URL_SAFE_NO_PAD.encode(format!("{}:{}", created_at.and_utc().timestamp_micros(), id))
+}
+
+fn decode_cursor(cursor: &str) -> Option<(NaiveDateTime, uuid::Uuid)> {
+    let decoded = String::from_utf8(URL_SAFE_NO_PAD.decode(cursor).ok()?).ok()?;
+    let (micros, id) = decoded.split_once(':')?;
+    let created_at = chrono::DateTime::from_timestamp_micros(micros.parse().ok()?)?.naive_utc();
+    Some((created_at, uuid::Uuid::parse_str(id).ok()?))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NotificationsQuery {
     #[serde(default = "default_limit")]
     pub limit: i64,
+    /// next_cursor from the previous page
+    pub cursor: Option<String>,
+    /// social, system or ads
+    pub category: Option<String>,
+    /// Only this notification type, e.g. follow or comment
+    #[serde(rename = "type")]
+    #[param(rename = "type")]
+    pub notification_type: Option<String>,
 }
 
 fn default_limit() -> i64 {
     50
 }
 
-#[derive(Serialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ClearNotificationsQuery {
+    /// Delete notifications at least this many days old (default 30)
+    pub older_than_days: Option<i64>,
+    /// Delete unread ones too (default false)
+    #[serde(default)]
+    pub include_unread: bool,
+    /// Only this category: social, system or ads
+    pub category: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
 pub struct Notification {
     pub id: String,
     pub user_id: String,
     #[serde(rename = "type")]
     pub notification_type: String,
+    /// social, system or ads
+    pub category: String,
     pub from_user_id: Option<String>,
     pub from_username: Option<String>,
     pub from_avatar_url: Option<String>,
@@ -33,86 +109,189 @@ pub struct Notification {
     pub created_at: String,
 }
 
-#[derive(Serialize)]
+#[derive(sqlx::FromRow)]
+struct NotificationRow {
+    id: uuid::Uuid,
+    user_id: uuid::Uuid,
+    notification_type: String,
+    from_user_id: Option<uuid::Uuid>,
+    from_username: Option<String>,
+    from_avatar_url: Option<String>,
+    story_id: Option<uuid::Uuid>,
+    comment_id: Option<uuid::Uuid>,
+    message: Option<String>,
+    message_key: Option<String>,
+    message_args: serde_json::Value,
+    is_read: Option<bool>,
+    created_at: Option<chrono::NaiveDateTime>,
+}
+
+// Notifications with the sender's details, announcements in the reader's
+// language; $3 is always the locale
+const NOTIFICATION_COLUMNS: &str = r#"
+    n.id,
+    n.user_id,
+    n.type AS notification_type,
+    n.from_user_id,
+    u.username as from_username,
+    u.avatar_url as from_avatar_url,
+    n.story_id,
+    n.comment_id,
+    CASE WHEN a.id IS NOT NULL
+         THEN COALESCE(a.translations -> $3 ->> 'title', a.title) || ': ' || COALESCE(a.translations -> $3 ->> 'body', a.body)
+         ELSE n.message
+    END AS message,
+    n.message_key,
+    n.message_args,
+    n.is_read,
+    n.created_at
+    FROM notifications n
+    LEFT JOIN users u ON n.from_user_id = u.id
+    LEFT JOIN announcements a ON a.id = n.announcement_id
+"#;
+
+impl NotificationRow {
+    // Text from a message key is rendered in the reader's language
+    fn rendered_message(&self, locale: &str) -> Option<String> {
+        match &self.message_key {
+            Some(key) => Some(crate::i18n::render(locale, key, &self.message_args)),
+            None => self.message.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
 pub struct NotificationResponse {
     pub notifications: Vec<Notification>,
     pub unread_count: i64,
+    /// Pass as `cursor` for the next page; null on the last one
+    pub next_cursor: Option<String>,
 }
 
-// Get user's notifications
+// Get user's notifications, newest first, a page at a time
+#[utoipa::path(
+    get,
+    path = "/api/v1/notifications/{user_id}",
+    tag = "notifications",
+    params(("user_id" = String, Path, description = "User ID"), NotificationsQuery),
+    responses(
+        (status = 200, body = NotificationResponse),
+        (status = 304, description = "Unchanged since the ETag in If-None-Match"),
+        (status = 400, description = "Invalid cursor or category")
+    )
+)]
 pub async fn get_notifications(
     State(state): State<Arc<AppState>>,
     Path(user_id): Path<String>,
-    Query(params): Query<LimitQuery>,
+    Query(params): Query<NotificationsQuery>,
 ) -> Result<Json<NotificationResponse>, StatusCode> {
     let user_uuid = uuid::Uuid::parse_str(&user_id)
         .map_err(|_| StatusCode::BAD_REQUEST)?;
 
-    let limit = params.limit.min(100);
+    let (notifications, next_cursor) = fetch_page(&state.pool, user_uuid, &params).await?;
+    let unread_count = unread_count(&state.pool, user_uuid)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(NotificationResponse {
+        notifications,
+        unread_count,
+        next_cursor,
+    }))
+}
 
-    // Get notifications with user info
-    let notifications = sqlx::query!(
+/// One page of a user's notifications, newest first, with the cursor of the
+/// next page (shared with the GraphQL gateway)
+pub(crate) async fn fetch_page(
+    pool: &sqlx::PgPool,
+    user_uuid: uuid::Uuid,
+    params: &NotificationsQuery,
+) -> Result<(Vec<Notification>, Option<String>), StatusCode> {
+    let limit = params.limit.clamp(1, 100);
+    let cursor = match params.cursor.as_deref() {
+        Some(cursor) => Some(decode_cursor(cursor).ok_or(StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+    let (category_types, exclude_types) = category_filter(params.category.as_deref())?;
+
+    // Get notifications with user info, in the reader's language where possible
+    let locale = crate::i18n::user_locale(pool, user_uuid).await;
+    let mut notifications = sqlx::query_as::<_, NotificationRow>(&format!(
         r#"
-        SELECT 
-            n.id,
-            n.user_id,
-            n.type,
-            n.from_user_id,
-            u.username as from_username,
-            u.avatar_url as from_avatar_url,
-            n.story_id,
-            n.comment_id,
-            n.message,
-            n.is_read,
-            n.created_at
-        FROM notifications n
-        LEFT JOIN users u ON n.from_user_id = u.id
+        SELECT {}
         WHERE n.user_id = $1
-        ORDER BY n.created_at DESC
+          AND ($4::timestamp IS NULL OR (n.created_at, n.id) < ($4, $5))
+          AND (n.type = ANY($6) <> $7)
+          AND ($8::text IS NULL OR n.type = $8)
+        ORDER BY n.created_at DESC, n.id DESC
         LIMIT $2
         "#,
-        user_uuid,
-        limit
-    )
-    .fetch_all(&*state.pool)
+        NOTIFICATION_COLUMNS
+    ))
+    .bind(user_uuid)
+    // One extra row tells whether there's another page
+    .bind(limit + 1)
+    .bind(locale)
+    .bind(cursor.map(|(created_at, _)| created_at))
+    .bind(cursor.map(|(_, id)| id))
+    .bind(&category_types)
+    .bind(exclude_types)
+    .bind(&params.notification_type)
+    .fetch_all(pool)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    // Get unread count
-    let unread_count = sqlx::query!(
-        "SELECT COUNT(*) as count FROM notifications WHERE user_id = $1 AND is_read = FALSE",
-        user_uuid
-    )
-    .fetch_one(&*state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    .count
-    .unwrap_or(0);
+    let next_cursor = if notifications.len() as i64 > limit {
+        notifications.truncate(limit as usize);
+        notifications.last().and_then(|n| n.created_at.map(|created_at| encode_cursor(created_at, n.id)))
+    } else {
+        None
+    };
 
     let result = notifications
         .into_iter()
-        .map(|n| Notification {
-            id: n.id.to_string(),
-            user_id: n.user_id.to_string(),
-            notification_type: n.r#type,
-            from_user_id: n.from_user_id.map(|id| id.to_string()),
-            from_username: Some(n.from_username),
-            from_avatar_url: n.from_avatar_url,
-            story_id: n.story_id.map(|id| id.to_string()),
-            comment_id: n.comment_id.map(|id| id.to_string()),
-            message: n.message,
-            is_read: n.is_read.unwrap_or(false),
-            created_at: n.created_at.map(|t| t.to_string()).unwrap_or_default(),
+        .map(|n| {
+            let message = n.rendered_message(locale);
+            Notification {
+                id: n.id.to_string(),
+                user_id: n.user_id.to_string(),
+                category: category(&n.notification_type).to_string(),
+                notification_type: n.notification_type,
+                from_user_id: n.from_user_id.map(|id| id.to_string()),
+                from_username: n.from_username,
+                from_avatar_url: n.from_avatar_url,
+                story_id: n.story_id.map(|id| id.to_string()),
+                comment_id: n.comment_id.map(|id| id.to_string()),
+                message,
+                is_read: n.is_read.unwrap_or(false),
+                created_at: n.created_at.map(crate::timestamps::format).unwrap_or_default(),
+            }
         })
         .collect();
 
-    Ok(Json(NotificationResponse {
-        notifications: result,
-        unread_count,
-    }))
+    Ok((result, next_cursor))
+}
+
+/// How many of a user's notifications are unread
+pub(crate) async fn unread_count(pool: &sqlx::PgPool, user_uuid: uuid::Uuid) -> Result<i64, sqlx::Error> {
+    Ok(sqlx::query!(
+        "SELECT COUNT(*) as count FROM notifications WHERE user_id = $1 AND is_read = FALSE",
+        user_uuid
+    )
+    .fetch_one(pool)
+    .await?
+    .count
+    .unwrap_or(0))
 }
 
 // Mark notification as read
+#[utoipa::path(
+    post,
+    path = "/api/v1/notifications/{user_id}/{notification_id}/read",
+    tag = "notifications",
+    params(("user_id" = String, Path, description = "User ID"), ("notification_id" = String, Path, description = "Notification ID")),
+    responses((status = 200, body = serde_json::Value))
+)]
 pub async fn mark_notification_read(
     State(state): State<Arc<AppState>>,
     Path((user_id, notification_id)): Path<(String, String)>,
@@ -135,6 +314,13 @@ pub async fn mark_notification_read(
 }
 
 // Mark all notifications as read
+#[utoipa::path(
+    post,
+    path = "/api/v1/notifications/{user_id}/read-all",
+    tag = "notifications",
+    params(("user_id" = String, Path, description = "User ID")),
+    responses((status = 200, body = serde_json::Value))
+)]
 pub async fn mark_all_notifications_read(
     State(state): State<Arc<AppState>>,
     Path(user_id): Path<String>,
@@ -154,6 +340,13 @@ pub async fn mark_all_notifications_read(
 }
 
 // Delete notification
+#[utoipa::path(
+    delete,
+    path = "/api/v1/notifications/{user_id}/{notification_id}",
+    tag = "notifications",
+    params(("user_id" = String, Path, description = "User ID"), ("notification_id" = String, Path, description = "Notification ID")),
+    responses((status = 200, body = serde_json::Value))
+)]
 pub async fn delete_notification(
     State(state): State<Arc<AppState>>,
     Path((user_id, notification_id)): Path<(String, String)>,
@@ -175,7 +368,63 @@ pub async fn delete_notification(
     Ok(Json(serde_json::json!({ "success": true })))
 }
 
+// Clear out old notifications in bulk
+#[utoipa::path(
+    delete,
+    path = "/api/v1/notifications/{user_id}",
+    tag = "notifications",
+    params(("user_id" = String, Path, description = "User ID"), ClearNotificationsQuery),
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 400, description = "Invalid age or category"),
+        (status = 403, description = "Not your account"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn clear_notifications(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<String>,
+    Query(params): Query<ClearNotificationsQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let user_uuid = crate::settings::require_self(&user, &user_id)?;
+    let older_than_days = params.older_than_days.unwrap_or(30);
+    if older_than_days < 0 {
+        return Err((StatusCode::BAD_REQUEST, "older_than_days can't be negative".to_string()));
+    }
+    let (category_types, exclude_types) = category_filter(params.category.as_deref())
+        .map_err(|status| (status, "Unknown category".to_string()))?;
+
+    let result = sqlx::query(
+        r#"
+        DELETE FROM notifications
+        WHERE user_id = $1
+          AND created_at <= NOW() - make_interval(days => $2)
+          AND ($3 OR is_read = TRUE)
+          AND (type = ANY($4) <> $5)
+        "#
+    )
+    .bind(user_uuid)
+    .bind(older_than_days.min(36_500) as i32)
+    .bind(params.include_unread)
+    .bind(&category_types)
+    .bind(exclude_types)
+    .execute(&*state.pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to clear notifications".to_string()))?;
+
+    Ok(Json(serde_json::json!({ "success": true, "deleted": result.rows_affected() })))
+}
+
 // Get unread notification count
+#[utoipa::path(
+    get,
+    path = "/api/v1/notifications/{user_id}/unread",
+    tag = "notifications",
+    params(("user_id" = String, Path, description = "User ID")),
+    responses((status = 200, body = serde_json::Value), (status = 304, description = "Unchanged since the ETag in If-None-Match"))
+)]
 pub async fn get_unread_count(
     State(state): State<Arc<AppState>>,
     Path(user_id): Path<String>,
@@ -195,3 +444,95 @@ pub async fn get_unread_count(
 
     Ok(Json(serde_json::json!({ "unread_count": count })))
 }
+
+// Channel migration 082 announces new notifications on, as "<user_id>:<id>"
+const CREATED_CHANNEL: &str = "notification_created";
+const LISTEN_RETRY: Duration = Duration::from_secs(5);
+
+/// Push each new notification to its recipient's sockets on this instance as
+/// a NotificationCreated event, with their new unread count. Runs for the
+/// life of the process, reconnecting when the listening connection drops;
+/// notifications written meanwhile are only seen by polling.
+pub async fn push_new(pool: Arc<sqlx::PgPool>, connections: Connections) {
+    loop {
+        let mut listener = match sqlx::postgres::PgListener::connect_with(&pool).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                eprintln!("❌ Couldn't listen for new notifications: {:?}", e);
+                tokio::time::sleep(LISTEN_RETRY).await;
+                continue;
+            }
+        };
+        if let Err(e) = listener.listen(CREATED_CHANNEL).await {
+            eprintln!("❌ Couldn't listen for new notifications: {:?}", e);
+            tokio::time::sleep(LISTEN_RETRY).await;
+            continue;
+        }
+
+        loop {
+            let event = match listener.recv().await {
+                Ok(event) => event,
+                Err(e) => {
+                    eprintln!("❌ Lost the notification listener: {:?}", e);
+                    break;
+                }
+            };
+            let Some((user_id, notification_id)) = event
+                .payload()
+                .split_once(':')
+                .and_then(|(user_id, id)| Some((uuid::Uuid::parse_str(user_id).ok()?, uuid::Uuid::parse_str(id).ok()?)))
+            else {
+                continue;
+            };
+            // Most recipients aren't connected here; skip them before touching the database
+            if !connections.contains_key(&user_id) {
+                continue;
+            }
+            if let Err(e) = push_one(&pool, &connections, user_id, notification_id).await {
+                eprintln!("❌ Failed to push notification {}: {:?}", notification_id, e);
+            }
+        }
+        tokio::time::sleep(LISTEN_RETRY).await;
+    }
+}
+
+async fn push_one(pool: &sqlx::PgPool, connections: &Connections, user_id: uuid::Uuid, notification_id: uuid::Uuid) -> Result<(), sqlx::Error> {
+    let locale = crate::i18n::user_locale(pool, user_id).await;
+    let notification = sqlx::query_as::<_, NotificationRow>(&format!(
+        "SELECT {} WHERE n.id = $1 AND n.user_id = $2",
+        NOTIFICATION_COLUMNS
+    ))
+    .bind(notification_id)
+    .bind(user_id)
+    .bind(locale)
+    .fetch_optional(pool)
+    .await?;
+    // Deleted again before we got to it
+    let Some(notification) = notification else {
+        return Ok(());
+    };
+    let unread_count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM notifications WHERE user_id = $1 AND is_read = FALSE")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+
+    let message = notification.rendered_message(locale);
+    send_to_user(
+        connections,
+        user_id,
+        &WsMessage::NotificationCreated {
+            notification_id,
+            category: category(&notification.notification_type).to_string(),
+            notification_type: notification.notification_type,
+            from_user_id: notification.from_user_id,
+            from_username: notification.from_username,
+            from_avatar_url: notification.from_avatar_url,
+            story_id: notification.story_id,
+            comment_id: notification.comment_id,
+            message,
+            created_at: notification.created_at.map(crate::timestamps::format).unwrap_or_default(),
+            unread_count,
+        },
+    );
+    Ok(())
+}
@@ -0,0 +1,668 @@
+use axum::{response::Html, Json};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+// OpenAPI document for the /api/v1 REST surface. Paths come from the
+// #[utoipa::path] annotations on each handler and schemas from the request and
+// response structs, so the handlers stay the single source of truth.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Social Media App API",
+        version = "1.0.0",
+        description = "REST API for chat, stories, social graph, ads and administration"
+    ),
+    paths(
+        crate::auth::signup,
+        crate::auth::login,
+        crate::auth::login_with_phone,
+        crate::auth::recover_with_phone,
+        crate::phone::request_code,
+        crate::phone::verify_phone,
+        crate::phone::get_phone_settings,
+        crate::phone::update_phone_settings,
+        crate::phone::remove_phone,
+        crate::phone::match_contacts,
+        crate::chat::create_chat,
+        crate::chat::get_user_chats,
+        crate::chat::get_messages,
+        crate::chat::send_message_http,
+        crate::chat::mark_message_viewed,
+        crate::chat::get_message_media,
+        crate::chat::save_message,
+        crate::chat::unsave_message,
+        crate::chat::get_saved_messages,
+        crate::chat::delete_message_for_me,
+        crate::room_retention::set_retention,
+        crate::media::upload_image,
+        crate::media::upload_multipart,
+        crate::media_proxy::get_media,
+        crate::translation::translate_message,
+        crate::translation::translate_story_caption,
+        crate::gifs::search_gifs,
+        crate::gifs::trending_gifs,
+        crate::gifs::search_stickers,
+        crate::gifs::trending_stickers,
+        crate::stories::create_story_multipart,
+        crate::video_render::render_video,
+        crate::video_render::proxy_rendered_video,
+        crate::stories::get_user_stories,
+        crate::stories::get_feed_stories,
+        crate::stories::get_stories_by_user,
+        crate::stories::mark_story_viewed,
+        crate::story_media::mark_item_viewed,
+        crate::story_collaborators::invite_collaborator,
+        crate::story_collaborators::list_collaborations,
+        crate::story_collaborators::accept_collaboration,
+        crate::story_collaborators::decline_collaboration,
+        crate::story_collaborators::end_collaboration,
+        crate::story_permissions::get_permissions,
+        crate::story_permissions::update_permissions,
+        crate::story_permissions::share_story,
+        crate::stories::delete_story,
+        crate::memories::list_memories,
+        crate::memories::get_memory_settings,
+        crate::memories::update_memory_settings,
+        crate::memories::get_memory,
+        crate::memories::delete_memory,
+        crate::memories::reshare_memory,
+        crate::memories::create_highlight,
+        crate::memories::get_highlight_items,
+        crate::memories::delete_highlight,
+        crate::memories::add_highlight_item,
+        crate::memories::remove_highlight_item,
+        crate::social::follow_user,
+        crate::social::unfollow_user,
+        crate::social::get_follow_stats,
+        crate::social::get_followers,
+        crate::social::get_following,
+        crate::social::like_story,
+        crate::social::unlike_story,
+        crate::social::get_story_likes,
+        crate::social::add_comment,
+        crate::social::get_story_comments,
+        crate::social::delete_comment,
+        crate::social::add_reply,
+        crate::social::get_comment_replies,
+        crate::muted_words::list_muted_words,
+        crate::muted_words::add_muted_word,
+        crate::muted_words::delete_muted_word,
+        crate::social::get_user_profile,
+        crate::social::get_user_stories,
+        crate::memories::get_user_highlights,
+        crate::social::update_user_profile,
+        crate::settings::get_user_settings,
+        crate::settings::update_username,
+        crate::settings::update_email,
+        crate::settings::change_password,
+        crate::settings::delete_account,
+        crate::settings::get_usage,
+        crate::settings::get_demographics,
+        crate::settings::update_demographics,
+        crate::settings::get_dm_privacy,
+        crate::settings::update_dm_privacy,
+        crate::settings::get_comment_audience,
+        crate::settings::update_comment_audience,
+        crate::settings::get_locale,
+        crate::settings::update_locale,
+        crate::settings::get_federation,
+        crate::settings::update_federation,
+        crate::settings::get_activity_status,
+        crate::settings::update_activity_status,
+        crate::syndication::get_json_feed,
+        crate::syndication::get_rss_feed,
+        crate::discovery::search_users,
+        crate::discovery::get_popular_users,
+        crate::discovery::get_suggested_users,
+        crate::discovery::update_avatar,
+        crate::discovery::refresh_popular_users_view,
+        crate::algorithm::get_personalized_feed,
+        crate::algorithm::record_interaction,
+        crate::algorithm::recalculate_all_feeds,
+        crate::streaks::update_streak,
+        crate::streaks::get_streak,
+        crate::streaks::get_user_streaks,
+        crate::notifications::get_notifications,
+        crate::graphql::execute,
+        crate::graphql::sdl,
+        crate::notifications::get_unread_count,
+        crate::notifications::mark_notification_read,
+        crate::notifications::mark_all_notifications_read,
+        crate::notifications::delete_notification,
+        crate::notifications::clear_notifications,
+        crate::announcements::list_my_announcements,
+        crate::announcements::mark_announcement_read,
+        crate::announcements::create_announcement,
+        crate::announcements::list_announcements,
+        crate::announcements::cancel_announcement,
+        crate::takedowns::create_takedown,
+        crate::takedowns::list_takedowns,
+        crate::takedowns::lift_takedown,
+        crate::appeals::list_my_appeals,
+        crate::appeals::submit_appeal,
+        crate::appeals::list_appeals,
+        crate::appeals::decide_appeal,
+        crate::query_metrics::get_query_diagnostics,
+        crate::counters::trigger_reconcile,
+        crate::counters::reconcile_user,
+        crate::counters::reconcile_story,
+        crate::supervision::request_supervision,
+        crate::supervision::list_links,
+        crate::supervision::accept_link,
+        crate::supervision::decline_link,
+        crate::supervision::end_link,
+        crate::supervision::update_limits,
+        crate::supervision::get_activity,
+        crate::admin::list_users,
+        crate::admin::ban_user,
+        crate::admin::unban_user,
+        crate::admin::change_user_role,
+        crate::permissions::list_roles,
+        crate::permissions::create_role,
+        crate::permissions::update_role,
+        crate::permissions::delete_role,
+        crate::permissions::list_permissions,
+        crate::admin::delete_user,
+        crate::admin::get_admin_logs,
+        crate::admin::get_analytics,
+        crate::retention::get_retention,
+        crate::live_stats::get_live_stats,
+        crate::signup_guard::list_blocked_domains,
+        crate::signup_guard::block_domain,
+        crate::signup_guard::unblock_domain,
+        crate::username_policy::list_reserved,
+        crate::username_policy::reserve,
+        crate::username_policy::release,
+        crate::text_moderation::list_terms,
+        crate::text_moderation::add_term,
+        crate::text_moderation::remove_term,
+        crate::text_moderation::list_flags,
+        crate::text_moderation::review_flag,
+        crate::admin::list_ads,
+        crate::admin::create_ad,
+        crate::admin::update_ad,
+        crate::admin::delete_ad,
+        crate::admin::approve_ad,
+        crate::admin::reject_ad,
+        crate::ad_review::get_review_queue,
+        crate::ad_review::get_review_metrics,
+        crate::ad_review::list_revisions,
+        crate::ad_review::resubmit_ad,
+        crate::story_boosts::boost_story,
+        crate::story_boosts::list_boosts,
+        crate::story_boosts::get_boost_report,
+        crate::story_boosts::cancel_boost,
+        crate::story_boosts::record_impression,
+        crate::story_boosts::record_click,
+        crate::referrals::get_dashboard,
+        crate::referrals::get_badges,
+        crate::referrals::list_rewards,
+        crate::referrals::create_reward,
+        crate::referrals::delete_reward,
+        crate::invites::get_signup_mode,
+        crate::invites::validate_invite,
+        crate::invites::get_my_invites,
+        crate::invites::create_invite,
+        crate::invites::create_batch,
+        crate::invites::list_batches,
+        crate::invites::get_batch,
+        crate::invites::revoke_code,
+        crate::invites::set_invite_allowance,
+        crate::invites::get_invite_stats,
+        crate::waitlist::join_waitlist,
+        crate::waitlist::get_position,
+        crate::waitlist::list_waitlist,
+        crate::waitlist::release_waitlist,
+        crate::admin::get_ad_location_analytics,
+        crate::admin::get_ad_demographics_analytics,
+        crate::ad_fraud::get_ad_fraud_report,
+        crate::ad_fraud::list_fraud_flags,
+        crate::ad_fraud::clear_fraud_flag,
+        crate::ad_conversions::record_conversion,
+        crate::ad_conversions::conversion_pixel,
+        crate::ad_conversions::get_conversion_report,
+        crate::ad_billing::top_up_budget,
+        crate::ad_billing::get_spend_report,
+        crate::chat_export::export_chat,
+        crate::message_requests::list_requests,
+        crate::message_requests::accept_request,
+        crate::message_requests::decline_request,
+        crate::insights::set_account_type,
+        crate::insights::get_insights,
+        crate::jobs::list_jobs,
+        crate::jobs::create_job,
+        crate::jobs::get_job_stats,
+        crate::jobs::get_job,
+        crate::jobs::retry_job,
+        crate::jobs::cancel_job,
+        crate::jobs::get_job_status,
+        crate::feature_flags::get_feature_flags,
+        crate::feature_flags::list_flags,
+        crate::feature_flags::create_flag,
+        crate::feature_flags::update_flag,
+        crate::feature_flags::delete_flag,
+        crate::runtime_settings::list_settings,
+        crate::runtime_settings::update_setting,
+        crate::runtime_settings::reset_setting,
+        crate::runtime_settings::get_setting_history,
+        crate::webhooks::list_webhooks,
+        crate::webhooks::create_webhook,
+        crate::webhooks::update_webhook,
+        crate::webhooks::delete_webhook,
+        crate::webhooks::test_webhook,
+        crate::webhooks::list_deliveries,
+        crate::webhooks::redeliver,
+        crate::webhooks::admin_list_deliveries,
+        crate::mailer::list_deliveries,
+        crate::mailer::list_suppressions,
+        crate::mailer::add_suppression,
+        crate::mailer::remove_suppression,
+        crate::mailer::receive_event,
+        crate::scanning::list_quarantine,
+        crate::scanning::release_quarantined,
+        crate::communities::create_community,
+        crate::communities::discover_communities,
+        crate::communities::get_community,
+        crate::communities::get_community_by_invite,
+        crate::communities::join_community,
+        crate::communities::join_community_by_invite,
+        crate::communities::update_community,
+        crate::communities::reset_invite_link,
+        crate::communities::list_members,
+        crate::communities::set_member_role,
+        crate::communities::remove_member,
+        crate::communities::list_join_requests,
+        crate::communities::approve_join_request,
+        crate::communities::decline_join_request,
+        crate::communities::ban_member,
+        crate::communities::unban_member,
+        crate::communities::list_bans,
+        crate::communities::remove_message,
+        crate::communities::get_mod_log,
+        crate::channels::create_channel,
+        crate::channels::get_channel,
+        crate::channels::get_user_channel,
+        crate::channels::subscribe,
+        crate::channels::unsubscribe,
+        crate::channels::react,
+        crate::channels::remove_reaction,
+        crate::channels::get_reactions,
+        crate::calls::get_ice_servers,
+        crate::calls::list_calls,
+        crate::live::start_live,
+        crate::live::end_live,
+        crate::live::list_live,
+        crate::live::get_live,
+        crate::live::join_live,
+        crate::live::leave_live,
+        crate::live::get_live_chat,
+        crate::live::send_live_chat,
+        crate::bots::create_bot,
+        crate::bots::list_bots,
+        crate::bots::rotate_bot_token,
+        crate::bots::delete_bot,
+        crate::bots::add_bot_to_chat,
+        crate::bots::remove_bot_from_chat,
+        crate::bots::get_me,
+        crate::bots::get_updates,
+        crate::bots::send_message,
+        crate::bots::set_webhook,
+        crate::bots::delete_webhook,
+        crate::admin::get_next_ad,
+        crate::admin::record_ad_impression,
+        crate::admin::record_ad_click,
+        crate::admin::create_ad_public,
+        crate::admin::create_checkout_session,
+        crate::admin::stripe_webhook
+    ),
+    components(
+        schemas(
+            crate::admin::AdCampaign,
+            crate::admin::AdDemographicsAnalytics,
+            crate::ad_fraud::AdFraudReport,
+            crate::ad_fraud::FraudReasonCount,
+            crate::ad_fraud::FraudSuspect,
+            crate::ad_fraud::FraudFlag,
+            crate::ad_conversions::ConversionInput,
+            crate::ad_conversions::ConversionResponse,
+            crate::ad_conversions::ConversionReport,
+            crate::ad_conversions::ConversionEventStats,
+            crate::ad_billing::TopUpRequest,
+            crate::ad_billing::TopUpResponse,
+            crate::ad_billing::SpendReport,
+            crate::ad_billing::DailySpend,
+            crate::ad_billing::BudgetTopUp,
+            crate::ad_review::AdCreative,
+            crate::ad_review::CreativeChange,
+            crate::ad_review::PreviousReview,
+            crate::ad_review::ReviewQueueItem,
+            crate::ad_review::AdRevision,
+            crate::ad_review::ApproveAdRequest,
+            crate::ad_review::RejectAdRequest,
+            crate::ad_review::ResubmitAdRequest,
+            crate::ad_review::ReviewMetrics,
+            crate::story_boosts::StoryBoost,
+            crate::story_boosts::CreateBoostRequest,
+            crate::story_boosts::BoostCheckoutResponse,
+            crate::story_boosts::BoostReport,
+            crate::referrals::ReferralReward,
+            crate::referrals::EarnedReward,
+            crate::referrals::ReferredUser,
+            crate::referrals::ReferralDashboard,
+            crate::referrals::UserBadge,
+            crate::referrals::CreateRewardRequest,
+            crate::invites::InviteCode,
+            crate::invites::InvitedUser,
+            crate::invites::MyInvites,
+            crate::invites::SignupMode,
+            crate::invites::ValidateInviteRequest,
+            crate::invites::ValidateInviteResponse,
+            crate::invites::InviteBatch,
+            crate::invites::InviteBatchDetail,
+            crate::invites::CreateBatchRequest,
+            crate::invites::InviteAllowanceRequest,
+            crate::invites::InviteGeneration,
+            crate::invites::TopInviter,
+            crate::invites::InviteStats,
+            crate::waitlist::JoinWaitlistRequest,
+            crate::waitlist::WaitlistPosition,
+            crate::waitlist::WaitlistEntry,
+            crate::waitlist::WaitlistPage,
+            crate::waitlist::ReleaseRequest,
+            crate::waitlist::ReleasedInvite,
+            crate::waitlist::ReleaseResponse,
+            crate::chat_export::ChatExportRequest,
+            crate::chat_export::ChatExportResponse,
+            crate::message_requests::MessageRequest,
+            crate::insights::AccountTypeInput,
+            crate::insights::AccountTypeResponse,
+            crate::insights::InsightsResponse,
+            crate::insights::InsightsSummary,
+            crate::insights::InsightsDay,
+            crate::insights::ViewsBreakdown,
+            crate::insights::TopStory,
+            crate::admin::AdLocationAnalytics,
+            crate::admin::AdToShow,
+            crate::admin::AdminLogEntry,
+            crate::admin::AnalyticsResponse,
+            crate::admin::AnalyticsSnapshot,
+            crate::retention::RetentionResponse,
+            crate::retention::RetentionCohort,
+            crate::retention::RetentionWeek,
+            crate::live_stats::LiveStats,
+            crate::live_stats::LiveStatsSample,
+            crate::signup_guard::BlockedDomain,
+            crate::signup_guard::BlockDomainInput,
+            crate::username_policy::ReservedUsername,
+            crate::username_policy::ReserveUsernameInput,
+            crate::text_moderation::ModerationTerm,
+            crate::text_moderation::ModerationTermInput,
+            crate::text_moderation::ModerationFlag,
+            crate::text_moderation::ReviewFlagInput,
+            crate::admin::AnalyticsSummary,
+            crate::admin::BanUserInput,
+            crate::admin::ChangeRoleInput,
+            crate::permissions::RoleInfo,
+            crate::permissions::PermissionInfo,
+            crate::permissions::CreateRoleRequest,
+            crate::permissions::UpdateRoleRequest,
+            crate::admin::CheckoutSessionResponse,
+            crate::admin::CreateAdInput,
+            crate::admin::LogsResponse,
+            crate::admin::PublicCreateAdInput,
+            crate::admin::PublicCreateAdResponse,
+            crate::admin::UpdateAdInput,
+            crate::admin::UserInfo,
+            crate::admin::UserListResponse,
+            crate::algorithm::PersonalizedStory,
+            crate::algorithm::RecordInteractionRequest,
+            crate::auth::LoginInput,
+            crate::auth::LoginResponse,
+            crate::auth::SignupInput,
+            crate::auth::PhoneLoginInput,
+            crate::auth::PhoneRecoveryInput,
+            crate::phone::RequestCodeRequest,
+            crate::phone::CodeRequestedResponse,
+            crate::phone::VerifyPhoneRequest,
+            crate::phone::PhoneSettings,
+            crate::phone::UpdatePhoneSettingsRequest,
+            crate::phone::MatchContactsRequest,
+            crate::phone::ContactMatch,
+            crate::chat::ChatMemberResponse,
+            crate::chat::ChatRoomResponse,
+            crate::chat::CreateChatRequest,
+            crate::chat::MessageResponse,
+            crate::chat::SendMessageRequest,
+            crate::chat::SavedMessage,
+            crate::chat::SavedChatGroup,
+            crate::chat::SavedMessagesResponse,
+            crate::room_retention::RoomRetention,
+            crate::room_retention::SetRetentionRequest,
+            crate::discovery::UpdateAvatarRequest,
+            crate::discovery::UserSearchResult,
+            crate::jobs::EnqueueJobRequest,
+            crate::jobs::Job,
+            crate::jobs::JobListResponse,
+            crate::jobs::JobStats,
+            crate::jobs::JobStatusResponse,
+            crate::feature_flags::FeatureFlag,
+            crate::feature_flags::FeatureFlagsResponse,
+            crate::feature_flags::CreateFeatureFlagRequest,
+            crate::feature_flags::UpdateFeatureFlagRequest,
+            crate::runtime_settings::RuntimeSetting,
+            crate::runtime_settings::UpdateSettingInput,
+            crate::runtime_settings::RuntimeSettingChange,
+            crate::webhooks::WebhookSubscription,
+            crate::webhooks::WebhookDelivery,
+            crate::scanning::QuarantinedMedia,
+            crate::scanning::QuarantineResponse,
+            crate::webhooks::CreateWebhookRequest,
+            crate::webhooks::CreatedWebhookResponse,
+            crate::webhooks::UpdateWebhookRequest,
+            crate::mailer::MailDelivery,
+            crate::mailer::MailSuppression,
+            crate::mailer::SuppressAddressRequest,
+            crate::communities::Community,
+            crate::communities::CommunityMember,
+            crate::communities::JoinRequest,
+            crate::communities::CreateCommunityRequest,
+            crate::communities::UpdateCommunityRequest,
+            crate::communities::SetRoleRequest,
+            crate::communities::BanMemberRequest,
+            crate::communities::CommunityBan,
+            crate::communities::ModLogEntry,
+            crate::channels::Channel,
+            crate::channels::CreateChannelRequest,
+            crate::channels::ReactRequest,
+            crate::channels::ReactionCount,
+            crate::channels::ReactionSummary,
+            crate::calls::Call,
+            crate::calls::IceServer,
+            crate::calls::IceServersResponse,
+            crate::live::LiveSession,
+            crate::live::StartLiveRequest,
+            crate::live::JoinTokenResponse,
+            crate::live::LiveChatMessage,
+            crate::live::SendLiveChatRequest,
+            crate::bots::Bot,
+            crate::bots::BotTokenResponse,
+            crate::bots::CreateBotRequest,
+            crate::bots::AddBotRequest,
+            crate::bots::BotUpdate,
+            crate::bots::BotSendMessageRequest,
+            crate::bots::SetBotWebhookRequest,
+            crate::media::UploadImageRequest,
+            crate::media::UploadResponse,
+            crate::translation::TranslateRequest,
+            crate::translation::TranslationResponse,
+            crate::gifs::GifResult,
+            crate::gifs::GifSearchResponse,
+            crate::memories::AddHighlightItemRequest,
+            crate::memories::CreateHighlightRequest,
+            crate::memories::Highlight,
+            crate::memories::MemoriesResponse,
+            crate::memories::Memory,
+            crate::memories::MemorySettings,
+            crate::memories::ReshareResponse,
+            crate::notifications::Notification,
+            crate::notifications::NotificationResponse,
+            crate::announcements::Announcement,
+            crate::announcements::AnnouncementAudience,
+            crate::announcements::AnnouncementText,
+            crate::announcements::AnnouncementsResponse,
+            crate::announcements::CreateAnnouncementRequest,
+            crate::announcements::UserAnnouncement,
+            crate::settings::ChangePasswordRequest,
+            crate::settings::UpdateEmailRequest,
+            crate::settings::UpdateUsernameRequest,
+            crate::settings::UserSettingsResponse,
+            crate::settings::DemographicsResponse,
+            crate::settings::UpdateDemographicsRequest,
+            crate::settings::DmPrivacyResponse,
+            crate::settings::UpdateDmPrivacyRequest,
+            crate::settings::CommentAudienceResponse,
+            crate::settings::UpdateCommentAudienceRequest,
+            crate::settings::LocaleResponse,
+            crate::settings::UpdateLocaleRequest,
+            crate::settings::FederationResponse,
+            crate::settings::UpdateFederationRequest,
+            crate::settings::ActivityStatusResponse,
+            crate::settings::UpdateActivityStatusRequest,
+            crate::activity_status::ActivityStatus,
+            crate::supervision::ActivitySummary,
+            crate::supervision::DailyTimeSpent,
+            crate::supervision::NewContact,
+            crate::supervision::SupervisionLink,
+            crate::supervision::SupervisionRequest,
+            crate::supervision::UpdateLimitsRequest,
+            crate::takedowns::CreateTakedownRequest,
+            crate::takedowns::GeoTakedown,
+            crate::appeals::Sanction,
+            crate::appeals::Appeal,
+            crate::appeals::AppealsOverview,
+            crate::appeals::AppealForReview,
+            crate::appeals::SubmitAppealRequest,
+            crate::appeals::DecideAppealRequest,
+            crate::takedowns::LiftTakedownRequest,
+            crate::takedowns::TakedownsResponse,
+            crate::query_metrics::QueryDiagnostics,
+            crate::query_metrics::QueryStat,
+            crate::query_metrics::SlowQuery,
+            crate::counters::CounterDrift,
+            crate::counters::CounterReport,
+            crate::counters::ReconcileReport,
+            crate::quotas::QuotaUsage,
+            crate::quotas::UsageResponse,
+            crate::social::Comment,
+            crate::social::CommentResponse,
+            crate::social::CommentWithReplies,
+            crate::social::CreateCommentRequest,
+            crate::social::FollowResponse,
+            crate::social::FollowStats,
+            crate::social::LikeResponse,
+            crate::social::LikeUserItem,
+            crate::social::ProfileGridResponse,
+            crate::social::ProfileStory,
+            crate::social::ReplyRequest,
+            crate::social::UpdateProfileRequest,
+            crate::social::UserListItem,
+            crate::social::UserProfile,
+            crate::muted_words::MutedWord,
+            crate::muted_words::AddMutedWordRequest,
+            crate::stories::CreateStoryResponse,
+            crate::story_media::StoryMediaItem,
+            crate::story_collaborators::StoryCollaboration,
+            crate::story_collaborators::InviteCollaboratorRequest,
+            crate::story_permissions::StoryPermissions,
+            crate::story_permissions::UpdateStoryPermissionsRequest,
+            crate::story_permissions::ShareStoryRequest,
+            crate::stories::StoriesResponse,
+            crate::stories::Story,
+            crate::streaks::StreakInfo,
+            crate::streaks::StreakResponse,
+            crate::streaks::UserStreakInfo,
+            crate::video_render::RenderResponse
+        )
+    ),
+    modifiers(&SecurityAddon),
+    tags(
+        (name = "auth", description = "Signup and login"),
+        (name = "chat", description = "Chat rooms and messages"),
+        (name = "media", description = "Media uploads"),
+        (name = "stories", description = "Stories and story rendering"),
+        (name = "memories", description = "Story archive and highlights"),
+        (name = "social", description = "Follows, likes, comments and profiles"),
+        (name = "settings", description = "Account settings"),
+        (name = "supervision", description = "Guardian supervision of minor accounts"),
+        (name = "discovery", description = "User search and suggestions"),
+        (name = "feed", description = "Personalized feed"),
+        (name = "streaks", description = "Chat streaks"),
+        (name = "notifications", description = "In-app notifications"),
+        (name = "insights", description = "Insights for creator and business accounts"),
+        (name = "ads", description = "Advertising"),
+        (name = "admin", description = "Administration (admin or moderator role)"),
+        (name = "jobs", description = "Background jobs"),
+        (name = "feature-flags", description = "Feature flags and maintenance mode"),
+        (name = "webhooks", description = "Outbound webhook subscriptions and delivery logs"),
+        (name = "appeals", description = "Appealing bans and content removals"),
+        (name = "communities", description = "Public group chats: discovery, join links, roles, join requests and moderation"),
+        (name = "channels", description = "Broadcast channels: owner-only posts, subscriptions and reactions"),
+        (name = "live", description = "Live streaming sessions; WebRTC signaling runs over the WebSocket"),
+        (name = "bots", description = "Bot accounts and the bot API (Authorization: Bot <token>)"),
+        (name = "graphql", description = "Read-only GraphQL gateway over users, stories, chats and notifications")
+    )
+)]
+pub struct ApiDoc;
+
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "service_token",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-Service-Token"))),
+        );
+        components.add_security_scheme(
+            "bot_token",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                "Authorization",
+                "Bot token, sent as `Bot <token>`",
+            ))),
+        );
+    }
+}
+
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+// Swagger UI loaded from a CDN, pointed at our spec
+pub async fn swagger_ui() -> Html<&'static str> {
+    Html(r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="utf-8" />
+    <title>API Docs</title>
+    <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css" />
+</head>
+<body>
+    <div id="swagger-ui"></div>
+    <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js" crossorigin></script>
+    <script>
+        window.onload = () => {
+            window.ui = SwaggerUIBundle({ url: '/api/openapi.json', dom_id: '#swagger-ui' });
+        };
+    </script>
+</body>
+</html>"#)
+}
@@ -0,0 +1,459 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path, State},
+    http::{request::Parts, StatusCode},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::admin::{log_admin_action, AuthUser};
+use crate::AppState;
+
+// Staff permissions. A user's role (users.role) grants a set of permissions
+// through role_permissions (migration 078), and endpoints ask for the one
+// they need by taking a `Permitted<P>` argument, e.g. `Permitted<CanBan>`.
+// The permissions are fixed here and in the migration; roles and what they
+// grant are data, managed through the /admin/roles endpoints. Community
+// owners and moderators also hold a few permissions inside their own
+// communities (in_community).
+
+/// A permission an endpoint can require
+pub trait Permission: Send + Sync {
+    const NAME: &'static str;
+}
+
+/// Ban and unban users
+#[derive(Debug, Clone, Copy)]
+pub struct CanBan;
+
+impl Permission for CanBan {
+    const NAME: &'static str = "can_ban";
+}
+
+/// Delete user accounts
+#[derive(Debug, Clone, Copy)]
+pub struct CanDeleteUsers;
+
+impl Permission for CanDeleteUsers {
+    const NAME: &'static str = "can_delete_users";
+}
+
+/// Review flags, takedowns and quarantined uploads; reserve usernames and block signup domains
+#[derive(Debug, Clone, Copy)]
+pub struct CanModerateContent;
+
+impl Permission for CanModerateContent {
+    const NAME: &'static str = "can_moderate_content";
+}
+
+/// Manage and review any ad and see ad reports
+#[derive(Debug, Clone, Copy)]
+pub struct CanManageAds;
+
+impl Permission for CanManageAds {
+    const NAME: &'static str = "can_manage_ads";
+}
+
+/// Platform analytics, live stats, retention and the admin log
+#[derive(Debug, Clone, Copy)]
+pub struct CanViewAnalytics;
+
+impl Permission for CanViewAnalytics {
+    const NAME: &'static str = "can_view_analytics";
+}
+
+/// Runtime settings, feature flags, announcements, jobs, mail, webhooks and growth tools
+#[derive(Debug, Clone, Copy)]
+pub struct CanManagePlatform;
+
+impl Permission for CanManagePlatform {
+    const NAME: &'static str = "can_manage_platform";
+}
+
+/// Assign roles to users and change what each role grants
+#[derive(Debug, Clone, Copy)]
+pub struct CanManageRoles;
+
+impl Permission for CanManageRoles {
+    const NAME: &'static str = "can_manage_roles";
+}
+
+/// A signed-in user whose role grants `P`
+#[derive(Debug, Clone)]
+pub struct Permitted<P>(pub AuthUser, PhantomData<P>);
+
+#[async_trait]
+impl<P: Permission> FromRequestParts<Arc<AppState>> for Permitted<P> {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<AppState>) -> Result<Self, Self::Rejection> {
+        let user = AuthUser::from_request_parts(parts, state).await?;
+        if !role_has(&state.pool, &user.role, P::NAME).await {
+            return Err((StatusCode::FORBIDDEN, format!("Missing permission: {}", P::NAME)));
+        }
+        Ok(Permitted(user, PhantomData))
+    }
+}
+
+/// Whether `role` grants `permission`. Lookup failures count as no.
+pub async fn role_has(pool: &PgPool, role: &str, permission: &str) -> bool {
+    sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM role_permissions WHERE role = $1 AND permission = $2)")
+        .bind(role)
+        .bind(permission)
+        .fetch_one(pool)
+        .await
+        .unwrap_or_else(|e| {
+            eprintln!("❌ Permission query failed: {:?}", e);
+            false
+        })
+}
+
+/// Whether `role` grants any permission at all, i.e. belongs to staff
+pub async fn role_is_staff(pool: &PgPool, role: &str) -> bool {
+    sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM role_permissions WHERE role = $1)")
+        .bind(role)
+        .fetch_one(pool)
+        .await
+        .unwrap_or_else(|e| {
+            eprintln!("❌ Permission query failed: {:?}", e);
+            false
+        })
+}
+
+/// Where a power used inside a community comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// The caller's role grants it everywhere
+    Global,
+    /// The caller owns or moderates this community
+    Community,
+}
+
+// What owning or moderating a community grants inside it
+const COMMUNITY_GRANTS: [&str; 2] = [CanBan::NAME, CanModerateContent::NAME];
+
+/// Whether `user` may use `P` in community `room_id`: anywhere when their
+/// role grants it, otherwise in communities they own or moderate if `P` is
+/// one of COMMUNITY_GRANTS. None when they may not.
+pub async fn in_community<P: Permission>(pool: &PgPool, user: &AuthUser, room_id: Uuid) -> Result<Option<Scope>, sqlx::Error> {
+    if role_has(pool, &user.role, P::NAME).await {
+        return Ok(Some(Scope::Global));
+    }
+    if !COMMUNITY_GRANTS.contains(&P::NAME) {
+        return Ok(None);
+    }
+    let moderates = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM chat_members cm
+            JOIN chat_rooms r ON r.id = cm.chat_room_id
+            WHERE cm.chat_room_id = $1 AND cm.user_id = $2
+              AND cm.role IN ('owner', 'moderator') AND r.room_type = 'public'
+        )
+        "#
+    )
+    .bind(room_id)
+    .bind(user.id)
+    .fetch_one(pool)
+    .await?;
+    Ok(moderates.then_some(Scope::Community))
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    eprintln!("❌ Roles query failed: {:?}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load roles".to_string())
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct PermissionInfo {
+    name: String,
+    description: String,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct RoleInfo {
+    name: String,
+    description: String,
+    /// user, moderator and admin; they can be edited but not deleted
+    built_in: bool,
+    permissions: Vec<String>,
+    /// Accounts that have this role
+    user_count: i64,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateRoleRequest {
+    /// Lowercase letters, digits and underscores, starting with a letter
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    permissions: Vec<String>,
+}
+
+/// Fields left out are unchanged
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateRoleRequest {
+    description: Option<String>,
+    /// Replaces everything the role granted
+    permissions: Option<Vec<String>>,
+}
+
+async fn fetch_roles(pool: &PgPool, name: Option<&str>) -> Result<Vec<RoleInfo>, (StatusCode, String)> {
+    sqlx::query_as::<_, RoleInfo>(
+        r#"
+        SELECT r.name, r.description, r.built_in,
+               COALESCE(ARRAY(SELECT permission FROM role_permissions WHERE role = r.name ORDER BY permission), '{}') AS permissions,
+               (SELECT COUNT(*) FROM users WHERE role = r.name) AS user_count
+        FROM roles r
+        WHERE $1::text IS NULL OR r.name = $1
+        ORDER BY r.built_in DESC, r.name
+        "#
+    )
+    .bind(name)
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)
+}
+
+async fn fetch_role(pool: &PgPool, name: &str) -> Result<RoleInfo, (StatusCode, String)> {
+    fetch_roles(pool, Some(name))
+        .await?
+        .pop()
+        .ok_or((StatusCode::NOT_FOUND, "Role not found".to_string()))
+}
+
+async fn check_permission_names(pool: &PgPool, names: &[String]) -> Result<(), (StatusCode, String)> {
+    let unknown = sqlx::query_scalar::<_, String>(
+        "SELECT name FROM unnest($1::text[]) AS name WHERE name NOT IN (SELECT name FROM permissions)"
+    )
+    .bind(names)
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)?;
+    if !unknown.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, format!("Unknown permission(s): {}", unknown.join(", "))));
+    }
+    Ok(())
+}
+
+async fn set_permissions(pool: &PgPool, role: &str, permissions: &[String]) -> Result<(), (StatusCode, String)> {
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    sqlx::query("DELETE FROM role_permissions WHERE role = $1")
+        .bind(role)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    sqlx::query("INSERT INTO role_permissions (role, permission) SELECT DISTINCT $1::text, unnest($2::text[])")
+        .bind(role)
+        .bind(permissions)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)
+}
+
+// Every permission a role can grant
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/permissions",
+    tag = "admin",
+    responses((status = 200, body = [PermissionInfo]), (status = 403, description = "Missing can_manage_roles")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_permissions(
+    _admin: Permitted<CanManageRoles>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<PermissionInfo>>, (StatusCode, String)> {
+    let permissions = sqlx::query_as::<_, PermissionInfo>("SELECT name, description FROM permissions ORDER BY name")
+        .fetch_all(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+    Ok(Json(permissions))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/roles",
+    tag = "admin",
+    responses((status = 200, body = [RoleInfo]), (status = 403, description = "Missing can_manage_roles")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_roles(
+    _admin: Permitted<CanManageRoles>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<RoleInfo>>, (StatusCode, String)> {
+    fetch_roles(&state.pool, None).await.map(Json)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/roles",
+    tag = "admin",
+    request_body = CreateRoleRequest,
+    responses(
+        (status = 200, body = RoleInfo),
+        (status = 400, description = "Invalid name or unknown permission"),
+        (status = 403, description = "Missing can_manage_roles"),
+        (status = 409, description = "A role with this name exists")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn create_role(
+    admin: Permitted<CanManageRoles>,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CreateRoleRequest>,
+) -> Result<Json<RoleInfo>, (StatusCode, String)> {
+    let name = payload.name.trim().to_lowercase();
+    let valid = name.len() <= 32
+        && name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if !valid {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Role names are up to 32 lowercase letters, digits and underscores, starting with a letter".to_string(),
+        ));
+    }
+    check_permission_names(&state.pool, &payload.permissions).await?;
+
+    let created = sqlx::query("INSERT INTO roles (name, description) VALUES ($1, $2) ON CONFLICT (name) DO NOTHING")
+        .bind(&name)
+        .bind(payload.description.trim())
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+    if created.rows_affected() == 0 {
+        return Err((StatusCode::CONFLICT, format!("Role {} already exists", name)));
+    }
+    set_permissions(&state.pool, &name, &payload.permissions).await?;
+
+    log_admin_action(
+        &state,
+        admin.0.id,
+        "create_role".to_string(),
+        None,
+        Some("role".to_string()),
+        None,
+        serde_json::json!({ "role": name, "permissions": payload.permissions }),
+    ).await;
+
+    fetch_role(&state.pool, &name).await.map(Json)
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/roles/{name}",
+    tag = "admin",
+    params(("name" = String, Path, description = "Role name")),
+    request_body = UpdateRoleRequest,
+    responses(
+        (status = 200, body = RoleInfo),
+        (status = 400, description = "Unknown permission, or it would take can_manage_roles from your own role"),
+        (status = 403, description = "Missing can_manage_roles"),
+        (status = 404, description = "No such role")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_role(
+    admin: Permitted<CanManageRoles>,
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Json(payload): Json<UpdateRoleRequest>,
+) -> Result<Json<RoleInfo>, (StatusCode, String)> {
+    fetch_role(&state.pool, &name).await?;
+
+    if let Some(permissions) = &payload.permissions {
+        check_permission_names(&state.pool, permissions).await?;
+        // Someone has to be left able to manage roles
+        if name == admin.0.role && !permissions.iter().any(|p| p == CanManageRoles::NAME) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("Can't remove {} from your own role", CanManageRoles::NAME),
+            ));
+        }
+    }
+
+    if let Some(description) = &payload.description {
+        sqlx::query("UPDATE roles SET description = $2 WHERE name = $1")
+            .bind(&name)
+            .bind(description.trim())
+            .execute(state.pool.as_ref())
+            .await
+            .map_err(db_error)?;
+    }
+    if let Some(permissions) = &payload.permissions {
+        set_permissions(&state.pool, &name, permissions).await?;
+    }
+
+    log_admin_action(
+        &state,
+        admin.0.id,
+        "update_role".to_string(),
+        None,
+        Some("role".to_string()),
+        None,
+        serde_json::json!({ "role": name, "description": payload.description, "permissions": payload.permissions }),
+    ).await;
+
+    fetch_role(&state.pool, &name).await.map(Json)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/roles/{name}",
+    tag = "admin",
+    params(("name" = String, Path, description = "Role name")),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 400, description = "Built-in roles can't be deleted"),
+        (status = 403, description = "Missing can_manage_roles"),
+        (status = 404, description = "No such role"),
+        (status = 409, description = "Users still have this role")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn delete_role(
+    admin: Permitted<CanManageRoles>,
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let role = fetch_role(&state.pool, &name).await?;
+    if role.built_in {
+        return Err((StatusCode::BAD_REQUEST, "Built-in roles can't be deleted".to_string()));
+    }
+    if role.user_count > 0 {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("{} account(s) still have this role; move them to another role first", role.user_count),
+        ));
+    }
+
+    // The users foreign key refuses the delete if someone got the role meanwhile
+    sqlx::query("DELETE FROM roles WHERE name = $1 AND NOT built_in")
+        .bind(&name)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(ref db) if db.is_foreign_key_violation() => {
+                (StatusCode::CONFLICT, "Users still have this role".to_string())
+            }
+            e => db_error(e),
+        })?;
+
+    log_admin_action(
+        &state,
+        admin.0.id,
+        "delete_role".to_string(),
+        None,
+        Some("role".to_string()),
+        None,
+        serde_json::json!({ "role": name }),
+    ).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
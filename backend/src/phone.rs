@@ -0,0 +1,523 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::NaiveDateTime;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::client_ip::ClientIp;
+use crate::AppState;
+
+// Phone numbers (migration 069). A one-time code sent by SMS (sms.rs) proves
+// the caller holds a number. A code is asked for with a purpose:
+//   signup    the number isn't on any account yet; redeemed by /signup
+//   verify    a logged-in user adds or changes their number (/phone/verify)
+//   login     /login/phone, instead of a password
+//   recovery  /account-recovery/phone sets a new password
+// Login and recovery codes are only sent when the number belongs to an
+// account, but the reply is the same either way. Codes expire after 10
+// minutes and 5 wrong guesses, and sending is throttled per number and per IP.
+// Verified numbers power contact matching (/contacts/match) for users who
+// leave discoverable_by_phone on.
+
+pub const PURPOSES: &[&str] = &["signup", "verify", "login", "recovery"];
+
+const CODE_TTL_SECONDS: i64 = 10 * 60;
+const MAX_CODE_ATTEMPTS: i32 = 5;
+const RESEND_INTERVAL_SECONDS: i64 = 60;
+const MAX_CODES_PER_NUMBER_PER_HOUR: i64 = 5;
+const MAX_CODES_PER_IP_PER_HOUR: i64 = 20;
+// Codes are kept this long after they're sent, for throttling
+const CODE_RETENTION_HOURS: i32 = 24;
+const MAX_CONTACTS_PER_MATCH: usize = 2000;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RequestCodeRequest {
+    /// In international format, e.g. +14155550123
+    pub phone_number: String,
+    /// signup, verify, login or recovery
+    pub purpose: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CodeRequestedResponse {
+    /// The number the code went to, in E.164 form
+    pub phone_number: String,
+    pub expires_in_seconds: i64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct VerifyPhoneRequest {
+    pub phone_number: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct PhoneSettings {
+    pub phone_number: Option<String>,
+    #[serde(with = "crate::timestamps::option")]
+    pub phone_verified_at: Option<NaiveDateTime>,
+    /// People with the number in their contacts can find the account
+    pub discoverable_by_phone: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdatePhoneSettingsRequest {
+    pub discoverable_by_phone: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MatchContactsRequest {
+    /// Numbers from the caller's address book, in international format.
+    /// Ones that aren't are skipped.
+    pub phone_numbers: Vec<String>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct ContactMatch {
+    pub phone_number: String,
+    pub user_id: Uuid,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub is_following: bool,
+}
+
+/// A live code, as checked by `check_code`
+#[derive(sqlx::FromRow)]
+pub struct PhoneCode {
+    pub id: Uuid,
+    pub user_id: Option<Uuid>,
+    code_hash: String,
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    eprintln!("❌ Phone query failed: {:?}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+}
+
+fn invalid_code() -> (StatusCode, String) {
+    (StatusCode::BAD_REQUEST, "Invalid or expired code".to_string())
+}
+
+fn in_use() -> (StatusCode, String) {
+    (StatusCode::CONFLICT, "This phone number is already in use".to_string())
+}
+
+/// `number` in E.164 form ("+" and up to 15 digits), ignoring spaces,
+/// dashes, dots and parentheses
+pub fn normalize(number: &str) -> Option<String> {
+    let digits = number.trim().strip_prefix('+')?;
+    let digits: String = digits
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')'))
+        .collect();
+    let valid = (8..=15).contains(&digits.len())
+        && digits.chars().all(|c| c.is_ascii_digit())
+        && !digits.starts_with('0');
+    valid.then(|| format!("+{}", digits))
+}
+
+/// `number` normalized, or a 400 explaining the expected format
+pub fn require_normalized(number: &str) -> Result<String, (StatusCode, String)> {
+    normalize(number).ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            "Phone numbers must be in international format, e.g. +14155550123".to_string(),
+        )
+    })
+}
+
+fn code_hash(phone_number: &str, code: &str) -> String {
+    hex::encode(Sha256::digest(format!("{}:{}", phone_number, code.trim()).as_bytes()))
+}
+
+fn generate_code() -> String {
+    format!("{:06}", OsRng.next_u32() % 1_000_000)
+}
+
+/// Refuse a new code when the number or IP asked for too many lately
+async fn throttle(pool: &PgPool, phone_number: &str, ip: &str) -> Result<(), (StatusCode, String)> {
+    let (recent, for_number, for_ip) = sqlx::query_as::<_, (i64, i64, i64)>(
+        r#"
+        SELECT COUNT(*) FILTER (WHERE phone_number = $1 AND created_at > NOW() - make_interval(secs => $3)),
+               COUNT(*) FILTER (WHERE phone_number = $1),
+               COUNT(*) FILTER (WHERE requested_ip = $2)
+        FROM phone_codes
+        WHERE created_at > NOW() - INTERVAL '1 hour'
+          AND (phone_number = $1 OR requested_ip = $2)
+        "#
+    )
+    .bind(phone_number)
+    .bind(ip)
+    .bind(RESEND_INTERVAL_SECONDS as f64)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+
+    if recent > 0 {
+        return Err((StatusCode::TOO_MANY_REQUESTS, "Wait a minute before asking for another code".to_string()));
+    }
+    if for_number >= MAX_CODES_PER_NUMBER_PER_HOUR || for_ip >= MAX_CODES_PER_IP_PER_HOUR {
+        return Err((StatusCode::TOO_MANY_REQUESTS, "Too many codes requested; try again later".to_string()));
+    }
+    Ok(())
+}
+
+/// The account a verified number belongs to
+pub async fn owner_of(pool: &PgPool, phone_number: &str) -> Result<Option<Uuid>, (StatusCode, String)> {
+    sqlx::query_scalar::<_, Uuid>("SELECT id FROM users WHERE phone_number = $1")
+        .bind(phone_number)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)
+}
+
+/// Check `code` against the latest live code sent to `phone_number` for
+/// `purpose`. Every guess counts against the code.
+pub async fn check_code(pool: &PgPool, phone_number: &str, purpose: &str, code: &str) -> Result<PhoneCode, (StatusCode, String)> {
+    let live = sqlx::query_as::<_, PhoneCode>(
+        r#"
+        SELECT id, user_id, code_hash
+        FROM phone_codes
+        WHERE phone_number = $1 AND purpose = $2 AND consumed_at IS NULL AND expires_at > NOW()
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#
+    )
+    .bind(phone_number)
+    .bind(purpose)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?
+    .ok_or_else(invalid_code)?;
+
+    // Take an attempt before comparing, in one conditional update, so
+    // concurrent guesses can't all get in under the limit
+    let attempt = sqlx::query_scalar::<_, i32>(
+        "UPDATE phone_codes SET attempts = attempts + 1 WHERE id = $1 AND attempts < $2 RETURNING attempts"
+    )
+    .bind(live.id)
+    .bind(MAX_CODE_ATTEMPTS)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?;
+    if attempt.is_none() {
+        return Err((StatusCode::TOO_MANY_REQUESTS, "Too many wrong codes; ask for a new one".to_string()));
+    }
+    if !crate::admin::constant_time_eq(code_hash(phone_number, code).as_bytes(), live.code_hash.as_bytes()) {
+        return Err(invalid_code());
+    }
+    Ok(live)
+}
+
+/// Use up a checked code, so it can't be redeemed twice
+pub async fn consume<'e, E>(executor: E, code_id: Uuid) -> Result<(), (StatusCode, String)>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    let consumed = sqlx::query("UPDATE phone_codes SET consumed_at = NOW() WHERE id = $1 AND consumed_at IS NULL")
+        .bind(code_id)
+        .execute(executor)
+        .await
+        .map_err(db_error)?;
+    if consumed.rows_affected() == 0 {
+        return Err(invalid_code());
+    }
+    Ok(())
+}
+
+/// Drop codes past the throttling window (run by the expiration job)
+pub async fn purge_expired(pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM phone_codes WHERE created_at < NOW() - make_interval(hours => $1)")
+        .bind(CODE_RETENTION_HOURS)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Send a one-time code to a phone number
+#[utoipa::path(
+    post,
+    path = "/api/v1/phone/code",
+    tag = "auth",
+    request_body = RequestCodeRequest,
+    responses(
+        (status = 202, body = CodeRequestedResponse),
+        (status = 400, description = "Unknown purpose or malformed number"),
+        (status = 401, description = "verify needs a logged-in caller"),
+        (status = 409, description = "The number is already on another account"),
+        (status = 429, description = "Too many codes requested"),
+        (status = 502, description = "The SMS provider didn't take the message"),
+        (status = 503, description = "SMS isn't configured")
+    )
+)]
+pub async fn request_code(
+    user: Option<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    ClientIp(ip): ClientIp,
+    Json(payload): Json<RequestCodeRequest>,
+) -> Result<(StatusCode, Json<CodeRequestedResponse>), (StatusCode, String)> {
+    if !crate::sms::is_configured() {
+        return Err((StatusCode::SERVICE_UNAVAILABLE, "Phone verification isn't available".to_string()));
+    }
+    let purpose = payload.purpose.trim().to_lowercase();
+    if !PURPOSES.contains(&purpose.as_str()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("purpose must be one of: {}", PURPOSES.join(", ")),
+        ));
+    }
+    let phone_number = require_normalized(&payload.phone_number)?;
+
+    let owner = owner_of(&state.pool, &phone_number).await?;
+    let recipient = match purpose.as_str() {
+        "signup" if owner.is_some() => return Err(in_use()),
+        "signup" => None,
+        "verify" => {
+            let user = user.ok_or((StatusCode::UNAUTHORIZED, "Log in to add a phone number".to_string()))?;
+            if owner.is_some_and(|owner| owner != user.id) {
+                return Err(in_use());
+            }
+            Some(user.id)
+        }
+        _ => owner,
+    };
+    // Login and recovery don't say whether the number has an account
+    let sends = matches!(purpose.as_str(), "signup" | "verify") || owner.is_some();
+
+    let ip = ip.to_string();
+    throttle(&state.pool, &phone_number, &ip).await?;
+
+    if sends {
+        let code = generate_code();
+        sqlx::query(
+            r#"
+            INSERT INTO phone_codes (phone_number, purpose, user_id, code_hash, requested_ip, expires_at)
+            VALUES ($1, $2, $3, $4, $5, NOW() + make_interval(secs => $6))
+            "#
+        )
+        .bind(&phone_number)
+        .bind(&purpose)
+        .bind(recipient)
+        .bind(code_hash(&phone_number, &code))
+        .bind(&ip)
+        .bind(CODE_TTL_SECONDS as f64)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+
+        let text = format!(
+            "Your relays.social code is {}. It expires in {} minutes.",
+            code,
+            CODE_TTL_SECONDS / 60
+        );
+        crate::sms::send(&phone_number, &text).await.map_err(|e| {
+            eprintln!("❌ Failed to send code to {}: {}", phone_number, e);
+            (StatusCode::BAD_GATEWAY, "Failed to send the code".to_string())
+        })?;
+    }
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(CodeRequestedResponse {
+            phone_number,
+            expires_in_seconds: CODE_TTL_SECONDS,
+        }),
+    ))
+}
+
+async fn fetch_settings(pool: &PgPool, user_id: Uuid) -> Result<PhoneSettings, (StatusCode, String)> {
+    sqlx::query_as::<_, PhoneSettings>(
+        "SELECT phone_number, phone_verified_at, discoverable_by_phone FROM users WHERE id = $1"
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, "User not found".to_string()))
+}
+
+// Add or change the caller's phone number with a verify code
+#[utoipa::path(
+    post,
+    path = "/api/v1/phone/verify",
+    tag = "settings",
+    request_body = VerifyPhoneRequest,
+    responses(
+        (status = 200, body = PhoneSettings),
+        (status = 400, description = "Invalid or expired code"),
+        (status = 409, description = "The number is already on another account"),
+        (status = 429, description = "Too many wrong codes"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn verify_phone(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<VerifyPhoneRequest>,
+) -> Result<Json<PhoneSettings>, (StatusCode, String)> {
+    let phone_number = require_normalized(&payload.phone_number)?;
+    let code = check_code(&state.pool, &phone_number, "verify", &payload.code).await?;
+    if code.user_id != Some(user.id) {
+        return Err(invalid_code());
+    }
+    consume(state.pool.as_ref(), code.id).await?;
+
+    sqlx::query("UPDATE users SET phone_number = $2, phone_verified_at = NOW() WHERE id = $1")
+        .bind(user.id)
+        .bind(&phone_number)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(ref db) if db.is_unique_violation() => in_use(),
+            e => db_error(e),
+        })?;
+
+    Ok(Json(fetch_settings(&state.pool, user.id).await?))
+}
+
+// The caller's phone number and whether contacts can find them by it
+#[utoipa::path(
+    get,
+    path = "/api/v1/settings/{user_id}/phone",
+    tag = "settings",
+    params(("user_id" = String, Path, description = "User ID")),
+    responses((status = 200, body = PhoneSettings), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_phone_settings(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<String>,
+) -> Result<Json<PhoneSettings>, (StatusCode, String)> {
+    let user_uuid = crate::settings::require_self(&user, &user_id)?;
+    Ok(Json(fetch_settings(&state.pool, user_uuid).await?))
+}
+
+// Turn finding the account by phone number on or off
+#[utoipa::path(
+    put,
+    path = "/api/v1/settings/{user_id}/phone",
+    tag = "settings",
+    params(("user_id" = String, Path, description = "User ID")),
+    request_body = UpdatePhoneSettingsRequest,
+    responses((status = 200, body = PhoneSettings), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_phone_settings(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<String>,
+    Json(payload): Json<UpdatePhoneSettingsRequest>,
+) -> Result<Json<PhoneSettings>, (StatusCode, String)> {
+    let user_uuid = crate::settings::require_self(&user, &user_id)?;
+
+    sqlx::query("UPDATE users SET discoverable_by_phone = $2 WHERE id = $1")
+        .bind(user_uuid)
+        .bind(payload.discoverable_by_phone)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(fetch_settings(&state.pool, user_uuid).await?))
+}
+
+// Remove the caller's phone number. Phone login and recovery stop working.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/settings/{user_id}/phone",
+    tag = "settings",
+    params(("user_id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, body = PhoneSettings),
+        (status = 400, description = "The account has no other way to log in"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn remove_phone(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<String>,
+) -> Result<Json<PhoneSettings>, (StatusCode, String)> {
+    let user_uuid = crate::settings::require_self(&user, &user_id)?;
+    // Phone signups have a placeholder address until they add a real one
+    if user.email.ends_with(crate::auth::PHONE_SIGNUP_EMAIL_DOMAIN) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Add an email address before removing your phone number".to_string(),
+        ));
+    }
+
+    sqlx::query("UPDATE users SET phone_number = NULL, phone_verified_at = NULL WHERE id = $1")
+        .bind(user_uuid)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(fetch_settings(&state.pool, user_uuid).await?))
+}
+
+// Find accounts for numbers in the caller's address book
+#[utoipa::path(
+    post,
+    path = "/api/v1/contacts/match",
+    tag = "discovery",
+    request_body = MatchContactsRequest,
+    responses(
+        (status = 200, body = [ContactMatch]),
+        (status = 400, description = "Too many numbers"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn match_contacts(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<MatchContactsRequest>,
+) -> Result<Json<Vec<ContactMatch>>, (StatusCode, String)> {
+    if payload.phone_numbers.len() > MAX_CONTACTS_PER_MATCH {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("At most {} numbers can be matched at once", MAX_CONTACTS_PER_MATCH),
+        ));
+    }
+    let mut numbers: Vec<String> = payload.phone_numbers.iter().filter_map(|n| normalize(n)).collect();
+    numbers.sort();
+    numbers.dedup();
+    if numbers.is_empty() {
+        return Ok(Json(Vec::new()));
+    }
+
+    let matches = sqlx::query_as::<_, ContactMatch>(
+        r#"
+        SELECT u.phone_number, u.id AS user_id, u.username, u.display_name, u.avatar_url,
+               EXISTS (SELECT 1 FROM follows f WHERE f.follower_id = $1 AND f.following_id = u.id) AS is_following
+        FROM users u
+        WHERE u.phone_number = ANY($2)
+          AND u.phone_verified_at IS NOT NULL
+          AND u.discoverable_by_phone
+          AND u.id <> $1
+          AND NOT minor_contact_restricted($1, u.id)
+          AND NOT supervision_hides_from($1, u.id)
+        ORDER BY u.username
+        "#
+    )
+    .bind(user.id)
+    .bind(&numbers)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(matches))
+}
@@ -0,0 +1,408 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use chrono::NaiveDateTime;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::chat_export::escape_html;
+use crate::syndication::public_base_url;
+use crate::AppState;
+
+// Server-rendered public profile pages (/u/{username}), link previews for
+// shared stories (/s/{story_id}) and the sitemap that lists profiles, so
+// they can be shared with previews and indexed by search engines without
+// loading the app. Pages only show what's public: profile headers and live
+// public stories. Minors' and bots' profiles have no public page.
+
+const PAGE_STORIES: i64 = 12;
+// Per sitemap file (the protocol's limit)
+const SITEMAP_URLS: i64 = 50_000;
+const PAGE_MAX_AGE_SECS: u64 = 300;
+
+const PUBLIC_PROFILE: &str = "NOT u.is_bot AND NOT user_is_minor(u.id)";
+
+#[derive(sqlx::FromRow)]
+struct PublicProfile {
+    id: Uuid,
+    username: String,
+    display_name: Option<String>,
+    bio: Option<String>,
+    avatar_url: Option<String>,
+    follower_count: Option<i32>,
+    following_count: Option<i32>,
+    account_type: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct StoryThumbnail {
+    media_url: String,
+    media_type: String,
+    thumbnail_url: Option<String>,
+    alt_text: Option<String>,
+    expires_at: NaiveDateTime,
+}
+
+fn cached(content_type: &'static str, body: String) -> Response {
+    cached_for(content_type, body, PAGE_MAX_AGE_SECS)
+}
+
+fn cached_for(content_type: &'static str, body: String, max_age_secs: u64) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CACHE_CONTROL, format!("public, max-age={}", max_age_secs)),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+fn not_found_page(what: &str) -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Html(format!(
+            "<!DOCTYPE html><html><head><meta charset=\"UTF-8\"><title>Not found - relays.social</title>\
+             <meta name=\"robots\" content=\"noindex\"></head><body><h1>This {} isn't available</h1>\
+             <p><a href=\"/\">Go to relays.social</a></p></body></html>",
+            what
+        )),
+    )
+        .into_response()
+}
+
+// Public profile page with Open Graph tags
+pub async fn profile_page(
+    State(state): State<Arc<AppState>>,
+    Path(username): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let profile = sqlx::query_as::<_, PublicProfile>(&format!(
+        r#"
+        SELECT u.id, u.username, u.display_name, u.bio, u.avatar_url, u.follower_count, u.following_count, u.account_type
+        FROM users u
+        WHERE LOWER(u.username) = LOWER($1) AND {}
+        "#,
+        PUBLIC_PROFILE
+    ))
+    .bind(&username)
+    .fetch_optional(state.read_pool())
+    .await;
+
+    let profile = match profile {
+        Ok(Some(profile)) => profile,
+        Ok(None) => return not_found_page("profile"),
+        Err(e) => {
+            eprintln!("❌ Failed to load public profile {}: {:?}", username, e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let stories = sqlx::query_as::<_, StoryThumbnail>(&format!(
+        r#"
+        SELECT s.media_url, s.media_type, s.thumbnail_url, s.alt_text, s.expires_at
+        FROM stories s
+        JOIN users u ON u.id = s.user_id
+        WHERE s.user_id = $1 AND s.expires_at > NOW() AND {}
+        ORDER BY s.created_at DESC
+        LIMIT $2
+        "#,
+        crate::stories::PUBLIC_STORY
+    ))
+    .bind(profile.id)
+    .bind(PAGE_STORIES)
+    .fetch_all(state.read_pool())
+    .await
+    .unwrap_or_else(|e| {
+        eprintln!("❌ Failed to load public stories of {}: {:?}", profile.username, e);
+        Vec::new()
+    });
+
+    // Pages are cached for a few minutes, so links have to outlast that
+    let media = &state.media_service;
+    let link_until = chrono::Utc::now().naive_utc() + chrono::Duration::hours(1);
+    let avatar_url = match &profile.avatar_url {
+        Some(url) => Some(media.sign_url_until(url, link_until).await),
+        None => None,
+    };
+    let mut thumbnails = Vec::with_capacity(stories.len());
+    for story in &stories {
+        // Videos show their poster frame; without one there's nothing to show
+        let image = match (&story.thumbnail_url, story.media_type.as_str()) {
+            (Some(thumbnail), _) => thumbnail,
+            (None, "image") => &story.media_url,
+            (None, _) => continue,
+        };
+        let until = story.expires_at.min(link_until);
+        thumbnails.push((media.sign_url_until(image, until).await, story.alt_text.clone()));
+    }
+
+    let base_url = public_base_url(&headers);
+    let page_url = format!("{}/u/{}", base_url, profile.username);
+    let name = profile.display_name.clone().filter(|n| !n.is_empty()).unwrap_or_else(|| profile.username.clone());
+    let title = format!("{} (@{}) - relays.social", name, profile.username);
+    let description = profile.bio.clone().filter(|b| !b.is_empty()).unwrap_or_else(|| {
+        format!(
+            "{} followers, {} following on relays.social",
+            profile.follower_count.unwrap_or(0),
+            profile.following_count.unwrap_or(0)
+        )
+    });
+    let og_image = thumbnails.first().map(|(url, _)| url.clone()).or(avatar_url.clone());
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"UTF-8\">\n");
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">\n");
+    html.push_str("<link rel=\"icon\" type=\"image/jpeg\" href=\"/logo.jpg\">\n");
+    html.push_str(&format!("<title>{}</title>\n", escape_html(&title)));
+    html.push_str(&format!("<meta name=\"description\" content=\"{}\">\n", escape_html(&description)));
+    html.push_str(&format!("<link rel=\"canonical\" href=\"{}\">\n", escape_html(&page_url)));
+    html.push_str("<meta property=\"og:type\" content=\"profile\">\n");
+    html.push_str("<meta property=\"og:site_name\" content=\"relays.social\">\n");
+    html.push_str(&format!("<meta property=\"og:title\" content=\"{}\">\n", escape_html(&title)));
+    html.push_str(&format!("<meta property=\"og:description\" content=\"{}\">\n", escape_html(&description)));
+    html.push_str(&format!("<meta property=\"og:url\" content=\"{}\">\n", escape_html(&page_url)));
+    html.push_str(&format!("<meta property=\"profile:username\" content=\"{}\">\n", escape_html(&profile.username)));
+    if let Some(image) = &og_image {
+        html.push_str(&format!("<meta property=\"og:image\" content=\"{}\">\n", escape_html(image)));
+    }
+    let card = if thumbnails.is_empty() { "summary" } else { "summary_large_image" };
+    html.push_str(&format!("<meta name=\"twitter:card\" content=\"{}\">\n", card));
+    // Creator and business accounts have a story feed
+    if profile.account_type != "personal" {
+        html.push_str(&format!(
+            "<link rel=\"alternate\" type=\"application/rss+xml\" href=\"{}\">\n",
+            escape_html(&format!("{}/api/v1/users/{}/feed.rss", base_url, profile.username))
+        ));
+    }
+    html.push_str(concat!(
+        "<style>body{font-family:system-ui,sans-serif;max-width:720px;margin:0 auto;padding:24px;color:#111}",
+        "header{display:flex;gap:16px;align-items:center}header img{width:96px;height:96px;border-radius:50%;object-fit:cover}",
+        "h1{margin:0;font-size:1.4em}.stats{color:#555}.stories{display:grid;grid-template-columns:repeat(3,1fr);gap:8px;margin-top:24px}",
+        ".stories img{width:100%;aspect-ratio:9/16;object-fit:cover;border-radius:8px}a.open{display:inline-block;margin-top:24px}</style>\n",
+    ));
+    html.push_str("</head>\n<body>\n<header>\n");
+    if let Some(avatar) = &avatar_url {
+        html.push_str(&format!("<img src=\"{}\" alt=\"\">\n", escape_html(avatar)));
+    }
+    html.push_str(&format!(
+        "<div><h1>{}</h1><div>@{}</div><div class=\"stats\">{} followers · {} following</div></div>\n</header>\n",
+        escape_html(&name),
+        escape_html(&profile.username),
+        profile.follower_count.unwrap_or(0),
+        profile.following_count.unwrap_or(0)
+    ));
+    if let Some(bio) = profile.bio.as_deref().filter(|b| !b.is_empty()) {
+        html.push_str(&format!("<p>{}</p>\n", escape_html(bio).replace('\n', "<br>")));
+    }
+    if !thumbnails.is_empty() {
+        html.push_str("<section class=\"stories\">\n");
+        for (url, alt_text) in &thumbnails {
+            html.push_str(&format!(
+                "<img src=\"{}\" alt=\"{}\" loading=\"lazy\">\n",
+                escape_html(url),
+                escape_html(alt_text.as_deref().unwrap_or("Story"))
+            ));
+        }
+        html.push_str("</section>\n");
+    }
+    html.push_str(&format!(
+        "<a class=\"open\" href=\"/profile.html?user_id={}\">Open in relays.social</a>\n</body>\n</html>\n",
+        profile.id
+    ));
+
+    cached("text/html; charset=utf-8", html)
+}
+
+#[derive(sqlx::FromRow)]
+struct SharedStory {
+    username: String,
+    display_name: Option<String>,
+    media_url: String,
+    media_type: String,
+    thumbnail_url: Option<String>,
+    caption: Option<String>,
+    alt_text: Option<String>,
+    expires_at: NaiveDateTime,
+}
+
+// Link preview for a shared story: Open Graph and Twitter card tags with a
+// signed thumbnail. Only live public stories of public profiles have one;
+// anything else (expired, 18+, taken down, a minor's) is a plain 404.
+pub async fn story_page(
+    State(state): State<Arc<AppState>>,
+    Path(story_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Response {
+    let story = sqlx::query_as::<_, SharedStory>(&format!(
+        r#"
+        SELECT u.username, u.display_name, s.media_url, s.media_type, s.thumbnail_url,
+               s.caption, s.alt_text, s.expires_at
+        FROM stories s
+        JOIN users u ON u.id = s.user_id
+        WHERE s.id = $1 AND s.expires_at > NOW() AND {} AND {}
+        "#,
+        crate::stories::PUBLIC_STORY,
+        PUBLIC_PROFILE
+    ))
+    .bind(story_id)
+    .fetch_optional(state.read_pool())
+    .await;
+
+    let story = match story {
+        Ok(Some(story)) => story,
+        Ok(None) => return not_found_page("story"),
+        Err(e) => {
+            eprintln!("❌ Failed to load shared story {}: {:?}", story_id, e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    // Neither the page nor its image link may outlive the story
+    let now = chrono::Utc::now().naive_utc();
+    let seconds_left = (story.expires_at - now).num_seconds().max(0) as u64;
+    let link_until = story.expires_at.min(now + chrono::Duration::hours(1));
+    let image = match (&story.thumbnail_url, story.media_type.as_str()) {
+        (Some(thumbnail), _) => Some(state.media_service.sign_url_until(thumbnail, link_until).await),
+        (None, "image") => Some(state.media_service.sign_url_until(&story.media_url, link_until).await),
+        (None, _) => None,
+    };
+
+    let base_url = public_base_url(&headers);
+    let page_url = format!("{}/s/{}", base_url, story_id);
+    let name = story.display_name.clone().filter(|n| !n.is_empty()).unwrap_or_else(|| story.username.clone());
+    let title = format!("Story by {} (@{})", name, story.username);
+    let description = story
+        .caption
+        .clone()
+        .filter(|c| !c.is_empty())
+        .unwrap_or_else(|| format!("See @{}'s story on relays.social before it disappears", story.username));
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"UTF-8\">\n");
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">\n");
+    html.push_str("<link rel=\"icon\" type=\"image/jpeg\" href=\"/logo.jpg\">\n");
+    // Stories disappear within a day, so there's nothing worth indexing
+    html.push_str("<meta name=\"robots\" content=\"noindex\">\n");
+    html.push_str(&format!("<title>{} - relays.social</title>\n", escape_html(&title)));
+    html.push_str(&format!("<meta name=\"description\" content=\"{}\">\n", escape_html(&description)));
+    html.push_str("<meta property=\"og:type\" content=\"article\">\n");
+    html.push_str("<meta property=\"og:site_name\" content=\"relays.social\">\n");
+    html.push_str(&format!("<meta property=\"og:title\" content=\"{}\">\n", escape_html(&title)));
+    html.push_str(&format!("<meta property=\"og:description\" content=\"{}\">\n", escape_html(&description)));
+    html.push_str(&format!("<meta property=\"og:url\" content=\"{}\">\n", escape_html(&page_url)));
+    html.push_str(&format!(
+        "<meta property=\"article:expiration_time\" content=\"{}\">\n",
+        story.expires_at.and_utc().to_rfc3339()
+    ));
+    if let Some(image) = &image {
+        html.push_str(&format!("<meta property=\"og:image\" content=\"{}\">\n", escape_html(image)));
+        if let Some(alt_text) = &story.alt_text {
+            html.push_str(&format!("<meta property=\"og:image:alt\" content=\"{}\">\n", escape_html(alt_text)));
+        }
+        html.push_str("<meta name=\"twitter:card\" content=\"summary_large_image\">\n");
+    } else {
+        html.push_str("<meta name=\"twitter:card\" content=\"summary\">\n");
+    }
+    html.push_str(concat!(
+        "<style>body{font-family:system-ui,sans-serif;max-width:480px;margin:0 auto;padding:24px;color:#111}",
+        "img{width:100%;aspect-ratio:9/16;object-fit:cover;border-radius:12px}a.open{display:inline-block;margin-top:16px}</style>\n",
+    ));
+    html.push_str("</head>\n<body>\n");
+    html.push_str(&format!(
+        "<p><a href=\"/u/{0}\">@{0}</a></p>\n",
+        escape_html(&story.username)
+    ));
+    if let Some(image) = &image {
+        html.push_str(&format!(
+            "<img src=\"{}\" alt=\"{}\">\n",
+            escape_html(image),
+            escape_html(story.alt_text.as_deref().unwrap_or("Story"))
+        ));
+    }
+    if let Some(caption) = story.caption.as_deref().filter(|c| !c.is_empty()) {
+        html.push_str(&format!("<p>{}</p>\n", escape_html(caption)));
+    }
+    html.push_str("<a class=\"open\" href=\"/stories\">Open in relays.social</a>\n</body>\n</html>\n");
+
+    cached_for("text/html; charset=utf-8", html, seconds_left.min(PAGE_MAX_AGE_SECS))
+}
+
+// Sitemap index: one sitemap per SITEMAP_URLS public profiles
+pub async fn sitemap_index(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Result<Response, StatusCode> {
+    let profiles = sqlx::query_scalar::<_, i64>(&format!("SELECT COUNT(*) FROM users u WHERE {}", PUBLIC_PROFILE))
+        .fetch_one(state.read_pool())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let base_url = public_base_url(&headers);
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for page in 0..(profiles.max(1) + SITEMAP_URLS - 1) / SITEMAP_URLS {
+        xml.push_str(&format!(
+            "<sitemap><loc>{}/sitemaps/profiles/{}</loc></sitemap>\n",
+            escape_html(&base_url),
+            page
+        ));
+    }
+    xml.push_str("</sitemapindex>\n");
+
+    Ok(cached("application/xml; charset=utf-8", xml))
+}
+
+// One page of public profile URLs, last modified at their latest public story
+pub async fn profiles_sitemap(
+    State(state): State<Arc<AppState>>,
+    Path(page): Path<i64>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    if page < 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let profiles = sqlx::query_as::<_, (String, Option<NaiveDateTime>)>(&format!(
+        r#"
+        SELECT u.username,
+               GREATEST(u.updated_at, (SELECT MAX(s.created_at) FROM stories s
+                                       WHERE s.user_id = u.id AND {})) AS last_modified
+        FROM users u
+        WHERE {}
+        ORDER BY u.created_at, u.id
+        OFFSET $1
+        LIMIT $2
+        "#,
+        crate::stories::PUBLIC_STORY,
+        PUBLIC_PROFILE
+    ))
+    .bind(page * SITEMAP_URLS)
+    .bind(SITEMAP_URLS)
+    .fetch_all(state.read_pool())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if profiles.is_empty() && page > 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let base_url = public_base_url(&headers);
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for (username, last_modified) in profiles {
+        xml.push_str(&format!("<url><loc>{}/u/{}</loc>", escape_html(&base_url), escape_html(&username)));
+        if let Some(last_modified) = last_modified {
+            xml.push_str(&format!("<lastmod>{}</lastmod>", last_modified.format("%Y-%m-%d")));
+        }
+        xml.push_str("</url>\n");
+    }
+    xml.push_str("</urlset>\n");
+
+    Ok(cached("application/xml; charset=utf-8", xml))
+}
+
+pub async fn robots_txt(headers: HeaderMap) -> Response {
+    let body = format!(
+        "User-agent: *\nAllow: /u/\nDisallow: /api/\nDisallow: /ws/\nSitemap: {}/sitemap.xml\n",
+        public_base_url(&headers)
+    );
+    cached("text/plain; charset=utf-8", body)
+}
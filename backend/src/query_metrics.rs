@@ -0,0 +1,299 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{NaiveDateTime, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::permissions::{CanManagePlatform, Permitted};
+use crate::AppState;
+
+// Per-query timing for the database calls worth watching. Wrap the call in
+// `observe` (or `observe_statement` when the SQL text is at hand) under a
+// stable name; durations go into an in-memory histogram per name, and calls
+// slower than SLOW_QUERY_MS are logged with literals stripped from the SQL.
+// Bind parameters are never logged. Numbers reset when the process restarts.
+
+// Histogram bucket upper bounds in milliseconds; slower calls land in a final overflow bucket
+const BUCKETS_MS: [u64; 12] = [1, 5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000];
+const RECENT_SLOW_CAPACITY: usize = 50;
+const DEFAULT_SLOW_QUERY_MS: u64 = 250;
+
+#[derive(Default)]
+struct Stats {
+    calls: u64,
+    errors: u64,
+    slow_calls: u64,
+    total: Duration,
+    max: Duration,
+    buckets: [u64; BUCKETS_MS.len() + 1],
+    last_slow_at: Option<NaiveDateTime>,
+}
+
+impl Stats {
+    /// Upper bound of the bucket holding the given percentile
+    fn percentile_ms(&self, percentile: f64) -> u64 {
+        let target = ((self.calls as f64) * percentile).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (i, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                return BUCKETS_MS.get(i).copied().unwrap_or(self.max.as_millis() as u64);
+            }
+        }
+        self.max.as_millis() as u64
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SlowQuery {
+    pub name: String,
+    pub elapsed_ms: u64,
+    /// SQL with literals replaced by ?, when the call site provided it
+    pub statement: Option<String>,
+    pub failed: bool,
+    #[serde(with = "crate::timestamps")]
+    pub at: NaiveDateTime,
+}
+
+fn stats() -> &'static DashMap<&'static str, Stats> {
+    static STATS: OnceLock<DashMap<&'static str, Stats>> = OnceLock::new();
+    STATS.get_or_init(DashMap::new)
+}
+
+fn recent_slow() -> &'static Mutex<VecDeque<SlowQuery>> {
+    static RECENT: OnceLock<Mutex<VecDeque<SlowQuery>>> = OnceLock::new();
+    RECENT.get_or_init(|| Mutex::new(VecDeque::with_capacity(RECENT_SLOW_CAPACITY)))
+}
+
+fn slow_threshold() -> Duration {
+    static THRESHOLD: OnceLock<Duration> = OnceLock::new();
+    *THRESHOLD.get_or_init(|| {
+        let ms = std::env::var("SLOW_QUERY_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_SLOW_QUERY_MS);
+        Duration::from_millis(ms)
+    })
+}
+
+/// Time a database call under `name`
+pub async fn observe<T, E, F>(name: &'static str, query: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    let started = Instant::now();
+    let result = query.await;
+    record(name, None, started.elapsed(), result.is_err());
+    result
+}
+
+/// Like `observe`, also logging `sql` (literals redacted) when the call is slow
+pub async fn observe_statement<T, E, F>(name: &'static str, sql: &str, query: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    let started = Instant::now();
+    let result = query.await;
+    record(name, Some(sql), started.elapsed(), result.is_err());
+    result
+}
+
+fn record(name: &'static str, sql: Option<&str>, elapsed: Duration, failed: bool) {
+    let elapsed_ms = elapsed.as_millis() as u64;
+    let slow = elapsed >= slow_threshold();
+    let now = Utc::now().naive_utc();
+
+    {
+        let mut entry = stats().entry(name).or_default();
+        entry.calls += 1;
+        entry.total += elapsed;
+        entry.max = entry.max.max(elapsed);
+        let bucket = BUCKETS_MS.iter().position(|&le| elapsed_ms <= le).unwrap_or(BUCKETS_MS.len());
+        entry.buckets[bucket] += 1;
+        if failed {
+            entry.errors += 1;
+        }
+        if slow {
+            entry.slow_calls += 1;
+            entry.last_slow_at = Some(now);
+        }
+    }
+
+    if !slow {
+        return;
+    }
+
+    let statement = sql.map(redact_sql);
+    tracing::warn!(
+        query = name,
+        elapsed_ms,
+        failed,
+        statement = statement.as_deref().unwrap_or(""),
+        "Slow query"
+    );
+
+    let mut recent = recent_slow().lock().unwrap_or_else(|e| e.into_inner());
+    if recent.len() == RECENT_SLOW_CAPACITY {
+        recent.pop_front();
+    }
+    recent.push_back(SlowQuery {
+        name: name.to_string(),
+        elapsed_ms,
+        statement,
+        failed,
+        at: now,
+    });
+}
+
+/// Collapse whitespace and replace string and numeric literals with ?.
+/// Placeholders ($1, $2, ...) are kept since they carry no data.
+fn redact_sql(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    let mut prev: Option<char> = None;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                // Skip to the closing quote; '' is an escaped quote inside the literal
+                while let Some(next) = chars.next() {
+                    if next == '\'' {
+                        if chars.peek() == Some(&'\'') {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                out.push('?');
+            }
+            '$' if chars.peek().is_some_and(|n| n.is_ascii_digit()) => {
+                out.push(c);
+                while let Some(&digit) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                    out.push(digit);
+                    chars.next();
+                }
+            }
+            c if c.is_ascii_digit() && !prev.is_some_and(|p| p.is_alphanumeric() || p == '_') => {
+                while chars.peek().is_some_and(|n| n.is_ascii_digit() || *n == '.') {
+                    chars.next();
+                }
+                out.push('?');
+            }
+            c if c.is_whitespace() => {
+                if !out.ends_with(' ') && !out.is_empty() {
+                    out.push(' ');
+                }
+            }
+            c => out.push(c),
+        }
+        prev = Some(c);
+    }
+
+    out.trim_end().to_string()
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct QueryStat {
+    pub name: String,
+    pub calls: u64,
+    pub errors: u64,
+    pub slow_calls: u64,
+    pub total_ms: u64,
+    pub mean_ms: f64,
+    pub max_ms: u64,
+    /// Percentiles are bucket upper bounds, so they overestimate slightly
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub p99_ms: u64,
+    #[serde(with = "crate::timestamps::option")]
+    pub last_slow_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct QueryDiagnostics {
+    pub slow_query_threshold_ms: u64,
+    pub queries: Vec<QueryStat>,
+    /// Most recent slow calls, newest first
+    pub recent_slow: Vec<SlowQuery>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DiagnosticsQuery {
+    /// p95 (default), total, max or calls
+    pub sort: Option<String>,
+    pub limit: Option<usize>,
+}
+
+// Worst database queries since the process started
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/diagnostics/queries",
+    tag = "admin",
+    params(DiagnosticsQuery),
+    responses(
+        (status = 200, body = QueryDiagnostics),
+        (status = 400, description = "Unknown sort"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_query_diagnostics(
+    _admin: Permitted<CanManagePlatform>,
+    State(_state): State<Arc<AppState>>,
+    Query(params): Query<DiagnosticsQuery>,
+) -> Result<Json<QueryDiagnostics>, (StatusCode, String)> {
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+
+    let mut queries: Vec<QueryStat> = stats()
+        .iter()
+        .map(|entry| {
+            let s = entry.value();
+            QueryStat {
+                name: entry.key().to_string(),
+                calls: s.calls,
+                errors: s.errors,
+                slow_calls: s.slow_calls,
+                total_ms: s.total.as_millis() as u64,
+                mean_ms: s.total.as_secs_f64() * 1000.0 / s.calls.max(1) as f64,
+                max_ms: s.max.as_millis() as u64,
+                p50_ms: s.percentile_ms(0.50),
+                p95_ms: s.percentile_ms(0.95),
+                p99_ms: s.percentile_ms(0.99),
+                last_slow_at: s.last_slow_at,
+            }
+        })
+        .collect();
+
+    match params.sort.as_deref().unwrap_or("p95") {
+        "p95" => queries.sort_by_key(|q| std::cmp::Reverse((q.p95_ms, q.max_ms))),
+        "total" => queries.sort_by_key(|q| std::cmp::Reverse(q.total_ms)),
+        "max" => queries.sort_by_key(|q| std::cmp::Reverse(q.max_ms)),
+        "calls" => queries.sort_by_key(|q| std::cmp::Reverse(q.calls)),
+        other => return Err((StatusCode::BAD_REQUEST, format!("Unknown sort {:?}", other))),
+    }
+    queries.truncate(limit);
+
+    let recent_slow = recent_slow()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .rev()
+        .cloned()
+        .collect();
+
+    Ok(Json(QueryDiagnostics {
+        slow_query_threshold_ms: slow_threshold().as_millis() as u64,
+        queries,
+        recent_slow,
+    }))
+}
@@ -0,0 +1,315 @@
+use axum::{
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Duration, NaiveDateTime, Timelike, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::runtime_settings::{
+    self, QUOTA_CHATS_PER_HOUR, QUOTA_CHAT_EXPORTS_PER_DAY, QUOTA_COMMENTS_PER_DAY, QUOTA_COMMENT_MAX_LENGTH,
+    QUOTA_STORIES_PER_DAY, QUOTA_UPLOAD_MB_PER_DAY,
+};
+
+/// Per-user content limits, read from the quotas.* runtime settings so
+/// admins can change them without a restart
+#[derive(Debug, Clone)]
+pub struct Quotas {
+    pub stories_per_day: i64,
+    pub chats_per_hour: i64,
+    pub comments_per_day: i64,
+    pub comment_max_length: i64,
+    pub upload_bytes_per_day: i64,
+    pub chat_exports_per_day: i64,
+}
+
+impl Quotas {
+    pub fn get() -> Quotas {
+        Quotas {
+            stories_per_day: runtime_settings::int(QUOTA_STORIES_PER_DAY),
+            chats_per_hour: runtime_settings::int(QUOTA_CHATS_PER_HOUR),
+            comments_per_day: runtime_settings::int(QUOTA_COMMENTS_PER_DAY),
+            comment_max_length: runtime_settings::int(QUOTA_COMMENT_MAX_LENGTH),
+            upload_bytes_per_day: runtime_settings::int(QUOTA_UPLOAD_MB_PER_DAY) * 1024 * 1024,
+            chat_exports_per_day: runtime_settings::int(QUOTA_CHAT_EXPORTS_PER_DAY),
+        }
+    }
+}
+
+// Quotas use fixed UTC windows so the usage endpoint can say when they reset
+fn day_window() -> (NaiveDateTime, NaiveDateTime) {
+    let start = Utc::now().date_naive().and_hms_opt(0, 0, 0).unwrap();
+    (start, start + Duration::days(1))
+}
+
+fn hour_window() -> (NaiveDateTime, NaiveDateTime) {
+    let now = Utc::now().naive_utc();
+    let start = now.date().and_hms_opt(now.hour(), 0, 0).unwrap();
+    (start, start + Duration::hours(1))
+}
+
+/// Rejection returned when a user is over a limit (429) or a single item is
+/// too large (413)
+#[derive(Debug)]
+pub struct QuotaExceeded {
+    status: StatusCode,
+    quota: &'static str,
+    message: String,
+    limit: i64,
+    used: Option<i64>,
+    resets_at: Option<NaiveDateTime>,
+}
+
+impl IntoResponse for QuotaExceeded {
+    fn into_response(self) -> Response {
+        let mut response = (
+            self.status,
+            Json(serde_json::json!({
+                "error": "quota_exceeded",
+                "quota": self.quota,
+                "message": self.message,
+                "limit": self.limit,
+                "used": self.used,
+                "resets_at": self.resets_at.map(crate::timestamps::format),
+            })),
+        )
+            .into_response();
+
+        if let Some(resets_at) = self.resets_at {
+            let seconds = (resets_at - Utc::now().naive_utc()).num_seconds().max(1);
+            if let Ok(value) = HeaderValue::from_str(&seconds.to_string()) {
+                response.headers_mut().insert(header::RETRY_AFTER, value);
+            }
+        }
+
+        response
+    }
+}
+
+fn over_limit(quota: &'static str, what: &str, limit: i64, used: i64, resets_at: NaiveDateTime) -> QuotaExceeded {
+    QuotaExceeded {
+        status: StatusCode::TOO_MANY_REQUESTS,
+        quota,
+        message: format!("You can create at most {} {}; try again later", limit, what),
+        limit,
+        used: Some(used),
+        resets_at: Some(resets_at),
+    }
+}
+
+fn db_error(e: sqlx::Error) -> StatusCode {
+    eprintln!("❌ Quota lookup failed: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+async fn stories_today(pool: &PgPool, user_id: Uuid) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM stories WHERE user_id = $1 AND created_at >= $2")
+        .bind(user_id)
+        .bind(day_window().0)
+        .fetch_one(pool)
+        .await
+}
+
+async fn chats_this_hour(pool: &PgPool, user_id: Uuid) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM chat_rooms WHERE created_by = $1 AND created_at >= $2")
+        .bind(user_id)
+        .bind(hour_window().0)
+        .fetch_one(pool)
+        .await
+}
+
+async fn comments_today(pool: &PgPool, user_id: Uuid) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM story_comments WHERE user_id = $1 AND created_at >= $2")
+        .bind(user_id)
+        .bind(day_window().0)
+        .fetch_one(pool)
+        .await
+}
+
+async fn chat_exports_today(pool: &PgPool, user_id: Uuid) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM chat_exports WHERE user_id = $1 AND created_at >= $2")
+        .bind(user_id)
+        .bind(day_window().0)
+        .fetch_one(pool)
+        .await
+}
+
+async fn upload_bytes_today(pool: &PgPool, user_id: Uuid) -> Result<i64, sqlx::Error> {
+    let bytes = sqlx::query_scalar::<_, i64>("SELECT bytes FROM upload_usage WHERE user_id = $1 AND day = $2")
+        .bind(user_id)
+        .bind(day_window().0.date())
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(bytes.unwrap_or(0))
+}
+
+/// Check a story post of `upload_bytes` against the daily story and upload limits
+pub async fn check_story(pool: &PgPool, user_id: Uuid, upload_bytes: usize) -> axum::response::Result<()> {
+    let quotas = Quotas::get();
+    let (_, resets_at) = day_window();
+
+    let stories = stories_today(pool, user_id).await.map_err(db_error)?;
+    if stories >= quotas.stories_per_day {
+        return Err(over_limit("stories_per_day", "stories per day", quotas.stories_per_day, stories, resets_at).into());
+    }
+
+    check_upload(pool, user_id, upload_bytes).await
+}
+
+/// Reject an upload that would push the user past their daily byte allowance
+pub async fn check_upload(pool: &PgPool, user_id: Uuid, upload_bytes: usize) -> axum::response::Result<()> {
+    let quotas = Quotas::get();
+    let (_, resets_at) = day_window();
+
+    let used = upload_bytes_today(pool, user_id).await.map_err(db_error)?;
+    if used + upload_bytes as i64 > quotas.upload_bytes_per_day {
+        return Err(QuotaExceeded {
+            status: StatusCode::PAYLOAD_TOO_LARGE,
+            quota: "upload_bytes_per_day",
+            message: format!(
+                "This upload would exceed your daily upload allowance of {} bytes",
+                quotas.upload_bytes_per_day
+            ),
+            limit: quotas.upload_bytes_per_day,
+            used: Some(used),
+            resets_at: Some(resets_at),
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Count a finished upload toward today's allowance
+pub async fn record_upload(pool: &PgPool, user_id: Uuid, upload_bytes: usize) {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO upload_usage (user_id, day, bytes)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, day) DO UPDATE SET bytes = upload_usage.bytes + EXCLUDED.bytes
+        "#
+    )
+    .bind(user_id)
+    .bind(day_window().0.date())
+    .bind(upload_bytes as i64)
+    .execute(pool)
+    .await;
+
+    if let Err(e) = result {
+        eprintln!("❌ Failed to record upload usage: {:?}", e);
+    }
+}
+
+pub async fn check_chat_creation(pool: &PgPool, user_id: Uuid) -> axum::response::Result<()> {
+    let quotas = Quotas::get();
+    let (_, resets_at) = hour_window();
+
+    let chats = chats_this_hour(pool, user_id).await.map_err(db_error)?;
+    if chats >= quotas.chats_per_hour {
+        return Err(over_limit("chats_per_hour", "new chats per hour", quotas.chats_per_hour, chats, resets_at).into());
+    }
+
+    Ok(())
+}
+
+pub async fn check_chat_export(pool: &PgPool, user_id: Uuid) -> axum::response::Result<()> {
+    let quotas = Quotas::get();
+    let (_, resets_at) = day_window();
+
+    let exports = chat_exports_today(pool, user_id).await.map_err(db_error)?;
+    if exports >= quotas.chat_exports_per_day {
+        return Err(over_limit("chat_exports_per_day", "chat exports per day", quotas.chat_exports_per_day, exports, resets_at).into());
+    }
+
+    Ok(())
+}
+
+/// Check a comment or reply for length and the daily comment limit
+pub async fn check_comment(pool: &PgPool, user_id: Uuid, comment_text: &str) -> axum::response::Result<()> {
+    let quotas = Quotas::get();
+
+    let length = comment_text.chars().count() as i64;
+    if length > quotas.comment_max_length {
+        return Err(QuotaExceeded {
+            status: StatusCode::PAYLOAD_TOO_LARGE,
+            quota: "comment_max_length",
+            message: format!("Comments can be at most {} characters", quotas.comment_max_length),
+            limit: quotas.comment_max_length,
+            used: None,
+            resets_at: None,
+        }
+        .into());
+    }
+
+    let (_, resets_at) = day_window();
+    let comments = comments_today(pool, user_id).await.map_err(db_error)?;
+    if comments >= quotas.comments_per_day {
+        return Err(over_limit("comments_per_day", "comments per day", quotas.comments_per_day, comments, resets_at).into());
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct QuotaUsage {
+    pub quota: String,
+    pub used: i64,
+    pub limit: i64,
+    #[serde(with = "crate::timestamps")]
+    pub resets_at: NaiveDateTime,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct UsageResponse {
+    pub usage: Vec<QuotaUsage>,
+    pub comment_max_length: i64,
+}
+
+/// Current usage against every windowed quota
+pub async fn usage(pool: &PgPool, user_id: Uuid) -> Result<UsageResponse, sqlx::Error> {
+    let quotas = Quotas::get();
+    let (_, day_end) = day_window();
+    let (_, hour_end) = hour_window();
+
+    let usage = vec![
+        QuotaUsage {
+            quota: "stories_per_day".to_string(),
+            used: stories_today(pool, user_id).await?,
+            limit: quotas.stories_per_day,
+            resets_at: day_end,
+        },
+        QuotaUsage {
+            quota: "chats_per_hour".to_string(),
+            used: chats_this_hour(pool, user_id).await?,
+            limit: quotas.chats_per_hour,
+            resets_at: hour_end,
+        },
+        QuotaUsage {
+            quota: "comments_per_day".to_string(),
+            used: comments_today(pool, user_id).await?,
+            limit: quotas.comments_per_day,
+            resets_at: day_end,
+        },
+        QuotaUsage {
+            quota: "upload_bytes_per_day".to_string(),
+            used: upload_bytes_today(pool, user_id).await?,
+            limit: quotas.upload_bytes_per_day,
+            resets_at: day_end,
+        },
+        QuotaUsage {
+            quota: "chat_exports_per_day".to_string(),
+            used: chat_exports_today(pool, user_id).await?,
+            limit: quotas.chat_exports_per_day,
+            resets_at: day_end,
+        },
+    ];
+
+    Ok(UsageResponse {
+        usage,
+        comment_max_length: quotas.comment_max_length,
+    })
+}
@@ -8,6 +8,11 @@ pub struct RedisClient {
     manager: ConnectionManager,
 }
 
+const PRESENCE_TTL_SECS: u64 = 90;
+// Sorted set of online user IDs scored by their last heartbeat, so they can
+// be counted without scanning presence keys
+const ONLINE_USERS_KEY: &str = "presence:online";
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct UserPresence {
     pub user_id: Uuid,
@@ -23,6 +28,10 @@ impl RedisClient {
         Ok(Self { manager })
     }
 
+    pub async fn ping(&mut self) -> RedisResult<()> {
+        redis::cmd("PING").query_async::<_, String>(&mut self.manager).await.map(|_| ())
+    }
+
     // Presence management
     pub async fn set_user_online(&mut self, user_id: Uuid) -> RedisResult<()> {
         let key = format!("presence:user:{}", user_id);
@@ -33,7 +42,10 @@ impl RedisClient {
             typing_in_chat: None,
         };
         let value = serde_json::to_string(&presence).unwrap();
-        self.manager.set_ex(&key, value, 300).await // 5 min TTL
+        // Short TTL, refreshed by WebSocket heartbeats, so a crashed server
+        // doesn't leave users online forever
+        let _: () = self.manager.set_ex(&key, value, PRESENCE_TTL_SECS).await?;
+        self.manager.zadd(ONLINE_USERS_KEY, user_id.to_string(), Utc::now().timestamp()).await
     }
 
     pub async fn set_user_offline(&mut self, user_id: Uuid) -> RedisResult<()> {
@@ -45,7 +57,29 @@ impl RedisClient {
             typing_in_chat: None,
         };
         let value = serde_json::to_string(&presence).unwrap();
-        self.manager.set_ex(&key, value, 86400).await // 24 hours
+        let _: () = self.manager.set_ex(&key, value, 86400).await?; // 24 hours
+        self.manager.zrem(ONLINE_USERS_KEY, user_id.to_string()).await
+    }
+
+    /// Presence entries for the given users, in order; None where a user has
+    /// none (never connected, or offline for over a day)
+    pub async fn get_presence(&mut self, user_ids: &[Uuid]) -> RedisResult<Vec<Option<UserPresence>>> {
+        if user_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let keys: Vec<String> = user_ids.iter().map(|id| format!("presence:user:{}", id)).collect();
+        let values: Vec<Option<String>> = redis::cmd("MGET").arg(&keys).query_async(&mut self.manager).await?;
+        Ok(values
+            .into_iter()
+            .map(|value| value.and_then(|v| serde_json::from_str(&v).ok()))
+            .collect())
+    }
+
+    /// Users whose presence is still live, across every server
+    pub async fn count_online_users(&mut self) -> RedisResult<i64> {
+        let cutoff = Utc::now().timestamp() - PRESENCE_TTL_SECS as i64;
+        let _: () = self.manager.zrembyscore(ONLINE_USERS_KEY, "-inf", cutoff).await?;
+        self.manager.zcard(ONLINE_USERS_KEY).await
     }
 
     pub async fn set_typing(&mut self, user_id: Uuid, chat_room_id: Uuid) -> RedisResult<()> {
@@ -58,62 +92,55 @@ impl RedisClient {
         self.manager.del(&key).await
     }
 
-    pub async fn get_typing_users(&mut self, chat_room_id: Uuid) -> RedisResult<Vec<Uuid>> {
-        let pattern = format!("typing:{}:*", chat_room_id);
-        let keys: Vec<String> = self.manager.keys(&pattern).await?;
-
-        let user_ids = keys.iter()
-            .filter_map(|key| {
-                key.split(':').nth(2).and_then(|id| Uuid::parse_str(id).ok())
-            })
-            .collect();
-
-        Ok(user_ids)
-    }
-
-    // Cache message reads
-    pub async fn cache_last_read(&mut self, user_id: Uuid, chat_room_id: Uuid) -> RedisResult<()> {
-        let key = format!("last_read:{}:{}", user_id, chat_room_id);
-        let timestamp = Utc::now().timestamp();
-        self.manager.set_ex(&key, timestamp, 3600).await // 1 hour cache
+    // Unread message counter
+    pub async fn increment_unread(&mut self, user_id: Uuid, chat_room_id: Uuid) -> RedisResult<i32> {
+        let key = format!("unread:{}:{}", user_id, chat_room_id);
+        self.manager.incr(&key, 1).await
     }
 
-    // WebSocket connection tracking
-    pub async fn add_ws_connection(&mut self, user_id: Uuid, connection_id: &str) -> RedisResult<()> {
-        let key = format!("ws_connections:{}", user_id);
-        self.manager.sadd(&key, connection_id).await
+    pub async fn clear_unread(&mut self, user_id: Uuid, chat_room_id: Uuid) -> RedisResult<()> {
+        let key = format!("unread:{}:{}", user_id, chat_room_id);
+        self.manager.del(&key).await
     }
 
-    pub async fn remove_ws_connection(&mut self, user_id: Uuid, connection_id: &str) -> RedisResult<()> {
-        let key = format!("ws_connections:{}", user_id);
-        self.manager.srem(&key, connection_id).await
+    // Generic short-lived cache entries
+    pub async fn cache_get(&mut self, key: &str) -> RedisResult<Option<String>> {
+        self.manager.get(key).await
     }
 
-    pub async fn get_user_connections(&mut self, user_id: Uuid) -> RedisResult<Vec<String>> {
-        let key = format!("ws_connections:{}", user_id);
-        self.manager.smembers(&key).await
+    pub async fn cache_set(&mut self, key: &str, value: &str, ttl_seconds: u64) -> RedisResult<()> {
+        self.manager.set_ex(key, value, ttl_seconds).await
     }
 
-    // Message delivery tracking
-    pub async fn mark_message_delivered(&mut self, message_id: Uuid, user_id: Uuid) -> RedisResult<()> {
-        let key = format!("delivered:{}:{}", message_id, user_id);
-        self.manager.set_ex(&key, "1", 86400).await // 24 hours
+    pub async fn cache_delete(&mut self, key: &str) -> RedisResult<()> {
+        self.manager.del(key).await
     }
 
-    // Unread message counter
-    pub async fn increment_unread(&mut self, user_id: Uuid, chat_room_id: Uuid) -> RedisResult<i32> {
-        let key = format!("unread:{}:{}", user_id, chat_room_id);
-        self.manager.incr(&key, 1).await
+    /// Append an entry to a stream, dropping entries older than `min_id_ms`
+    pub async fn stream_add(&mut self, key: &str, min_id_ms: i64, fields: &[(&str, i64)]) -> RedisResult<()> {
+        let mut cmd = redis::cmd("XADD");
+        cmd.arg(key).arg("MINID").arg("~").arg(min_id_ms).arg("*");
+        for (field, value) in fields {
+            cmd.arg(*field).arg(*value);
+        }
+        let _: String = cmd.query_async(&mut self.manager).await?;
+        Ok(())
     }
 
-    pub async fn clear_unread(&mut self, user_id: Uuid, chat_room_id: Uuid) -> RedisResult<()> {
-        let key = format!("unread:{}:{}", user_id, chat_room_id);
-        self.manager.del(&key).await
+    /// Stream entries from `start_ms` on, as (entry ID, field/value pairs)
+    pub async fn stream_range(&mut self, key: &str, start_ms: i64) -> RedisResult<Vec<(String, Vec<(String, i64)>)>> {
+        redis::cmd("XRANGE")
+            .arg(key)
+            .arg(start_ms)
+            .arg("+")
+            .query_async(&mut self.manager)
+            .await
     }
 
-    pub async fn get_unread_count(&mut self, user_id: Uuid, chat_room_id: Uuid) -> RedisResult<i32> {
-        let key = format!("unread:{}:{}", user_id, chat_room_id);
-        let count: Option<i32> = self.manager.get(&key).await?;
-        Ok(count.unwrap_or(0))
+    /// Increment a counter and (re)set its expiry
+    pub async fn cache_incr(&mut self, key: &str, ttl_seconds: i64) -> RedisResult<i64> {
+        let value: i64 = self.manager.incr(key, 1).await?;
+        let _: () = self.manager.expire(key, ttl_seconds).await?;
+        Ok(value)
     }
 }
@@ -0,0 +1,604 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::NaiveDateTime;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::permissions::{CanManagePlatform, Permitted};
+use crate::AppState;
+
+// Referral program (migration 072). Every user gets a referral code on first
+// visiting their dashboard; signing up with it attributes the new account to
+// them. The referral converts once the new user posts a story or sends a
+// message, and each converted referral counts towards the reward tiers admins
+// configure: a profile badge or streak freezes. The IPs and device ids
+// (X-Device-Id) accounts sign up and log in from are kept for 90 days, and a
+// referral whose two accounts share one is flagged and never converts.
+
+const CODE_LENGTH: usize = 8;
+// No 0/O or 1/I, so codes survive being read out loud
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const MAX_DEVICE_ID_LENGTH: usize = 128;
+const FINGERPRINT_RETENTION_DAYS: i32 = 90;
+const DASHBOARD_REFERRALS: i64 = 100;
+const MAX_BADGE_LENGTH: usize = 40;
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct ReferralReward {
+    pub id: Uuid,
+    /// Converted referrals needed to earn it
+    pub referrals_required: i32,
+    /// badge or streak_freezes
+    pub kind: String,
+    pub badge: Option<String>,
+    pub streak_freezes: Option<i32>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct EarnedReward {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub reward: ReferralReward,
+    #[serde(with = "crate::timestamps")]
+    pub granted_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct ReferredUser {
+    pub username: String,
+    /// pending, converted or flagged
+    pub status: String,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps::option")]
+    pub converted_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReferralDashboard {
+    pub code: String,
+    /// Signup link carrying the code
+    pub link: String,
+    pub invited: i64,
+    pub pending: i64,
+    pub converted: i64,
+    /// Signups from the same network or device as you, which earn nothing
+    pub flagged: i64,
+    /// Latest 100
+    pub referrals: Vec<ReferredUser>,
+    pub rewards: Vec<EarnedReward>,
+    /// The next tier, if any is left to earn
+    pub next_reward: Option<ReferralReward>,
+    pub streak_freezes: i32,
+    pub badges: Vec<String>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct UserBadge {
+    pub badge: String,
+    #[serde(with = "crate::timestamps")]
+    pub awarded_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateRewardRequest {
+    pub referrals_required: i32,
+    /// badge or streak_freezes
+    pub kind: String,
+    /// Required for badge rewards
+    pub badge: Option<String>,
+    /// Required for streak_freezes rewards
+    pub streak_freezes: Option<i32>,
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    eprintln!("❌ Referral query failed: {:?}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+}
+
+fn generate_code() -> String {
+    let mut buf = [0u8; CODE_LENGTH];
+    OsRng.fill_bytes(&mut buf);
+    buf.iter()
+        .map(|b| CODE_ALPHABET[*b as usize % CODE_ALPHABET.len()] as char)
+        .collect()
+}
+
+fn device_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("X-Device-Id")
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_DEVICE_ID_LENGTH)
+        .map(str::to_string)
+}
+
+/// Remember the IP and device `user_id` is signing up or logging in from.
+/// Failures are only logged; they never block a login.
+pub async fn record_fingerprints(pool: &PgPool, user_id: Uuid, headers: &HeaderMap) {
+    let fingerprints = [
+        ("ip", crate::ad_fraud::client_ip(headers)),
+        ("device", device_id(headers)),
+    ];
+    for (kind, value) in fingerprints {
+        let Some(value) = value else { continue };
+        if let Err(e) = sqlx::query(
+            r#"
+            INSERT INTO account_fingerprints (user_id, kind, value)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, kind, value) DO UPDATE SET last_seen_at = NOW()
+            "#
+        )
+        .bind(user_id)
+        .bind(kind)
+        .bind(&value)
+        .execute(pool)
+        .await
+        {
+            eprintln!("❌ Failed to record {} fingerprint of {}: {:?}", kind, user_id, e);
+        }
+    }
+}
+
+/// The account a referral code belongs to
+pub async fn referrer_for_code(pool: &PgPool, code: &str) -> Result<Option<Uuid>, sqlx::Error> {
+    sqlx::query_scalar("SELECT id FROM users WHERE referral_code = $1")
+        .bind(code.trim().to_uppercase())
+        .fetch_optional(pool)
+        .await
+}
+
+/// Flag pending referrals whose accounts share an IP or device, only
+/// `referred_id`'s when given
+async fn flag_shared_fingerprints(pool: &PgPool, referred_id: Option<Uuid>) -> Result<u64, sqlx::Error> {
+    let flagged = sqlx::query(
+        r#"
+        UPDATE referrals r
+        SET status = 'flagged', flag_reason = 'same_' || shared.kind
+        FROM (
+            SELECT DISTINCT ON (p.referred_id) p.referred_id, a.kind
+            FROM referrals p
+            JOIN account_fingerprints a ON a.user_id = p.referrer_id
+            JOIN account_fingerprints b ON b.user_id = p.referred_id AND b.kind = a.kind AND b.value = a.value
+            WHERE p.status = 'pending' AND ($1::uuid IS NULL OR p.referred_id = $1)
+            -- A shared device says more than a shared network
+            ORDER BY p.referred_id, a.kind
+        ) shared
+        WHERE r.referred_id = shared.referred_id
+        "#
+    )
+    .bind(referred_id)
+    .execute(pool)
+    .await?;
+    Ok(flagged.rows_affected())
+}
+
+/// Attribute the new account `referred_id` to `referrer_id`. Call after
+/// recording the new account's fingerprints, so a self-referral is flagged
+/// straight away.
+pub async fn attribute(pool: &PgPool, referred_id: Uuid, referrer_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO referrals (referred_id, referrer_id) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+        .bind(referred_id)
+        .bind(referrer_id)
+        .execute(pool)
+        .await?;
+    if flag_shared_fingerprints(pool, Some(referred_id)).await? > 0 {
+        tracing::warn!(referrer_id = %referrer_id, referred_id = %referred_id, "Referral flagged at signup");
+    }
+    Ok(())
+}
+
+/// Job body: flag and convert pending referrals, grant the rewards they
+/// earned, and forget old fingerprints
+pub async fn process(pool: &PgPool) -> Result<Option<serde_json::Value>, String> {
+    let flagged = flag_shared_fingerprints(pool, None).await.map_err(|e| e.to_string())?;
+    if flagged > 0 {
+        tracing::warn!(count = flagged, "Referrals flagged for shared fingerprints");
+    }
+
+    let converted = sqlx::query(
+        r#"
+        UPDATE referrals r
+        SET status = 'converted', converted_at = NOW()
+        WHERE r.status = 'pending'
+          AND (EXISTS (SELECT 1 FROM stories s WHERE s.user_id = r.referred_id)
+               OR EXISTS (SELECT 1 FROM messages m WHERE m.sender_id = r.referred_id))
+        "#
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .rows_affected();
+
+    grant_rewards(pool).await.map_err(|e| e.to_string())?;
+
+    sqlx::query("DELETE FROM account_fingerprints WHERE last_seen_at < NOW() - make_interval(days => $1)")
+        .bind(FINGERPRINT_RETENTION_DAYS)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(Some(serde_json::json!({
+        "referrals_flagged": flagged,
+        "referrals_converted": converted,
+    })))
+}
+
+async fn grant_rewards(pool: &PgPool) -> Result<(), sqlx::Error> {
+    #[derive(sqlx::FromRow)]
+    struct Earned {
+        user_id: Uuid,
+        #[sqlx(flatten)]
+        reward: ReferralReward,
+    }
+
+    let earned = sqlx::query_as::<_, Earned>(
+        r#"
+        SELECT c.referrer_id AS user_id, rr.id, rr.referrals_required, rr.kind, rr.badge, rr.streak_freezes
+        FROM (
+            SELECT referrer_id, COUNT(*) AS converted
+            FROM referrals
+            WHERE status = 'converted'
+            GROUP BY referrer_id
+        ) c
+        JOIN referral_rewards rr ON rr.referrals_required <= c.converted
+        WHERE NOT EXISTS (
+            SELECT 1 FROM referral_reward_grants g WHERE g.reward_id = rr.id AND g.user_id = c.referrer_id
+        )
+        ORDER BY rr.referrals_required
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for Earned { user_id, reward } in earned {
+        let mut tx = pool.begin().await?;
+        let granted = sqlx::query(
+            "INSERT INTO referral_reward_grants (reward_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING"
+        )
+        .bind(reward.id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        if granted.rows_affected() == 0 {
+            continue;
+        }
+
+        let message = match (reward.badge.as_deref(), reward.streak_freezes) {
+            (Some(badge), _) if reward.kind == "badge" => {
+                sqlx::query("INSERT INTO user_badges (user_id, badge) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+                    .bind(user_id)
+                    .bind(badge)
+                    .execute(&mut *tx)
+                    .await?;
+                crate::i18n::Message::new("referral_badge").arg("badge", badge)
+            }
+            (_, Some(freezes)) => {
+                sqlx::query("UPDATE users SET streak_freezes = streak_freezes + $2 WHERE id = $1")
+                    .bind(user_id)
+                    .bind(freezes)
+                    .execute(&mut *tx)
+                    .await?;
+                crate::i18n::Message::new("referral_streak_freezes").arg("count", freezes)
+            }
+            _ => continue,
+        };
+
+        sqlx::query(
+            "INSERT INTO notifications (user_id, type, message, message_key, message_args) VALUES ($1, 'referral_reward', $2, $3, $4)"
+        )
+        .bind(user_id)
+        .bind(message.text())
+        .bind(message.key)
+        .bind(&message.args)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        tracing::info!(user_id = %user_id, reward_id = %reward.id, "Referral reward granted");
+    }
+    Ok(())
+}
+
+/// `user_id`'s referral code, created on first use
+async fn referral_code(pool: &PgPool, user_id: Uuid) -> Result<String, sqlx::Error> {
+    if let Some(code) = sqlx::query_scalar::<_, Option<String>>("SELECT referral_code FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?
+    {
+        return Ok(code);
+    }
+
+    loop {
+        let result = sqlx::query_scalar::<_, String>(
+            r#"
+            UPDATE users SET referral_code = COALESCE(referral_code, $2)
+            WHERE id = $1
+            RETURNING referral_code
+            "#
+        )
+        .bind(user_id)
+        .bind(generate_code())
+        .fetch_one(pool)
+        .await;
+        match result {
+            // Another account already has it; draw again
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => continue,
+            result => return result,
+        }
+    }
+}
+
+// Your referral code and link, who signed up with it, and the rewards earned
+#[utoipa::path(
+    get,
+    path = "/api/v1/referrals/{user_id}",
+    tag = "users",
+    params(("user_id" = String, Path, description = "Your user ID")),
+    responses(
+        (status = 200, body = ReferralDashboard),
+        (status = 403, description = "Not your account")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_dashboard(
+    State(state): State<Arc<AppState>>,
+    user: AuthUser,
+    headers: HeaderMap,
+    Path(user_id): Path<String>,
+) -> Result<Json<ReferralDashboard>, (StatusCode, String)> {
+    let user_id = crate::settings::require_self(&user, &user_id)?;
+    let pool = state.pool.as_ref();
+
+    let code = referral_code(pool, user_id).await.map_err(db_error)?;
+
+    let (invited, pending, converted, flagged) = sqlx::query_as::<_, (i64, i64, i64, i64)>(
+        r#"
+        SELECT
+            COUNT(*),
+            COUNT(*) FILTER (WHERE status = 'pending'),
+            COUNT(*) FILTER (WHERE status = 'converted'),
+            COUNT(*) FILTER (WHERE status = 'flagged')
+        FROM referrals
+        WHERE referrer_id = $1
+        "#
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+
+    let referrals = sqlx::query_as::<_, ReferredUser>(
+        r#"
+        SELECT u.username, r.status, r.created_at, r.converted_at
+        FROM referrals r
+        JOIN users u ON u.id = r.referred_id
+        WHERE r.referrer_id = $1
+        ORDER BY r.created_at DESC
+        LIMIT $2
+        "#
+    )
+    .bind(user_id)
+    .bind(DASHBOARD_REFERRALS)
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)?;
+
+    let rewards = sqlx::query_as::<_, EarnedReward>(
+        r#"
+        SELECT rr.id, rr.referrals_required, rr.kind, rr.badge, rr.streak_freezes, g.granted_at
+        FROM referral_reward_grants g
+        JOIN referral_rewards rr ON rr.id = g.reward_id
+        WHERE g.user_id = $1
+        ORDER BY g.granted_at
+        "#
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)?;
+
+    let next_reward = sqlx::query_as::<_, ReferralReward>(
+        r#"
+        SELECT id, referrals_required, kind, badge, streak_freezes
+        FROM referral_rewards rr
+        WHERE NOT EXISTS (SELECT 1 FROM referral_reward_grants g WHERE g.reward_id = rr.id AND g.user_id = $1)
+        ORDER BY referrals_required
+        LIMIT 1
+        "#
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?;
+
+    let streak_freezes = sqlx::query_scalar::<_, i32>("SELECT streak_freezes FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .map_err(db_error)?;
+
+    let badges = sqlx::query_scalar::<_, String>("SELECT badge FROM user_badges WHERE user_id = $1 ORDER BY awarded_at")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(ReferralDashboard {
+        link: format!("{}/signup?ref={}", crate::syndication::public_base_url(&headers), code),
+        code,
+        invited,
+        pending,
+        converted,
+        flagged,
+        referrals,
+        rewards,
+        next_reward,
+        streak_freezes,
+        badges,
+    }))
+}
+
+// Badges shown on a profile
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/{user_id}/badges",
+    tag = "users",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200, body = [UserBadge]))
+)]
+pub async fn get_badges(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<Vec<UserBadge>>, (StatusCode, String)> {
+    let badges = sqlx::query_as::<_, UserBadge>(
+        "SELECT badge, awarded_at FROM user_badges WHERE user_id = $1 ORDER BY awarded_at"
+    )
+    .bind(user_id)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+    Ok(Json(badges))
+}
+
+// List referral reward tiers
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/referral-rewards",
+    tag = "admin",
+    responses((status = 200, body = [ReferralReward]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_rewards(
+    State(state): State<Arc<AppState>>,
+    _admin: Permitted<CanManagePlatform>,
+) -> Result<Json<Vec<ReferralReward>>, (StatusCode, String)> {
+    let rewards = sqlx::query_as::<_, ReferralReward>(
+        "SELECT id, referrals_required, kind, badge, streak_freezes FROM referral_rewards ORDER BY referrals_required"
+    )
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+    Ok(Json(rewards))
+}
+
+// Add a referral reward tier. Referrers who already qualify get it on the next sweep.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/referral-rewards",
+    tag = "admin",
+    request_body = CreateRewardRequest,
+    responses(
+        (status = 200, body = ReferralReward),
+        (status = 400, description = "Invalid reward"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn create_reward(
+    State(state): State<Arc<AppState>>,
+    admin: Permitted<CanManagePlatform>,
+    Json(payload): Json<CreateRewardRequest>,
+) -> Result<Json<ReferralReward>, (StatusCode, String)> {
+    if payload.referrals_required < 1 {
+        return Err((StatusCode::BAD_REQUEST, "referrals_required must be at least 1".to_string()));
+    }
+    let (badge, streak_freezes) = match payload.kind.as_str() {
+        "badge" => {
+            let badge = payload.badge.as_deref().map(str::trim).unwrap_or_default().to_lowercase();
+            if badge.is_empty() || badge.chars().count() > MAX_BADGE_LENGTH {
+                return Err((StatusCode::BAD_REQUEST, format!("badge must be 1-{} characters", MAX_BADGE_LENGTH)));
+            }
+            (Some(badge), None)
+        }
+        "streak_freezes" => match payload.streak_freezes {
+            Some(freezes) if freezes > 0 => (None, Some(freezes)),
+            _ => return Err((StatusCode::BAD_REQUEST, "streak_freezes must be at least 1".to_string())),
+        },
+        _ => return Err((StatusCode::BAD_REQUEST, "kind must be badge or streak_freezes".to_string())),
+    };
+
+    let reward = sqlx::query_as::<_, ReferralReward>(
+        r#"
+        INSERT INTO referral_rewards (referrals_required, kind, badge, streak_freezes)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, referrals_required, kind, badge, streak_freezes
+        "#
+    )
+    .bind(payload.referrals_required)
+    .bind(&payload.kind)
+    .bind(&badge)
+    .bind(streak_freezes)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "create_referral_reward".to_string(),
+        None,
+        Some("referral_reward".to_string()),
+        Some(reward.id),
+        serde_json::json!({
+            "referrals_required": reward.referrals_required,
+            "kind": reward.kind,
+            "badge": reward.badge,
+            "streak_freezes": reward.streak_freezes,
+        }),
+    )
+    .await;
+
+    Ok(Json(reward))
+}
+
+// Remove a referral reward tier. Badges and streak freezes already handed out are kept.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/referral-rewards/{reward_id}",
+    tag = "admin",
+    params(("reward_id" = Uuid, Path, description = "Reward ID")),
+    responses(
+        (status = 204, description = "Reward removed"),
+        (status = 404, description = "Reward not found"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn delete_reward(
+    State(state): State<Arc<AppState>>,
+    admin: Permitted<CanManagePlatform>,
+    Path(reward_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let removed = sqlx::query("DELETE FROM referral_rewards WHERE id = $1")
+        .bind(reward_id)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(db_error)?
+        .rows_affected();
+    if removed == 0 {
+        return Err((StatusCode::NOT_FOUND, "Reward not found".to_string()));
+    }
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "delete_referral_reward".to_string(),
+        None,
+        Some("referral_reward".to_string()),
+        Some(reward_id),
+        serde_json::json!({}),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,184 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::permissions::{CanViewAnalytics, Permitted};
+use crate::AppState;
+
+// Signup-week cohort retention. A user counts as active in a week when they
+// logged in, viewed a story or sent a message (user_activity_weeks, migration
+// 050). The compute_retention job rebuilds the recent cohorts once a day;
+// older cohorts keep their last figures.
+
+// Signup weeks recomputed on each run
+const RECOMPUTE_WEEKS: i32 = 26;
+const DEFAULT_COHORTS: i64 = 12;
+const MAX_COHORTS: i64 = 104;
+
+/// Note that `user_id` was active this week (logins; views and messages are
+/// recorded by triggers)
+pub async fn record_activity(pool: &PgPool, user_id: Uuid) {
+    let result = sqlx::query(
+        "INSERT INTO user_activity_weeks (user_id, week) VALUES ($1, date_trunc('week', NOW())::date) ON CONFLICT DO NOTHING",
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await;
+    if let Err(e) = result {
+        eprintln!("Failed to record activity for {}: {}", user_id, e);
+    }
+}
+
+/// Job body: recompute retention for the last RECOMPUTE_WEEKS signup weeks,
+/// one row per cohort and week since signup, up to the current week
+pub async fn compute(pool: &PgPool) -> Result<Option<serde_json::Value>, String> {
+    let rows = sqlx::query(
+        r#"
+        WITH cohorts AS (
+            SELECT id, date_trunc('week', created_at)::date AS cohort_week
+            FROM users
+            WHERE NOT is_bot
+              AND created_at >= date_trunc('week', NOW()) - make_interval(weeks => $1)
+        ),
+        sizes AS (
+            SELECT cohort_week, COUNT(*)::int AS cohort_size FROM cohorts GROUP BY cohort_week
+        ),
+        activity AS (
+            SELECT c.cohort_week, (a.week - c.cohort_week) / 7 AS week_offset, COUNT(*)::int AS active_users
+            FROM cohorts c
+            JOIN user_activity_weeks a ON a.user_id = c.id AND a.week >= c.cohort_week
+            GROUP BY 1, 2
+        )
+        INSERT INTO retention_cohorts (cohort_week, week_offset, cohort_size, active_users, computed_at)
+        SELECT s.cohort_week, o.week_offset, s.cohort_size, COALESCE(a.active_users, 0), NOW()
+        FROM sizes s
+        CROSS JOIN LATERAL generate_series(0, (date_trunc('week', NOW())::date - s.cohort_week) / 7) AS o(week_offset)
+        LEFT JOIN activity a ON a.cohort_week = s.cohort_week AND a.week_offset = o.week_offset
+        ON CONFLICT (cohort_week, week_offset) DO UPDATE
+        SET cohort_size = EXCLUDED.cohort_size,
+            active_users = EXCLUDED.active_users,
+            computed_at = EXCLUDED.computed_at
+        "#
+    )
+    .bind(RECOMPUTE_WEEKS)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .rows_affected();
+
+    Ok(Some(serde_json::json!({ "rows_written": rows })))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RetentionQuery {
+    /// Most recent signup weeks to return (default 12, max 104)
+    weeks: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RetentionWeek {
+    /// Weeks since signup; 0 is the signup week
+    week_offset: i32,
+    active_users: i32,
+    /// Share of the cohort active that week, in percent
+    retention_percentage: f64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RetentionCohort {
+    /// Monday of the signup week
+    cohort_week: NaiveDate,
+    cohort_size: i32,
+    weeks: Vec<RetentionWeek>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RetentionResponse {
+    /// Newest signup week first
+    cohorts: Vec<RetentionCohort>,
+    /// When the job last ran; null before its first run
+    #[serde(with = "crate::timestamps::option")]
+    computed_at: Option<NaiveDateTime>,
+}
+
+#[derive(sqlx::FromRow)]
+struct CohortRow {
+    cohort_week: NaiveDate,
+    week_offset: i32,
+    cohort_size: i32,
+    active_users: i32,
+}
+
+// Weekly retention of signup cohorts
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/analytics/retention",
+    tag = "admin",
+    params(RetentionQuery),
+    responses((status = 200, body = RetentionResponse), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_retention(
+    _admin: Permitted<CanViewAnalytics>,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<RetentionQuery>,
+) -> Result<Json<RetentionResponse>, (StatusCode, String)> {
+    let weeks = params.weeks.unwrap_or(DEFAULT_COHORTS).clamp(1, MAX_COHORTS);
+    let pool = state.read_pool();
+    let internal = |e: sqlx::Error| {
+        eprintln!("Retention error: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch retention".to_string())
+    };
+
+    let rows = sqlx::query_as::<_, CohortRow>(
+        r#"
+        SELECT cohort_week, week_offset, cohort_size, active_users
+        FROM retention_cohorts
+        WHERE cohort_week >= date_trunc('week', NOW())::date - make_interval(weeks => $1)
+        ORDER BY cohort_week DESC, week_offset
+        "#
+    )
+    .bind((weeks - 1) as i32)
+    .fetch_all(pool)
+    .await
+    .map_err(internal)?;
+
+    let computed_at = sqlx::query_scalar::<_, Option<NaiveDateTime>>("SELECT MAX(computed_at) FROM retention_cohorts")
+        .fetch_one(pool)
+        .await
+        .map_err(internal)?;
+
+    let mut cohorts: Vec<RetentionCohort> = Vec::new();
+    for row in rows {
+        if cohorts.last().is_none_or(|c| c.cohort_week != row.cohort_week) {
+            cohorts.push(RetentionCohort {
+                cohort_week: row.cohort_week,
+                cohort_size: row.cohort_size,
+                weeks: Vec::new(),
+            });
+        }
+        let retention_percentage = if row.cohort_size > 0 {
+            row.active_users as f64 / row.cohort_size as f64 * 100.0
+        } else {
+            0.0
+        };
+        if let Some(cohort) = cohorts.last_mut() {
+            cohort.weeks.push(RetentionWeek {
+                week_offset: row.week_offset,
+                active_users: row.active_users,
+                retention_percentage,
+            });
+        }
+    }
+
+    Ok(Json(RetentionResponse { cohorts, computed_at }))
+}
@@ -0,0 +1,122 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::websocket::{self, WsMessage};
+use crate::AppState;
+
+// Room retention (migration 067). A group's owner picks how long the room
+// keeps messages, e.g. a week. ExpirationService::apply_room_retention gives
+// messages reaching that age an expires_at once per pass, so from there they
+// take the same path as self-destructing messages: a TimeToExpire warning,
+// retention for members who saved them, then deletion. The policy is part of
+// the room's metadata (ChatRoomResponse.retention_seconds).
+
+pub const MIN_RETENTION_SECONDS: i32 = 60 * 60;
+pub const MAX_RETENTION_SECONDS: i32 = 365 * 24 * 60 * 60;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RoomRetention {
+    pub chat_room_id: Uuid,
+    pub retention_seconds: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetRetentionRequest {
+    /// How long messages stay in the room after they're sent; null keeps them
+    pub retention_seconds: Option<i32>,
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    eprintln!("❌ Room retention query failed: {:?}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+}
+
+/// Refuse anyone but the owner of a group chat: the creator of a private
+/// group or channel, or the member holding the owner role in a community
+async fn require_group_owner(pool: &PgPool, chat_room_id: Uuid, user_id: Uuid) -> Result<(), (StatusCode, String)> {
+    let (is_group, is_owner) = sqlx::query_as::<_, (bool, bool)>(
+        r#"
+        SELECT r.is_group,
+               cm.role = 'owner' OR (r.room_type <> 'public' AND r.created_by = $2)
+        FROM chat_rooms r
+        JOIN chat_members cm ON cm.chat_room_id = r.id AND cm.user_id = $2
+        WHERE r.id = $1
+        "#
+    )
+    .bind(chat_room_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::FORBIDDEN, "You are not a member of this chat".to_string()))?;
+
+    if !is_group {
+        return Err((StatusCode::BAD_REQUEST, "Retention can only be set on group chats".to_string()));
+    }
+    if !is_owner {
+        return Err((StatusCode::FORBIDDEN, "Only the group's owner can change retention".to_string()));
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/chats/{chat_room_id}/retention",
+    tag = "chat",
+    params(("chat_room_id" = Uuid, Path, description = "Chat room ID")),
+    request_body = SetRetentionRequest,
+    responses(
+        (status = 200, body = RoomRetention),
+        (status = 400, description = "Not a group chat, or retention out of range"),
+        (status = 403, description = "Not the group's owner"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn set_retention(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(chat_room_id): Path<Uuid>,
+    Json(payload): Json<SetRetentionRequest>,
+) -> Result<Json<RoomRetention>, (StatusCode, String)> {
+    if let Some(seconds) = payload.retention_seconds {
+        if !(MIN_RETENTION_SECONDS..=MAX_RETENTION_SECONDS).contains(&seconds) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "retention_seconds must be between {} and {}",
+                    MIN_RETENTION_SECONDS, MAX_RETENTION_SECONDS
+                ),
+            ));
+        }
+    }
+    require_group_owner(&state.pool, chat_room_id, user.id).await?;
+
+    sqlx::query("UPDATE chat_rooms SET retention_seconds = $2 WHERE id = $1")
+        .bind(chat_room_id)
+        .bind(payload.retention_seconds)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+
+    let event = WsMessage::RoomRetentionChanged {
+        chat_room_id,
+        retention_seconds: payload.retention_seconds,
+        changed_by: user.id,
+    };
+    websocket::broadcast_to_room(&state.pool, &state.connections, chat_room_id, &event).await;
+
+    Ok(Json(RoomRetention {
+        chat_room_id,
+        retention_seconds: payload.retention_seconds,
+    }))
+}
@@ -0,0 +1,673 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::permissions::{CanManagePlatform, Permitted};
+use crate::redis_client::RedisClient;
+use crate::AppState;
+
+// Tunables that ops can change at runtime. Every setting is declared in
+// DEFINITIONS with its type, bounds and default; runtime_settings (migration
+// 054) only holds overrides. Hot paths read the overrides from memory, which
+// every server refreshes from Redis (falling back to the database) every few
+// seconds, so a change reaches all servers within REFRESH_INTERVAL.
+
+pub const STORY_TTL_HOURS: &str = "stories.ttl_hours";
+pub const AD_STORY_INTERVAL: &str = "ads.story_interval";
+pub const AD_REVIEW_SLA_HOURS: &str = "ads.review_sla_hours";
+pub const BOOST_CPM_USD: &str = "boosts.cpm_usd";
+pub const SIGNUP_INVITE_ONLY: &str = "signup.invite_only";
+pub const INVITES_PER_USER: &str = "invites.per_user";
+pub const FEED_RECENCY_POINTS: &str = "feed.recency_points";
+pub const FEED_RECENCY_DECAY_HOURS: &str = "feed.recency_decay_hours";
+pub const FEED_FOLLOWING_BONUS: &str = "feed.following_bonus";
+pub const MAX_IMAGE_MB: &str = "uploads.max_image_mb";
+pub const MAX_GIF_MB: &str = "uploads.max_gif_mb";
+pub const MAX_VIDEO_MB: &str = "uploads.max_video_mb";
+pub const MAX_REQUEST_KB: &str = "requests.max_body_kb";
+pub const MAX_UPLOAD_REQUEST_MB: &str = "requests.max_upload_mb";
+pub const WS_BUFFER_SIZE: &str = "websocket.buffer_size";
+pub const WS_DISCONNECT_ON_LAG: &str = "websocket.disconnect_on_lag";
+pub const WS_FRAMES_PER_MINUTE: &str = "websocket.frames_per_minute";
+pub const QUOTA_STORIES_PER_DAY: &str = "quotas.stories_per_day";
+pub const QUOTA_CHATS_PER_HOUR: &str = "quotas.chats_per_hour";
+pub const QUOTA_COMMENTS_PER_DAY: &str = "quotas.comments_per_day";
+pub const QUOTA_COMMENT_MAX_LENGTH: &str = "quotas.comment_max_length";
+pub const QUOTA_UPLOAD_MB_PER_DAY: &str = "quotas.upload_mb_per_day";
+pub const QUOTA_CHAT_EXPORTS_PER_DAY: &str = "quotas.chat_exports_per_day";
+
+const CACHE_KEY: &str = "runtime_settings:overrides";
+const CACHE_TTL_SECONDS: u64 = 5 * 60;
+const REFRESH_INTERVAL: Duration = Duration::from_secs(15);
+const DEFAULT_HISTORY_LIMIT: i64 = 50;
+const MAX_HISTORY_LIMIT: i64 = 500;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Integer,
+    Float,
+}
+
+struct Definition {
+    key: &'static str,
+    description: &'static str,
+    kind: Kind,
+    default: f64,
+    min: f64,
+    max: f64,
+}
+
+// Upload limits stop at 100 MB, body_limit::HARD_LIMIT_BYTES
+const DEFINITIONS: &[Definition] = &[
+    Definition {
+        key: STORY_TTL_HOURS,
+        description: "Hours a new story stays up",
+        kind: Kind::Integer,
+        default: 24.0,
+        min: 1.0,
+        max: 168.0,
+    },
+    Definition {
+        key: AD_STORY_INTERVAL,
+        description: "Stories between ads in the story feed; 0 turns feed ads off",
+        kind: Kind::Integer,
+        default: 2.0,
+        min: 0.0,
+        max: 100.0,
+    },
+    Definition {
+        key: AD_REVIEW_SLA_HOURS,
+        description: "Hours a paid ad may wait for review before it counts as overdue",
+        kind: Kind::Integer,
+        default: 24.0,
+        min: 1.0,
+        max: 168.0,
+    },
+    Definition {
+        key: BOOST_CPM_USD,
+        description: "Price of a story boost per thousand impressions, in USD",
+        kind: Kind::Float,
+        default: 5.0,
+        min: 0.1,
+        max: 100.0,
+    },
+    Definition {
+        key: SIGNUP_INVITE_ONLY,
+        description: "1 to require an invite code to sign up, 0 to let anyone sign up",
+        kind: Kind::Integer,
+        default: 0.0,
+        min: 0.0,
+        max: 1.0,
+    },
+    Definition {
+        key: INVITES_PER_USER,
+        description: "Invite codes each user can create, unless an admin gave them their own allowance",
+        kind: Kind::Integer,
+        default: 3.0,
+        min: 0.0,
+        max: 1000.0,
+    },
+    Definition {
+        key: FEED_RECENCY_POINTS,
+        description: "Feed score bonus for a brand new story",
+        kind: Kind::Float,
+        default: 10.0,
+        min: 0.0,
+        max: 100.0,
+    },
+    Definition {
+        key: FEED_RECENCY_DECAY_HOURS,
+        description: "Hours until the recency bonus has decayed to zero",
+        kind: Kind::Float,
+        default: 168.0,
+        min: 1.0,
+        max: 168.0,
+    },
+    Definition {
+        key: FEED_FOLLOWING_BONUS,
+        description: "Feed score bonus for stories from followed accounts",
+        kind: Kind::Float,
+        default: 20.0,
+        min: 0.0,
+        max: 100.0,
+    },
+    Definition {
+        key: MAX_IMAGE_MB,
+        description: "Largest JPEG, PNG or WebP upload, in MB",
+        kind: Kind::Integer,
+        default: 20.0,
+        min: 1.0,
+        max: 100.0,
+    },
+    Definition {
+        key: MAX_GIF_MB,
+        // Animated GIFs get big fast; anything larger should be a video
+        description: "Largest GIF upload, in MB",
+        kind: Kind::Integer,
+        default: 15.0,
+        min: 1.0,
+        max: 100.0,
+    },
+    Definition {
+        key: MAX_VIDEO_MB,
+        description: "Largest video upload, in MB",
+        kind: Kind::Integer,
+        default: 100.0,
+        min: 1.0,
+        max: 100.0,
+    },
+    Definition {
+        key: MAX_REQUEST_KB,
+        description: "Largest request body outside the upload endpoints, in KB",
+        kind: Kind::Integer,
+        default: 256.0,
+        min: 16.0,
+        max: 10240.0,
+    },
+    Definition {
+        key: MAX_UPLOAD_REQUEST_MB,
+        // Room for the largest upload plus the rest of the multipart form
+        description: "Largest request body on the upload endpoints, in MB",
+        kind: Kind::Integer,
+        default: 100.0,
+        min: 1.0,
+        max: 100.0,
+    },
+    Definition {
+        key: WS_BUFFER_SIZE,
+        // The channel is sized when a user's first socket connects
+        description: "Events queued per connected user before a slow socket starts missing them",
+        kind: Kind::Integer,
+        default: 100.0,
+        min: 16.0,
+        max: 10000.0,
+    },
+    Definition {
+        key: WS_DISCONNECT_ON_LAG,
+        description: "1 to close a socket that fell behind so the client reconnects, 0 to keep it open and ask the client to resync",
+        kind: Kind::Integer,
+        default: 0.0,
+        min: 0.0,
+        max: 1.0,
+    },
+    Definition {
+        key: WS_FRAMES_PER_MINUTE,
+        description: "Frames a socket may send per minute before the rest are refused as rate_limited; 0 for no limit",
+        kind: Kind::Integer,
+        default: 600.0,
+        min: 0.0,
+        max: 100000.0,
+    },
+    Definition {
+        key: QUOTA_STORIES_PER_DAY,
+        description: "Stories a user can post per UTC day",
+        kind: Kind::Integer,
+        default: 50.0,
+        min: 1.0,
+        max: 10000.0,
+    },
+    Definition {
+        key: QUOTA_CHATS_PER_HOUR,
+        description: "New chats a user can start per hour",
+        kind: Kind::Integer,
+        default: 20.0,
+        min: 1.0,
+        max: 10000.0,
+    },
+    Definition {
+        key: QUOTA_COMMENTS_PER_DAY,
+        description: "Comments a user can post per UTC day",
+        kind: Kind::Integer,
+        default: 500.0,
+        min: 1.0,
+        max: 100000.0,
+    },
+    Definition {
+        key: QUOTA_COMMENT_MAX_LENGTH,
+        description: "Longest comment, in characters",
+        kind: Kind::Integer,
+        default: 1000.0,
+        min: 1.0,
+        max: 10000.0,
+    },
+    Definition {
+        key: QUOTA_UPLOAD_MB_PER_DAY,
+        description: "Media a user can upload per UTC day, in MB",
+        kind: Kind::Integer,
+        default: 500.0,
+        min: 1.0,
+        max: 100000.0,
+    },
+    Definition {
+        key: QUOTA_CHAT_EXPORTS_PER_DAY,
+        description: "Chat exports a user can request per UTC day",
+        kind: Kind::Integer,
+        default: 5.0,
+        min: 1.0,
+        max: 100.0,
+    },
+];
+
+fn definition(key: &str) -> Option<&'static Definition> {
+    DEFINITIONS.iter().find(|d| d.key == key)
+}
+
+fn overrides() -> &'static RwLock<HashMap<String, f64>> {
+    static OVERRIDES: OnceLock<RwLock<HashMap<String, f64>>> = OnceLock::new();
+    OVERRIDES.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+fn value(key: &str) -> f64 {
+    let Some(def) = definition(key) else {
+        tracing::warn!(key, "unknown runtime setting");
+        return 0.0;
+    };
+    overrides()
+        .read()
+        .ok()
+        .and_then(|o| o.get(key).copied())
+        .map_or(def.default, |v| v.clamp(def.min, def.max))
+}
+
+/// Current value of an integer setting
+pub fn int(key: &str) -> i64 {
+    value(key) as i64
+}
+
+/// Current value of a float setting
+pub fn float(key: &str) -> f64 {
+    value(key)
+}
+
+async fn load_overrides(pool: &PgPool) -> Result<HashMap<String, f64>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, (String, serde_json::Value)>("SELECT key, value FROM runtime_settings")
+        .fetch_all(pool)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value.as_f64()?)))
+        .collect())
+}
+
+fn store(loaded: HashMap<String, f64>) {
+    if let Ok(mut current) = overrides().write() {
+        *current = loaded;
+    }
+}
+
+/// Pull the overrides into memory, from Redis when they're cached there
+pub async fn refresh(pool: &PgPool, redis: &Mutex<RedisClient>) {
+    if let Some(cached) = crate::cache::get::<HashMap<String, f64>>(redis, CACHE_KEY).await {
+        store(cached);
+        return;
+    }
+    match load_overrides(pool).await {
+        Ok(loaded) => {
+            crate::cache::set(redis, CACHE_KEY, &loaded, CACHE_TTL_SECONDS).await;
+            store(loaded);
+        }
+        Err(e) => tracing::warn!("Failed to load runtime settings: {}", e),
+    }
+}
+
+/// Keep this server's copy of the overrides current
+pub async fn keep_fresh(pool: Arc<PgPool>, redis: Arc<Mutex<RedisClient>>) {
+    let mut ticker = tokio::time::interval(REFRESH_INTERVAL);
+    loop {
+        ticker.tick().await;
+        refresh(&pool, &redis).await;
+    }
+}
+
+// After a write: reload from the database and replace the Redis copy so
+// other servers see the change on their next refresh
+async fn publish(state: &AppState) {
+    match load_overrides(&state.pool).await {
+        Ok(loaded) => {
+            crate::cache::set(&state.redis, CACHE_KEY, &loaded, CACHE_TTL_SECONDS).await;
+            store(loaded);
+        }
+        Err(e) => {
+            tracing::warn!("Failed to reload runtime settings: {}", e);
+            let _ = state.redis.lock().await.cache_delete(CACHE_KEY).await;
+        }
+    }
+}
+
+// ============================================================================
+// ADMINISTRATION
+// ============================================================================
+
+fn json_value(def: &Definition, value: f64) -> serde_json::Value {
+    match def.kind {
+        Kind::Integer => serde_json::json!(value as i64),
+        Kind::Float => serde_json::json!(value),
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RuntimeSetting {
+    key: String,
+    description: String,
+    /// integer or float
+    value_type: String,
+    #[schema(value_type = f64)]
+    value: serde_json::Value,
+    #[schema(value_type = f64)]
+    default_value: serde_json::Value,
+    min: f64,
+    max: f64,
+    /// Whether an admin has changed the setting from its default
+    overridden: bool,
+    updated_by: Option<Uuid>,
+    #[serde(with = "crate::timestamps::option")]
+    updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(sqlx::FromRow)]
+struct OverrideRow {
+    key: String,
+    value: serde_json::Value,
+    updated_by: Option<Uuid>,
+    updated_at: NaiveDateTime,
+}
+
+fn describe(def: &Definition, row: Option<&OverrideRow>) -> RuntimeSetting {
+    let value = row.and_then(|r| r.value.as_f64()).map_or(def.default, |v| v.clamp(def.min, def.max));
+    RuntimeSetting {
+        key: def.key.to_string(),
+        description: def.description.to_string(),
+        value_type: match def.kind {
+            Kind::Integer => "integer",
+            Kind::Float => "float",
+        }
+        .to_string(),
+        value: json_value(def, value),
+        default_value: json_value(def, def.default),
+        min: def.min,
+        max: def.max,
+        overridden: row.is_some(),
+        updated_by: row.and_then(|r| r.updated_by),
+        updated_at: row.map(|r| r.updated_at),
+    }
+}
+
+fn known(key: &str) -> Result<&'static Definition, (StatusCode, String)> {
+    definition(key).ok_or((StatusCode::NOT_FOUND, "Unknown setting".to_string()))
+}
+
+async fn current_row(pool: &PgPool, key: &str) -> Result<Option<OverrideRow>, (StatusCode, String)> {
+    sqlx::query_as::<_, OverrideRow>("SELECT key, value, updated_by, updated_at FROM runtime_settings WHERE key = $1")
+        .bind(key)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+// List every runtime setting with its current value
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/settings",
+    tag = "admin",
+    responses((status = 200, body = [RuntimeSetting]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_settings(
+    State(state): State<Arc<AppState>>,
+    _admin: Permitted<CanManagePlatform>,
+) -> Result<Json<Vec<RuntimeSetting>>, (StatusCode, String)> {
+    let rows = sqlx::query_as::<_, OverrideRow>("SELECT key, value, updated_by, updated_at FROM runtime_settings")
+        .fetch_all(state.pool.as_ref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let settings = DEFINITIONS
+        .iter()
+        .map(|def| describe(def, rows.iter().find(|r| r.key == def.key)))
+        .collect();
+    Ok(Json(settings))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateSettingInput {
+    value: f64,
+    /// Why the setting changed, kept in its history
+    reason: Option<String>,
+}
+
+// Change a runtime setting
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/settings/{key}",
+    tag = "admin",
+    params(("key" = String, Path, description = "Setting key")),
+    request_body = UpdateSettingInput,
+    responses(
+        (status = 200, body = RuntimeSetting),
+        (status = 400, description = "Value has the wrong type or is out of bounds"),
+        (status = 404, description = "Unknown setting"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_setting(
+    State(state): State<Arc<AppState>>,
+    admin: Permitted<CanManagePlatform>,
+    Path(key): Path<String>,
+    Json(payload): Json<UpdateSettingInput>,
+) -> Result<Json<RuntimeSetting>, (StatusCode, String)> {
+    let def = known(&key)?;
+    if def.kind == Kind::Integer && payload.value.fract() != 0.0 {
+        return Err((StatusCode::BAD_REQUEST, format!("{} must be a whole number", key)));
+    }
+    if !(def.min..=def.max).contains(&payload.value) {
+        return Err((StatusCode::BAD_REQUEST, format!("{} must be between {} and {}", key, def.min, def.max)));
+    }
+
+    let new_value = json_value(def, payload.value);
+    let old_value = change(&state, def, Some(&new_value), payload.reason.as_deref(), admin.0.id).await?;
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "update_runtime_setting".to_string(),
+        None,
+        Some("runtime_setting".to_string()),
+        None,
+        serde_json::json!({ "key": key, "old_value": old_value, "new_value": new_value, "reason": payload.reason }),
+    )
+    .await;
+
+    let row = current_row(&state.pool, def.key).await?;
+    Ok(Json(describe(def, row.as_ref())))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ResetSettingQuery {
+    /// Why the setting was reset, kept in its history
+    reason: Option<String>,
+}
+
+// Put a runtime setting back on its default
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/settings/{key}",
+    tag = "admin",
+    params(("key" = String, Path, description = "Setting key"), ResetSettingQuery),
+    responses(
+        (status = 200, body = RuntimeSetting),
+        (status = 404, description = "Unknown setting"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn reset_setting(
+    State(state): State<Arc<AppState>>,
+    admin: Permitted<CanManagePlatform>,
+    Path(key): Path<String>,
+    Query(params): Query<ResetSettingQuery>,
+) -> Result<Json<RuntimeSetting>, (StatusCode, String)> {
+    let def = known(&key)?;
+    let old_value = change(&state, def, None, params.reason.as_deref(), admin.0.id).await?;
+
+    if old_value.is_some() {
+        crate::admin::log_admin_action(
+            &state,
+            admin.0.id,
+            "reset_runtime_setting".to_string(),
+            None,
+            Some("runtime_setting".to_string()),
+            None,
+            serde_json::json!({ "key": key, "old_value": old_value, "reason": params.reason }),
+        )
+        .await;
+    }
+
+    Ok(Json(describe(def, None)))
+}
+
+// Write or remove an override along with its history row. Returns the
+// previous override; resetting a setting that is already on its default
+// changes nothing.
+async fn change(
+    state: &AppState,
+    def: &Definition,
+    new_value: Option<&serde_json::Value>,
+    reason: Option<&str>,
+    admin_id: Uuid,
+) -> Result<Option<serde_json::Value>, (StatusCode, String)> {
+    let internal = |e: sqlx::Error| {
+        eprintln!("Runtime setting update failed: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update setting".to_string())
+    };
+
+    let mut tx = state.pool.begin().await.map_err(internal)?;
+    let old_value = sqlx::query_scalar::<_, serde_json::Value>("SELECT value FROM runtime_settings WHERE key = $1 FOR UPDATE")
+        .bind(def.key)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(internal)?;
+    if old_value.is_none() && new_value.is_none() {
+        return Ok(None);
+    }
+
+    match new_value {
+        Some(value) => {
+            sqlx::query(
+                r#"
+                INSERT INTO runtime_settings (key, value, updated_by, updated_at)
+                VALUES ($1, $2, $3, NOW())
+                ON CONFLICT (key) DO UPDATE
+                SET value = EXCLUDED.value, updated_by = EXCLUDED.updated_by, updated_at = EXCLUDED.updated_at
+                "#
+            )
+            .bind(def.key)
+            .bind(value)
+            .bind(admin_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(internal)?;
+        }
+        None => {
+            sqlx::query("DELETE FROM runtime_settings WHERE key = $1")
+                .bind(def.key)
+                .execute(&mut *tx)
+                .await
+                .map_err(internal)?;
+        }
+    }
+
+    sqlx::query(
+        "INSERT INTO runtime_setting_changes (key, old_value, new_value, reason, changed_by) VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(def.key)
+    .bind(&old_value)
+    .bind(new_value)
+    .bind(reason)
+    .bind(admin_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(internal)?;
+
+    tx.commit().await.map_err(internal)?;
+    publish(state).await;
+    Ok(old_value)
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SettingHistoryQuery {
+    /// Changes to return, newest first (default 50, max 500)
+    limit: Option<i64>,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct RuntimeSettingChange {
+    id: Uuid,
+    key: String,
+    /// Null when the default was in effect
+    #[schema(value_type = Option<f64>)]
+    old_value: Option<serde_json::Value>,
+    /// Null when the setting was reset to its default
+    #[schema(value_type = Option<f64>)]
+    new_value: Option<serde_json::Value>,
+    reason: Option<String>,
+    changed_by: Option<Uuid>,
+    changed_by_username: Option<String>,
+    #[serde(with = "crate::timestamps")]
+    changed_at: NaiveDateTime,
+}
+
+// Change history of a runtime setting
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/settings/{key}/history",
+    tag = "admin",
+    params(("key" = String, Path, description = "Setting key"), SettingHistoryQuery),
+    responses(
+        (status = 200, body = [RuntimeSettingChange]),
+        (status = 404, description = "Unknown setting"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_setting_history(
+    State(state): State<Arc<AppState>>,
+    _admin: Permitted<CanManagePlatform>,
+    Path(key): Path<String>,
+    Query(params): Query<SettingHistoryQuery>,
+) -> Result<Json<Vec<RuntimeSettingChange>>, (StatusCode, String)> {
+    let def = known(&key)?;
+    let limit = params.limit.unwrap_or(DEFAULT_HISTORY_LIMIT).clamp(1, MAX_HISTORY_LIMIT);
+
+    let changes = sqlx::query_as::<_, RuntimeSettingChange>(
+        r#"
+        SELECT c.id, c.key, c.old_value, c.new_value, c.reason, c.changed_by,
+               u.username AS changed_by_username, c.changed_at
+        FROM runtime_setting_changes c
+        LEFT JOIN users u ON u.id = c.changed_by
+        WHERE c.key = $1
+        ORDER BY c.changed_at DESC
+        LIMIT $2
+        "#
+    )
+    .bind(def.key)
+    .bind(limit)
+    .fetch_all(state.read_pool())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(changes))
+}
@@ -0,0 +1,416 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::i18n::Message;
+use crate::permissions::{CanModerateContent, Permitted};
+use crate::AppState;
+
+// Malware scanning for uploads, picked with UPLOAD_SCANNER:
+//   clamav - clamd INSTREAM over TCP at CLAMAV_ADDR (e.g. a clamav sidecar)
+//   http   - POST the raw bytes to SCAN_API_URL (e.g. a Lambda function URL),
+//            expects {"infected": bool, "signature": "..."}
+// Leave it unset to store uploads unscanned. When the scanner can't be reached
+// uploads are refused unless SCAN_FAIL_OPEN=true.
+
+const CLAMAV_CHUNK_SIZE: usize = 64 * 1024;
+const SCAN_TIMEOUT: Duration = Duration::from_secs(60);
+// clamd's default StreamMaxLength
+const DEFAULT_MAX_SCAN_BYTES: usize = 25 * 1024 * 1024;
+
+pub const STATUS_UNSCANNED: &str = "unscanned";
+pub const STATUS_CLEAN: &str = "clean";
+pub const STATUS_INFECTED: &str = "infected";
+
+#[derive(Debug, Clone, Copy)]
+enum Scanner {
+    ClamAv,
+    Http,
+}
+
+impl Scanner {
+    fn from_env() -> Option<Scanner> {
+        match std::env::var("UPLOAD_SCANNER").ok()?.to_lowercase().as_str() {
+            "clamav" => Some(Scanner::ClamAv),
+            "http" => Some(Scanner::Http),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Verdict {
+    Clean,
+    Infected(String),
+    /// Scanning is off, or the file is too large for the scanner
+    Skipped,
+}
+
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(SCAN_TIMEOUT)
+            .build()
+            .expect("Failed to build scanner HTTP client")
+    })
+}
+
+fn max_scan_bytes() -> usize {
+    std::env::var("SCAN_MAX_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_SCAN_BYTES)
+}
+
+fn fail_open() -> bool {
+    std::env::var("SCAN_FAIL_OPEN").is_ok_and(|v| v == "true")
+}
+
+async fn clamav_connect() -> Result<TcpStream, String> {
+    let addr = std::env::var("CLAMAV_ADDR").unwrap_or_else(|_| "127.0.0.1:3310".to_string());
+    let connect = async {
+        let mut stream = TcpStream::connect(&addr).await?;
+        stream.write_all(b"zINSTREAM\0").await?;
+        Ok::<_, std::io::Error>(stream)
+    };
+    tokio::time::timeout(SCAN_TIMEOUT, connect)
+        .await
+        .map_err(|_| "ClamAV connection timed out".to_string())?
+        .map_err(|e| format!("ClamAV at {} unavailable: {}", addr, e))
+}
+
+async fn clamav_send(stream: &mut TcpStream, data: &[u8]) -> Result<(), String> {
+    let send = async {
+        for chunk in data.chunks(CLAMAV_CHUNK_SIZE) {
+            stream.write_all(&(chunk.len() as u32).to_be_bytes()).await?;
+            stream.write_all(chunk).await?;
+        }
+        Ok::<_, std::io::Error>(())
+    };
+    tokio::time::timeout(SCAN_TIMEOUT, send)
+        .await
+        .map_err(|_| "ClamAV scan timed out".to_string())?
+        .map_err(|e| format!("ClamAV stream failed: {}", e))
+}
+
+async fn clamav_verdict(mut stream: TcpStream) -> Result<Verdict, String> {
+    let exchange = async {
+        stream.write_all(&0u32.to_be_bytes()).await?;
+        let mut reply = Vec::new();
+        stream.read_to_end(&mut reply).await?;
+        Ok::<_, std::io::Error>(reply)
+    };
+    let reply = tokio::time::timeout(SCAN_TIMEOUT, exchange)
+        .await
+        .map_err(|_| "ClamAV scan timed out".to_string())?
+        .map_err(|e| format!("ClamAV stream failed: {}", e))?;
+
+    // "stream: OK", "stream: <signature> FOUND" or "<reason> ERROR"
+    let reply = String::from_utf8_lossy(&reply);
+    let reply = reply.trim_end_matches('\0').trim();
+    if reply.ends_with("OK") {
+        Ok(Verdict::Clean)
+    } else if let Some(found) = reply.strip_suffix("FOUND") {
+        let signature = found.trim().trim_start_matches("stream:").trim();
+        Ok(Verdict::Infected(signature.to_string()))
+    } else {
+        Err(format!("ClamAV error: {}", reply))
+    }
+}
+
+async fn scan_http(data: Vec<u8>) -> Result<Verdict, String> {
+    #[derive(Deserialize)]
+    struct Response {
+        infected: bool,
+        signature: Option<String>,
+    }
+
+    let url = std::env::var("SCAN_API_URL").map_err(|_| "SCAN_API_URL is not set".to_string())?;
+    let response: Response = http_client()
+        .post(url)
+        .bearer_auth(std::env::var("SCAN_API_KEY").unwrap_or_default())
+        .header("Content-Type", "application/octet-stream")
+        .body(data)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Scan request failed: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Unexpected scan response: {}", e))?;
+
+    Ok(if response.infected {
+        Verdict::Infected(response.signature.unwrap_or_else(|| "unknown".to_string()))
+    } else {
+        Verdict::Clean
+    })
+}
+
+/// Scan an upload. Errors mean the scanner couldn't give a verdict and the
+/// upload must be refused (unless SCAN_FAIL_OPEN lets it through unscanned).
+pub async fn scan(data: &[u8]) -> Result<Verdict, String> {
+    let mut scan = StreamScan::start();
+    scan.feed(data).await;
+    scan.finish().await
+}
+
+/// A scan fed chunk by chunk while an upload streams in. ClamAV gets each
+/// chunk as it arrives; the HTTP scanner takes the file in one request, so
+/// its bytes are held until finish (at most SCAN_MAX_BYTES of them). Files
+/// over that size are not scanned, as with scan().
+pub struct StreamScan {
+    scanner: Option<Scanner>,
+    max_bytes: usize,
+    size: usize,
+    clamav: Option<TcpStream>,
+    buffer: Vec<u8>,
+    /// The first failure; reported by finish
+    error: Option<String>,
+}
+
+impl StreamScan {
+    pub fn start() -> Self {
+        Self {
+            scanner: Scanner::from_env(),
+            max_bytes: max_scan_bytes(),
+            size: 0,
+            clamav: None,
+            buffer: Vec::new(),
+            error: None,
+        }
+    }
+
+    pub async fn feed(&mut self, chunk: &[u8]) {
+        let Some(scanner) = self.scanner else {
+            return;
+        };
+        self.size += chunk.len();
+        if self.error.is_some() || self.size > self.max_bytes {
+            // Too large to scan: drop what was sent so far
+            self.clamav = None;
+            self.buffer = Vec::new();
+            return;
+        }
+
+        match scanner {
+            Scanner::ClamAv => {
+                let stream = match self.clamav.as_mut() {
+                    Some(stream) => stream,
+                    None => match clamav_connect().await {
+                        Ok(stream) => self.clamav.insert(stream),
+                        Err(e) => {
+                            self.error = Some(e);
+                            return;
+                        }
+                    },
+                };
+                if let Err(e) = clamav_send(stream, chunk).await {
+                    self.clamav = None;
+                    self.error = Some(e);
+                }
+            }
+            Scanner::Http => self.buffer.extend_from_slice(chunk),
+        }
+    }
+
+    pub async fn finish(self) -> Result<Verdict, String> {
+        let Some(scanner) = self.scanner else {
+            return Ok(Verdict::Skipped);
+        };
+        if self.size > self.max_bytes {
+            return Ok(Verdict::Skipped);
+        }
+
+        let result = match (self.error, scanner) {
+            (Some(e), _) => Err(e),
+            (None, Scanner::ClamAv) => match self.clamav {
+                Some(stream) => clamav_verdict(stream).await,
+                // Nothing was fed
+                None => match clamav_connect().await {
+                    Ok(stream) => clamav_verdict(stream).await,
+                    Err(e) => Err(e),
+                },
+            },
+            (None, Scanner::Http) => scan_http(self.buffer).await,
+        };
+
+        match result {
+            Err(e) if fail_open() => {
+                eprintln!("⚠️ Upload scan failed, storing unscanned: {}", e);
+                Ok(Verdict::Skipped)
+            }
+            result => result,
+        }
+    }
+}
+
+/// Notify admins and moderators that an infected upload was blocked
+pub async fn alert_admins(pool: &PgPool, sha256: &str, signature: &str, uploaded_by: Option<Uuid>) {
+    tracing::warn!(sha256, signature, ?uploaded_by, "Blocked infected upload");
+
+    let message = match uploaded_by {
+        Some(user_id) => Message::new("security_alert_from_user").arg("signature", signature).arg("user_id", user_id),
+        None => Message::new("security_alert").arg("signature", signature),
+    };
+    if let Err(e) = sqlx::query(
+        r#"
+        INSERT INTO notifications (user_id, type, from_user_id, message, message_key, message_args)
+        SELECT id, 'security_alert', $1, $2, $3, $4 FROM users WHERE role IN ('admin', 'moderator')
+        "#
+    )
+    .bind(uploaded_by)
+    .bind(message.text())
+    .bind(message.key)
+    .bind(&message.args)
+    .execute(pool)
+    .await
+    {
+        eprintln!("❌ Failed to alert admins about infected upload: {:?}", e);
+    }
+}
+
+// ============= Admin: quarantine =============
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct QuarantinedMedia {
+    pub sha256: String,
+    pub s3_key: String,
+    pub content_type: Option<String>,
+    pub size_bytes: i64,
+    pub scan_signature: Option<String>,
+    pub uploaded_by: Option<Uuid>,
+    pub uploaded_by_username: Option<String>,
+    #[serde(with = "crate::timestamps::option")]
+    pub scanned_at: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QuarantineQuery {
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct QuarantineResponse {
+    items: Vec<QuarantinedMedia>,
+    total: i64,
+    page: i64,
+    per_page: i64,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/media/quarantine",
+    tag = "admin",
+    params(QuarantineQuery),
+    responses((status = 200, body = QuarantineResponse), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_quarantine(
+    _admin: Permitted<CanModerateContent>,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<QuarantineQuery>,
+) -> Result<Json<QuarantineResponse>, StatusCode> {
+    let page = params.page.unwrap_or(1).max(1);
+    let per_page = params.per_page.unwrap_or(50).clamp(1, 100);
+    let offset = (page - 1) * per_page;
+
+    let items = sqlx::query_as::<_, QuarantinedMedia>(
+        r#"
+        SELECT m.sha256, m.s3_key, m.content_type, m.size_bytes, m.scan_signature,
+               m.uploaded_by, u.username AS uploaded_by_username, m.scanned_at
+        FROM media_objects m
+        LEFT JOIN users u ON u.id = m.uploaded_by
+        WHERE m.scan_status = 'infected'
+        ORDER BY m.scanned_at DESC
+        LIMIT $1 OFFSET $2
+        "#
+    )
+    .bind(per_page)
+    .bind(offset)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let total = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM media_objects WHERE scan_status = 'infected'")
+        .fetch_one(state.pool.as_ref())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(QuarantineResponse { items, total, page, per_page }))
+}
+
+// Release a false positive: move it out of quarantine so it can be used
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/media/quarantine/{sha256}/release",
+    tag = "admin",
+    params(("sha256" = String, Path, description = "Content hash")),
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 404, description = "Not in quarantine"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn release_quarantined(
+    admin: Permitted<CanModerateContent>,
+    State(state): State<Arc<AppState>>,
+    Path(sha256): Path<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let quarantine_key = sqlx::query_scalar::<_, String>(
+        "SELECT s3_key FROM media_objects WHERE sha256 = $1 AND scan_status = 'infected'"
+    )
+    .bind(&sha256)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Not in quarantine".to_string()))?;
+
+    let extension = quarantine_key.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("bin");
+    let released_key = crate::media::content_key(&sha256, extension);
+    state
+        .media_service
+        .copy_media(&quarantine_key, &released_key)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    sqlx::query(
+        "UPDATE media_objects SET s3_key = $2, scan_status = 'clean', scanned_at = NOW(), last_used_at = NOW() WHERE sha256 = $1"
+    )
+    .bind(&sha256)
+    .bind(&released_key)
+    .execute(state.pool.as_ref())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if let Err(e) = state.media_service.delete_media_batch(&[quarantine_key]).await {
+        eprintln!("Failed to remove released quarantine copy: {}", e);
+    }
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "release_quarantined_media".to_string(),
+        None,
+        Some("media".to_string()),
+        None,
+        serde_json::json!({ "sha256": sha256 }),
+    )
+    .await;
+
+    Ok(Json(serde_json::json!({ "sha256": sha256, "s3_key": released_key })))
+}
@@ -0,0 +1,229 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::media::MediaService;
+
+// Startup self-check. Before serving, make sure the environment is complete,
+// the database has been migrated and media storage takes writes, and stop
+// with a list of what to fix rather than failing requests later on. The
+// environment is checked before connecting to anything, the rest once the
+// pool and media service are up. Set STARTUP_CHECKS=warn to log the problems
+// and start anyway (e.g. locally without a bucket), or STARTUP_CHECKS=off to
+// skip the checks.
+//
+// SCHEMA lists objects the code calls by name, plus something from the newest
+// migration as a marker that migrations are current; move the marker along
+// with each migration the code depends on.
+
+const STORAGE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+
+enum ObjectKind {
+    Relation,
+    Function,
+}
+
+const SCHEMA: &[(&str, ObjectKind, &str)] = &[
+    ("users", ObjectKind::Relation, "000_initial_schema.sql"),
+    ("chat_rooms", ObjectKind::Relation, "001_messaging_schema.sql"),
+    ("chat_members", ObjectKind::Relation, "001_messaging_schema.sql"),
+    ("messages", ObjectKind::Relation, "001_messaging_schema.sql"),
+    ("stories", ObjectKind::Relation, "002_stories_schema.sql"),
+    ("popular_users", ObjectKind::Relation, "005_production_ready.sql"),
+    ("update_streak", ObjectKind::Function, "006_streaks.sql"),
+    ("find_direct_chat", ObjectKind::Function, "008_snapchat_features.sql"),
+    ("get_age_range", ObjectKind::Function, "012_ad_analytics.sql"),
+    ("background_jobs", ObjectKind::Relation, "014_background_jobs.sql"),
+    ("feature_flags", ObjectKind::Relation, "019_feature_flags.sql"),
+    ("media_objects", ObjectKind::Relation, "028_media_objects.sql"),
+    ("user_is_adult", ObjectKind::Function, "032_age_gating.sql"),
+    ("user_is_minor", ObjectKind::Function, "032_age_gating.sql"),
+    ("geo_blocked", ObjectKind::Function, "034_geo_takedowns.sql"),
+    ("runtime_settings", ObjectKind::Relation, "054_runtime_settings.sql"),
+    ("story_comment_audience", ObjectKind::Function, "064_comment_audience.sql"),
+    ("waitlist", ObjectKind::Relation, "074_waitlist.sql"),
+    ("activity_visible", ObjectKind::Function, "076_activity_status.sql"),
+    ("message_delivery_state", ObjectKind::Function, "077_message_delivery_state.sql"),
+    ("role_permissions", ObjectKind::Relation, "078_roles_permissions.sql"),
+    ("community_mod_logs", ObjectKind::Relation, "079_community_moderation.sql"),
+    ("appealable_sanctions", ObjectKind::Relation, "080_appeals.sql"),
+    ("idx_notifications_user_created", ObjectKind::Relation, "081_notification_paging.sql"),
+    ("announce_notification", ObjectKind::Function, "082_notification_events.sql"),
+];
+
+// An environment variable, required always or only when another one has
+// a given value
+struct Requirement {
+    name: &'static str,
+    when: Option<(&'static str, &'static str)>,
+    why: &'static str,
+}
+
+const ENVIRONMENT: &[Requirement] = &[
+    Requirement { name: "DATABASE_URL", when: None, why: "Postgres connection string" },
+    Requirement { name: "PUBLIC_BASE_URL", when: None, why: "public links would be built from the client's Host header" },
+    Requirement { name: "SCAN_API_URL", when: Some(("UPLOAD_SCANNER", "http")), why: "uploads are refused without a scanner to call" },
+    Requirement { name: "SMTP_URL", when: Some(("MAIL_PROVIDER", "smtp")), why: "no email can be sent" },
+    Requirement { name: "MAIL_API_URL", when: Some(("MAIL_PROVIDER", "http")), why: "no email can be sent" },
+    Requirement { name: "CAPTION_API_URL", when: Some(("CAPTION_PROVIDER", "http")), why: "alt text generation fails" },
+    Requirement { name: "CAPTION_API_KEY", when: Some(("CAPTION_PROVIDER", "openai")), why: "alt text generation fails" },
+    Requirement { name: "TRANSLATION_API_KEY", when: Some(("TRANSLATION_PROVIDER", "deepl")), why: "translation requests fail" },
+    Requirement { name: "TRANSLATION_API_KEY", when: Some(("TRANSLATION_PROVIDER", "google")), why: "translation requests fail" },
+    Requirement { name: "CAPTCHA_SECRET", when: Some(("CAPTCHA_PROVIDER", "turnstile")), why: "captchas go unchecked" },
+    Requirement { name: "CAPTCHA_SECRET", when: Some(("CAPTCHA_PROVIDER", "hcaptcha")), why: "captchas go unchecked" },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Strict,
+    Warn,
+    Off,
+}
+
+fn mode() -> Mode {
+    match std::env::var("STARTUP_CHECKS").unwrap_or_default().to_lowercase().as_str() {
+        "off" | "false" => Mode::Off,
+        "warn" => Mode::Warn,
+        _ => Mode::Strict,
+    }
+}
+
+fn env_set(name: &str) -> bool {
+    std::env::var(name).is_ok_and(|v| !v.trim().is_empty())
+}
+
+fn check_environment(problems: &mut Vec<String>) {
+    for requirement in ENVIRONMENT.iter().filter(|r| !env_set(r.name)) {
+        match requirement.when {
+            None => problems.push(format!("{} is not set ({})", requirement.name, requirement.why)),
+            Some((switch, value)) if std::env::var(switch).is_ok_and(|v| v.eq_ignore_ascii_case(value)) => {
+                problems.push(format!("{} is not set but {}={}: {}", requirement.name, switch, value, requirement.why));
+            }
+            Some(_) => {}
+        }
+    }
+    if crate::ad_billing::stripe_secret_key().is_some() && !env_set("STRIPE_WEBHOOK_SECRET") {
+        problems.push(
+            "STRIPE_WEBHOOK_SECRET is not set but STRIPE_SECRET_KEY is: Stripe webhooks are refused".to_string(),
+        );
+    }
+}
+
+async fn check_schema(pool: &PgPool, problems: &mut Vec<String>) {
+    let (relations, functions): (Vec<_>, Vec<_>) = SCHEMA.iter().partition(|(_, kind, _)| matches!(kind, ObjectKind::Relation));
+    let relations: Vec<&str> = relations.iter().map(|(name, _, _)| *name).collect();
+    let functions: Vec<&str> = functions.iter().map(|(name, _, _)| *name).collect();
+
+    let missing = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT name FROM unnest($1::text[]) AS name WHERE to_regclass(name) IS NULL
+        UNION ALL
+        SELECT name FROM unnest($2::text[]) AS name
+        WHERE NOT EXISTS (SELECT 1 FROM pg_proc p WHERE p.proname = name AND pg_function_is_visible(p.oid))
+        "#
+    )
+    .bind(&relations)
+    .bind(&functions)
+    .fetch_all(pool)
+    .await;
+
+    match missing {
+        Ok(missing) => {
+            for (name, kind, migration) in SCHEMA.iter().filter(|(name, _, _)| missing.iter().any(|m| m == name)) {
+                let kind = match kind {
+                    ObjectKind::Relation => "Relation",
+                    ObjectKind::Function => "Function",
+                };
+                problems.push(format!(
+                    "{} {} is missing; run migrations up to {} (./run_migrations.sh)",
+                    kind, name, migration
+                ));
+            }
+        }
+        Err(e) => problems.push(format!("Couldn't inspect the database schema: {}", e)),
+    }
+}
+
+async fn check_storage(media: &MediaService, problems: &mut Vec<String>) {
+    let storage = &media.storage;
+    let Ok(reachable) = tokio::time::timeout(STORAGE_TIMEOUT, storage.check()).await else {
+        problems.push(format!(
+            "{} didn't answer within {}s; check R2_ENDPOINT and network access",
+            storage.describe(),
+            STORAGE_TIMEOUT.as_secs()
+        ));
+        return;
+    };
+    if let Err(e) = reachable {
+        problems.push(format!(
+            "{} is unreachable ({}); check STORAGE_PROVIDER, S3_BUCKET_NAME, R2_ENDPOINT and the AWS_* credentials, or LOCAL_STORAGE_PATH",
+            storage.describe(),
+            e
+        ));
+        return;
+    }
+
+    // Uploads need write and delete access, which reaching the store doesn't prove
+    let probe_key = format!("selfcheck/{}", Uuid::new_v4());
+    if let Err(e) = storage.put(&probe_key, b"ok".to_vec(), &Default::default()).await {
+        problems.push(format!(
+            "{} is not writable ({}); S3 credentials need s3:PutObject",
+            storage.describe(),
+            e
+        ));
+        return;
+    }
+    match storage.delete(std::slice::from_ref(&probe_key)).await {
+        Ok(deleted) if deleted.contains(&probe_key) => {}
+        Ok(_) => problems.push(format!(
+            "{} doesn't allow deletes; S3 credentials need s3:DeleteObject",
+            storage.describe()
+        )),
+        Err(e) => problems.push(format!(
+            "{} doesn't allow deletes ({}); S3 credentials need s3:DeleteObject",
+            storage.describe(),
+            e
+        )),
+    }
+}
+
+/// Check the environment before anything connects. In strict mode (the
+/// default) the process exits when something is missing.
+pub fn environment() {
+    let mode = mode();
+    if mode == Mode::Off {
+        println!("⚠️ Startup checks skipped (STARTUP_CHECKS=off)");
+        return;
+    }
+    let mut problems = Vec::new();
+    check_environment(&mut problems);
+    report("Environment", &problems, mode);
+}
+
+/// Check the database schema and the media bucket once both are set up
+pub async fn dependencies(pool: &PgPool, media: &MediaService) {
+    let mode = mode();
+    if mode == Mode::Off {
+        return;
+    }
+    let mut problems = Vec::new();
+    check_schema(pool, &mut problems).await;
+    check_storage(media, &mut problems).await;
+    report("Schema and storage", &problems, mode);
+}
+
+fn report(phase: &str, problems: &[String], mode: Mode) {
+    if problems.is_empty() {
+        println!("✓ {} checks passed", phase);
+        return;
+    }
+
+    eprintln!("✗ {} checks found {} problem(s):", phase, problems.len());
+    for problem in problems {
+        eprintln!("  - {}", problem);
+    }
+    if mode == Mode::Strict {
+        eprintln!("Fix the above, or set STARTUP_CHECKS=warn to start anyway.");
+        std::process::exit(1);
+    }
+    eprintln!("⚠️ Starting anyway (STARTUP_CHECKS=warn)");
+}
@@ -4,35 +4,85 @@ use axum::{
     Json,
 };
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use std::sync::Arc;
 use crate::AppState;
+use crate::admin::AuthUser;
+use crate::quotas::UsageResponse;
+use chrono::{Datelike, NaiveDate, Utc};
 use argon2::{Argon2, PasswordHash, PasswordVerifier, PasswordHasher};
 use argon2::password_hash::SaltString;
 use rand_core::OsRng;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct UpdateUsernameRequest {
     pub username: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct UpdateEmailRequest {
     pub email: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct ChangePasswordRequest {
     pub current_password: String,
     pub new_password: String,
 }
 
-#[derive(Serialize)]
+// Accounts are for people aged 13 and up
+pub const MIN_AGE: i32 = 13;
+const MAX_AGE: i32 = 120;
+const GENDERS: &[&str] = &["female", "male", "non_binary", "other", "prefer_not_to_say"];
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct DemographicsResponse {
+    pub birthdate: Option<NaiveDate>,
+    pub gender: Option<String>,
+    /// ISO 3166-1 alpha-2
+    pub country: Option<String>,
+    /// Let ads be targeted by age range
+    pub share_age_with_ads: bool,
+    pub share_gender_with_ads: bool,
+    pub share_country_with_ads: bool,
+}
+
+/// Fields left out are unchanged; an empty string clears gender or country
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateDemographicsRequest {
+    /// Can only be set once; contact support to correct it
+    pub birthdate: Option<NaiveDate>,
+    /// female, male, non_binary, other or prefer_not_to_say
+    pub gender: Option<String>,
+    pub country: Option<String>,
+    pub share_age_with_ads: Option<bool>,
+    pub share_gender_with_ads: Option<bool>,
+    pub share_country_with_ads: Option<bool>,
+}
+
+/// Age in whole years on `today`
+pub fn age_on(birthdate: NaiveDate, today: NaiveDate) -> i32 {
+    let mut age = today.year() - birthdate.year();
+    if (today.month(), today.day()) < (birthdate.month(), birthdate.day()) {
+        age -= 1;
+    }
+    age
+}
+
+#[derive(Serialize, ToSchema)]
 pub struct UserSettingsResponse {
     pub username: String,
     pub email: String,
 }
 
 // Get user settings (username and email)
+#[utoipa::path(
+    get,
+    path = "/api/v1/settings/{user_id}",
+    tag = "settings",
+    params(("user_id" = String, Path, description = "User ID")),
+    responses((status = 200, body = UserSettingsResponse))
+)]
 pub async fn get_user_settings(
     State(state): State<Arc<AppState>>,
     Path(user_id): Path<String>,
@@ -56,17 +106,27 @@ pub async fn get_user_settings(
 }
 
 // Update username
+#[utoipa::path(
+    post,
+    path = "/api/v1/settings/{user_id}/username",
+    tag = "settings",
+    params(("user_id" = String, Path, description = "User ID")),
+    request_body = UpdateUsernameRequest,
+    responses(
+        (status = 200, description = "Success"),
+        (status = 400, description = "Username not allowed"),
+        (status = 409, description = "Username taken")
+    )
+)]
 pub async fn update_username(
     State(state): State<Arc<AppState>>,
     Path(user_id): Path<String>,
     Json(payload): Json<UpdateUsernameRequest>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, (StatusCode, String)> {
     let user_uuid = uuid::Uuid::parse_str(&user_id)
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid user ID".to_string()))?;
 
-    if payload.username.is_empty() || payload.username.len() > 30 {
-        return Err(StatusCode::BAD_REQUEST);
-    }
+    crate::username_policy::check(&state.pool, &payload.username, Some(user_uuid)).await?;
 
     // Check if username is already taken
     let existing = sqlx::query!(
@@ -76,10 +136,10 @@ pub async fn update_username(
     )
     .fetch_optional(&*state.pool)
     .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update username".to_string()))?;
 
     if existing.is_some() {
-        return Err(StatusCode::CONFLICT);
+        return Err((StatusCode::CONFLICT, "Username already taken".to_string()));
     }
 
     sqlx::query!(
@@ -89,12 +149,22 @@ pub async fn update_username(
     )
     .execute(&*state.pool)
     .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update username".to_string()))?;
+
+    crate::cache::invalidate_profile(&state.redis, user_uuid).await;
 
     Ok(StatusCode::OK)
 }
 
 // Update email
+#[utoipa::path(
+    post,
+    path = "/api/v1/settings/{user_id}/email",
+    tag = "settings",
+    params(("user_id" = String, Path, description = "User ID")),
+    request_body = UpdateEmailRequest,
+    responses((status = 200, description = "Success"))
+)]
 pub async fn update_email(
     State(state): State<Arc<AppState>>,
     Path(user_id): Path<String>,
@@ -135,10 +205,20 @@ pub async fn update_email(
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    crate::cache::invalidate_profile(&state.redis, user_uuid).await;
+
     Ok(StatusCode::OK)
 }
 
 // Change password
+#[utoipa::path(
+    post,
+    path = "/api/v1/settings/{user_id}/password",
+    tag = "settings",
+    params(("user_id" = String, Path, description = "User ID")),
+    request_body = ChangePasswordRequest,
+    responses((status = 200, description = "Success"))
+)]
 pub async fn change_password(
     State(state): State<Arc<AppState>>,
     Path(user_id): Path<String>,
@@ -192,6 +272,13 @@ pub async fn change_password(
 }
 
 // Delete account
+#[utoipa::path(
+    delete,
+    path = "/api/v1/settings/{user_id}/delete",
+    tag = "settings",
+    params(("user_id" = String, Path, description = "User ID")),
+    responses((status = 200, description = "Success"))
+)]
 pub async fn delete_account(
     State(state): State<Arc<AppState>>,
     Path(user_id): Path<String>,
@@ -208,5 +295,579 @@ pub async fn delete_account(
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    crate::cache::invalidate_profile(&state.redis, user_uuid).await;
+
     Ok(StatusCode::OK)
 }
+
+// Current usage against the per-user quotas
+#[utoipa::path(
+    get,
+    path = "/api/v1/settings/{user_id}/usage",
+    tag = "settings",
+    params(("user_id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, body = UsageResponse),
+        (status = 403, description = "Not your account"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_usage(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<String>,
+) -> Result<Json<UsageResponse>, (StatusCode, String)> {
+    let user_uuid = require_self(&user, &user_id)?;
+
+    let usage = crate::quotas::usage(&state.pool, user_uuid)
+        .await
+        .map_err(|e| {
+            eprintln!("❌ Usage lookup failed: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load usage".to_string())
+        })?;
+
+    Ok(Json(usage))
+}
+
+async fn fetch_demographics(pool: &sqlx::PgPool, user_id: uuid::Uuid) -> Result<DemographicsResponse, StatusCode> {
+    sqlx::query_as::<_, DemographicsResponse>(
+        r#"
+        SELECT birthdate, gender, country, share_age_with_ads, share_gender_with_ads, share_country_with_ads
+        FROM users WHERE id = $1
+        "#
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)
+}
+
+pub(crate) fn require_self(user: &AuthUser, user_id: &str) -> Result<uuid::Uuid, (StatusCode, String)> {
+    let user_uuid = uuid::Uuid::parse_str(user_id)
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid user ID".to_string()))?;
+    if user.id != user_uuid {
+        return Err((StatusCode::FORBIDDEN, "You can only manage your own settings".to_string()));
+    }
+    Ok(user_uuid)
+}
+
+// Birthdate, gender, country and whether each may be used for ad targeting
+#[utoipa::path(
+    get,
+    path = "/api/v1/settings/{user_id}/demographics",
+    tag = "settings",
+    params(("user_id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, body = DemographicsResponse),
+        (status = 403, description = "Not your account"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_demographics(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<String>,
+) -> Result<Json<DemographicsResponse>, (StatusCode, String)> {
+    let user_uuid = require_self(&user, &user_id)?;
+    fetch_demographics(&state.pool, user_uuid)
+        .await
+        .map(Json)
+        .map_err(|status| (status, "Failed to load demographics".to_string()))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/settings/{user_id}/demographics",
+    tag = "settings",
+    params(("user_id" = String, Path, description = "User ID")),
+    request_body = UpdateDemographicsRequest,
+    responses(
+        (status = 200, body = DemographicsResponse),
+        (status = 400, description = "Invalid birthdate, gender or country"),
+        (status = 403, description = "Not your account, or younger than 13"),
+        (status = 409, description = "Birthdate is already set"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_demographics(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<String>,
+    Json(payload): Json<UpdateDemographicsRequest>,
+) -> Result<Json<DemographicsResponse>, (StatusCode, String)> {
+    let user_uuid = require_self(&user, &user_id)?;
+    let current = fetch_demographics(&state.pool, user_uuid)
+        .await
+        .map_err(|status| (status, "Failed to load demographics".to_string()))?;
+
+    if let Some(birthdate) = payload.birthdate {
+        // Locked once set, so age-restricted features can rely on it
+        if current.birthdate.is_some_and(|existing| existing != birthdate) {
+            return Err((StatusCode::CONFLICT, "Birthdate is already set; contact support to correct it".to_string()));
+        }
+        let today = Utc::now().date_naive();
+        let age = age_on(birthdate, today);
+        if birthdate > today || age > MAX_AGE {
+            return Err((StatusCode::BAD_REQUEST, "Invalid birthdate".to_string()));
+        }
+        if age < MIN_AGE {
+            return Err((StatusCode::FORBIDDEN, format!("You must be at least {} to use this app", MIN_AGE)));
+        }
+    }
+
+    let gender = payload.gender.map(|g| g.trim().to_lowercase());
+    if let Some(g) = gender.as_deref().filter(|g| !g.is_empty()) {
+        if !GENDERS.contains(&g) {
+            return Err((StatusCode::BAD_REQUEST, format!("gender must be one of: {}", GENDERS.join(", "))));
+        }
+    }
+
+    let country = payload.country.map(|c| c.trim().to_uppercase());
+    if let Some(c) = country.as_deref().filter(|c| !c.is_empty()) {
+        if c.len() != 2 || !c.chars().all(|ch| ch.is_ascii_uppercase()) {
+            return Err((StatusCode::BAD_REQUEST, "country must be a two-letter ISO 3166-1 code".to_string()));
+        }
+    }
+
+    // $n IS NULL leaves a field alone; '' clears gender/country
+    sqlx::query(
+        r#"
+        UPDATE users SET
+            birthdate = COALESCE($2, birthdate),
+            gender = CASE WHEN $3::text IS NULL THEN gender ELSE NULLIF($3, '') END,
+            country = CASE WHEN $4::text IS NULL THEN country ELSE NULLIF($4, '') END,
+            share_age_with_ads = COALESCE($5, share_age_with_ads),
+            share_gender_with_ads = COALESCE($6, share_gender_with_ads),
+            share_country_with_ads = COALESCE($7, share_country_with_ads)
+        WHERE id = $1
+        "#
+    )
+    .bind(user_uuid)
+    .bind(payload.birthdate)
+    .bind(gender)
+    .bind(country)
+    .bind(payload.share_age_with_ads)
+    .bind(payload.share_gender_with_ads)
+    .bind(payload.share_country_with_ads)
+    .execute(&*state.pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    fetch_demographics(&state.pool, user_uuid)
+        .await
+        .map(Json)
+        .map_err(|status| (status, "Failed to load demographics".to_string()))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DmPrivacyResponse {
+    /// Who may message you: everyone, followers, mutuals or nobody.
+    /// Anyone else's chats arrive as message requests (refused with nobody).
+    pub dm_privacy: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateDmPrivacyRequest {
+    /// everyone, followers, mutuals or nobody
+    pub dm_privacy: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/settings/{user_id}/dm-privacy",
+    tag = "settings",
+    params(("user_id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, body = DmPrivacyResponse),
+        (status = 403, description = "Not your account"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_dm_privacy(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<String>,
+) -> Result<Json<DmPrivacyResponse>, (StatusCode, String)> {
+    let user_uuid = require_self(&user, &user_id)?;
+    let dm_privacy = sqlx::query_scalar::<_, String>("SELECT dm_privacy FROM users WHERE id = $1")
+        .bind(user_uuid)
+        .fetch_one(&*state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(DmPrivacyResponse { dm_privacy }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/settings/{user_id}/dm-privacy",
+    tag = "settings",
+    params(("user_id" = String, Path, description = "User ID")),
+    request_body = UpdateDmPrivacyRequest,
+    responses(
+        (status = 200, body = DmPrivacyResponse),
+        (status = 400, description = "Unknown setting"),
+        (status = 403, description = "Not your account"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_dm_privacy(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<String>,
+    Json(payload): Json<UpdateDmPrivacyRequest>,
+) -> Result<Json<DmPrivacyResponse>, (StatusCode, String)> {
+    let user_uuid = require_self(&user, &user_id)?;
+    let dm_privacy = payload.dm_privacy.trim().to_lowercase();
+    if !crate::message_requests::DM_PRIVACY.contains(&dm_privacy.as_str()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("dm_privacy must be one of: {}", crate::message_requests::DM_PRIVACY.join(", ")),
+        ));
+    }
+
+    sqlx::query("UPDATE users SET dm_privacy = $2 WHERE id = $1")
+        .bind(user_uuid)
+        .bind(&dm_privacy)
+        .execute(&*state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(DmPrivacyResponse { dm_privacy }))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CommentAudienceResponse {
+    /// Who may comment on your stories: everyone, followers, following
+    /// (people you follow) or off. Stories can override it.
+    pub comment_audience: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateCommentAudienceRequest {
+    /// everyone, followers, following or off
+    pub comment_audience: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/settings/{user_id}/comment-audience",
+    tag = "settings",
+    params(("user_id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, body = CommentAudienceResponse),
+        (status = 403, description = "Not your account"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_comment_audience(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<String>,
+) -> Result<Json<CommentAudienceResponse>, (StatusCode, String)> {
+    let user_uuid = require_self(&user, &user_id)?;
+    let comment_audience = sqlx::query_scalar::<_, String>("SELECT comment_audience FROM users WHERE id = $1")
+        .bind(user_uuid)
+        .fetch_one(&*state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(CommentAudienceResponse { comment_audience }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/settings/{user_id}/comment-audience",
+    tag = "settings",
+    params(("user_id" = String, Path, description = "User ID")),
+    request_body = UpdateCommentAudienceRequest,
+    responses(
+        (status = 200, body = CommentAudienceResponse),
+        (status = 400, description = "Unknown setting"),
+        (status = 403, description = "Not your account"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_comment_audience(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<String>,
+    Json(payload): Json<UpdateCommentAudienceRequest>,
+) -> Result<Json<CommentAudienceResponse>, (StatusCode, String)> {
+    let user_uuid = require_self(&user, &user_id)?;
+    let comment_audience = payload.comment_audience.trim().to_lowercase();
+    if !crate::story_permissions::COMMENT_AUDIENCES.contains(&comment_audience.as_str()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("comment_audience must be one of: {}", crate::story_permissions::COMMENT_AUDIENCES.join(", ")),
+        ));
+    }
+
+    sqlx::query("UPDATE users SET comment_audience = $2 WHERE id = $1")
+        .bind(user_uuid)
+        .bind(&comment_audience)
+        .execute(&*state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    // Cached story lists carry the effective audience
+    crate::stories::invalidate_author(&state, user_uuid).await;
+
+    Ok(Json(CommentAudienceResponse { comment_audience }))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct LocaleResponse {
+    /// Language for notifications and announcements; null follows the
+    /// Accept-Language of each request
+    pub locale: Option<String>,
+    pub available: Vec<&'static str>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateLocaleRequest {
+    /// One of the available locales, or null to follow Accept-Language
+    pub locale: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/settings/{user_id}/locale",
+    tag = "settings",
+    params(("user_id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, body = LocaleResponse),
+        (status = 403, description = "Not your account"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_locale(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<String>,
+) -> Result<Json<LocaleResponse>, (StatusCode, String)> {
+    let user_uuid = require_self(&user, &user_id)?;
+    let locale = sqlx::query_scalar::<_, Option<String>>("SELECT locale FROM users WHERE id = $1")
+        .bind(user_uuid)
+        .fetch_one(&*state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(LocaleResponse { locale, available: crate::i18n::available() }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/settings/{user_id}/locale",
+    tag = "settings",
+    params(("user_id" = String, Path, description = "User ID")),
+    request_body = UpdateLocaleRequest,
+    responses(
+        (status = 200, body = LocaleResponse),
+        (status = 400, description = "Unsupported locale"),
+        (status = 403, description = "Not your account"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_locale(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<String>,
+    Json(payload): Json<UpdateLocaleRequest>,
+) -> Result<Json<LocaleResponse>, (StatusCode, String)> {
+    let user_uuid = require_self(&user, &user_id)?;
+    let locale = match payload.locale.as_deref().map(str::trim).filter(|l| !l.is_empty()) {
+        Some(tag) => Some(crate::i18n::supported(tag).ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                format!("locale must be one of: {}", crate::i18n::available().join(", ")),
+            )
+        })?),
+        None => None,
+    };
+
+    sqlx::query("UPDATE users SET locale = $2 WHERE id = $1")
+        .bind(user_uuid)
+        .bind(locale)
+        .execute(&*state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(LocaleResponse { locale: locale.map(String::from), available: crate::i18n::available() }))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct FederationResponse {
+    /// Public stories are shared with the fediverse and remote accounts can follow you
+    pub enabled: bool,
+    /// Fediverse handle (@username@domain) while enabled
+    pub handle: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateFederationRequest {
+    pub enabled: bool,
+}
+
+async fn fetch_federation(pool: &sqlx::PgPool, user_id: uuid::Uuid) -> Result<FederationResponse, (StatusCode, String)> {
+    let (enabled, username) = sqlx::query_as::<_, (bool, String)>("SELECT federated, username FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let handle = crate::federation::domain()
+        .filter(|_| enabled)
+        .map(|domain| format!("@{}@{}", username, domain));
+    Ok(FederationResponse { enabled, handle })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/settings/{user_id}/federation",
+    tag = "settings",
+    params(("user_id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, body = FederationResponse),
+        (status = 403, description = "Not your account"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_federation(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<String>,
+) -> Result<Json<FederationResponse>, (StatusCode, String)> {
+    let user_uuid = require_self(&user, &user_id)?;
+    fetch_federation(&state.pool, user_uuid).await.map(Json)
+}
+
+// Opt in to or out of ActivityPub federation. Opting out withdraws the
+// public stories already shared and stops new remote follows.
+#[utoipa::path(
+    put,
+    path = "/api/v1/settings/{user_id}/federation",
+    tag = "settings",
+    params(("user_id" = String, Path, description = "User ID")),
+    request_body = UpdateFederationRequest,
+    responses(
+        (status = 200, body = FederationResponse),
+        (status = 400, description = "Federation is turned off on this server"),
+        (status = 403, description = "Not your account, or a minor's account"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_federation(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<String>,
+    Json(payload): Json<UpdateFederationRequest>,
+) -> Result<Json<FederationResponse>, (StatusCode, String)> {
+    let user_uuid = require_self(&user, &user_id)?;
+
+    if payload.enabled {
+        if crate::federation::domain().is_none() {
+            return Err((StatusCode::BAD_REQUEST, "Federation is turned off on this server".to_string()));
+        }
+        let minor = sqlx::query_scalar::<_, bool>("SELECT user_is_minor($1)")
+            .bind(user_uuid)
+            .fetch_one(&*state.pool)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        if minor {
+            return Err((StatusCode::FORBIDDEN, "Federation isn't available to accounts under 18".to_string()));
+        }
+        crate::federation::ensure_key(&state.pool, user_uuid)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    }
+
+    sqlx::query("UPDATE users SET federated = $2 WHERE id = $1")
+        .bind(user_uuid)
+        .bind(payload.enabled)
+        .execute(&*state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    fetch_federation(&state.pool, user_uuid).await.map(Json)
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ActivityStatusResponse {
+    /// Mutuals see when you're online and when you were last active. While
+    /// this is off you don't see theirs either.
+    pub show_activity_status: bool,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateActivityStatusRequest {
+    pub show_activity_status: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/settings/{user_id}/activity-status",
+    tag = "settings",
+    params(("user_id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, body = ActivityStatusResponse),
+        (status = 403, description = "Not your account"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_activity_status(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<String>,
+) -> Result<Json<ActivityStatusResponse>, (StatusCode, String)> {
+    let user_uuid = require_self(&user, &user_id)?;
+    let show_activity_status = sqlx::query_scalar::<_, bool>("SELECT show_activity_status FROM users WHERE id = $1")
+        .bind(user_uuid)
+        .fetch_one(&*state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(ActivityStatusResponse { show_activity_status }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/settings/{user_id}/activity-status",
+    tag = "settings",
+    params(("user_id" = String, Path, description = "User ID")),
+    request_body = UpdateActivityStatusRequest,
+    responses(
+        (status = 200, body = ActivityStatusResponse),
+        (status = 403, description = "Not your account"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_activity_status(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<String>,
+    Json(payload): Json<UpdateActivityStatusRequest>,
+) -> Result<Json<ActivityStatusResponse>, (StatusCode, String)> {
+    let user_uuid = require_self(&user, &user_id)?;
+    sqlx::query("UPDATE users SET show_activity_status = $2 WHERE id = $1")
+        .bind(user_uuid)
+        .bind(payload.show_activity_status)
+        .execute(&*state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(ActivityStatusResponse { show_activity_status: payload.show_activity_status }))
+}
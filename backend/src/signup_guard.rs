@@ -0,0 +1,422 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::permissions::{CanModerateContent, Permitted};
+use crate::AppState;
+
+// Checks a signup has to pass before the account is created, to slow down
+// bot farms:
+//   throttle   - at most SIGNUP_MAX_PER_IP_PER_HOUR attempts per client IP
+//                (default 5, 0 turns it off)
+//   CAPTCHA    - with CAPTCHA_PROVIDER (turnstile or hcaptcha) and
+//                CAPTCHA_SECRET set, the client must send a captcha_token
+//   blocklist  - domains admins blocked, and known disposable-mail providers
+//                (plus any in SIGNUP_DISPOSABLE_DOMAINS)
+//   MX         - with SIGNUP_VERIFY_MX=true the domain must accept mail,
+//                looked up over DNS-over-HTTPS at DNS_OVER_HTTPS_URL
+// CAPTCHA and MX lookups that fail to get an answer let the signup through.
+
+const DEFAULT_MAX_PER_IP_PER_HOUR: i64 = 5;
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_DOH_URL: &str = "https://cloudflare-dns.com/dns-query";
+const TURNSTILE_VERIFY_URL: &str = "https://challenges.cloudflare.com/turnstile/v0/siteverify";
+const HCAPTCHA_VERIFY_URL: &str = "https://api.hcaptcha.com/siteverify";
+
+// Widely used throwaway-mail providers
+const DISPOSABLE_DOMAINS: &[&str] = &[
+    "10minutemail.com",
+    "33mail.com",
+    "dispostable.com",
+    "emailondeck.com",
+    "fakeinbox.com",
+    "getairmail.com",
+    "getnada.com",
+    "guerrillamail.com",
+    "guerrillamail.net",
+    "guerrillamailblock.com",
+    "maildrop.cc",
+    "mailinator.com",
+    "mailnesia.com",
+    "mintemail.com",
+    "mohmal.com",
+    "moakt.com",
+    "mytemp.email",
+    "sharklasers.com",
+    "spamgourmet.com",
+    "temp-mail.org",
+    "tempail.com",
+    "tempmail.com",
+    "tempmail.net",
+    "tempmailo.com",
+    "throwawaymail.com",
+    "trashmail.com",
+    "yopmail.com",
+    "yopmail.net",
+];
+
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(LOOKUP_TIMEOUT)
+            .build()
+            .expect("Failed to build signup check HTTP client")
+    })
+}
+
+fn rejected(message: &str) -> (StatusCode, String) {
+    (StatusCode::BAD_REQUEST, message.to_string())
+}
+
+/// Lowercased domain of a plausible address, None if it isn't one
+fn email_domain(email: &str) -> Option<String> {
+    let (local, domain) = email.trim().split_once('@')?;
+    let valid = !local.is_empty()
+        && !domain.contains('@')
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !email.trim().contains(char::is_whitespace);
+    valid.then(|| domain.to_lowercase())
+}
+
+/// The domain and each parent short of the top level: a.b.com, b.com
+fn domain_and_parents(domain: &str) -> Vec<&str> {
+    let mut domains = vec![domain];
+    let mut rest = domain;
+    while let Some((_, parent)) = rest.split_once('.') {
+        if !parent.contains('.') {
+            break;
+        }
+        domains.push(parent);
+        rest = parent;
+    }
+    domains
+}
+
+fn is_disposable(domains: &[&str]) -> bool {
+    let extra = std::env::var("SIGNUP_DISPOSABLE_DOMAINS").unwrap_or_default();
+    let extra: Vec<String> = extra
+        .split(',')
+        .map(|d| d.trim().to_lowercase())
+        .filter(|d| !d.is_empty())
+        .collect();
+    domains
+        .iter()
+        .any(|d| DISPOSABLE_DOMAINS.contains(d) || extra.iter().any(|e| e == d))
+}
+
+async fn throttle(state: &AppState, ip: &str) -> Result<(), (StatusCode, String)> {
+    let limit = std::env::var("SIGNUP_MAX_PER_IP_PER_HOUR")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_MAX_PER_IP_PER_HOUR);
+    if limit <= 0 {
+        return Ok(());
+    }
+    let key = format!("signup:ip:{}:{}", ip, Utc::now().timestamp() / 3600);
+    let attempts = state.redis.lock().await.cache_incr(&key, 3600).await.unwrap_or(0);
+    if attempts > limit {
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            "Too many signups from this network; try again later".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+async fn verify_captcha(token: Option<&str>, ip: Option<&str>) -> Result<(), (StatusCode, String)> {
+    #[derive(Deserialize)]
+    struct Verification {
+        success: bool,
+    }
+
+    let verify_url = match std::env::var("CAPTCHA_PROVIDER").unwrap_or_default().as_str() {
+        "turnstile" => TURNSTILE_VERIFY_URL,
+        "hcaptcha" => HCAPTCHA_VERIFY_URL,
+        _ => return Ok(()),
+    };
+    let Ok(secret) = std::env::var("CAPTCHA_SECRET") else {
+        return Ok(());
+    };
+    let token = token
+        .filter(|t| !t.is_empty())
+        .ok_or_else(|| rejected("captcha_token is required"))?;
+
+    let mut form = vec![("secret", secret.as_str()), ("response", token)];
+    if let Some(ip) = ip {
+        form.push(("remoteip", ip));
+    }
+    let verification = http_client()
+        .post(verify_url)
+        .form(&form)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status());
+    let verification: Verification = match verification {
+        Ok(response) => match response.json().await {
+            Ok(verification) => verification,
+            Err(e) => {
+                eprintln!("⚠️ Unexpected CAPTCHA response, allowing signup: {}", e);
+                return Ok(());
+            }
+        },
+        Err(e) => {
+            eprintln!("⚠️ CAPTCHA verification failed, allowing signup: {}", e);
+            return Ok(());
+        }
+    };
+
+    if verification.success {
+        Ok(())
+    } else {
+        Err(rejected("CAPTCHA verification failed"))
+    }
+}
+
+#[derive(Deserialize)]
+struct DnsAnswer {
+    #[serde(rename = "type")]
+    record_type: u16,
+    data: String,
+}
+
+#[derive(Deserialize)]
+struct DnsResponse {
+    #[serde(rename = "Status")]
+    status: u16,
+    #[serde(rename = "Answer", default)]
+    answer: Vec<DnsAnswer>,
+}
+
+const DNS_NXDOMAIN: u16 = 3;
+const DNS_TYPE_A: u16 = 1;
+const DNS_TYPE_MX: u16 = 15;
+const DNS_TYPE_AAAA: u16 = 28;
+
+async fn resolve(domain: &str, record_type: &str) -> Result<DnsResponse, reqwest::Error> {
+    let url = std::env::var("DNS_OVER_HTTPS_URL")
+        .ok()
+        .filter(|u| !u.is_empty())
+        .unwrap_or_else(|| DEFAULT_DOH_URL.to_string());
+    http_client()
+        .get(url)
+        .query(&[("name", domain), ("type", record_type)])
+        .header("Accept", "application/dns-json")
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+}
+
+/// Whether mail to `domain` can be delivered: it has an MX record other than
+/// a null MX (RFC 7505), or failing that an address record (RFC 5321 5.1)
+async fn accepts_mail(domain: &str) -> Result<bool, reqwest::Error> {
+    let mx = resolve(domain, "MX").await?;
+    if mx.status == DNS_NXDOMAIN {
+        return Ok(false);
+    }
+    let exchanges: Vec<&DnsAnswer> = mx.answer.iter().filter(|a| a.record_type == DNS_TYPE_MX).collect();
+    if !exchanges.is_empty() {
+        let null_mx = exchanges.iter().all(|a| a.data.split_whitespace().nth(1) == Some("."));
+        return Ok(!null_mx);
+    }
+
+    let a = resolve(domain, "A").await?;
+    Ok(a
+        .answer
+        .iter()
+        .any(|a| a.record_type == DNS_TYPE_A || a.record_type == DNS_TYPE_AAAA))
+}
+
+/// Run every signup check for `email` (absent for phone signups), coming
+/// from the client at `ip`
+pub async fn check(
+    state: &AppState,
+    ip: IpAddr,
+    email: Option<&str>,
+    captcha_token: Option<&str>,
+) -> Result<(), (StatusCode, String)> {
+    let domain = email
+        .map(|email| email_domain(email).ok_or_else(|| rejected("Invalid email address")))
+        .transpose()?;
+    let ip = ip.to_string();
+
+    throttle(state, &ip).await?;
+    verify_captcha(captcha_token, Some(&ip)).await?;
+
+    // Phone signups prove the number with a code instead
+    let Some(domain) = domain else {
+        return Ok(());
+    };
+
+    let domains = domain_and_parents(&domain);
+    if is_disposable(&domains) {
+        return Err(rejected("Disposable email addresses can't be used to sign up"));
+    }
+    let blocked: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM blocked_email_domains WHERE domain = ANY($1))")
+        .bind(&domains)
+        .fetch_one(state.pool.as_ref())
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to check blocked domains: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create account".to_string())
+        })?;
+    if blocked {
+        return Err(rejected("Email addresses at this domain can't be used to sign up"));
+    }
+
+    if std::env::var("SIGNUP_VERIFY_MX").is_ok_and(|v| v == "true") {
+        match accepts_mail(&domain).await {
+            Ok(true) => {}
+            Ok(false) => return Err(rejected("This email domain doesn't accept mail")),
+            Err(e) => eprintln!("⚠️ MX lookup for {} failed, allowing signup: {}", domain, e),
+        }
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// BLOCKLIST ADMINISTRATION
+// ============================================================================
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct BlockedDomain {
+    domain: String,
+    reason: Option<String>,
+    created_by: Option<Uuid>,
+    #[serde(with = "crate::timestamps")]
+    created_at: NaiveDateTime,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct BlockDomainInput {
+    /// Domain to refuse; its subdomains are refused too
+    domain: String,
+    reason: Option<String>,
+}
+
+// List blocked signup email domains
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/signup/blocked-domains",
+    tag = "admin",
+    responses((status = 200, body = [BlockedDomain]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_blocked_domains(
+    State(state): State<Arc<AppState>>,
+    _admin: Permitted<CanModerateContent>,
+) -> Result<Json<Vec<BlockedDomain>>, (StatusCode, String)> {
+    let domains = sqlx::query_as::<_, BlockedDomain>("SELECT * FROM blocked_email_domains ORDER BY domain")
+        .fetch_all(state.pool.as_ref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(domains))
+}
+
+// Refuse signups from an email domain
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/signup/blocked-domains",
+    tag = "admin",
+    request_body = BlockDomainInput,
+    responses(
+        (status = 200, body = BlockedDomain),
+        (status = 400, description = "Not a domain"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn block_domain(
+    State(state): State<Arc<AppState>>,
+    admin: Permitted<CanModerateContent>,
+    Json(payload): Json<BlockDomainInput>,
+) -> Result<Json<BlockedDomain>, (StatusCode, String)> {
+    let domain = payload.domain.trim().trim_start_matches('@').to_lowercase();
+    if email_domain(&format!("x@{}", domain)).is_none() {
+        return Err(rejected("domain must look like example.com"));
+    }
+
+    let blocked = sqlx::query_as::<_, BlockedDomain>(
+        r#"
+        INSERT INTO blocked_email_domains (domain, reason, created_by)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (domain) DO UPDATE SET reason = EXCLUDED.reason
+        RETURNING *
+        "#
+    )
+    .bind(&domain)
+    .bind(&payload.reason)
+    .bind(admin.0.id)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "block_email_domain".to_string(),
+        None,
+        Some("email_domain".to_string()),
+        None,
+        serde_json::json!({ "domain": domain, "reason": payload.reason }),
+    )
+    .await;
+
+    Ok(Json(blocked))
+}
+
+// Allow signups from a blocked email domain again
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/signup/blocked-domains/{domain}",
+    tag = "admin",
+    params(("domain" = String, Path, description = "Blocked domain")),
+    responses(
+        (status = 204, description = "Domain unblocked"),
+        (status = 404, description = "Domain is not blocked"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn unblock_domain(
+    State(state): State<Arc<AppState>>,
+    admin: Permitted<CanModerateContent>,
+    Path(domain): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let domain = domain.to_lowercase();
+    let removed = sqlx::query("DELETE FROM blocked_email_domains WHERE domain = $1")
+        .bind(&domain)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .rows_affected();
+    if removed == 0 {
+        return Err((StatusCode::NOT_FOUND, "Domain is not blocked".to_string()));
+    }
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "unblock_email_domain".to_string(),
+        None,
+        Some("email_domain".to_string()),
+        None,
+        serde_json::json!({ "domain": domain }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
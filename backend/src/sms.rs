@@ -0,0 +1,96 @@
+use serde::Serialize;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+// Outgoing SMS, for phone verification codes (see phone.rs). The provider is
+// picked with SMS_PROVIDER:
+//   twilio  TWILIO_ACCOUNT_SID, TWILIO_AUTH_TOKEN and TWILIO_FROM (a number
+//           or messaging service SID)
+//   http    JSON {"to", "text"} POSTed to SMS_API_URL with SMS_API_KEY as the
+//           bearer token, for gateways with a simple HTTP front
+//   log     prints the message instead of sending it; development only
+// Without a provider phone verification is unavailable.
+
+const SMS_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// SMS backend, picked with SMS_PROVIDER
+#[derive(Debug, Clone, Copy)]
+enum Provider {
+    Twilio,
+    Http,
+    Log,
+}
+
+impl Provider {
+    fn from_env() -> Option<Provider> {
+        match std::env::var("SMS_PROVIDER").ok()?.to_lowercase().as_str() {
+            "twilio" => Some(Provider::Twilio),
+            "http" => Some(Provider::Http),
+            "log" => Some(Provider::Log),
+            other => {
+                tracing::warn!("Unknown SMS_PROVIDER {:?}; SMS disabled", other);
+                None
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct HttpSms<'a> {
+    to: &'a str,
+    text: &'a str,
+}
+
+fn env(name: &str) -> Result<String, String> {
+    std::env::var(name)
+        .ok()
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| format!("{} is not set", name))
+}
+
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(SMS_TIMEOUT)
+            .build()
+            .expect("Failed to build SMS HTTP client")
+    })
+}
+
+pub fn is_configured() -> bool {
+    Provider::from_env().is_some()
+}
+
+/// Send `text` to the E.164 number `to`
+pub async fn send(to: &str, text: &str) -> Result<(), String> {
+    let provider = Provider::from_env().ok_or_else(|| "SMS_PROVIDER is not configured".to_string())?;
+
+    let request = match provider {
+        Provider::Twilio => {
+            let account_sid = env("TWILIO_ACCOUNT_SID")?;
+            let from = env("TWILIO_FROM")?;
+            // Messaging service SIDs go in their own field
+            let from_field = if from.starts_with("MG") { "MessagingServiceSid" } else { "From" };
+            http_client()
+                .post(format!("https://api.twilio.com/2010-04-01/Accounts/{}/Messages.json", account_sid))
+                .basic_auth(&account_sid, Some(env("TWILIO_AUTH_TOKEN")?))
+                .form(&[("To", to), (from_field, from.as_str()), ("Body", text)])
+        }
+        Provider::Http => http_client()
+            .post(env("SMS_API_URL")?)
+            .bearer_auth(std::env::var("SMS_API_KEY").unwrap_or_default())
+            .json(&HttpSms { to, text }),
+        Provider::Log => {
+            println!("📱 SMS to {}: {}", to, text);
+            return Ok(());
+        }
+    };
+
+    request
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("SMS request failed: {}", e))?;
+    Ok(())
+}
@@ -1,25 +1,27 @@
 use axum::{
-    extract::{State, Path},
+    extract::{State, Path, Query},
     Json,
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
 };
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 use std::sync::Arc;
 use uuid::Uuid;
 use chrono::NaiveDateTime;
 
 use crate::AppState;
+use crate::admin::AuthUser;
 
 // ============= Follow System =============
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct FollowResponse {
     pub success: bool,
     pub message: String,
     pub is_following: bool,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct FollowStats {
     pub follower_count: i32,
     pub following_count: i32,
@@ -27,6 +29,13 @@ pub struct FollowStats {
 }
 
 // Follow a user
+#[utoipa::path(
+    post,
+    path = "/api/v1/social/follow/{follower_id}/{following_id}",
+    tag = "social",
+    params(("follower_id" = Uuid, Path, description = "Follower ID"), ("following_id" = Uuid, Path, description = "Following ID")),
+    responses((status = 200, body = FollowResponse))
+)]
 pub async fn follow_user(
     State(state): State<Arc<AppState>>,
     Path((follower_id, following_id)): Path<(Uuid, Uuid)>,
@@ -53,21 +62,49 @@ pub async fn follow_user(
     .await;
 
     match result {
-        Ok(_) => Ok(Json(FollowResponse {
-            success: true,
-            message: "Successfully followed user".to_string(),
-            is_following: true,
-        })),
+        Ok(result) => {
+            // Only a new follow is an event; re-following is a no-op
+            if result.rows_affected() > 0 {
+                invalidate_follow(&state, follower_id, following_id).await;
+                crate::webhooks::dispatch(
+                    &state.pool,
+                    &[following_id],
+                    crate::webhooks::EVENT_NEW_FOLLOWER,
+                    serde_json::json!({ "follower_id": follower_id, "following_id": following_id }),
+                )
+                .await;
+            }
+
+            Ok(Json(FollowResponse {
+                success: true,
+                message: "Successfully followed user".to_string(),
+                is_following: true,
+            }))
+        }
         Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
 }
 
+/// Both profiles' counts and the follower's discovery results are stale
+async fn invalidate_follow(state: &AppState, follower_id: Uuid, following_id: Uuid) {
+    crate::cache::invalidate_profile(&state.redis, follower_id).await;
+    crate::cache::invalidate_profile(&state.redis, following_id).await;
+    crate::cache::invalidate_discovery(&state.redis, follower_id).await;
+}
+
 // Unfollow a user
+#[utoipa::path(
+    post,
+    path = "/api/v1/social/unfollow/{follower_id}/{following_id}",
+    tag = "social",
+    params(("follower_id" = Uuid, Path, description = "Follower ID"), ("following_id" = Uuid, Path, description = "Following ID")),
+    responses((status = 200, body = FollowResponse))
+)]
 pub async fn unfollow_user(
     State(state): State<Arc<AppState>>,
     Path((follower_id, following_id)): Path<(Uuid, Uuid)>,
 ) -> Result<Json<FollowResponse>, StatusCode> {
-    sqlx::query!(
+    let result = sqlx::query!(
         r#"
         DELETE FROM follows
         WHERE follower_id = $1 AND following_id = $2
@@ -79,6 +116,10 @@ pub async fn unfollow_user(
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    if result.rows_affected() > 0 {
+        invalidate_follow(&state, follower_id, following_id).await;
+    }
+
     Ok(Json(FollowResponse {
         success: true,
         message: "Successfully unfollowed user".to_string(),
@@ -87,6 +128,13 @@ pub async fn unfollow_user(
 }
 
 // Get follow stats for a user
+#[utoipa::path(
+    get,
+    path = "/api/v1/social/follow-stats/{user_id}/{viewer_id}",
+    tag = "social",
+    params(("user_id" = Uuid, Path, description = "User ID"), ("viewer_id" = Uuid, Path, description = "Viewer ID")),
+    responses((status = 200, body = FollowStats))
+)]
 pub async fn get_follow_stats(
     State(state): State<Arc<AppState>>,
     Path((user_id, viewer_id)): Path<(Uuid, Uuid)>,
@@ -128,7 +176,7 @@ pub async fn get_follow_stats(
 }
 
 // Get list of followers
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct UserListItem {
     pub id: Uuid,
     pub username: String,
@@ -136,6 +184,13 @@ pub struct UserListItem {
     pub is_following: bool,
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/social/followers/{user_id}/{viewer_id}",
+    tag = "social",
+    params(("user_id" = Uuid, Path, description = "User ID"), ("viewer_id" = Uuid, Path, description = "Viewer ID")),
+    responses((status = 200, body = [UserListItem]))
+)]
 pub async fn get_followers(
     State(state): State<Arc<AppState>>,
     Path((user_id, viewer_id)): Path<(Uuid, Uuid)>,
@@ -173,6 +228,13 @@ pub async fn get_followers(
 }
 
 // Get list of following
+#[utoipa::path(
+    get,
+    path = "/api/v1/social/following/{user_id}/{viewer_id}",
+    tag = "social",
+    params(("user_id" = Uuid, Path, description = "User ID"), ("viewer_id" = Uuid, Path, description = "Viewer ID")),
+    responses((status = 200, body = [UserListItem]))
+)]
 pub async fn get_following(
     State(state): State<Arc<AppState>>,
     Path((user_id, viewer_id)): Path<(Uuid, Uuid)>,
@@ -211,14 +273,23 @@ pub async fn get_following(
 
 // ============= Story Likes =============
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct LikeResponse {
     pub success: bool,
     pub is_liked: bool,
-    pub like_count: i32,
+    /// Left out when the author hid the count from this user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub like_count: Option<i32>,
 }
 
 // Like a story
+#[utoipa::path(
+    post,
+    path = "/api/v1/social/like/{story_id}/{user_id}",
+    tag = "social",
+    params(("story_id" = Uuid, Path, description = "Story ID"), ("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200, body = LikeResponse))
+)]
 pub async fn like_story(
     State(state): State<Arc<AppState>>,
     Path((story_id, user_id)): Path<(Uuid, Uuid)>,
@@ -248,14 +319,25 @@ pub async fn like_story(
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    let hidden = crate::story_permissions::like_count_hidden(&state.pool, story_id, Some(user_id))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
     Ok(Json(LikeResponse {
         success: true,
         is_liked: true,
-        like_count: story.like_count.unwrap_or(0),
+        like_count: (!hidden).then(|| story.like_count.unwrap_or(0)),
     }))
 }
 
 // Unlike a story
+#[utoipa::path(
+    post,
+    path = "/api/v1/social/unlike/{story_id}/{user_id}",
+    tag = "social",
+    params(("story_id" = Uuid, Path, description = "Story ID"), ("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200, body = LikeResponse))
+)]
 pub async fn unlike_story(
     State(state): State<Arc<AppState>>,
     Path((story_id, user_id)): Path<(Uuid, Uuid)>,
@@ -284,25 +366,50 @@ pub async fn unlike_story(
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    let hidden = crate::story_permissions::like_count_hidden(&state.pool, story_id, Some(user_id))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
     Ok(Json(LikeResponse {
         success: true,
         is_liked: false,
-        like_count: story.like_count.unwrap_or(0),
+        like_count: (!hidden).then(|| story.like_count.unwrap_or(0)),
     }))
 }
 
 // Get users who liked a story
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct LikeUserItem {
     pub id: Uuid,
     pub username: String,
+    #[serde(with = "crate::timestamps")]
     pub created_at: NaiveDateTime,
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/social/likes/{story_id}",
+    tag = "social",
+    params(("story_id" = Uuid, Path, description = "Story ID")),
+    responses(
+        (status = 200, body = [LikeUserItem]),
+        (status = 403, description = "The author hid the story's likes")
+    ),
+    security((), ("bearer_auth" = []))
+)]
 pub async fn get_story_likes(
+    viewer: Option<AuthUser>,
     State(state): State<Arc<AppState>>,
     Path(story_id): Path<Uuid>,
 ) -> Result<Json<Vec<LikeUserItem>>, StatusCode> {
+    // Who liked it would give the count away
+    if crate::story_permissions::like_count_hidden(&state.pool, story_id, viewer.map(|v| v.id))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
     let likes = sqlx::query!(
         r#"
         SELECT 
@@ -331,12 +438,12 @@ pub async fn get_story_likes(
 
 // ============= Story Comments =============
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateCommentRequest {
     pub comment_text: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
 pub struct Comment {
     pub id: Uuid,
     pub story_id: Uuid,
@@ -346,40 +453,64 @@ pub struct Comment {
     pub comment_text: String,
     pub parent_comment_id: Option<Uuid>,
     pub reply_count: Option<i32>,
+    #[serde(with = "crate::timestamps")]
     pub created_at: NaiveDateTime,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct CommentResponse {
     pub success: bool,
     pub comment: Comment,
 }
 
 // Add a comment to a story
+#[utoipa::path(
+    post,
+    path = "/api/v1/social/comment/{story_id}/{user_id}",
+    tag = "social",
+    params(("story_id" = Uuid, Path, description = "Story ID"), ("user_id" = Uuid, Path, description = "User ID")),
+    request_body = CreateCommentRequest,
+    responses(
+        (status = 200, body = CommentResponse),
+        (status = 403, description = "Comments are off, or restricted to an audience the commenter isn't in (see reason)"),
+        (status = 413, description = "Comment is too long"),
+        (status = 429, description = "Daily comment limit reached")
+    )
+)]
 pub async fn add_comment(
     State(state): State<Arc<AppState>>,
     Path((story_id, user_id)): Path<(Uuid, Uuid)>,
     Json(req): Json<CreateCommentRequest>,
-) -> Result<Json<CommentResponse>, StatusCode> {
+) -> axum::response::Result<Json<CommentResponse>> {
     if req.comment_text.trim().is_empty() {
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(StatusCode::BAD_REQUEST.into());
     }
 
+    crate::story_permissions::check_commenter(&state.pool, story_id, user_id).await?;
+    crate::quotas::check_comment(&state.pool, user_id, req.comment_text.trim()).await?;
+    let screened = crate::text_moderation::screen(&state.pool, req.comment_text.trim()).await?;
+
     let comment_id = Uuid::new_v4();
+    // Only the commenter sees it if it contains a word the author muted
+    let hidden = crate::muted_words::hides_comment(&state.pool, story_id, &screened.text).await;
 
-    sqlx::query!(
+    sqlx::query(
         r#"
-        INSERT INTO story_comments (id, story_id, user_id, comment_text)
-        VALUES ($1, $2, $3, $4)
-        "#,
-        comment_id,
-        story_id,
-        user_id,
-        req.comment_text.trim()
+        INSERT INTO story_comments (id, story_id, user_id, comment_text, hidden_by_filter)
+        VALUES ($1, $2, $3, $4, $5)
+        "#
     )
+    .bind(comment_id)
+    .bind(story_id)
+    .bind(user_id)
+    .bind(&screened.text)
+    .bind(hidden)
     .execute(state.pool.as_ref())
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    screened
+        .record(&state.pool, crate::text_moderation::CONTENT_COMMENT, comment_id, user_id)
+        .await;
 
     // Fetch the created comment with username
     let comment = sqlx::query!(
@@ -419,11 +550,21 @@ pub async fn add_comment(
 }
 
 // Get comments for a story
+#[utoipa::path(
+    get,
+    path = "/api/v1/social/comments/{story_id}",
+    tag = "social",
+    params(("story_id" = Uuid, Path, description = "Story ID")),
+    responses((status = 200, body = [Comment])),
+    security((), ("bearer_auth" = []))
+)]
 pub async fn get_story_comments(
+    viewer: Option<AuthUser>,
     State(state): State<Arc<AppState>>,
     Path(story_id): Path<Uuid>,
+    headers: HeaderMap,
 ) -> Result<Json<Vec<Comment>>, StatusCode> {
-    let comments = sqlx::query!(
+    let comments = sqlx::query_as::<_, Comment>(
         r#"
         SELECT
             sc.id,
@@ -438,30 +579,31 @@ pub async fn get_story_comments(
         FROM story_comments sc
         JOIN users u ON sc.user_id = u.id
         WHERE sc.story_id = $1 AND sc.parent_comment_id IS NULL
+          AND story_comment_audience(sc.story_id) <> 'off'
+          AND (NOT sc.hidden_by_filter OR sc.user_id = $3)
+          AND NOT geo_blocked('story', sc.story_id, $2)
+          AND NOT geo_blocked('comment', sc.id, $2)
         ORDER BY sc.created_at ASC
-        "#,
-        story_id
+        "#
     )
+    .bind(story_id)
+    .bind(crate::takedowns::request_country(&headers))
+    .bind(viewer.map(|v| v.id))
     .fetch_all(state.pool.as_ref())
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let result = comments.into_iter().map(|c| Comment {
-        id: c.id,
-        story_id: c.story_id,
-        user_id: c.user_id,
-        username: c.username,
-        avatar_url: c.avatar_url,
-        comment_text: c.comment_text,
-        parent_comment_id: c.parent_comment_id,
-        reply_count: c.reply_count,
-        created_at: c.created_at,
-    }).collect();
-
-    Ok(Json(result))
+    Ok(Json(comments))
 }
 
 // Delete a comment
+#[utoipa::path(
+    delete,
+    path = "/api/v1/social/comment/delete/{comment_id}/{user_id}",
+    tag = "social",
+    params(("comment_id" = Uuid, Path, description = "Comment ID"), ("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200, description = "Success"))
+)]
 pub async fn delete_comment(
     State(state): State<Arc<AppState>>,
     Path((comment_id, user_id)): Path<(Uuid, Uuid)>,
@@ -483,7 +625,7 @@ pub async fn delete_comment(
 
 // ============= Profile System =============
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct UserProfile {
     pub id: Uuid,
     pub username: String,
@@ -499,7 +641,22 @@ pub struct UserProfile {
     pub email: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Serialize, Deserialize, sqlx::FromRow)]
+struct CachedProfile {
+    id: Uuid,
+    username: String,
+    display_name: Option<String>,
+    avatar_url: Option<String>,
+    bio: Option<String>,
+    about: Option<String>,
+    profile_link: Option<String>,
+    email: String,
+    follower_count: Option<i32>,
+    following_count: Option<i32>,
+    story_count: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateProfileRequest {
     pub display_name: Option<String>,
     pub bio: Option<String>,
@@ -509,85 +666,214 @@ pub struct UpdateProfileRequest {
 }
 
 // Get user profile
+#[utoipa::path(
+    get,
+    path = "/api/v1/profile/{user_id}/{viewer_id}",
+    tag = "social",
+    params(("user_id" = Uuid, Path, description = "User ID"), ("viewer_id" = Uuid, Path, description = "Viewer ID")),
+    responses((status = 200, body = UserProfile), (status = 304, description = "Unchanged since the ETag in If-None-Match"))
+)]
 pub async fn get_user_profile(
     State(state): State<Arc<AppState>>,
     Path((user_id, viewer_id)): Path<(Uuid, Uuid)>,
+    headers: axum::http::HeaderMap,
 ) -> Result<Json<UserProfile>, StatusCode> {
-    let profile = sqlx::query_as!(
-        UserProfile,
-        r#"
-        SELECT 
-            u.id,
-            u.username,
-            u.display_name,
-            u.avatar_url,
-            u.bio,
-            u.about,
-            u.profile_link,
-            u.email,
-            u.follower_count,
-            u.following_count,
-            u.story_count,
-            EXISTS(
-                SELECT 1 FROM follows 
-                WHERE follower_id = $2 AND following_id = $1
-            ) as "is_following?"
-        FROM users u
-        WHERE u.id = $1
-        "#,
-        user_id,
-        viewer_id
+    // The viewer-independent part is cached; is_following is looked up per request
+    let key = crate::cache::profile_key(user_id);
+    let cached = match crate::cache::get::<CachedProfile>(&state.redis, &key).await {
+        Some(cached) => cached,
+        None => {
+            let cached = sqlx::query_as::<_, CachedProfile>(
+                r#"
+                SELECT id, username, display_name, avatar_url, bio, about, profile_link, email,
+                       follower_count, following_count, story_count
+                FROM users
+                WHERE id = $1
+                "#
+            )
+            .bind(user_id)
+            .fetch_optional(state.pool.as_ref())
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .ok_or(StatusCode::NOT_FOUND)?;
+            crate::cache::set(&state.redis, &key, &cached, crate::cache::PROFILE_TTL_SECS).await;
+            cached
+        }
+    };
+
+    let is_following = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM follows WHERE follower_id = $2 AND following_id = $1)"
     )
+    .bind(user_id)
+    .bind(viewer_id)
     .fetch_one(state.pool.as_ref())
     .await
-    .map_err(|_| StatusCode::NOT_FOUND)?;
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let profile = UserProfile {
+        id: cached.id,
+        username: cached.username,
+        display_name: cached.display_name,
+        avatar_url: cached.avatar_url,
+        bio: cached.bio,
+        about: cached.about,
+        profile_link: cached.profile_link,
+        follower_count: cached.follower_count,
+        following_count: cached.following_count,
+        story_count: cached.story_count,
+        is_following: Some(is_following),
+        email: Some(cached.email),
+    };
+
+    crate::insights::record_profile_visit(&state.pool, user_id, viewer_id, &headers).await;
 
     Ok(Json(profile))
 }
 
 // Get user's stories (for profile grid)
-#[derive(Debug, Serialize)]
+// Live stories, highlighted memories and - for the owner only - the rest of
+// their archive, merged newest first. item_type is "story", "highlight" or "archive".
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
 pub struct ProfileStory {
     pub id: Uuid,
+    pub item_type: String,
+    pub highlight_id: Option<Uuid>,
     pub media_url: String,
+    /// HLS playlist for live video stories
+    pub playback_url: Option<String>,
     pub media_type: String,
+    pub thumbnail_url: Option<String>,
     pub caption: Option<String>,
+    pub alt_text: Option<String>,
+    pub alt_text_generated: bool,
+    /// 18+ content, only shown to the owner and adult viewers
+    pub is_mature: bool,
     pub view_count: Option<i32>,
     pub like_count: Option<i32>,
     pub comment_count: Option<i32>,
+    #[serde(with = "crate::timestamps")]
     pub created_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps::option")]
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ProfileGridQuery {
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
 }
 
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProfileGridResponse {
+    pub items: Vec<ProfileStory>,
+    pub page: i64,
+    pub per_page: i64,
+    pub has_more: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/profile/{user_id}/stories",
+    operation_id = "get_profile_grid",
+    tag = "social",
+    params(("user_id" = Uuid, Path, description = "User ID"), ProfileGridQuery),
+    responses((status = 200, body = ProfileGridResponse), (status = 401, description = "Missing or invalid credentials")),
+    security((), ("bearer_auth" = []))
+)]
 pub async fn get_user_stories(
+    viewer: Option<AuthUser>,
     State(state): State<Arc<AppState>>,
     Path(user_id): Path<Uuid>,
-) -> Result<Json<Vec<ProfileStory>>, StatusCode> {
-    let stories = sqlx::query_as!(
-        ProfileStory,
+    Query(params): Query<ProfileGridQuery>,
+    headers: HeaderMap,
+) -> Result<Json<ProfileGridResponse>, StatusCode> {
+    let country = crate::takedowns::request_country(&headers);
+    let page = params.page.unwrap_or(1).max(1);
+    let per_page = params.per_page.unwrap_or(30).clamp(1, 100);
+    let offset = (page - 1) * per_page;
+    let viewer_id = viewer.map(|v| v.id);
+    let is_owner = viewer_id == Some(user_id);
+
+    let mut items = sqlx::query_as::<_, ProfileStory>(
         r#"
-        SELECT 
-            id,
-            media_url,
-            media_type,
-            caption,
-            view_count,
-            like_count,
-            comment_count,
-            created_at
-            FROM stories
-            WHERE user_id = $1 AND expires_at > NOW()
-            ORDER BY created_at DESC
-        "#,
-        user_id
+        SELECT * FROM (
+            SELECT s.id, 'story' AS item_type, NULL::uuid AS highlight_id,
+                   s.media_url, s.playback_url, s.media_type, s.thumbnail_url, s.caption, s.alt_text, s.alt_text_generated, s.is_mature,
+                   s.view_count,
+                   CASE WHEN s.hide_like_count AND NOT $2 THEN NULL ELSE s.like_count END AS like_count,
+                   s.comment_count,
+                   s.created_at, s.expires_at
+            FROM stories s
+            WHERE s.user_id = $1 AND s.expires_at > NOW()
+              AND (NOT s.is_mature OR $2 OR user_is_adult($5))
+              AND NOT geo_blocked('story', s.id, $6)
+
+            UNION ALL
+
+            (SELECT DISTINCT ON (a.id) a.id, 'highlight', hi.highlight_id,
+                   a.media_url, NULL::text, a.media_type, a.thumbnail_url, a.caption, a.alt_text, a.alt_text_generated, a.is_mature,
+                   a.view_count, a.like_count, a.comment_count,
+                   a.original_created_at, NULL::timestamp
+            FROM story_archive a
+            JOIN story_highlight_items hi ON hi.memory_id = a.id
+            WHERE a.user_id = $1
+              AND (NOT a.is_mature OR $2 OR user_is_adult($5))
+              AND NOT geo_blocked('story', a.original_story_id, $6)
+            ORDER BY a.id, hi.added_at)
+
+            UNION ALL
+
+            SELECT a.id, 'archive', NULL::uuid,
+                   a.media_url, NULL::text, a.media_type, a.thumbnail_url, a.caption, a.alt_text, a.alt_text_generated, a.is_mature,
+                   a.view_count, a.like_count, a.comment_count,
+                   a.original_created_at, NULL::timestamp
+            FROM story_archive a
+            WHERE a.user_id = $1
+              AND $2
+              AND NOT EXISTS(SELECT 1 FROM story_highlight_items hi WHERE hi.memory_id = a.id)
+        ) grid
+        ORDER BY created_at DESC
+        LIMIT $3 OFFSET $4
+        "#
     )
+    .bind(user_id)
+    .bind(is_owner)
+    .bind(per_page + 1)
+    .bind(offset)
+    .bind(viewer_id)
+    .bind(&country)
     .fetch_all(state.pool.as_ref())
     .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .map_err(|e| {
+        eprintln!("Profile grid error: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
 
-    Ok(Json(stories))
+    let has_more = items.len() as i64 > per_page;
+    items.truncate(per_page as usize);
+    for item in &mut items {
+        state.media_service.sign_in_place(&mut item.media_url).await;
+        state.media_service.sign_opt(&mut item.thumbnail_url).await;
+    }
+
+    Ok(Json(ProfileGridResponse {
+        items,
+        page,
+        per_page,
+        has_more,
+    }))
 }
 
 // Update user profile
+#[utoipa::path(
+    post,
+    path = "/api/v1/profile/{user_id}/update",
+    tag = "social",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    request_body = UpdateProfileRequest,
+    responses((status = 200, description = "Success"))
+)]
 pub async fn update_user_profile(
     State(state): State<Arc<AppState>>,
     Path(user_id): Path<Uuid>,
@@ -615,12 +901,14 @@ pub async fn update_user_profile(
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    crate::cache::invalidate_profile(&state.redis, user_id).await;
+
     Ok(StatusCode::OK)
 }
 
 // ============= Comment Replies =============
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
 pub struct CommentWithReplies {
     pub id: Uuid,
     pub story_id: Uuid,
@@ -630,56 +918,89 @@ pub struct CommentWithReplies {
     pub comment_text: String,
     pub parent_comment_id: Option<Uuid>,
     pub reply_count: Option<i32>,
+    #[serde(with = "crate::timestamps")]
     pub created_at: NaiveDateTime,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct ReplyRequest {
     pub comment_text: String,
     pub parent_comment_id: Uuid,
 }
 
 // Add reply to comment
+#[utoipa::path(
+    post,
+    path = "/api/v1/social/reply/{story_id}/{user_id}",
+    tag = "social",
+    params(("story_id" = Uuid, Path, description = "Story ID"), ("user_id" = Uuid, Path, description = "User ID")),
+    request_body = ReplyRequest,
+    responses(
+        (status = 200, body = CommentWithReplies),
+        (status = 403, description = "Comments are off, or restricted to an audience the commenter isn't in (see reason)"),
+        (status = 413, description = "Reply is too long"),
+        (status = 429, description = "Daily comment limit reached")
+    )
+)]
 pub async fn add_reply(
     State(state): State<Arc<AppState>>,
     Path((story_id, user_id)): Path<(Uuid, Uuid)>,
     Json(payload): Json<ReplyRequest>,
-) -> Result<Json<CommentWithReplies>, StatusCode> {
-    let reply = sqlx::query_as!(
-        CommentWithReplies,
+) -> axum::response::Result<Json<CommentWithReplies>> {
+    crate::story_permissions::check_commenter(&state.pool, story_id, user_id).await?;
+    crate::quotas::check_comment(&state.pool, user_id, &payload.comment_text).await?;
+    let screened = crate::text_moderation::screen(&state.pool, &payload.comment_text).await?;
+
+    // Only the replier sees it if it contains a word the author muted
+    let hidden = crate::muted_words::hides_comment(&state.pool, story_id, &screened.text).await;
+
+    let reply = sqlx::query_as::<_, CommentWithReplies>(
         r#"
-        INSERT INTO story_comments (story_id, user_id, comment_text, parent_comment_id)
-        VALUES ($1, $2, $3, $4)
+        INSERT INTO story_comments (story_id, user_id, comment_text, parent_comment_id, hidden_by_filter)
+        VALUES ($1, $2, $3, $4, $5)
         RETURNING
             id,
             story_id,
             user_id,
-            (SELECT username FROM users WHERE id = $2) as "username!",
-            (SELECT avatar_url FROM users WHERE id = $2) as "avatar_url",
+            (SELECT username FROM users WHERE id = $2) AS username,
+            (SELECT avatar_url FROM users WHERE id = $2) AS avatar_url,
             comment_text,
             parent_comment_id,
             reply_count,
             created_at
-        "#,
-        story_id,
-        user_id,
-        payload.comment_text,
-        payload.parent_comment_id
+        "#
     )
+    .bind(story_id)
+    .bind(user_id)
+    .bind(&screened.text)
+    .bind(payload.parent_comment_id)
+    .bind(hidden)
     .fetch_one(state.pool.as_ref())
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    screened
+        .record(&state.pool, crate::text_moderation::CONTENT_COMMENT, reply.id, user_id)
+        .await;
 
     Ok(Json(reply))
 }
 
 // Get replies to a comment
+#[utoipa::path(
+    get,
+    path = "/api/v1/social/replies/{comment_id}",
+    tag = "social",
+    params(("comment_id" = Uuid, Path, description = "Comment ID")),
+    responses((status = 200, body = [CommentWithReplies])),
+    security((), ("bearer_auth" = []))
+)]
 pub async fn get_comment_replies(
+    viewer: Option<AuthUser>,
     State(state): State<Arc<AppState>>,
     Path(comment_id): Path<Uuid>,
+    headers: HeaderMap,
 ) -> Result<Json<Vec<CommentWithReplies>>, StatusCode> {
-    let replies = sqlx::query_as!(
-        CommentWithReplies,
+    let replies = sqlx::query_as::<_, CommentWithReplies>(
         r#"
         SELECT
             c.id,
@@ -694,10 +1015,17 @@ pub async fn get_comment_replies(
         FROM story_comments c
         JOIN users u ON c.user_id = u.id
         WHERE c.parent_comment_id = $1
+          AND story_comment_audience(c.story_id) <> 'off'
+          AND (NOT c.hidden_by_filter OR c.user_id = $3)
+          AND NOT geo_blocked('story', c.story_id, $2)
+          AND NOT geo_blocked('comment', c.parent_comment_id, $2)
+          AND NOT geo_blocked('comment', c.id, $2)
         ORDER BY c.created_at ASC
-        "#,
-        comment_id
+        "#
     )
+    .bind(comment_id)
+    .bind(crate::takedowns::request_country(&headers))
+    .bind(viewer.map(|v| v.id))
     .fetch_all(state.pool.as_ref())
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -0,0 +1,841 @@
+use axum::async_trait;
+use aws_sdk_s3::error::DisplayErrorContext;
+use aws_sdk_s3::presigning::PresigningConfig;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart, Delete, ObjectIdentifier};
+use aws_sdk_s3::Client as S3Client;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::stream::{BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::io::SeekFrom;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use uuid::Uuid;
+
+// Where media bytes live. MediaService and everything that reads or writes
+// objects go through the Storage trait, addressing objects by key; turning
+// keys into URLs stays with MediaService. STORAGE_PROVIDER picks the driver:
+//   - s3 (default): AWS S3, or R2 and other S3-compatible services when
+//     R2_ENDPOINT is set
+//   - local: a directory (LOCAL_STORAGE_PATH, default ./storage) for
+//     development without a bucket. It can't presign, so media is served
+//     through media_proxy. Objects are kept as objects/<key>, the headers
+//     they were stored with as meta/<key>.json, and writes in progress
+//     under uploads/ until they're moved into place.
+
+// S3 DeleteObjects accepts at most 1000 keys per request
+const DELETE_BATCH_SIZE: usize = 1000;
+// Read size when streaming a local file
+const LOCAL_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Storage driver, picked with STORAGE_PROVIDER
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+    S3,
+    Local,
+}
+
+impl Provider {
+    pub fn from_env() -> Provider {
+        match std::env::var("STORAGE_PROVIDER").unwrap_or_default().to_lowercase().as_str() {
+            "" | "s3" | "r2" => Provider::S3,
+            "local" => Provider::Local,
+            other => {
+                tracing::warn!("Unknown STORAGE_PROVIDER {:?}; using S3", other);
+                Provider::S3
+            }
+        }
+    }
+}
+
+/// Headers stored with an object and sent back when it's served
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectMeta {
+    pub content_type: Option<String>,
+    pub cache_control: Option<String>,
+    pub content_disposition: Option<String>,
+}
+
+impl ObjectMeta {
+    pub fn typed(content_type: &str) -> Self {
+        Self { content_type: Some(content_type.to_string()), ..Default::default() }
+    }
+}
+
+/// An object found by Storage::list
+pub struct ListedObject {
+    pub key: String,
+    pub size: i64,
+    pub last_modified: DateTime<Utc>,
+}
+
+/// Range and conditional request headers to apply to a read
+#[derive(Debug, Default)]
+pub struct ReadConditions {
+    pub range: Option<String>,
+    pub if_none_match: Option<String>,
+    /// An ETag the object must still have (If-Range)
+    pub if_match: Option<String>,
+}
+
+/// An object, or the requested range of it, as it's being read
+pub struct Fetched {
+    pub body: BoxStream<'static, Result<Bytes, std::io::Error>>,
+    pub meta: ObjectMeta,
+    pub content_length: Option<i64>,
+    /// Set when only a range is sent
+    pub content_range: Option<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+/// Why Storage::fetch returned no body
+#[derive(Debug)]
+pub enum FetchError {
+    NotModified,
+    RangeNotSatisfiable,
+    /// The object changed since the ETag in ReadConditions::if_match
+    PreconditionFailed,
+    NotFound,
+    Failed(String),
+}
+
+/// A part written by Storage::upload_part, for complete_upload
+pub struct UploadedPart {
+    pub part_number: i32,
+    pub etag: Option<String>,
+}
+
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// The bucket or directory, for log and error messages
+    fn describe(&self) -> String;
+
+    /// Whether the store can be reached at all
+    async fn check(&self) -> Result<(), String>;
+
+    async fn put(&self, key: &str, data: Vec<u8>, meta: &ObjectMeta) -> Result<(), String>;
+
+    /// Read a whole object into memory
+    async fn get(&self, key: &str) -> Result<Vec<u8>, String>;
+
+    /// Stream an object, honouring Range and conditional headers
+    async fn fetch(&self, key: &str, conditions: &ReadConditions) -> Result<Fetched, FetchError>;
+
+    async fn exists(&self, key: &str) -> Result<bool, String>;
+
+    /// Copy an object within the store, headers included
+    async fn copy(&self, source_key: &str, dest_key: &str) -> Result<(), String>;
+
+    /// Delete objects; keys that don't exist count as deleted. Returns the
+    /// deleted keys, per-key failures are logged and left out.
+    async fn delete(&self, keys: &[String]) -> Result<Vec<String>, String>;
+
+    /// Every object in the store
+    async fn list(&self) -> Result<Vec<ListedObject>, String>;
+
+    /// GET URL for an object that works without credentials for `ttl`
+    async fn presign_get(&self, key: &str, ttl: Duration) -> Result<String, String>;
+
+    /// Begin writing an object in parts. Returns the upload id.
+    async fn start_upload(&self, key: &str, meta: &ObjectMeta) -> Result<String, String>;
+
+    /// Write the next part of an upload. Parts arrive in order.
+    async fn upload_part(&self, key: &str, upload_id: &str, part_number: i32, data: Vec<u8>) -> Result<UploadedPart, String>;
+
+    async fn complete_upload(&self, key: &str, upload_id: &str, parts: Vec<UploadedPart>) -> Result<(), String>;
+
+    async fn abort_upload(&self, key: &str, upload_id: &str) -> Result<(), String>;
+
+    /// Abort uploads under `prefix` begun more than `older_than` ago and never
+    /// completed. Returns how many were aborted.
+    async fn abort_stale_uploads(&self, prefix: &str, older_than: chrono::Duration) -> Result<usize, String>;
+}
+
+pub struct S3Storage {
+    client: S3Client,
+    bucket: String,
+}
+
+impl S3Storage {
+    pub async fn from_env(bucket: &str) -> Self {
+        let config = aws_config::defaults(aws_config::BehaviorVersion::latest())
+            .load()
+            .await;
+
+        // Check if using Cloudflare R2 (or other S3-compatible service)
+        let client = if let Ok(r2_endpoint) = std::env::var("R2_ENDPOINT") {
+            tracing::info!(
+                endpoint = %r2_endpoint,
+                region = %std::env::var("AWS_REGION").unwrap_or_else(|_| "not set".to_string()),
+                access_key_set = std::env::var("AWS_ACCESS_KEY_ID").is_ok(),
+                secret_key_set = std::env::var("AWS_SECRET_ACCESS_KEY").is_ok(),
+                "Using Cloudflare R2"
+            );
+
+            // Configure S3 client with custom endpoint for R2
+            let s3_config = aws_sdk_s3::config::Builder::from(&config)
+                .endpoint_url(r2_endpoint)
+                .force_path_style(true) // R2 requires path-style URLs
+                .build();
+
+            S3Client::from_conf(s3_config)
+        } else {
+            // Standard AWS S3
+            tracing::info!("Using AWS S3");
+            S3Client::new(&config)
+        };
+        tracing::info!(bucket, "S3/R2 bucket");
+
+        Self { client, bucket: bucket.to_string() }
+    }
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+    fn describe(&self) -> String {
+        format!("Bucket {}", self.bucket)
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        self.client
+            .head_bucket()
+            .bucket(&self.bucket)
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(|e| DisplayErrorContext(&e).to_string())
+    }
+
+    async fn put(&self, key: &str, data: Vec<u8>, meta: &ObjectMeta) -> Result<(), String> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .body(ByteStream::from(data))
+            .set_content_type(meta.content_type.clone())
+            .set_cache_control(meta.cache_control.clone())
+            .set_content_disposition(meta.content_disposition.clone())
+            .send()
+            .await
+            .map_err(|e| format!("Failed to upload {} to S3/R2: {}", key, DisplayErrorContext(&e)))?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, String> {
+        let object = self.client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to download {}: {}", key, e))?;
+        let data = object.body.collect().await.map_err(|e| format!("Failed to read {}: {}", key, e))?;
+        Ok(data.into_bytes().to_vec())
+    }
+
+    async fn fetch(&self, key: &str, conditions: &ReadConditions) -> Result<Fetched, FetchError> {
+        let object = self.client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .set_range(conditions.range.clone())
+            .set_if_none_match(conditions.if_none_match.clone())
+            .set_if_match(conditions.if_match.clone())
+            .send()
+            .await;
+
+        let object = match object {
+            Ok(object) => object,
+            Err(e) => {
+                return Err(match e.raw_response().map(|r| r.status().as_u16()) {
+                    Some(304) => FetchError::NotModified,
+                    Some(416) => FetchError::RangeNotSatisfiable,
+                    Some(412) => FetchError::PreconditionFailed,
+                    Some(403) | Some(404) => FetchError::NotFound,
+                    _ => FetchError::Failed(e.to_string()),
+                });
+            }
+        };
+
+        let meta = ObjectMeta {
+            content_type: object.content_type().map(str::to_string),
+            cache_control: object.cache_control().map(str::to_string),
+            content_disposition: object.content_disposition().map(str::to_string),
+        };
+        let content_length = object.content_length();
+        let content_range = object.content_range().map(str::to_string);
+        let etag = object.e_tag().map(str::to_string);
+        let last_modified = object.last_modified().and_then(|t| DateTime::from_timestamp(t.secs(), t.subsec_nanos()));
+
+        // Chunks are passed on as S3 sends them; nothing is buffered here
+        let body = futures::stream::unfold(object.body, |mut body| async move {
+            body.next().await.map(|chunk| (chunk.map_err(std::io::Error::other), body))
+        });
+
+        Ok(Fetched { body: body.boxed(), meta, content_length, content_range, etag, last_modified })
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, String> {
+        match self.client.head_object().bucket(&self.bucket).key(key).send().await {
+            Ok(_) => Ok(true),
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_not_found()) => Ok(false),
+            Err(e) => Err(format!("Failed to look up {}: {}", key, e)),
+        }
+    }
+
+    async fn copy(&self, source_key: &str, dest_key: &str) -> Result<(), String> {
+        self.client
+            .copy_object()
+            .bucket(&self.bucket)
+            .copy_source(format!("{}/{}", self.bucket, source_key))
+            .key(dest_key)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to copy {} in S3: {}", source_key, e))?;
+        Ok(())
+    }
+
+    async fn delete(&self, keys: &[String]) -> Result<Vec<String>, String> {
+        let mut deleted = Vec::with_capacity(keys.len());
+
+        for chunk in keys.chunks(DELETE_BATCH_SIZE) {
+            let objects = chunk
+                .iter()
+                .map(|key| ObjectIdentifier::builder().key(key).build())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Invalid object key: {}", e))?;
+
+            let delete = Delete::builder()
+                .set_objects(Some(objects))
+                .build()
+                .map_err(|e| format!("Failed to build delete request: {}", e))?;
+
+            let output = self.client
+                .delete_objects()
+                .bucket(&self.bucket)
+                .delete(delete)
+                .send()
+                .await
+                .map_err(|e| format!("Failed to batch delete from S3: {}", e))?;
+
+            for error in output.errors() {
+                tracing::error!(
+                    "Failed to delete {}: {}",
+                    error.key().unwrap_or("?"),
+                    error.message().unwrap_or("unknown error")
+                );
+            }
+
+            deleted.extend(output.deleted().iter().filter_map(|d| d.key().map(|k| k.to_string())));
+        }
+
+        Ok(deleted)
+    }
+
+    async fn list(&self) -> Result<Vec<ListedObject>, String> {
+        let mut objects = Vec::new();
+        let mut continuation_token: Option<String> = None;
+
+        loop {
+            let response = self.client
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .set_continuation_token(continuation_token.take())
+                .send()
+                .await
+                .map_err(|e| format!("Failed to list objects: {}", e))?;
+
+            for object in response.contents() {
+                if let (Some(key), Some(size), Some(last_modified)) = (object.key(), object.size(), object.last_modified()) {
+                    objects.push(ListedObject {
+                        key: key.to_string(),
+                        size,
+                        last_modified: DateTime::from_timestamp(last_modified.secs(), last_modified.subsec_nanos())
+                            .unwrap_or_else(Utc::now),
+                    });
+                }
+            }
+
+            if response.is_truncated() != Some(true) {
+                return Ok(objects);
+            }
+            continuation_token = response.next_continuation_token().map(str::to_string);
+        }
+    }
+
+    async fn presign_get(&self, key: &str, ttl: Duration) -> Result<String, String> {
+        let config = PresigningConfig::expires_in(ttl).map_err(|e| format!("Invalid presign TTL: {}", e))?;
+        let request = self.client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .presigned(config)
+            .await
+            .map_err(|e| format!("Failed to presign {}: {}", key, e))?;
+
+        Ok(request.uri().to_string())
+    }
+
+    async fn start_upload(&self, key: &str, meta: &ObjectMeta) -> Result<String, String> {
+        let created = self.client
+            .create_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .set_content_type(meta.content_type.clone())
+            .set_cache_control(meta.cache_control.clone())
+            .set_content_disposition(meta.content_disposition.clone())
+            .send()
+            .await
+            .map_err(|e| format!("Failed to start multipart upload: {}", e))?;
+        Ok(created.upload_id().ok_or("S3 returned no upload id")?.to_string())
+    }
+
+    async fn upload_part(&self, key: &str, upload_id: &str, part_number: i32, data: Vec<u8>) -> Result<UploadedPart, String> {
+        let uploaded = self.client
+            .upload_part()
+            .bucket(&self.bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .part_number(part_number)
+            .body(ByteStream::from(data))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to upload part {}: {}", part_number, e))?;
+        Ok(UploadedPart { part_number, etag: uploaded.e_tag().map(str::to_string) })
+    }
+
+    async fn complete_upload(&self, key: &str, upload_id: &str, parts: Vec<UploadedPart>) -> Result<(), String> {
+        let parts = parts
+            .into_iter()
+            .map(|part| CompletedPart::builder().part_number(part.part_number).set_e_tag(part.etag).build())
+            .collect();
+        self.client
+            .complete_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(parts)).build())
+            .send()
+            .await
+            .map_err(|e| format!("Failed to complete multipart upload: {}", e))?;
+        Ok(())
+    }
+
+    async fn abort_upload(&self, key: &str, upload_id: &str) -> Result<(), String> {
+        self.client
+            .abort_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to abort multipart upload {}: {}", key, e))?;
+        Ok(())
+    }
+
+    async fn abort_stale_uploads(&self, prefix: &str, older_than: chrono::Duration) -> Result<usize, String> {
+        let cutoff = Utc::now() - older_than;
+        let mut aborted = 0;
+        let mut key_marker: Option<String> = None;
+        let mut upload_id_marker: Option<String> = None;
+
+        loop {
+            let listed = self.client
+                .list_multipart_uploads()
+                .bucket(&self.bucket)
+                .prefix(prefix)
+                .set_key_marker(key_marker.take())
+                .set_upload_id_marker(upload_id_marker.take())
+                .send()
+                .await
+                .map_err(|e| format!("Failed to list multipart uploads: {}", e))?;
+
+            for upload in listed.uploads() {
+                let (Some(key), Some(upload_id), Some(initiated)) = (upload.key(), upload.upload_id(), upload.initiated()) else {
+                    continue;
+                };
+                if initiated.secs() > cutoff.timestamp() {
+                    continue;
+                }
+                match self.abort_upload(key, upload_id).await {
+                    Ok(()) => aborted += 1,
+                    Err(e) => tracing::error!("{}", e),
+                }
+            }
+
+            if listed.is_truncated() != Some(true) {
+                return Ok(aborted);
+            }
+            key_marker = listed.next_key_marker().map(str::to_string);
+            upload_id_marker = listed.next_upload_id_marker().map(str::to_string);
+        }
+    }
+}
+
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn from_env() -> Self {
+        let root = PathBuf::from(std::env::var("LOCAL_STORAGE_PATH").unwrap_or_else(|_| "./storage".to_string()));
+        tracing::info!("Using local storage in {}", root.display());
+        Self { root }
+    }
+
+    fn object_path(&self, key: &str) -> Result<PathBuf, String> {
+        within(&self.root.join("objects"), key)
+    }
+
+    fn meta_path(&self, key: &str) -> Result<PathBuf, String> {
+        let mut path = within(&self.root.join("meta"), key)?.into_os_string();
+        path.push(".json");
+        Ok(path.into())
+    }
+
+    fn upload_path(&self, upload_id: &str) -> Result<PathBuf, String> {
+        within(&self.root.join("uploads"), upload_id)
+    }
+
+    async fn read_meta(&self, key: &str) -> ObjectMeta {
+        let Ok(path) = self.meta_path(key) else {
+            return ObjectMeta::default();
+        };
+        match tokio::fs::read(&path).await {
+            Ok(json) => serde_json::from_slice(&json).unwrap_or_default(),
+            Err(_) => ObjectMeta::default(),
+        }
+    }
+
+    async fn write_meta(&self, key: &str, meta: &ObjectMeta) -> Result<(), String> {
+        let path = self.meta_path(key)?;
+        if *meta == ObjectMeta::default() {
+            return remove_if_exists(&path).await.map_err(|e| format!("Failed to clear headers of {}: {}", key, e));
+        }
+        let json = serde_json::to_vec(meta).map_err(|e| e.to_string())?;
+        write_file(&path, &json).await.map_err(|e| format!("Failed to store headers of {}: {}", key, e))
+    }
+
+    // Move a finished file into place, so readers never see half an object
+    async fn publish(&self, staged: &Path, key: &str) -> Result<(), String> {
+        let path = self.object_path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(|e| format!("Failed to store {}: {}", key, e))?;
+        }
+        tokio::fs::rename(staged, &path).await.map_err(|e| format!("Failed to store {}: {}", key, e))
+    }
+
+    fn temp_path(&self) -> PathBuf {
+        self.root.join("uploads").join(format!("put-{}", Uuid::new_v4()))
+    }
+}
+
+// `key` as a path under `dir`, refusing anything that would leave it
+fn within(dir: &Path, key: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(key);
+    if key.is_empty() || !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(format!("Invalid object key: {}", key));
+    }
+    Ok(dir.join(relative))
+}
+
+async fn write_file(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(path, data).await
+}
+
+async fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+// Whether an If-None-Match / If-Match header value names `etag`
+fn etag_matches(header: &str, etag: &str) -> bool {
+    header.split(',').map(str::trim).any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+// The inclusive byte range a Range header asks for. Headers this doesn't
+// understand (other units, several ranges) mean the whole object; Err means
+// the range lies outside it.
+fn parse_range(header: &str, size: u64) -> Result<Option<(u64, u64)>, ()> {
+    let Some((start, end)) = header.trim().strip_prefix("bytes=").and_then(|spec| spec.split_once('-')) else {
+        return Ok(None);
+    };
+    if end.contains(',') {
+        return Ok(None);
+    }
+    let last = size.checked_sub(1).ok_or(())?;
+    let (start, end) = match (start.trim().parse::<u64>().ok(), end.trim().parse::<u64>().ok()) {
+        (Some(start), end) => (start, end.unwrap_or(last).min(last)),
+        (None, Some(suffix)) if suffix > 0 => (size.saturating_sub(suffix), last),
+        _ => return Ok(None),
+    };
+    if start > end {
+        return Err(());
+    }
+    Ok(Some((start, end)))
+}
+
+fn read_chunks(reader: impl AsyncRead + Send + Unpin + 'static) -> BoxStream<'static, Result<Bytes, std::io::Error>> {
+    futures::stream::unfold(Some(reader), |reader| async move {
+        let mut reader = reader?;
+        let mut buf = vec![0; LOCAL_CHUNK_BYTES];
+        match reader.read(&mut buf).await {
+            Ok(0) => None,
+            Ok(n) => {
+                buf.truncate(n);
+                Some((Ok(Bytes::from(buf)), Some(reader)))
+            }
+            Err(e) => Some((Err(e), None)),
+        }
+    })
+    .boxed()
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    fn describe(&self) -> String {
+        format!("Directory {}", self.root.display())
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        for dir in ["objects", "meta", "uploads"] {
+            tokio::fs::create_dir_all(self.root.join(dir)).await.map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    async fn put(&self, key: &str, data: Vec<u8>, meta: &ObjectMeta) -> Result<(), String> {
+        let temp = self.temp_path();
+        write_file(&temp, &data).await.map_err(|e| format!("Failed to write {}: {}", key, e))?;
+        self.write_meta(key, meta).await?;
+        self.publish(&temp, key).await
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, String> {
+        tokio::fs::read(self.object_path(key)?).await.map_err(|e| format!("Failed to read {}: {}", key, e))
+    }
+
+    async fn fetch(&self, key: &str, conditions: &ReadConditions) -> Result<Fetched, FetchError> {
+        let path = self.object_path(key).map_err(|_| FetchError::NotFound)?;
+        let mut file = match tokio::fs::File::open(&path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(FetchError::NotFound),
+            Err(e) => return Err(FetchError::Failed(e.to_string())),
+        };
+        let metadata = file.metadata().await.map_err(|e| FetchError::Failed(e.to_string()))?;
+        if metadata.is_dir() {
+            return Err(FetchError::NotFound);
+        }
+
+        let size = metadata.len();
+        let last_modified = metadata.modified().ok().map(DateTime::<Utc>::from);
+        let etag = format!("\"{:x}-{:x}\"", size, last_modified.map_or(0, |t| t.timestamp()));
+        if conditions.if_none_match.as_deref().is_some_and(|tags| etag_matches(tags, &etag)) {
+            return Err(FetchError::NotModified);
+        }
+        if conditions.if_match.as_deref().is_some_and(|tags| !etag_matches(tags, &etag)) {
+            return Err(FetchError::PreconditionFailed);
+        }
+
+        let range = match conditions.range.as_deref().map(|range| parse_range(range, size)) {
+            Some(Err(())) => return Err(FetchError::RangeNotSatisfiable),
+            Some(Ok(range)) => range,
+            None => None,
+        };
+        let (start, end) = range.unwrap_or((0, size.saturating_sub(1)));
+        let length = if size == 0 { 0 } else { end - start + 1 };
+        file.seek(SeekFrom::Start(start)).await.map_err(|e| FetchError::Failed(e.to_string()))?;
+
+        Ok(Fetched {
+            body: read_chunks(file.take(length)),
+            meta: self.read_meta(key).await,
+            content_length: Some(length as i64),
+            content_range: range.map(|(start, end)| format!("bytes {}-{}/{}", start, end, size)),
+            etag: Some(etag),
+            last_modified,
+        })
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, String> {
+        tokio::fs::try_exists(self.object_path(key)?).await.map_err(|e| format!("Failed to look up {}: {}", key, e))
+    }
+
+    async fn copy(&self, source_key: &str, dest_key: &str) -> Result<(), String> {
+        let temp = self.temp_path();
+        if let Some(parent) = temp.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(|e| e.to_string())?;
+        }
+        tokio::fs::copy(self.object_path(source_key)?, &temp)
+            .await
+            .map_err(|e| format!("Failed to copy {}: {}", source_key, e))?;
+        self.write_meta(dest_key, &self.read_meta(source_key).await).await?;
+        self.publish(&temp, dest_key).await
+    }
+
+    async fn delete(&self, keys: &[String]) -> Result<Vec<String>, String> {
+        let mut deleted = Vec::with_capacity(keys.len());
+        for key in keys {
+            let removed = match (self.object_path(key), self.meta_path(key)) {
+                (Ok(object), Ok(meta)) => match remove_if_exists(&object).await {
+                    Ok(()) => remove_if_exists(&meta).await,
+                    Err(e) => Err(e),
+                }
+                .map_err(|e| e.to_string()),
+                (Err(e), _) | (_, Err(e)) => Err(e),
+            };
+            match removed {
+                Ok(()) => deleted.push(key.clone()),
+                Err(e) => tracing::error!("Failed to delete {}: {}", key, e),
+            }
+        }
+        Ok(deleted)
+    }
+
+    async fn list(&self) -> Result<Vec<ListedObject>, String> {
+        let objects_dir = self.root.join("objects");
+        let mut objects = Vec::new();
+        let mut pending = vec![objects_dir.clone()];
+
+        while let Some(dir) = pending.pop() {
+            let mut entries = match tokio::fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(format!("Failed to list {}: {}", dir.display(), e)),
+            };
+            while let Some(entry) = entries.next_entry().await.map_err(|e| format!("Failed to list {}: {}", dir.display(), e))? {
+                let metadata = entry.metadata().await.map_err(|e| e.to_string())?;
+                if metadata.is_dir() {
+                    pending.push(entry.path());
+                    continue;
+                }
+                let Ok(relative) = entry.path().strip_prefix(&objects_dir).map(Path::to_path_buf) else {
+                    continue;
+                };
+                let key = relative.components().filter_map(|c| c.as_os_str().to_str()).collect::<Vec<_>>().join("/");
+                objects.push(ListedObject {
+                    key,
+                    size: metadata.len() as i64,
+                    last_modified: metadata.modified().map(DateTime::<Utc>::from).unwrap_or_else(|_| Utc::now()),
+                });
+            }
+        }
+
+        Ok(objects)
+    }
+
+    async fn presign_get(&self, key: &str, _ttl: Duration) -> Result<String, String> {
+        Err(format!("Can't presign {}: local storage is only served through the media proxy", key))
+    }
+
+    async fn start_upload(&self, _key: &str, meta: &ObjectMeta) -> Result<String, String> {
+        let upload_id = Uuid::new_v4().to_string();
+        write_file(&self.upload_path(&upload_id)?, &[]).await.map_err(|e| format!("Failed to start upload: {}", e))?;
+        let meta = serde_json::to_vec(meta).map_err(|e| e.to_string())?;
+        write_file(&self.upload_path(&format!("{}.json", upload_id))?, &meta)
+            .await
+            .map_err(|e| format!("Failed to start upload: {}", e))?;
+        Ok(upload_id)
+    }
+
+    async fn upload_part(&self, _key: &str, upload_id: &str, part_number: i32, data: Vec<u8>) -> Result<UploadedPart, String> {
+        let mut file = tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(self.upload_path(upload_id)?)
+            .await
+            .map_err(|e| format!("Failed to upload part {}: {}", part_number, e))?;
+        file.write_all(&data).await.map_err(|e| format!("Failed to upload part {}: {}", part_number, e))?;
+        Ok(UploadedPart { part_number, etag: None })
+    }
+
+    async fn complete_upload(&self, key: &str, upload_id: &str, _parts: Vec<UploadedPart>) -> Result<(), String> {
+        let meta_path = self.upload_path(&format!("{}.json", upload_id))?;
+        let meta = match tokio::fs::read(&meta_path).await {
+            Ok(json) => serde_json::from_slice(&json).unwrap_or_default(),
+            Err(_) => ObjectMeta::default(),
+        };
+        self.write_meta(key, &meta).await?;
+        self.publish(&self.upload_path(upload_id)?, key).await?;
+        let _ = remove_if_exists(&meta_path).await;
+        Ok(())
+    }
+
+    async fn abort_upload(&self, key: &str, upload_id: &str) -> Result<(), String> {
+        for path in [self.upload_path(upload_id)?, self.upload_path(&format!("{}.json", upload_id))?] {
+            remove_if_exists(&path).await.map_err(|e| format!("Failed to abort upload {}: {}", key, e))?;
+        }
+        Ok(())
+    }
+
+    // Every write passes through uploads/, so anything left there is
+    // abandoned whatever key it was meant for
+    async fn abort_stale_uploads(&self, _prefix: &str, older_than: chrono::Duration) -> Result<usize, String> {
+        let cutoff = Utc::now() - older_than;
+        let dir = self.root.join("uploads");
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(format!("Failed to list uploads: {}", e)),
+        };
+
+        let mut aborted = 0;
+        while let Some(entry) = entries.next_entry().await.map_err(|e| format!("Failed to list uploads: {}", e))? {
+            let modified = entry.metadata().await.and_then(|m| m.modified()).map(DateTime::<Utc>::from);
+            if modified.is_ok_and(|modified| modified > cutoff) {
+                continue;
+            }
+            match tokio::fs::remove_file(entry.path()).await {
+                Ok(()) if !entry.file_name().to_string_lossy().ends_with(".json") => aborted += 1,
+                Ok(()) => {}
+                Err(e) => tracing::error!("Failed to remove abandoned upload {}: {}", entry.path().display(), e),
+            }
+        }
+        Ok(aborted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_byte_ranges() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Ok(Some((0, 99))));
+        assert_eq!(parse_range("bytes=500-", 1000), Ok(Some((500, 999))));
+        assert_eq!(parse_range("bytes=-100", 1000), Ok(Some((900, 999))));
+        assert_eq!(parse_range(" bytes=990-2000 ", 1000), Ok(Some((990, 999))));
+        // A suffix longer than the object is the whole object
+        assert_eq!(parse_range("bytes=-5000", 1000), Ok(Some((0, 999))));
+    }
+
+    #[test]
+    fn ignores_ranges_it_does_not_serve() {
+        assert_eq!(parse_range("items=0-99", 1000), Ok(None));
+        assert_eq!(parse_range("bytes=0-9,20-29", 1000), Ok(None));
+        assert_eq!(parse_range("bytes=-0", 1000), Ok(None));
+        assert_eq!(parse_range("bytes=abc", 1000), Ok(None));
+    }
+
+    #[test]
+    fn rejects_unsatisfiable_ranges() {
+        assert_eq!(parse_range("bytes=1000-", 1000), Err(()));
+        assert_eq!(parse_range("bytes=50-10", 1000), Err(()));
+        assert_eq!(parse_range("bytes=0-", 0), Err(()));
+    }
+
+    #[test]
+    fn keeps_keys_inside_the_storage_root() {
+        let root = Path::new("/srv/storage");
+        assert_eq!(within(root, "media/abc.jpg"), Ok(root.join("media/abc.jpg")));
+        for key in ["", "../secret", "media/../../secret", "/etc/passwd", "./media/abc.jpg"] {
+            assert!(within(root, key).is_err(), "{:?} should be rejected", key);
+        }
+    }
+}
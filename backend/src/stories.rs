@@ -1,66 +1,246 @@
 use axum::{
-    extract::{State, Path, Multipart},
+    extract::{State, Path, Query, Multipart},
     Json,
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
 };
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use std::sync::Arc;
 use uuid::Uuid;
-use chrono::{Utc, NaiveDateTime};
-use aws_sdk_s3::primitives::ByteStream;
+use chrono::NaiveDateTime;
 
+use crate::admin::AuthUser;
+use crate::expiration::StoryTtl;
+use crate::fieldsets::FieldsQuery;
+use crate::mime_sniff::{FormatError, MediaKind};
+use crate::story_media::StoryMediaItem;
+use crate::upload_stream::StagedUpload;
 use crate::AppState;
 
-#[derive(Debug, Serialize, Deserialize)]
+// SQL condition (over stories s JOIN users u) for stories that may be shown
+// outside the app, in feeds and federation: not 18+, not by a minor and not
+// taken down anywhere
+pub const PUBLIC_STORY: &str = r#"
+    NOT s.is_mature
+    AND NOT user_is_minor(u.id)
+    AND NOT EXISTS (SELECT 1 FROM geo_takedowns t
+                    WHERE t.content_type = 'story' AND t.content_id = s.id AND t.lifted_at IS NULL)
+"#;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
 pub struct Story {
     pub id: Uuid,
     pub user_id: Uuid,
     pub media_url: String,
+    /// HLS master playlist for videos once packaged; prefer it over media_url
+    pub playback_url: Option<String>,
     pub media_type: String,
+    /// Poster frame for videos
     pub thumbnail_url: Option<String>,
+    /// Short looping muted clip for videos
+    pub preview_url: Option<String>,
     pub caption: Option<String>,
+    /// Screen reader description of the media
+    pub alt_text: Option<String>,
+    /// alt_text was generated rather than written by the author
+    pub alt_text_generated: bool,
+    /// 18+ content, only shown to adult viewers
+    #[sqlx(default)]
+    pub is_mature: bool,
     pub view_count: Option<i32>,
     pub like_count: Option<i32>,
     pub comment_count: Option<i32>,
+    #[serde(with = "crate::timestamps")]
     pub created_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps")]
     pub expires_at: NaiveDateTime,
     pub username: Option<String>,
+    /// Accepted co-author; the story also shows on their profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[sqlx(default)]
+    pub co_author_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[sqlx(default)]
+    pub co_author_username: Option<String>,
+    /// What the author lets viewers do; see story_permissions.rs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[sqlx(default)]
+    pub allow_sharing: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[sqlx(default)]
+    pub allow_comments: Option<bool>,
+    /// Who can comment: everyone, followers, following or off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[sqlx(default)]
+    pub comment_audience: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[sqlx(default)]
+    pub screenshot_warning: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[sqlx(default)]
+    pub hide_like_count: Option<bool>,
+    #[sqlx(default)]
     pub is_viewed: Option<bool>,
+    #[sqlx(default)]
     pub is_liked: Option<bool>,
 
     // Ad-specific fields
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[sqlx(default)]
     pub is_ad: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[sqlx(default)]
     pub ad_title: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[sqlx(default)]
     pub ad_link: Option<String>,
+
+    /// A creator's boosted story (story_boosts.rs), labelled as sponsored
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[sqlx(default)]
+    pub is_sponsored: Option<bool>,
+    /// Reported back with the boost's impression and click
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[sqlx(default)]
+    pub boost_id: Option<Uuid>,
+
+    /// Every item of a carousel story in order, the first being the story's
+    /// own media; empty for single-media stories
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[sqlx(skip)]
+    pub media_items: Vec<StoryMediaItem>,
+}
+
+impl Story {
+    /// Replace stored media URLs with short-lived signed ones
+    pub async fn sign_media(&mut self, media: &crate::media::MediaService) {
+        media.sign_in_place(&mut self.media_url).await;
+        media.sign_opt(&mut self.thumbnail_url).await;
+        media.sign_opt(&mut self.preview_url).await;
+        for item in &mut self.media_items {
+            media.sign_in_place(&mut item.media_url).await;
+        }
+    }
+
+    /// Leave out the like count if the author hid it and `viewer_id` isn't
+    /// the author or co-author
+    pub fn hide_likes_from(&mut self, viewer_id: Option<Uuid>) {
+        let is_author = viewer_id.is_some_and(|id| id == self.user_id || Some(id) == self.co_author_id);
+        if self.hide_like_count == Some(true) && !is_author {
+            self.like_count = None;
+        }
+    }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CreateStoryResponse {
     pub story_id: Uuid,
     pub upload_url: String,
+    #[serde(with = "crate::timestamps")]
+    pub expires_at: NaiveDateTime,
+    /// Kept up until deleted; expires_at is then a far-future placeholder
+    #[serde(default)]
+    pub is_permanent: bool,
     pub message: String,
+    /// Author-written alt text; generated alt text is filled in later
+    #[serde(default)]
+    pub alt_text: Option<String>,
+    /// Every item of a carousel story in order; empty for a single file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub media_items: Vec<StoryMediaItem>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct StoriesResponse {
     pub stories: Vec<Story>,
 }
 
+#[derive(sqlx::FromRow)]
+struct FeedAd {
+    id: Uuid,
+    created_by: Uuid,
+    title: String,
+    description: Option<String>,
+    image_url: Option<String>,
+    link_url: Option<String>,
+    is_mature: bool,
+    created_at: NaiveDateTime,
+}
+
+/// An ad dressed as a story for the feed
+fn ad_story(ad: &FeedAd) -> Story {
+    Story {
+        id: ad.id,
+        user_id: ad.created_by,
+        media_url: ad.image_url.clone().unwrap_or_default(),
+        playback_url: None,
+        media_type: "image".to_string(),
+        thumbnail_url: ad.image_url.clone(),
+        preview_url: None,
+        caption: ad.description.clone(),
+        alt_text: None,
+        alt_text_generated: false,
+        is_mature: ad.is_mature,
+        view_count: None,
+        like_count: None,
+        comment_count: None,
+        created_at: ad.created_at,
+        expires_at: crate::expiration::story_expires_at(),
+        username: Some("Sponsored".to_string()),
+        co_author_id: None,
+        co_author_username: None,
+        allow_sharing: None,
+        allow_comments: None,
+        comment_audience: None,
+        screenshot_warning: None,
+        hide_like_count: None,
+        is_viewed: None,
+        is_liked: None,
+        is_ad: Some(true),
+        ad_title: Some(ad.title.clone()),
+        ad_link: ad.link_url.clone(),
+        is_sponsored: None,
+        boost_id: None,
+        media_items: Vec::new(),
+    }
+}
+
 // Create a new story with multipart upload
+#[utoipa::path(
+    post,
+    path = "/api/v1/stories/create",
+    tag = "stories",
+    params(("Idempotency-Key" = Option<String>, Header, description = "Retries with the same key return the original story instead of posting it again")),
+    request_body(content = String, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, body = CreateStoryResponse),
+        (status = 400, description = "More than 10 files, ttl is not 1h, 6h, 24h, 48h or permanent, or an unknown comment_audience"),
+        (status = 403, description = "Permanent stories need a creator or business account"),
+        (status = 409, description = "A request with this idempotency key is still being processed"),
+        (status = 413, description = "Daily upload allowance exceeded, or file too large for its format"),
+        (status = 415, description = "Unsupported file format"),
+        (status = 422, description = "File rejected by malware scan"),
+        (status = 429, description = "Daily story limit reached"),
+        (status = 503, description = "Malware scanner unavailable")
+    )
+)]
 pub async fn create_story_multipart(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     mut multipart: Multipart,
-) -> Result<Json<CreateStoryResponse>, StatusCode> {
+) -> axum::response::Result<Json<CreateStoryResponse>> {
+    use crate::idempotency::{self, Reservation, SCOPE_CREATE_STORY};
+
     println!("📸 Received story creation request");
     
     let mut user_id: Option<Uuid> = None;
     let mut media_type: Option<String> = None;
     let mut caption: Option<String> = None;
-    let mut file_data: Option<Vec<u8>> = None;
-    let mut filename: Option<String> = None;
+    let mut alt_texts: Vec<String> = Vec::new();
+    let mut is_mature = false;
+    let mut ttl = StoryTtl::Default;
+    let mut permissions = StoryPermissionFlags::default();
+    let mut files: Vec<StagedUpload> = Vec::new();
 
     // Parse multipart form data
     while let Some(field) = multipart.next_field().await.unwrap() {
@@ -77,201 +257,557 @@ pub async fn create_story_multipart(
             "caption" => {
                 caption = Some(field.text().await.unwrap());
             }
+            "alt_text" => {
+                alt_texts.push(field_text(field).await?);
+            }
+            "is_mature" => {
+                is_mature = crate::age_gate::parse_flag(&field_text(field).await?);
+            }
+            "allow_sharing" => {
+                permissions.allow_sharing = crate::age_gate::parse_flag(&field_text(field).await?);
+            }
+            "allow_comments" => {
+                permissions.allow_comments = crate::age_gate::parse_flag(&field_text(field).await?);
+            }
+            "comment_audience" => {
+                let audience = field_text(field).await?.trim().to_lowercase();
+                if !crate::story_permissions::COMMENT_AUDIENCES.contains(&audience.as_str()) {
+                    return Err((
+                        StatusCode::BAD_REQUEST,
+                        format!("comment_audience must be one of: {}", crate::story_permissions::COMMENT_AUDIENCES.join(", ")),
+                    )
+                        .into());
+                }
+                permissions.comment_audience = Some(audience);
+            }
+            "screenshot_warning" => {
+                permissions.screenshot_warning = crate::age_gate::parse_flag(&field_text(field).await?);
+            }
+            "hide_like_count" => {
+                permissions.hide_like_count = crate::age_gate::parse_flag(&field_text(field).await?);
+            }
+            "ttl" => {
+                ttl = StoryTtl::parse(&field_text(field).await?).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+            }
             "file" => {
-                filename = field.file_name().map(|s| s.to_string());
-                file_data = Some(field.bytes().await.unwrap().to_vec());
+                // Streamed straight to S3; see upload_stream.rs. Staged files
+                // a failed request leaves behind are swept by bucket cleanup.
+                if files.len() == crate::story_media::MAX_ITEMS {
+                    state.media_service.discard_staged(&files).await;
+                    return Err((
+                        StatusCode::BAD_REQUEST,
+                        format!("A story holds at most {} files", crate::story_media::MAX_ITEMS),
+                    )
+                        .into());
+                }
+                match crate::upload_stream::stage(&state.media_service, field, None, false).await {
+                    Ok(staged) => files.push(staged),
+                    Err(e) => {
+                        eprintln!("❌ Rejected story upload: {}", e);
+                        state.media_service.discard_staged(&files).await;
+                        return Err((e.status(), e.to_string()).into());
+                    }
+                }
             }
             _ => {}
         }
     }
 
-    let user_id = user_id.ok_or_else(|| {
+    let Some(user_id) = user_id else {
         eprintln!("❌ Missing user_id in story creation");
-        StatusCode::BAD_REQUEST
-    })?;
-    let media_type = media_type.unwrap_or_else(|| "image".to_string());
-    let file_data = file_data.ok_or_else(|| {
+        state.media_service.discard_staged(&files).await;
+        return Err(StatusCode::BAD_REQUEST.into());
+    };
+    if files.is_empty() {
         eprintln!("❌ Missing file data in story creation");
-        StatusCode::BAD_REQUEST
-    })?;
-    // Always generate a unique filename to prevent overwriting
-    let unique_filename = format!("story_{}.jpg", Uuid::new_v4());
-    let filename = unique_filename;
+        return Err(StatusCode::BAD_REQUEST.into());
+    }
 
-    println!("📤 Uploading story for user {} ({})", user_id, filename);
+    // The file's bytes decide its format; a declared media_type only has to
+    // agree, and describes the first file
+    let declared = match media_type.as_deref() {
+        Some("image") => Some(MediaKind::Image),
+        Some("video") => Some(MediaKind::Video),
+        _ => None,
+    };
+    if let Some(expected) = declared.filter(|expected| files[0].format.kind != *expected) {
+        let e = FormatError::WrongKind { expected, found: files[0].format.content_type };
+        eprintln!("❌ Rejected story upload: {}", e);
+        state.media_service.discard_staged(&files).await;
+        return Err((e.status(), e.to_string()).into());
+    }
+    // alt_text fields pair up with file fields in order
+    let alt_texts: Vec<Option<String>> = alt_texts
+        .into_iter()
+        .take(files.len())
+        .map(|text| text.trim().to_string())
+        .map(|text| (!text.is_empty()).then_some(text))
+        .collect();
+    if alt_texts.iter().flatten().any(|text| text.chars().count() > crate::captioning::MAX_ALT_TEXT_LENGTH) {
+        eprintln!("❌ Story alt text too long");
+        state.media_service.discard_staged(&files).await;
+        return Err(StatusCode::PAYLOAD_TOO_LARGE.into());
+    }
+    let mut alt_texts = alt_texts.into_iter();
+    let uploads: Vec<StoryUpload> = files
+        .into_iter()
+        .map(|staged| StoryUpload { staged, alt_text: alt_texts.next().flatten() })
+        .collect();
 
-    // Upload to S3
-    let story_id = Uuid::new_v4();
-    let s3_key = format!("stories/{}/{}", user_id, filename);
-    
-    let byte_stream = ByteStream::from(file_data.clone());
-    state.media_service.s3_client
-        .put_object()
-        .bucket(&state.media_service.bucket_name)
-        .key(&s3_key)
-        .body(byte_stream)
-        .send()
+    let options = StoryOptions { caption, is_mature, ttl, permissions };
+    let key = match idempotency::key_from_headers(&headers) {
+        Ok(key) => key,
+        Err(e) => {
+            discard_uploads(&state, &uploads).await;
+            return Err(e.into());
+        }
+    };
+    let Some(key) = key else {
+        return publish_story(&state, user_id, options, uploads).await.map(Json);
+    };
+
+    let reservation = match idempotency::reserve::<CreateStoryResponse>(&state.pool, user_id, SCOPE_CREATE_STORY, &key).await {
+        Ok(reservation) => reservation,
+        Err(e) => {
+            eprintln!("❌ Failed to reserve idempotency key: {:?}", e);
+            discard_uploads(&state, &uploads).await;
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
+        }
+    };
+    match reservation {
+        Reservation::New => {}
+        Reservation::Replay(original) => {
+            println!("↩️  Replaying story {} for retried request", original.story_id);
+            discard_uploads(&state, &uploads).await;
+            return Ok(Json(original));
+        }
+        Reservation::InProgress => {
+            discard_uploads(&state, &uploads).await;
+            return Err(StatusCode::CONFLICT.into());
+        }
+    }
+
+    match publish_story(&state, user_id, options, uploads).await {
+        Ok(response) => {
+            if let Err(e) = idempotency::complete(&state.pool, user_id, SCOPE_CREATE_STORY, &key, &response).await {
+                eprintln!("❌ Failed to store idempotent response: {:?}", e);
+            }
+            Ok(Json(response))
+        }
+        Err(status) => {
+            idempotency::release(&state.pool, user_id, SCOPE_CREATE_STORY, &key).await;
+            Err(status)
+        }
+    }
+}
+
+// What the author picked for a new story besides the files
+struct StoryOptions {
+    caption: Option<String>,
+    is_mature: bool,
+    ttl: StoryTtl,
+    permissions: StoryPermissionFlags,
+}
+
+// Per-story permissions picked at upload; see story_permissions.rs
+struct StoryPermissionFlags {
+    allow_sharing: bool,
+    allow_comments: bool,
+    /// None follows the author's account setting
+    comment_audience: Option<String>,
+    screenshot_warning: bool,
+    hide_like_count: bool,
+}
+
+impl Default for StoryPermissionFlags {
+    fn default() -> Self {
+        Self {
+            allow_sharing: true,
+            allow_comments: true,
+            comment_audience: None,
+            screenshot_warning: false,
+            hide_like_count: false,
+        }
+    }
+}
+
+// One file of a new story, staged but not yet stored
+struct StoryUpload {
+    staged: StagedUpload,
+    alt_text: Option<String>,
+}
+
+// A form field whose body can't be read as text is a bad request
+async fn field_text(field: axum::extract::multipart::Field<'_>) -> Result<String, (StatusCode, String)> {
+    field
+        .text()
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid form field: {}", e)))
+}
+
+async fn discard_uploads(state: &AppState, uploads: &[StoryUpload]) {
+    state.media_service.discard_staged(uploads.iter().map(|u| &u.staged)).await;
+}
+
+// Permanent stories are a creator feature; personal accounts get the timed options
+async fn check_permanent_allowed(state: &AppState, user_id: Uuid) -> axum::response::Result<()> {
+    let account_type = sqlx::query_scalar::<_, String>("SELECT account_type FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(state.pool.as_ref())
         .await
         .map_err(|e| {
-            eprintln!("❌ S3 upload failed: {:?}", e);
+            eprintln!("❌ Failed to load account type: {:?}", e);
             StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if account_type == "personal" {
+        return Err((StatusCode::FORBIDDEN, "Permanent stories need a creator or business account").into());
+    }
+    Ok(())
+}
 
-    // Construct public URL
-    let media_url = if let Some(ref public_base) = state.media_service.public_url_base {
-        format!("{}/{}", public_base, s3_key)
-    } else {
-        format!("https://{}.s3.amazonaws.com/{}", state.media_service.bucket_name, s3_key)
+// Everything that can refuse a new story before its files are stored
+async fn check_new_story(
+    state: &AppState,
+    user_id: Uuid,
+    ttl: StoryTtl,
+    total_bytes: usize,
+    caption: Option<&str>,
+) -> axum::response::Result<Option<crate::text_moderation::Screened>> {
+    if ttl.is_permanent() {
+        check_permanent_allowed(state, user_id).await?;
+    }
+    crate::quotas::check_story(&state.pool, user_id, total_bytes).await?;
+    match caption {
+        Some(text) => Ok(Some(crate::text_moderation::screen(&state.pool, text).await?)),
+        None => Ok(None),
+    }
+}
+
+// Upload story media and create the story row. More than one file makes a
+// carousel: the first is the story's own media and all of them are listed in
+// story_media.
+async fn publish_story(
+    state: &AppState,
+    user_id: Uuid,
+    options: StoryOptions,
+    uploads: Vec<StoryUpload>,
+) -> axum::response::Result<CreateStoryResponse> {
+    let StoryOptions { caption, is_mature, ttl, permissions } = options;
+    let total_bytes: usize = uploads.iter().map(|u| u.staged.size).sum();
+    let screened = match check_new_story(state, user_id, ttl, total_bytes, caption.as_deref()).await {
+        Ok(screened) => screened,
+        Err(e) => {
+            discard_uploads(state, &uploads).await;
+            return Err(e);
+        }
     };
+    let caption = screened.as_ref().map(|s| s.text.clone());
+
+    println!("📤 Uploading story for user {} ({} files, {} bytes)", user_id, uploads.len(), total_bytes);
+
+    // Upload to S3; re-posting the same file reuses the stored object
+    let story_id = Uuid::new_v4();
+    let mut items = Vec::with_capacity(uploads.len());
+    let mut uploads = uploads.into_iter();
+    while let Some(upload) = uploads.next() {
+        let media_type = upload.staged.format.kind.as_str().to_string();
+        let media_url = match state.media_service.store_staged(&state.pool, upload.staged, Some(user_id)).await {
+            Ok(media_url) => media_url,
+            Err(e) => {
+                eprintln!("❌ S3 upload failed: {}", e);
+                discard_uploads(state, uploads.as_slice()).await;
+                return Err((e.status(), e.to_string()).into());
+            }
+        };
+        items.push(crate::story_media::NewItem {
+            media_url,
+            media_type,
+            alt_text: upload.alt_text,
+        });
+    }
+
+    crate::quotas::record_upload(&state.pool, user_id, total_bytes).await;
 
     // Create story in database
-    let expires_at = Utc::now().naive_utc() + chrono::Duration::hours(24);
+    let expires_at = ttl.expires_at();
+    let media_url = items[0].media_url.clone();
+    let media_type = items[0].media_type.clone();
+    let alt_text = items[0].alt_text.clone();
 
-    sqlx::query!(
+    let mut tx = state.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    sqlx::query(
         r#"
-        INSERT INTO stories (id, user_id, media_url, media_type, caption, expires_at)
-        VALUES ($1, $2, $3, $4, $5, $6)
-        "#,
-        story_id,
-        user_id,
-        media_url,
-        media_type,
-        caption,
-        expires_at
+        INSERT INTO stories (id, user_id, media_url, media_type, caption, alt_text, is_mature, expires_at, is_permanent,
+                             allow_sharing, allow_comments, comment_audience, screenshot_warning, hide_like_count)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+        "#
     )
-    .execute(state.pool.as_ref())
+    .bind(story_id)
+    .bind(user_id)
+    .bind(&media_url)
+    .bind(&media_type)
+    .bind(&caption)
+    .bind(&alt_text)
+    .bind(is_mature)
+    .bind(expires_at)
+    .bind(ttl.is_permanent())
+    .bind(permissions.allow_sharing)
+    .bind(permissions.allow_comments)
+    .bind(&permissions.comment_audience)
+    .bind(permissions.screenshot_warning)
+    .bind(permissions.hide_like_count)
+    .execute(&mut *tx)
     .await
     .map_err(|e| {
         eprintln!("❌ Database insert failed: {:?}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
+    let mut media_items = if items.len() > 1 {
+        crate::story_media::insert(&mut tx, story_id, &items).await.map_err(|e| {
+            eprintln!("❌ Carousel insert failed: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+    } else {
+        Vec::new()
+    };
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // One reference per item, even when a file appears twice
+    for item in &items {
+        state.media_service.acquire(&state.pool, &[&item.media_url]).await;
+    }
+    if let Some(screened) = &screened {
+        screened
+            .record(&state.pool, crate::text_moderation::CONTENT_CAPTION, story_id, user_id)
+            .await;
+    }
 
     println!("✅ Story created successfully: {}", story_id);
 
-    Ok(Json(CreateStoryResponse {
+    if alt_text.is_none() {
+        crate::captioning::enqueue(&state.pool, story_id).await;
+    }
+    crate::video_render::enqueue_video_processing(&state.pool, story_id, &media_type).await;
+    invalidate_author(state, user_id).await;
+    crate::federation::publish_story(state, story_id).await;
+
+    crate::webhooks::dispatch(
+        &state.pool,
+        &[user_id],
+        crate::webhooks::EVENT_STORY_CREATED,
+        serde_json::json!({
+            "story_id": story_id,
+            "user_id": user_id,
+            "media_url": media_url,
+            "media_type": media_type,
+            "caption": caption,
+            "alt_text": alt_text,
+            "is_mature": is_mature,
+            "expires_at": expires_at.and_utc(),
+            "is_permanent": ttl.is_permanent(),
+            "media_count": items.len(),
+        }),
+    )
+    .await;
+
+    for item in &mut media_items {
+        state.media_service.sign_in_place(&mut item.media_url).await;
+    }
+
+    Ok(CreateStoryResponse {
         story_id,
-        upload_url: media_url.clone(),
+        upload_url: state.media_service.sign_url(&media_url).await,
+        expires_at,
+        is_permanent: ttl.is_permanent(),
         message: "Story created successfully".to_string(),
-    }))
+        alt_text,
+        media_items,
+    })
 }
 
 // Get stories for a specific user
+#[utoipa::path(
+    get,
+    path = "/api/v1/stories/user/{user_id}",
+    tag = "stories",
+    params(("user_id" = Uuid, Path, description = "User ID"), FieldsQuery),
+    responses((status = 200, body = StoriesResponse)),
+    security((), ("bearer_auth" = []))
+)]
 pub async fn get_user_stories(
+    viewer: Option<AuthUser>,
     State(state): State<Arc<AppState>>,
     Path(user_id): Path<Uuid>,
-) -> Result<Json<StoriesResponse>, StatusCode> {
-    let stories = sqlx::query!(
-        r#"
-        SELECT
-            s.id,
-            s.user_id,
-            s.media_url,
-            s.media_type,
-            s.thumbnail_url,
-            s.caption,
-            s.view_count,
-            s.like_count,
-            s.comment_count,
-            s.created_at,
-            s.expires_at,
-            u.username
-        FROM stories s
-        JOIN users u ON s.user_id = u.id
-        WHERE s.user_id = $1
-        AND s.expires_at > NOW()
-        ORDER BY s.created_at DESC
-        "#,
-        user_id
-    )
-    .fetch_all(state.pool.as_ref())
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    .into_iter()
-    .map(|row| Story {
-        id: row.id,
-        user_id: row.user_id,
-        media_url: row.media_url,
-        media_type: row.media_type,
-        thumbnail_url: row.thumbnail_url,
-        caption: row.caption,
-        view_count: row.view_count,
-        like_count: row.like_count,
-        comment_count: row.comment_count,
-        created_at: row.created_at,
-        expires_at: row.expires_at,
-        username: Some(row.username),
-        is_viewed: None,
-        is_liked: None,
-        is_ad: None,
-        ad_title: None,
-        ad_link: None,
-    })
-    .collect();
+    Query(fields): Query<FieldsQuery>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let viewer_id = viewer.map(|v| v.id);
+    let country = crate::takedowns::request_country(&headers);
+
+    // Cached per country for every viewer; mature stories are filtered below
+    let key = crate::cache::stories_key(&state.redis, user_id, country.as_deref()).await;
+    let mut stories = match crate::cache::get::<Vec<Story>>(&state.redis, &key).await {
+        Some(stories) => stories,
+        None => {
+            let mut stories = sqlx::query_as::<_, Story>(
+                r#"
+                SELECT
+                    s.id,
+                    s.user_id,
+                    s.media_url,
+                    s.playback_url,
+                    s.media_type,
+                    s.thumbnail_url,
+                    s.preview_url,
+                    s.caption,
+                    s.alt_text,
+                    s.alt_text_generated,
+                    s.is_mature,
+                    s.view_count,
+                    s.like_count,
+                    s.comment_count,
+                    s.created_at,
+                    s.expires_at,
+                    u.username,
+                    sc.user_id AS co_author_id,
+                    cu.username AS co_author_username,
+                    s.allow_sharing,
+                    s.allow_comments,
+                    story_comment_audience(s.id) AS comment_audience,
+                    s.screenshot_warning,
+                    s.hide_like_count
+                FROM stories s
+                JOIN users u ON s.user_id = u.id
+                LEFT JOIN story_collaborators sc ON sc.story_id = s.id AND sc.status = 'accepted'
+                LEFT JOIN users cu ON cu.id = sc.user_id
+                WHERE (s.user_id = $1 OR sc.user_id = $1)
+                AND s.expires_at > NOW()
+                AND NOT geo_blocked('story', s.id, $2)
+                ORDER BY s.created_at DESC
+                "#
+            )
+            .bind(user_id)
+            .bind(&country)
+            .fetch_all(state.pool.as_ref())
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            crate::story_media::attach(&state.pool, &mut stories).await;
+
+            crate::cache::set(&state.redis, &key, &stories, crate::cache::STORIES_TTL_SECS).await;
+            stories
+        }
+    };
+
+    // A cached list can outlive some of its stories
+    let now = chrono::Utc::now().naive_utc();
+    stories.retain(|s| s.expires_at > now);
+
+    if viewer_id != Some(user_id) && stories.iter().any(|s| s.is_mature) {
+        let viewer_is_adult = match viewer_id {
+            Some(viewer_id) => sqlx::query_scalar::<_, Option<bool>>("SELECT user_is_adult($1)")
+                .bind(viewer_id)
+                .fetch_one(state.pool.as_ref())
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                .unwrap_or(false),
+            None => false,
+        };
+        if !viewer_is_adult {
+            stories.retain(|s| !s.is_mature);
+        }
+    }
+
+    for story in &mut stories {
+        story.hide_likes_from(viewer_id);
+        story.sign_media(&state.media_service).await;
+    }
+
+    stories_response(&fields, &stories)
+}
 
-    Ok(Json(StoriesResponse { stories }))
+/// A StoriesResponse trimmed to the fields the client asked for
+fn stories_response(fields: &FieldsQuery, stories: &[Story]) -> Result<Json<serde_json::Value>, StatusCode> {
+    let stories = fields.select(stories).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(serde_json::json!({ "stories": stories })))
 }
 
 // Get feed stories (from all users or friends)
+#[utoipa::path(
+    get,
+    path = "/api/v1/stories/feed/{viewer_id}",
+    tag = "stories",
+    params(("viewer_id" = Uuid, Path, description = "Viewer ID"), FieldsQuery),
+    responses((status = 200, body = StoriesResponse))
+)]
 pub async fn get_feed_stories(
     State(state): State<Arc<AppState>>,
     Path(viewer_id): Path<Uuid>,
-) -> Result<Json<StoriesResponse>, StatusCode> {
+    Query(fields): Query<FieldsQuery>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let country = crate::takedowns::request_country(&headers);
+
     // Fetch regular stories (excluding already viewed ones)
-    let mut stories = sqlx::query!(
-        r#"
+    let mut stories = crate::query_metrics::observe(
+        "stories.feed",
+        sqlx::query_as::<_, Story>(
+            r#"
         SELECT
             s.id,
             s.user_id,
             s.media_url,
+            s.playback_url,
             s.media_type,
             s.thumbnail_url,
+            s.preview_url,
             s.caption,
+            s.alt_text,
+            s.alt_text_generated,
+            s.is_mature,
             s.view_count,
             s.like_count,
             s.comment_count,
             s.created_at,
             s.expires_at,
             u.username,
+            sc.user_id AS co_author_id,
+            cu.username AS co_author_username,
+            s.allow_sharing,
+            s.allow_comments,
+            story_comment_audience(s.id) AS comment_audience,
+            s.screenshot_warning,
+            s.hide_like_count,
             FALSE as is_viewed,
             EXISTS(SELECT 1 FROM story_likes sl WHERE sl.story_id = s.id AND sl.user_id = $1) as is_liked
         FROM stories s
         JOIN users u ON s.user_id = u.id
+        LEFT JOIN story_collaborators sc ON sc.story_id = s.id AND sc.status = 'accepted'
+        LEFT JOIN users cu ON cu.id = sc.user_id
         LEFT JOIN story_views sv ON s.id = sv.story_id AND sv.viewer_id = $1
         WHERE s.expires_at > NOW()
+          AND (NOT s.is_permanent OR s.created_at > NOW() - make_interval(hours => $3))
           AND sv.viewer_id IS NULL
+          AND (NOT s.is_mature OR s.user_id = $1 OR user_is_adult($1))
+          AND NOT geo_blocked('story', s.id, $2)
         ORDER BY s.created_at DESC
         LIMIT 50
-        "#,
-        viewer_id
+        "#
+        )
+        .bind(viewer_id)
+        .bind(&country)
+        .bind(crate::expiration::default_story_ttl_hours() as i32)
+        .fetch_all(state.read_pool()),
     )
-    .fetch_all(state.pool.as_ref())
     .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    .into_iter()
-    .map(|row| Story {
-        id: row.id,
-        user_id: row.user_id,
-        media_url: row.media_url,
-        media_type: row.media_type,
-        thumbnail_url: row.thumbnail_url,
-        caption: row.caption,
-        view_count: row.view_count,
-        like_count: row.like_count,
-        comment_count: row.comment_count,
-        created_at: row.created_at,
-        expires_at: row.expires_at,
-        username: Some(row.username),
-        is_viewed: row.is_viewed,
-        is_liked: row.is_liked,
-        is_ad: None,
-        ad_title: None,
-        ad_link: None,
-    })
-    .collect::<Vec<Story>>();
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    // Fetch active ads that this user hasn't seen yet
-    let ads = sqlx::query!(
-        r#"
+    // Fetch active ads that this user hasn't seen yet and that target their location
+    let ad_ctx = crate::ad_targeting::ServeContext::from_headers(&headers);
+    let ads = crate::query_metrics::observe(
+        "stories.feed_ads",
+        sqlx::query_as::<_, FeedAd>(
+            r#"
         SELECT
             a.id,
             a.created_by,
@@ -279,117 +815,191 @@ pub async fn get_feed_stories(
             a.description,
             a.image_url,
             a.link_url,
+            a.is_mature,
             a.created_at
         FROM advertisements a
         LEFT JOIN ad_impressions ai ON a.id = ai.ad_id AND ai.user_id = $1
         WHERE a.status = 'active'
-            AND a.current_impressions < a.target_impressions
+            AND ad_in_budget(a.billing_model, a.current_impressions, a.target_impressions, a.budget_balance, a.cpm_rate)
             AND (a.expires_at IS NULL OR a.expires_at > NOW())
             AND ai.id IS NULL
+            AND (NOT a.is_mature OR user_is_adult($1))
+            AND ad_geo_match(a.target_countries, a.target_languages, $2, $3)
         ORDER BY RANDOM()
         LIMIT 10
-        "#,
-        viewer_id
+        "#
+        )
+        .bind(viewer_id)
+        .bind(&ad_ctx.country)
+        .bind(&ad_ctx.language)
+        .fetch_all(state.pool.as_ref()),
     )
-    .fetch_all(state.pool.as_ref())
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    // Inject ads into feed every 5 stories
-    if !ads.is_empty() {
+    // Boosted stories of creators the viewer doesn't follow; ones already in
+    // the feed on their own merit aren't charged for
+    let boosts = crate::story_boosts::for_feed(state.read_pool(), viewer_id, &country)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let boosts: Vec<Story> = boosts
+        .into_iter()
+        .filter(|boosted| !stories.iter().any(|s| s.id == boosted.id))
+        .collect();
+
+    // Inject a sponsored story after every `interval` stories (a runtime
+    // setting; 0 means none), boosted stories and ads taking turns
+    let interval = crate::runtime_settings::int(crate::runtime_settings::AD_STORY_INTERVAL).max(0) as usize;
+    if (!ads.is_empty() || !boosts.is_empty()) && interval > 0 {
+        let mut sponsored = Vec::with_capacity(boosts.len() + ads.len());
+        let (mut boosts, mut ad_stories) = (boosts.into_iter(), ads.iter().map(ad_story));
+        loop {
+            let (boost, ad) = (boosts.next(), ad_stories.next());
+            if boost.is_none() && ad.is_none() {
+                break;
+            }
+            sponsored.extend(boost);
+            sponsored.extend(ad);
+        }
+
         let mut result = Vec::new();
-        let mut ad_index = 0;
+        let mut sponsored = sponsored.into_iter();
+        let mut served = Vec::new();
 
         for (i, story) in stories.into_iter().enumerate() {
             result.push(story);
 
-            // Insert an ad after every 2 stories (was 5, reduced for testing)
-            if (i + 1) % 2 == 0 && ad_index < ads.len() {
-                let ad = &ads[ad_index];
-                let ad_story = Story {
-                    id: ad.id,
-                    user_id: ad.created_by,
-                    media_url: ad.image_url.clone().unwrap_or_default(),
-                    media_type: "image".to_string(),
-                    thumbnail_url: ad.image_url.clone(),
-                    caption: ad.description.clone(),
-                    view_count: None,
-                    like_count: None,
-                    comment_count: None,
-                    created_at: ad.created_at,
-                    expires_at: Utc::now().naive_utc() + chrono::Duration::days(1),
-                    username: Some("Sponsored".to_string()),
-                    is_viewed: None,
-                    is_liked: None,
-                    is_ad: Some(true),
-                    ad_title: Some(ad.title.clone()),
-                    ad_link: ad.link_url.clone(),
-                };
-                result.push(ad_story);
-                ad_index += 1;
+            if (i + 1) % interval == 0 {
+                if let Some(next) = sponsored.next() {
+                    if next.is_ad == Some(true) {
+                        served.push(next.id);
+                    }
+                    result.push(next);
+                }
             }
         }
 
         stories = result;
+        crate::ad_targeting::log_decision("story_feed", viewer_id, &ad_ctx, &served);
+    }
+
+    crate::story_media::attach(state.read_pool(), &mut stories).await;
+
+    // Ad creatives live outside the bucket and pass through unchanged
+    for story in &mut stories {
+        story.hide_likes_from(Some(viewer_id));
+        story.sign_media(&state.media_service).await;
     }
 
-    Ok(Json(StoriesResponse { stories }))
+    stories_response(&fields, &stories)
 }
 
-// Get stories grouped by user for the stories page
+// Get stories grouped by user for the stories page; co-authored stories
+// show up under both authors
+#[utoipa::path(
+    get,
+    path = "/api/v1/stories/by-user/{viewer_id}",
+    tag = "stories",
+    params(("viewer_id" = Uuid, Path, description = "Viewer ID")),
+    responses((status = 200, body = serde_json::Value))
+)]
 pub async fn get_stories_by_user(
     State(state): State<Arc<AppState>>,
     Path(viewer_id): Path<Uuid>,
+    headers: HeaderMap,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    #[derive(Debug, Serialize)]
+    let country = crate::takedowns::request_country(&headers);
+
+    #[derive(Debug, Serialize, sqlx::FromRow)]
     struct UserStories {
         user_id: Uuid,
         username: String,
         latest_story_url: String,
+        latest_playback_url: Option<String>,
+        latest_media_type: String,
+        /// Poster frame of the latest story when it's a video
+        latest_thumbnail_url: Option<String>,
+        latest_preview_url: Option<String>,
         story_count: i64,
         has_unviewed: bool,
     }
 
-    let user_stories = sqlx::query_as!(
-        UserStories,
+    let mut user_stories = sqlx::query_as::<_, UserStories>(
         r#"
         SELECT 
-            s.user_id,
+            a.user_id,
             u.username,
-            (SELECT media_url FROM stories WHERE user_id = s.user_id AND expires_at > NOW() ORDER BY created_at DESC LIMIT 1) as "latest_story_url!",
-            COUNT(DISTINCT s.id) as "story_count!",
-            COALESCE(BOOL_OR(sv.viewer_id IS NULL), false) as "has_unviewed!"
-        FROM stories s
-        JOIN users u ON s.user_id = u.id
+            latest.media_url AS latest_story_url,
+            latest.playback_url AS latest_playback_url,
+            latest.media_type AS latest_media_type,
+            latest.thumbnail_url AS latest_thumbnail_url,
+            latest.preview_url AS latest_preview_url,
+            COUNT(DISTINCT s.id) AS story_count,
+            COALESCE(BOOL_OR(sv.viewer_id IS NULL), false) AS has_unviewed
+        FROM story_authors a
+        JOIN stories s ON s.id = a.story_id
+        JOIN users u ON a.user_id = u.id
         LEFT JOIN story_views sv ON s.id = sv.story_id AND sv.viewer_id = $1
+        CROSS JOIN LATERAL (
+            SELECT ls.media_url, ls.playback_url, ls.media_type, ls.thumbnail_url, ls.preview_url
+            FROM story_authors la
+            JOIN stories ls ON ls.id = la.story_id
+            WHERE la.user_id = a.user_id AND ls.expires_at > NOW()
+              AND (NOT ls.is_permanent OR ls.created_at > NOW() - make_interval(hours => $3))
+              AND (NOT ls.is_mature OR ls.user_id = $1 OR user_is_adult($1))
+              AND NOT geo_blocked('story', ls.id, $2)
+            ORDER BY ls.created_at DESC
+            LIMIT 1
+        ) latest
         WHERE s.expires_at > NOW()
-        GROUP BY s.user_id, u.username
+          AND (NOT s.is_permanent OR s.created_at > NOW() - make_interval(hours => $3))
+          AND (NOT s.is_mature OR s.user_id = $1 OR user_is_adult($1))
+          AND NOT geo_blocked('story', s.id, $2)
+        GROUP BY a.user_id, u.username, latest.media_url, latest.playback_url, latest.media_type, latest.thumbnail_url, latest.preview_url
         ORDER BY COALESCE(BOOL_OR(sv.viewer_id IS NULL), false) DESC, MAX(s.created_at) DESC
-        "#,
-        viewer_id
+        "#
     )
-    .fetch_all(state.pool.as_ref())
+    .bind(viewer_id)
+    .bind(&country)
+    .bind(crate::expiration::default_story_ttl_hours() as i32)
+    .fetch_all(state.read_pool())
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    for entry in &mut user_stories {
+        state.media_service.sign_in_place(&mut entry.latest_story_url).await;
+        state.media_service.sign_opt(&mut entry.latest_thumbnail_url).await;
+        state.media_service.sign_opt(&mut entry.latest_preview_url).await;
+    }
+
     Ok(Json(serde_json::json!({ "users": user_stories })))
 }
 
 // Mark story as viewed
+#[utoipa::path(
+    post,
+    path = "/api/v1/stories/{story_id}/view/{viewer_id}",
+    tag = "stories",
+    params(("story_id" = Uuid, Path, description = "Story ID"), ("viewer_id" = Uuid, Path, description = "Viewer ID")),
+    responses((status = 200, description = "Success"))
+)]
 pub async fn mark_story_viewed(
     State(state): State<Arc<AppState>>,
     Path((story_id, viewer_id)): Path<(Uuid, Uuid)>,
+    headers: axum::http::HeaderMap,
 ) -> Result<StatusCode, StatusCode> {
-    // Insert view record
-    sqlx::query!(
+    // Insert view record; country and device feed creator insights
+    sqlx::query(
         r#"
-        INSERT INTO story_views (story_id, viewer_id)
-        VALUES ($1, $2)
+        INSERT INTO story_views (story_id, viewer_id, country, device_type)
+        VALUES ($1, $2, $3, $4)
         ON CONFLICT (story_id, viewer_id) DO NOTHING
-        "#,
-        story_id,
-        viewer_id
+        "#
     )
+    .bind(story_id)
+    .bind(viewer_id)
+    .bind(crate::takedowns::request_country(&headers))
+    .bind(crate::insights::device_type(&headers))
     .execute(state.pool.as_ref())
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -410,36 +1020,45 @@ pub async fn mark_story_viewed(
     Ok(StatusCode::OK)
 }
 
-// Delete a story
+// Delete a story; the author and an accepted co-author both may
+#[utoipa::path(
+    delete,
+    path = "/api/v1/stories/{story_id}/delete/{user_id}",
+    tag = "stories",
+    params(("story_id" = Uuid, Path, description = "Story ID"), ("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200, description = "Success"))
+)]
 pub async fn delete_story(
     State(state): State<Arc<AppState>>,
     Path((story_id, user_id)): Path<(Uuid, Uuid)>,
 ) -> Result<StatusCode, StatusCode> {
+    #[derive(sqlx::FromRow)]
+    struct DeletableStory {
+        media_url: String,
+        author_id: Uuid,
+        co_author_id: Option<Uuid>,
+    }
+
     // Get story to delete media
-    let story = sqlx::query!(
+    let story = sqlx::query_as::<_, DeletableStory>(
         r#"
-        SELECT media_url FROM stories
-        WHERE id = $1 AND user_id = $2
-        "#,
-        story_id,
-        user_id
+        SELECT s.media_url, s.user_id AS author_id, sc.user_id AS co_author_id
+        FROM stories s
+        LEFT JOIN story_collaborators sc ON sc.story_id = s.id AND sc.status = 'accepted'
+        WHERE s.id = $1 AND (s.user_id = $2 OR sc.user_id = $2)
+        "#
     )
+    .bind(story_id)
+    .bind(user_id)
     .fetch_optional(state.pool.as_ref())
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
     .ok_or(StatusCode::NOT_FOUND)?;
+    let extra_urls = crate::story_media::extra_urls(&state.pool, story_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    // Delete from S3 - extract key from URL
-    if let Some(key) = story.media_url.split('/').skip(3).collect::<Vec<_>>().join("/").into() {
-        if let Err(e) = state.media_service.s3_client
-            .delete_object()
-            .bucket(&state.media_service.bucket_name)
-            .key(key)
-            .send()
-            .await {
-            eprintln!("Failed to delete media from S3: {}", e);
-        }
-    }
+    let mut tx = state.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     // Delete from database
     sqlx::query!(
@@ -448,11 +1067,38 @@ pub async fn delete_story(
         WHERE id = $1 AND user_id = $2
         "#,
         story_id,
-        user_id
+        story.author_id
     )
-    .execute(state.pool.as_ref())
+    .execute(&mut *tx)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    // Release the story's reference; the object is only deleted from S3 if
+    // nothing else (a reposted story, a message) still uses it, and only
+    // once the row is gone
+    let keys: Vec<String> = std::iter::once(&story.media_url)
+        .chain(&extra_urls)
+        .filter_map(|url| state.media_service.s3_key_from_url(url))
+        .collect();
+    let outbox_entry = crate::media_outbox::release_and_queue(&mut tx, &keys, "story_deleted")
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to release story media: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    crate::media_outbox::flush(&state.pool, &state.media_service, outbox_entry).await;
+    invalidate_author(&state, story.author_id).await;
+    if let Some(co_author_id) = story.co_author_id {
+        invalidate_author(&state, co_author_id).await;
+    }
+
     Ok(StatusCode::OK)
 }
+
+/// The author's story list and story count changed
+pub async fn invalidate_author(state: &AppState, user_id: Uuid) {
+    crate::cache::invalidate_stories(&state.redis, user_id).await;
+    crate::cache::invalidate_profile(&state.redis, user_id).await;
+}
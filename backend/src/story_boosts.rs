@@ -0,0 +1,559 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::runtime_settings;
+use crate::stories::Story;
+use crate::AppState;
+
+// Boosted stories (migration 071). A creator pays, through the same Stripe
+// checkout as ad budgets, to show one of their own stories to people who
+// don't follow them. A boost buys target_impressions at the boosts.cpm_usd
+// runtime setting and runs from payment until it has them or the story
+// expires. Boosted stories share the sponsored slots of the story feed with
+// ads and carry is_sponsored; the client reports impressions and profile
+// taps, and follows and likes from viewers after their impression are
+// credited to the boost in its report.
+
+const MIN_IMPRESSIONS: i32 = 100;
+const MAX_IMPRESSIONS: i32 = 1_000_000;
+const MIN_PRICE: f64 = 1.0;
+// A boost needs some of the story's lifetime left to deliver in
+const MIN_REMAINING_HOURS: i32 = 1;
+const FEED_BOOSTS: i64 = 3;
+// Checkouts nobody finished are dropped after this
+const UNPAID_TTL_HOURS: i32 = 24;
+
+const BOOST_COLUMNS: &str = "b.id, b.story_id, b.status, b.target_impressions, b.impressions, b.clicks, \
+     b.price::float8 AS price, b.target_countries, b.created_at, b.paid_at, b.ended_at, \
+     s.expires_at AS story_expires_at";
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct StoryBoost {
+    pub id: Uuid,
+    /// Null once the story is deleted
+    pub story_id: Option<Uuid>,
+    /// pending_payment, active, completed or cancelled
+    pub status: String,
+    pub target_impressions: i32,
+    pub impressions: i32,
+    pub clicks: i32,
+    /// USD
+    pub price: f64,
+    pub target_countries: Vec<String>,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps::option")]
+    pub paid_at: Option<NaiveDateTime>,
+    #[serde(with = "crate::timestamps::option")]
+    pub ended_at: Option<NaiveDateTime>,
+    /// The boost stops delivering when the story expires
+    #[serde(with = "crate::timestamps::option")]
+    pub story_expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateBoostRequest {
+    /// Impressions to buy, 100 to 1,000,000
+    pub target_impressions: i32,
+    /// ISO 3166-1 alpha-2 countries to show the story in; everywhere when empty
+    #[serde(default)]
+    pub target_countries: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BoostCheckoutResponse {
+    pub boost: StoryBoost,
+    pub session_id: Option<String>,
+    /// Stripe checkout page to send the creator to; none when the boost is
+    /// already paid for (development)
+    pub checkout_url: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BoostReport {
+    pub boost: StoryBoost,
+    /// Share of impressions that tapped through to the profile, 0-100
+    pub click_through_percentage: f64,
+    /// Viewers reached by the boost who opened the story
+    pub views: i64,
+    /// Viewers reached by the boost who liked the story
+    pub likes: i64,
+    /// Viewers reached by the boost who followed the creator afterwards
+    pub follows: i64,
+    /// USD of the price delivered so far
+    pub spend: f64,
+    pub cost_per_follow: Option<f64>,
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    eprintln!("❌ Story boost query failed: {:?}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+}
+
+/// What `impressions` cost at the current rate, in USD
+fn price_for(impressions: i32) -> f64 {
+    let price = impressions as f64 * runtime_settings::float(runtime_settings::BOOST_CPM_USD) / 1000.0;
+    (price * 100.0).round().max(MIN_PRICE * 100.0) / 100.0
+}
+
+async fn fetch_boost(pool: &PgPool, boost_id: Uuid) -> Result<Option<(Uuid, StoryBoost)>, sqlx::Error> {
+    #[derive(sqlx::FromRow)]
+    struct Owned {
+        user_id: Uuid,
+        #[sqlx(flatten)]
+        boost: StoryBoost,
+    }
+
+    let owned = sqlx::query_as::<_, Owned>(&format!(
+        "SELECT b.user_id, {} FROM story_boosts b LEFT JOIN stories s ON s.id = b.story_id WHERE b.id = $1",
+        BOOST_COLUMNS
+    ))
+    .bind(boost_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(owned.map(|owned| (owned.user_id, owned.boost)))
+}
+
+/// The boost, if `user` paid for it or is an admin
+async fn own_boost(pool: &PgPool, boost_id: Uuid, user: &AuthUser) -> Result<StoryBoost, (StatusCode, String)> {
+    let (owner_id, boost) = fetch_boost(pool, boost_id)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Boost not found".to_string()))?;
+    if owner_id != user.id && user.role != "admin" {
+        return Err((StatusCode::FORBIDDEN, "Not your boost".to_string()));
+    }
+    Ok(boost)
+}
+
+/// Start a paid boost. Boosts already running are left alone, so Stripe
+/// redelivering an event is harmless.
+pub async fn activate(pool: &PgPool, boost_id: Uuid, session_id: Option<&str>) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE story_boosts
+        SET status = 'active', paid_at = NOW(), stripe_session_id = COALESCE($2, stripe_session_id)
+        WHERE id = $1 AND status = 'pending_payment'
+        "#
+    )
+    .bind(boost_id)
+    .bind(session_id)
+    .execute(pool)
+    .await?;
+    tracing::info!(boost_id = %boost_id, "Story boost paid");
+    Ok(())
+}
+
+/// Finish boosts whose story expired or was deleted, and drop checkouts
+/// nobody completed
+pub async fn end_expired(pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE story_boosts b
+        SET status = 'completed', ended_at = NOW()
+        WHERE b.status = 'active'
+          AND NOT EXISTS (SELECT 1 FROM stories s WHERE s.id = b.story_id AND s.expires_at > NOW())
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE story_boosts
+        SET status = 'cancelled', ended_at = NOW()
+        WHERE status = 'pending_payment' AND created_at < NOW() - make_interval(hours => $1)
+        "#
+    )
+    .bind(UNPAID_TTL_HOURS)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Boosted stories to place in `viewer_id`'s feed: running boosts of creators
+/// they don't follow, for stories they haven't seen, in `country`
+pub async fn for_feed(
+    pool: &PgPool,
+    viewer_id: Uuid,
+    country: &Option<String>,
+) -> Result<Vec<Story>, sqlx::Error> {
+    sqlx::query_as::<_, Story>(
+        r#"
+        SELECT
+            s.id,
+            s.user_id,
+            s.media_url,
+            s.playback_url,
+            s.media_type,
+            s.thumbnail_url,
+            s.preview_url,
+            s.caption,
+            s.alt_text,
+            s.alt_text_generated,
+            s.is_mature,
+            s.view_count,
+            s.like_count,
+            s.comment_count,
+            s.created_at,
+            s.expires_at,
+            u.username,
+            sc.user_id AS co_author_id,
+            cu.username AS co_author_username,
+            s.allow_sharing,
+            s.allow_comments,
+            story_comment_audience(s.id) AS comment_audience,
+            s.screenshot_warning,
+            s.hide_like_count,
+            FALSE AS is_viewed,
+            EXISTS(SELECT 1 FROM story_likes sl WHERE sl.story_id = s.id AND sl.user_id = $1) AS is_liked,
+            TRUE AS is_sponsored,
+            b.id AS boost_id
+        FROM story_boosts b
+        JOIN stories s ON s.id = b.story_id
+        JOIN users u ON u.id = s.user_id
+        LEFT JOIN story_collaborators sc ON sc.story_id = s.id AND sc.status = 'accepted'
+        LEFT JOIN users cu ON cu.id = sc.user_id
+        WHERE b.status = 'active'
+          AND b.impressions < b.target_impressions
+          AND s.expires_at > NOW()
+          AND s.user_id <> $1
+          AND NOT EXISTS (SELECT 1 FROM follows f WHERE f.follower_id = $1 AND f.following_id = s.user_id)
+          AND NOT EXISTS (SELECT 1 FROM story_boost_impressions bi WHERE bi.boost_id = b.id AND bi.user_id = $1)
+          AND NOT EXISTS (SELECT 1 FROM story_views sv WHERE sv.story_id = s.id AND sv.viewer_id = $1)
+          AND (NOT s.is_mature OR user_is_adult($1))
+          AND NOT geo_blocked('story', s.id, $2)
+          AND (cardinality(b.target_countries) = 0 OR COALESCE($2 = ANY(b.target_countries), FALSE))
+        ORDER BY RANDOM()
+        LIMIT $3
+        "#
+    )
+    .bind(viewer_id)
+    .bind(country)
+    .bind(FEED_BOOSTS)
+    .fetch_all(pool)
+    .await
+}
+
+// Pay to show one of your stories to people who don't follow you
+#[utoipa::path(
+    post,
+    path = "/api/v1/stories/{story_id}/boost",
+    tag = "ads",
+    params(("story_id" = Uuid, Path, description = "Story ID")),
+    request_body = CreateBoostRequest,
+    responses(
+        (status = 200, body = BoostCheckoutResponse),
+        (status = 400, description = "Impressions out of range, bad countries, or the story expires too soon"),
+        (status = 403, description = "Not your story"),
+        (status = 404, description = "Story not found"),
+        (status = 409, description = "The story already has a boost running"),
+        (status = 502, description = "Stripe checkout could not be created"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn boost_story(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(story_id): Path<Uuid>,
+    Json(input): Json<CreateBoostRequest>,
+) -> Result<Json<BoostCheckoutResponse>, (StatusCode, String)> {
+    if !(MIN_IMPRESSIONS..=MAX_IMPRESSIONS).contains(&input.target_impressions) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("target_impressions must be between {} and {}", MIN_IMPRESSIONS, MAX_IMPRESSIONS),
+        ));
+    }
+    let (target_countries, _) = crate::ad_targeting::normalize_targets(&input.target_countries, &[])
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let (author_id, lasts) = sqlx::query_as::<_, (Uuid, bool)>(
+        "SELECT user_id, expires_at > NOW() + make_interval(hours => $2) FROM stories WHERE id = $1"
+    )
+    .bind(story_id)
+    .bind(MIN_REMAINING_HOURS)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, "Story not found".to_string()))?;
+    if author_id != user.id {
+        return Err((StatusCode::FORBIDDEN, "You can only boost your own stories".to_string()));
+    }
+    if !lasts {
+        return Err((StatusCode::BAD_REQUEST, "This story expires too soon to boost".to_string()));
+    }
+
+    let price = price_for(input.target_impressions);
+    let boost_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO story_boosts (story_id, user_id, target_impressions, price, target_countries)
+        SELECT $1, $2, $3, $4, $5
+        WHERE NOT EXISTS (SELECT 1 FROM story_boosts WHERE story_id = $1 AND status = 'active')
+        RETURNING id
+        "#
+    )
+    .bind(story_id)
+    .bind(user.id)
+    .bind(input.target_impressions)
+    .bind(crate::ad_billing::money(price))
+    .bind(&target_countries)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::CONFLICT, "This story already has a boost running".to_string()))?;
+
+    let (session_id, checkout_url) = match crate::ad_billing::stripe_secret_key() {
+        // Development mode - start straight away
+        None => {
+            activate(&state.pool, boost_id, None).await.map_err(db_error)?;
+            (None, None)
+        }
+        Some(secret_key) => {
+            let session = crate::ad_billing::create_checkout_session(
+                &secret_key,
+                "Story boost",
+                &crate::ad_billing::money(price),
+                ("boost", boost_id),
+                story_id,
+            )
+            .await
+            .map_err(|e| {
+                tracing::error!(boost_id = %boost_id, error = %e, "Stripe checkout failed");
+                (StatusCode::BAD_GATEWAY, "Could not start checkout".to_string())
+            })?;
+            sqlx::query("UPDATE story_boosts SET stripe_session_id = $2 WHERE id = $1")
+                .bind(boost_id)
+                .bind(&session.id)
+                .execute(state.pool.as_ref())
+                .await
+                .map_err(db_error)?;
+            (Some(session.id), session.url)
+        }
+    };
+
+    let (_, boost) = fetch_boost(&state.pool, boost_id)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Boost not found".to_string()))?;
+    Ok(Json(BoostCheckoutResponse { boost, session_id, checkout_url }))
+}
+
+// The caller's boosts, newest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/boosts",
+    tag = "ads",
+    responses((status = 200, body = [StoryBoost]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_boosts(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<StoryBoost>>, (StatusCode, String)> {
+    let boosts = sqlx::query_as::<_, StoryBoost>(&format!(
+        r#"
+        SELECT {} FROM story_boosts b
+        LEFT JOIN stories s ON s.id = b.story_id
+        WHERE b.user_id = $1
+        ORDER BY b.created_at DESC
+        LIMIT 100
+        "#,
+        BOOST_COLUMNS
+    ))
+    .bind(user.id)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(boosts))
+}
+
+// How a boost is performing
+#[utoipa::path(
+    get,
+    path = "/api/v1/boosts/{boost_id}",
+    tag = "ads",
+    params(("boost_id" = Uuid, Path, description = "Boost ID")),
+    responses(
+        (status = 200, body = BoostReport),
+        (status = 403, description = "Not your boost"),
+        (status = 404, description = "Boost not found"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_boost_report(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(boost_id): Path<Uuid>,
+) -> Result<Json<BoostReport>, (StatusCode, String)> {
+    let boost = own_boost(&state.pool, boost_id, &user).await?;
+
+    let (views, likes, follows) = sqlx::query_as::<_, (i64, i64, i64)>(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE EXISTS (
+                SELECT 1 FROM story_views sv WHERE sv.story_id = b.story_id AND sv.viewer_id = bi.user_id
+            )),
+            COUNT(*) FILTER (WHERE EXISTS (
+                SELECT 1 FROM story_likes sl WHERE sl.story_id = b.story_id AND sl.user_id = bi.user_id
+            )),
+            COUNT(*) FILTER (WHERE EXISTS (
+                SELECT 1 FROM follows f
+                WHERE f.follower_id = bi.user_id AND f.following_id = b.user_id AND f.created_at >= bi.created_at
+            ))
+        FROM story_boost_impressions bi
+        JOIN story_boosts b ON b.id = bi.boost_id
+        WHERE bi.boost_id = $1
+        "#
+    )
+    .bind(boost_id)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    let delivered = boost.impressions.min(boost.target_impressions) as f64 / boost.target_impressions as f64;
+    let spend = (boost.price * delivered * 100.0).round() / 100.0;
+    let click_through_percentage = if boost.impressions > 0 {
+        boost.clicks as f64 / boost.impressions as f64 * 100.0
+    } else {
+        0.0
+    };
+
+    Ok(Json(BoostReport {
+        click_through_percentage,
+        views,
+        likes,
+        follows,
+        spend,
+        cost_per_follow: (follows > 0).then(|| spend / follows as f64),
+        boost,
+    }))
+}
+
+// Stop a boost. Delivery ends straight away; the undelivered part of the
+// price isn't refunded automatically.
+#[utoipa::path(
+    post,
+    path = "/api/v1/boosts/{boost_id}/cancel",
+    tag = "ads",
+    params(("boost_id" = Uuid, Path, description = "Boost ID")),
+    responses(
+        (status = 200, body = StoryBoost),
+        (status = 403, description = "Not your boost"),
+        (status = 404, description = "Boost not found"),
+        (status = 409, description = "The boost already ended"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn cancel_boost(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(boost_id): Path<Uuid>,
+) -> Result<Json<StoryBoost>, (StatusCode, String)> {
+    own_boost(&state.pool, boost_id, &user).await?;
+
+    let cancelled = sqlx::query(
+        r#"
+        UPDATE story_boosts SET status = 'cancelled', ended_at = NOW()
+        WHERE id = $1 AND status IN ('pending_payment', 'active')
+        "#
+    )
+    .bind(boost_id)
+    .execute(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+    if cancelled.rows_affected() == 0 {
+        return Err((StatusCode::CONFLICT, "This boost already ended".to_string()));
+    }
+
+    own_boost(&state.pool, boost_id, &user).await.map(Json)
+}
+
+// The boosted story was shown to the caller
+#[utoipa::path(
+    post,
+    path = "/api/v1/boosts/{boost_id}/impression",
+    tag = "ads",
+    params(("boost_id" = Uuid, Path, description = "Boost ID")),
+    responses((status = 204, description = "Recorded"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn record_impression(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(boost_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    // Counted once per viewer; the boost completes with its last impression
+    sqlx::query(
+        r#"
+        WITH seen AS (
+            INSERT INTO story_boost_impressions (boost_id, user_id)
+            SELECT id, $2 FROM story_boosts WHERE id = $1 AND status = 'active' AND user_id <> $2
+            ON CONFLICT DO NOTHING
+            RETURNING boost_id
+        )
+        UPDATE story_boosts b
+        SET impressions = b.impressions + 1,
+            status = CASE WHEN b.impressions + 1 >= b.target_impressions THEN 'completed' ELSE b.status END,
+            ended_at = CASE WHEN b.impressions + 1 >= b.target_impressions THEN NOW() ELSE b.ended_at END
+        FROM seen
+        WHERE b.id = seen.boost_id
+        "#
+    )
+    .bind(boost_id)
+    .bind(user.id)
+    .execute(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// The caller tapped through from the boosted story to the creator's profile
+#[utoipa::path(
+    post,
+    path = "/api/v1/boosts/{boost_id}/click",
+    tag = "ads",
+    params(("boost_id" = Uuid, Path, description = "Boost ID")),
+    responses((status = 204, description = "Recorded"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn record_click(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(boost_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    // Only after an impression, once per viewer
+    sqlx::query(
+        r#"
+        WITH clicked AS (
+            UPDATE story_boost_impressions SET clicked_at = NOW()
+            WHERE boost_id = $1 AND user_id = $2 AND clicked_at IS NULL
+            RETURNING boost_id
+        )
+        UPDATE story_boosts b SET clicks = b.clicks + 1
+        FROM clicked
+        WHERE b.id = clicked.boost_id
+        "#
+    )
+    .bind(boost_id)
+    .bind(user.id)
+    .execute(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,334 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::i18n::Message;
+use crate::AppState;
+
+// Co-authored stories. A story's author invites one other user, who has to
+// accept before the story shows on their profile and under their name in the
+// story tray (story_authors view, migration 057). Either co-author can delete
+// the story; either side can withdraw the invitation or end the collaboration.
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct StoryCollaboration {
+    pub id: Uuid,
+    pub story_id: Uuid,
+    pub author_id: Uuid,
+    pub author_username: String,
+    /// The invited co-author
+    pub user_id: Uuid,
+    pub username: String,
+    /// pending or accepted
+    pub status: String,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps::option")]
+    pub responded_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct InviteCollaboratorRequest {
+    pub user_id: Uuid,
+}
+
+const COLLABORATION_COLUMNS: &str = "c.id, c.story_id, s.user_id AS author_id, a.username AS author_username, \
+     c.user_id, u.username, c.status, c.created_at, c.responded_at";
+
+async fn fetch_collaboration(pool: &PgPool, id: Uuid) -> Result<Option<StoryCollaboration>, sqlx::Error> {
+    sqlx::query_as::<_, StoryCollaboration>(&format!(
+        "SELECT {} FROM story_collaborators c JOIN stories s ON s.id = c.story_id \
+         JOIN users a ON a.id = s.user_id JOIN users u ON u.id = c.user_id WHERE c.id = $1",
+        COLLABORATION_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+async fn notify(pool: &PgPool, user_id: Uuid, kind: &str, from_user_id: Uuid, story_id: Uuid, message: Message) {
+    if let Err(e) = sqlx::query(
+        r#"
+        INSERT INTO notifications (user_id, type, from_user_id, story_id, message, message_key, message_args)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#
+    )
+    .bind(user_id)
+    .bind(kind)
+    .bind(from_user_id)
+    .bind(story_id)
+    .bind(message.text())
+    .bind(message.key)
+    .bind(&message.args)
+    .execute(pool)
+    .await
+    {
+        eprintln!("❌ Failed to notify {} about a story collaboration: {:?}", user_id, e);
+    }
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    eprintln!("❌ Story collaboration query failed: {:?}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+}
+
+// Invite another user to co-author one of the caller's stories
+#[utoipa::path(
+    post,
+    path = "/api/v1/stories/{story_id}/collaborators",
+    tag = "stories",
+    params(("story_id" = Uuid, Path, description = "Story ID")),
+    request_body = InviteCollaboratorRequest,
+    responses(
+        (status = 201, body = StoryCollaboration),
+        (status = 400, description = "Inviting yourself, or a minor to a mature story"),
+        (status = 404, description = "No live story of the caller's, or no such user"),
+        (status = 409, description = "The story already has a co-author or an open invitation"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn invite_collaborator(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(story_id): Path<Uuid>,
+    Json(payload): Json<InviteCollaboratorRequest>,
+) -> Result<(StatusCode, Json<StoryCollaboration>), (StatusCode, String)> {
+    if payload.user_id == user.id {
+        return Err((StatusCode::BAD_REQUEST, "You can't co-author your own story".to_string()));
+    }
+
+    let is_mature = sqlx::query_scalar::<_, bool>(
+        "SELECT is_mature FROM stories WHERE id = $1 AND user_id = $2 AND expires_at > NOW()"
+    )
+    .bind(story_id)
+    .bind(user.id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, "Story not found".to_string()))?;
+
+    let invitee_is_minor = sqlx::query_scalar::<_, bool>("SELECT user_is_minor(id) FROM users WHERE id = $1")
+        .bind(payload.user_id)
+        .fetch_optional(state.pool.as_ref())
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::NOT_FOUND, "User not found".to_string()))?;
+    if is_mature && invitee_is_minor {
+        return Err((StatusCode::BAD_REQUEST, "Minors can't co-author mature stories".to_string()));
+    }
+
+    let id = sqlx::query_scalar::<_, Uuid>(
+        "INSERT INTO story_collaborators (story_id, user_id, invited_by) VALUES ($1, $2, $3) RETURNING id"
+    )
+    .bind(story_id)
+    .bind(payload.user_id)
+    .bind(user.id)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(ref db) if db.is_unique_violation() => {
+            (StatusCode::CONFLICT, "This story already has a co-author or an open invitation".to_string())
+        }
+        e => db_error(e),
+    })?;
+
+    notify(
+        &state.pool,
+        payload.user_id,
+        "story_collab_invite",
+        user.id,
+        story_id,
+        Message::new("story_collab_invite").arg("username", &user.username),
+    )
+    .await;
+
+    let collaboration = fetch_collaboration(&state.pool, id)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Collaboration vanished".to_string()))?;
+
+    Ok((StatusCode::CREATED, Json(collaboration)))
+}
+
+// Open invitations and accepted collaborations on live stories, sent or received
+#[utoipa::path(
+    get,
+    path = "/api/v1/stories/collaborations",
+    tag = "stories",
+    responses((status = 200, body = [StoryCollaboration]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_collaborations(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<StoryCollaboration>>, StatusCode> {
+    let collaborations = sqlx::query_as::<_, StoryCollaboration>(&format!(
+        r#"
+        SELECT {} FROM story_collaborators c
+        JOIN stories s ON s.id = c.story_id
+        JOIN users a ON a.id = s.user_id
+        JOIN users u ON u.id = c.user_id
+        WHERE (c.user_id = $1 OR s.user_id = $1)
+          AND c.status IN ('pending', 'accepted')
+          AND s.expires_at > NOW()
+        ORDER BY c.created_at DESC
+        "#,
+        COLLABORATION_COLUMNS
+    ))
+    .bind(user.id)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(collaborations))
+}
+
+// The invited user agrees to co-author the story
+#[utoipa::path(
+    post,
+    path = "/api/v1/stories/collaborations/{collaboration_id}/accept",
+    tag = "stories",
+    params(("collaboration_id" = Uuid, Path, description = "Collaboration ID")),
+    responses(
+        (status = 200, body = StoryCollaboration),
+        (status = 404, description = "No pending invitation for the caller"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn accept_collaboration(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(collaboration_id): Path<Uuid>,
+) -> Result<Json<StoryCollaboration>, (StatusCode, String)> {
+    let (story_id, author_id) = sqlx::query_as::<_, (Uuid, Uuid)>(
+        r#"
+        UPDATE story_collaborators c SET status = 'accepted', responded_at = NOW()
+        FROM stories s
+        WHERE c.id = $1 AND c.user_id = $2 AND c.status = 'pending'
+          AND s.id = c.story_id AND s.expires_at > NOW()
+        RETURNING s.id, s.user_id
+        "#
+    )
+    .bind(collaboration_id)
+    .bind(user.id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, "Invitation not found".to_string()))?;
+
+    crate::stories::invalidate_author(&state, author_id).await;
+    crate::stories::invalidate_author(&state, user.id).await;
+    notify(
+        &state.pool,
+        author_id,
+        "story_collab_accepted",
+        user.id,
+        story_id,
+        Message::new("story_collab_accepted").arg("username", &user.username),
+    )
+    .await;
+
+    let collaboration = fetch_collaboration(&state.pool, collaboration_id)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Invitation not found".to_string()))?;
+
+    Ok(Json(collaboration))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/stories/collaborations/{collaboration_id}/decline",
+    tag = "stories",
+    params(("collaboration_id" = Uuid, Path, description = "Collaboration ID")),
+    responses(
+        (status = 204, description = "Declined"),
+        (status = 404, description = "No pending invitation for the caller"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn decline_collaboration(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(collaboration_id): Path<Uuid>,
+) -> Result<StatusCode, StatusCode> {
+    let result = sqlx::query(
+        "UPDATE story_collaborators SET status = 'declined', responded_at = NOW() WHERE id = $1 AND user_id = $2 AND status = 'pending'"
+    )
+    .bind(collaboration_id)
+    .bind(user.id)
+    .execute(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// The author withdraws an invitation or removes the co-author, or the
+// co-author leaves. The story itself stays up.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/stories/collaborations/{collaboration_id}",
+    tag = "stories",
+    params(("collaboration_id" = Uuid, Path, description = "Collaboration ID")),
+    responses(
+        (status = 204, description = "Ended"),
+        (status = 404, description = "No open collaboration for the caller"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn end_collaboration(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(collaboration_id): Path<Uuid>,
+) -> Result<StatusCode, StatusCode> {
+    let (story_id, author_id, co_author_id, status) = sqlx::query_as::<_, (Uuid, Uuid, Uuid, String)>(
+        r#"
+        DELETE FROM story_collaborators c
+        USING stories s
+        WHERE c.id = $1 AND s.id = c.story_id
+          AND (c.user_id = $2 OR s.user_id = $2)
+          AND c.status IN ('pending', 'accepted')
+        RETURNING s.id, s.user_id, c.user_id, c.status
+        "#
+    )
+    .bind(collaboration_id)
+    .bind(user.id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    if status == "accepted" {
+        crate::stories::invalidate_author(&state, author_id).await;
+        crate::stories::invalidate_author(&state, co_author_id).await;
+        let other = if user.id == author_id { co_author_id } else { author_id };
+        notify(
+            &state.pool,
+            other,
+            "story_collab_ended",
+            user.id,
+            story_id,
+            Message::new("story_collab_ended").arg("username", &user.username),
+        )
+        .await;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,165 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::AppState;
+
+// Carousel stories. The story row keeps the first item as its own media, so
+// feeds, previews, alt text generation and video packaging work as they do
+// for single stories; story_media (migration 056) lists every item in order.
+// Later items are served as uploaded.
+
+pub const MAX_ITEMS: usize = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct StoryMediaItem {
+    pub id: Uuid,
+    #[serde(skip)]
+    pub story_id: Uuid,
+    /// 0-based; item 0 is the story's own media
+    pub position: i16,
+    pub media_url: String,
+    pub media_type: String,
+    pub alt_text: Option<String>,
+    pub view_count: i32,
+}
+
+/// An item to store with a new carousel story
+pub struct NewItem {
+    pub media_url: String,
+    pub media_type: String,
+    pub alt_text: Option<String>,
+}
+
+/// Items of the given stories, in order; single-media stories have none
+pub async fn load(pool: &PgPool, story_ids: &[Uuid]) -> HashMap<Uuid, Vec<StoryMediaItem>> {
+    let mut by_story: HashMap<Uuid, Vec<StoryMediaItem>> = HashMap::new();
+    if story_ids.is_empty() {
+        return by_story;
+    }
+
+    let items = sqlx::query_as::<_, StoryMediaItem>(
+        r#"
+        SELECT id, story_id, position, media_url, media_type, alt_text, view_count
+        FROM story_media
+        WHERE story_id = ANY($1)
+        ORDER BY story_id, position
+        "#
+    )
+    .bind(story_ids)
+    .fetch_all(pool)
+    .await;
+
+    match items {
+        Ok(items) => {
+            for item in items {
+                by_story.entry(item.story_id).or_default().push(item);
+            }
+        }
+        Err(e) => eprintln!("❌ Failed to load carousel items: {:?}", e),
+    }
+    by_story
+}
+
+/// Fill in media_items on stories that are carousels
+pub async fn attach(pool: &PgPool, stories: &mut [crate::stories::Story]) {
+    let ids: Vec<Uuid> = stories.iter().filter(|s| s.is_ad.is_none()).map(|s| s.id).collect();
+    let mut items = load(pool, &ids).await;
+    for story in stories.iter_mut() {
+        if let Some(story_items) = items.remove(&story.id) {
+            story.media_items = story_items;
+        }
+    }
+}
+
+/// Store the items of a new story, in the same transaction as the story row
+pub async fn insert(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    story_id: Uuid,
+    items: &[NewItem],
+) -> Result<Vec<StoryMediaItem>, sqlx::Error> {
+    let positions: Vec<i16> = (0..items.len() as i16).collect();
+    let urls: Vec<&str> = items.iter().map(|i| i.media_url.as_str()).collect();
+    let types: Vec<&str> = items.iter().map(|i| i.media_type.as_str()).collect();
+    let alt_texts: Vec<Option<&str>> = items.iter().map(|i| i.alt_text.as_deref()).collect();
+
+    sqlx::query_as::<_, StoryMediaItem>(
+        r#"
+        INSERT INTO story_media (story_id, position, media_url, media_type, alt_text)
+        SELECT $1, * FROM UNNEST($2::smallint[], $3::text[], $4::text[], $5::text[])
+        RETURNING id, story_id, position, media_url, media_type, alt_text, view_count
+        "#
+    )
+    .bind(story_id)
+    .bind(&positions)
+    .bind(&urls)
+    .bind(&types)
+    .bind(&alt_texts)
+    .fetch_all(&mut **tx)
+    .await
+}
+
+/// Media URLs of a story's items after the first, each holding a reference
+pub async fn extra_urls(pool: &PgPool, story_id: Uuid) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>("SELECT media_url FROM story_media WHERE story_id = $1 AND position > 0")
+        .bind(story_id)
+        .fetch_all(pool)
+        .await
+}
+
+// Mark one item of a carousel story as viewed
+#[utoipa::path(
+    post,
+    path = "/api/v1/stories/{story_id}/items/{position}/view/{viewer_id}",
+    tag = "stories",
+    params(
+        ("story_id" = Uuid, Path, description = "Story ID"),
+        ("position" = i16, Path, description = "Item position, starting at 0"),
+        ("viewer_id" = Uuid, Path, description = "Viewer ID")
+    ),
+    responses((status = 200, description = "Success"), (status = 404, description = "Story has no item at this position"))
+)]
+pub async fn mark_item_viewed(
+    State(state): State<Arc<AppState>>,
+    Path((story_id, position, viewer_id)): Path<(Uuid, i16, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    let media_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        SELECT m.id FROM story_media m
+        JOIN stories s ON s.id = m.story_id
+        WHERE m.story_id = $1 AND m.position = $2 AND s.expires_at > NOW()
+        "#
+    )
+    .bind(story_id)
+    .bind(position)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    // Each viewer counts once per item
+    sqlx::query(
+        r#"
+        WITH viewed AS (
+            INSERT INTO story_media_views (media_id, viewer_id) VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+            RETURNING media_id
+        )
+        UPDATE story_media SET view_count = view_count + 1 WHERE id IN (SELECT media_id FROM viewed)
+        "#
+    )
+    .bind(media_id)
+    .bind(viewer_id)
+    .execute(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,355 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::chat::{MessageResponse, OutgoingMessage};
+use crate::AppState;
+
+// What the author lets viewers do with a story (migration 063). Set at
+// upload time or changed later through /stories/:id/permissions, and
+// enforced where the action happens:
+//   allow_sharing       share_story below; sharing into a chat is only
+//                       possible through it, as a 'story' message
+//   allow_comments      off turns comments off whatever the audience
+//   comment_audience    who may comment (migration 064): everyone,
+//                       followers, following or off; NULL on the story
+//                       follows the author's account setting. add_comment
+//                       and add_reply check it with check_commenter, and
+//                       while comments are off existing ones aren't listed
+//   screenshot_warning  only a hint for clients
+//   hide_like_count     like_count is left out for everyone but the author
+//                       and co-author, and the list of likers is theirs only
+
+pub const COMMENT_AUDIENCES: [&str; 4] = ["everyone", "followers", "following", "off"];
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct StoryPermissions {
+    pub story_id: Uuid,
+    pub allow_sharing: bool,
+    pub allow_comments: bool,
+    /// This story's comment audience; null follows the author's account setting
+    pub comment_audience: Option<String>,
+    /// Who can comment right now, after allow_comments and the account setting
+    pub effective_comment_audience: String,
+    pub screenshot_warning: bool,
+    pub hide_like_count: bool,
+}
+
+/// Fields left out keep their current value
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateStoryPermissionsRequest {
+    pub allow_sharing: Option<bool>,
+    pub allow_comments: Option<bool>,
+    /// everyone, followers, following, off, or default to follow the
+    /// account setting again
+    pub comment_audience: Option<String>,
+    pub screenshot_warning: Option<bool>,
+    pub hide_like_count: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ShareStoryRequest {
+    pub chat_room_id: Uuid,
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    eprintln!("❌ Story permissions query failed: {:?}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+}
+
+/// Whether `user_id` wrote the story or co-authors it
+pub async fn is_author(pool: &PgPool, story_id: Uuid, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(SELECT 1 FROM stories WHERE id = $1 AND user_id = $2)
+            OR EXISTS(SELECT 1 FROM story_collaborators
+                      WHERE story_id = $1 AND user_id = $2 AND status = 'accepted')
+        "#
+    )
+    .bind(story_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+}
+
+/// A comment the story's audience setting doesn't allow. The body carries a
+/// reason code so clients can say why commenting is disabled:
+/// comments_off, followers_only or following_only.
+#[derive(Debug)]
+pub struct CommentsRestricted {
+    reason: &'static str,
+    message: &'static str,
+}
+
+impl IntoResponse for CommentsRestricted {
+    fn into_response(self) -> Response {
+        (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "error": "comments_restricted",
+                "reason": self.reason,
+                "message": self.message,
+            })),
+        )
+            .into_response()
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct CommentAccess {
+    audience: String,
+    is_author: bool,
+    follows_author: bool,
+    followed_by_author: bool,
+}
+
+/// Reject a new comment or reply from `user_id` if the story's comment
+/// audience leaves them out. The author and co-author may always comment
+/// unless comments are off.
+pub async fn check_commenter(pool: &PgPool, story_id: Uuid, user_id: Uuid) -> axum::response::Result<()> {
+    let access = sqlx::query_as::<_, CommentAccess>(
+        r#"
+        SELECT story_comment_audience(s.id) AS audience,
+               s.user_id = $2 OR EXISTS(SELECT 1 FROM story_collaborators
+                                        WHERE story_id = s.id AND user_id = $2 AND status = 'accepted') AS is_author,
+               EXISTS(SELECT 1 FROM follows WHERE follower_id = $2 AND following_id = s.user_id) AS follows_author,
+               EXISTS(SELECT 1 FROM follows WHERE follower_id = s.user_id AND following_id = $2) AS followed_by_author
+        FROM stories s
+        WHERE s.id = $1
+        "#
+    )
+    .bind(story_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, "Story not found".to_string()))?;
+
+    let restricted = match access.audience.as_str() {
+        "off" => CommentsRestricted {
+            reason: "comments_off",
+            message: "Comments are turned off for this story",
+        },
+        "followers" if !access.is_author && !access.follows_author => CommentsRestricted {
+            reason: "followers_only",
+            message: "Only the author's followers can comment on this story",
+        },
+        "following" if !access.is_author && !access.followed_by_author => CommentsRestricted {
+            reason: "following_only",
+            message: "Only people the author follows can comment on this story",
+        },
+        _ => return Ok(()),
+    };
+    Err(restricted.into())
+}
+
+/// Whether the story's like count should be kept from `viewer_id`
+pub async fn like_count_hidden(pool: &PgPool, story_id: Uuid, viewer_id: Option<Uuid>) -> Result<bool, sqlx::Error> {
+    let hidden = sqlx::query_scalar::<_, bool>("SELECT hide_like_count FROM stories WHERE id = $1")
+        .bind(story_id)
+        .fetch_optional(pool)
+        .await?
+        .unwrap_or(false);
+    match viewer_id {
+        Some(viewer_id) if hidden => Ok(!is_author(pool, story_id, viewer_id).await?),
+        _ => Ok(hidden),
+    }
+}
+
+async fn fetch_permissions(pool: &PgPool, story_id: Uuid) -> Result<Option<StoryPermissions>, sqlx::Error> {
+    sqlx::query_as::<_, StoryPermissions>(
+        r#"
+        SELECT id AS story_id, allow_sharing, allow_comments, comment_audience,
+               story_comment_audience(id) AS effective_comment_audience, screenshot_warning, hide_like_count
+        FROM stories
+        WHERE id = $1 AND expires_at > NOW()
+        "#
+    )
+    .bind(story_id)
+    .fetch_optional(pool)
+    .await
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/stories/{story_id}/permissions",
+    tag = "stories",
+    params(("story_id" = Uuid, Path, description = "Story ID")),
+    responses((status = 200, body = StoryPermissions), (status = 404, description = "No such live story"))
+)]
+pub async fn get_permissions(
+    State(state): State<Arc<AppState>>,
+    Path(story_id): Path<Uuid>,
+) -> Result<Json<StoryPermissions>, (StatusCode, String)> {
+    fetch_permissions(&state.pool, story_id)
+        .await
+        .map_err(db_error)?
+        .map(Json)
+        .ok_or((StatusCode::NOT_FOUND, "Story not found".to_string()))
+}
+
+// The author changes what viewers may do with one of their stories
+#[utoipa::path(
+    put,
+    path = "/api/v1/stories/{story_id}/permissions",
+    tag = "stories",
+    params(("story_id" = Uuid, Path, description = "Story ID")),
+    request_body = UpdateStoryPermissionsRequest,
+    responses(
+        (status = 200, body = StoryPermissions),
+        (status = 400, description = "Unknown comment audience"),
+        (status = 404, description = "No live story of the caller's"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_permissions(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(story_id): Path<Uuid>,
+    Json(payload): Json<UpdateStoryPermissionsRequest>,
+) -> Result<Json<StoryPermissions>, (StatusCode, String)> {
+    let comment_audience = payload.comment_audience.map(|a| a.trim().to_lowercase());
+    if let Some(audience) = comment_audience.as_deref() {
+        if audience != "default" && !COMMENT_AUDIENCES.contains(&audience) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("comment_audience must be one of: {}, default", COMMENT_AUDIENCES.join(", ")),
+            ));
+        }
+    }
+
+    let updated = sqlx::query(
+        r#"
+        UPDATE stories SET
+            allow_sharing = COALESCE($3, allow_sharing),
+            allow_comments = COALESCE($4, allow_comments),
+            comment_audience = CASE WHEN $5::text IS NULL THEN comment_audience
+                                    WHEN $5 = 'default' THEN NULL
+                                    ELSE $5 END,
+            screenshot_warning = COALESCE($6, screenshot_warning),
+            hide_like_count = COALESCE($7, hide_like_count)
+        WHERE id = $1 AND user_id = $2 AND expires_at > NOW()
+        "#
+    )
+    .bind(story_id)
+    .bind(user.id)
+    .bind(payload.allow_sharing)
+    .bind(payload.allow_comments)
+    .bind(&comment_audience)
+    .bind(payload.screenshot_warning)
+    .bind(payload.hide_like_count)
+    .execute(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+    if updated.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Story not found".to_string()));
+    }
+    let permissions = fetch_permissions(&state.pool, story_id)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Story not found".to_string()))?;
+
+    // Cached story lists carry the flags
+    crate::stories::invalidate_author(&state, user.id).await;
+    let co_author_id = sqlx::query_scalar::<_, Uuid>(
+        "SELECT user_id FROM story_collaborators WHERE story_id = $1 AND status = 'accepted'"
+    )
+    .bind(story_id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+    if let Some(co_author_id) = co_author_id {
+        crate::stories::invalidate_author(&state, co_author_id).await;
+    }
+
+    println!("🔒 Story {} permissions updated by {}", story_id, user.username);
+    Ok(Json(permissions))
+}
+
+// Send a story into one of the caller's chats
+#[utoipa::path(
+    post,
+    path = "/api/v1/stories/{story_id}/share",
+    tag = "stories",
+    params(("story_id" = Uuid, Path, description = "Story ID")),
+    request_body = ShareStoryRequest,
+    responses(
+        (status = 200, body = MessageResponse),
+        (status = 403, description = "The author turned sharing off, or the chat doesn't accept messages from the caller"),
+        (status = 404, description = "No such live story, or the caller isn't in the chat"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn share_story(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(story_id): Path<Uuid>,
+    Json(payload): Json<ShareStoryRequest>,
+) -> Result<Json<MessageResponse>, (StatusCode, String)> {
+    // Stories the caller may not see can't be shared either
+    let allow_sharing = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT allow_sharing FROM stories
+        WHERE id = $1 AND expires_at > NOW()
+          AND (NOT is_mature OR user_id = $2 OR user_is_adult($2))
+        "#
+    )
+    .bind(story_id)
+    .bind(user.id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, "Story not found".to_string()))?;
+    if !allow_sharing && !is_author(&state.pool, story_id, user.id).await.map_err(db_error)? {
+        return Err((StatusCode::FORBIDDEN, "Sharing is turned off for this story".to_string()));
+    }
+
+    let is_member = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM chat_members WHERE chat_room_id = $1 AND user_id = $2)"
+    )
+    .bind(payload.chat_room_id)
+    .bind(user.id)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+    if !is_member {
+        return Err((StatusCode::NOT_FOUND, "Chat not found".to_string()));
+    }
+    crate::message_requests::check_send(&state.pool, user.id, payload.chat_room_id).await?;
+    crate::channels::check_send(&state.pool, user.id, payload.chat_room_id).await?;
+
+    let message = OutgoingMessage {
+        chat_room_id: payload.chat_room_id,
+        message_type: "story".to_string(),
+        content: Some(story_id.to_string()),
+        media_url: None,
+        media_thumbnail_url: None,
+        view_once: false,
+        expires_at: None,
+        client_msg_id: None,
+    };
+    let response = crate::chat::deliver_message(
+        &state.pool,
+        &state.redis,
+        &state.connections,
+        &state.media_service,
+        user.id,
+        message,
+    )
+    .await
+    .map_err(db_error)?;
+
+    println!("📨 Story {} shared into chat {} by {}", story_id, payload.chat_room_id, user.username);
+    Ok(Json(response))
+}
@@ -4,12 +4,13 @@ use axum::{
     Json,
 };
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 use std::sync::Arc;
 
 use crate::AppState;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct StreakInfo {
     pub current_streak: i32,
     pub longest_streak: i32,
@@ -17,7 +18,7 @@ pub struct StreakInfo {
     pub last_interaction_date: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct StreakResponse {
     pub success: bool,
     pub streak: StreakInfo,
@@ -25,6 +26,13 @@ pub struct StreakResponse {
 
 /// Update streak when a message is sent between two users
 /// POST /api/streaks/update/:user1_id/:user2_id
+#[utoipa::path(
+    post,
+    path = "/api/v1/streaks/update/{user1_id}/{user2_id}",
+    tag = "streaks",
+    params(("user1_id" = Uuid, Path, description = "User1 ID"), ("user2_id" = Uuid, Path, description = "User2 ID")),
+    responses((status = 200, body = StreakResponse))
+)]
 pub async fn update_streak(
     State(state): State<Arc<AppState>>,
     Path((user1_id, user2_id)): Path<(Uuid, Uuid)>,
@@ -53,6 +61,13 @@ pub async fn update_streak(
 
 /// Get streak information between two users
 /// GET /api/streaks/:user1_id/:user2_id
+#[utoipa::path(
+    get,
+    path = "/api/v1/streaks/{user1_id}/{user2_id}",
+    tag = "streaks",
+    params(("user1_id" = Uuid, Path, description = "User1 ID"), ("user2_id" = Uuid, Path, description = "User2 ID")),
+    responses((status = 200, body = StreakResponse))
+)]
 pub async fn get_streak(
     State(state): State<Arc<AppState>>,
     Path((user1_id, user2_id)): Path<(Uuid, Uuid)>,
@@ -93,6 +108,13 @@ pub async fn get_streak(
 
 /// Get all streaks for a specific user
 /// GET /api/streaks/user/:user_id
+#[utoipa::path(
+    get,
+    path = "/api/v1/streaks/user/{user_id}",
+    tag = "streaks",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200, body = [UserStreakInfo]))
+)]
 pub async fn get_user_streaks(
     State(state): State<Arc<AppState>>,
     Path(user_id): Path<Uuid>,
@@ -134,7 +156,7 @@ pub async fn get_user_streaks(
     Ok(Json(streaks))
 }
 
-#[derive(Debug, Serialize, sqlx::FromRow)]
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
 pub struct UserStreakInfo {
     pub other_user_id: Uuid,
     pub other_username: String,
@@ -0,0 +1,583 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::i18n::Message;
+use crate::AppState;
+
+// Guardian supervision of minor accounts. An adult asks to supervise a minor
+// and the link only goes live once the minor accepts; either side can end it,
+// and it ends on its own when the minor turns 18. Guardians see time spent and
+// new contacts (never messages or stories) and can limit who may start chats
+// with the minor or find them in discovery. The limits are enforced by the
+// SQL helpers in migration 033.
+
+const MESSAGE_POLICIES: [&str; 3] = ["everyone", "following", "nobody"];
+const DEFAULT_ACTIVITY_DAYS: i64 = 7;
+const MAX_ACTIVITY_DAYS: i64 = 30;
+// Time-spent rows are only kept this long
+const ACTIVITY_RETENTION_DAYS: i32 = 90;
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct SupervisionLink {
+    pub id: Uuid,
+    pub guardian_id: Uuid,
+    pub guardian_username: String,
+    pub minor_id: Uuid,
+    pub minor_username: String,
+    /// pending, active, declined or ended
+    pub status: String,
+    /// Who may start chats with the minor: everyone, following or nobody
+    pub message_policy: String,
+    /// Leave the minor out of search and suggestions for people they don't follow
+    pub hide_from_discovery: bool,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps::option")]
+    pub accepted_at: Option<NaiveDateTime>,
+    #[serde(with = "crate::timestamps::option")]
+    pub ended_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SupervisionRequest {
+    pub minor_id: Uuid,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateLimitsRequest {
+    pub message_policy: Option<String>,
+    pub hide_from_discovery: Option<bool>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ActivityQuery {
+    /// How many days back to report (1-30, default 7)
+    pub days: Option<i64>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct DailyTimeSpent {
+    pub day: NaiveDate,
+    pub minutes: i32,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct NewContact {
+    pub user_id: Uuid,
+    pub username: String,
+    pub display_name: Option<String>,
+    /// chat, following or follower
+    pub kind: String,
+    #[serde(with = "crate::timestamps")]
+    pub since: NaiveDateTime,
+}
+
+/// What a guardian can see about a supervised account
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ActivitySummary {
+    pub minor_id: Uuid,
+    pub days: i64,
+    pub time_spent: Vec<DailyTimeSpent>,
+    pub total_minutes: i64,
+    pub new_contacts: Vec<NewContact>,
+}
+
+const LINK_COLUMNS: &str = "l.id, l.guardian_id, g.username AS guardian_username, l.minor_id, m.username AS minor_username, \
+     l.status, l.message_policy, l.hide_from_discovery, l.created_at, l.accepted_at, l.ended_at";
+
+async fn fetch_link(pool: &PgPool, link_id: Uuid) -> Result<Option<SupervisionLink>, sqlx::Error> {
+    sqlx::query_as::<_, SupervisionLink>(&format!(
+        "SELECT {} FROM supervision_links l JOIN users g ON g.id = l.guardian_id JOIN users m ON m.id = l.minor_id WHERE l.id = $1",
+        LINK_COLUMNS
+    ))
+    .bind(link_id)
+    .fetch_optional(pool)
+    .await
+}
+
+async fn notify(pool: &PgPool, user_id: Uuid, kind: &str, from_user_id: Uuid, message: Message) {
+    if let Err(e) = sqlx::query(
+        "INSERT INTO notifications (user_id, type, from_user_id, message, message_key, message_args) VALUES ($1, $2, $3, $4, $5, $6)"
+    )
+    .bind(user_id)
+    .bind(kind)
+    .bind(from_user_id)
+    .bind(message.text())
+    .bind(message.key)
+    .bind(&message.args)
+    .execute(pool)
+    .await
+    {
+        eprintln!("❌ Failed to notify {} about supervision: {:?}", user_id, e);
+    }
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    eprintln!("❌ Supervision query failed: {:?}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+}
+
+/// Reject a new chat if a member's guardian doesn't allow the creator to contact them
+pub async fn check_chat_members(pool: &PgPool, creator_id: Uuid, member_ids: &[Uuid]) -> axum::response::Result<()> {
+    let blocked: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT m FROM UNNEST($2::uuid[]) AS m WHERE m <> $1 AND supervision_blocks_contact($1, m)"
+    )
+    .bind(creator_id)
+    .bind(member_ids)
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)?;
+
+    if !blocked.is_empty() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "This account's guardian has limited who can message them".to_string(),
+        )
+            .into());
+    }
+
+    Ok(())
+}
+
+/// Add a finished WebSocket session to the minor's time spent. Nothing is
+/// recorded for accounts that aren't supervised.
+pub async fn record_session(pool: &PgPool, user_id: Uuid, seconds: i64) {
+    if seconds <= 0 {
+        return;
+    }
+
+    if let Err(e) = sqlx::query(
+        r#"
+        INSERT INTO supervised_activity (minor_id, day, seconds_active)
+        SELECT $1, CURRENT_DATE, $2
+        WHERE EXISTS(SELECT 1 FROM supervision_links WHERE minor_id = $1 AND status = 'active')
+        ON CONFLICT (minor_id, day) DO UPDATE
+        SET seconds_active = supervised_activity.seconds_active + EXCLUDED.seconds_active
+        "#
+    )
+    .bind(user_id)
+    .bind(seconds.min(i32::MAX as i64) as i32)
+    .execute(pool)
+    .await
+    {
+        eprintln!("❌ Failed to record session time for {}: {:?}", user_id, e);
+    }
+}
+
+/// End supervision of users who have turned 18 and drop old time-spent rows
+pub async fn expire(pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE supervision_links SET status = 'ended', ended_at = NOW()
+        WHERE status IN ('pending', 'active') AND NOT user_is_minor(minor_id)
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query("DELETE FROM supervised_activity WHERE day < CURRENT_DATE - $1")
+        .bind(ACTIVITY_RETENTION_DAYS)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+// Ask to supervise a minor's account; the minor has to accept
+#[utoipa::path(
+    post,
+    path = "/api/v1/supervision/requests",
+    tag = "supervision",
+    request_body = SupervisionRequest,
+    responses(
+        (status = 201, body = SupervisionLink),
+        (status = 400, description = "The account isn't a minor"),
+        (status = 403, description = "Guardians must be adults with a birthdate on file"),
+        (status = 409, description = "A request is already open"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn request_supervision(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<SupervisionRequest>,
+) -> Result<(StatusCode, Json<SupervisionLink>), (StatusCode, String)> {
+    let (guardian_is_adult, minor_is_minor) = sqlx::query_as::<_, (bool, bool)>(
+        "SELECT user_is_adult($1), user_is_minor($2)"
+    )
+    .bind(user.id)
+    .bind(payload.minor_id)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    if !guardian_is_adult {
+        return Err((StatusCode::FORBIDDEN, "Guardians must be adults with a birthdate on file".to_string()));
+    }
+    if !minor_is_minor {
+        return Err((StatusCode::BAD_REQUEST, "Only accounts of users under 18 can be supervised".to_string()));
+    }
+
+    let link_id = sqlx::query_scalar::<_, Uuid>(
+        "INSERT INTO supervision_links (guardian_id, minor_id) VALUES ($1, $2) RETURNING id"
+    )
+    .bind(user.id)
+    .bind(payload.minor_id)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(ref db) if db.is_unique_violation() => {
+            (StatusCode::CONFLICT, "You already have an open supervision request for this account".to_string())
+        }
+        e => db_error(e),
+    })?;
+
+    notify(
+        &state.pool,
+        payload.minor_id,
+        "supervision_request",
+        user.id,
+        Message::new("supervision_request").arg("username", &user.username),
+    )
+    .await;
+
+    let link = fetch_link(&state.pool, link_id)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Supervision link vanished".to_string()))?;
+
+    Ok((StatusCode::CREATED, Json(link)))
+}
+
+// Supervision links the caller is part of, as guardian or as minor
+#[utoipa::path(
+    get,
+    path = "/api/v1/supervision",
+    tag = "supervision",
+    responses((status = 200, body = [SupervisionLink]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_links(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<SupervisionLink>>, StatusCode> {
+    let links = sqlx::query_as::<_, SupervisionLink>(&format!(
+        r#"
+        SELECT {} FROM supervision_links l
+        JOIN users g ON g.id = l.guardian_id
+        JOIN users m ON m.id = l.minor_id
+        WHERE (l.guardian_id = $1 OR l.minor_id = $1)
+          AND l.status IN ('pending', 'active')
+        ORDER BY l.created_at DESC
+        "#,
+        LINK_COLUMNS
+    ))
+    .bind(user.id)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(links))
+}
+
+// The minor consents to being supervised
+#[utoipa::path(
+    post,
+    path = "/api/v1/supervision/{link_id}/accept",
+    tag = "supervision",
+    params(("link_id" = Uuid, Path, description = "Supervision link ID")),
+    responses(
+        (status = 200, body = SupervisionLink),
+        (status = 404, description = "No pending request for the caller"),
+        (status = 409, description = "The account already has a guardian"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn accept_link(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(link_id): Path<Uuid>,
+) -> Result<Json<SupervisionLink>, (StatusCode, String)> {
+    let guardian_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE supervision_links SET status = 'active', accepted_at = NOW()
+        WHERE id = $1 AND minor_id = $2 AND status = 'pending'
+        RETURNING guardian_id
+        "#
+    )
+    .bind(link_id)
+    .bind(user.id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(ref db) if db.is_unique_violation() => {
+            (StatusCode::CONFLICT, "Your account already has a guardian".to_string())
+        }
+        e => db_error(e),
+    })?
+    .ok_or((StatusCode::NOT_FOUND, "Supervision request not found".to_string()))?;
+
+    notify(
+        &state.pool,
+        guardian_id,
+        "supervision_accepted",
+        user.id,
+        Message::new("supervision_accepted").arg("username", &user.username),
+    )
+    .await;
+
+    let link = fetch_link(&state.pool, link_id)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Supervision request not found".to_string()))?;
+
+    Ok(Json(link))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/supervision/{link_id}/decline",
+    tag = "supervision",
+    params(("link_id" = Uuid, Path, description = "Supervision link ID")),
+    responses(
+        (status = 204, description = "Declined"),
+        (status = 404, description = "No pending request for the caller"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn decline_link(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(link_id): Path<Uuid>,
+) -> Result<StatusCode, StatusCode> {
+    let result = sqlx::query(
+        "UPDATE supervision_links SET status = 'declined', ended_at = NOW() WHERE id = $1 AND minor_id = $2 AND status = 'pending'"
+    )
+    .bind(link_id)
+    .bind(user.id)
+    .execute(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Either side can withdraw a request or end supervision
+#[utoipa::path(
+    delete,
+    path = "/api/v1/supervision/{link_id}",
+    tag = "supervision",
+    params(("link_id" = Uuid, Path, description = "Supervision link ID")),
+    responses(
+        (status = 204, description = "Ended"),
+        (status = 404, description = "No open link for the caller"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn end_link(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(link_id): Path<Uuid>,
+) -> Result<StatusCode, StatusCode> {
+    let ended = sqlx::query_as::<_, (Uuid, Uuid)>(
+        r#"
+        UPDATE supervision_links SET status = 'ended', ended_at = NOW()
+        WHERE id = $1 AND (guardian_id = $2 OR minor_id = $2) AND status IN ('pending', 'active')
+        RETURNING guardian_id, minor_id
+        "#
+    )
+    .bind(link_id)
+    .bind(user.id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let (guardian_id, minor_id) = ended;
+    let other = if user.id == guardian_id { minor_id } else { guardian_id };
+    notify(
+        &state.pool,
+        other,
+        "supervision_ended",
+        user.id,
+        Message::new("supervision_ended").arg("username", &user.username),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Guardian sets who can message or find the minor
+#[utoipa::path(
+    put,
+    path = "/api/v1/supervision/{link_id}/limits",
+    tag = "supervision",
+    params(("link_id" = Uuid, Path, description = "Supervision link ID")),
+    request_body = UpdateLimitsRequest,
+    responses(
+        (status = 200, body = SupervisionLink),
+        (status = 400, description = "Invalid message policy"),
+        (status = 404, description = "No active link where the caller is the guardian"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_limits(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(link_id): Path<Uuid>,
+    Json(payload): Json<UpdateLimitsRequest>,
+) -> Result<Json<SupervisionLink>, (StatusCode, String)> {
+    if let Some(policy) = payload.message_policy.as_deref() {
+        if !MESSAGE_POLICIES.contains(&policy) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("message_policy must be one of: {}", MESSAGE_POLICIES.join(", ")),
+            ));
+        }
+    }
+
+    let minor_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE supervision_links
+        SET message_policy = COALESCE($3, message_policy),
+            hide_from_discovery = COALESCE($4, hide_from_discovery)
+        WHERE id = $1 AND guardian_id = $2 AND status = 'active'
+        RETURNING minor_id
+        "#
+    )
+    .bind(link_id)
+    .bind(user.id)
+    .bind(&payload.message_policy)
+    .bind(payload.hide_from_discovery)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, "No active supervision link".to_string()))?;
+
+    // Limits are never applied silently
+    notify(
+        &state.pool,
+        minor_id,
+        "supervision_limits",
+        user.id,
+        Message::new("supervision_limits").arg("username", &user.username),
+    )
+    .await;
+
+    let link = fetch_link(&state.pool, link_id)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::NOT_FOUND, "No active supervision link".to_string()))?;
+
+    Ok(Json(link))
+}
+
+// High-level activity for the guardian: time spent and new contacts, no content
+#[utoipa::path(
+    get,
+    path = "/api/v1/supervision/{link_id}/activity",
+    tag = "supervision",
+    params(("link_id" = Uuid, Path, description = "Supervision link ID"), ActivityQuery),
+    responses(
+        (status = 200, body = ActivitySummary),
+        (status = 404, description = "No active link where the caller is the guardian"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_activity(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(link_id): Path<Uuid>,
+    Query(params): Query<ActivityQuery>,
+) -> Result<Json<ActivitySummary>, StatusCode> {
+    let minor_id = sqlx::query_scalar::<_, Uuid>(
+        "SELECT minor_id FROM supervision_links WHERE id = $1 AND guardian_id = $2 AND status = 'active'"
+    )
+    .bind(link_id)
+    .bind(user.id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let days = params.days.unwrap_or(DEFAULT_ACTIVITY_DAYS).clamp(1, MAX_ACTIVITY_DAYS);
+    let since = Utc::now().naive_utc() - Duration::days(days);
+
+    let time_spent = sqlx::query_as::<_, DailyTimeSpent>(
+        r#"
+        SELECT day, (seconds_active / 60) AS minutes
+        FROM supervised_activity
+        WHERE minor_id = $1 AND day >= $2::date
+        ORDER BY day DESC
+        "#
+    )
+    .bind(minor_id)
+    .bind(since)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let new_contacts = sqlx::query_as::<_, NewContact>(
+        r#"
+        SELECT c.user_id, u.username, u.display_name, c.kind, c.since
+        FROM (
+            SELECT other.user_id, 'chat' AS kind, MIN(GREATEST(me.joined_at, other.joined_at)) AS since
+            FROM chat_members me
+            JOIN chat_members other ON other.chat_room_id = me.chat_room_id AND other.user_id != me.user_id
+            WHERE me.user_id = $1 AND GREATEST(me.joined_at, other.joined_at) >= $2
+            GROUP BY other.user_id
+
+            UNION ALL
+
+            SELECT following_id, 'following', created_at FROM follows
+            WHERE follower_id = $1 AND created_at >= $2
+
+            UNION ALL
+
+            SELECT follower_id, 'follower', created_at FROM follows
+            WHERE following_id = $1 AND created_at >= $2
+        ) c
+        JOIN users u ON u.id = c.user_id
+        ORDER BY c.since DESC
+        LIMIT 200
+        "#
+    )
+    .bind(minor_id)
+    .bind(since)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(|e| {
+        eprintln!("❌ Supervision contacts query failed: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let total_minutes = time_spent.iter().map(|d| d.minutes as i64).sum();
+
+    Ok(Json(ActivitySummary {
+        minor_id,
+        days,
+        time_spent,
+        total_minutes,
+        new_contacts,
+    }))
+}
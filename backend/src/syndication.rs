@@ -0,0 +1,282 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::NaiveDateTime;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::chat_export::escape_html;
+use crate::AppState;
+
+// JSON Feed and RSS of a creator's live public stories, for embedding and
+// syndication. Only creator and business accounts have feeds. Rendered feeds
+// are cached; posting or removing a story refreshes them right away. Feeds
+// whose links come from the request's Host header (no PUBLIC_BASE_URL) are
+// never cached, so a forged Host can't end up in what other readers get.
+
+const FEED_ITEMS: i64 = 50;
+
+#[derive(Clone, Copy)]
+enum FeedFormat {
+    Json,
+    Rss,
+}
+
+impl FeedFormat {
+    fn name(self) -> &'static str {
+        match self {
+            FeedFormat::Json => "json",
+            FeedFormat::Rss => "rss",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            FeedFormat::Json => "application/feed+json; charset=utf-8",
+            FeedFormat::Rss => "application/rss+xml; charset=utf-8",
+        }
+    }
+}
+
+fn configured_base_url() -> Option<String> {
+    std::env::var("PUBLIC_BASE_URL")
+        .ok()
+        .filter(|base| !base.is_empty())
+        .map(|base| base.trim_end_matches('/').to_string())
+}
+
+/// Public address of the site (PUBLIC_BASE_URL, else the request's host)
+/// for links that leave the app
+pub fn public_base_url(headers: &HeaderMap) -> String {
+    if let Some(base) = configured_base_url() {
+        return base;
+    }
+    let host = headers
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("localhost");
+    format!("https://{}", host)
+}
+
+#[derive(sqlx::FromRow)]
+struct Creator {
+    id: Uuid,
+    username: String,
+    display_name: Option<String>,
+    bio: Option<String>,
+    avatar_url: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct FeedStory {
+    id: Uuid,
+    media_url: String,
+    media_type: String,
+    caption: Option<String>,
+    alt_text: Option<String>,
+    created_at: NaiveDateTime,
+    expires_at: NaiveDateTime,
+}
+
+struct FeedItem {
+    story: FeedStory,
+    media_url: String,
+    mime_type: &'static str,
+}
+
+async fn render(state: &AppState, username: &str, headers: &HeaderMap, format: FeedFormat) -> Result<Response, StatusCode> {
+    let creator = sqlx::query_as::<_, Creator>(
+        r#"
+        SELECT id, username, display_name, bio, avatar_url
+        FROM users
+        WHERE LOWER(username) = LOWER($1) AND account_type <> 'personal' AND NOT user_is_minor(id)
+        "#
+    )
+    .bind(username)
+    .fetch_optional(state.read_pool())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let cacheable = configured_base_url().is_some();
+    let key = crate::cache::feed_key(&state.redis, creator.id, format.name()).await;
+    if cacheable {
+        if let Some(body) = crate::cache::get::<String>(&state.redis, &key).await {
+            return Ok(feed_response(format, body, true));
+        }
+    }
+
+    let stories = sqlx::query_as::<_, FeedStory>(&format!(
+        r#"
+        SELECT s.id, s.media_url, s.media_type, s.caption, s.alt_text, s.created_at, s.expires_at
+        FROM stories s
+        JOIN users u ON u.id = s.user_id
+        WHERE s.user_id = $1 AND s.expires_at > NOW() AND {}
+        ORDER BY s.created_at DESC
+        LIMIT $2
+        "#,
+        crate::stories::PUBLIC_STORY
+    ))
+    .bind(creator.id)
+    .bind(FEED_ITEMS)
+    .fetch_all(state.read_pool())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut items = Vec::with_capacity(stories.len());
+    for story in stories {
+        let media_url = state.media_service.sign_url_until(&story.media_url, story.expires_at).await;
+        let mime_type = crate::mime_sniff::content_type_from_url(&media_url).unwrap_or(if story.media_type == "video" {
+            "video/mp4"
+        } else {
+            "image/jpeg"
+        });
+        items.push(FeedItem { story, media_url, mime_type });
+    }
+
+    let avatar_url = match &creator.avatar_url {
+        Some(url) => Some(state.media_service.sign_url(url).await),
+        None => None,
+    };
+    let base_url = public_base_url(headers);
+
+    let body = match format {
+        FeedFormat::Json => json_feed(&base_url, &creator, avatar_url.as_deref(), &items),
+        FeedFormat::Rss => rss_feed(&base_url, &creator, &items),
+    };
+
+    if cacheable {
+        crate::cache::set(&state.redis, &key, &body, crate::cache::FEED_TTL_SECS).await;
+    }
+    Ok(feed_response(format, body, cacheable))
+}
+
+fn feed_response(format: FeedFormat, body: String, cacheable: bool) -> Response {
+    let cache_control = if cacheable {
+        format!("public, max-age={}", crate::cache::FEED_TTL_SECS)
+    } else {
+        "no-store".to_string()
+    };
+    (
+        [(header::CONTENT_TYPE, format.content_type().to_string()), (header::CACHE_CONTROL, cache_control)],
+        body,
+    )
+        .into_response()
+}
+
+fn feed_title(creator: &Creator) -> String {
+    match &creator.display_name {
+        Some(name) if !name.is_empty() => format!("{} (@{})", name, creator.username),
+        _ => format!("@{}", creator.username),
+    }
+}
+
+fn json_feed(base_url: &str, creator: &Creator, avatar_url: Option<&str>, items: &[FeedItem]) -> String {
+    let items: Vec<serde_json::Value> = items
+        .iter()
+        .map(|item| {
+            serde_json::json!({
+                "id": item.story.id,
+                "url": item.media_url,
+                "content_text": item.story.caption.clone().unwrap_or_default(),
+                "image": (item.story.media_type != "video").then_some(&item.media_url),
+                "date_published": item.story.created_at.and_utc().to_rfc3339(),
+                "attachments": [{
+                    "url": item.media_url,
+                    "mime_type": item.mime_type,
+                    "title": item.story.alt_text,
+                }],
+                "_relays": { "expires_at": item.story.expires_at.and_utc().to_rfc3339() },
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "version": "https://jsonfeed.org/version/1.1",
+        "title": feed_title(creator),
+        "home_page_url": format!("{}/u/{}", base_url, creator.username),
+        "feed_url": format!("{}/api/v1/users/{}/feed.json", base_url, creator.username),
+        "description": creator.bio,
+        "icon": avatar_url,
+        "authors": [{ "name": feed_title(creator), "avatar": avatar_url }],
+        "items": items,
+    })
+    .to_string()
+}
+
+fn rss_feed(base_url: &str, creator: &Creator, items: &[FeedItem]) -> String {
+    let title = escape_html(&feed_title(creator));
+    let mut rss = format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            "\n<rss version=\"2.0\">\n<channel>\n",
+            "<title>{title}</title>\n<link>{link}</link>\n<description>{description}</description>\n",
+            "<ttl>{ttl}</ttl>\n"
+        ),
+        title = title,
+        link = escape_html(&format!("{}/u/{}", base_url, creator.username)),
+        description = escape_html(creator.bio.as_deref().unwrap_or(&format!("Stories by @{}", creator.username))),
+        ttl = crate::cache::FEED_TTL_SECS / 60,
+    );
+
+    for item in items {
+        let caption = item.story.caption.as_deref().filter(|c| !c.is_empty());
+        rss.push_str(&format!(
+            concat!(
+                "<item>\n<title>{title}</title>\n<link>{url}</link>\n",
+                "<guid isPermaLink=\"false\">{id}</guid>\n<pubDate>{published}</pubDate>\n",
+                "<description>{description}</description>\n",
+                "<enclosure url=\"{url}\" length=\"0\" type=\"{mime_type}\"/>\n</item>\n"
+            ),
+            title = escape_html(caption.unwrap_or(&format!("Story by @{}", creator.username))),
+            url = escape_html(&item.media_url),
+            id = item.story.id,
+            published = item.story.created_at.and_utc().to_rfc2822(),
+            description = escape_html(caption.or(item.story.alt_text.as_deref()).unwrap_or_default()),
+            mime_type = item.mime_type,
+        ));
+    }
+
+    rss.push_str("</channel>\n</rss>\n");
+    rss
+}
+
+// JSON Feed of a creator's live public stories
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/{username}/feed.json",
+    tag = "stories",
+    params(("username" = String, Path, description = "Username of a creator or business account")),
+    responses(
+        (status = 200, description = "JSON Feed 1.1 document", content_type = "application/feed+json"),
+        (status = 404, description = "No such creator account")
+    )
+)]
+pub async fn get_json_feed(
+    State(state): State<Arc<AppState>>,
+    Path(username): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    render(&state, &username, &headers, FeedFormat::Json).await
+}
+
+// RSS 2.0 feed of a creator's live public stories, media as enclosures
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/{username}/feed.rss",
+    tag = "stories",
+    params(("username" = String, Path, description = "Username of a creator or business account")),
+    responses(
+        (status = 200, description = "RSS 2.0 document", content_type = "application/rss+xml"),
+        (status = 404, description = "No such creator account")
+    )
+)]
+pub async fn get_rss_feed(
+    State(state): State<Arc<AppState>>,
+    Path(username): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    render(&state, &username, &headers, FeedFormat::Rss).await
+}
@@ -0,0 +1,281 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::permissions::{CanModerateContent, Permitted};
+use crate::AppState;
+
+// Country-specific legal takedowns. Admins withhold a story or comment in the
+// countries a legal request covers; feed, story and comment endpoints skip it
+// for requests whose CF-IPCountry header is one of them (see geo_blocked in
+// migration 034). Takedowns are lifted, never deleted, to keep the record.
+
+const CONTENT_TYPES: [&str; 2] = ["story", "comment"];
+const MAX_LEGAL_BASIS_LENGTH: usize = 5000;
+
+/// Country the request comes from, as reported by Cloudflare. Unknown ("XX")
+/// and Tor ("T1") come back as None, which no takedown applies to.
+pub fn request_country(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("CF-IPCountry")
+        .and_then(|v| v.to_str().ok())
+        .map(|c| c.trim().to_uppercase())
+        .filter(|c| is_country_code(c) && c != "XX")
+}
+
+fn is_country_code(code: &str) -> bool {
+    code.len() == 2 && code.chars().all(|c| c.is_ascii_uppercase())
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct GeoTakedown {
+    pub id: Uuid,
+    /// story or comment
+    pub content_type: String,
+    pub content_id: Uuid,
+    /// ISO 3166-1 alpha-2 codes the content is withheld in
+    pub countries: Vec<String>,
+    pub legal_basis: String,
+    pub reference: Option<String>,
+    pub requesting_authority: Option<String>,
+    pub created_by: Option<Uuid>,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps::option")]
+    pub lifted_at: Option<NaiveDateTime>,
+    pub lifted_by: Option<Uuid>,
+    pub lift_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CreateTakedownRequest {
+    pub content_type: String,
+    pub content_id: Uuid,
+    pub countries: Vec<String>,
+    /// Law or order the takedown is based on
+    pub legal_basis: String,
+    pub reference: Option<String>,
+    pub requesting_authority: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LiftTakedownRequest {
+    pub reason: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TakedownsQuery {
+    pub content_type: Option<String>,
+    pub content_id: Option<Uuid>,
+    /// Include lifted takedowns (default false)
+    pub include_lifted: Option<bool>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TakedownsResponse {
+    pub takedowns: Vec<GeoTakedown>,
+    pub page: i64,
+    pub per_page: i64,
+}
+
+async fn content_exists(pool: &PgPool, content_type: &str, content_id: Uuid) -> Result<bool, sqlx::Error> {
+    let query = match content_type {
+        "story" => {
+            "SELECT EXISTS(SELECT 1 FROM stories WHERE id = $1) \
+             OR EXISTS(SELECT 1 FROM story_archive WHERE original_story_id = $1)"
+        }
+        _ => "SELECT EXISTS(SELECT 1 FROM story_comments WHERE id = $1)",
+    };
+    sqlx::query_scalar::<_, bool>(query).bind(content_id).fetch_one(pool).await
+}
+
+// Withhold a story or comment in specific countries
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/takedowns",
+    tag = "admin",
+    request_body = CreateTakedownRequest,
+    responses(
+        (status = 201, body = GeoTakedown),
+        (status = 400, description = "Invalid takedown"),
+        (status = 404, description = "Content not found"),
+        (status = 403, description = "Only admins can file takedowns"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn create_takedown(
+    admin: Permitted<CanModerateContent>,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CreateTakedownRequest>,
+) -> Result<(StatusCode, Json<GeoTakedown>), (StatusCode, String)> {
+    if admin.0.role != "admin" {
+        return Err((StatusCode::FORBIDDEN, "Only admins can file takedowns".to_string()));
+    }
+    if !CONTENT_TYPES.contains(&payload.content_type.as_str()) {
+        return Err((StatusCode::BAD_REQUEST, format!("content_type must be one of: {}", CONTENT_TYPES.join(", "))));
+    }
+
+    let mut countries: Vec<String> = payload.countries.iter().map(|c| c.trim().to_uppercase()).collect();
+    countries.sort();
+    countries.dedup();
+    if countries.is_empty() || !countries.iter().all(|c| is_country_code(c)) {
+        return Err((StatusCode::BAD_REQUEST, "countries must be ISO 3166-1 alpha-2 codes".to_string()));
+    }
+
+    let legal_basis = payload.legal_basis.trim();
+    if legal_basis.is_empty() || legal_basis.chars().count() > MAX_LEGAL_BASIS_LENGTH {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("legal_basis must be 1-{} characters", MAX_LEGAL_BASIS_LENGTH),
+        ));
+    }
+
+    let exists = content_exists(&state.pool, &payload.content_type, payload.content_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !exists {
+        return Err((StatusCode::NOT_FOUND, "Content not found".to_string()));
+    }
+
+    let takedown = sqlx::query_as::<_, GeoTakedown>(
+        r#"
+        INSERT INTO geo_takedowns
+            (content_type, content_id, countries, legal_basis, reference, requesting_authority, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING *
+        "#
+    )
+    .bind(&payload.content_type)
+    .bind(payload.content_id)
+    .bind(&countries)
+    .bind(legal_basis)
+    .bind(payload.reference.as_deref().map(str::trim).filter(|r| !r.is_empty()))
+    .bind(payload.requesting_authority.as_deref().map(str::trim).filter(|r| !r.is_empty()))
+    .bind(admin.0.id)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "create_takedown".to_string(),
+        None,
+        Some(payload.content_type.clone()),
+        Some(payload.content_id),
+        serde_json::json!({ "takedown_id": takedown.id, "countries": countries }),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(takedown)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/takedowns",
+    tag = "admin",
+    params(TakedownsQuery),
+    responses((status = 200, body = TakedownsResponse), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_takedowns(
+    _admin: Permitted<CanModerateContent>,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<TakedownsQuery>,
+) -> Result<Json<TakedownsResponse>, StatusCode> {
+    let page = params.page.unwrap_or(1).max(1);
+    let per_page = params.per_page.unwrap_or(50).clamp(1, 100);
+    let offset = (page - 1) * per_page;
+
+    let takedowns = sqlx::query_as::<_, GeoTakedown>(
+        r#"
+        SELECT * FROM geo_takedowns
+        WHERE ($1::text IS NULL OR content_type = $1)
+          AND ($2::uuid IS NULL OR content_id = $2)
+          AND ($3 OR lifted_at IS NULL)
+        ORDER BY created_at DESC
+        LIMIT $4 OFFSET $5
+        "#
+    )
+    .bind(&params.content_type)
+    .bind(params.content_id)
+    .bind(params.include_lifted.unwrap_or(false))
+    .bind(per_page)
+    .bind(offset)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(TakedownsResponse { takedowns, page, per_page }))
+}
+
+// Lift a takedown; the row stays for the record
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/takedowns/{takedown_id}/lift",
+    tag = "admin",
+    params(("takedown_id" = Uuid, Path, description = "Takedown ID")),
+    request_body = LiftTakedownRequest,
+    responses(
+        (status = 200, body = GeoTakedown),
+        (status = 400, description = "A reason is required"),
+        (status = 404, description = "Takedown not found or already lifted"),
+        (status = 403, description = "Only admins can lift takedowns"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn lift_takedown(
+    admin: Permitted<CanModerateContent>,
+    State(state): State<Arc<AppState>>,
+    Path(takedown_id): Path<Uuid>,
+    Json(payload): Json<LiftTakedownRequest>,
+) -> Result<Json<GeoTakedown>, (StatusCode, String)> {
+    if admin.0.role != "admin" {
+        return Err((StatusCode::FORBIDDEN, "Only admins can lift takedowns".to_string()));
+    }
+    let reason = payload.reason.trim();
+    if reason.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "A reason is required".to_string()));
+    }
+
+    let takedown = sqlx::query_as::<_, GeoTakedown>(
+        r#"
+        UPDATE geo_takedowns SET lifted_at = NOW(), lifted_by = $2, lift_reason = $3
+        WHERE id = $1 AND lifted_at IS NULL
+        RETURNING *
+        "#
+    )
+    .bind(takedown_id)
+    .bind(admin.0.id)
+    .bind(reason)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Takedown not found or already lifted".to_string()))?;
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "lift_takedown".to_string(),
+        None,
+        Some(takedown.content_type.clone()),
+        Some(takedown.content_id),
+        serde_json::json!({ "takedown_id": takedown.id, "reason": reason }),
+    )
+    .await;
+
+    Ok(Json(takedown))
+}
@@ -0,0 +1,522 @@
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::StatusCode,
+};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::permissions::{CanModerateContent, Permitted};
+use crate::AppState;
+
+// Word filter for captions, comments and group names. Each word is folded
+// before matching: lower case, leetspeak to letters (sh1t, a$$), other symbols
+// dropped and repeated letters collapsed (fuuuck), then compared whole, or
+// with a common suffix, against moderation_terms. What a match does depends
+// on the term's severity, set with MODERATION_MODE_HIGH / _MEDIUM / _LOW:
+//   block - refuse the text (default for high)
+//   mask  - replace the word with its first letter and asterisks (medium)
+//   flag  - keep the text and queue it in moderation_flags for review (low)
+//   off   - ignore the term
+
+pub const CONTENT_CAPTION: &str = "caption";
+pub const CONTENT_COMMENT: &str = "comment";
+pub const CONTENT_GROUP_NAME: &str = "group_name";
+pub const CONTENT_LIVE_TITLE: &str = "live_title";
+pub const CONTENT_LIVE_CHAT: &str = "live_chat";
+
+const SEVERITIES: [&str; 3] = ["low", "medium", "high"];
+const SUFFIXES: [&str; 10] = ["s", "es", "ed", "er", "ers", "ing", "in", "y", "ty", "ies"];
+const TERMS_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl Severity {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "low" => Some(Severity::Low),
+            "medium" => Some(Severity::Medium),
+            "high" => Some(Severity::High),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Block,
+    Mask,
+    Flag,
+    Off,
+}
+
+fn mode(severity: Severity) -> Mode {
+    let (name, default) = match severity {
+        Severity::High => ("MODERATION_MODE_HIGH", Mode::Block),
+        Severity::Medium => ("MODERATION_MODE_MEDIUM", Mode::Mask),
+        Severity::Low => ("MODERATION_MODE_LOW", Mode::Flag),
+    };
+    match std::env::var(name).unwrap_or_default().as_str() {
+        "block" => Mode::Block,
+        "mask" => Mode::Mask,
+        "flag" => Mode::Flag,
+        "off" => Mode::Off,
+        _ => default,
+    }
+}
+
+struct Term {
+    folded: String,
+    term: String,
+    severity: Severity,
+}
+
+type TermCache = RwLock<Option<(Instant, Arc<Vec<Term>>)>>;
+
+fn term_cache() -> &'static TermCache {
+    static CACHE: OnceLock<TermCache> = OnceLock::new();
+    CACHE.get_or_init(|| RwLock::new(None))
+}
+
+fn invalidate_terms() {
+    if let Ok(mut cache) = term_cache().write() {
+        *cache = None;
+    }
+}
+
+async fn terms(pool: &PgPool) -> Result<Arc<Vec<Term>>, sqlx::Error> {
+    if let Ok(cache) = term_cache().read() {
+        if let Some((loaded_at, terms)) = cache.as_ref() {
+            if loaded_at.elapsed() < TERMS_TTL {
+                return Ok(terms.clone());
+            }
+        }
+    }
+
+    let rows = sqlx::query_as::<_, (String, String)>("SELECT term, severity FROM moderation_terms")
+        .fetch_all(pool)
+        .await?;
+    let terms = Arc::new(
+        rows.into_iter()
+            .filter_map(|(term, severity)| {
+                Some(Term {
+                    folded: fold(&term),
+                    severity: Severity::parse(&severity)?,
+                    term,
+                })
+            })
+            .filter(|t| !t.folded.is_empty())
+            .collect::<Vec<_>>(),
+    );
+    if let Ok(mut cache) = term_cache().write() {
+        *cache = Some((Instant::now(), terms.clone()));
+    }
+    Ok(terms)
+}
+
+/// Reduce a word to the form it is matched in
+fn fold(word: &str) -> String {
+    let mut folded = String::with_capacity(word.len());
+    for c in word.chars().flat_map(char::to_lowercase) {
+        let c = match c {
+            '0' => 'o',
+            '1' | '!' | '|' => 'i',
+            '3' => 'e',
+            '4' | '@' => 'a',
+            '5' | '$' => 's',
+            '7' | '+' => 't',
+            '8' => 'b',
+            c if c.is_alphanumeric() => c,
+            _ => continue,
+        };
+        if !folded.ends_with(c) {
+            folded.push(c);
+        }
+    }
+    folded
+}
+
+fn matches(folded: &str, term: &str) -> bool {
+    folded == term
+        || folded
+            .strip_prefix(term)
+            .is_some_and(|rest| SUFFIXES.iter().any(|s| fold(s) == rest))
+}
+
+// Punctuation around a word that isn't part of it
+fn is_surrounding(c: char) -> bool {
+    ".,;:?!\"'()[]{}<>*_~".contains(c)
+}
+
+/// Text that passed the filter, with masked words replaced
+pub struct Screened {
+    pub text: String,
+    flagged_terms: Vec<String>,
+    flag_severity: Option<Severity>,
+}
+
+impl Screened {
+    /// Queue the stored content for review if a term in flag mode matched
+    pub async fn record(&self, pool: &PgPool, content_type: &str, content_id: Uuid, user_id: Uuid) {
+        let Some(severity) = self.flag_severity else {
+            return;
+        };
+        let result = sqlx::query(
+            r#"
+            INSERT INTO moderation_flags (content_type, content_id, user_id, content_text, matched_terms, severity)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#
+        )
+        .bind(content_type)
+        .bind(content_id)
+        .bind(user_id)
+        .bind(&self.text)
+        .bind(&self.flagged_terms)
+        .bind(severity.as_str())
+        .execute(pool)
+        .await;
+        if let Err(e) = result {
+            eprintln!("Failed to flag {} {}: {}", content_type, content_id, e);
+        }
+    }
+}
+
+/// Run `text` through the filter: refused if a blocked term matches,
+/// otherwise returned with masked terms replaced
+pub async fn screen(pool: &PgPool, text: &str) -> Result<Screened, (StatusCode, String)> {
+    let terms = terms(pool).await.map_err(|e| {
+        eprintln!("Failed to load moderation terms: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to check text".to_string())
+    })?;
+
+    let mut screened = Screened {
+        text: String::with_capacity(text.len()),
+        flagged_terms: Vec::new(),
+        flag_severity: None,
+    };
+    let mut rest = text;
+    while !rest.is_empty() {
+        // Copy whitespace through, then handle one word
+        let word_start = rest.find(|c: char| !c.is_whitespace()).unwrap_or(rest.len());
+        screened.text.push_str(&rest[..word_start]);
+        rest = &rest[word_start..];
+        let word_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let word = &rest[..word_end];
+        rest = &rest[word_end..];
+
+        let core = word.trim_matches(is_surrounding);
+        let folded = fold(core);
+        let matched = terms
+            .iter()
+            .filter(|t| matches(&folded, &t.folded) && mode(t.severity) != Mode::Off)
+            .max_by_key(|t| t.severity);
+        let Some(term) = matched else {
+            screened.text.push_str(word);
+            continue;
+        };
+
+        match mode(term.severity) {
+            Mode::Block => {
+                return Err((StatusCode::BAD_REQUEST, "This contains language that isn't allowed".to_string()));
+            }
+            Mode::Mask => {
+                let offset = word.len() - word.trim_start_matches(is_surrounding).len();
+                let mut chars = core.chars();
+                let masked: String = chars.next().into_iter().chain(chars.map(|_| '*')).collect();
+                screened.text.push_str(&word[..offset]);
+                screened.text.push_str(&masked);
+                screened.text.push_str(&word[offset + core.len()..]);
+            }
+            Mode::Flag => {
+                screened.text.push_str(word);
+                if !screened.flagged_terms.contains(&term.term) {
+                    screened.flagged_terms.push(term.term.clone());
+                }
+                screened.flag_severity = screened.flag_severity.max(Some(term.severity));
+            }
+            Mode::Off => screened.text.push_str(word),
+        }
+    }
+
+    Ok(screened)
+}
+
+// ============================================================================
+// ADMINISTRATION
+// ============================================================================
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct ModerationTerm {
+    term: String,
+    /// low, medium or high
+    severity: String,
+    created_by: Option<Uuid>,
+    #[serde(with = "crate::timestamps")]
+    created_at: NaiveDateTime,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ModerationTermInput {
+    term: String,
+    /// low, medium or high
+    severity: String,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct ModerationFlag {
+    id: Uuid,
+    /// caption, comment, group_name, live_title or live_chat
+    content_type: String,
+    content_id: Uuid,
+    user_id: Option<Uuid>,
+    content_text: String,
+    matched_terms: Vec<String>,
+    severity: String,
+    /// pending, dismissed or actioned
+    status: String,
+    reviewed_by: Option<Uuid>,
+    #[serde(with = "crate::timestamps::option")]
+    reviewed_at: Option<NaiveDateTime>,
+    #[serde(with = "crate::timestamps")]
+    created_at: NaiveDateTime,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ModerationFlagsQuery {
+    /// pending (default), dismissed or actioned
+    status: Option<String>,
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ReviewFlagInput {
+    /// dismissed or actioned
+    status: String,
+}
+
+// List filter terms
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/moderation/terms",
+    tag = "admin",
+    responses((status = 200, body = [ModerationTerm]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_terms(
+    State(state): State<Arc<AppState>>,
+    _admin: Permitted<CanModerateContent>,
+) -> Result<Json<Vec<ModerationTerm>>, (StatusCode, String)> {
+    let terms = sqlx::query_as::<_, ModerationTerm>("SELECT * FROM moderation_terms ORDER BY severity DESC, term")
+        .fetch_all(state.pool.as_ref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(terms))
+}
+
+// Add a filter term or change its severity
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/moderation/terms",
+    tag = "admin",
+    request_body = ModerationTermInput,
+    responses(
+        (status = 200, body = ModerationTerm),
+        (status = 400, description = "Empty term or unknown severity"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn add_term(
+    State(state): State<Arc<AppState>>,
+    admin: Permitted<CanModerateContent>,
+    Json(payload): Json<ModerationTermInput>,
+) -> Result<Json<ModerationTerm>, (StatusCode, String)> {
+    let term = payload.term.trim().to_lowercase();
+    if fold(&term).is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "term must contain letters".to_string()));
+    }
+    if !SEVERITIES.contains(&payload.severity.as_str()) {
+        return Err((StatusCode::BAD_REQUEST, format!("severity must be one of: {}", SEVERITIES.join(", "))));
+    }
+
+    let added = sqlx::query_as::<_, ModerationTerm>(
+        r#"
+        INSERT INTO moderation_terms (term, severity, created_by)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (term) DO UPDATE SET severity = EXCLUDED.severity
+        RETURNING *
+        "#
+    )
+    .bind(&term)
+    .bind(&payload.severity)
+    .bind(admin.0.id)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    invalidate_terms();
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "add_moderation_term".to_string(),
+        None,
+        Some("moderation_term".to_string()),
+        None,
+        serde_json::json!({ "term": term, "severity": payload.severity }),
+    )
+    .await;
+
+    Ok(Json(added))
+}
+
+// Remove a filter term
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/moderation/terms/{term}",
+    tag = "admin",
+    params(("term" = String, Path, description = "Filter term")),
+    responses(
+        (status = 204, description = "Term removed"),
+        (status = 404, description = "No such term"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn remove_term(
+    State(state): State<Arc<AppState>>,
+    admin: Permitted<CanModerateContent>,
+    Path(term): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let term = term.to_lowercase();
+    let removed = sqlx::query("DELETE FROM moderation_terms WHERE term = $1")
+        .bind(&term)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .rows_affected();
+    if removed == 0 {
+        return Err((StatusCode::NOT_FOUND, "No such term".to_string()));
+    }
+    invalidate_terms();
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "remove_moderation_term".to_string(),
+        None,
+        Some("moderation_term".to_string()),
+        None,
+        serde_json::json!({ "term": term }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Content queued for review by the filter
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/moderation/flags",
+    tag = "admin",
+    params(ModerationFlagsQuery),
+    responses((status = 200, body = [ModerationFlag]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_flags(
+    State(state): State<Arc<AppState>>,
+    _admin: Permitted<CanModerateContent>,
+    Query(params): Query<ModerationFlagsQuery>,
+) -> Result<Json<Vec<ModerationFlag>>, (StatusCode, String)> {
+    let page = params.page.unwrap_or(1).max(1);
+    let per_page = params.per_page.unwrap_or(50).clamp(1, 100);
+
+    let flags = sqlx::query_as::<_, ModerationFlag>(
+        r#"
+        SELECT * FROM moderation_flags
+        WHERE status = $1
+        ORDER BY created_at DESC
+        LIMIT $2 OFFSET $3
+        "#
+    )
+    .bind(params.status.as_deref().unwrap_or("pending"))
+    .bind(per_page)
+    .bind((page - 1) * per_page)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(flags))
+}
+
+// Close a flag: dismissed (fine as is) or actioned (content dealt with)
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/moderation/flags/{flag_id}/review",
+    tag = "admin",
+    params(("flag_id" = Uuid, Path, description = "Flag ID")),
+    request_body = ReviewFlagInput,
+    responses(
+        (status = 200, body = ModerationFlag),
+        (status = 400, description = "Unknown status"),
+        (status = 404, description = "Flag not found or already reviewed"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn review_flag(
+    State(state): State<Arc<AppState>>,
+    admin: Permitted<CanModerateContent>,
+    Path(flag_id): Path<Uuid>,
+    Json(payload): Json<ReviewFlagInput>,
+) -> Result<Json<ModerationFlag>, (StatusCode, String)> {
+    if payload.status != "dismissed" && payload.status != "actioned" {
+        return Err((StatusCode::BAD_REQUEST, "status must be one of: dismissed, actioned".to_string()));
+    }
+
+    let flag = sqlx::query_as::<_, ModerationFlag>(
+        r#"
+        UPDATE moderation_flags SET status = $2, reviewed_by = $3, reviewed_at = NOW()
+        WHERE id = $1 AND status = 'pending'
+        RETURNING *
+        "#
+    )
+    .bind(flag_id)
+    .bind(&payload.status)
+    .bind(admin.0.id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Flag not found or already reviewed".to_string()))?;
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "review_moderation_flag".to_string(),
+        flag.user_id,
+        Some("moderation_flag".to_string()),
+        Some(flag.id),
+        serde_json::json!({ "status": flag.status, "content_type": flag.content_type, "content_id": flag.content_id }),
+    )
+    .await;
+
+    Ok(Json(flag))
+}
@@ -0,0 +1,105 @@
+use axum::{
+    extract::Request,
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, NaiveDateTime, SecondsFormat};
+use serde::{Deserialize, Deserializer, Serializer};
+
+use crate::versioning::ApiVersion;
+
+// API timestamps are UTC and go out as RFC3339 ("2026-03-01T12:00:00.123456Z").
+// Columns are TIMESTAMP (the pool pins the session time zone to UTC), so
+// structs keep NaiveDateTime and opt into this format with
+// #[serde(with = "crate::timestamps")].
+//
+// Clients written against the old zone-less format ("2026-03-01T12:00:00.123456")
+// keep getting it on the legacy /api routes, or anywhere by sending
+// `X-Timestamp-Format: naive`. Both formats are accepted on the way in.
+
+pub const FORMAT_HEADER: &str = "x-timestamp-format";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Rfc3339,
+    /// Zone-less ISO 8601, the format before timestamps carried a zone
+    Naive,
+}
+
+tokio::task_local! {
+    static STYLE: Style;
+}
+
+/// Format a UTC timestamp in the style the current request asked for.
+/// Outside a request (WebSocket pushes, jobs, webhooks) this is RFC3339.
+pub fn format(at: NaiveDateTime) -> String {
+    match STYLE.try_with(|style| *style).unwrap_or(Style::Rfc3339) {
+        Style::Rfc3339 => at.and_utc().to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        Style::Naive => at.format("%Y-%m-%dT%H:%M:%S%.f").to_string(),
+    }
+}
+
+/// Parse either an RFC3339 timestamp (converted to UTC) or a zone-less one
+/// (taken as UTC)
+pub fn parse(value: &str) -> Option<NaiveDateTime> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|at| at.naive_utc())
+        .or_else(|_| value.parse::<NaiveDateTime>())
+        .ok()
+}
+
+pub fn serialize<S: Serializer>(at: &NaiveDateTime, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format(*at))
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDateTime, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse(&value).ok_or_else(|| serde::de::Error::custom(format!("invalid timestamp {:?}", value)))
+}
+
+/// The same for Option<NaiveDateTime>; pair with #[serde(default)] on
+/// request fields that may be left out
+pub mod option {
+    use chrono::NaiveDateTime;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(at: &Option<NaiveDateTime>, serializer: S) -> Result<S::Ok, S::Error> {
+        match at {
+            Some(at) => super::serialize(at, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<NaiveDateTime>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(value) => super::parse(&value)
+                .map(Some)
+                .ok_or_else(|| serde::de::Error::custom(format!("invalid timestamp {:?}", value))),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Middleware for a versioned router: pick the timestamp style from
+/// X-Timestamp-Format, falling back to the API version's default
+pub async fn negotiate(request: Request, next: Next) -> Response {
+    let requested = request
+        .headers()
+        .get(FORMAT_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| match v.trim().to_ascii_lowercase().as_str() {
+            "rfc3339" => Some(Style::Rfc3339),
+            "naive" => Some(Style::Naive),
+            _ => None,
+        });
+    let style = requested.unwrap_or_else(|| match request.extensions().get::<ApiVersion>() {
+        Some(ApiVersion::V1) => Style::Rfc3339,
+        _ => Style::Naive,
+    });
+
+    let mut response = STYLE.scope(style, next.run(request)).await;
+    // Responses differ by the header, so caches and ETags must too
+    response.headers_mut().append(header::VARY, HeaderValue::from_static(FORMAT_HEADER));
+    response
+}
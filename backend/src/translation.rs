@@ -0,0 +1,398 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::AppState;
+
+const MAX_TEXT_LENGTH: usize = 5000;
+
+/// Translation backend, picked with TRANSLATION_PROVIDER
+#[derive(Debug, Clone, Copy)]
+enum Provider {
+    DeepL,
+    Google,
+    LibreTranslate,
+}
+
+impl Provider {
+    fn from_env() -> Option<Provider> {
+        match std::env::var("TRANSLATION_PROVIDER").ok()?.to_lowercase().as_str() {
+            "deepl" => Some(Provider::DeepL),
+            "google" => Some(Provider::Google),
+            "libretranslate" => Some(Provider::LibreTranslate),
+            other => {
+                tracing::warn!("Unknown TRANSLATION_PROVIDER {:?}; translation disabled", other);
+                None
+            }
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Provider::DeepL => "deepl",
+            Provider::Google => "google",
+            Provider::LibreTranslate => "libretranslate",
+        }
+    }
+
+    fn default_url(self) -> &'static str {
+        match self {
+            Provider::DeepL => "https://api-free.deepl.com",
+            Provider::Google => "https://translation.googleapis.com",
+            Provider::LibreTranslate => "https://libretranslate.com",
+        }
+    }
+}
+
+struct Translated {
+    text: String,
+    source_lang: Option<String>,
+}
+
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("Failed to build translation HTTP client")
+    })
+}
+
+async fn call_provider(provider: Provider, text: &str, target_lang: &str) -> Result<Translated, reqwest::Error> {
+    let base_url = std::env::var("TRANSLATION_API_URL").unwrap_or_else(|_| provider.default_url().to_string());
+    let base_url = base_url.trim_end_matches('/');
+    let api_key = std::env::var("TRANSLATION_API_KEY").unwrap_or_default();
+
+    match provider {
+        Provider::DeepL => {
+            #[derive(Deserialize)]
+            struct Response {
+                translations: Vec<Item>,
+            }
+            #[derive(Deserialize)]
+            struct Item {
+                text: String,
+                detected_source_language: Option<String>,
+            }
+
+            let response: Response = http_client()
+                .post(format!("{}/v2/translate", base_url))
+                .header("Authorization", format!("DeepL-Auth-Key {}", api_key))
+                .json(&json!({ "text": [text], "target_lang": target_lang.to_uppercase() }))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            let item = response.translations.into_iter().next();
+            Ok(Translated {
+                text: item.as_ref().map(|i| i.text.clone()).unwrap_or_default(),
+                source_lang: item.and_then(|i| i.detected_source_language).map(|l| l.to_lowercase()),
+            })
+        }
+        Provider::Google => {
+            #[derive(Deserialize)]
+            struct Response {
+                data: Data,
+            }
+            #[derive(Deserialize)]
+            struct Data {
+                translations: Vec<Item>,
+            }
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Item {
+                translated_text: String,
+                detected_source_language: Option<String>,
+            }
+
+            let response: Response = http_client()
+                .post(format!("{}/language/translate/v2", base_url))
+                .query(&[("key", api_key.as_str())])
+                .json(&json!({ "q": text, "target": target_lang, "format": "text" }))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            let item = response.data.translations.into_iter().next();
+            Ok(Translated {
+                text: item.as_ref().map(|i| i.translated_text.clone()).unwrap_or_default(),
+                source_lang: item.and_then(|i| i.detected_source_language),
+            })
+        }
+        Provider::LibreTranslate => {
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Response {
+                translated_text: String,
+                detected_language: Option<Detected>,
+            }
+            #[derive(Deserialize)]
+            struct Detected {
+                language: String,
+            }
+
+            let response: Response = http_client()
+                .post(format!("{}/translate", base_url))
+                .json(&json!({
+                    "q": text,
+                    "source": "auto",
+                    "target": target_lang,
+                    "format": "text",
+                    "api_key": api_key,
+                }))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            Ok(Translated {
+                text: response.translated_text,
+                source_lang: response.detected_language.map(|d| d.language),
+            })
+        }
+    }
+}
+
+// ISO 639 language, optionally with a region ("de", "pt-br"), lowercased
+fn normalize_lang(lang: &str) -> Option<String> {
+    let lang = lang.trim().to_lowercase();
+    let mut parts = lang.split('-');
+    let language = parts.next()?;
+    let region = parts.next();
+    let valid = (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && region.is_none_or(|r| (2..=4).contains(&r.len()) && r.chars().all(|c| c.is_ascii_alphanumeric()))
+        && parts.next().is_none();
+    valid.then_some(lang)
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct TranslateRequest {
+    /// Language to translate into, e.g. "en" or "pt-br"
+    pub target_lang: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TranslationResponse {
+    /// Detected language of the original, when the backend reports it
+    pub source_lang: Option<String>,
+    pub target_lang: String,
+    pub translated_text: String,
+    /// Served from the translation cache
+    pub cached: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct CachedTranslation {
+    source_lang: Option<String>,
+    translated_text: String,
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    eprintln!("❌ Translation query failed: {:?}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+}
+
+// What's being translated; the cache table and key column depend on it
+#[derive(Clone, Copy)]
+enum Source {
+    Message(Uuid),
+    Story(Uuid),
+}
+
+impl Source {
+    fn table(self) -> (&'static str, &'static str, Uuid) {
+        match self {
+            Source::Message(id) => ("message_translations", "message_id", id),
+            Source::Story(id) => ("story_translations", "story_id", id),
+        }
+    }
+}
+
+async fn translate_cached(
+    pool: &PgPool,
+    source: Source,
+    text: &str,
+    target_lang: &str,
+) -> Result<TranslationResponse, (StatusCode, String)> {
+    let (table, column, id) = source.table();
+
+    let cached = sqlx::query_as::<_, CachedTranslation>(&format!(
+        "SELECT source_lang, translated_text FROM {} WHERE {} = $1 AND target_lang = $2",
+        table, column
+    ))
+    .bind(id)
+    .bind(target_lang)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?;
+
+    if let Some(cached) = cached {
+        return Ok(TranslationResponse {
+            source_lang: cached.source_lang,
+            target_lang: target_lang.to_string(),
+            translated_text: cached.translated_text,
+            cached: true,
+        });
+    }
+
+    let provider = Provider::from_env()
+        .ok_or((StatusCode::SERVICE_UNAVAILABLE, "Translation is not configured".to_string()))?;
+    if text.chars().count() > MAX_TEXT_LENGTH {
+        return Err((StatusCode::PAYLOAD_TOO_LARGE, "Text is too long to translate".to_string()));
+    }
+
+    let translated = call_provider(provider, text, target_lang).await.map_err(|e| {
+        tracing::error!("{} translation failed: {}", provider.name(), e.without_url());
+        (StatusCode::BAD_GATEWAY, "Translation service unavailable".to_string())
+    })?;
+
+    sqlx::query(&format!(
+        r#"
+        INSERT INTO {} ({}, target_lang, source_lang, translated_text, provider)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT DO NOTHING
+        "#,
+        table, column
+    ))
+    .bind(id)
+    .bind(target_lang)
+    .bind(&translated.source_lang)
+    .bind(&translated.text)
+    .bind(provider.name())
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+
+    Ok(TranslationResponse {
+        source_lang: translated.source_lang,
+        target_lang: target_lang.to_string(),
+        translated_text: translated.text,
+        cached: false,
+    })
+}
+
+#[derive(sqlx::FromRow)]
+struct TranslatableMessage {
+    content: Option<String>,
+    is_e2ee: bool,
+    is_member: bool,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/messages/{message_id}/translate",
+    tag = "chat",
+    params(("message_id" = Uuid, Path, description = "Message ID")),
+    request_body = TranslateRequest,
+    responses(
+        (status = 200, body = TranslationResponse),
+        (status = 403, description = "Not a member of this chat"),
+        (status = 404, description = "Message not found"),
+        (status = 422, description = "Message has no text or is end-to-end encrypted"),
+        (status = 502, description = "Translation service unavailable"),
+        (status = 503, description = "Translation is not configured"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn translate_message(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(message_id): Path<Uuid>,
+    Json(req): Json<TranslateRequest>,
+) -> Result<Json<TranslationResponse>, (StatusCode, String)> {
+    let target_lang = normalize_lang(&req.target_lang)
+        .ok_or((StatusCode::BAD_REQUEST, "Invalid target_lang".to_string()))?;
+
+    let message = sqlx::query_as::<_, TranslatableMessage>(
+        r#"
+        SELECT m.content, r.is_e2ee,
+               EXISTS(SELECT 1 FROM chat_members cm WHERE cm.chat_room_id = m.chat_room_id AND cm.user_id = $2) AS is_member
+        FROM messages m
+        JOIN chat_rooms r ON r.id = m.chat_room_id
+        WHERE m.id = $1 AND m.deleted_at IS NULL
+          AND NOT EXISTS(SELECT 1 FROM message_recipients h WHERE h.message_id = m.id AND h.user_id = $2 AND h.hidden_at IS NOT NULL)
+        "#
+    )
+    .bind(message_id)
+    .bind(user.id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, "Message not found".to_string()))?;
+
+    if !message.is_member {
+        return Err((StatusCode::FORBIDDEN, "Not a member of this chat".to_string()));
+    }
+    // The server only sees ciphertext in E2EE chats, and sending it to a third
+    // party would defeat the point anyway
+    if message.is_e2ee {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Messages in end-to-end encrypted chats can't be translated".to_string(),
+        ));
+    }
+    let text = message
+        .content
+        .filter(|c| !c.trim().is_empty())
+        .ok_or((StatusCode::UNPROCESSABLE_ENTITY, "Message has no text to translate".to_string()))?;
+
+    translate_cached(&state.pool, Source::Message(message_id), &text, &target_lang)
+        .await
+        .map(Json)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/stories/{story_id}/translate",
+    tag = "stories",
+    params(("story_id" = Uuid, Path, description = "Story ID")),
+    request_body = TranslateRequest,
+    responses(
+        (status = 200, body = TranslationResponse),
+        (status = 404, description = "Story not found"),
+        (status = 422, description = "Story has no caption"),
+        (status = 502, description = "Translation service unavailable"),
+        (status = 503, description = "Translation is not configured"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn translate_story_caption(
+    _user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(story_id): Path<Uuid>,
+    Json(req): Json<TranslateRequest>,
+) -> Result<Json<TranslationResponse>, (StatusCode, String)> {
+    let target_lang = normalize_lang(&req.target_lang)
+        .ok_or((StatusCode::BAD_REQUEST, "Invalid target_lang".to_string()))?;
+
+    let caption = sqlx::query_scalar::<_, Option<String>>(
+        "SELECT caption FROM stories WHERE id = $1 AND expires_at > NOW()"
+    )
+    .bind(story_id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, "Story not found".to_string()))?
+    .filter(|c| !c.trim().is_empty())
+    .ok_or((StatusCode::UNPROCESSABLE_ENTITY, "Story has no caption to translate".to_string()))?;
+
+    translate_cached(&state.pool, Source::Story(story_id), &caption, &target_lang)
+        .await
+        .map(Json)
+}
@@ -0,0 +1,179 @@
+use axum::extract::multipart::Field;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::media::{MediaService, StoreError};
+use crate::mime_sniff::{Format, FormatError, MediaKind};
+use crate::scanning::{StreamScan, Verdict};
+use crate::storage::{ObjectMeta, UploadedPart};
+
+// Streaming file uploads. A multipart file field is read chunk by chunk:
+// the leading bytes decide the format, every chunk is hashed and fed to the
+// malware scanner, and the bytes go to storage under staging/ as a multipart
+// upload of PART_SIZE parts, so a request holds about one part in memory
+// however large the file is. MediaService::store_staged then moves the staged object
+// to its content-addressed key once the hash is known. Staged objects and
+// unfinished multipart uploads left behind by failed requests are removed by
+// the bucket cleanup job.
+
+pub const STAGING_PREFIX: &str = "staging/";
+// S3 wants every part but the last to be at least 5 MB
+const PART_SIZE: usize = 8 * 1024 * 1024;
+// mime_sniff looks at no more than this many leading bytes
+const SNIFF_BYTES: usize = 12;
+
+/// A file streamed to a staging key, not yet in the content store
+pub struct StagedUpload {
+    pub format: Format,
+    pub size: usize,
+    pub sha256: String,
+    pub staging_key: String,
+    /// Scanner result for the file; store_staged decides what to do with it
+    pub verdict: Result<Verdict, String>,
+    /// The file's bytes, kept for images when the caller asked for them
+    pub data: Option<Vec<u8>>,
+}
+
+// The storage side of a staged upload
+struct Staging<'a> {
+    media: &'a MediaService,
+    key: String,
+    upload_id: Option<String>,
+    parts: Vec<UploadedPart>,
+}
+
+impl<'a> Staging<'a> {
+    fn new(media: &'a MediaService) -> Self {
+        Self {
+            media,
+            key: format!("{}{}", STAGING_PREFIX, Uuid::new_v4()),
+            upload_id: None,
+            parts: Vec::new(),
+        }
+    }
+
+    async fn upload_part(&mut self, format: &Format, data: Vec<u8>) -> Result<(), String> {
+        let storage = &self.media.storage;
+        let upload_id = match &self.upload_id {
+            Some(upload_id) => upload_id.clone(),
+            None => {
+                let upload_id = storage.start_upload(&self.key, &ObjectMeta::typed(format.content_type)).await?;
+                self.upload_id.insert(upload_id).clone()
+            }
+        };
+
+        let part_number = self.parts.len() as i32 + 1;
+        let part = storage.upload_part(&self.key, &upload_id, part_number, data).await?;
+        self.parts.push(part);
+        Ok(())
+    }
+
+    /// Write the last bytes. Files that fit in one part skip the multipart API.
+    async fn finish(&mut self, format: &Format, rest: Vec<u8>) -> Result<(), String> {
+        let Some(upload_id) = self.upload_id.clone() else {
+            return self.media.storage.put(&self.key, rest, &ObjectMeta::typed(format.content_type)).await;
+        };
+
+        if !rest.is_empty() {
+            self.upload_part(format, rest).await?;
+        }
+        self.media.storage.complete_upload(&self.key, &upload_id, std::mem::take(&mut self.parts)).await
+    }
+
+    async fn abort(self) {
+        let Some(upload_id) = self.upload_id else {
+            return;
+        };
+        if let Err(e) = self.media.storage.abort_upload(&self.key, &upload_id).await {
+            eprintln!("❌ {}", e);
+        }
+    }
+}
+
+/// Stream a multipart file field to a staging key. The format is sniffed from
+/// the first bytes and its size limit enforced as the rest arrives, so a bad
+/// file is refused before it is read in full. With `keep_images` an image's
+/// bytes are also kept in memory (for thumbnailing).
+pub async fn stage(
+    media: &MediaService,
+    field: Field<'_>,
+    expected: Option<MediaKind>,
+    keep_images: bool,
+) -> Result<StagedUpload, StoreError> {
+    let mut staging = Staging::new(media);
+    match read_field(&mut staging, field, expected, keep_images).await {
+        Ok(staged) => Ok(staged),
+        Err(e) => {
+            staging.abort().await;
+            Err(e)
+        }
+    }
+}
+
+async fn read_field(
+    staging: &mut Staging<'_>,
+    mut field: Field<'_>,
+    expected: Option<MediaKind>,
+    keep_images: bool,
+) -> Result<StagedUpload, StoreError> {
+    let mut hasher = Sha256::new();
+    let mut scan = StreamScan::start();
+    let mut format: Option<Format> = None;
+    let mut kept: Option<Vec<u8>> = None;
+    let mut part = Vec::new();
+    let mut size = 0;
+
+    while let Some(chunk) = field.chunk().await.map_err(StoreError::Upload)? {
+        size += chunk.len();
+        if let Some(format) = &format {
+            let max_bytes = format.max_bytes();
+            if size > max_bytes {
+                return Err(FormatError::TooLarge { content_type: format.content_type, max_bytes }.into());
+            }
+        }
+        hasher.update(&chunk);
+        scan.feed(&chunk).await;
+        part.extend_from_slice(&chunk);
+
+        if format.is_none() && part.len() >= SNIFF_BYTES {
+            let sniffed = crate::mime_sniff::validate(&part, expected)?;
+            if keep_images && sniffed.kind == MediaKind::Image {
+                kept = Some(Vec::new());
+            }
+            format = Some(sniffed);
+        }
+        if let Some(format) = &format {
+            if part.len() >= PART_SIZE {
+                if let Some(kept) = kept.as_mut() {
+                    kept.extend_from_slice(&part);
+                }
+                staging.upload_part(format, std::mem::take(&mut part)).await.map_err(StoreError::Failed)?;
+            }
+        }
+    }
+
+    // Files shorter than the sniffed prefix
+    let format = match format {
+        Some(format) => format,
+        None => {
+            let sniffed = crate::mime_sniff::validate(&part, expected)?;
+            if keep_images && sniffed.kind == MediaKind::Image {
+                kept = Some(Vec::new());
+            }
+            sniffed
+        }
+    };
+    if let Some(kept) = kept.as_mut() {
+        kept.extend_from_slice(&part);
+    }
+    staging.finish(&format, part).await.map_err(StoreError::Failed)?;
+
+    Ok(StagedUpload {
+        format,
+        size,
+        sha256: hex::encode(hasher.finalize()),
+        staging_key: staging.key.clone(),
+        verdict: scan.finish().await,
+        data: kept,
+    })
+}
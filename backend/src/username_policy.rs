@@ -0,0 +1,236 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::permissions::{CanModerateContent, Permitted};
+use crate::AppState;
+
+// Rules a username has to pass at signup, rename and bot creation. Names are
+// compared by skeleton (username_skeleton in migration 052), so lookalikes
+// built from Cyrillic letters, digits or separators are caught too:
+//   - letters, digits, _ . - only, at most MAX_LENGTH characters
+//   - not a reserved name (reserved_usernames, managed by admins)
+//   - not a lookalike of another account's name
+//   - no profanity (PROFANITY plus USERNAME_BLOCKED_WORDS)
+
+const MAX_LENGTH: usize = 30;
+
+// Matched anywhere in the name, so only words that rarely occur inside others
+const PROFANITY: &[&str] = &[
+    "asshole", "bitch", "cunt", "fuck", "faggot", "nigger", "nigga", "rapist", "shit", "slut", "whore",
+];
+
+fn blocked_words() -> Vec<String> {
+    let extra = std::env::var("USERNAME_BLOCKED_WORDS").unwrap_or_default();
+    PROFANITY
+        .iter()
+        .map(|w| w.to_string())
+        .chain(extra.split(',').map(|w| w.trim().to_lowercase()).filter(|w| !w.is_empty()))
+        .collect()
+}
+
+fn rejected(message: String) -> (StatusCode, String) {
+    (StatusCode::BAD_REQUEST, message)
+}
+
+#[derive(sqlx::FromRow)]
+struct PolicyMatch {
+    reserved: Option<String>,
+    lookalike: Option<String>,
+    profane: Option<String>,
+}
+
+/// Check `username` for an account; `user_id` is the account being renamed,
+/// None for a new one. Taking a name someone already has is left to the
+/// callers' uniqueness checks.
+pub async fn check(pool: &PgPool, username: &str, user_id: Option<Uuid>) -> Result<(), (StatusCode, String)> {
+    if username.is_empty() || username.chars().count() > MAX_LENGTH {
+        return Err(rejected(format!("Usernames are 1-{} characters", MAX_LENGTH)));
+    }
+    if !username.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '-')) {
+        return Err(rejected("Usernames may only contain letters, digits, _ . and -".to_string()));
+    }
+
+    let matched = sqlx::query_as::<_, PolicyMatch>(
+        r#"
+        SELECT
+            (SELECT r.name FROM reserved_usernames r
+             WHERE username_skeleton(r.name) = username_skeleton($1)
+                OR (r.match_substring AND position(username_skeleton(r.name) IN username_skeleton($1)) > 0)
+             LIMIT 1) AS reserved,
+            (SELECT u.username FROM users u
+             WHERE username_skeleton(u.username) = username_skeleton($1)
+               AND u.username <> $1
+               AND u.id IS DISTINCT FROM $2
+             LIMIT 1) AS lookalike,
+            (SELECT w FROM unnest($3::text[]) w
+             WHERE position(username_skeleton(w) IN username_skeleton($1)) > 0
+             LIMIT 1) AS profane
+        "#
+    )
+    .bind(username)
+    .bind(user_id)
+    .bind(blocked_words())
+    .fetch_one(pool)
+    .await
+    .map_err(|e| {
+        eprintln!("Username policy check failed: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to check username".to_string())
+    })?;
+
+    if matched.reserved.is_some() {
+        return Err(rejected("This username is reserved".to_string()));
+    }
+    if let Some(existing) = matched.lookalike {
+        return Err(rejected(format!("This username is too similar to @{}", existing)));
+    }
+    if matched.profane.is_some() {
+        return Err(rejected("This username isn't allowed".to_string()));
+    }
+    Ok(())
+}
+
+// ============================================================================
+// RESERVED NAMES ADMINISTRATION
+// ============================================================================
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct ReservedUsername {
+    name: String,
+    /// Also refuse any username containing the name
+    match_substring: bool,
+    reason: Option<String>,
+    created_by: Option<Uuid>,
+    #[serde(with = "crate::timestamps")]
+    created_at: NaiveDateTime,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ReserveUsernameInput {
+    name: String,
+    #[serde(default)]
+    match_substring: bool,
+    reason: Option<String>,
+}
+
+// List reserved usernames
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/usernames/reserved",
+    tag = "admin",
+    responses((status = 200, body = [ReservedUsername]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_reserved(
+    State(state): State<Arc<AppState>>,
+    _admin: Permitted<CanModerateContent>,
+) -> Result<Json<Vec<ReservedUsername>>, (StatusCode, String)> {
+    let names = sqlx::query_as::<_, ReservedUsername>("SELECT * FROM reserved_usernames ORDER BY name")
+        .fetch_all(state.pool.as_ref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(names))
+}
+
+// Reserve a username. Accounts that already have it keep it.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/usernames/reserved",
+    tag = "admin",
+    request_body = ReserveUsernameInput,
+    responses(
+        (status = 200, body = ReservedUsername),
+        (status = 400, description = "Empty name"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn reserve(
+    State(state): State<Arc<AppState>>,
+    admin: Permitted<CanModerateContent>,
+    Json(payload): Json<ReserveUsernameInput>,
+) -> Result<Json<ReservedUsername>, (StatusCode, String)> {
+    let name = payload.name.trim().trim_start_matches('@').to_lowercase();
+    if name.is_empty() || name.chars().count() > MAX_LENGTH {
+        return Err(rejected(format!("name must be 1-{} characters", MAX_LENGTH)));
+    }
+
+    let reserved = sqlx::query_as::<_, ReservedUsername>(
+        r#"
+        INSERT INTO reserved_usernames (name, match_substring, reason, created_by)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (name) DO UPDATE SET match_substring = EXCLUDED.match_substring, reason = EXCLUDED.reason
+        RETURNING *
+        "#
+    )
+    .bind(&name)
+    .bind(payload.match_substring)
+    .bind(&payload.reason)
+    .bind(admin.0.id)
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "reserve_username".to_string(),
+        None,
+        Some("reserved_username".to_string()),
+        None,
+        serde_json::json!({ "name": name, "match_substring": payload.match_substring, "reason": payload.reason }),
+    )
+    .await;
+
+    Ok(Json(reserved))
+}
+
+// Release a reserved username
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/usernames/reserved/{name}",
+    tag = "admin",
+    params(("name" = String, Path, description = "Reserved name")),
+    responses(
+        (status = 204, description = "Name released"),
+        (status = 404, description = "Name is not reserved"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn release(
+    State(state): State<Arc<AppState>>,
+    admin: Permitted<CanModerateContent>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let name = name.to_lowercase();
+    let removed = sqlx::query("DELETE FROM reserved_usernames WHERE name = $1")
+        .bind(&name)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .rows_affected();
+    if removed == 0 {
+        return Err((StatusCode::NOT_FOUND, "Name is not reserved".to_string()));
+    }
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "release_username".to_string(),
+        None,
+        Some("reserved_username".to_string()),
+        None,
+        serde_json::json!({ "name": name }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
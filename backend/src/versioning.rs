@@ -0,0 +1,135 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request},
+    http::{header::HeaderValue, request::Parts, StatusCode},
+    middleware::Next,
+    response::Response,
+    routing::MethodRouter,
+    Extension, Router,
+};
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::AppState;
+
+// Legacy unversioned /api/... routes stop working after this date unless
+// API_LEGACY_SUNSET overrides it (YYYY-MM-DD)
+const DEFAULT_LEGACY_SUNSET: &str = "2027-07-01";
+
+/// API version a request was routed through
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiVersion {
+    /// Unversioned `/api/...` alias, served by the v1 handlers
+    Legacy,
+    V1,
+}
+
+impl ApiVersion {
+    pub fn prefix(self) -> &'static str {
+        match self {
+            ApiVersion::Legacy => "/api",
+            ApiVersion::V1 => "/api/v1",
+        }
+    }
+}
+
+// Handlers can take `version: ApiVersion` to branch on the caller's version
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ApiVersion {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<ApiVersion>()
+            .copied()
+            .unwrap_or(ApiVersion::Legacy))
+    }
+}
+
+/// Route list for one API version. Newer versions start from the previous
+/// version's table and `replace` only the endpoints whose contract changed,
+/// so unchanged handlers are registered side by side under every version.
+pub struct RouteTable {
+    // Path, handlers, and whether they take uploads (see body_limit.rs)
+    routes: Vec<(&'static str, MethodRouter<Arc<AppState>>, bool)>,
+}
+
+impl RouteTable {
+    pub fn new() -> Self {
+        Self { routes: Vec::new() }
+    }
+
+    pub fn route(mut self, path: &'static str, method_router: MethodRouter<Arc<AppState>>) -> Self {
+        self.routes.push((path, method_router, false));
+        self
+    }
+
+    /// Register handlers that take file uploads, which get the upload body
+    /// limit instead of the standard one
+    pub fn upload(mut self, path: &'static str, method_router: MethodRouter<Arc<AppState>>) -> Self {
+        self.routes.push((path, method_router, true));
+        self
+    }
+
+    /// Swap every handler registered at `path` for a new one
+    #[allow(dead_code)]
+    pub fn replace(mut self, path: &'static str, method_router: MethodRouter<Arc<AppState>>) -> Self {
+        self.routes.retain(|(existing, _, _)| *existing != path);
+        self.route(path, method_router)
+    }
+
+    /// Build the router for `version`, tagging requests so ApiVersion can be extracted
+    pub fn into_router(self, version: ApiVersion) -> Router<Arc<AppState>> {
+        let router = self
+            .routes
+            .into_iter()
+            .fold(Router::new(), |router, (path, method_router, upload)| {
+                let method_router = if upload {
+                    method_router.layer(axum::middleware::from_fn(crate::body_limit::uploads))
+                } else {
+                    method_router.layer(axum::middleware::from_fn(crate::body_limit::standard))
+                };
+                router.route(path, method_router)
+            })
+            .layer(axum::middleware::from_fn(crate::timestamps::negotiate))
+            .layer(Extension(version));
+
+        if version == ApiVersion::Legacy {
+            router.layer(axum::middleware::from_fn(legacy_deprecation_headers))
+        } else {
+            router
+        }
+    }
+}
+
+// Mark legacy responses as deprecated and point at the /api/v1 equivalent
+async fn legacy_deprecation_headers(request: Request, next: Next) -> Response {
+    // Inside the nested router the /api prefix has already been stripped
+    let successor = format!("{}{}", ApiVersion::V1.prefix(), request.uri().path());
+    let mut response = next.run(request).await;
+
+    let headers = response.headers_mut();
+    headers.insert("deprecation", HeaderValue::from_static("true"));
+    if let Ok(sunset) = HeaderValue::from_str(&legacy_sunset()) {
+        headers.insert("sunset", sunset);
+    }
+    if let Ok(link) = HeaderValue::from_str(&format!("<{}>; rel=\"successor-version\"", successor)) {
+        headers.insert("link", link);
+    }
+
+    response
+}
+
+// Sunset date as an HTTP-date
+fn legacy_sunset() -> String {
+    let date = std::env::var("API_LEGACY_SUNSET")
+        .ok()
+        .and_then(|v| NaiveDate::parse_from_str(&v, "%Y-%m-%d").ok())
+        .or_else(|| NaiveDate::parse_from_str(DEFAULT_LEGACY_SUNSET, "%Y-%m-%d").ok())
+        .unwrap_or_default();
+
+    date.format("%a, %d %b %Y 00:00:00 GMT").to_string()
+}
@@ -1,18 +1,18 @@
 use axum::{
     extract::{State, Multipart, Path},
     Json,
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::Response,
-    body::Body,
 };
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use std::sync::Arc;
 use uuid::Uuid;
 use std::process::Command;
 use tokio::fs;
 use tempfile::TempDir;
-use aws_sdk_s3::primitives::ByteStream;
 
+use crate::storage::ObjectMeta;
 use crate::AppState;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,16 +42,7 @@ pub struct AudioTrack {
     pub volume: f64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct RenderRequest {
-    pub user_id: Uuid,
-    pub text_elements: Vec<TextElement>,
-    pub video_clips: Vec<VideoClip>,
-    pub audio_tracks: Vec<AudioTrack>,
-    pub speed: f64,
-}
-
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct RenderResponse {
     pub render_id: Uuid,
     pub video_url: String,
@@ -60,6 +51,13 @@ pub struct RenderResponse {
 }
 
 /// Render video with edits using FFmpeg (server-side, 10-100x faster than browser)
+#[utoipa::path(
+    post,
+    path = "/api/v1/stories/render",
+    tag = "stories",
+    request_body(content = String, content_type = "multipart/form-data"),
+    responses((status = 200, body = RenderResponse))
+)]
 pub async fn render_video(
     State(state): State<Arc<AppState>>,
     mut multipart: Multipart,
@@ -68,7 +66,6 @@ pub async fn render_video(
 
     let mut user_id: Option<Uuid> = None;
     let mut original_video_data: Option<Vec<u8>> = None;
-    let mut original_filename: Option<String> = None;
     let mut text_elements: Vec<TextElement> = Vec::new();
     let mut video_clips: Vec<VideoClip> = Vec::new();
     let mut audio_tracks: Vec<AudioTrack> = Vec::new();
@@ -86,7 +83,6 @@ pub async fn render_video(
                 user_id = Uuid::parse_str(&value).ok();
             }
             "video" => {
-                original_filename = field.file_name().map(|s| s.to_string());
                 original_video_data = Some(field.bytes().await.unwrap().to_vec());
             }
             "text_elements" => {
@@ -235,7 +231,7 @@ pub async fn render_video(
     }
 
     // Mix audio if multiple tracks
-    let audio_stream = if audio_tracks.len() > 0 {
+    let audio_stream = if !audio_tracks.is_empty() {
         let audio_inputs: String = (0..=audio_tracks.len())
             .map(|i| format!("[{}:a]", i))
             .collect::<Vec<_>>()
@@ -300,24 +296,18 @@ pub async fn render_video(
     let render_id = Uuid::new_v4();
     let s3_key = format!("stories/{}/rendered_{}.mp4", user_id, render_id);
 
-    let byte_stream = ByteStream::from(rendered_data);
-    state.media_service.s3_client
-        .put_object()
-        .bucket(&state.media_service.bucket_name)
-        .key(&s3_key)
-        .body(byte_stream)
-        .content_type("video/mp4")
-        .send()
+    state.media_service.storage
+        .put(&s3_key, rendered_data, &ObjectMeta::typed("video/mp4"))
         .await
         .map_err(|e| {
-            eprintln!("❌ S3 upload failed: {:?}", e);
+            eprintln!("❌ Upload failed: {}", e);
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
     // Use proxy URL to avoid CORS issues
     let video_url = format!("/api/stories/proxy/{}", s3_key);
 
-    println!("✅ Rendered video uploaded to S3: {}", s3_key);
+    println!("✅ Rendered video uploaded: {}", s3_key);
     println!("✅ Proxy URL: {}", video_url);
 
     Ok(Json(RenderResponse {
@@ -329,48 +319,355 @@ pub async fn render_video(
 }
 
 /// Proxy endpoint to download rendered videos from R2 (avoids CORS issues)
+#[utoipa::path(
+    get,
+    path = "/api/v1/stories/proxy/{s3_key}",
+    tag = "stories",
+    params(("s3_key" = String, Path, description = "S3 key")),
+    responses((status = 200, description = "Raw response body"))
+)]
 pub async fn proxy_rendered_video(
     State(state): State<Arc<AppState>>,
     Path(s3_key): Path<String>,
+    headers: HeaderMap,
 ) -> Result<Response, StatusCode> {
     println!("📥 Proxying video download: {}", s3_key);
+    crate::media_proxy::stream_object(&state, &s3_key, &headers, "public, max-age=31536000").await
+}
+
+// ============= Story video processing =============
+
+// Length of the looping preview clip
+const PREVIEW_SECONDS: &str = "2";
+const HLS_SEGMENT_SECONDS: &str = "4";
+// HLS ladder: (width, video bitrate). Story video is portrait, so renditions
+// are picked by width; ones wider than the source are skipped.
+const HLS_RENDITIONS: &[(u32, &str)] = &[(360, "800k"), (540, "1400k"), (720, "2800k")];
+// Packaged output never changes, so CDNs and players may cache it forever
+const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+/// Queue poster/preview generation and HLS packaging for a freshly posted video story
+pub async fn enqueue_video_processing(pool: &sqlx::PgPool, story_id: Uuid, media_type: &str) {
+    if media_type != "video" {
+        return;
+    }
+    for job_type in [crate::jobs::GENERATE_STORY_PREVIEWS, crate::jobs::PACKAGE_STORY_HLS] {
+        let payload = serde_json::json!({ "story_id": story_id });
+        if let Err(e) = crate::jobs::enqueue(pool, job_type, payload, None).await {
+            eprintln!("❌ Failed to queue {} for story {}: {:?}", job_type, story_id, e);
+        }
+    }
+}
 
-    // Download from S3/R2
-    let get_result = state.media_service.s3_client
-        .get_object()
-        .bucket(&state.media_service.bucket_name)
-        .key(&s3_key)
-        .send()
+async fn run_ffmpeg(program: &str, args: &[&std::ffi::OsStr]) -> Result<Vec<u8>, String> {
+    let output = tokio::process::Command::new(program)
+        .args(args)
+        .output()
         .await
-        .map_err(|e| {
-            eprintln!("❌ Failed to download from R2: {}", e);
-            StatusCode::NOT_FOUND
-        })?;
+        .map_err(|e| format!("{} execution failed: {}", program, e))?;
 
-    // Get content type
-    let content_type = get_result.content_type()
-        .unwrap_or("video/mp4")
-        .to_string();
+    if !output.status.success() {
+        return Err(format!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr)));
+    }
+    Ok(output.stdout)
+}
 
-    // Read body
-    let body_bytes = get_result.body
-        .collect()
-        .await
-        .map_err(|e| {
-            eprintln!("❌ Failed to read video body: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .into_bytes();
-
-    println!("✅ Downloaded {} bytes from R2", body_bytes.len());
-
-    // Return video with proper headers
-    Response::builder()
-        .status(StatusCode::OK)
-        .header("Content-Type", content_type)
-        .header("Content-Length", body_bytes.len().to_string())
-        .header("Accept-Ranges", "bytes")
-        .header("Cache-Control", "public, max-age=31536000")
-        .body(Body::from(body_bytes))
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+async fn upload_output(
+    state: &AppState,
+    key: &str,
+    path: &std::path::Path,
+    content_type: &str,
+    cache_control: Option<&str>,
+) -> Result<String, String> {
+    let data = fs::read(path).await.map_err(|e| format!("Failed to read {}: {}", key, e))?;
+    let meta = ObjectMeta {
+        cache_control: cache_control.map(str::to_string),
+        ..ObjectMeta::typed(content_type)
+    };
+    state.media_service.storage.put(key, data, &meta).await?;
+
+    Ok(state.media_service.public_url(key))
+}
+
+fn story_id_from_payload(payload: &serde_json::Value) -> Result<Uuid, String> {
+    payload
+        .get("story_id")
+        .and_then(|v| v.as_str())
+        .and_then(|v| Uuid::parse_str(v).ok())
+        .ok_or_else(|| "Missing story_id".to_string())
+}
+
+// Download a live video story into `dir`. Returns the author's id and the
+// local path, or None when the story has expired or been deleted.
+async fn download_story_video(
+    state: &AppState,
+    story_id: Uuid,
+    dir: &std::path::Path,
+) -> Result<Option<(Uuid, std::path::PathBuf)>, String> {
+    let story = sqlx::query_as::<_, (Uuid, String)>(
+        "SELECT user_id, media_url FROM stories WHERE id = $1 AND media_type = 'video' AND expires_at > NOW()"
+    )
+    .bind(story_id)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let Some((user_id, media_url)) = story else {
+        return Ok(None);
+    };
+
+    let source_key = state
+        .media_service
+        .s3_key_from_url(&media_url)
+        .ok_or_else(|| format!("Unrecognized media URL: {}", media_url))?;
+    let video = state.media_service.storage.get(&source_key).await?;
+
+    let input = dir.join("input.mp4");
+    fs::write(&input, &video).await.map_err(|e| e.to_string())?;
+
+    Ok(Some((user_id, input)))
+}
+
+/// Job body: extract a poster frame and a short muted preview from a video
+/// story and store them as its thumbnail_url / preview_url
+pub async fn generate_story_previews(state: &AppState, payload: &serde_json::Value) -> Result<Option<serde_json::Value>, String> {
+    let story_id = story_id_from_payload(payload)?;
+    let temp_dir = TempDir::new().map_err(|e| e.to_string())?;
+
+    let Some((user_id, input)) = download_story_video(state, story_id, temp_dir.path()).await? else {
+        return Ok(Some(serde_json::json!({ "skipped": "story gone" })));
+    };
+    let poster = temp_dir.path().join("poster.jpg");
+    let preview = temp_dir.path().join("preview.mp4");
+
+    // The thumbnail filter picks the most representative of the first frames,
+    // which avoids black fade-in posters
+    run_ffmpeg("ffmpeg", &[
+        "-y".as_ref(), "-i".as_ref(), input.as_os_str(),
+        "-vf".as_ref(), "thumbnail=50,scale='min(720,iw)':-2".as_ref(),
+        "-frames:v".as_ref(), "1".as_ref(),
+        "-q:v".as_ref(), "3".as_ref(),
+        poster.as_os_str(),
+    ])
+    .await?;
+
+    run_ffmpeg("ffmpeg", &[
+        "-y".as_ref(), "-i".as_ref(), input.as_os_str(),
+        "-t".as_ref(), PREVIEW_SECONDS.as_ref(),
+        "-an".as_ref(),
+        "-vf".as_ref(), "scale='min(360,iw)':-2,fps=15".as_ref(),
+        "-c:v".as_ref(), "libx264".as_ref(),
+        "-preset".as_ref(), "veryfast".as_ref(),
+        "-crf".as_ref(), "28".as_ref(),
+        "-pix_fmt".as_ref(), "yuv420p".as_ref(),
+        "-movflags".as_ref(), "+faststart".as_ref(),
+        preview.as_os_str(),
+    ])
+    .await?;
+
+    let poster_url = upload_output(
+        state,
+        &format!("stories/{}/story_{}_poster.jpg", user_id, story_id),
+        &poster,
+        "image/jpeg",
+        None,
+    )
+    .await?;
+    let preview_url = upload_output(
+        state,
+        &format!("stories/{}/story_{}_preview.mp4", user_id, story_id),
+        &preview,
+        "video/mp4",
+        None,
+    )
+    .await?;
+
+    let previous = sqlx::query_as::<_, (Option<String>, Option<String>)>(
+        r#"
+        UPDATE stories s SET thumbnail_url = $2, preview_url = $3
+        FROM (SELECT thumbnail_url AS old_thumbnail_url, preview_url AS old_preview_url FROM stories WHERE id = $1 FOR UPDATE) old
+        WHERE s.id = $1
+        RETURNING old.old_thumbnail_url, old.old_preview_url
+        "#
+    )
+    .bind(story_id)
+    .bind(&poster_url)
+    .bind(&preview_url)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // A rerun overwrote the same keys, so the CDN may still have the old render
+    if let Some((old_thumbnail_url, old_preview_url)) = previous {
+        let mut overwritten = Vec::new();
+        if old_thumbnail_url.as_deref() == Some(poster_url.as_str()) {
+            overwritten.push(poster_url.as_str());
+        }
+        if old_preview_url.as_deref() == Some(preview_url.as_str()) {
+            overwritten.push(preview_url.as_str());
+        }
+        state.media_service.purge_urls(&overwritten).await;
+    }
+
+    Ok(Some(serde_json::json!({ "thumbnail_url": poster_url, "preview_url": preview_url })))
+}
+
+// Width of the first video stream and whether there's an audio stream
+async fn probe_video(input: &std::path::Path) -> Result<(u32, bool), String> {
+    #[derive(Deserialize)]
+    struct Probe {
+        streams: Vec<ProbeStream>,
+    }
+    #[derive(Deserialize)]
+    struct ProbeStream {
+        codec_type: String,
+        width: Option<u32>,
+    }
+
+    let stdout = run_ffmpeg("ffprobe", &[
+        "-v".as_ref(), "error".as_ref(),
+        "-show_entries".as_ref(), "stream=codec_type,width".as_ref(),
+        "-of".as_ref(), "json".as_ref(),
+        input.as_os_str(),
+    ])
+    .await?;
+    let probe: Probe = serde_json::from_slice(&stdout).map_err(|e| format!("Unexpected ffprobe output: {}", e))?;
+
+    let width = probe
+        .streams
+        .iter()
+        .find(|s| s.codec_type == "video")
+        .and_then(|s| s.width)
+        .ok_or_else(|| "No video stream".to_string())?;
+    let has_audio = probe.streams.iter().any(|s| s.codec_type == "audio");
+
+    Ok((width, has_audio))
+}
+
+/// Public URL for an HLS object. HLS_PUBLIC_URL_BASE points playback at a CDN
+/// in front of the bucket, falling back to CDN_URL; playlists use relative URIs
+/// so segments follow it.
+fn hls_url(state: &AppState, key: &str) -> String {
+    match std::env::var("HLS_PUBLIC_URL_BASE") {
+        Ok(base) if !base.is_empty() => format!("{}/{}", base.trim_end_matches('/'), key),
+        _ => match &state.media_service.cdn {
+            Some(cdn) => cdn.url(key),
+            None => state.media_service.public_url(key),
+        },
+    }
+}
+
+/// Job body: package a video story as multi-bitrate HLS and store the master
+/// playlist URL as its playback_url
+pub async fn package_story_hls(state: &AppState, payload: &serde_json::Value) -> Result<Option<serde_json::Value>, String> {
+    let story_id = story_id_from_payload(payload)?;
+    let temp_dir = TempDir::new().map_err(|e| e.to_string())?;
+
+    let Some((user_id, input)) = download_story_video(state, story_id, temp_dir.path()).await? else {
+        return Ok(Some(serde_json::json!({ "skipped": "story gone" })));
+    };
+    let (source_width, has_audio) = probe_video(&input).await?;
+
+    // Always keep the lowest rendition, even for tiny sources
+    let renditions: Vec<(u32, &str)> = HLS_RENDITIONS
+        .iter()
+        .enumerate()
+        .filter(|(i, (width, _))| *i == 0 || *width <= source_width)
+        .map(|(_, r)| *r)
+        .collect();
+
+    let out_dir = temp_dir.path().join("hls");
+    for i in 0..renditions.len() {
+        fs::create_dir_all(out_dir.join(format!("v{}", i))).await.map_err(|e| e.to_string())?;
+    }
+
+    let split_outputs: String = (0..renditions.len()).map(|i| format!("[s{}]", i)).collect();
+    let mut filter = format!("[0:v]split={}{}", renditions.len(), split_outputs);
+    for (i, (width, _)) in renditions.iter().enumerate() {
+        filter.push_str(&format!(";[s{}]scale='min({},iw)':-2[v{}]", i, width, i));
+    }
+
+    let mut args: Vec<String> = vec!["-y".into(), "-i".into(), input.to_string_lossy().into_owned()];
+    args.extend(["-filter_complex".into(), filter]);
+    let mut stream_map = Vec::new();
+    for (i, (_, bitrate)) in renditions.iter().enumerate() {
+        args.extend(["-map".into(), format!("[v{}]", i)]);
+        args.extend([format!("-c:v:{}", i), "libx264".into()]);
+        args.extend([format!("-b:v:{}", i), bitrate.to_string()]);
+        args.extend([format!("-maxrate:v:{}", i), bitrate.to_string()]);
+        args.extend([format!("-bufsize:v:{}", i), bitrate.to_string()]);
+        if has_audio {
+            args.extend(["-map".into(), "0:a:0".into()]);
+            args.extend([format!("-c:a:{}", i), "aac".into()]);
+            args.extend([format!("-b:a:{}", i), "96k".into()]);
+            stream_map.push(format!("v:{},a:{}", i, i));
+        } else {
+            stream_map.push(format!("v:{}", i));
+        }
+    }
+    args.extend([
+        "-preset".into(), "veryfast".into(),
+        "-pix_fmt".into(), "yuv420p".into(),
+        // Keyframe at every segment boundary so renditions can switch cleanly
+        "-force_key_frames".into(), format!("expr:gte(t,n_forced*{})", HLS_SEGMENT_SECONDS),
+        "-f".into(), "hls".into(),
+        "-hls_time".into(), HLS_SEGMENT_SECONDS.into(),
+        "-hls_playlist_type".into(), "vod".into(),
+        "-hls_flags".into(), "independent_segments".into(),
+        "-hls_segment_filename".into(), out_dir.join("v%v/seg_%03d.ts").to_string_lossy().into_owned(),
+        "-master_pl_name".into(), "master.m3u8".into(),
+        "-var_stream_map".into(), stream_map.join(" "),
+        out_dir.join("v%v/index.m3u8").to_string_lossy().into_owned(),
+    ]);
+    let arg_refs: Vec<&std::ffi::OsStr> = args.iter().map(|a| a.as_ref()).collect();
+    run_ffmpeg("ffmpeg", &arg_refs).await?;
+
+    // Upload the whole package under one prefix so cleanup can drop it at once
+    let prefix = format!("stories/{}/hls/{}", user_id, story_id);
+    let mut pending = vec![out_dir.clone()];
+    let mut uploaded = Vec::new();
+    while let Some(dir) = pending.pop() {
+        let mut entries = fs::read_dir(&dir).await.map_err(|e| e.to_string())?;
+        while let Some(entry) = entries.next_entry().await.map_err(|e| e.to_string())? {
+            let path = entry.path();
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let relative = path.strip_prefix(&out_dir).map_err(|e| e.to_string())?;
+            let content_type = match path.extension().and_then(|e| e.to_str()) {
+                Some("m3u8") => "application/vnd.apple.mpegurl",
+                _ => "video/mp2t",
+            };
+            let key = format!("{}/{}", prefix, relative.to_string_lossy());
+            upload_output(state, &key, &path, content_type, Some(IMMUTABLE_CACHE_CONTROL)).await?;
+            uploaded.push(key);
+        }
+    }
+
+    let playback_url = hls_url(state, &format!("{}/master.m3u8", prefix));
+    let previous = sqlx::query_scalar::<_, Option<String>>(
+        r#"
+        UPDATE stories s SET playback_url = $2
+        FROM (SELECT playback_url AS old_playback_url FROM stories WHERE id = $1 FOR UPDATE) old
+        WHERE s.id = $1
+        RETURNING old.old_playback_url
+        "#
+    )
+    .bind(story_id)
+    .bind(&playback_url)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // Segments are cached as immutable, so a repackaged story must be purged
+    if previous.flatten().is_some() {
+        state.media_service.purge(&uploaded).await;
+    }
+
+    Ok(Some(serde_json::json!({
+        "playback_url": playback_url,
+        "renditions": renditions.len(),
+        "files": uploaded.len(),
+    })))
 }
@@ -0,0 +1,386 @@
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::client_ip::ClientIp;
+use crate::permissions::{CanManagePlatform, Permitted};
+use crate::AppState;
+
+// Waitlist (migration 074). While signup is invite-only (see invites.rs),
+// anyone can leave an email address and look up their place in line with it.
+// Admins release the longest-waiting addresses in batches: each release
+// creates an invite batch with one single-use code per address and, when mail
+// is configured, emails the codes with the waitlist_invite template.
+// Addresses that got an account some other way are dropped at the next
+// release.
+
+const MAX_EMAIL_LENGTH: usize = 255;
+const MAX_RELEASE: i64 = 1000;
+const DEFAULT_LIST_LIMIT: i64 = 100;
+const MAX_LIST_LIMIT: i64 = 1000;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct JoinWaitlistRequest {
+    pub email: String,
+    /// Turnstile or hCaptcha response, required when a CAPTCHA is configured
+    pub captcha_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PositionQuery {
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct WaitlistPosition {
+    /// 1 for the next address to be invited; null once invited
+    pub position: Option<i64>,
+    /// Addresses still waiting
+    pub waiting: i64,
+    #[serde(with = "crate::timestamps")]
+    pub joined_at: NaiveDateTime,
+    /// When the invite was sent
+    #[serde(with = "crate::timestamps::option")]
+    pub released_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct WaitlistListQuery {
+    /// waiting (default) or released
+    pub status: Option<String>,
+    /// Default 100, at most 1000
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct WaitlistEntry {
+    pub id: Uuid,
+    pub email: String,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps::option")]
+    pub released_at: Option<NaiveDateTime>,
+    pub invite_code: Option<String>,
+    /// Whether an account was created with the invite
+    pub signed_up: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WaitlistPage {
+    pub waiting: i64,
+    pub released: i64,
+    /// Released addresses that signed up
+    pub signed_up: i64,
+    pub entries: Vec<WaitlistEntry>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ReleaseRequest {
+    /// Addresses to invite, longest waiting first, 1 to 1000
+    pub count: i64,
+    /// Days until the codes stop working; never when omitted
+    pub expires_in_days: Option<i32>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct ReleasedInvite {
+    pub email: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReleaseResponse {
+    /// The invite batch holding the codes; none when nobody was waiting
+    pub batch_id: Option<Uuid>,
+    pub invites: Vec<ReleasedInvite>,
+    /// Whether the invites were queued for email; hand them out yourself otherwise
+    pub emailed: bool,
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    eprintln!("❌ Waitlist query failed: {:?}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+}
+
+const POSITION_QUERY: &str = r#"
+    SELECT
+        CASE WHEN w.released_at IS NULL THEN (
+            SELECT COUNT(*) FROM waitlist o
+            WHERE o.released_at IS NULL AND (o.created_at, o.id) <= (w.created_at, w.id)
+        ) END AS position,
+        (SELECT COUNT(*) FROM waitlist WHERE released_at IS NULL) AS waiting,
+        w.created_at AS joined_at,
+        w.released_at
+    FROM waitlist w
+    WHERE w.email = $1
+"#;
+
+// Join the waitlist while signup is invite-only. Joining again just returns your place.
+#[utoipa::path(
+    post,
+    path = "/api/v1/waitlist",
+    tag = "auth",
+    request_body = JoinWaitlistRequest,
+    responses(
+        (status = 200, body = WaitlistPosition),
+        (status = 400, description = "Invalid, disposable or blocked email, or failed CAPTCHA"),
+        (status = 409, description = "Signup is open, so there's no waitlist"),
+        (status = 429, description = "Too many signups from this network")
+    )
+)]
+pub async fn join_waitlist(
+    State(state): State<Arc<AppState>>,
+    ClientIp(ip): ClientIp,
+    Json(payload): Json<JoinWaitlistRequest>,
+) -> Result<Json<WaitlistPosition>, (StatusCode, String)> {
+    if !crate::invites::invite_only() {
+        return Err((StatusCode::CONFLICT, "Signup is open, so there's no waitlist".to_string()));
+    }
+    let email = payload.email.trim().to_lowercase();
+    if email.chars().count() > MAX_EMAIL_LENGTH {
+        return Err((StatusCode::BAD_REQUEST, "Invalid email address".to_string()));
+    }
+    crate::signup_guard::check(&state, ip, Some(&email), payload.captcha_token.as_deref()).await?;
+
+    sqlx::query("INSERT INTO waitlist (email) VALUES ($1) ON CONFLICT (email) DO NOTHING")
+        .bind(&email)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+
+    let position = sqlx::query_as::<_, WaitlistPosition>(POSITION_QUERY)
+        .bind(&email)
+        .fetch_one(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+    Ok(Json(position))
+}
+
+// Look up an address's place on the waitlist
+#[utoipa::path(
+    get,
+    path = "/api/v1/waitlist/position",
+    tag = "auth",
+    params(PositionQuery),
+    responses(
+        (status = 200, body = WaitlistPosition),
+        (status = 404, description = "The address isn't on the waitlist")
+    )
+)]
+pub async fn get_position(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<PositionQuery>,
+) -> Result<Json<WaitlistPosition>, (StatusCode, String)> {
+    let position = sqlx::query_as::<_, WaitlistPosition>(POSITION_QUERY)
+        .bind(params.email.trim().to_lowercase())
+        .fetch_optional(state.pool.as_ref())
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::NOT_FOUND, "This email isn't on the waitlist".to_string()))?;
+    Ok(Json(position))
+}
+
+// List waiting or released addresses
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/waitlist",
+    tag = "admin",
+    params(WaitlistListQuery),
+    responses(
+        (status = 200, body = WaitlistPage),
+        (status = 400, description = "Unknown status"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_waitlist(
+    State(state): State<Arc<AppState>>,
+    _admin: Permitted<CanManagePlatform>,
+    Query(params): Query<WaitlistListQuery>,
+) -> Result<Json<WaitlistPage>, (StatusCode, String)> {
+    let released = match params.status.as_deref().unwrap_or("waiting") {
+        "waiting" => false,
+        "released" => true,
+        _ => return Err((StatusCode::BAD_REQUEST, "status must be waiting or released".to_string())),
+    };
+    let limit = params.limit.unwrap_or(DEFAULT_LIST_LIMIT).clamp(1, MAX_LIST_LIMIT);
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    let (waiting, released_count, signed_up) = sqlx::query_as::<_, (i64, i64, i64)>(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE w.released_at IS NULL),
+            COUNT(*) FILTER (WHERE w.released_at IS NOT NULL),
+            COUNT(*) FILTER (WHERE EXISTS (SELECT 1 FROM invite_redemptions r WHERE r.code_id = w.invite_code_id))
+        FROM waitlist w
+        "#
+    )
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    // Waiting addresses in line order, released ones latest first
+    let entries = sqlx::query_as::<_, WaitlistEntry>(
+        r#"
+        SELECT
+            w.id,
+            w.email,
+            w.created_at,
+            w.released_at,
+            c.code AS invite_code,
+            EXISTS (SELECT 1 FROM invite_redemptions r WHERE r.code_id = w.invite_code_id) AS signed_up
+        FROM waitlist w
+        LEFT JOIN invite_codes c ON c.id = w.invite_code_id
+        WHERE (w.released_at IS NOT NULL) = $1
+        ORDER BY
+            CASE WHEN $1 THEN w.released_at END DESC,
+            w.created_at,
+            w.id
+        LIMIT $2 OFFSET $3
+        "#
+    )
+    .bind(released)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(WaitlistPage {
+        waiting,
+        released: released_count,
+        signed_up,
+        entries,
+    }))
+}
+
+// Invite the longest-waiting addresses, one single-use code each
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/waitlist/release",
+    tag = "admin",
+    request_body = ReleaseRequest,
+    responses(
+        (status = 200, body = ReleaseResponse),
+        (status = 400, description = "Count or expiry out of range"),
+        (status = 401, description = "Missing or invalid credentials")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn release_waitlist(
+    State(state): State<Arc<AppState>>,
+    admin: Permitted<CanManagePlatform>,
+    headers: HeaderMap,
+    Json(payload): Json<ReleaseRequest>,
+) -> Result<Json<ReleaseResponse>, (StatusCode, String)> {
+    if !(1..=MAX_RELEASE).contains(&payload.count) {
+        return Err((StatusCode::BAD_REQUEST, format!("count must be between 1 and {}", MAX_RELEASE)));
+    }
+    if payload.expires_in_days.is_some_and(|days| days < 1) {
+        return Err((StatusCode::BAD_REQUEST, "expires_in_days must be at least 1".to_string()));
+    }
+
+    let mut tx = state.pool.begin().await.map_err(db_error)?;
+
+    // Nobody needs an invite for an address that already has an account
+    sqlx::query(
+        r#"
+        DELETE FROM waitlist w
+        WHERE w.released_at IS NULL
+          AND EXISTS (SELECT 1 FROM users u WHERE LOWER(u.email) = w.email)
+        "#
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+
+    let entry_ids = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        SELECT id FROM waitlist
+        WHERE released_at IS NULL
+        ORDER BY created_at, id
+        LIMIT $1
+        FOR UPDATE SKIP LOCKED
+        "#
+    )
+    .bind(payload.count)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(db_error)?;
+    if entry_ids.is_empty() {
+        return Ok(Json(ReleaseResponse { batch_id: None, invites: Vec::new(), emailed: false }));
+    }
+
+    let label = format!("Waitlist release {}", chrono::Utc::now().format("%Y-%m-%d %H:%M"));
+    let batch_id = crate::invites::insert_batch(&mut tx, &label, admin.0.id, entry_ids.len() as i64, 1, payload.expires_in_days)
+        .await
+        .map_err(db_error)?;
+
+    // Pair the batch's codes with the addresses in line order
+    let invites = sqlx::query_as::<_, ReleasedInvite>(
+        r#"
+        WITH entries AS (
+            SELECT id, ord FROM unnest($1::uuid[]) WITH ORDINALITY AS e(id, ord)
+        ),
+        codes AS (
+            SELECT id, code, ROW_NUMBER() OVER (ORDER BY code) AS ord
+            FROM invite_codes
+            WHERE batch_id = $2
+        )
+        UPDATE waitlist w
+        SET released_at = NOW(), invite_code_id = codes.id
+        FROM entries
+        JOIN codes ON codes.ord = entries.ord
+        WHERE w.id = entries.id
+        RETURNING w.email, codes.code
+        "#
+    )
+    .bind(&entry_ids)
+    .bind(batch_id)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(db_error)?;
+
+    tx.commit().await.map_err(db_error)?;
+
+    let emailed = crate::mailer::is_configured();
+    if emailed {
+        let base = crate::syndication::public_base_url(&headers);
+        for invite in &invites {
+            let args = serde_json::json!({
+                "code": invite.code,
+                "link": format!("{}/signup?invite={}", base, invite.code),
+            });
+            if let Err(e) = crate::mailer::queue(&state.pool, std::slice::from_ref(&invite.email), "waitlist_invite", &args).await {
+                eprintln!("❌ Failed to email waitlist invite to {}: {}", invite.email, e);
+            }
+        }
+    }
+
+    crate::admin::log_admin_action(
+        &state,
+        admin.0.id,
+        "release_waitlist".to_string(),
+        None,
+        Some("invite_batch".to_string()),
+        Some(batch_id),
+        serde_json::json!({ "released": invites.len(), "expires_in_days": payload.expires_in_days }),
+    )
+    .await;
+
+    Ok(Json(ReleaseResponse {
+        batch_id: Some(batch_id),
+        invites,
+        emailed,
+    }))
+}
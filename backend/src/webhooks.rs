@@ -0,0 +1,815 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{NaiveDateTime, Utc};
+use hmac::{Hmac, Mac};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::PgPool;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::admin::AuthUser;
+use crate::permissions::{CanManagePlatform, Permitted};
+use crate::AppState;
+
+// Events a subscription can ask for. Each one is sent to the webhooks of the
+// user it concerns (the followed user, the story author, the message recipient).
+pub const EVENT_NEW_FOLLOWER: &str = "new_follower";
+pub const EVENT_STORY_CREATED: &str = "story_created";
+pub const EVENT_MESSAGE_RECEIVED: &str = "message_received";
+// Sent by the test endpoint only
+pub const EVENT_PING: &str = "ping";
+
+const EVENT_TYPES: &[&str] = &[EVENT_NEW_FOLLOWER, EVENT_STORY_CREATED, EVENT_MESSAGE_RECEIVED];
+
+const MAX_SUBSCRIPTIONS_PER_USER: i64 = 10;
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+// Only the start of the receiver's reply is kept in the delivery log
+const MAX_LOGGED_RESPONSE_BYTES: usize = 2048;
+// Delivery logs are kept this long
+const DELIVERY_RETENTION_DAYS: i32 = 30;
+
+const SUBSCRIPTION_COLUMNS: &str = "id, user_id, url, event_types, active, created_at, updated_at";
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct WebhookSubscription {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub active: bool,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps")]
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub subscription_id: Uuid,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    pub status: String,
+    pub attempts: i32,
+    pub response_status: Option<i32>,
+    pub response_body: Option<String>,
+    pub last_error: Option<String>,
+    #[serde(with = "crate::timestamps")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "crate::timestamps::option")]
+    pub delivered_at: Option<NaiveDateTime>,
+}
+
+#[derive(sqlx::FromRow)]
+struct DeliveryTarget {
+    event_type: String,
+    payload: serde_json::Value,
+    url: String,
+    secret: String,
+    active: bool,
+}
+
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            // A redirect could point the request at an internal address
+            .redirect(reqwest::redirect::Policy::none())
+            .dns_resolver(Arc::new(PublicResolver))
+            .build()
+            .expect("Failed to build webhook HTTP client")
+    })
+}
+
+fn envelope(event_type: &str, data: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "event": event_type,
+        "created_at": Utc::now().to_rfc3339(),
+        "data": data,
+    })
+}
+
+/// Queue `event_type` for every active subscription owned by one of `user_ids`
+/// that asked for it. Failures are logged and never fail the caller's request.
+pub async fn dispatch(pool: &PgPool, user_ids: &[Uuid], event_type: &str, data: serde_json::Value) {
+    let deliveries = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO webhook_deliveries (subscription_id, event_type, payload)
+        SELECT id, $2, $3
+        FROM webhook_subscriptions
+        WHERE user_id = ANY($1) AND active AND $2 = ANY(event_types)
+        RETURNING id
+        "#
+    )
+    .bind(user_ids)
+    .bind(event_type)
+    .bind(envelope(event_type, data))
+    .fetch_all(pool)
+    .await;
+
+    match deliveries {
+        Ok(deliveries) => {
+            for delivery_id in deliveries {
+                enqueue_delivery(pool, delivery_id).await;
+            }
+        }
+        Err(e) => tracing::error!("Failed to queue {} webhooks: {}", event_type, e),
+    }
+}
+
+async fn enqueue_delivery(pool: &PgPool, delivery_id: Uuid) {
+    let queued = crate::jobs::enqueue(
+        pool,
+        crate::jobs::DELIVER_WEBHOOK,
+        serde_json::json!({ "delivery_id": delivery_id }),
+        None,
+    )
+    .await;
+
+    if let Err(e) = queued {
+        tracing::error!("Failed to enqueue webhook delivery {}: {}", delivery_id, e);
+    }
+}
+
+/// Hex HMAC-SHA256 of "{timestamp}.{body}" keyed with the subscription secret.
+/// Receivers recompute it to check the payload came from us and wasn't replayed.
+fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// POST one delivery to its endpoint (run by the job worker). Errors make the
+/// job retry with backoff; `final_attempt` marks the delivery failed for good.
+pub async fn deliver(pool: &PgPool, payload: &serde_json::Value, final_attempt: bool) -> Result<Option<serde_json::Value>, String> {
+    let delivery_id = payload
+        .get("delivery_id")
+        .and_then(|id| id.as_str())
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .ok_or("Missing delivery_id")?;
+
+    let target = sqlx::query_as::<_, DeliveryTarget>(
+        r#"
+        SELECT d.event_type, d.payload, s.url, s.secret, s.active
+        FROM webhook_deliveries d
+        JOIN webhook_subscriptions s ON s.id = d.subscription_id
+        WHERE d.id = $1
+        "#
+    )
+    .bind(delivery_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // Subscription deleted or paused since the event was queued
+    let Some(target) = target.filter(|t| t.active) else {
+        return Ok(Some(serde_json::json!({ "skipped": true })));
+    };
+
+    let body = target.payload.to_string();
+    let timestamp = Utc::now().timestamp();
+
+    // Checked again on every delivery: the rules may have tightened since
+    // the subscription was saved
+    let result = match validate_url(&target.url).await {
+        Ok(()) => http_client()
+            .post(&target.url)
+            .header("content-type", "application/json")
+            .header("x-webhook-event", &target.event_type)
+            .header("x-webhook-delivery", delivery_id.to_string())
+            .header("x-webhook-timestamp", timestamp.to_string())
+            .header("x-webhook-signature", format!("sha256={}", sign(&target.secret, timestamp, &body)))
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {}", e)),
+        Err((_, e)) => Err(e),
+    };
+
+    let (response_status, response_body, error) = match result {
+        Ok(response) => {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            let text: String = text.chars().take(MAX_LOGGED_RESPONSE_BYTES).collect();
+            let error = (!status.is_success()).then(|| format!("Endpoint responded with {}", status));
+            (Some(status.as_u16() as i32), Some(text), error)
+        }
+        Err(e) => (None, None, Some(e)),
+    };
+
+    let status = match (&error, final_attempt) {
+        (None, _) => "succeeded",
+        (Some(_), true) => "failed",
+        (Some(_), false) => "retrying",
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE webhook_deliveries
+        SET status = $2,
+            attempts = attempts + 1,
+            response_status = $3,
+            response_body = $4,
+            last_error = $5,
+            delivered_at = CASE WHEN $2 = 'succeeded' THEN NOW() ELSE delivered_at END
+        WHERE id = $1
+        "#
+    )
+    .bind(delivery_id)
+    .bind(status)
+    .bind(response_status)
+    .bind(&response_body)
+    .bind(&error)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    match error {
+        None => Ok(Some(serde_json::json!({ "response_status": response_status }))),
+        Some(error) => Err(error),
+    }
+}
+
+/// Drop delivery logs past the retention window (run by the expiration sweep)
+pub async fn purge_old_deliveries(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM webhook_deliveries WHERE created_at < NOW() - make_interval(days => $1)")
+        .bind(DELIVERY_RETENTION_DAYS)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+fn allow_insecure() -> bool {
+    std::env::var("WEBHOOK_ALLOW_INSECURE").is_ok_and(|v| v == "true")
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || a == 0
+        // Carrier-grade NAT, 100.64.0.0/10
+        || (a == 100 && (64..128).contains(&b))
+        // IETF protocol assignments, 192.0.0.0/24
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking, 198.18.0.0/15
+        || (a == 198 && (b == 18 || b == 19))
+        // Reserved, 240.0.0.0/4
+        || a >= 240)
+}
+
+/// Whether an address is on the public internet. IPv6 forms that embed an
+/// IPv4 address are judged by the embedded address or refused outright.
+pub(crate) fn is_public_ip(ip: IpAddr) -> bool {
+    let ip = match ip {
+        IpAddr::V4(ip) => return is_public_ipv4(ip),
+        IpAddr::V6(ip) => ip,
+    };
+    if let Some(mapped) = ip.to_ipv4_mapped() {
+        return is_public_ipv4(mapped);
+    }
+    let segments = ip.segments();
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // IPv4-compatible, ::a.b.c.d
+        || segments[..6].iter().all(|s| *s == 0)
+        // Unique local, fc00::/7
+        || (segments[0] & 0xfe00) == 0xfc00
+        // Link-local, fe80::/10
+        || (segments[0] & 0xffc0) == 0xfe80
+        // NAT64 translation, 64:ff9b::/96 and 64:ff9b:1::/48
+        || (segments[0] == 0x64 && segments[1] == 0xff9b)
+        // Teredo 2001::/32 and documentation 2001:db8::/32
+        || (segments[0] == 0x2001 && (segments[1] == 0 || segments[1] == 0xdb8))
+        // 6to4, 2002::/16
+        || segments[0] == 0x2002)
+}
+
+// Addresses a host resolves to, keeping only the ones outbound requests
+// may connect to
+async fn resolve_public(host: &str) -> Result<Vec<SocketAddr>, String> {
+    let addrs = tokio::net::lookup_host((host, 0))
+        .await
+        .map_err(|e| format!("Couldn't resolve {}: {}", host, e))?;
+    let allowed: Vec<SocketAddr> = addrs.filter(|addr| allow_insecure() || is_public_ip(addr.ip())).collect();
+    if allowed.is_empty() {
+        return Err(format!("{} doesn't resolve to a public address", host));
+    }
+    Ok(allowed)
+}
+
+/// DNS resolver for clients that call user-supplied URLs (webhooks,
+/// federation). It only hands reqwest public addresses, and reqwest connects
+/// to what it was handed, so a name can't be re-resolved to an internal
+/// address after the check.
+pub(crate) struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: hyper::client::connect::dns::Name) -> reqwest::dns::Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs = resolve_public(&host).await?;
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+// Webhook URLs must be public http(s) endpoints; plain http and private
+// addresses are only allowed when WEBHOOK_ALLOW_INSECURE=true (local
+// development). Hostnames are resolved, and refused unless they have a public
+// address; requests go through PublicResolver so they connect to one of those.
+pub(crate) async fn validate_url(url: &str) -> Result<(), (StatusCode, String)> {
+    let invalid = |message: &str| (StatusCode::BAD_REQUEST, message.to_string());
+    let parsed = reqwest::Url::parse(url).map_err(|_| invalid("Invalid webhook URL"))?;
+
+    match parsed.scheme() {
+        "https" => {}
+        "http" if allow_insecure() => {}
+        _ => return Err(invalid("Webhook URLs must use https")),
+    }
+    if allow_insecure() {
+        return Ok(());
+    }
+
+    let host = parsed.host_str().ok_or_else(|| invalid("Webhook URL has no host"))?;
+    match host.trim_matches(|c| c == '[' || c == ']').parse::<IpAddr>() {
+        Ok(ip) if is_public_ip(ip) => Ok(()),
+        Ok(_) => Err(invalid("Webhook URLs must point to a public host")),
+        Err(_) => resolve_public(host).await.map(|_| ()).map_err(|e| (StatusCode::BAD_REQUEST, e)),
+    }
+}
+
+fn validate_event_types(event_types: &[String]) -> Result<(), (StatusCode, String)> {
+    if event_types.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Pick at least one event type".to_string()));
+    }
+    if let Some(unknown) = event_types.iter().find(|e| !EVENT_TYPES.contains(&e.as_str())) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Unknown event type {} (expected one of {})", unknown, EVENT_TYPES.join(", ")),
+        ));
+    }
+    Ok(())
+}
+
+fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    format!("whsec_{}", hex::encode(bytes))
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    eprintln!("Webhook query error: {:?}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+}
+
+async fn find_subscription(pool: &PgPool, user_id: Uuid, subscription_id: Uuid) -> Result<WebhookSubscription, (StatusCode, String)> {
+    sqlx::query_as::<_, WebhookSubscription>(&format!(
+        "SELECT {} FROM webhook_subscriptions WHERE id = $1 AND user_id = $2",
+        SUBSCRIPTION_COLUMNS
+    ))
+    .bind(subscription_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, "Webhook not found".to_string()))
+}
+
+// ============================================================================
+// HANDLERS
+// ============================================================================
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/webhooks",
+    tag = "webhooks",
+    responses((status = 200, body = [WebhookSubscription]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_webhooks(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<WebhookSubscription>>, (StatusCode, String)> {
+    let subscriptions = sqlx::query_as::<_, WebhookSubscription>(&format!(
+        "SELECT {} FROM webhook_subscriptions WHERE user_id = $1 ORDER BY created_at",
+        SUBSCRIPTION_COLUMNS
+    ))
+    .bind(user.id)
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(subscriptions))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateWebhookRequest {
+    url: String,
+    event_types: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CreatedWebhookResponse {
+    pub subscription: WebhookSubscription,
+    /// Signing secret; only returned here, so store it now
+    pub secret: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/webhooks",
+    tag = "webhooks",
+    request_body = CreateWebhookRequest,
+    responses((status = 200, body = CreatedWebhookResponse), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn create_webhook(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<CreateWebhookRequest>,
+) -> Result<Json<CreatedWebhookResponse>, (StatusCode, String)> {
+    validate_url(&req.url).await?;
+    validate_event_types(&req.event_types)?;
+
+    let existing = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM webhook_subscriptions WHERE user_id = $1")
+        .bind(user.id)
+        .fetch_one(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+    if existing >= MAX_SUBSCRIPTIONS_PER_USER {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("You can register at most {} webhooks", MAX_SUBSCRIPTIONS_PER_USER),
+        ));
+    }
+
+    Ok(Json(insert_subscription(&state.pool, user.id, &req.url, &req.event_types).await?))
+}
+
+/// Store a new subscription with a fresh signing secret. Callers validate the URL first.
+pub(crate) async fn insert_subscription(
+    pool: &PgPool,
+    user_id: Uuid,
+    url: &str,
+    event_types: &[String],
+) -> Result<CreatedWebhookResponse, (StatusCode, String)> {
+    let secret = generate_secret();
+    let subscription = sqlx::query_as::<_, WebhookSubscription>(&format!(
+        "INSERT INTO webhook_subscriptions (user_id, url, secret, event_types) VALUES ($1, $2, $3, $4) RETURNING {}",
+        SUBSCRIPTION_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(url)
+    .bind(&secret)
+    .bind(event_types)
+    .fetch_one(pool)
+    .await
+    .map_err(db_error)?;
+
+    Ok(CreatedWebhookResponse { subscription, secret })
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateWebhookRequest {
+    url: Option<String>,
+    event_types: Option<Vec<String>>,
+    active: Option<bool>,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/v1/webhooks/{webhook_id}",
+    tag = "webhooks",
+    params(("webhook_id" = Uuid, Path, description = "Webhook ID")),
+    request_body = UpdateWebhookRequest,
+    responses((status = 200, body = WebhookSubscription), (status = 404, description = "Webhook not found"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_webhook(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(webhook_id): Path<Uuid>,
+    Json(req): Json<UpdateWebhookRequest>,
+) -> Result<Json<WebhookSubscription>, (StatusCode, String)> {
+    if let Some(url) = &req.url {
+        validate_url(url).await?;
+    }
+    if let Some(event_types) = &req.event_types {
+        validate_event_types(event_types)?;
+    }
+
+    let subscription = sqlx::query_as::<_, WebhookSubscription>(&format!(
+        r#"
+        UPDATE webhook_subscriptions
+        SET url = COALESCE($3, url),
+            event_types = COALESCE($4, event_types),
+            active = COALESCE($5, active),
+            updated_at = NOW()
+        WHERE id = $1 AND user_id = $2
+        RETURNING {}
+        "#,
+        SUBSCRIPTION_COLUMNS
+    ))
+    .bind(webhook_id)
+    .bind(user.id)
+    .bind(&req.url)
+    .bind(&req.event_types)
+    .bind(req.active)
+    .fetch_optional(state.pool.as_ref())
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, "Webhook not found".to_string()))?;
+
+    Ok(Json(subscription))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/webhooks/{webhook_id}",
+    tag = "webhooks",
+    params(("webhook_id" = Uuid, Path, description = "Webhook ID")),
+    responses((status = 204, description = "Deleted"), (status = 404, description = "Webhook not found"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn delete_webhook(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(webhook_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let result = sqlx::query("DELETE FROM webhook_subscriptions WHERE id = $1 AND user_id = $2")
+        .bind(webhook_id)
+        .bind(user.id)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Webhook not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Send a ping event to check the endpoint and signature verification
+#[utoipa::path(
+    post,
+    path = "/api/v1/webhooks/{webhook_id}/test",
+    tag = "webhooks",
+    params(("webhook_id" = Uuid, Path, description = "Webhook ID")),
+    responses((status = 202, body = serde_json::Value), (status = 404, description = "Webhook not found"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn test_webhook(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(webhook_id): Path<Uuid>,
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, String)> {
+    let subscription = find_subscription(&state.pool, user.id, webhook_id).await?;
+
+    let delivery_id = sqlx::query_scalar::<_, Uuid>(
+        "INSERT INTO webhook_deliveries (subscription_id, event_type, payload) VALUES ($1, $2, $3) RETURNING id"
+    )
+    .bind(subscription.id)
+    .bind(EVENT_PING)
+    .bind(envelope(EVENT_PING, serde_json::json!({ "webhook_id": subscription.id })))
+    .fetch_one(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    enqueue_delivery(&state.pool, delivery_id).await;
+
+    Ok((StatusCode::ACCEPTED, Json(serde_json::json!({ "delivery_id": delivery_id }))))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeliveriesQuery {
+    /// Filter by status (pending, retrying, succeeded, failed)
+    status: Option<String>,
+    #[serde(default = "default_limit")]
+    limit: i64,
+    #[serde(default)]
+    offset: i64,
+}
+
+fn default_limit() -> i64 {
+    50
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/webhooks/{webhook_id}/deliveries",
+    tag = "webhooks",
+    params(("webhook_id" = Uuid, Path, description = "Webhook ID"), DeliveriesQuery),
+    responses((status = 200, body = [WebhookDelivery]), (status = 404, description = "Webhook not found"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_deliveries(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(webhook_id): Path<Uuid>,
+    Query(params): Query<DeliveriesQuery>,
+) -> Result<Json<Vec<WebhookDelivery>>, (StatusCode, String)> {
+    let subscription = find_subscription(&state.pool, user.id, webhook_id).await?;
+
+    let deliveries = sqlx::query_as::<_, WebhookDelivery>(
+        r#"
+        SELECT * FROM webhook_deliveries
+        WHERE subscription_id = $1 AND ($2::VARCHAR IS NULL OR status = $2)
+        ORDER BY created_at DESC
+        LIMIT $3 OFFSET $4
+        "#
+    )
+    .bind(subscription.id)
+    .bind(&params.status)
+    .bind(params.limit.clamp(1, 200))
+    .bind(params.offset.max(0))
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(deliveries))
+}
+
+// Queue a past delivery again (e.g. after fixing the receiving endpoint)
+#[utoipa::path(
+    post,
+    path = "/api/v1/webhooks/{webhook_id}/deliveries/{delivery_id}/redeliver",
+    tag = "webhooks",
+    params(("webhook_id" = Uuid, Path, description = "Webhook ID"), ("delivery_id" = Uuid, Path, description = "Delivery ID")),
+    responses((status = 202, body = serde_json::Value), (status = 404, description = "Delivery not found"), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn redeliver(
+    user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path((webhook_id, delivery_id)): Path<(Uuid, Uuid)>,
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, String)> {
+    let subscription = find_subscription(&state.pool, user.id, webhook_id).await?;
+
+    let result = sqlx::query("UPDATE webhook_deliveries SET status = 'pending' WHERE id = $1 AND subscription_id = $2")
+        .bind(delivery_id)
+        .bind(subscription.id)
+        .execute(state.pool.as_ref())
+        .await
+        .map_err(db_error)?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Delivery not found".to_string()));
+    }
+
+    enqueue_delivery(&state.pool, delivery_id).await;
+
+    Ok((StatusCode::ACCEPTED, Json(serde_json::json!({ "delivery_id": delivery_id }))))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AdminDeliveriesQuery {
+    /// Filter by status (pending, retrying, succeeded, failed)
+    status: Option<String>,
+    /// Only deliveries for this user's webhooks
+    user_id: Option<Uuid>,
+    #[serde(default = "default_limit")]
+    limit: i64,
+    #[serde(default)]
+    offset: i64,
+}
+
+// Delivery log across all users, newest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/webhooks/deliveries",
+    tag = "webhooks",
+    params(AdminDeliveriesQuery),
+    responses((status = 200, body = [WebhookDelivery]), (status = 401, description = "Missing or invalid credentials")),
+    security(("bearer_auth" = []))
+)]
+pub async fn admin_list_deliveries(
+    _admin: Permitted<CanManagePlatform>,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<AdminDeliveriesQuery>,
+) -> Result<Json<Vec<WebhookDelivery>>, (StatusCode, String)> {
+    let deliveries = sqlx::query_as::<_, WebhookDelivery>(
+        r#"
+        SELECT d.*
+        FROM webhook_deliveries d
+        JOIN webhook_subscriptions s ON s.id = d.subscription_id
+        WHERE ($1::VARCHAR IS NULL OR d.status = $1)
+          AND ($2::UUID IS NULL OR s.user_id = $2)
+        ORDER BY d.created_at DESC
+        LIMIT $3 OFFSET $4
+        "#
+    )
+    .bind(&params.status)
+    .bind(params.user_id)
+    .bind(params.limit.clamp(1, 200))
+    .bind(params.offset.max(0))
+    .fetch_all(state.pool.as_ref())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(deliveries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn public(ip: &str) -> bool {
+        is_public_ip(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn allows_public_addresses() {
+        for ip in ["8.8.8.8", "1.1.1.1", "100.63.255.255", "100.128.0.1", "198.20.0.1", "2606:4700:4700::1111", "2a00:1450::1"] {
+            assert!(public(ip), "{} should be public", ip);
+        }
+    }
+
+    #[test]
+    fn refuses_internal_ipv4() {
+        for ip in [
+            "10.0.0.1",
+            "172.16.0.1",
+            "192.168.1.1",
+            "127.0.0.1",
+            "169.254.169.254",
+            "0.0.0.0",
+            "0.1.2.3",
+            "255.255.255.255",
+            "224.0.0.1",
+            "192.0.2.1",
+            "192.0.0.8",
+            "198.18.0.1",
+            "198.19.255.255",
+            "240.0.0.1",
+        ] {
+            assert!(!public(ip), "{} should be refused", ip);
+        }
+    }
+
+    #[test]
+    fn refuses_carrier_grade_nat() {
+        assert!(!public("100.64.0.1"));
+        assert!(!public("100.100.100.200"));
+        assert!(!public("100.127.255.255"));
+    }
+
+    #[test]
+    fn judges_mapped_ipv6_by_the_embedded_address() {
+        assert!(!public("::ffff:127.0.0.1"));
+        assert!(!public("::ffff:10.0.0.1"));
+        assert!(!public("::ffff:169.254.169.254"));
+        assert!(!public("::ffff:100.64.0.1"));
+        assert!(public("::ffff:8.8.8.8"));
+    }
+
+    #[test]
+    fn refuses_ipv4_compatible_ipv6() {
+        assert!(!public("::127.0.0.1"));
+        assert!(!public("::8.8.8.8"));
+        assert!(!public("::"));
+        assert!(!public("::1"));
+    }
+
+    #[test]
+    fn refuses_ipv6_forms_that_tunnel_to_ipv4() {
+        // NAT64, well-known and local-use prefixes
+        assert!(!public("64:ff9b::7f00:1"));
+        assert!(!public("64:ff9b::808:808"));
+        assert!(!public("64:ff9b:1::a00:1"));
+        // 6to4
+        assert!(!public("2002:7f00:1::1"));
+        assert!(!public("2002:808:808::1"));
+        // Teredo
+        assert!(!public("2001:0:4136:e378:8000:63bf:3fff:fdd2"));
+        assert!(!public("2001::1"));
+    }
+
+    #[test]
+    fn refuses_internal_ipv6() {
+        for ip in ["fc00::1", "fd12:3456::1", "fe80::1", "febf::1", "ff02::1", "2001:db8::1"] {
+            assert!(!public(ip), "{} should be refused", ip);
+        }
+        // Only 2001:0::/32 is Teredo; the rest of 2001::/16 is ordinary space
+        assert!(public("2001:4860:4860::8888"));
+    }
+}
@@ -1,33 +1,82 @@
 use axum::{
     extract::{
-        ws::{Message, WebSocket, WebSocketUpgrade},
-        State, Path,
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        Query, State, Path,
     },
-    response::IntoResponse,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
 };
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use futures::{sink::SinkExt, stream::StreamExt};
 use std::sync::Arc;
 use dashmap::DashMap;
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, mpsc};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::time::Duration;
 
+use crate::runtime_settings::{self, WS_BUFFER_SIZE, WS_DISCONNECT_ON_LAG, WS_FRAMES_PER_MINUTE};
 use crate::AppState;
 
 // Global map to track active WebSocket connections
 pub type Connections = Arc<DashMap<Uuid, broadcast::Sender<String>>>;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+// A single socket's own queue, for replies only the socket that sent a frame
+// should see (Error NACKs), unlike events sent to all of a user's sockets
+pub type Outbox = mpsc::Sender<String>;
+
+// Server pings every HEARTBEAT_INTERVAL; a client silent for IDLE_TIMEOUT is dropped
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+const IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+const REAP_INTERVAL: Duration = Duration::from_secs(60);
+
+// A user's sockets share one broadcast channel of websocket.buffer_size
+// events. A socket that can't keep up misses the oldest ones; it is then sent
+// a Resync so the client refetches what it missed, or closed with
+// LAG_CLOSE_CODE when websocket.disconnect_on_lag is set, and the lag is
+// counted towards the admin live stats.
+const LAG_CLOSE_CODE: u16 = 1013; // Try Again Later
+static LAGGED_SOCKETS: AtomicU64 = AtomicU64::new(0);
+static SKIPPED_EVENTS: AtomicU64 = AtomicU64::new(0);
+static LAG_DISCONNECTS: AtomicU64 = AtomicU64::new(0);
+
+/// Lag counters for this server since it started
+pub struct LagCounts {
+    pub lagged_sockets: u64,
+    pub skipped_events: u64,
+    pub disconnects: u64,
+}
+
+pub fn lag_counts() -> LagCounts {
+    LagCounts {
+        lagged_sockets: LAGGED_SOCKETS.load(Ordering::Relaxed),
+        skipped_events: SKIPPED_EVENTS.load(Ordering::Relaxed),
+        disconnects: LAG_DISCONNECTS.load(Ordering::Relaxed),
+    }
+}
+
+// Bumped whenever WsMessage changes in a way existing clients can't handle
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Every frame on /ws/{user_id} is one of these, as JSON tagged by `type`
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum WsMessage {
     // Client -> Server
     SendMessage {
         chat_room_id: Uuid,
         content: Option<String>,
+        /// text, image, video, gif or sticker
         message_type: String,
         media_url: Option<String>,
         view_once: bool,
         expires_in_seconds: Option<i64>,
+        /// Client generated id, echoed in the sender's NewMessage. Resending the
+        /// same id within 24h doesn't create a duplicate.
+        #[serde(default)]
+        client_msg_id: Option<String>,
     },
     TypingStart {
         chat_room_id: Uuid,
@@ -41,8 +90,63 @@ pub enum WsMessage {
     MarkViewed {
         message_id: Uuid,
     },
+    /// Take back your own message within 30 seconds of sending it
+    UnsendMessage {
+        message_id: Uuid,
+    },
+    /// Present a viewer join token from POST /live/{session_id}/join
+    LiveJoin {
+        session_id: Uuid,
+        join_token: String,
+    },
+
+    /// Call the other member of a 1:1 chat
+    CallOffer {
+        chat_room_id: Uuid,
+        /// audio or video
+        media: String,
+        sdp: String,
+    },
+    CallAnswer {
+        call_id: Uuid,
+        sdp: String,
+    },
+    /// Hang up, cancel a call still ringing, or decline an incoming one
+    CallHangup {
+        call_id: Uuid,
+    },
+    /// Both directions: relayed to the other party of the call
+    CallIce {
+        call_id: Uuid,
+        candidate: String,
+        sdp_mid: Option<String>,
+        sdp_mline_index: Option<u32>,
+    },
+
+    // Both directions: relayed between a live host and one of their viewers.
+    // peer_id is the recipient when sent, and the sender when received.
+    LiveSdp {
+        session_id: Uuid,
+        peer_id: Uuid,
+        /// offer or answer
+        sdp_type: String,
+        sdp: String,
+    },
+    LiveIce {
+        session_id: Uuid,
+        peer_id: Uuid,
+        candidate: String,
+        sdp_mid: Option<String>,
+        sdp_mline_index: Option<u32>,
+    },
 
     // Server -> Client
+    /// First frame after connecting
+    Hello {
+        protocol_version: u32,
+        heartbeat_interval_seconds: u64,
+        server_time: String,
+    },
     NewMessage {
         id: Uuid,
         chat_room_id: Uuid,
@@ -53,7 +157,14 @@ pub enum WsMessage {
         media_url: Option<String>,
         media_thumbnail_url: Option<String>,
         view_once: bool,
+        expires_at: Option<String>,
         created_at: String,
+        /// Only present on the sender's copy, matching its SendMessage
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        client_msg_id: Option<String>,
+        /// The recipient's muted word the message contains; show it folded
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        muted_word: Option<String>,
     },
     UserTyping {
         chat_room_id: Uuid,
@@ -64,6 +175,12 @@ pub enum WsMessage {
         chat_room_id: Uuid,
         user_id: Uuid,
     },
+    /// One of your messages reached a device of `user_id`
+    MessageDelivered {
+        message_id: Uuid,
+        user_id: Uuid,
+        delivered_at: String,
+    },
     MessageRead {
         message_id: Uuid,
         user_id: Uuid,
@@ -77,44 +194,294 @@ pub enum WsMessage {
     MessageExpired {
         message_id: Uuid,
     },
+    /// The sender unsent the message, or a community moderator removed it
+    /// (unsent_by is then the moderator); it stays in the chat as an 'unsent'
+    /// placeholder without content or media
+    MessageDeleted {
+        message_id: Uuid,
+        chat_room_id: Uuid,
+        unsent_by: Uuid,
+    },
+    /// A member accepted the message request for this chat
+    MessageRequestAccepted {
+        chat_room_id: Uuid,
+        user_id: Uuid,
+    },
+    /// The group's owner changed how long the room keeps messages; null
+    /// keeps them until they're deleted
+    RoomRetentionChanged {
+        chat_room_id: Uuid,
+        retention_seconds: Option<i32>,
+        changed_by: Uuid,
+    },
+    /// A subscriber reacted to a channel post, or took their reaction back
+    /// (emoji is null). Only sent to the channel owner.
+    MessageReaction {
+        message_id: Uuid,
+        chat_room_id: Uuid,
+        user_id: Uuid,
+        emoji: Option<String>,
+    },
+    /// Sent to the caller once the call is placed
+    CallRinging {
+        call_id: Uuid,
+        chat_room_id: Uuid,
+        callee_id: Uuid,
+    },
+    CallIncoming {
+        call_id: Uuid,
+        chat_room_id: Uuid,
+        caller_id: Uuid,
+        caller_username: String,
+        media: String,
+        sdp: String,
+    },
+    CallAnswered {
+        call_id: Uuid,
+        sdp: String,
+    },
+    /// Sent to both parties; status is ended, declined or missed
+    CallEnded {
+        call_id: Uuid,
+        status: String,
+        duration_seconds: Option<i32>,
+    },
+    /// Someone the user follows went live
+    LiveStarted {
+        session_id: Uuid,
+        host_id: Uuid,
+        host_username: String,
+        title: Option<String>,
+    },
+    /// Sent to the host; start the WebRTC offer to this viewer
+    LiveViewerJoined {
+        session_id: Uuid,
+        user_id: Uuid,
+        username: String,
+    },
+    LiveViewerLeft {
+        session_id: Uuid,
+        user_id: Uuid,
+    },
+    LiveChatMessage {
+        id: Uuid,
+        session_id: Uuid,
+        user_id: Uuid,
+        username: String,
+        content: String,
+        created_at: String,
+    },
+    /// The host stopped streaming
+    LiveEnded {
+        session_id: Uuid,
+    },
+    TimeToExpire {
+        message_id: Uuid,
+        chat_room_id: Uuid,
+        expires_at: String,
+        seconds_remaining: i64,
+    },
+    /// System announcement delivered to this user
+    Announcement {
+        announcement_id: Uuid,
+        notification_id: Uuid,
+        title: String,
+        body: String,
+        link_url: Option<String>,
+        expires_at: Option<String>,
+    },
+    /// A notification was just written for this user; unread_count is their
+    /// new total, for the badge
+    NotificationCreated {
+        notification_id: Uuid,
+        notification_type: String,
+        /// social, system or ads
+        category: String,
+        from_user_id: Option<Uuid>,
+        from_username: Option<String>,
+        from_avatar_url: Option<String>,
+        story_id: Option<Uuid>,
+        comment_id: Option<Uuid>,
+        message: Option<String>,
+        created_at: String,
+        unread_count: i64,
+    },
+    /// This socket fell behind and `skipped` events were dropped. Refetch
+    /// chats, unread messages and notifications rather than relying on the
+    /// events received so far.
+    Resync {
+        skipped: u64,
+    },
+    /// A frame was refused or failed. request_id is the one the frame
+    /// carried, if any; the user's other sockets get the Error too.
     Error {
+        code: ErrorCode,
         message: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        request_id: Option<String>,
     },
 }
 
+/// Why a client frame was refused
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// Not allowed to do this: not your message, an invalid join token, a
+    /// channel only its owner posts in, a declined message request
+    Unauthorized,
+    /// Not a member of the chat, call or live session the frame is about
+    NotAMember,
+    /// Over websocket.frames_per_minute; the frame was dropped
+    RateLimited,
+    /// The frame couldn't be parsed or one of its fields is invalid
+    PayloadInvalid,
+    /// The call or message it refers to is gone
+    NotFound,
+    /// Can't be done right now, e.g. the other party is on another call
+    Conflict,
+    /// The server failed; retrying may work
+    Internal,
+}
+
+// A client frame's optional correlation id, next to its `type`
+#[derive(Deserialize)]
+struct FrameMeta {
+    request_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct WsConnectQuery {
+    protocol_version: Option<u32>,
+}
+
 pub async fn ws_handler(
     ws: WebSocketUpgrade,
     Path(user_id): Path<Uuid>,
+    Query(query): Query<WsConnectQuery>,
     State(state): State<Arc<AppState>>,
-) -> impl IntoResponse {
+) -> Response {
+    // Clients that state a version must speak ours; older clients that don't send one are let through
+    if let Some(requested) = query.protocol_version {
+        if requested != PROTOCOL_VERSION {
+            return (
+                StatusCode::BAD_REQUEST,
+                format!("Unsupported protocol version {} (server speaks {})", requested, PROTOCOL_VERSION),
+            )
+                .into_response();
+        }
+    }
+
     ws.on_upgrade(move |socket| handle_socket(socket, user_id, state))
 }
 
+// Machine-readable description of the WebSocket protocol
+pub async fn ws_schema() -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "protocol_version": PROTOCOL_VERSION,
+        "endpoint": "/ws/{user_id}?protocol_version={protocol_version}",
+        "heartbeat_interval_seconds": HEARTBEAT_INTERVAL.as_secs(),
+        "idle_timeout_seconds": IDLE_TIMEOUT.as_secs(),
+        "lag_close_code": LAG_CLOSE_CODE,
+        "request_id": "Any client frame may carry a request_id string; an Error it causes echoes it and goes only to the socket that sent the frame",
+        "message_schema": schemars::schema_for!(WsMessage),
+    }))
+}
+
 async fn handle_socket(socket: WebSocket, user_id: Uuid, state: Arc<AppState>) {
     let (mut sender, mut receiver) = socket.split();
 
+    // Handshake: tell the client which protocol and heartbeat settings apply
+    let hello = WsMessage::Hello {
+        protocol_version: PROTOCOL_VERSION,
+        heartbeat_interval_seconds: HEARTBEAT_INTERVAL.as_secs(),
+        server_time: crate::timestamps::format(chrono::Utc::now().naive_utc()),
+    };
+    if sender.send(Message::Text(serde_json::to_string(&hello).unwrap())).await.is_err() {
+        return;
+    }
+
     // Only create a new broadcast channel if one does not exist
     // Always ensure a broadcast channel exists for the user
     let tx = state.connections.entry(user_id).or_insert_with(|| {
-        let (tx, _) = broadcast::channel(100);
+        let (tx, _) = broadcast::channel(runtime_settings::int(WS_BUFFER_SIZE) as usize);
         tx
     }).clone();
     let mut rx = tx.subscribe();
+    let (outbox, mut replies) = mpsc::channel::<String>(runtime_settings::int(WS_BUFFER_SIZE) as usize);
 
     tracing::info!("WebSocket connected: {}", user_id);
+    let connected_at = chrono::Utc::now().timestamp();
 
     // Set user online in Redis
     {
         let mut redis = state.redis.lock().await;
         let _ = redis.set_user_online(user_id).await;
     }
+    crate::activity_status::touch(&state.pool, user_id).await;
+
+    // Last time we heard anything from the client (text, ping or pong), in unix seconds
+    let last_activity = Arc::new(AtomicI64::new(chrono::Utc::now().timestamp()));
 
-    // Spawn a task to forward broadcast messages to WebSocket
+    // Spawn a task to forward broadcast messages to WebSocket and drive heartbeats
+    let send_activity = last_activity.clone();
+    let send_redis = state.redis.clone();
     let mut send_task = tokio::spawn(async move {
-        while let Ok(msg) = rx.recv().await {
-            if let Err(e) = sender.send(Message::Text(msg)).await {
-                tracing::warn!("WebSocket send error for user {}: {:?}", user_id, e);
-                break;
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+        heartbeat.tick().await; // first tick fires immediately
+
+        loop {
+            tokio::select! {
+                Some(reply) = replies.recv() => {
+                    if sender.send(Message::Text(reply)).await.is_err() {
+                        break;
+                    }
+                }
+                msg = rx.recv() => {
+                    let msg = match msg {
+                        Ok(msg) => msg,
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            tracing::warn!("WebSocket for user {} lagged, skipped {} messages", user_id, skipped);
+                            LAGGED_SOCKETS.fetch_add(1, Ordering::Relaxed);
+                            SKIPPED_EVENTS.fetch_add(skipped, Ordering::Relaxed);
+                            let resync = serde_json::to_string(&WsMessage::Resync { skipped }).unwrap();
+                            if sender.send(Message::Text(resync)).await.is_err() {
+                                break;
+                            }
+                            if runtime_settings::int(WS_DISCONNECT_ON_LAG) == 1 {
+                                LAG_DISCONNECTS.fetch_add(1, Ordering::Relaxed);
+                                let _ = sender
+                                    .send(Message::Close(Some(CloseFrame {
+                                        code: LAG_CLOSE_CODE,
+                                        reason: "Fell behind; reconnect and resync".into(),
+                                    })))
+                                    .await;
+                                break;
+                            }
+                            continue;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    };
+                    if let Err(e) = sender.send(Message::Text(msg)).await {
+                        tracing::warn!("WebSocket send error for user {}: {:?}", user_id, e);
+                        break;
+                    }
+                }
+                _ = heartbeat.tick() => {
+                    let idle = chrono::Utc::now().timestamp() - send_activity.load(Ordering::Relaxed);
+                    if idle > IDLE_TIMEOUT.as_secs() as i64 {
+                        tracing::info!("WebSocket idle timeout for user {} ({}s)", user_id, idle);
+                        let _ = sender.send(Message::Close(None)).await;
+                        break;
+                    }
+
+                    if sender.send(Message::Ping(Vec::new())).await.is_err() {
+                        break;
+                    }
+
+                    // Presence only stays alive while heartbeats keep succeeding
+                    let mut redis = send_redis.lock().await;
+                    let _ = redis.set_user_online(user_id).await;
+                }
             }
         }
     });
@@ -123,47 +490,232 @@ async fn handle_socket(socket: WebSocket, user_id: Uuid, state: Arc<AppState>) {
     let connections = state.connections.clone();
     let pool = state.pool.clone();
     let redis = state.redis.clone();
+    let media = state.media_service.clone();
+    let recv_activity = last_activity.clone();
 
     let mut recv_task = tokio::spawn(async move {
-        while let Some(Ok(Message::Text(text))) = receiver.next().await {
-            match serde_json::from_str::<WsMessage>(&text) {
-                Ok(ws_msg) => {
-                    handle_ws_message(ws_msg, user_id, &pool, &redis, &connections).await;
-                }
-                Err(e) => {
-                    tracing::error!("Failed to parse WsMessage: {}", e);
+        // Fixed one-minute windows for websocket.frames_per_minute
+        let mut window_started = std::time::Instant::now();
+        let mut frames_in_window = 0i64;
+
+        while let Some(Ok(frame)) = receiver.next().await {
+            recv_activity.store(chrono::Utc::now().timestamp(), Ordering::Relaxed);
+
+            match frame {
+                Message::Text(text) => {
+                    let request_id = serde_json::from_str::<FrameMeta>(&text).ok().and_then(|meta| meta.request_id);
+
+                    if window_started.elapsed() >= Duration::from_secs(60) {
+                        window_started = std::time::Instant::now();
+                        frames_in_window = 0;
+                    }
+                    frames_in_window += 1;
+                    let limit = runtime_settings::int(WS_FRAMES_PER_MINUTE);
+                    if limit > 0 && frames_in_window > limit {
+                        nack(&outbox, request_id.as_deref(), ErrorCode::RateLimited, "Too many frames; slow down");
+                        continue;
+                    }
+
+                    match serde_json::from_str::<WsMessage>(&text) {
+                        Ok(ws_msg) => {
+                            handle_ws_message(ws_msg, request_id.as_deref(), &outbox, user_id, &pool, &redis, &connections, &media).await;
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to parse WsMessage: {}", e);
+                            nack(&outbox, request_id.as_deref(), ErrorCode::PayloadInvalid, format!("Invalid frame: {}", e));
+                        }
+                    }
                 }
+                Message::Close(_) => break,
+                // Pings are answered automatically; pongs only refresh activity
+                _ => {}
             }
         }
     });
 
-    // Wait for either task to finish
+    // Wait for either task to finish, then make sure the other one is gone
+    // so its broadcast receiver is dropped before we check for remaining tabs
     tokio::select! {
         _ = (&mut send_task) => {
             tracing::info!("Send task ended for user {}", user_id);
-            recv_task.abort()
+            recv_task.abort();
+            let _ = recv_task.await;
         },
         _ = (&mut recv_task) => {
             tracing::info!("Recv task ended for user {}", user_id);
-            send_task.abort()
+            send_task.abort();
+            let _ = send_task.await;
         },
     };
 
-    // Clean up connection
-    state.connections.remove(&user_id);
+    // Clean up connection unless another tab/device is still subscribed
+    drop(tx);
+    let removed = state
+        .connections
+        .remove_if(&user_id, |_, tx| tx.receiver_count() == 0)
+        .is_some();
     tracing::info!("WebSocket disconnected: {}", user_id);
-    {
+    // Count time up to the last thing the client did, not the idle timeout
+    let active_seconds = last_activity.load(Ordering::Relaxed) - connected_at;
+    crate::supervision::record_session(&state.pool, user_id, active_seconds).await;
+    if removed {
+        crate::activity_status::touch(&state.pool, user_id).await;
+        crate::live::disconnected(&state.pool, &state.connections, user_id).await;
+        crate::calls::disconnected(&state.pool, &state.connections, user_id).await;
         let mut redis = state.redis.lock().await;
         let _ = redis.set_user_offline(user_id).await;
     }
 }
 
+/// Periodically drop connection entries that no longer have any live socket
+/// behind them (e.g. tasks that died without running their cleanup)
+pub async fn reap_stale_connections(connections: Connections, redis: Arc<tokio::sync::Mutex<crate::redis_client::RedisClient>>) {
+    let mut ticker = tokio::time::interval(REAP_INTERVAL);
+
+    loop {
+        ticker.tick().await;
+
+        let stale: Vec<Uuid> = connections
+            .iter()
+            .filter(|entry| entry.value().receiver_count() == 0)
+            .map(|entry| *entry.key())
+            .collect();
+
+        for user_id in stale {
+            if connections.remove_if(&user_id, |_, tx| tx.receiver_count() == 0).is_some() {
+                let mut redis = redis.lock().await;
+                let _ = redis.set_user_offline(user_id).await;
+                tracing::info!("Reaped stale WebSocket entry for user {}", user_id);
+            }
+        }
+    }
+}
+
+/// Send an event to a single user's sockets, if they are connected
+pub fn send_to_user(connections: &Connections, user_id: Uuid, msg: &WsMessage) {
+    if let Some(conn) = connections.get(&user_id) {
+        let _ = conn.send(serde_json::to_string(msg).unwrap());
+    }
+}
+
+/// Refuse a client frame with an Error carrying `code` and the frame's
+/// request_id, sent only to the socket the frame came in on
+pub fn nack(outbox: &Outbox, request_id: Option<&str>, code: ErrorCode, message: impl Into<String>) {
+    let error = WsMessage::Error {
+        code,
+        message: message.into(),
+        request_id: request_id.map(str::to_string),
+    };
+    // A client that doesn't read its errors loses them rather than stalling
+    let _ = outbox.try_send(serde_json::to_string(&error).unwrap());
+}
+
+// The ErrorCode for a refusal from an HTTP-shaped check
+fn status_code(status: StatusCode) -> ErrorCode {
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ErrorCode::Unauthorized,
+        StatusCode::NOT_FOUND => ErrorCode::NotFound,
+        StatusCode::CONFLICT => ErrorCode::Conflict,
+        StatusCode::TOO_MANY_REQUESTS => ErrorCode::RateLimited,
+        s if s.is_client_error() => ErrorCode::PayloadInvalid,
+        _ => ErrorCode::Internal,
+    }
+}
+
+// Whether the user is in the chat a frame is about; NACKs the frame if not
+async fn check_member(
+    pool: &sqlx::PgPool,
+    redis: &tokio::sync::Mutex<crate::redis_client::RedisClient>,
+    outbox: &Outbox,
+    request_id: Option<&str>,
+    user_id: Uuid,
+    chat_room_id: Uuid,
+) -> bool {
+    let key = crate::cache::membership_key(chat_room_id, user_id);
+    if crate::cache::get::<bool>(redis, &key).await == Some(true) {
+        return true;
+    }
+
+    let member = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM chat_members WHERE chat_room_id = $1 AND user_id = $2)")
+        .bind(chat_room_id)
+        .bind(user_id)
+        .fetch_one(pool)
+        .await;
+    match member {
+        Ok(true) => {
+            crate::cache::set(redis, &key, &true, crate::cache::MEMBERSHIP_TTL_SECS).await;
+            true
+        }
+        Ok(false) => {
+            nack(outbox, request_id, ErrorCode::NotAMember, "You're not a member of this chat");
+            false
+        }
+        Err(e) => {
+            tracing::error!("Failed to check membership of room {}: {}", chat_room_id, e);
+            nack(outbox, request_id, ErrorCode::Internal, "Couldn't check chat membership");
+            false
+        }
+    }
+}
+
+// check_member for the chat a message was sent in
+async fn check_message_member(
+    pool: &sqlx::PgPool,
+    redis: &tokio::sync::Mutex<crate::redis_client::RedisClient>,
+    outbox: &Outbox,
+    request_id: Option<&str>,
+    user_id: Uuid,
+    message_id: Uuid,
+) -> bool {
+    let chat_room_id = sqlx::query_scalar::<_, Uuid>("SELECT chat_room_id FROM messages WHERE id = $1")
+        .bind(message_id)
+        .fetch_optional(pool)
+        .await;
+    match chat_room_id {
+        Ok(Some(chat_room_id)) => check_member(pool, redis, outbox, request_id, user_id, chat_room_id).await,
+        Ok(None) => {
+            nack(outbox, request_id, ErrorCode::NotFound, "Message not found");
+            false
+        }
+        Err(e) => {
+            tracing::error!("Failed to look up message {}: {}", message_id, e);
+            nack(outbox, request_id, ErrorCode::Internal, "Couldn't look up the message");
+            false
+        }
+    }
+}
+
+/// Send an event to every connected member of a chat room
+pub async fn broadcast_to_room(pool: &sqlx::PgPool, connections: &Connections, chat_room_id: Uuid, msg: &WsMessage) {
+    let members = sqlx::query_scalar::<_, Uuid>("SELECT user_id FROM chat_members WHERE chat_room_id = $1")
+        .bind(chat_room_id)
+        .fetch_all(pool)
+        .await;
+
+    match members {
+        Ok(members) => {
+            let msg_json = serde_json::to_string(msg).unwrap();
+            for member_id in members {
+                if let Some(conn) = connections.get(&member_id) {
+                    let _ = conn.send(msg_json.clone());
+                }
+            }
+        }
+        Err(e) => tracing::error!("Failed to fetch chat members for room {}: {}", chat_room_id, e),
+    }
+}
+
+// Handle a parsed client frame; errors go back to `outbox`, the sending socket
+#[allow(clippy::too_many_arguments)]
 async fn handle_ws_message(
     msg: WsMessage,
+    request_id: Option<&str>,
+    outbox: &Outbox,
     user_id: Uuid,
     pool: &Arc<sqlx::PgPool>,
     redis: &Arc<tokio::sync::Mutex<crate::redis_client::RedisClient>>,
     connections: &Connections,
+    media: &crate::media::MediaService,
 ) {
     match msg {
         WsMessage::SendMessage {
@@ -173,80 +725,93 @@ async fn handle_ws_message(
             media_url,
             view_once,
             expires_in_seconds,
+            client_msg_id,
         } => {
+            use crate::idempotency::{self, Reservation, SCOPE_SEND_MESSAGE};
+
             // Calculate expiration
-            let expires_at = expires_in_seconds.map(|seconds| {
-                (chrono::Utc::now() + chrono::Duration::seconds(seconds)).naive_utc()
-            });
+            let expires_at = match crate::expiration::message_expires_at(expires_in_seconds) {
+                Ok(expires_at) => expires_at,
+                Err(message) => {
+                    nack(outbox, request_id, ErrorCode::PayloadInvalid, message);
+                    return;
+                }
+            };
 
-            // Insert message into database
-            let result = sqlx::query!(
-                r#"
-                INSERT INTO messages
-                (chat_room_id, sender_id, message_type, content, media_url, view_once, expires_at)
-                VALUES ($1, $2, $3, $4, $5, $6, $7)
-                RETURNING id, created_at
-                "#,
+            let message = crate::chat::OutgoingMessage {
                 chat_room_id,
-                user_id,
                 message_type,
                 content,
                 media_url,
+                media_thumbnail_url: None,
                 view_once,
-                expires_at
-            )
-            .fetch_one(pool.as_ref())
-            .await;
+                expires_at,
+                client_msg_id: client_msg_id.clone(),
+            };
 
-            if let Ok(record) = result {
-                // Get sender username
-                let sender = sqlx::query!("SELECT username FROM users WHERE id = $1", user_id)
-                    .fetch_one(pool.as_ref())
-                    .await;
-                if let Ok(sender) = sender {
-                    // Get all members of the chat room
-                    let members = sqlx::query!(
-                        "SELECT user_id FROM chat_members WHERE chat_room_id = $1",
-                        chat_room_id
-                    )
-                    .fetch_all(pool.as_ref())
-                    .await;
-                    if let Ok(members) = members {
-                        // Broadcast to all chat members (including sender)
-                        let broadcast_msg = WsMessage::NewMessage {
-                            id: record.id,
-                            chat_room_id,
-                            sender_id: user_id,
-                            sender_username: sender.username,
-                            message_type: message_type.clone(),
-                            content: content.clone(),
-                            media_url: media_url.clone(),
-                            media_thumbnail_url: None,
-                            view_once,
-                            created_at: record.created_at.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
-                        };
-                        let msg_json = serde_json::to_string(&broadcast_msg).unwrap();
-                        for member in members {
-                            if let Some(conn) = connections.get(&member.user_id) {
-                                let _ = conn.send(msg_json.clone());
-                            } else {
-                                // User is offline, increment unread counter
-                                let mut redis_guard = redis.lock().await;
-                                let _ = redis_guard.increment_unread(member.user_id, chat_room_id).await;
-                            }
+            if let Err(message) = message.validate() {
+                nack(outbox, request_id, ErrorCode::PayloadInvalid, message);
+                return;
+            }
+            if !check_member(pool, redis, outbox, request_id, user_id, chat_room_id).await {
+                return;
+            }
+            if let Err((status, message)) = crate::message_requests::check_send(pool, user_id, chat_room_id).await {
+                nack(outbox, request_id, status_code(status), message);
+                return;
+            }
+            if let Err((status, message)) = crate::channels::check_send(pool, user_id, chat_room_id).await {
+                nack(outbox, request_id, status_code(status), message);
+                return;
+            }
+
+            // A resent client_msg_id means the client never saw our NewMessage;
+            // replay it to the sender instead of storing the message twice
+            if let Some(key) = &client_msg_id {
+                if let Err(message) = idempotency::validate_key(key) {
+                    nack(outbox, request_id, ErrorCode::PayloadInvalid, message);
+                    return;
+                }
+
+                match idempotency::reserve::<crate::chat::MessageResponse>(pool, user_id, SCOPE_SEND_MESSAGE, key).await {
+                    Ok(Reservation::New) => {}
+                    Ok(Reservation::Replay(mut original)) => {
+                        original.sign_media(media, user_id).await;
+                        send_to_user(connections, user_id, &original.to_ws_event(client_msg_id.clone()));
+                        return;
+                    }
+                    // The first attempt will broadcast when it finishes
+                    Ok(Reservation::InProgress) => return,
+                    Err(e) => {
+                        tracing::error!("Failed to reserve client_msg_id: {}", e);
+                        nack(outbox, request_id, ErrorCode::Internal, "Failed to send message");
+                        return;
+                    }
+                }
+            }
+
+            match crate::chat::deliver_message(pool, redis, connections, media, user_id, message).await {
+                Ok(response) => {
+                    if let Some(key) = &client_msg_id {
+                        if let Err(e) = idempotency::complete(pool, user_id, SCOPE_SEND_MESSAGE, key, &response).await {
+                            tracing::error!("Failed to store idempotent response: {}", e);
                         }
-                    } else {
-                        tracing::error!("Failed to fetch chat members for room {}", chat_room_id);
                     }
-                } else {
-                    tracing::error!("Failed to fetch sender username for user {}", user_id);
                 }
-            } else if let Err(e) = result {
-                tracing::error!("Failed to insert message: {}", e);
+                Err(e) => {
+                    tracing::error!("Failed to send message: {}", e);
+                    nack(outbox, request_id, ErrorCode::Internal, "Failed to send message");
+                    if let Some(key) = &client_msg_id {
+                        idempotency::release(pool, user_id, SCOPE_SEND_MESSAGE, key).await;
+                    }
+                }
             }
         }
 
         WsMessage::TypingStart { chat_room_id } => {
+            if !check_member(pool, redis, outbox, request_id, user_id, chat_room_id).await {
+                return;
+            }
             {
                 let mut redis_guard = redis.lock().await;
                 let _ = redis_guard.set_typing(user_id, chat_room_id).await;
@@ -283,6 +848,9 @@ async fn handle_ws_message(
         }
 
         WsMessage::TypingStop { chat_room_id } => {
+            if !check_member(pool, redis, outbox, request_id, user_id, chat_room_id).await {
+                return;
+            }
             {
                 let mut redis_guard = redis.lock().await;
                 let _ = redis_guard.clear_typing(user_id, chat_room_id).await;
@@ -312,6 +880,10 @@ async fn handle_ws_message(
         }
 
         WsMessage::MarkRead { message_id } => {
+            if !check_message_member(pool, redis, outbox, request_id, user_id, message_id).await {
+                return;
+            }
+
             // Insert read receipt
             let result = sqlx::query!(
                 "INSERT INTO message_reads (message_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING RETURNING read_at",
@@ -340,7 +912,7 @@ async fn handle_ws_message(
                     let read_msg = WsMessage::MessageRead {
                         message_id,
                         user_id,
-                        read_at: record.read_at.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
+                        read_at: crate::timestamps::format(record.read_at),
                     };
 
                     let msg_json = serde_json::to_string(&read_msg).unwrap();
@@ -355,6 +927,10 @@ async fn handle_ws_message(
         }
 
         WsMessage::MarkViewed { message_id } => {
+            if !check_message_member(pool, redis, outbox, request_id, user_id, message_id).await {
+                return;
+            }
+
             // Insert view record
             let result = sqlx::query!(
                 "INSERT INTO message_views (message_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING RETURNING viewed_at",
@@ -377,7 +953,7 @@ async fn handle_ws_message(
                     let viewed_msg = WsMessage::MessageViewed {
                         message_id,
                         user_id,
-                        viewed_at: record.viewed_at.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
+                        viewed_at: crate::timestamps::format(record.viewed_at),
                     };
 
                     let msg_json = serde_json::to_string(&viewed_msg).unwrap();
@@ -386,31 +962,30 @@ async fn handle_ws_message(
                         let _ = conn.send(msg_json);
                     }
 
-                    // If view_once, delete the message and notify all participants
+                    // The view trigger retired a view-once message (see
+                    // retire_message in migration 043): deleted outright, or
+                    // retained for the members who saved it. Tell everyone it's
+                    // gone for.
                     if msg.view_once {
-                        let _ = sqlx::query!(
-                            "UPDATE messages SET deleted_at = NOW() WHERE id = $1",
-                            message_id
-                        )
-                        .execute(pool.as_ref())
-                        .await;
-
-                        let expired_msg = WsMessage::MessageExpired { message_id };
-                        let expired_json = serde_json::to_string(&expired_msg).unwrap();
-
-                        // Get all members of the chat room
-                        if let Ok(members) = sqlx::query!(
-                            "SELECT user_id FROM chat_members WHERE chat_room_id = (SELECT chat_room_id FROM messages WHERE id = $1)",
-                            message_id
+                        let gone_for = sqlx::query_scalar::<_, Uuid>(
+                            r#"
+                            SELECT cm.user_id
+                            FROM messages m
+                            JOIN chat_members cm ON cm.chat_room_id = m.chat_room_id
+                            WHERE m.id = $1
+                              AND (m.deleted_at IS NOT NULL
+                                   OR EXISTS(SELECT 1 FROM message_recipients h WHERE h.message_id = m.id AND h.user_id = cm.user_id
+                                              AND h.hidden_at IS NOT NULL))
+                            "#
                         )
+                        .bind(message_id)
                         .fetch_all(pool.as_ref())
                         .await
-                        {
-                            for member in members {
-                                if let Some(conn) = connections.get(&member.user_id) {
-                                    let _ = conn.send(expired_json.clone());
-                                }
-                            }
+                        .unwrap_or_default();
+
+                        let expired_msg = WsMessage::MessageExpired { message_id };
+                        for member_id in gone_for {
+                            send_to_user(connections, member_id, &expired_msg);
                         }
                     }
                 }
@@ -419,6 +994,23 @@ async fn handle_ws_message(
             }
         }
 
+        WsMessage::UnsendMessage { message_id } => {
+            if let Err((code, message)) = crate::chat::unsend_message(pool, connections, media, user_id, message_id).await {
+                nack(outbox, request_id, code, message);
+            }
+        }
+
+        msg @ (WsMessage::CallOffer { .. }
+        | WsMessage::CallAnswer { .. }
+        | WsMessage::CallHangup { .. }
+        | WsMessage::CallIce { .. }) => {
+            crate::calls::handle_ws_message(msg, request_id, outbox, user_id, pool, connections).await;
+        }
+
+        msg @ (WsMessage::LiveJoin { .. } | WsMessage::LiveSdp { .. } | WsMessage::LiveIce { .. }) => {
+            crate::live::handle_ws_message(msg, request_id, outbox, user_id, pool, connections).await;
+        }
+
         _ => {}
     }
 }